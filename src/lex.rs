@@ -65,15 +65,36 @@
 //!
 //!     For the complete end-to-end pipeline documentation, see [parsing](parsing) module.
 
+pub mod analysis;
 pub mod annotation;
 pub mod assembling;
 pub mod ast;
+pub mod bibliography;
 pub mod building;
+pub mod diagrams;
+pub mod diff;
+pub mod display_math;
+pub mod exec;
+pub mod extensions;
+pub mod filters;
 pub mod formats;
+pub mod formatting;
+#[cfg(feature = "obsidian")]
+pub mod frontmatter;
 pub mod inlines;
 pub mod lexing;
+pub mod lint;
+pub mod literate;
 pub mod loader;
+pub mod locale;
+#[cfg(feature = "obsidian")]
+pub mod obsidian;
 pub mod parsing;
+pub mod tables;
+pub mod templates;
+#[cfg(any(test, feature = "testing"))]
 pub mod testing;
 pub mod token;
 pub mod transforms;
+pub mod variables;
+pub mod watch;
@@ -0,0 +1,68 @@
+//! Document analysis
+//!
+//!     Read-only queries over a parsed document, shared by the CLI, the language server and
+//!     viewers so they report the same numbers:
+//!
+//!         - [stats](stats::stats): element counts, depth histograms and word counts.
+//!         - [definitions](definitions::ReferenceIndex): where references are defined and
+//!           used, for go-to-definition and find-references.
+//!         - [folding](folding::folding_ranges): foldable line spans, honoring sessions marked
+//!           `collapsed`.
+//!         - [highlighting](highlighting::semantic_tokens): semantic tokens for editors, in
+//!           LSP's encoding, with deltas between versions.
+//!         - [hover](hover::hover_at): Markdown previews of what a reference points at, and of
+//!           annotation parameters.
+//!         - [navigation](navigation::reference_at): the reference under a position and where
+//!           it points, for following references.
+//!         - [outline](outline::outline): session titles and definition subjects, with fuzzy
+//!           matching for jump-to-section pickers, and the nested
+//!           [document symbols](outline::document_symbols) of outline views, and the
+//!           session tree as [JSON](outline::outline_json) for web front ends.
+//!         - [references]: the directed graph of footnotes, citations, internal references and
+//!           includes, with cycle and orphan detection.
+//!         - [search](search::search_document): full-text search with structural context, over
+//!           a document or a directory of documents.
+//!         - [tags](tags::Taxonomy): `:: tags ::` annotations on documents and sessions,
+//!           collected into a workspace taxonomy.
+//!         - [walls](walls::indentation_walls): the indentation wall of each line and where
+//!           lines miss it, drawn as guides by [render_walls](walls::render_walls).
+//!         - [workspace](workspace::Workspace): reference graphs of many documents, for
+//!           backlinks between them.
+
+pub mod definitions;
+pub mod folding;
+pub mod highlighting;
+pub mod hover;
+pub mod navigation;
+pub mod outline;
+pub mod references;
+pub mod search;
+pub mod stats;
+pub mod tags;
+pub mod walls;
+pub mod workspace;
+
+pub use definitions::{definition_at, references_at, Definition, ReferenceIndex, TargetKey, Usage};
+pub use folding::{folding_ranges, FoldingKind, FoldingRange};
+pub use highlighting::{
+    delta, encode, semantic_tokens, semantic_tokens_in_range, SemanticKind, SemanticToken,
+    SemanticTokensEdit,
+};
+pub use hover::{hover_at, Hover};
+pub use navigation::{
+    reference_at, reference_at_with_parser, resolve_reference, resolve_section, NavigationTarget,
+    ReferenceAt,
+};
+pub use outline::{
+    document_symbols, fuzzy_find, fuzzy_score, outline, outline_json, DocumentSymbol, OutlineEntry,
+    OutlineKind, SymbolKind,
+};
+pub use references::{NodeKind, ReferenceEdge, ReferenceGraph, ReferenceKind, ReferenceNode};
+pub use search::{
+    search_dir, search_document, DirectorySearch, MatchKind, SearchMatch, SearchOptions,
+    SearchScope,
+};
+pub use stats::{stats, DocumentStats, SessionStats};
+pub use tags::{document_tags, session_tags, TagUse, Taxonomy};
+pub use walls::{indentation_walls, render_walls, LineWall, WallRegion, WallReport, WallViolation};
+pub use workspace::{resolve_file_reference, Backlink, Workspace, WorkspaceDocument};
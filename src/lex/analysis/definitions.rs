@@ -0,0 +1,304 @@
+//! Definitions and usages of references
+//!
+//!     Go-to-definition and find-references for the language server
+//!     (`textDocument/definition`, `textDocument/references`). A [ReferenceIndex] lists every
+//!     reference written in a document with its range, keyed by what it points at, so both
+//!     directions are lookups:
+//!
+//!         - [definition_at]: from a reference to its target. Footnotes (`[42]`, `[^note]`)
+//!           go to their annotation, citations (`[@key]`) to the entry in the
+//!           [bibliography](crate::lex::bibliography) when one is given, else to the
+//!           annotation with the key as label. Session and general references resolve as in
+//!           [navigation](super::navigation).
+//!         - [references_at]: from a reference, or an annotation's label, to every usage of
+//!           the same target, citation keys included.
+//!
+//!     A citation with several keys has a usage per key, ranging over its `@key`, so each
+//!     key leads to its own entry. URLs, files, images and placeholders have no definition in
+//!     the document and are not indexed.
+
+use super::navigation::{references_in_text, resolve_reference, NavigationTarget};
+use crate::lex::ast::{Annotation, ContentItem, Document, Position, Range, TextContent};
+use crate::lex::bibliography::{BibRegistry, EntryOrigin};
+use crate::lex::inlines::{InlineParser, ReferenceType};
+
+/// What a reference points at
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TargetKey {
+    /// Footnote annotation, by label (`[42]` is the annotation labeled `42`)
+    Annotation(String),
+    /// Citation key, without the `@`
+    Citation(String),
+    /// Session by id, marker or title
+    Session(String),
+    /// Session, definition or annotation by name
+    General(String),
+}
+
+/// A reference written in the document
+#[derive(Debug, Clone, PartialEq)]
+pub struct Usage {
+    pub key: TargetKey,
+    pub range: Range,
+}
+
+/// Where a reference is defined
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Definition {
+    /// Position in the document
+    Position(Position),
+    /// Entry in a bibliography file
+    Bibliography(EntryOrigin),
+}
+
+/// The references of a document, in source order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReferenceIndex {
+    pub usages: Vec<Usage>,
+}
+
+impl ReferenceIndex {
+    /// Index of the references in `document`
+    pub fn build(document: &Document) -> Self {
+        Self::build_with_parser(document, &InlineParser::new())
+    }
+
+    /// Index of the references in `document`, with inlines parsed by `parser`
+    pub fn build_with_parser(document: &Document, parser: &InlineParser) -> Self {
+        let mut texts = Vec::new();
+        for annotation in document
+            .annotations
+            .iter()
+            .chain(&document.root.annotations)
+        {
+            annotation_texts(annotation, &mut texts);
+        }
+        texts.push(&document.root.title);
+        item_texts(&document.root.children, &mut texts);
+
+        let mut usages = Vec::new();
+        for text in texts {
+            for found in references_in_text(text, parser) {
+                let range = found.range;
+                let key = match found.reference.reference_type {
+                    ReferenceType::FootnoteNumber { number } => {
+                        TargetKey::Annotation(number.to_string())
+                    }
+                    ReferenceType::FootnoteLabeled { label } => TargetKey::Annotation(label),
+                    ReferenceType::Session { target } => TargetKey::Session(target),
+                    ReferenceType::General { target } => TargetKey::General(target),
+                    ReferenceType::Citation(citation) => {
+                        let raw = &found.reference.raw;
+                        let mut from = 0;
+                        for key in citation.keys {
+                            let written = format!("@{key}");
+                            let key_range = match raw[from..].find(&written) {
+                                Some(offset) => {
+                                    from += offset + written.len();
+                                    // Columns in the reference, past its opening bracket
+                                    let (start, end) = (from - written.len() + 1, from + 1);
+                                    Range::new(
+                                        range.span.start + start..range.span.start + end,
+                                        Position::new(range.start.line, range.start.column + start),
+                                        Position::new(range.start.line, range.start.column + end),
+                                    )
+                                }
+                                None => range.clone(),
+                            };
+                            usages.push(Usage {
+                                key: TargetKey::Citation(key),
+                                range: key_range,
+                            });
+                        }
+                        continue;
+                    }
+                    _ => continue,
+                };
+                usages.push(Usage { key, range });
+            }
+        }
+        Self { usages }
+    }
+
+    /// The usage at `position`
+    pub fn usage_at(&self, position: Position) -> Option<&Usage> {
+        self.usages.iter().find(|usage| {
+            usage.range.start.line == position.line
+                && (usage.range.start.column..usage.range.end.column).contains(&position.column)
+        })
+    }
+
+    /// Usages pointing at `key`
+    pub fn usages_of<'a>(&'a self, key: &'a TargetKey) -> impl Iterator<Item = &'a Usage> {
+        self.usages.iter().filter(move |usage| &usage.key == key)
+    }
+}
+
+/// Definition of the reference at `position`; citations are looked up in `bibliography`
+/// first
+pub fn definition_at(
+    document: &Document,
+    index: &ReferenceIndex,
+    position: Position,
+    bibliography: Option<&BibRegistry>,
+) -> Option<Definition> {
+    let annotation = |label: &str| {
+        find_annotation(document, label)
+            .map(|annotation| Definition::Position(annotation.header_location().start))
+    };
+    let reference = match &index.usage_at(position)?.key {
+        TargetKey::Annotation(label) => return annotation(label),
+        TargetKey::Citation(key) => {
+            return bibliography
+                .and_then(|registry| registry.origin(key))
+                .map(|origin| Definition::Bibliography(origin.clone()))
+                .or_else(|| annotation(key))
+        }
+        TargetKey::Session(target) => ReferenceType::Session {
+            target: target.clone(),
+        },
+        TargetKey::General(target) => ReferenceType::General {
+            target: target.clone(),
+        },
+    };
+    match resolve_reference(document, &reference)? {
+        NavigationTarget::Position(position) => Some(Definition::Position(position)),
+        _ => None,
+    }
+}
+
+/// Ranges of the usages of the target referenced at `position`, or of the annotation whose
+/// label is at `position`
+pub fn references_at(
+    document: &Document,
+    index: &ReferenceIndex,
+    position: Position,
+) -> Vec<Range> {
+    if let Some(usage) = index.usage_at(position) {
+        return index
+            .usages_of(&usage.key)
+            .map(|usage| usage.range.clone())
+            .collect();
+    }
+    let Some(annotation) = annotation_at(document, position) else {
+        return Vec::new();
+    };
+    let label = annotation.data.label.value.trim();
+    index
+        .usages
+        .iter()
+        .filter(|usage| match &usage.key {
+            TargetKey::Annotation(name) | TargetKey::Citation(name) => name == label,
+            _ => false,
+        })
+        .map(|usage| usage.range.clone())
+        .collect()
+}
+
+/// The annotation whose header is at `position`
+pub(crate) fn annotation_at(document: &Document, position: Position) -> Option<&Annotation> {
+    all_annotations(document)
+        .into_iter()
+        .find(|annotation| annotation.header_location().contains(position))
+}
+
+/// The first annotation labeled `label`, attached ones included
+pub(crate) fn find_annotation<'a>(document: &'a Document, label: &str) -> Option<&'a Annotation> {
+    all_annotations(document)
+        .into_iter()
+        .find(|annotation| annotation.data.label.value.trim() == label)
+}
+
+/// Annotations of the document, of its elements and in its content
+fn all_annotations(document: &Document) -> Vec<&Annotation> {
+    let mut annotations: Vec<&Annotation> = document
+        .annotations
+        .iter()
+        .chain(&document.root.annotations)
+        .collect();
+    for item in document.root.iter_all_nodes() {
+        annotations.extend(item.annotations());
+        if let ContentItem::Annotation(annotation) = item {
+            annotations.push(annotation);
+        }
+    }
+    annotations
+}
+
+fn annotation_texts<'a>(annotation: &'a Annotation, out: &mut Vec<&'a TextContent>) {
+    item_texts(&annotation.children, out);
+}
+
+fn item_texts<'a>(items: &'a [ContentItem], out: &mut Vec<&'a TextContent>) {
+    for item in items {
+        for annotation in item.annotations() {
+            annotation_texts(annotation, out);
+        }
+        match item {
+            ContentItem::Session(session) => {
+                out.push(&session.title);
+                item_texts(&session.children, out);
+            }
+            ContentItem::Paragraph(paragraph) => item_texts(&paragraph.lines, out),
+            ContentItem::TextLine(line) => out.push(&line.content),
+            ContentItem::List(list) => item_texts(&list.items, out),
+            ContentItem::ListItem(list_item) => {
+                out.extend(&list_item.text);
+                item_texts(&list_item.children, out);
+            }
+            ContentItem::Definition(definition) => {
+                out.push(&definition.subject);
+                item_texts(&definition.children, out);
+            }
+            ContentItem::Annotation(annotation) => annotation_texts(annotation, out),
+            ContentItem::VerbatimBlock(_)
+            | ContentItem::VerbatimLine(_)
+            | ContentItem::BlankLineGroup(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    const SOURCE: &str = "Notes\n\nSee [42] and [@doe2024; @roe2020].\n\nAgain [42] and [@doe2024].\n\n:: 42 ::\n    Footnote text.\n";
+
+    #[test]
+    fn test_definitions_and_references() {
+        let doc = parse_document(SOURCE).unwrap();
+        let index = ReferenceIndex::build(&doc);
+        assert_eq!(index.usages.len(), 5);
+        assert_eq!(
+            index.usages[2].key,
+            TargetKey::Citation("roe2020".to_string())
+        );
+        // `@roe2020`, after `See [42] and [@doe2024; `
+        assert_eq!(index.usages[2].range.start.column, 24);
+        assert_eq!(index.usages[2].range.end.column, 32);
+
+        let footnote = definition_at(&doc, &index, Position::new(2, 5), None);
+        assert_eq!(footnote, Some(Definition::Position(Position::new(6, 3))));
+
+        let mut registry = BibRegistry::new();
+        registry
+            .load_bibtex("refs.bib", "@book{doe2024,\n  title = {Lex},\n}\n")
+            .unwrap();
+        match definition_at(&doc, &index, Position::new(2, 16), Some(&registry)) {
+            Some(Definition::Bibliography(origin)) => assert_eq!(origin.source, "refs.bib"),
+            other => panic!("Expected bibliography entry, got {other:?}"),
+        }
+        assert_eq!(
+            definition_at(&doc, &index, Position::new(2, 26), None),
+            None
+        );
+
+        let usages = references_at(&doc, &index, Position::new(4, 17));
+        assert_eq!(usages.len(), 2);
+        assert_eq!(usages[0].start.line, 2);
+        // From the footnote's label
+        assert_eq!(references_at(&doc, &index, Position::new(6, 3)).len(), 2);
+        assert!(references_at(&doc, &index, Position::new(7, 6)).is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+//! Folding ranges
+//!
+//! The line spans an editor or viewer can fold: sessions, definitions, verbatim blocks and
+//! annotations with content. Each range starts at the element's header line and ends at its
+//! last line; sessions marked `collapsed` (see [Session::is_collapsed]) are flagged to start
+//! folded.
+
+use crate::lex::ast::traits::AstNode;
+use crate::lex::ast::{ContentItem, Document, Range, Session};
+
+/// Kind of element a folding range covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoldingKind {
+    Session,
+    Definition,
+    Verbatim,
+    Annotation,
+}
+
+/// A foldable span of lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FoldingRange {
+    /// First line (0-based), the element's header
+    pub start_line: usize,
+    /// Last line (0-based, inclusive)
+    pub end_line: usize,
+    pub kind: FoldingKind,
+    /// Whether the range should start folded
+    pub collapsed: bool,
+}
+
+/// Folding ranges of `document`, in document order
+pub fn folding_ranges(document: &Document) -> Vec<FoldingRange> {
+    document
+        .root
+        .iter_all_nodes()
+        .filter_map(|item| {
+            let (kind, collapsed) = match item {
+                ContentItem::Session(session) => {
+                    (FoldingKind::Session, Session::is_collapsed(session))
+                }
+                ContentItem::Definition(_) => (FoldingKind::Definition, false),
+                ContentItem::VerbatimBlock(_) => (FoldingKind::Verbatim, false),
+                ContentItem::Annotation(annotation) if !annotation.children.is_empty() => {
+                    (FoldingKind::Annotation, false)
+                }
+                _ => return None,
+            };
+            let (start_line, end_line) = lines(item.range())?;
+            Some(FoldingRange {
+                start_line,
+                end_line,
+                kind,
+                collapsed,
+            })
+        })
+        .collect()
+}
+
+/// Inclusive line span of a range covering more than one line
+fn lines(range: &Range) -> Option<(usize, usize)> {
+    let end = if range.end.column == 0 && range.end.line > range.start.line {
+        range.end.line - 1
+    } else {
+        range.end.line
+    };
+    (end > range.start.line).then_some((range.start.line, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    #[test]
+    fn test_folding_ranges() {
+        let source = "Title\n\nIntro.\n\n1. Open\n\n    Text.\n\n    More.\n\n:: details collapsed=true ::\n2. Closed\n\n    Hidden text.\n\n    More.\n";
+        let doc = parse_document(source).unwrap();
+        let ranges = folding_ranges(&doc);
+        let sessions: Vec<_> = ranges
+            .iter()
+            .filter(|range| range.kind == FoldingKind::Session)
+            .map(|range| (range.start_line, range.collapsed))
+            .collect();
+        assert_eq!(sessions, vec![(4, false), (11, true)]);
+        assert!(ranges.iter().all(|range| range.end_line > range.start_line));
+    }
+}
@@ -0,0 +1,443 @@
+//! Semantic highlighting
+//!
+//!     Semantic tokens are the highlighted spans of a document, classified from the AST rather
+//!     than by a grammar: session titles, list markers, definition subjects, annotation
+//!     headers, verbatim subjects and closing labels, and the strong, emphasis, code, math and
+//!     reference inlines of text. [semantic_tokens] lists them in source order, without
+//!     overlaps (an inline nested in another splits it).
+//!
+//!     [encode] packs tokens into the relative five-number form of LSP's
+//!     `textDocument/semanticTokens`, with types numbered as in [SemanticKind::LEGEND], and
+//!     [delta] turns two encodings into the edits of a `semanticTokens/full/delta` response.
+//!     Columns and lengths are byte offsets, like every position in the AST; servers speaking
+//!     UTF-16 convert them per line.
+
+use crate::lex::ast::{Annotation, ContentItem, Document, Range, TextContent};
+use crate::lex::inlines::InlineNode;
+
+/// What a semantic token highlights
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SemanticKind {
+    SessionTitle,
+    ListMarker,
+    DefinitionSubject,
+    /// `:: label params ::`
+    AnnotationHeader,
+    VerbatimSubject,
+    /// The closing `:: label` of a verbatim block
+    VerbatimLabel,
+    Strong,
+    Emphasis,
+    Code,
+    Math,
+    Reference,
+}
+
+impl SemanticKind {
+    /// Token types, in the order their indices refer to
+    pub const LEGEND: [SemanticKind; 11] = [
+        SemanticKind::SessionTitle,
+        SemanticKind::ListMarker,
+        SemanticKind::DefinitionSubject,
+        SemanticKind::AnnotationHeader,
+        SemanticKind::VerbatimSubject,
+        SemanticKind::VerbatimLabel,
+        SemanticKind::Strong,
+        SemanticKind::Emphasis,
+        SemanticKind::Code,
+        SemanticKind::Math,
+        SemanticKind::Reference,
+    ];
+
+    /// Token type name, as announced in the legend
+    pub fn name(&self) -> &'static str {
+        match self {
+            SemanticKind::SessionTitle => "sessionTitle",
+            SemanticKind::ListMarker => "listMarker",
+            SemanticKind::DefinitionSubject => "definitionSubject",
+            SemanticKind::AnnotationHeader => "annotationHeader",
+            SemanticKind::VerbatimSubject => "verbatimSubject",
+            SemanticKind::VerbatimLabel => "verbatimLabel",
+            SemanticKind::Strong => "strong",
+            SemanticKind::Emphasis => "emphasis",
+            SemanticKind::Code => "code",
+            SemanticKind::Math => "math",
+            SemanticKind::Reference => "reference",
+        }
+    }
+
+    /// Index of the kind in [LEGEND](Self::LEGEND)
+    pub fn index(&self) -> u32 {
+        Self::LEGEND
+            .iter()
+            .position(|kind| kind == self)
+            .expect("every kind is in the legend") as u32
+    }
+}
+
+/// A highlighted span, on a single line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SemanticToken {
+    /// Line (0-based)
+    pub line: usize,
+    /// Column (byte offset in the line) of the first character
+    pub start: usize,
+    /// Length in bytes
+    pub length: usize,
+    pub kind: SemanticKind,
+}
+
+/// One edit of a delta between two token encodings, in units of the encoded array
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SemanticTokensEdit {
+    pub start: usize,
+    pub delete_count: usize,
+    pub data: Vec<u32>,
+}
+
+/// Semantic tokens of `document`, in source order
+pub fn semantic_tokens(document: &Document) -> Vec<SemanticToken> {
+    let mut collector = Collector::default();
+    collector.annotations(&document.annotations);
+    collector.items(&document.root.children);
+    collector.finish()
+}
+
+/// Semantic tokens of `document` on the lines `range` covers
+pub fn semantic_tokens_in_range(document: &Document, range: &Range) -> Vec<SemanticToken> {
+    semantic_tokens(document)
+        .into_iter()
+        .filter(|token| (range.start.line..=range.end.line).contains(&token.line))
+        .collect()
+}
+
+/// `tokens` in LSP's encoding: for each token, its line relative to the previous token's,
+/// its start (relative to the previous token's when on the same line), length, type index
+/// and modifiers (none)
+pub fn encode(tokens: &[SemanticToken]) -> Vec<u32> {
+    let mut data = Vec::with_capacity(tokens.len() * 5);
+    let (mut line, mut start) = (0, 0);
+    for token in tokens {
+        let delta_line = token.line - line;
+        let delta_start = if delta_line == 0 {
+            token.start - start
+        } else {
+            token.start
+        };
+        data.extend([
+            delta_line as u32,
+            delta_start as u32,
+            token.length as u32,
+            token.kind.index(),
+            0,
+        ]);
+        line = token.line;
+        start = token.start;
+    }
+    data
+}
+
+/// Edits turning the encoding `previous` into `current`: a single edit replacing what lies
+/// between their common prefix and suffix, or none when they are equal
+pub fn delta(previous: &[u32], current: &[u32]) -> Vec<SemanticTokensEdit> {
+    let prefix = previous
+        .iter()
+        .zip(current)
+        .take_while(|(old, new)| old == new)
+        .count();
+    if prefix == previous.len() && prefix == current.len() {
+        return Vec::new();
+    }
+    let suffix = previous[prefix..]
+        .iter()
+        .rev()
+        .zip(current[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    vec![SemanticTokensEdit {
+        start: prefix,
+        delete_count: previous.len() - prefix - suffix,
+        data: current[prefix..current.len() - suffix].to_vec(),
+    }]
+}
+
+#[derive(Default)]
+struct Collector {
+    /// Spans as (line, start, end, kind, nesting depth)
+    spans: Vec<(usize, usize, usize, SemanticKind, usize)>,
+}
+
+impl Collector {
+    fn range(&mut self, range: &Range, kind: SemanticKind) {
+        if range.start.line == range.end.line && range.end.column > range.start.column {
+            self.spans.push((
+                range.start.line,
+                range.start.column,
+                range.end.column,
+                kind,
+                0,
+            ));
+        }
+    }
+
+    fn items(&mut self, items: &[ContentItem]) {
+        for item in items {
+            self.item(item);
+        }
+    }
+
+    fn item(&mut self, item: &ContentItem) {
+        self.annotations(item.annotations());
+        match item {
+            ContentItem::Session(session) => {
+                self.text(&session.title, Some(SemanticKind::SessionTitle));
+                self.items(&session.children);
+            }
+            ContentItem::Paragraph(paragraph) => self.items(&paragraph.lines),
+            ContentItem::TextLine(line) => self.text(&line.content, None),
+            ContentItem::List(list) => self.items(&list.items),
+            ContentItem::ListItem(list_item) => {
+                if let Some(location) = &list_item.marker.location {
+                    self.range(location, SemanticKind::ListMarker);
+                }
+                for text in &list_item.text {
+                    self.text(text, None);
+                }
+                self.items(&list_item.children);
+            }
+            ContentItem::Definition(definition) => {
+                self.text(&definition.subject, Some(SemanticKind::DefinitionSubject));
+                self.items(&definition.children);
+            }
+            ContentItem::Annotation(annotation) => self.annotation(annotation),
+            ContentItem::VerbatimBlock(verbatim) => {
+                if let Some(location) = &verbatim.subject.location {
+                    self.range(location, SemanticKind::VerbatimSubject);
+                }
+                self.range(&verbatim.closing_data.location, SemanticKind::VerbatimLabel);
+            }
+            ContentItem::VerbatimLine(_) | ContentItem::BlankLineGroup(_) => {}
+        }
+    }
+
+    fn annotations(&mut self, annotations: &[Annotation]) {
+        for annotation in annotations {
+            self.annotation(annotation);
+        }
+    }
+
+    fn annotation(&mut self, annotation: &Annotation) {
+        self.range(annotation.header_location(), SemanticKind::AnnotationHeader);
+        self.items(&annotation.children);
+    }
+
+    /// Tokens of a line of text: the whole line as `kind`, if any, and its inlines
+    fn text(&mut self, text: &TextContent, kind: Option<SemanticKind>) {
+        let Some(location) = &text.location else {
+            return;
+        };
+        if let Some(kind) = kind {
+            self.range(location, kind);
+        }
+        if location.start.line != location.end.line {
+            return;
+        }
+        let source = text.as_string();
+        let mut from = 0;
+        let mut found = Vec::new();
+        inline_spans(source, &text.inline_items(), &mut from, 1, &mut found);
+        for (start, end, kind, depth) in found {
+            self.spans.push((
+                location.start.line,
+                location.start.column + start,
+                location.start.column + end,
+                kind,
+                depth,
+            ));
+        }
+    }
+
+    /// Tokens without overlaps: where spans nest, the innermost wins
+    fn finish(mut self) -> Vec<SemanticToken> {
+        self.spans.sort_by_key(|&(line, start, end, _, depth)| {
+            (line, start, std::cmp::Reverse(end), depth)
+        });
+        let mut tokens: Vec<SemanticToken> = Vec::new();
+        let mut index = 0;
+        while index < self.spans.len() {
+            let line = self.spans[index].0;
+            let mut group = Vec::new();
+            while index < self.spans.len() && self.spans[index].0 == line {
+                group.push(self.spans[index]);
+                index += 1;
+            }
+            let mut cuts: Vec<usize> = group
+                .iter()
+                .flat_map(|&(_, start, end, _, _)| [start, end])
+                .collect();
+            cuts.sort_unstable();
+            cuts.dedup();
+            for window in cuts.windows(2) {
+                let (start, end) = (window[0], window[1]);
+                let innermost = group
+                    .iter()
+                    .filter(|&&(_, from, to, _, _)| from <= start && end <= to)
+                    .max_by_key(|&&(_, _, _, _, depth)| depth);
+                let Some(&(_, _, _, kind, _)) = innermost else {
+                    continue;
+                };
+                match tokens.last_mut() {
+                    Some(last)
+                        if last.line == line
+                            && last.kind == kind
+                            && last.start + last.length == start =>
+                    {
+                        last.length += end - start
+                    }
+                    _ => tokens.push(SemanticToken {
+                        line,
+                        start,
+                        length: end - start,
+                        kind,
+                    }),
+                }
+            }
+        }
+        tokens
+    }
+}
+
+/// Byte spans of the inlines `nodes` in `source`, searched from `from` on
+///
+/// Inlines carry no locations, so their markers are found again in the text, in order.
+fn inline_spans(
+    source: &str,
+    nodes: &[InlineNode],
+    from: &mut usize,
+    depth: usize,
+    out: &mut Vec<(usize, usize, SemanticKind, usize)>,
+) {
+    for node in nodes {
+        let (kind, written) = match node {
+            InlineNode::Strong { content, .. } | InlineNode::Emphasis { content, .. } => {
+                let (kind, marker) = match node {
+                    InlineNode::Strong { .. } => (SemanticKind::Strong, '*'),
+                    _ => (SemanticKind::Emphasis, '_'),
+                };
+                let Some(start) = find_marker(source, *from, marker) else {
+                    continue;
+                };
+                *from = start + 1;
+                inline_spans(source, content, from, depth + 1, out);
+                let Some(end) = find_marker(source, *from, marker) else {
+                    continue;
+                };
+                *from = end + 1;
+                out.push((start, end + 1, kind, depth));
+                continue;
+            }
+            InlineNode::Code { text, .. } => (SemanticKind::Code, format!("`{text}`")),
+            InlineNode::Math { text, .. } => (SemanticKind::Math, format!("#{text}#")),
+            InlineNode::Reference { data, .. } => {
+                (SemanticKind::Reference, format!("[{}]", data.raw))
+            }
+            _ => continue,
+        };
+        if let Some(found) = source[*from..].find(&written) {
+            let start = *from + found;
+            *from = start + written.len();
+            out.push((start, *from, kind, depth));
+        }
+    }
+}
+
+/// Offset of the next unescaped `marker` in `source` from `from` on
+fn find_marker(source: &str, from: usize, marker: char) -> Option<usize> {
+    source[from..]
+        .match_indices(marker)
+        .map(|(offset, _)| from + offset)
+        .find(|&offset| !source[..offset].ends_with('\\'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::ast::Position;
+    use crate::lex::parsing::parse_document;
+
+    const SOURCE: &str = "Guide\n\n1. Setup\n\n    Run *fast `make` now* and see [42].\n\n    - First\n    - Second\n\n    Sample:\n        print(1)\n    :: python\n\n    Cache:\n        Where #x^2# goes.\n\n:: 42 ::\n    A note.\n::\n";
+
+    fn summary(tokens: &[SemanticToken]) -> Vec<(usize, usize, usize, &'static str)> {
+        tokens
+            .iter()
+            .map(|token| (token.line, token.start, token.length, token.kind.name()))
+            .collect()
+    }
+
+    #[test]
+    fn test_semantic_tokens() {
+        let doc = parse_document(SOURCE).unwrap();
+        let tokens = semantic_tokens(&doc);
+        let found = summary(&tokens);
+        assert!(found.contains(&(2, 0, 8, "sessionTitle")));
+        // `make` splits the strong span around it
+        assert!(found.contains(&(4, 8, 6, "strong")));
+        assert!(found.contains(&(4, 14, 6, "code")));
+        assert!(found.contains(&(4, 20, 5, "strong")));
+        assert!(found.contains(&(4, 34, 4, "reference")));
+        assert!(found.contains(&(6, 4, 1, "listMarker")));
+        assert!(found.contains(&(9, 4, 6, "verbatimSubject")));
+        assert!(found.contains(&(11, 7, 6, "verbatimLabel")));
+        assert!(found.contains(&(13, 4, 5, "definitionSubject")));
+        assert!(found.contains(&(14, 14, 5, "math")));
+        assert!(found.contains(&(16, 3, 3, "annotationHeader")));
+        assert!(tokens
+            .windows(2)
+            .all(|pair| (pair[0].line, pair[0].start + pair[0].length)
+                <= (pair[1].line, pair[1].start)));
+
+        let lines = Range::new(0..0, Position::new(4, 0), Position::new(6, 0));
+        let in_range = semantic_tokens_in_range(&doc, &lines);
+        assert!(in_range.iter().all(|token| (4..=6).contains(&token.line)));
+        assert!(!in_range.is_empty());
+    }
+
+    #[test]
+    fn test_encode_and_delta() {
+        let tokens = [
+            SemanticToken {
+                line: 2,
+                start: 4,
+                length: 3,
+                kind: SemanticKind::Strong,
+            },
+            SemanticToken {
+                line: 2,
+                start: 10,
+                length: 2,
+                kind: SemanticKind::Code,
+            },
+            SemanticToken {
+                line: 5,
+                start: 1,
+                length: 4,
+                kind: SemanticKind::SessionTitle,
+            },
+        ];
+        let previous = encode(&tokens);
+        assert_eq!(previous, vec![2, 4, 3, 6, 0, 0, 6, 2, 8, 0, 3, 1, 4, 0, 0]);
+        assert!(delta(&previous, &previous).is_empty());
+
+        let mut changed = tokens;
+        changed[1].length = 5;
+        let current = encode(&changed);
+        assert_eq!(
+            delta(&previous, &current),
+            vec![SemanticTokensEdit {
+                start: 7,
+                delete_count: 1,
+                data: vec![5],
+            }]
+        );
+    }
+}
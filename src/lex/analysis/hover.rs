@@ -0,0 +1,236 @@
+//! Hover previews
+//!
+//!     What the language server shows on `textDocument/hover`, as Markdown:
+//!
+//!         - Footnote references: the footnote's content.
+//!         - Citations: each key's entry, formatted in a citation style when a bibliography
+//!           is given, else the annotation with the key as label.
+//!         - Session and general references: the session's title, the definition's subject
+//!           and content, the annotation's content, or the equation, by the rules of
+//!           [navigation](super::navigation).
+//!         - URLs, files, wiki links and images: the resolved target.
+//!         - Annotation labels: the annotation's parameters.
+//!
+//!     References that don't resolve have no hover; they are reported as diagnostics
+//!     instead.
+
+use super::definitions::{annotation_at, find_annotation};
+use super::navigation::{reference_at, resolve_reference, NavigationTarget};
+use crate::lex::ast::{Annotation, ContentItem, Document, Position, Range};
+use crate::lex::bibliography::{BibRegistry, CitationStyle};
+use crate::lex::display_math::{equations, find_equation};
+use crate::lex::inlines::ReferenceType;
+use crate::lex::literate::code_block;
+
+/// Hover content and the range it applies to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hover {
+    /// Markdown
+    pub contents: String,
+    pub range: Range,
+}
+
+/// Hover at `position`; citations are formatted in `style` from `bibliography` when given
+pub fn hover_at(
+    document: &Document,
+    position: Position,
+    bibliography: Option<(&BibRegistry, CitationStyle)>,
+) -> Option<Hover> {
+    if let Some(found) = reference_at(document, position) {
+        let contents = reference_preview(document, &found.reference.reference_type, bibliography)?;
+        return Some(Hover {
+            contents,
+            range: found.range,
+        });
+    }
+    let annotation = annotation_at(document, position)?;
+    Some(Hover {
+        contents: parameters_preview(annotation),
+        range: annotation.header_location().clone(),
+    })
+}
+
+fn reference_preview(
+    document: &Document,
+    reference: &ReferenceType,
+    bibliography: Option<(&BibRegistry, CitationStyle)>,
+) -> Option<String> {
+    let footnote = |label: &str| {
+        find_annotation(document, label).map(|annotation| markdown(&annotation.children))
+    };
+    match reference {
+        ReferenceType::FootnoteNumber { number } => footnote(&number.to_string()),
+        ReferenceType::FootnoteLabeled { label } => footnote(label),
+        ReferenceType::Citation(citation) => {
+            let entries: Vec<String> = citation
+                .keys
+                .iter()
+                .filter_map(|key| {
+                    let entry = bibliography.and_then(|(registry, style)| {
+                        registry.get(key).map(|entry| style.format_entry(entry))
+                    });
+                    entry.or_else(|| footnote(key))
+                })
+                .collect();
+            (!entries.is_empty()).then(|| entries.join("\n\n"))
+        }
+        ReferenceType::Session { target } => {
+            if let Some(equation) = find_equation(&equations(document), target) {
+                return Some(format!(
+                    "Equation ({})\n\n{}",
+                    equation.number,
+                    equation.to_markdown()
+                ));
+            }
+            target_preview(document, reference)
+        }
+        ReferenceType::General { .. } => target_preview(document, reference),
+        ReferenceType::Url { target } => Some(format!("<{target}>")),
+        ReferenceType::File { .. } | ReferenceType::WikiLink(_) => {
+            match resolve_reference(document, reference)? {
+                NavigationTarget::File { path, section }
+                | NavigationTarget::WikiLink {
+                    target: path,
+                    section,
+                } => Some(match section {
+                    Some(section) => format!("`{path}`, section {section}"),
+                    None => format!("`{path}`"),
+                }),
+                _ => None,
+            }
+        }
+        ReferenceType::Image(image) => Some(format!(
+            "![{}]({})",
+            image.alt.as_deref().unwrap_or_default(),
+            image.src
+        )),
+        ReferenceType::ToCome { .. } | ReferenceType::NotSure => None,
+    }
+}
+
+/// Preview of the session, definition or annotation a reference resolves to
+fn target_preview(document: &Document, reference: &ReferenceType) -> Option<String> {
+    let NavigationTarget::Position(position) = resolve_reference(document, reference)? else {
+        return None;
+    };
+    let starts_at =
+        |header: Option<&Range>, location: &Range| header.unwrap_or(location).start == position;
+    let element = document.root.iter_all_nodes().find_map(|item| match item {
+        ContentItem::Session(session)
+            if starts_at(session.header_location(), &session.location) =>
+        {
+            Some(format!("**{}**", session.title.as_string().trim()))
+        }
+        ContentItem::Definition(definition)
+            if starts_at(definition.header_location(), &definition.location) =>
+        {
+            Some(format!(
+                "**{}**\n\n{}",
+                definition.subject.as_string().trim(),
+                markdown(&definition.children)
+            ))
+        }
+        _ => None,
+    });
+    element.or_else(|| {
+        let ReferenceType::General { target } = reference else {
+            return None;
+        };
+        find_annotation(document, target).map(|annotation| markdown(&annotation.children))
+    })
+}
+
+fn parameters_preview(annotation: &Annotation) -> String {
+    let mut out = format!("`:: {} ::`", annotation.data.label.value.trim());
+    if annotation.data.parameters.is_empty() {
+        out.push_str("\n\nNo parameters");
+    }
+    for (index, parameter) in annotation.data.parameters.iter().enumerate() {
+        out.push_str(if index == 0 { "\n\n" } else { "\n" });
+        out.push_str(&format!("- `{}`: {}", parameter.key, parameter.value));
+    }
+    out
+}
+
+/// Content as Markdown: paragraphs, list items, definitions and code blocks
+fn markdown(items: &[ContentItem]) -> String {
+    let mut blocks = Vec::new();
+    for item in items {
+        match item {
+            ContentItem::Paragraph(paragraph) => blocks.push(paragraph.text()),
+            ContentItem::List(list) => {
+                let items: Vec<String> = list
+                    .items
+                    .iter()
+                    .filter_map(|item| match item {
+                        ContentItem::ListItem(list_item) => {
+                            Some(format!("- {}", list_item.text().trim()))
+                        }
+                        _ => None,
+                    })
+                    .collect();
+                blocks.push(items.join("\n"));
+            }
+            ContentItem::Definition(definition) => blocks.push(format!(
+                "**{}**\n\n{}",
+                definition.subject.as_string().trim(),
+                markdown(&definition.children)
+            )),
+            ContentItem::VerbatimBlock(verbatim) => {
+                let block = code_block(verbatim);
+                blocks.push(format!(
+                    "```{}\n{}\n```",
+                    block.language,
+                    block.text.trim_end()
+                ));
+            }
+            _ => {}
+        }
+    }
+    blocks.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    const SOURCE: &str = "Notes\n\nSee [42], [@doe2024], [Cache] and [https://lex.ing].\n\n:: 42 status=draft ::\n    Footnote text.\n\nCache:\n    Where downloads go.\n";
+
+    #[test]
+    fn test_hover() {
+        let doc = parse_document(SOURCE).unwrap();
+        let at = |line, column| hover_at(&doc, Position::new(line, column), None);
+
+        let footnote = at(2, 5).unwrap();
+        assert_eq!(footnote.contents, "Footnote text.");
+        assert_eq!(footnote.range.start.column, 4);
+        assert_eq!(
+            at(2, 26).unwrap().contents,
+            "**Cache**\n\nWhere downloads go."
+        );
+        assert_eq!(at(2, 40).unwrap().contents, "<https://lex.ing>");
+        assert_eq!(
+            at(4, 4).unwrap().contents,
+            "`:: 42 ::`\n\n- `status`: draft"
+        );
+        assert!(at(2, 1).is_none());
+
+        // Without a bibliography or annotation, the citation has nothing to show
+        assert!(at(2, 13).is_none());
+        let mut registry = BibRegistry::new();
+        registry
+            .load_bibtex(
+                "refs.bib",
+                "@book{doe2024,\n  author = {Doe, Jane},\n  title = {Lex},\n  year = {2024},\n}\n",
+            )
+            .unwrap();
+        let citation = hover_at(
+            &doc,
+            Position::new(2, 13),
+            Some((&registry, CitationStyle::Apa)),
+        )
+        .unwrap();
+        assert!(citation.contents.contains("_Lex_"));
+    }
+}
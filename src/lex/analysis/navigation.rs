@@ -0,0 +1,343 @@
+//! Reference navigation
+//!
+//!     Following the reference under the cursor, as viewers do on Enter and the language
+//!     server does for go-to-definition. [reference_at] finds the reference at a position,
+//!     with its range on the line, and [resolve_reference] where it points:
+//!
+//!         - Footnotes and citations: the annotation with the label (the first key of a
+//!           citation).
+//!         - Session references: the session by id, marker or title, or the
+//!           [display math](crate::lex::display_math) block with the id.
+//!         - General references: a session by title (with or without marker), then a
+//!           definition by subject, then an annotation by label.
+//!         - Files and wiki links: another document, with the section named by the
+//!           `#fragment`; [Workspace](super::workspace::Workspace) resolves them to paths, and
+//!           [resolve_section] finds the section once the document is loaded.
+//!         - URLs: left to the front end to open.
+//!         - Inline images: the image file, or its URL.
+//!
+//!     These are the rules of the [reference graph](super::references). Keeping the back
+//!     stack of jumps is left to the front end, which knows the documents it has open.
+
+use crate::lex::ast::{ContentItem, Document, Position, Range, TextContent};
+use crate::lex::display_math::is_math;
+use crate::lex::inlines::{InlineNode, InlineParser, ReferenceInline, ReferenceType};
+
+/// A reference in the source, with its range (brackets included)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReferenceAt {
+    pub reference: ReferenceInline,
+    pub range: Range,
+}
+
+/// Where a reference points
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NavigationTarget {
+    /// Position in the same document
+    Position(Position),
+    /// Another document, by path relative to the referencing one
+    File {
+        path: String,
+        section: Option<String>,
+    },
+    /// Another document, by file name or title
+    WikiLink {
+        target: String,
+        section: Option<String>,
+    },
+    Url(String),
+}
+
+/// Reference at `position` in `document`
+pub fn reference_at(document: &Document, position: Position) -> Option<ReferenceAt> {
+    reference_at_with_parser(document, position, &InlineParser::new())
+}
+
+/// Reference at `position` in `document`, with inlines parsed by `parser`, as when wiki
+/// links are enabled
+pub fn reference_at_with_parser(
+    document: &Document,
+    position: Position,
+    parser: &InlineParser,
+) -> Option<ReferenceAt> {
+    let texts: Vec<&TextContent> = match document.element_at(position)? {
+        ContentItem::TextLine(line) => vec![&line.content],
+        ContentItem::Session(session) => vec![&session.title],
+        ContentItem::Definition(definition) => vec![&definition.subject],
+        ContentItem::ListItem(item) => item.text.iter().collect(),
+        _ => return None,
+    };
+    texts
+        .into_iter()
+        .find_map(|text| reference_in_text(text, position, parser))
+}
+
+fn reference_in_text(
+    text: &TextContent,
+    position: Position,
+    parser: &InlineParser,
+) -> Option<ReferenceAt> {
+    let location = text.location.as_ref()?;
+    if location.start.line != position.line {
+        return None;
+    }
+    references_in_text(text, parser)
+        .into_iter()
+        .find(|found| (found.range.start.column..found.range.end.column).contains(&position.column))
+}
+
+/// References written in `text`, in order, with their ranges
+pub(crate) fn references_in_text(text: &TextContent, parser: &InlineParser) -> Vec<ReferenceAt> {
+    let Some(location) = text.location.as_ref() else {
+        return Vec::new();
+    };
+    let source = text.as_string();
+    let mut references = Vec::new();
+    collect_references(&parser.parse(source), &mut references);
+
+    // Inlines carry no locations, so references are found again in the text, in order
+    let mut found_references = Vec::new();
+    let mut from = 0;
+    for reference in references {
+        let written = format!("[{}]", reference.raw);
+        let Some(found) = source[from..].find(&written) else {
+            continue;
+        };
+        let (start, end) = (from + found, from + found + written.len());
+        from = end;
+        let line = location.start.line;
+        let range = Range::new(
+            location.span.start + start..location.span.start + end,
+            Position::new(line, location.start.column + start),
+            Position::new(line, location.start.column + end),
+        );
+        found_references.push(ReferenceAt { reference, range });
+    }
+    found_references
+}
+
+fn collect_references(inlines: &[InlineNode], out: &mut Vec<ReferenceInline>) {
+    for inline in inlines {
+        match inline {
+            InlineNode::Reference { data, .. } => out.push(data.clone()),
+            InlineNode::Strong { content, .. } | InlineNode::Emphasis { content, .. } => {
+                collect_references(content, out)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Where `reference` points, for a reference in `document`; `None` for placeholders and
+/// references that don't resolve
+pub fn resolve_reference(
+    document: &Document,
+    reference: &ReferenceType,
+) -> Option<NavigationTarget> {
+    let annotation = |label: &str| {
+        document
+            .find_annotation_by_label(label)
+            .map(|annotation| annotation.header_location().start)
+    };
+    let position = match reference {
+        ReferenceType::FootnoteNumber { number } => annotation(&number.to_string()),
+        ReferenceType::FootnoteLabeled { label } => annotation(label),
+        ReferenceType::Citation(citation) => annotation(citation.keys.first()?),
+        ReferenceType::Session { target } => find_session_by_id(document, target).or_else(|| {
+            let marker = target.trim_end_matches('.');
+            find_session(document, |session_marker, title, _| {
+                session_marker == Some(marker) || title == target
+            })
+            .or_else(|| find_equation_by_id(document, target))
+        }),
+        ReferenceType::General { target } => find_session_by_title(document, target)
+            .or_else(|| find_definition(document, target))
+            .or_else(|| annotation(target)),
+        ReferenceType::Url { target } => return Some(NavigationTarget::Url(target.clone())),
+        ReferenceType::File { target } => {
+            let (path, section) = split_fragment(target);
+            return Some(NavigationTarget::File { path, section });
+        }
+        ReferenceType::Image(image) if image.src.contains("://") => {
+            return Some(NavigationTarget::Url(image.src.clone()))
+        }
+        ReferenceType::Image(image) => {
+            return Some(NavigationTarget::File {
+                path: image.src.clone(),
+                section: None,
+            })
+        }
+        ReferenceType::WikiLink(link) => {
+            return Some(NavigationTarget::WikiLink {
+                target: link.target.clone(),
+                section: link.section.clone(),
+            })
+        }
+        ReferenceType::ToCome { .. } | ReferenceType::NotSure => None,
+    };
+    position.map(NavigationTarget::Position)
+}
+
+/// Position of the session with id `name`, else titled `name` (with or without its
+/// marker), as named by a `#fragment`
+pub fn resolve_section(document: &Document, name: &str) -> Option<Position> {
+    let name = name.trim();
+    find_session_by_id(document, name).or_else(|| find_session_by_title(document, name))
+}
+
+fn find_session_by_title(document: &Document, name: &str) -> Option<Position> {
+    find_session(document, |_, title, _| title == name)
+        .or_else(|| find_session(document, |_, _, title_text| title_text == name))
+}
+
+fn find_session_by_id(document: &Document, id: &str) -> Option<Position> {
+    document
+        .find_session_by_id(id)
+        .map(|session| header_start(session.header_location(), &session.location))
+}
+
+/// Position of the first session whose marker (without trailing period), title and title
+/// without marker satisfy `matches`
+fn find_session(
+    document: &Document,
+    matches: impl Fn(Option<&str>, &str, &str) -> bool,
+) -> Option<Position> {
+    document.root.iter_all_nodes().find_map(|item| match item {
+        ContentItem::Session(session) => {
+            let marker = session
+                .marker
+                .as_ref()
+                .map(|marker| marker.as_str().trim_end_matches('.'));
+            let title = session.title.as_string().trim();
+            matches(marker, title, session.title_text().trim())
+                .then(|| header_start(session.header_location(), &session.location))
+        }
+        _ => None,
+    })
+}
+
+fn find_equation_by_id(document: &Document, id: &str) -> Option<Position> {
+    document.root.iter_all_nodes().find_map(|item| match item {
+        ContentItem::VerbatimBlock(verbatim)
+            if is_math(&verbatim.closing_data.label.value)
+                && verbatim
+                    .parameter("id")
+                    .map(|value| value.trim_matches('"'))
+                    == Some(id) =>
+        {
+            Some(verbatim.location.start)
+        }
+        _ => None,
+    })
+}
+
+fn find_definition(document: &Document, subject: &str) -> Option<Position> {
+    document.root.iter_all_nodes().find_map(|item| match item {
+        ContentItem::Definition(definition) if definition.subject.as_string().trim() == subject => {
+            Some(header_start(
+                definition.header_location(),
+                &definition.location,
+            ))
+        }
+        _ => None,
+    })
+}
+
+fn header_start(header: Option<&Range>, location: &Range) -> Position {
+    header.unwrap_or(location).start
+}
+
+fn split_fragment(target: &str) -> (String, Option<String>) {
+    match target.split_once('#') {
+        Some((path, section)) => (path.to_string(), Some(section.to_string())),
+        None => (target.to_string(), None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    const SOURCE: &str = "Guide\n\n1. Setup\n\n    See [42], [#2], [Cache] and [./other.lex#Intro].\n\n    Cache:\n        Where downloads go.\n\n:: id usage ::\n2. Usage\n\n    Text.\n\n:: 42 ::\n    Footnote text.\n";
+
+    fn target_at(doc: &Document, column: usize) -> Option<NavigationTarget> {
+        let found = reference_at(doc, Position::new(4, column))?;
+        resolve_reference(doc, &found.reference.reference_type)
+    }
+
+    #[test]
+    fn test_reference_at() {
+        let doc = parse_document(SOURCE).unwrap();
+        let found = reference_at(&doc, Position::new(4, 10)).unwrap();
+        assert_eq!(found.reference.raw, "42");
+        assert_eq!(
+            (found.range.start, found.range.end),
+            (Position::new(4, 8), Position::new(4, 12))
+        );
+        assert_eq!(&SOURCE[found.range.span], "[42]");
+        assert!(reference_at(&doc, Position::new(4, 5)).is_none());
+        assert!(reference_at(&doc, Position::new(11, 5)).is_none());
+    }
+
+    #[test]
+    fn test_resolve_reference() {
+        let doc = parse_document(SOURCE).unwrap();
+        assert_eq!(
+            target_at(&doc, 9),
+            Some(NavigationTarget::Position(Position::new(14, 3)))
+        );
+        assert_eq!(
+            target_at(&doc, 15),
+            Some(NavigationTarget::Position(Position::new(10, 0)))
+        );
+        assert_eq!(
+            target_at(&doc, 22),
+            Some(NavigationTarget::Position(Position::new(6, 4)))
+        );
+        assert_eq!(
+            target_at(&doc, 35),
+            Some(NavigationTarget::File {
+                path: "./other.lex".to_string(),
+                section: Some("Intro".to_string()),
+            })
+        );
+        assert_eq!(resolve_section(&doc, "Usage"), Some(Position::new(10, 0)));
+        assert_eq!(resolve_section(&doc, "usage"), Some(Position::new(10, 0)));
+        let by_id = ReferenceType::Session {
+            target: "usage".to_string(),
+        };
+        assert_eq!(
+            resolve_reference(&doc, &by_id),
+            Some(NavigationTarget::Position(Position::new(10, 0)))
+        );
+        assert_eq!(resolve_section(&doc, "Missing"), None);
+    }
+
+    #[test]
+    fn test_equations() {
+        let doc = parse_document(
+            "Notes\n\nSee [#energy].\n\nRest energy:\n    E = m c^2\n:: math id=energy\n",
+        )
+        .unwrap();
+        let found = reference_at(&doc, Position::new(2, 6)).unwrap();
+        assert_eq!(
+            resolve_reference(&doc, &found.reference.reference_type),
+            Some(NavigationTarget::Position(Position::new(4, 0)))
+        );
+    }
+
+    #[test]
+    fn test_wiki_links() {
+        let doc = parse_document("Notes\n\nSee [[Other Note#Part|there]].\n").unwrap();
+        let parser = InlineParser::new().with_wiki_links();
+        let found = reference_at_with_parser(&doc, Position::new(2, 6), &parser).unwrap();
+        assert_eq!(
+            resolve_reference(&doc, &found.reference.reference_type),
+            Some(NavigationTarget::WikiLink {
+                target: "Other Note".to_string(),
+                section: Some("Part".to_string()),
+            })
+        );
+    }
+}
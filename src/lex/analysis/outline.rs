@@ -0,0 +1,383 @@
+//! Outline and fuzzy jump
+//!
+//!     The outline of a document lists its session titles and definition subjects, the
+//!     places a reader jumps to. [fuzzy_find] ranks outline entries against a query the way
+//!     fuzzy finders (fzf, Telescope) do: the query's characters must appear in the entry in
+//!     order, case-insensitively, and matches scoring higher have them in runs, at word
+//!     starts and early in the text. Front ends use it for jump-to-section pickers and
+//!     workspace symbol search.
+//!
+//!     [document_symbols] is the fuller, nested outline of an editor's outline view (LSP's
+//!     `textDocument/documentSymbol`): sessions, definitions, verbatim blocks and annotations,
+//!     each with the range of the whole element and of its header line.
+//!
+//!     [outline_json] is the session tree alone as compact JSON (titles, levels, ids, ranges
+//!     and word counts), for web front ends and documentation portals that render their own
+//!     navigation and have no use for the full AST.
+
+use super::stats::{session_stats, stats};
+use crate::lex::ast::traits::AstNode;
+use crate::lex::ast::{Annotation, ContentItem, Document, Position, Range};
+use serde_json::{json, Value};
+
+/// Kind of element an outline entry is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutlineKind {
+    Session,
+    Definition,
+}
+
+/// A session or definition of the outline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutlineEntry {
+    pub kind: OutlineKind,
+    /// Session title or definition subject, trimmed
+    pub title: String,
+    /// Line (0-based) of the title
+    pub line: usize,
+    /// Number of enclosing sessions and definitions
+    pub depth: usize,
+}
+
+/// Outline of `document`, in document order
+pub fn outline(document: &Document) -> Vec<OutlineEntry> {
+    let mut entries = Vec::new();
+    collect(&document.root.children, 0, &mut entries);
+    entries
+}
+
+fn collect(items: &[ContentItem], depth: usize, out: &mut Vec<OutlineEntry>) {
+    for item in items {
+        let (kind, title, line, children): (_, _, _, &[ContentItem]) = match item {
+            ContentItem::Session(session) => (
+                OutlineKind::Session,
+                &session.title,
+                session.location.start.line,
+                &session.children,
+            ),
+            ContentItem::Definition(definition) => (
+                OutlineKind::Definition,
+                &definition.subject,
+                definition.location.start.line,
+                &definition.children,
+            ),
+            _ => continue,
+        };
+        out.push(OutlineEntry {
+            kind,
+            title: title.as_string().trim().to_string(),
+            line,
+            depth,
+        });
+        collect(children, depth + 1, out);
+    }
+}
+
+/// Kind of element a document symbol is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Session,
+    Definition,
+    Verbatim,
+    Annotation,
+}
+
+/// An element of the nested outline
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentSymbol {
+    /// Session title (with its marker), definition subject, verbatim subject or annotation
+    /// label
+    pub name: String,
+    /// Closing label of a verbatim block, parameters of an annotation
+    pub detail: Option<String>,
+    pub kind: SymbolKind,
+    /// The whole element
+    pub range: Range,
+    /// The element's header line, to select when jumping to the symbol
+    pub selection_range: Range,
+    pub children: Vec<DocumentSymbol>,
+}
+
+/// Nested outline of `document`: its annotations, then its top-level elements
+pub fn document_symbols(document: &Document) -> Vec<DocumentSymbol> {
+    let mut symbols: Vec<DocumentSymbol> = document.annotations.iter().map(annotation).collect();
+    symbols.extend(symbols_of(&document.root.children));
+    symbols
+}
+
+/// Symbols of `items`; annotations attached to an element come right before it, as in the
+/// source, and list items are looked into without a symbol of their own
+fn symbols_of(items: &[ContentItem]) -> Vec<DocumentSymbol> {
+    let mut symbols = Vec::new();
+    for item in items {
+        symbols.extend(item.annotations().iter().map(annotation));
+        let (kind, name, detail, header, children): (_, _, _, _, &[ContentItem]) = match item {
+            ContentItem::Session(session) => (
+                SymbolKind::Session,
+                session.title.as_string(),
+                None,
+                session.header_location(),
+                &session.children,
+            ),
+            ContentItem::Definition(definition) => (
+                SymbolKind::Definition,
+                definition.subject.as_string(),
+                None,
+                definition.header_location(),
+                &definition.children,
+            ),
+            ContentItem::VerbatimBlock(verbatim) => (
+                SymbolKind::Verbatim,
+                verbatim.subject.as_string(),
+                Some(verbatim.closing_data.label.value.clone()),
+                verbatim.subject.location.as_ref(),
+                &[],
+            ),
+            ContentItem::Annotation(item) => {
+                symbols.push(annotation(item));
+                continue;
+            }
+            ContentItem::List(list) => {
+                symbols.extend(symbols_of(&list.items));
+                continue;
+            }
+            ContentItem::ListItem(list_item) => {
+                symbols.extend(symbols_of(&list_item.children));
+                continue;
+            }
+            _ => continue,
+        };
+        let selection_range = header.unwrap_or(item.range()).clone();
+        let range = match item {
+            ContentItem::VerbatimBlock(verbatim) => {
+                Range::bounding_box([&selection_range, &verbatim.closing_data.location].into_iter())
+                    .unwrap_or_else(|| item.range().clone())
+            }
+            _ => span(&selection_range, children),
+        };
+        symbols.push(DocumentSymbol {
+            name: name.trim().trim_end_matches(':').to_string(),
+            detail,
+            kind,
+            range,
+            selection_range,
+            children: symbols_of(children),
+        });
+    }
+    symbols
+}
+
+fn annotation(annotation: &Annotation) -> DocumentSymbol {
+    let parameters: Vec<String> = annotation
+        .data
+        .parameters
+        .iter()
+        .map(|param| format!("{}={}", param.key, param.value))
+        .collect();
+    DocumentSymbol {
+        name: annotation.data.label.value.clone(),
+        detail: (!parameters.is_empty()).then(|| parameters.join(" ")),
+        kind: SymbolKind::Annotation,
+        range: span(annotation.header_location(), &annotation.children),
+        selection_range: annotation.header_location().clone(),
+        children: symbols_of(&annotation.children),
+    }
+}
+
+/// Range from an element's header to the end of its children
+///
+/// Element locations also cover attached annotations, which come before the header.
+fn span(header: &Range, children: &[ContentItem]) -> Range {
+    Range::bounding_box(std::iter::once(header).chain(children.iter().map(|item| item.range())))
+        .unwrap_or_else(|| header.clone())
+}
+
+/// Session tree of `document` as JSON
+///
+/// The document's title and word count, and its sessions, each with its title, level (1 for
+/// top level sessions), explicit id (`null` without one), range from the title to the end of
+/// its content, word count (nested sessions included) and nested sessions.
+pub fn outline_json(document: &Document) -> Value {
+    json!({
+        "title": document.title().trim(),
+        "words": stats(document).words,
+        "sessions": sessions_json(&document.root.children, 1),
+    })
+}
+
+fn sessions_json(items: &[ContentItem], level: usize) -> Vec<Value> {
+    items
+        .iter()
+        .filter_map(ContentItem::as_session)
+        .map(|session| {
+            let header = session.header_location().unwrap_or(&session.location);
+            let range = span(header, &session.children);
+            json!({
+                "title": session.title.as_string().trim(),
+                "level": level,
+                "id": session.id(),
+                "range": { "start": position_json(range.start), "end": position_json(range.end) },
+                "words": session_stats(session, level).words,
+                "children": sessions_json(&session.children, level + 1),
+            })
+        })
+        .collect()
+}
+
+fn position_json(position: Position) -> Value {
+    json!({ "line": position.line, "column": position.column })
+}
+
+/// Score of `candidate` for `query`; `None` unless every character of the query appears in
+/// the candidate, in order
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<u32> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    let candidate: Vec<char> = candidate.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut first = None;
+    let mut previous: Option<usize> = None;
+    for wanted in query {
+        let offset = candidate[next..].iter().position(|c| *c == wanted)?;
+        let index = next + offset;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == index) {
+            score += 5;
+        }
+        if index == 0 || !candidate[index - 1].is_alphanumeric() {
+            score += 3;
+        }
+        first.get_or_insert(index);
+        previous = Some(index);
+        next = index + 1;
+    }
+    // Matches starting early break ties
+    let early = 10 - first.unwrap_or(0).min(10);
+    Some((score * 10 + early) as u32)
+}
+
+/// Outline entries matching `query`, best first; ties keep document order
+pub fn fuzzy_find<'a>(entries: &'a [OutlineEntry], query: &str) -> Vec<&'a OutlineEntry> {
+    let mut scored: Vec<(u32, &OutlineEntry)> = entries
+        .iter()
+        .filter_map(|entry| Some((fuzzy_score(query, &entry.title)?, entry)))
+        .collect();
+    scored.sort_by(|a, b| b.0.cmp(&a.0));
+    scored.into_iter().map(|(_, entry)| entry).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    const SOURCE: &str = "Guide\n\n1. Installation\n\n    Cache directory:\n        Where downloads go.\n\n    1.1. Upgrading\n\n        Text.\n\n2. Configuration\n\n    Text.\n";
+
+    #[test]
+    fn test_outline() {
+        let doc = parse_document(SOURCE).unwrap();
+        let entries: Vec<_> = outline(&doc)
+            .into_iter()
+            .map(|entry| (entry.kind, entry.title, entry.line, entry.depth))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (OutlineKind::Session, "1. Installation".to_string(), 2, 0),
+                (OutlineKind::Definition, "Cache directory".to_string(), 4, 1),
+                (OutlineKind::Session, "1.1. Upgrading".to_string(), 7, 1),
+                (OutlineKind::Session, "2. Configuration".to_string(), 11, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_document_symbols() {
+        let source = "Guide\n\n1. Installation\n\n    :: note ::\n    Cache directory:\n        Where downloads go.\n\n    1.1. Upgrading\n\n        Sample:\n            run()\n        :: python file=up.py\n\n2. Configuration\n\n    Text.\n";
+        let doc = parse_document(source).unwrap();
+        let symbols = document_symbols(&doc);
+        let names: Vec<_> = symbols
+            .iter()
+            .map(|symbol| (symbol.kind, symbol.name.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                (SymbolKind::Session, "1. Installation"),
+                (SymbolKind::Session, "2. Configuration"),
+            ]
+        );
+        let installation = &symbols[0];
+        assert_eq!(installation.selection_range.start.line, 2);
+        assert!(installation.range.end.line >= 12);
+        let nested: Vec<_> = installation
+            .children
+            .iter()
+            .map(|symbol| (symbol.kind, symbol.name.as_str()))
+            .collect();
+        assert_eq!(
+            nested,
+            vec![
+                (SymbolKind::Annotation, "note"),
+                (SymbolKind::Definition, "Cache directory"),
+                (SymbolKind::Session, "1.1. Upgrading"),
+            ]
+        );
+        let sample = &installation.children[2].children[0];
+        assert_eq!(sample.kind, SymbolKind::Verbatim);
+        assert_eq!(sample.name, "Sample");
+        assert_eq!(sample.detail.as_deref(), Some("python"));
+        assert_eq!(sample.selection_range.start.line, 10);
+        assert_eq!(sample.range.end.line, 12);
+    }
+
+    #[test]
+    fn test_outline_json() {
+        let source = "Guide\n\n:: id install ::\n1. Installation\n\n    Get it.\n\n    1.1. Upgrading\n\n        Text.\n\n2. Configuration\n\n    Text.\n";
+        let doc = parse_document(source).unwrap();
+        let json = outline_json(&doc);
+        assert_eq!(json["title"], "Guide");
+        let sessions = json["sessions"].as_array().unwrap();
+        assert_eq!(sessions.len(), 2);
+        let installation = &sessions[0];
+        assert_eq!(installation["title"], "1. Installation");
+        assert_eq!(installation["level"], 1);
+        assert_eq!(installation["id"], "install");
+        assert_eq!(installation["words"], 7);
+        assert_eq!(
+            installation["range"]["start"],
+            json!({ "line": 3, "column": 0 })
+        );
+        // Ends where the next session starts, past the blank line
+        assert_eq!(
+            installation["range"]["end"],
+            json!({ "line": 11, "column": 0 })
+        );
+        let upgrading = &installation["children"][0];
+        assert_eq!(upgrading["level"], 2);
+        assert_eq!(upgrading["id"], Value::Null);
+        assert_eq!(sessions[1]["children"], json!([]));
+    }
+
+    #[test]
+    fn test_fuzzy_find() {
+        let doc = parse_document(SOURCE).unwrap();
+        let entries = outline(&doc);
+        let titles = |query: &str| -> Vec<String> {
+            fuzzy_find(&entries, query)
+                .into_iter()
+                .map(|entry| entry.title.clone())
+                .collect()
+        };
+        assert_eq!(titles("cfg"), vec!["2. Configuration"]);
+        assert_eq!(titles("CACHE"), vec!["Cache directory"]);
+        assert_eq!(titles("upg")[0], "1.1. Upgrading");
+        assert_eq!(titles("in")[0], "1. Installation");
+        assert!(titles("xyz").is_empty());
+        assert!(fuzzy_score("up", "Upgrading") > fuzzy_score("up", "Setup page"));
+    }
+}
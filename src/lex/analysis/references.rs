@@ -0,0 +1,689 @@
+//! Reference graph
+//!
+//!     Collects the inline references of a document as a directed graph: an edge goes from
+//!     the element containing the reference to the element it points at. Sources are the
+//!     innermost enclosing session (or the document itself for top level content), or the
+//!     annotation whose body holds the reference, so a footnote citing a source is an edge
+//!     from the footnote.
+//!
+//!     Targets are resolved against the document:
+//!
+//!         - Footnotes (`[12]`, `[^note]`) and citation keys (`[@key]`): annotations by label.
+//!         - Session references (`[#2.1]`, `[#intro]`): sessions by id, marker or title.
+//!         - General references (`[Cache]`): sessions by title (with or without marker), then
+//!           definitions by subject, then annotations by label.
+//!         - URLs and files are external nodes, as are inline images (`[!./photo.png]`), the
+//!           `src` parameters of verbatim blocks (includes: images, code files, data) and wiki
+//!           links (`[[Target]]`), which only appear when the graph is built with a parser
+//!           that has them enabled.
+//!
+//!     References that don't resolve point at a [NodeKind::Missing] node. `[TK]` placeholders
+//!     and unclassified references are not part of the graph.
+//!
+//!     Nodes are keyed by kind and label, so sessions sharing a title share a node.
+//!
+//! Checks and export
+//!
+//!     [ReferenceGraph::cycles] finds groups of nodes referencing each other in a loop, and
+//!     [ReferenceGraph::orphans] footnotes nobody references. The graph exports as Graphviz
+//!     DOT ([ReferenceGraph::to_dot]) or JSON ([ReferenceGraph::to_json]), which back the
+//!     `refs` and `refs-dot` output formats (`lex inspect ast-refs`).
+
+use crate::lex::ast::{Annotation, ContentItem, Document, Session, TextContent};
+use crate::lex::inlines::{InlineNode, InlineParser, ReferenceType};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+
+/// What a graph node stands for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum NodeKind {
+    /// The document itself, source of references outside any session
+    Document,
+    Session,
+    Definition,
+    Annotation,
+    /// URL or file outside the document
+    External,
+    /// Target of a reference that doesn't resolve
+    Missing,
+}
+
+impl fmt::Display for NodeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            NodeKind::Document => "document",
+            NodeKind::Session => "session",
+            NodeKind::Definition => "definition",
+            NodeKind::Annotation => "annotation",
+            NodeKind::External => "external",
+            NodeKind::Missing => "missing",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Kind of reference an edge was created from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ReferenceKind {
+    Footnote,
+    Citation,
+    Session,
+    General,
+    Url,
+    File,
+    /// `src` parameter of a verbatim block
+    Include,
+    /// `[[Target]]` wiki link to another document
+    WikiLink,
+}
+
+impl fmt::Display for ReferenceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ReferenceKind::Footnote => "footnote",
+            ReferenceKind::Citation => "citation",
+            ReferenceKind::Session => "session",
+            ReferenceKind::General => "general",
+            ReferenceKind::Url => "url",
+            ReferenceKind::File => "file",
+            ReferenceKind::Include => "include",
+            ReferenceKind::WikiLink => "wiki",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A graph node
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceNode {
+    pub kind: NodeKind,
+    /// Session title, definition subject, annotation label or external target
+    pub label: String,
+}
+
+/// A reference from one node to another, as indices into [ReferenceGraph::nodes]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceEdge {
+    pub source: usize,
+    pub target: usize,
+    pub kind: ReferenceKind,
+}
+
+/// Directed graph of a document's references
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReferenceGraph {
+    pub nodes: Vec<ReferenceNode>,
+    pub edges: Vec<ReferenceEdge>,
+}
+
+impl ReferenceGraph {
+    /// Build the reference graph of `document`
+    pub fn build(document: &Document) -> Self {
+        let mut collector = Collector::default();
+        collector.walk_document(document);
+        collector.resolve()
+    }
+
+    /// Build the reference graph of `document`, parsing its inlines with `parser`
+    ///
+    /// Use a parser [with wiki links](InlineParser::with_wiki_links) to include them.
+    pub fn build_with_parser(document: &Document, parser: &InlineParser) -> Self {
+        let mut collector = Collector {
+            parser: Some(parser.clone()),
+            ..Default::default()
+        };
+        collector.walk_document(document);
+        collector.resolve()
+    }
+
+    /// Edges whose target doesn't resolve
+    pub fn unresolved(&self) -> impl Iterator<Item = &ReferenceEdge> {
+        self.edges
+            .iter()
+            .filter(|edge| self.nodes[edge.target].kind == NodeKind::Missing)
+    }
+
+    /// Number of edges of each kind
+    pub fn count_by_kind(&self) -> HashMap<ReferenceKind, usize> {
+        let mut counts = HashMap::new();
+        for edge in &self.edges {
+            *counts.entry(edge.kind).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Edges pointing at `node`
+    pub fn incoming(&self, node: usize) -> impl Iterator<Item = &ReferenceEdge> {
+        self.edges.iter().filter(move |edge| edge.target == node)
+    }
+
+    /// Groups of nodes that reference each other in a loop
+    ///
+    /// Each cycle is a strongly connected component of more than one node, or a node
+    /// referencing itself, listed in node order.
+    pub fn cycles(&self) -> Vec<Vec<usize>> {
+        let mut cycles: Vec<Vec<usize>> = Tarjan::new(self)
+            .components()
+            .into_iter()
+            .filter(|component| match component.as_slice() {
+                [node] => self
+                    .edges
+                    .iter()
+                    .any(|edge| edge.source == *node && edge.target == *node),
+                _ => true,
+            })
+            .map(|mut component| {
+                component.sort_unstable();
+                component
+            })
+            .collect();
+        cycles.sort();
+        cycles
+    }
+
+    /// Footnotes (annotations with a numeric label) that are never referenced
+    pub fn orphans(&self) -> Vec<usize> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| {
+                node.kind == NodeKind::Annotation
+                    && !node.label.is_empty()
+                    && node.label.chars().all(|c| c.is_ascii_digit())
+            })
+            .map(|(index, _)| index)
+            .filter(|index| self.incoming(*index).next().is_none())
+            .collect()
+    }
+
+    /// Graphviz DOT rendering
+    ///
+    /// Missing targets are drawn dashed, external ones as notes and the document as a box.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph references {\n");
+        for (index, node) in self.nodes.iter().enumerate() {
+            let label = match node.kind {
+                NodeKind::Document => "document".to_string(),
+                _ => node.label.clone(),
+            };
+            let style = match node.kind {
+                NodeKind::Document => ", shape=box",
+                NodeKind::External => ", shape=note",
+                NodeKind::Missing => ", style=dashed",
+                _ => "",
+            };
+            out.push_str(&format!(
+                "    n{index} [label={}{style}];\n",
+                dot_string(&label)
+            ));
+        }
+        for edge in &self.edges {
+            out.push_str(&format!(
+                "    n{} -> n{} [label={}];\n",
+                edge.source,
+                edge.target,
+                dot_string(&edge.kind.to_string())
+            ));
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// JSON rendering, including cycles and orphans
+    pub fn to_json(&self) -> Value {
+        let nodes: Vec<Value> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| {
+                json!({ "id": index, "kind": node.kind.to_string(), "label": node.label })
+            })
+            .collect();
+        let edges: Vec<Value> = self
+            .edges
+            .iter()
+            .map(|edge| {
+                json!({
+                    "source": edge.source,
+                    "target": edge.target,
+                    "kind": edge.kind.to_string(),
+                })
+            })
+            .collect();
+        json!({
+            "nodes": nodes,
+            "edges": edges,
+            "cycles": self.cycles(),
+            "orphans": self.orphans(),
+        })
+    }
+
+    fn node(&mut self, kind: NodeKind, label: &str) -> usize {
+        if let Some(index) = self
+            .nodes
+            .iter()
+            .position(|node| node.kind == kind && node.label == label)
+        {
+            return index;
+        }
+        self.nodes.push(ReferenceNode {
+            kind,
+            label: label.to_string(),
+        });
+        self.nodes.len() - 1
+    }
+}
+
+/// Quoted DOT string
+fn dot_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Tarjan's strongly connected components
+struct Tarjan<'g> {
+    graph: &'g ReferenceGraph,
+    index: Vec<Option<usize>>,
+    lowlink: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    next: usize,
+    components: Vec<Vec<usize>>,
+}
+
+impl<'g> Tarjan<'g> {
+    fn new(graph: &'g ReferenceGraph) -> Self {
+        let count = graph.nodes.len();
+        Self {
+            graph,
+            index: vec![None; count],
+            lowlink: vec![0; count],
+            on_stack: vec![false; count],
+            stack: Vec::new(),
+            next: 0,
+            components: Vec::new(),
+        }
+    }
+
+    fn components(mut self) -> Vec<Vec<usize>> {
+        for node in 0..self.graph.nodes.len() {
+            if self.index[node].is_none() {
+                self.visit(node);
+            }
+        }
+        self.components
+    }
+
+    fn visit(&mut self, node: usize) {
+        self.index[node] = Some(self.next);
+        self.lowlink[node] = self.next;
+        self.next += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+
+        let graph = self.graph;
+        for edge in graph.edges.iter().filter(|edge| edge.source == node) {
+            match self.index[edge.target] {
+                None => {
+                    self.visit(edge.target);
+                    self.lowlink[node] = self.lowlink[node].min(self.lowlink[edge.target]);
+                }
+                Some(index) if self.on_stack[edge.target] => {
+                    self.lowlink[node] = self.lowlink[node].min(index);
+                }
+                Some(_) => {}
+            }
+        }
+
+        if Some(self.lowlink[node]) == self.index[node] {
+            let mut component = Vec::new();
+            while let Some(member) = self.stack.pop() {
+                self.on_stack[member] = false;
+                component.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
+    }
+}
+
+/// A reference as found, before its target is resolved
+struct RawReference {
+    source: usize,
+    kind: ReferenceKind,
+    target: String,
+}
+
+/// Walks the document, registering source nodes and collecting references
+#[derive(Default)]
+struct Collector {
+    graph: ReferenceGraph,
+    references: Vec<RawReference>,
+    /// Session marker (without trailing period) to session node
+    session_markers: HashMap<String, usize>,
+    /// Explicit session id (`:: id intro ::`) to session node
+    session_ids: HashMap<String, usize>,
+    /// Session title without its marker to session node
+    session_titles: HashMap<String, usize>,
+    /// Parser for inlines; the default parser when unset
+    parser: Option<InlineParser>,
+}
+
+impl Collector {
+    fn walk_document(&mut self, document: &Document) {
+        let root = self.graph.node(NodeKind::Document, "");
+        for annotation in document
+            .annotations
+            .iter()
+            .chain(&document.root.annotations)
+        {
+            self.annotation(annotation);
+        }
+        self.items(&document.root.children, root);
+    }
+
+    fn items(&mut self, items: &[ContentItem], source: usize) {
+        for item in items {
+            self.item(item, source);
+            for annotation in item.annotations() {
+                self.annotation(annotation);
+            }
+        }
+    }
+
+    fn item(&mut self, item: &ContentItem, source: usize) {
+        match item {
+            ContentItem::Session(session) => {
+                let node = self.session(session);
+                self.text(&session.title, node);
+                self.items(&session.children, node);
+            }
+            ContentItem::Paragraph(paragraph) => self.items(&paragraph.lines, source),
+            ContentItem::TextLine(line) => self.text(&line.content, source),
+            ContentItem::List(list) => self.items(&list.items, source),
+            ContentItem::ListItem(item) => {
+                for text in &item.text {
+                    self.text(text, source);
+                }
+                self.items(&item.children, source);
+            }
+            ContentItem::Definition(definition) => {
+                self.graph
+                    .node(NodeKind::Definition, definition.subject.as_string().trim());
+                self.text(&definition.subject, source);
+                self.items(&definition.children, source);
+            }
+            ContentItem::Annotation(annotation) => self.annotation(annotation),
+            ContentItem::VerbatimBlock(verbatim) => {
+                if let Some(src) = verbatim.src_parameter() {
+                    self.references.push(RawReference {
+                        source,
+                        kind: ReferenceKind::Include,
+                        target: src.to_string(),
+                    });
+                }
+            }
+            ContentItem::VerbatimLine(_) | ContentItem::BlankLineGroup(_) => {}
+        }
+    }
+
+    fn session(&mut self, session: &Session) -> usize {
+        let node = self
+            .graph
+            .node(NodeKind::Session, session.title.as_string().trim());
+        if let Some(marker) = &session.marker {
+            let key = marker.as_str().trim_end_matches('.').to_string();
+            self.session_markers.entry(key).or_insert(node);
+        }
+        if let Some(id) = session.id() {
+            self.session_ids.entry(id.to_string()).or_insert(node);
+        }
+        self.session_titles
+            .entry(session.title_text().trim().to_string())
+            .or_insert(node);
+        node
+    }
+
+    fn annotation(&mut self, annotation: &Annotation) {
+        let node = self
+            .graph
+            .node(NodeKind::Annotation, annotation.data.label.value.trim());
+        self.items(&annotation.children, node);
+    }
+
+    fn text(&mut self, text: &TextContent, source: usize) {
+        let inlines = match &self.parser {
+            Some(parser) => parser.parse(text.as_string()),
+            None => text.inline_items(),
+        };
+        self.inlines(&inlines, source);
+    }
+
+    fn inlines(&mut self, inlines: &[InlineNode], source: usize) {
+        for inline in inlines {
+            match inline {
+                InlineNode::Reference { data, .. } => self.reference(&data.reference_type, source),
+                InlineNode::Strong { content, .. } | InlineNode::Emphasis { content, .. } => {
+                    self.inlines(content, source)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn reference(&mut self, reference: &ReferenceType, source: usize) {
+        let mut push = |kind, target: &str| {
+            self.references.push(RawReference {
+                source,
+                kind,
+                target: target.to_string(),
+            })
+        };
+        match reference {
+            ReferenceType::FootnoteNumber { number } => {
+                push(ReferenceKind::Footnote, &number.to_string())
+            }
+            ReferenceType::FootnoteLabeled { label } => push(ReferenceKind::Footnote, label),
+            ReferenceType::Citation(citation) => {
+                for key in &citation.keys {
+                    push(ReferenceKind::Citation, key);
+                }
+            }
+            ReferenceType::Session { target } => push(ReferenceKind::Session, target),
+            ReferenceType::General { target } => push(ReferenceKind::General, target),
+            ReferenceType::Url { target } => push(ReferenceKind::Url, target),
+            ReferenceType::File { target } => push(ReferenceKind::File, target),
+            ReferenceType::WikiLink(link) => push(ReferenceKind::WikiLink, &link.target),
+            ReferenceType::Image(image) if image.src.contains("://") => {
+                push(ReferenceKind::Url, &image.src)
+            }
+            ReferenceType::Image(image) => push(ReferenceKind::File, &image.src),
+            ReferenceType::ToCome { .. } | ReferenceType::NotSure => {}
+        }
+    }
+
+    fn resolve(mut self) -> ReferenceGraph {
+        let references = std::mem::take(&mut self.references);
+        for reference in references {
+            let target = self.target(reference.kind, &reference.target);
+            self.graph.edges.push(ReferenceEdge {
+                source: reference.source,
+                target,
+                kind: reference.kind,
+            });
+        }
+        self.graph
+    }
+
+    fn target(&mut self, kind: ReferenceKind, target: &str) -> usize {
+        let find = |graph: &ReferenceGraph, kind: NodeKind| {
+            graph
+                .nodes
+                .iter()
+                .position(|node| node.kind == kind && node.label == target)
+        };
+        let found = match kind {
+            ReferenceKind::Footnote | ReferenceKind::Citation => {
+                find(&self.graph, NodeKind::Annotation)
+            }
+            ReferenceKind::Session => self
+                .session_ids
+                .get(target)
+                .or_else(|| self.session_markers.get(target.trim_end_matches('.')))
+                .copied()
+                .or_else(|| find(&self.graph, NodeKind::Session)),
+            ReferenceKind::General => find(&self.graph, NodeKind::Session)
+                .or_else(|| self.session_titles.get(target).copied())
+                .or_else(|| find(&self.graph, NodeKind::Definition))
+                .or_else(|| find(&self.graph, NodeKind::Annotation)),
+            ReferenceKind::Url
+            | ReferenceKind::File
+            | ReferenceKind::Include
+            | ReferenceKind::WikiLink => Some(self.graph.node(NodeKind::External, target)),
+        };
+        found.unwrap_or_else(|| self.graph.node(NodeKind::Missing, target))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    fn edge_labels(graph: &ReferenceGraph) -> Vec<(String, ReferenceKind, NodeKind, String)> {
+        graph
+            .edges
+            .iter()
+            .map(|edge| {
+                let (source, target) = (&graph.nodes[edge.source], &graph.nodes[edge.target]);
+                (
+                    source.label.clone(),
+                    edge.kind,
+                    target.kind,
+                    target.label.clone(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_definitions_and_footnotes() {
+        let source = "Title\n\nUses [Cache] and [^missing].\n\nCache:\n    Kept around [1].\n\nEnd.\n\n:: 1 ::\n    The footnote.\n::\n";
+        let doc = parse_document(source).unwrap();
+        let graph = ReferenceGraph::build(&doc);
+        let edges = edge_labels(&graph);
+
+        assert!(edges.contains(&(
+            String::new(),
+            ReferenceKind::General,
+            NodeKind::Definition,
+            "Cache".to_string()
+        )));
+        assert!(edges.contains(&(
+            String::new(),
+            ReferenceKind::Footnote,
+            NodeKind::Annotation,
+            "1".to_string()
+        )));
+        assert_eq!(graph.count_by_kind()[&ReferenceKind::Footnote], 2);
+        assert_eq!(graph.unresolved().count(), 1);
+    }
+
+    #[test]
+    fn test_sources_and_resolution() {
+        let source = "Title\n\n1. First\n\n    See [#2] and [https://lex.ing].\n\n2. Second\n\n    Back to [First].\n\n:: note ::\n    Cites [@nobody].\n::\n";
+        let doc = parse_document(source).unwrap();
+        let graph = ReferenceGraph::build(&doc);
+        let edges = edge_labels(&graph);
+
+        assert!(edges.contains(&(
+            "1. First".to_string(),
+            ReferenceKind::Session,
+            NodeKind::Session,
+            "2. Second".to_string()
+        )));
+        assert!(edges.contains(&(
+            "1. First".to_string(),
+            ReferenceKind::Url,
+            NodeKind::External,
+            "https://lex.ing".to_string()
+        )));
+        assert!(edges.contains(&(
+            "2. Second".to_string(),
+            ReferenceKind::General,
+            NodeKind::Session,
+            "1. First".to_string()
+        )));
+        assert!(edges.contains(&(
+            "note".to_string(),
+            ReferenceKind::Citation,
+            NodeKind::Missing,
+            "nobody".to_string()
+        )));
+        assert_eq!(graph.unresolved().count(), 1);
+    }
+
+    #[test]
+    fn test_session_ids() {
+        let source =
+            "Title\n\n1. First\n\n    See [#intro].\n\n:: id intro ::\n2. Second\n\n    Text.\n";
+        let doc = parse_document(source).unwrap();
+        let graph = ReferenceGraph::build(&doc);
+
+        assert!(edge_labels(&graph).contains(&(
+            "1. First".to_string(),
+            ReferenceKind::Session,
+            NodeKind::Session,
+            "2. Second".to_string()
+        )));
+        assert_eq!(graph.unresolved().count(), 0);
+    }
+
+    #[test]
+    fn test_cycles_orphans_and_includes() {
+        let source = "Title\n\n1. First\n\n    See [#2].\n\n2. Second\n\n    See [#1].\n\n    Diagram:\n        boxes\n    :: image src=./diagram.png ::\n\n3. Third\n\n    See [#3].\n\nEnd.\n\n:: 7 ::\n    Nobody cites this.\n::\n";
+        let doc = parse_document(source).unwrap();
+        let graph = ReferenceGraph::build(&doc);
+
+        let labels = |nodes: &[usize]| -> Vec<String> {
+            nodes
+                .iter()
+                .map(|index| graph.nodes[*index].label.clone())
+                .collect()
+        };
+        let cycles: Vec<Vec<String>> = graph.cycles().iter().map(|cycle| labels(cycle)).collect();
+        assert_eq!(
+            cycles,
+            vec![
+                vec!["1. First".to_string(), "2. Second".to_string()],
+                vec!["3. Third".to_string()]
+            ]
+        );
+        assert_eq!(labels(&graph.orphans()), vec!["7"]);
+        assert!(edge_labels(&graph).contains(&(
+            "2. Second".to_string(),
+            ReferenceKind::Include,
+            NodeKind::External,
+            "./diagram.png".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_exports() {
+        let source = "Title\n\nA [\"quoted\"] reference.\n";
+        let graph = ReferenceGraph::build(&parse_document(source).unwrap());
+
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph references {\n"));
+        assert!(dot.contains("n0 [label=\"document\", shape=box];"));
+        assert!(dot.contains("style=dashed"));
+        assert!(dot.contains("n0 -> n1 [label=\"general\"];"));
+
+        let json = graph.to_json();
+        assert_eq!(json["edges"][0]["kind"], "general");
+        assert_eq!(json["nodes"][1]["kind"], "missing");
+        assert_eq!(json["cycles"], json!([]));
+    }
+}
@@ -0,0 +1,375 @@
+//! Full-text search
+//!
+//!     Finds text in a document, or in every `.lex` file under a directory, and reports each
+//!     match with its structural context: the file, the line, the kind of element and the
+//!     titles of the sessions enclosing it. Search is case-insensitive unless asked otherwise,
+//!     and can be restricted to session titles, definition subjects or annotations
+//!     ([SearchScope]); in the annotations scope, both annotation headers and the text inside
+//!     annotations match. Searches can also be limited to content carrying a tag (see
+//!     [tags](super::tags)): a tagged document, or tagged sessions of other documents.
+
+use super::tags::{document_tags, session_tags};
+use crate::lex::ast::{Annotation, ContentItem, Document, TextContent};
+use crate::lex::parsing::parse_document;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Which elements a search looks at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchScope {
+    #[default]
+    All,
+    Titles,
+    Definitions,
+    Annotations,
+}
+
+impl SearchScope {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "all" => Some(SearchScope::All),
+            "titles" => Some(SearchScope::Titles),
+            "definitions" => Some(SearchScope::Definitions),
+            "annotations" => Some(SearchScope::Annotations),
+            _ => None,
+        }
+    }
+}
+
+/// How to search
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    pub scope: SearchScope,
+    pub case_sensitive: bool,
+    /// Only search content carrying this tag
+    pub tag: Option<String>,
+}
+
+/// Kind of element a match is in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchKind {
+    SessionTitle,
+    Paragraph,
+    ListItem,
+    DefinitionSubject,
+    Annotation,
+    Verbatim,
+}
+
+impl fmt::Display for MatchKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MatchKind::SessionTitle => "session title",
+            MatchKind::Paragraph => "paragraph",
+            MatchKind::ListItem => "list item",
+            MatchKind::DefinitionSubject => "definition",
+            MatchKind::Annotation => "annotation",
+            MatchKind::Verbatim => "verbatim",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A line of text containing the query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchMatch {
+    /// File the match is in; `None` when searching a single document
+    pub path: Option<PathBuf>,
+    /// Line (0-based) of the matching text
+    pub line: usize,
+    pub kind: MatchKind,
+    /// Titles of the enclosing sessions, outermost first
+    pub session_path: Vec<String>,
+    /// The matching text, trimmed
+    pub text: String,
+}
+
+/// Matches across a directory, plus the files that failed to load
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DirectorySearch {
+    pub matches: Vec<SearchMatch>,
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+/// Matches of `query` in `document`, in document order
+pub fn search_document(
+    document: &Document,
+    query: &str,
+    options: &SearchOptions,
+) -> Vec<SearchMatch> {
+    let tag = options.tag.as_deref().map(str::to_lowercase);
+    let mut search = Search {
+        query: normalize(query, options.case_sensitive),
+        options,
+        tagged: match &tag {
+            Some(tag) => document_tags(document).contains(tag),
+            None => true,
+        },
+        tag,
+        path: Vec::new(),
+        matches: Vec::new(),
+    };
+    if search.query.is_empty() {
+        return Vec::new();
+    }
+    for annotation in &document.annotations {
+        search.annotation(annotation);
+    }
+    search.items(&document.root.children, false);
+    // Document-level annotations can sit anywhere in the source
+    search.matches.sort_by_key(|found| found.line);
+    search.matches
+}
+
+/// Matches of `query` in every `.lex` file under `dir`, in path order
+pub fn search_dir(dir: &Path, query: &str, options: &SearchOptions) -> DirectorySearch {
+    let mut result = DirectorySearch::default();
+    let mut files = Vec::new();
+    if let Err(error) = lex_files(dir, &mut files) {
+        result.errors.push((dir.to_path_buf(), error.to_string()));
+    }
+    files.sort();
+    for path in files {
+        let document = std::fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|source| parse_document(&source));
+        match document {
+            Ok(document) => {
+                result
+                    .matches
+                    .extend(
+                        search_document(&document, query, options)
+                            .into_iter()
+                            .map(|found| SearchMatch {
+                                path: Some(path.clone()),
+                                ..found
+                            }),
+                    )
+            }
+            Err(error) => result.errors.push((path, error)),
+        }
+    }
+    result
+}
+
+/// Every `.lex` file under `dir`, recursively
+pub(crate) fn lex_files(dir: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            lex_files(&path, out)?;
+        } else if path.extension().and_then(|ext| ext.to_str()) == Some("lex") {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn normalize(text: &str, case_sensitive: bool) -> String {
+    if case_sensitive {
+        text.to_string()
+    } else {
+        text.to_lowercase()
+    }
+}
+
+struct Search<'a> {
+    query: String,
+    options: &'a SearchOptions,
+    tag: Option<String>,
+    /// Whether the current content carries the tag searched for
+    tagged: bool,
+    path: Vec<String>,
+    matches: Vec<SearchMatch>,
+}
+
+impl Search<'_> {
+    fn check(&mut self, kind: MatchKind, in_annotation: bool, line: usize, text: &str) {
+        let in_scope = match self.options.scope {
+            SearchScope::All => true,
+            SearchScope::Titles => kind == MatchKind::SessionTitle,
+            SearchScope::Definitions => kind == MatchKind::DefinitionSubject,
+            SearchScope::Annotations => in_annotation,
+        };
+        if in_scope
+            && self.tagged
+            && normalize(text, self.options.case_sensitive).contains(&self.query)
+        {
+            self.matches.push(SearchMatch {
+                path: None,
+                line,
+                kind,
+                session_path: self.path.clone(),
+                text: text.trim().to_string(),
+            });
+        }
+    }
+
+    fn text(&mut self, kind: MatchKind, in_annotation: bool, line: usize, text: &TextContent) {
+        self.check(kind, in_annotation, line, text.as_string());
+    }
+
+    fn annotation(&mut self, annotation: &Annotation) {
+        let mut header = annotation.data.label.value.clone();
+        for param in &annotation.data.parameters {
+            header.push_str(&format!(" {}={}", param.key, param.value));
+        }
+        let line = annotation.header_location().start.line;
+        self.check(MatchKind::Annotation, true, line, &header);
+        self.items(&annotation.children, true);
+    }
+
+    fn items(&mut self, items: &[ContentItem], in_annotation: bool) {
+        for item in items {
+            self.item(item, in_annotation);
+        }
+    }
+
+    fn item(&mut self, item: &ContentItem, in_annotation: bool) {
+        for annotation in item.annotations() {
+            self.annotation(annotation);
+        }
+        match item {
+            ContentItem::Session(session) => {
+                let outer = self.tagged;
+                if let Some(tag) = &self.tag {
+                    self.tagged |= session_tags(session).contains(tag);
+                }
+                let line = session.location.start.line;
+                self.text(MatchKind::SessionTitle, in_annotation, line, &session.title);
+                self.path.push(session.title.as_string().trim().to_string());
+                self.items(&session.children, in_annotation);
+                self.path.pop();
+                self.tagged = outer;
+            }
+            ContentItem::Paragraph(paragraph) => self.items(&paragraph.lines, in_annotation),
+            ContentItem::TextLine(line) => self.text(
+                MatchKind::Paragraph,
+                in_annotation,
+                line.location.start.line,
+                &line.content,
+            ),
+            ContentItem::List(list) => self.items(&list.items, in_annotation),
+            ContentItem::ListItem(list_item) => {
+                let line = list_item.location.start.line;
+                for text in &list_item.text {
+                    self.text(MatchKind::ListItem, in_annotation, line, text);
+                }
+                self.items(&list_item.children, in_annotation);
+            }
+            ContentItem::Definition(definition) => {
+                let line = definition.location.start.line;
+                self.text(
+                    MatchKind::DefinitionSubject,
+                    in_annotation,
+                    line,
+                    &definition.subject,
+                );
+                self.items(&definition.children, in_annotation);
+            }
+            ContentItem::Annotation(annotation) => self.annotation(annotation),
+            ContentItem::VerbatimBlock(verbatim) => {
+                let line = verbatim.location.start.line;
+                self.text(MatchKind::Verbatim, in_annotation, line, &verbatim.subject);
+                for content in verbatim.content_lines() {
+                    self.text(
+                        MatchKind::Verbatim,
+                        in_annotation,
+                        content.location.start.line,
+                        &content.content,
+                    );
+                }
+            }
+            ContentItem::VerbatimLine(line) => self.text(
+                MatchKind::Verbatim,
+                in_annotation,
+                line.location.start.line,
+                &line.content,
+            ),
+            ContentItem::BlankLineGroup(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "Guide\n\nCaching matters.\n\n1. Setup\n\n    Cache:\n        Where results are kept.\n\n    1.1. Cache size\n\n        - Keep the cache small.\n        - Clear it weekly.\n\n:: note ::\n    The cache is cleared on restart.\n::\n";
+
+    fn found(matches: &[SearchMatch]) -> Vec<(usize, MatchKind, String)> {
+        matches
+            .iter()
+            .map(|found| (found.line, found.kind, found.session_path.join(" > ")))
+            .collect()
+    }
+
+    #[test]
+    fn test_search_document() {
+        let doc = parse_document(SOURCE).unwrap();
+        let matches = search_document(&doc, "cache", &SearchOptions::default());
+        assert_eq!(
+            found(&matches),
+            vec![
+                (6, MatchKind::DefinitionSubject, "1. Setup".to_string()),
+                (9, MatchKind::SessionTitle, "1. Setup".to_string()),
+                (
+                    11,
+                    MatchKind::ListItem,
+                    "1. Setup > 1.1. Cache size".to_string()
+                ),
+                (15, MatchKind::Paragraph, String::new()),
+            ]
+        );
+
+        let titles = SearchOptions {
+            scope: SearchScope::Titles,
+            ..Default::default()
+        };
+        assert_eq!(search_document(&doc, "cache", &titles).len(), 1);
+
+        let annotations = SearchOptions {
+            scope: SearchScope::Annotations,
+            ..Default::default()
+        };
+        let matches = search_document(&doc, "cache", &annotations);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].text, "The cache is cleared on restart.");
+
+        let exact = SearchOptions {
+            case_sensitive: true,
+            ..Default::default()
+        };
+        assert_eq!(search_document(&doc, "Cache", &exact).len(), 2);
+
+        let source = ":: tags ops ::\n\nGuide\n\nCache here.\n\n:: tags perf ::\n1. Tuning\n\n    Cache there.\n";
+        let doc = parse_document(source).unwrap();
+        let tagged = |tag: &str| SearchOptions {
+            tag: Some(tag.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(search_document(&doc, "cache", &tagged("OPS")).len(), 2);
+        assert_eq!(search_document(&doc, "cache", &tagged("perf")).len(), 1);
+        assert!(search_document(&doc, "cache", &tagged("none")).is_empty());
+    }
+
+    #[test]
+    fn test_search_dir() {
+        let dir = std::env::temp_dir().join(format!("lex-search-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(dir.join("guide.lex"), SOURCE).unwrap();
+        std::fs::write(dir.join("nested/notes.lex"), "Notes\n\nNo caching here.\n").unwrap();
+        std::fs::write(dir.join("readme.txt"), "cache").unwrap();
+
+        let result = search_dir(&dir, "cach", &SearchOptions::default());
+        let files: Vec<_> = result
+            .matches
+            .iter()
+            .filter_map(|found| found.path.as_ref()?.file_name()?.to_str())
+            .collect();
+        assert_eq!(files.len(), 6);
+        assert_eq!(files.last(), Some(&"notes.lex"));
+        assert!(result.errors.is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
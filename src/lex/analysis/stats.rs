@@ -0,0 +1,170 @@
+//! Document statistics
+//!
+//!     Element counts, nesting depth histograms, word counts and the reference graph of a
+//!     document, computed in one place for the CLI `stats` command, editor lenses and status
+//!     bars.
+//!
+//!     Words are whitespace separated runs in session titles, paragraphs, list items and
+//!     definitions. Verbatim content and annotation bodies are not counted: they are code and
+//!     metadata rather than prose.
+
+use super::references::ReferenceGraph;
+use crate::lex::ast::traits::AstNode;
+use crate::lex::ast::{ContentItem, Document, Session, TextContent};
+use std::collections::BTreeMap;
+
+/// Statistics of a document
+#[derive(Debug, Clone, PartialEq)]
+pub struct DocumentStats {
+    /// Number of elements of each type, keyed by node type (`Session`, `Paragraph`, ...);
+    /// blank line groups are left out
+    pub elements: BTreeMap<&'static str, usize>,
+    /// Number of elements at each nesting depth, top level elements being at depth 0
+    pub depths: BTreeMap<usize, usize>,
+    /// Number of sessions at each session level, top level sessions being at level 1
+    pub session_levels: BTreeMap<usize, usize>,
+    /// Words in the whole document
+    pub words: usize,
+    /// Every session, in document order
+    pub sessions: Vec<SessionStats>,
+    pub references: ReferenceGraph,
+}
+
+impl DocumentStats {
+    /// Number of elements of `node_type`
+    pub fn count(&self, node_type: &str) -> usize {
+        self.elements.get(node_type).copied().unwrap_or(0)
+    }
+
+    /// Deepest nesting depth, if the document has any content
+    pub fn max_depth(&self) -> Option<usize> {
+        self.depths.keys().next_back().copied()
+    }
+}
+
+/// Statistics of a single session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionStats {
+    pub title: String,
+    /// Session level, 1 for top level sessions
+    pub level: usize,
+    /// Words in the title and the whole body, nested sessions included
+    pub words: usize,
+    /// Words in the title and the body, nested sessions excluded
+    pub own_words: usize,
+}
+
+/// Compute the statistics of `document`
+pub fn stats(document: &Document) -> DocumentStats {
+    let mut elements = BTreeMap::new();
+    let mut depths = BTreeMap::new();
+    for (item, depth) in document.root.iter_all_nodes_with_depth() {
+        if item.is_blank_line_group() {
+            continue;
+        }
+        *elements.entry(item.node_type()).or_insert(0) += 1;
+        *depths.entry(depth).or_insert(0) += 1;
+    }
+
+    let mut sessions = Vec::new();
+    collect_sessions(&document.root.children, 1, &mut sessions);
+    let mut session_levels = BTreeMap::new();
+    for session in &sessions {
+        *session_levels.entry(session.level).or_insert(0) += 1;
+    }
+
+    DocumentStats {
+        elements,
+        depths,
+        session_levels,
+        words: items_words(&document.root.children, true),
+        sessions,
+        references: ReferenceGraph::build(document),
+    }
+}
+
+fn collect_sessions(items: &[ContentItem], level: usize, out: &mut Vec<SessionStats>) {
+    for session in items.iter().filter_map(ContentItem::as_session) {
+        out.push(session_stats(session, level));
+        collect_sessions(&session.children, level + 1, out);
+    }
+}
+
+pub(crate) fn session_stats(session: &Session, level: usize) -> SessionStats {
+    let title = text_words(&session.title);
+    SessionStats {
+        title: session.title.as_string().trim().to_string(),
+        level,
+        words: title + items_words(&session.children, true),
+        own_words: title + items_words(&session.children, false),
+    }
+}
+
+/// Words in `items`, descending into sessions if `nested` is set
+fn items_words(items: &[ContentItem], nested: bool) -> usize {
+    items.iter().map(|item| item_words(item, nested)).sum()
+}
+
+fn item_words(item: &ContentItem, nested: bool) -> usize {
+    match item {
+        ContentItem::Session(session) if nested => {
+            text_words(&session.title) + items_words(&session.children, nested)
+        }
+        ContentItem::Session(_) => 0,
+        ContentItem::Paragraph(paragraph) => items_words(&paragraph.lines, nested),
+        ContentItem::TextLine(line) => text_words(&line.content),
+        ContentItem::List(list) => items_words(&list.items, nested),
+        ContentItem::ListItem(list_item) => {
+            list_item.text.iter().map(text_words).sum::<usize>()
+                + items_words(&list_item.children, nested)
+        }
+        ContentItem::Definition(definition) => {
+            text_words(&definition.subject) + items_words(&definition.children, nested)
+        }
+        ContentItem::Annotation(_)
+        | ContentItem::VerbatimBlock(_)
+        | ContentItem::VerbatimLine(_)
+        | ContentItem::BlankLineGroup(_) => 0,
+    }
+}
+
+fn text_words(text: &TextContent) -> usize {
+    text.as_string().split_whitespace().count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+    use crate::lex::testing::lexplore::Lexplore;
+
+    #[test]
+    fn test_counts_and_words() {
+        let source = "Title\n\n1. First\n\n    Two words.\n\n    1.1. Nested\n\n        - one item\n        - two items\n\n2. Second\n\n    Code:\n        not counted\n    :: shell ::\n";
+        let doc = parse_document(source).unwrap();
+        let stats = stats(&doc);
+
+        assert_eq!(stats.count("Session"), 3);
+        assert_eq!(stats.count("List"), 1);
+        assert_eq!(stats.count("ListItem"), 2);
+        assert_eq!(stats.session_levels, BTreeMap::from([(1, 2), (2, 1)]));
+
+        let first = &stats.sessions[0];
+        assert_eq!(first.title, "1. First");
+        assert_eq!(first.own_words, 4);
+        assert_eq!(first.words, 4 + 6);
+        assert_eq!(stats.sessions[1].level, 2);
+        assert_eq!(stats.sessions[2].words, 2);
+    }
+
+    #[test]
+    fn test_fixture_stats() {
+        let doc = Lexplore::benchmark(50).parse().unwrap();
+        let stats = stats(&doc);
+
+        assert!(stats.words > 0);
+        assert_eq!(stats.max_depth(), stats.depths.keys().max().copied());
+        assert_eq!(stats.count("BlankLineGroup"), 0);
+        assert!(!stats.references.edges.is_empty());
+    }
+}
@@ -0,0 +1,220 @@
+//! Tags
+//!
+//!     Documents and sessions are tagged with a `tags` annotation:
+//!
+//!         :: tags rust, parsing ::
+//!
+//!     A document-level annotation tags the whole document; an annotation attached to a
+//!     session tags that session. Tags are compared case-insensitively and stored lowercase.
+//!
+//!     [Taxonomy] collects the tags of many documents (a workspace) into an index from tag to
+//!     the places using it, for listing tags, filtering search results
+//!     ([super::search::SearchOptions::tag]) and generating tag index pages.
+
+use super::search::lex_files;
+use crate::lex::ast::{Annotation, Document, Session};
+use crate::lex::parsing::parse_document;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Annotation label carrying tags
+pub const TAGS_LABEL: &str = "tags";
+
+/// Tags named by `annotation`, if it is a `tags` annotation
+///
+/// The annotation header `tags rust, parsing` reads as the label `tags rust` with a
+/// parameter `parsing` that has no value, so tags come from both.
+pub fn annotation_tags(annotation: &Annotation) -> Vec<String> {
+    let mut words = annotation.data.label.value.split_whitespace();
+    if words.next() != Some(TAGS_LABEL) {
+        return Vec::new();
+    }
+    let mut tags: Vec<String> = words.map(normalize).collect();
+    tags.extend(
+        annotation
+            .data
+            .parameters
+            .iter()
+            .filter(|param| param.value.is_empty())
+            .map(|param| normalize(&param.key)),
+    );
+    tags.retain(|tag| !tag.is_empty());
+    tags
+}
+
+fn normalize(tag: &str) -> String {
+    tag.trim_matches(|c: char| c == ',' || c.is_whitespace())
+        .to_lowercase()
+}
+
+fn collect<'a>(annotations: impl IntoIterator<Item = &'a Annotation>) -> Vec<String> {
+    let mut tags: Vec<String> = annotations.into_iter().flat_map(annotation_tags).collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Tags of the whole document
+pub fn document_tags(document: &Document) -> Vec<String> {
+    collect(
+        document
+            .annotations
+            .iter()
+            .chain(document.root.annotations.iter()),
+    )
+}
+
+/// Tags of `session` itself, not inherited from the document or enclosing sessions
+pub fn session_tags(session: &Session) -> Vec<String> {
+    collect(&session.annotations)
+}
+
+/// A place a tag is used
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TagUse {
+    pub path: PathBuf,
+    /// Title of the tagged session; `None` when the whole document is tagged
+    pub session: Option<String>,
+    /// Line (0-based) of the tagged session, 0 for documents
+    pub line: usize,
+}
+
+/// Tags across a set of documents
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Taxonomy {
+    tags: BTreeMap<String, Vec<TagUse>>,
+}
+
+impl Taxonomy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the tags of `document`, read from `path`
+    pub fn add_document(&mut self, path: &Path, document: &Document) {
+        for tag in document_tags(document) {
+            self.insert(
+                tag,
+                TagUse {
+                    path: path.to_path_buf(),
+                    session: None,
+                    line: 0,
+                },
+            );
+        }
+        for session in document.root.iter_sessions_recursive() {
+            for tag in session_tags(session) {
+                self.insert(
+                    tag,
+                    TagUse {
+                        path: path.to_path_buf(),
+                        session: Some(session.title.as_string().trim().to_string()),
+                        line: session.location.start.line,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Taxonomy of every `.lex` file under `dir`; files that fail to parse are skipped
+    pub fn from_dir(dir: &Path) -> std::io::Result<Self> {
+        let mut taxonomy = Self::new();
+        let mut files = Vec::new();
+        lex_files(dir, &mut files)?;
+        files.sort();
+        for path in files {
+            let source = std::fs::read_to_string(&path)?;
+            if let Ok(document) = parse_document(&source) {
+                taxonomy.add_document(&path, &document);
+            }
+        }
+        Ok(taxonomy)
+    }
+
+    fn insert(&mut self, tag: String, tag_use: TagUse) {
+        let uses = self.tags.entry(tag).or_default();
+        if !uses.contains(&tag_use) {
+            uses.push(tag_use);
+        }
+    }
+
+    /// Tags with their number of uses, sorted by tag
+    pub fn tags(&self) -> Vec<(&str, usize)> {
+        self.tags
+            .iter()
+            .map(|(tag, uses)| (tag.as_str(), uses.len()))
+            .collect()
+    }
+
+    /// Places `tag` is used
+    pub fn uses(&self, tag: &str) -> &[TagUse] {
+        self.tags
+            .get(&normalize(tag))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// A Lex document listing each tag and the places using it
+    pub fn index_document(&self, title: &str) -> String {
+        let mut output = format!("{title}\n");
+        for (number, (tag, uses)) in self.tags.iter().enumerate() {
+            output.push_str(&format!("\n{}. {tag}\n\n", number + 1));
+            let mut entries: Vec<String> = uses
+                .iter()
+                .map(|tag_use| match &tag_use.session {
+                    Some(session) => {
+                        format!("    - {} ({session})\n", tag_use.path.display())
+                    }
+                    None => format!("    - {}\n", tag_use.path.display()),
+                })
+                .collect();
+            entries.sort();
+            output.push_str(&entries.concat());
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GUIDE: &str = ":: tags Rust, parsing ::\n\nGuide\n\nIntro.\n\n:: tags cache ::\n1. Caching\n\n    Cache text.\n\n2. Other\n\n    More.\n";
+
+    #[test]
+    fn test_document_and_session_tags() {
+        let doc = parse_document(GUIDE).unwrap();
+        assert_eq!(document_tags(&doc), vec!["parsing", "rust"]);
+        let tagged: Vec<_> = doc
+            .root
+            .iter_sessions_recursive()
+            .map(session_tags)
+            .collect();
+        assert_eq!(tagged, vec![vec!["cache".to_string()], vec![]]);
+    }
+
+    #[test]
+    fn test_taxonomy() {
+        let mut taxonomy = Taxonomy::new();
+        taxonomy.add_document(Path::new("guide.lex"), &parse_document(GUIDE).unwrap());
+        let notes = parse_document(":: tags rust ::\n\nNotes\n\nText.\n").unwrap();
+        taxonomy.add_document(Path::new("notes.lex"), &notes);
+
+        assert_eq!(
+            taxonomy.tags(),
+            vec![("cache", 1), ("parsing", 1), ("rust", 2)]
+        );
+        assert_eq!(
+            taxonomy.uses("Cache"),
+            &[TagUse {
+                path: PathBuf::from("guide.lex"),
+                session: Some("1. Caching".to_string()),
+                line: 7,
+            }]
+        );
+
+        let index = taxonomy.index_document("Tags");
+        assert!(index.starts_with("Tags\n\n1. cache\n\n    - guide.lex (1. Caching)\n"));
+        assert!(parse_document(&index).is_ok());
+    }
+}
@@ -0,0 +1,247 @@
+//! Indentation walls
+//!
+//!     Every container's content sits against a wall: the column its lines start at, one
+//!     level (four spaces or a tab) right of the container's own wall. Lines are read by the
+//!     wall they are against, so a line that misses it by a space belongs to another
+//!     container than the one it looks like it is in. That is the format's most confusing
+//!     failure, and [indentation_walls] makes it visible:
+//!
+//!         - [LineWall]: each line's indentation, its level and the wall it is read against.
+//!         - [WallRegion]: runs of lines against the same wall.
+//!         - [WallViolation]: lines missing a wall, by a partial level or by skipping levels.
+//!
+//!     Verbatim content may be indented freely past its block's wall, so it is exempt.
+//!     [render_walls] draws the walls as vertical guides next to the source, as the
+//!     [INDENTATION_WALLS](crate::lex::transforms::standard::INDENTATION_WALLS) inspect
+//!     transform does.
+
+use crate::lex::ast::{AstNode, ContentItem};
+use crate::lex::parsing::parse_with_recovery;
+use std::collections::HashMap;
+
+/// Columns in one indentation level
+pub const LEVEL_WIDTH: usize = 4;
+
+/// Indentation of a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineWall {
+    /// 0-based line
+    pub line: usize,
+    /// Leading whitespace in columns, a tab counting as a level
+    pub indent: usize,
+    /// Level of the wall the line is read against
+    pub level: usize,
+    /// Whether the line is blank, and so belongs to whatever surrounds it
+    pub blank: bool,
+    /// Whether the line is verbatim content, exempt from the wall
+    pub verbatim: bool,
+}
+
+impl LineWall {
+    /// Column of the wall
+    pub fn wall(&self) -> usize {
+        self.level * LEVEL_WIDTH
+    }
+}
+
+/// Consecutive lines against the same wall; blank lines belong to the region they are in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WallRegion {
+    pub start_line: usize,
+    /// Last line, inclusive
+    pub end_line: usize,
+    pub level: usize,
+}
+
+/// A line that misses its wall
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WallViolation {
+    pub line: usize,
+    pub indent: usize,
+    /// Column of the wall the line is read against
+    pub wall: usize,
+    pub message: String,
+}
+
+/// Walls of a source
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WallReport {
+    pub lines: Vec<LineWall>,
+    pub regions: Vec<WallRegion>,
+    pub violations: Vec<WallViolation>,
+}
+
+/// Indentation walls of `source`
+pub fn indentation_walls(source: &str) -> WallReport {
+    // Verbatim content lines, with the level of their block's wall
+    let document = parse_with_recovery(source).document;
+    let mut verbatim_levels = HashMap::new();
+    for item in document.root.iter_all_nodes() {
+        if let ContentItem::VerbatimBlock(verbatim) = item {
+            let range = verbatim.range();
+            let level = range.start.column / LEVEL_WIDTH + 1;
+            for line in range.start.line + 1..range.end.line {
+                verbatim_levels.insert(line, level);
+            }
+        }
+    }
+
+    let mut report = WallReport::default();
+    let mut previous_level = 0;
+    for (number, text) in source.lines().enumerate() {
+        let indent = indent_width(text);
+        let blank = text.trim().is_empty();
+        let verbatim = verbatim_levels.get(&number).copied();
+        let level = match verbatim {
+            Some(level) => level,
+            None if blank => previous_level,
+            None => indent / LEVEL_WIDTH,
+        };
+        if verbatim.is_none() && !blank {
+            let wall = level * LEVEL_WIDTH;
+            let message = if indent % LEVEL_WIDTH != 0 {
+                Some(format!(
+                    "Indented {indent} columns, {} past the wall at column {wall}",
+                    indent - wall
+                ))
+            } else if level > previous_level + 1 {
+                Some(format!(
+                    "Indented {} levels past the line above, where containers open one level at a time",
+                    level - previous_level
+                ))
+            } else {
+                None
+            };
+            if let Some(message) = message {
+                report.violations.push(WallViolation {
+                    line: number,
+                    indent,
+                    wall,
+                    message,
+                });
+            }
+            previous_level = level;
+        }
+
+        match report.regions.last_mut() {
+            Some(region) if region.level == level => region.end_line = number,
+            _ => report.regions.push(WallRegion {
+                start_line: number,
+                end_line: number,
+                level,
+            }),
+        }
+        report.lines.push(LineWall {
+            line: number,
+            indent,
+            level,
+            blank,
+            verbatim: verbatim.is_some(),
+        });
+    }
+    report
+}
+
+/// `source` with its walls drawn as `│` guides, line numbers in a gutter, and violations
+/// marked with `!` and listed after the lines
+pub fn render_walls(source: &str, report: &WallReport) -> String {
+    let lines: Vec<&str> = source.lines().collect();
+    let width = lines.len().to_string().len();
+    let mut out = String::new();
+    for wall in &report.lines {
+        let text = lines[wall.line].replace('\t', &" ".repeat(LEVEL_WIDTH));
+        let violated = report
+            .violations
+            .iter()
+            .any(|violation| violation.line == wall.line);
+        let mut guided: Vec<char> = text.chars().collect();
+        guided.resize(guided.len().max(wall.wall()), ' ');
+        for level in 1..=wall.level {
+            let column = level * LEVEL_WIDTH - 1;
+            if guided[column] == ' ' {
+                guided[column] = '│';
+            }
+        }
+        let guided: String = guided.into_iter().collect();
+        out.push_str(&format!(
+            "{:>width$} {} {}\n",
+            wall.line + 1,
+            if violated { '!' } else { ' ' },
+            guided.trim_end()
+        ));
+    }
+    for violation in &report.violations {
+        out.push_str(&format!(
+            "\nline {}: {}",
+            violation.line + 1,
+            violation.message
+        ));
+    }
+    if !report.violations.is_empty() {
+        out.push('\n');
+    }
+    out
+}
+
+fn indent_width(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { LEVEL_WIDTH } else { 1 })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "Guide\n\n1. Setup\n\n    Text.\n      Off by two.\n\n    Code:\n        x = 1\n            y = 2\n    :: python\n";
+
+    #[test]
+    fn test_walls() {
+        let report = indentation_walls(SOURCE);
+        assert_eq!(report.lines[4].level, 1);
+        assert_eq!(report.lines[5].indent, 6);
+        assert!(report.lines[9].verbatim);
+        assert_eq!(report.lines[9].level, 2);
+        assert_eq!(report.violations.len(), 1);
+        assert_eq!(report.violations[0].line, 5);
+        assert_eq!(report.violations[0].wall, 4);
+        assert_eq!(
+            report.regions[..3],
+            [
+                WallRegion {
+                    start_line: 0,
+                    end_line: 3,
+                    level: 0
+                },
+                WallRegion {
+                    start_line: 4,
+                    end_line: 7,
+                    level: 1
+                },
+                WallRegion {
+                    start_line: 8,
+                    end_line: 9,
+                    level: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_skipped_level() {
+        let report = indentation_walls("Title\n\n        Deep.\n");
+        assert_eq!(report.violations.len(), 1);
+        assert!(report.violations[0].message.contains("2 levels"));
+    }
+
+    #[test]
+    fn test_render() {
+        let rendered = render_walls(SOURCE, &indentation_walls(SOURCE));
+        let lines: Vec<&str> = rendered.lines().collect();
+        assert_eq!(lines[4], " 5      │Text.");
+        assert_eq!(lines[5], " 6 !    │  Off by two.");
+        assert_eq!(lines[9], "10      │   │    y = 2");
+        assert!(rendered.ends_with("line 6: Indented 6 columns, 2 past the wall at column 4\n"));
+    }
+}
@@ -0,0 +1,359 @@
+//! Workspace index
+//!
+//!     A [Workspace] holds the reference graphs of a set of documents, keyed by path, so
+//!     questions spanning documents can be answered without reparsing: chiefly backlinks,
+//!     the documents and sections referencing a given document through file references
+//!     (`[./notes.lex]`, `[../guide.lex#Setup]`).
+//!
+//!     File references are resolved relative to the directory of the document containing
+//!     them; a `#fragment` is ignored, and `.` and `..` components are resolved lexically, so
+//!     paths need not exist on disk.
+//!
+//!     Session ids (`:: id intro ::`) name sessions for `#fragment`s; [Workspace::duplicate_ids]
+//!     reports ids used more than once across the workspace.
+//!
+//!     With [Workspace::with_wiki_links], documents are indexed with wiki links enabled, and
+//!     `[[Target Note]]` links resolve ([Workspace::resolve_wiki_link]) to the document whose
+//!     file name (without extension) or title is the target, compared case-insensitively.
+
+use super::references::{NodeKind, ReferenceGraph, ReferenceKind};
+use super::search::lex_files;
+use crate::lex::ast::Document;
+use crate::lex::inlines::InlineParser;
+use crate::lex::parsing::parse_document;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+
+/// A document in the workspace
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceDocument {
+    pub title: String,
+    /// Explicit session ids (`:: id intro ::`), in document order
+    pub ids: Vec<String>,
+    pub graph: ReferenceGraph,
+}
+
+/// A reference to a document from elsewhere in the workspace
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Backlink {
+    /// Document containing the reference
+    pub source: PathBuf,
+    /// Title of the session containing the reference; `None` outside any session
+    pub section: Option<String>,
+}
+
+/// Documents of a workspace, keyed by path
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Workspace {
+    documents: BTreeMap<PathBuf, WorkspaceDocument>,
+    wiki_links: bool,
+}
+
+impl Workspace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index `[[Target]]` wiki links; applies to documents added afterwards
+    pub fn with_wiki_links(mut self) -> Self {
+        self.wiki_links = true;
+        self
+    }
+
+    /// Add `document`, read from `path`, replacing any document previously at that path
+    pub fn add_document(&mut self, path: &Path, document: &Document) {
+        self.documents.insert(
+            normalize(path),
+            WorkspaceDocument {
+                title: document.title().trim().to_string(),
+                ids: document
+                    .root
+                    .iter_sessions_recursive()
+                    .filter_map(|session| session.id())
+                    .map(str::to_string)
+                    .collect(),
+                graph: if self.wiki_links {
+                    ReferenceGraph::build_with_parser(
+                        document,
+                        &InlineParser::new().with_wiki_links(),
+                    )
+                } else {
+                    ReferenceGraph::build(document)
+                },
+            },
+        );
+    }
+
+    pub fn remove_document(&mut self, path: &Path) -> Option<WorkspaceDocument> {
+        self.documents.remove(&normalize(path))
+    }
+
+    /// Add every `.lex` file under `dir`; returns the files that failed to load
+    pub fn add_dir(&mut self, dir: &Path) -> std::io::Result<Vec<(PathBuf, String)>> {
+        let mut errors = Vec::new();
+        let mut files = Vec::new();
+        lex_files(dir, &mut files)?;
+        files.sort();
+        for path in files {
+            let document = std::fs::read_to_string(&path)
+                .map_err(|error| error.to_string())
+                .and_then(|source| parse_document(&source));
+            match document {
+                Ok(document) => self.add_document(&path, &document),
+                Err(error) => errors.push((path, error)),
+            }
+        }
+        Ok(errors)
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&WorkspaceDocument> {
+        self.documents.get(&normalize(path))
+    }
+
+    /// Documents in path order
+    pub fn documents(&self) -> impl Iterator<Item = (&Path, &WorkspaceDocument)> {
+        self.documents
+            .iter()
+            .map(|(path, document)| (path.as_path(), document))
+    }
+
+    pub fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.documents.is_empty()
+    }
+
+    /// Document a `[[target]]` wiki link points at, by file stem, then by title
+    pub fn resolve_wiki_link(&self, target: &str) -> Option<&Path> {
+        let target = target.trim().to_lowercase();
+        let stem = |path: &Path| {
+            path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .map(str::to_lowercase)
+        };
+        self.documents
+            .keys()
+            .find(|path| stem(path).as_deref() == Some(target.as_str()))
+            .or_else(|| {
+                self.documents
+                    .iter()
+                    .find(|(_, document)| document.title.to_lowercase() == target)
+                    .map(|(path, _)| path)
+            })
+            .map(PathBuf::as_path)
+    }
+
+    /// Session ids used more than once in the workspace, with the documents using them
+    pub fn duplicate_ids(&self) -> Vec<(String, Vec<PathBuf>)> {
+        let mut uses: BTreeMap<&str, Vec<PathBuf>> = BTreeMap::new();
+        for (path, document) in &self.documents {
+            for id in &document.ids {
+                uses.entry(id).or_default().push(path.clone());
+            }
+        }
+        uses.into_iter()
+            .filter(|(_, paths)| paths.len() > 1)
+            .map(|(id, mut paths)| {
+                paths.dedup();
+                (id.to_string(), paths)
+            })
+            .collect()
+    }
+
+    /// Places referencing the document at `target`, sorted by source and section
+    pub fn backlinks(&self, target: &Path) -> Vec<Backlink> {
+        let target = normalize(target);
+        let mut backlinks = Vec::new();
+        for (path, document) in &self.documents {
+            for edge in &document.graph.edges {
+                let reference = &document.graph.nodes[edge.target].label;
+                let resolved = match edge.kind {
+                    ReferenceKind::File => Some(resolve_file_reference(path, reference)),
+                    ReferenceKind::WikiLink => {
+                        self.resolve_wiki_link(reference).map(Path::to_path_buf)
+                    }
+                    _ => None,
+                };
+                if resolved.as_ref() != Some(&target) {
+                    continue;
+                }
+                let source = &document.graph.nodes[edge.source];
+                let backlink = Backlink {
+                    source: path.clone(),
+                    section: (source.kind == NodeKind::Session).then(|| source.label.clone()),
+                };
+                if !backlinks.contains(&backlink) {
+                    backlinks.push(backlink);
+                }
+            }
+        }
+        backlinks.sort();
+        backlinks
+    }
+}
+
+/// Path the file reference `target`, in the document at `from`, points at; a `#fragment` is
+/// ignored
+pub fn resolve_file_reference(from: &Path, target: &str) -> PathBuf {
+    let file = target.split('#').next().unwrap_or_default();
+    let dir = from.parent().unwrap_or(Path::new(""));
+    normalize(&dir.join(file))
+}
+
+/// `path` with `.` and `..` components resolved lexically
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) {
+                    normalized.pop();
+                } else {
+                    normalized.push("..");
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(files: &[(&str, &str)]) -> Workspace {
+        let mut workspace = Workspace::new();
+        for (path, source) in files {
+            workspace.add_document(Path::new(path), &parse_document(source).unwrap());
+        }
+        workspace
+    }
+
+    #[test]
+    fn test_backlinks() {
+        let workspace = workspace(&[
+            ("notes/cache.lex", "Cache\n\nSee [./eviction.lex].\n"),
+            (
+                "notes/eviction.lex",
+                "Eviction\n\n1. Policy\n\n    Back to [./cache.lex#Intro].\n",
+            ),
+            (
+                "guide.lex",
+                "Guide\n\nStart at [./notes/cache.lex].\n\n1. Details\n\n    See [./notes/../notes/cache.lex] and [./notes/cache.lex].\n",
+            ),
+        ]);
+
+        assert_eq!(
+            workspace.backlinks(Path::new("./notes/cache.lex")),
+            vec![
+                Backlink {
+                    source: PathBuf::from("guide.lex"),
+                    section: None,
+                },
+                Backlink {
+                    source: PathBuf::from("guide.lex"),
+                    section: Some("1. Details".to_string()),
+                },
+                Backlink {
+                    source: PathBuf::from("notes/eviction.lex"),
+                    section: Some("1. Policy".to_string()),
+                },
+            ]
+        );
+        assert_eq!(
+            workspace.backlinks(Path::new("notes/eviction.lex")),
+            vec![Backlink {
+                source: PathBuf::from("notes/cache.lex"),
+                section: None,
+            }]
+        );
+        assert!(workspace.backlinks(Path::new("guide.lex")).is_empty());
+        assert_eq!(
+            workspace.get(Path::new("guide.lex")).unwrap().title,
+            "Guide"
+        );
+    }
+
+    #[test]
+    fn test_wiki_links() {
+        let mut workspace = Workspace::new().with_wiki_links();
+        for (path, source) in [
+            (
+                "notes/cache-notes.lex",
+                "Caching Notes\n\nSee [[Eviction Policy]].\n",
+            ),
+            (
+                "eviction.lex",
+                "Eviction Policy\n\n1. Details\n\n    Back to [[cache-notes#Intro|the notes]].\n",
+            ),
+        ] {
+            workspace.add_document(Path::new(path), &parse_document(source).unwrap());
+        }
+
+        assert_eq!(
+            workspace.resolve_wiki_link("Caching notes"),
+            Some(Path::new("notes/cache-notes.lex"))
+        );
+        assert_eq!(workspace.resolve_wiki_link("missing"), None);
+        assert_eq!(
+            workspace.backlinks(Path::new("notes/cache-notes.lex")),
+            vec![Backlink {
+                source: PathBuf::from("eviction.lex"),
+                section: Some("1. Details".to_string()),
+            }]
+        );
+        assert_eq!(
+            workspace.backlinks(Path::new("eviction.lex"))[0].source,
+            PathBuf::from("notes/cache-notes.lex")
+        );
+    }
+
+    #[test]
+    fn test_resolve_file_reference() {
+        assert_eq!(
+            resolve_file_reference(Path::new("notes/cache.lex"), "../guide.lex#Setup"),
+            PathBuf::from("guide.lex")
+        );
+    }
+
+    #[test]
+    fn test_duplicate_ids() {
+        let workspace = workspace(&[
+            (
+                "a.lex",
+                "A\n\nText.\n\n:: id setup ::\n1. Setup\n\n    Text.\n\n:: id usage ::\n2. Usage\n\n    Text.\n",
+            ),
+            ("b.lex", "B\n\nText.\n\n:: id setup ::\n1. Setup\n\n    Text.\n"),
+        ]);
+        assert_eq!(
+            workspace.get(Path::new("a.lex")).unwrap().ids,
+            vec!["setup", "usage"]
+        );
+        assert_eq!(
+            workspace.duplicate_ids(),
+            vec![(
+                "setup".to_string(),
+                vec![PathBuf::from("a.lex"), PathBuf::from("b.lex")]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize(Path::new("a/./b/../c.lex")),
+            PathBuf::from("a/c.lex")
+        );
+        assert_eq!(
+            normalize(Path::new("../a/../../b")),
+            PathBuf::from("../../b")
+        );
+    }
+}
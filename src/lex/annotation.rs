@@ -3,6 +3,15 @@
 //! Currently this module focuses on analyzing annotation headers (the token
 //! sequence between `::` markers). The helpers keep the "label vs parameters"
 //! rules in one place so every stage enforces the same constraints.
+//!
+//! Annotations read by tooling rather than by the parser, such as `lex-ignore`
+//! directives, callout labels and import provenance, live in submodules.
+
+pub mod callout;
+pub mod ignore;
+pub mod profile;
+pub mod provenance;
+pub mod toc;
 
 use crate::lex::token::Token;
 use std::ops::Range;
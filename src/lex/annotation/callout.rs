@@ -0,0 +1,186 @@
+//! Callouts
+//!
+//! A paragraph or session annotated with a callout label is set apart from the text around
+//! it, the way admonitions are in other formats:
+//!
+//! ```text
+//! :: warning title="Data loss" ::
+//! Running this command deletes the cache.
+//! ```
+//!
+//! The labels are the five GitHub alert types (`note`, `tip`, `important`, `warning`,
+//! `caution`), plus a few common aliases. Output formats map them alike: an
+//! `<aside class="note">` in HTML, a `> [!NOTE]` alert in Markdown, a titled box in LaTeX.
+
+use crate::lex::ast::{Annotation, ContentItem, Document};
+use crate::lex::locale::{Locale, Term};
+
+/// Kind of callout
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CalloutKind {
+    Note,
+    Tip,
+    Important,
+    Warning,
+    Caution,
+}
+
+impl CalloutKind {
+    pub const ALL: [CalloutKind; 5] = [
+        CalloutKind::Note,
+        CalloutKind::Tip,
+        CalloutKind::Important,
+        CalloutKind::Warning,
+        CalloutKind::Caution,
+    ];
+
+    /// Canonical annotation label
+    pub fn label(&self) -> &'static str {
+        match self {
+            CalloutKind::Note => "note",
+            CalloutKind::Tip => "tip",
+            CalloutKind::Important => "important",
+            CalloutKind::Warning => "warning",
+            CalloutKind::Caution => "caution",
+        }
+    }
+
+    /// Kind named by an annotation label, case-insensitively; `info`, `hint` and `danger`
+    /// are accepted as aliases
+    pub fn from_label(label: &str) -> Option<Self> {
+        let label = label.trim().to_lowercase();
+        match label.as_str() {
+            "info" => Some(CalloutKind::Note),
+            "hint" => Some(CalloutKind::Tip),
+            "danger" => Some(CalloutKind::Caution),
+            _ => Self::ALL.into_iter().find(|kind| kind.label() == label),
+        }
+    }
+
+    /// Title shown when the callout has none of its own
+    pub fn default_title(&self) -> &'static str {
+        match self {
+            CalloutKind::Note => "Note",
+            CalloutKind::Tip => "Tip",
+            CalloutKind::Important => "Important",
+            CalloutKind::Warning => "Warning",
+            CalloutKind::Caution => "Caution",
+        }
+    }
+
+    /// Term of the kind's title, for localized output
+    pub fn term(&self) -> Term {
+        match self {
+            CalloutKind::Note => Term::Note,
+            CalloutKind::Tip => Term::Tip,
+            CalloutKind::Important => Term::Important,
+            CalloutKind::Warning => Term::Warning,
+            CalloutKind::Caution => Term::Caution,
+        }
+    }
+
+    /// Marker opening a GitHub Markdown alert, such as `[!NOTE]`
+    pub fn gfm_alert(&self) -> String {
+        format!("[!{}]", self.label().to_uppercase())
+    }
+}
+
+/// A paragraph or session marked as a callout
+#[derive(Debug, Clone, Copy)]
+pub struct Callout<'a> {
+    pub kind: CalloutKind,
+    /// The annotated element
+    pub item: &'a ContentItem,
+    /// The annotation carrying the callout label
+    pub annotation: &'a Annotation,
+}
+
+impl<'a> Callout<'a> {
+    /// The callout `item` is marked as, if any
+    pub fn from_item(item: &'a ContentItem) -> Option<Self> {
+        if !matches!(item, ContentItem::Paragraph(_) | ContentItem::Session(_)) {
+            return None;
+        }
+        item.annotations().iter().find_map(|annotation| {
+            CalloutKind::from_label(&annotation.data.label.value).map(|kind| Callout {
+                kind,
+                item,
+                annotation,
+            })
+        })
+    }
+
+    /// The `title` parameter, without quotes
+    pub fn title(&self) -> Option<&'a str> {
+        self.annotation
+            .data
+            .parameters
+            .iter()
+            .find(|param| param.key == "title")
+            .map(|param| param.value.trim_matches('"'))
+            .filter(|title| !title.is_empty())
+    }
+
+    /// The title to display: the `title` parameter, or the kind's default
+    pub fn display_title(&self) -> &'a str {
+        self.title().unwrap_or(self.kind.default_title())
+    }
+
+    /// Like [display_title](Self::display_title), with the default title from `locale`
+    pub fn localized_title<'l>(&self, locale: &'l Locale) -> &'l str
+    where
+        'a: 'l,
+    {
+        self.title().unwrap_or_else(|| locale.get(self.kind.term()))
+    }
+}
+
+/// Callouts of `document`, in document order
+pub fn callouts(document: &Document) -> Vec<Callout<'_>> {
+    document
+        .root
+        .iter_all_nodes()
+        .filter_map(Callout::from_item)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    #[test]
+    fn test_kinds() {
+        assert_eq!(
+            CalloutKind::from_label("Warning"),
+            Some(CalloutKind::Warning)
+        );
+        assert_eq!(CalloutKind::from_label("hint"), Some(CalloutKind::Tip));
+        assert_eq!(CalloutKind::from_label("lex-ignore"), None);
+        assert_eq!(CalloutKind::Important.gfm_alert(), "[!IMPORTANT]");
+    }
+
+    #[test]
+    fn test_callouts() {
+        let source = "Title\n\nIntro.\n\n:: note ::\nA plain note.\n\n:: warning title=\"Data loss\" ::\nRunning this deletes the cache.\n\n:: author ::\nNot a callout.\n";
+        let doc = parse_document(source).unwrap();
+        let found: Vec<_> = callouts(&doc)
+            .iter()
+            .map(|callout| (callout.kind, callout.display_title()))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (CalloutKind::Note, "Note"),
+                (CalloutKind::Warning, "Data loss")
+            ]
+        );
+
+        let german = Locale::builtin("de").unwrap();
+        let titles: Vec<_> = callouts(&doc)
+            .iter()
+            .map(|callout| callout.localized_title(&german))
+            .collect();
+        assert_eq!(titles, vec!["Hinweis", "Data loss"]);
+    }
+}
@@ -0,0 +1,229 @@
+//! Ignore directives
+//!
+//! A `:: lex-ignore ::` annotation suppresses tooling for the element it is attached to.
+//! Names following the label restrict it to specific rules:
+//!
+//! ```text
+//! :: lex-ignore list-markers, blank-lines ::
+//! ```
+//!
+//! Lint rule names and formatting rule names can be mixed. Two group names cover a whole
+//! tool: `lint` (every lint rule) and `fmt` (formatting altogether). Without names, the
+//! directive suppresses everything.
+//!
+//! Annotations attached to the document (at its start or end) apply to the whole file;
+//! annotations attached to an element apply to the element's lines, including its children.
+
+use crate::lex::ast::traits::AstNode;
+use crate::lex::ast::{Annotation, ContentItem, Diagnostic, Document, Range};
+
+/// Annotation label introducing an ignore directive
+pub const IGNORE_LABEL: &str = "lex-ignore";
+
+/// Rule group name covering every lint rule
+pub const LINT_GROUP: &str = "lint";
+
+/// Rule group name covering formatting
+pub const FORMAT_GROUP: &str = "fmt";
+
+/// Which tool a rule name belongs to, for group matching
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleKind {
+    Lint,
+    Format,
+}
+
+/// The rules named by a single directive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoreRules {
+    /// Named rules; empty means every rule
+    pub names: Vec<String>,
+}
+
+impl IgnoreRules {
+    /// Parse the directive carried by `annotation`, if it is one
+    ///
+    /// Rule names are label words after `lex-ignore`, or bare parameters (the header
+    /// parser reads `a, b` past the first name as value-less parameters).
+    pub fn from_annotation(annotation: &Annotation) -> Option<Self> {
+        let label = annotation.data.label.value.trim();
+        let rest = label.strip_prefix(IGNORE_LABEL)?;
+        if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+        let names = rest
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .chain(
+                annotation
+                    .data
+                    .parameters
+                    .iter()
+                    .filter(|param| param.value.is_empty())
+                    .map(|param| param.key.as_str()),
+            )
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect();
+        Some(Self { names })
+    }
+
+    /// Whether `rule` (of the given kind) is suppressed
+    pub fn covers(&self, rule: &str, kind: RuleKind) -> bool {
+        let group = match kind {
+            RuleKind::Lint => LINT_GROUP,
+            RuleKind::Format => FORMAT_GROUP,
+        };
+        self.names.is_empty() || self.names.iter().any(|name| name == rule || name == group)
+    }
+
+    /// Whether formatting is suppressed altogether
+    pub fn covers_all_formatting(&self) -> bool {
+        self.names.is_empty() || self.names.iter().any(|name| name == FORMAT_GROUP)
+    }
+}
+
+/// A directive and the source lines it applies to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IgnoreScope {
+    pub rules: IgnoreRules,
+    /// First and last line (inclusive) covered, or `None` for the whole file
+    pub lines: Option<(usize, usize)>,
+}
+
+/// All ignore directives of a document
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IgnoreDirectives {
+    scopes: Vec<IgnoreScope>,
+}
+
+impl IgnoreDirectives {
+    /// Collect the directives of `document`
+    pub fn collect(document: &Document) -> Self {
+        let mut scopes: Vec<IgnoreScope> = document
+            .annotations
+            .iter()
+            .chain(document.root.annotations.iter())
+            .filter_map(IgnoreRules::from_annotation)
+            .map(|rules| IgnoreScope { rules, lines: None })
+            .collect();
+
+        for item in document.root.iter_all_nodes() {
+            for annotation in item.annotations() {
+                if let Some(rules) = IgnoreRules::from_annotation(annotation) {
+                    scopes.push(IgnoreScope {
+                        rules,
+                        lines: Some(item_lines(item)),
+                    });
+                }
+            }
+        }
+        Self { scopes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.scopes.is_empty()
+    }
+
+    pub fn scopes(&self) -> &[IgnoreScope] {
+        &self.scopes
+    }
+
+    /// Whether `rule` is suppressed for the whole file
+    pub fn ignores_file(&self, rule: &str, kind: RuleKind) -> bool {
+        self.scopes
+            .iter()
+            .any(|scope| scope.lines.is_none() && scope.rules.covers(rule, kind))
+    }
+
+    /// Whether `rule` is suppressed at source line `line`
+    pub fn ignores_line(&self, rule: &str, kind: RuleKind, line: usize) -> bool {
+        self.scopes.iter().any(|scope| {
+            scope.rules.covers(rule, kind)
+                && scope
+                    .lines
+                    .is_none_or(|(first, last)| first <= line && line <= last)
+        })
+    }
+
+    /// Whether formatting is suppressed for the whole file
+    pub fn ignores_all_formatting(&self) -> bool {
+        self.scopes
+            .iter()
+            .any(|scope| scope.lines.is_none() && scope.rules.covers_all_formatting())
+    }
+
+    /// Whether formatting is suppressed altogether for an element starting at `line`
+    pub fn ignores_formatting_at(&self, line: usize) -> bool {
+        self.scopes.iter().any(|scope| {
+            scope.rules.covers_all_formatting()
+                && scope
+                    .lines
+                    .is_none_or(|(first, last)| first <= line && line <= last)
+        })
+    }
+
+    /// Drop diagnostics whose code is suppressed where they start
+    pub fn filter_diagnostics(&self, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        if self.is_empty() {
+            return diagnostics;
+        }
+        diagnostics
+            .into_iter()
+            .filter(|diagnostic| {
+                let code = diagnostic.code.as_deref().unwrap_or_default();
+                !self.ignores_line(code, RuleKind::Lint, diagnostic.range.start.line)
+            })
+            .collect()
+    }
+}
+
+/// Inclusive line span of an element
+fn item_lines(item: &ContentItem) -> (usize, usize) {
+    let range = item.range();
+    if *range == Range::default() {
+        // Unpositioned elements can't be matched against lines: scope nothing
+        return (usize::MAX, usize::MAX);
+    }
+    let end = if range.end.column == 0 && range.end.line > range.start.line {
+        range.end.line - 1
+    } else {
+        range.end.line
+    };
+    (range.start.line, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    #[test]
+    fn test_parses_rule_names() {
+        let source =
+            "Title\n\nIntro.\n\n:: lex-ignore list-markers, blank-lines ::\n- a\n- b\n\nAfter.\n";
+        let doc = parse_document(source).unwrap();
+        let directives = IgnoreDirectives::collect(&doc);
+        assert_eq!(directives.scopes().len(), 1);
+        let scope = &directives.scopes()[0];
+        assert_eq!(scope.rules.names, vec!["list-markers", "blank-lines"]);
+        assert_eq!(scope.lines, Some((5, 6)));
+        assert!(directives.ignores_line("list-markers", RuleKind::Lint, 6));
+        assert!(!directives.ignores_line("list-markers", RuleKind::Lint, 8));
+        assert!(!directives.ignores_line("session-numbering", RuleKind::Lint, 6));
+    }
+
+    #[test]
+    fn test_file_level_directive_without_names() {
+        let doc = parse_document(":: lex-ignore ::\n\nTitle\n\nText.\n").unwrap();
+        let directives = IgnoreDirectives::collect(&doc);
+        assert!(directives.ignores_file("blank-lines", RuleKind::Lint));
+        assert!(directives.ignores_all_formatting());
+    }
+
+    #[test]
+    fn test_other_labels_are_not_directives() {
+        let doc = parse_document(":: lex-ignored ::\n\nTitle\n\nText.\n").unwrap();
+        assert!(IgnoreDirectives::collect(&doc).is_empty());
+    }
+}
@@ -0,0 +1,136 @@
+//! Conditional content (profiles)
+//!
+//! One source can target several outputs or audiences. An element annotated with `only`
+//! is kept for the listed profiles alone, and one annotated with `exclude` is dropped for
+//! them:
+//!
+//! ```text
+//! :: only pdf print ::
+//! Printed copies are numbered.
+//!
+//! :: exclude web ::
+//! 1. Appendix
+//! ```
+//!
+//! Profiles are plain names: usually the output format, plus any audience the converter
+//! is asked for. [apply_profiles] filters the AST before serialization, removing dropped
+//! elements with their subtrees, and the profile annotations of kept ones, so output
+//! formats never see them. A standalone profile annotation (one attached to nothing)
+//! keeps or drops its own body the same way.
+
+use crate::lex::ast::{Annotation, ContentItem, Document};
+
+/// Annotation label keeping an element for the listed profiles only
+pub const ONLY_LABEL: &str = "only";
+
+/// Annotation label dropping an element for the listed profiles
+pub const EXCLUDE_LABEL: &str = "exclude";
+
+/// Profiles listed by `annotation` under `label`, or `None` when it has another label
+fn listed<'a>(annotation: &'a Annotation, label: &str) -> Option<Vec<&'a str>> {
+    let rest = annotation.data.label.value.trim().strip_prefix(label)?;
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(rest.split_whitespace().collect())
+}
+
+/// Whether `annotation` is an `only` or `exclude` annotation
+pub fn is_profile_annotation(annotation: &Annotation) -> bool {
+    listed(annotation, ONLY_LABEL).is_some() || listed(annotation, EXCLUDE_LABEL).is_some()
+}
+
+/// Whether content with `annotations` is kept when converting for `profiles`
+pub fn included<'a>(
+    annotations: impl IntoIterator<Item = &'a Annotation>,
+    profiles: &[&str],
+) -> bool {
+    let active = |names: &[&str]| names.iter().any(|name| profiles.contains(name));
+    annotations.into_iter().all(|annotation| {
+        listed(annotation, ONLY_LABEL).is_none_or(|names| active(&names))
+            && listed(annotation, EXCLUDE_LABEL).is_none_or(|names| !active(&names))
+    })
+}
+
+/// Remove the content of `document` not meant for `profiles`
+///
+/// Profile annotations at document level condition nothing and are only removed.
+pub fn apply_profiles(document: &mut Document, profiles: &[&str]) {
+    let keep = |annotation: &Annotation| !is_profile_annotation(annotation);
+    document.annotations.retain(keep);
+    document.root.annotations.retain(keep);
+    filter(document.root.children.as_mut_vec(), profiles);
+}
+
+fn filter(items: &mut Vec<ContentItem>, profiles: &[&str]) {
+    let mut kept = Vec::with_capacity(items.len());
+    for mut item in items.drain(..) {
+        if let ContentItem::Annotation(annotation) = &mut item {
+            if is_profile_annotation(annotation) {
+                if included([&*annotation], profiles) {
+                    let mut body = std::mem::take(annotation.children.as_mut_vec());
+                    filter(&mut body, profiles);
+                    kept.extend(body);
+                }
+                continue;
+            }
+        }
+        if !included(item.annotations(), profiles) {
+            continue;
+        }
+        if let Some(annotations) = annotations_mut(&mut item) {
+            annotations.retain(|annotation| !is_profile_annotation(annotation));
+        }
+        if let Some(children) = item.children_mut() {
+            filter(children, profiles);
+        }
+        kept.push(item);
+    }
+    *items = kept;
+}
+
+fn annotations_mut(item: &mut ContentItem) -> Option<&mut Vec<Annotation>> {
+    match item {
+        ContentItem::Session(session) => Some(&mut session.annotations),
+        ContentItem::Paragraph(paragraph) => Some(&mut paragraph.annotations),
+        ContentItem::ListItem(list_item) => Some(&mut list_item.annotations),
+        ContentItem::Definition(definition) => Some(&mut definition.annotations),
+        ContentItem::VerbatimBlock(verbatim) => Some(&mut verbatim.annotations),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    const SOURCE: &str = "Guide\n\nIntro.\n\n:: only pdf print ::\nPrinted copies are numbered.\n\nShared.\n\n1. Usage\n\n    - Online\n    - Offline\n\n:: exclude web ::\n2. Appendix\n\n    Text.\n";
+
+    fn texts(document: &Document) -> Vec<String> {
+        document
+            .root
+            .iter_paragraphs_recursive()
+            .map(|paragraph| paragraph.text())
+            .collect()
+    }
+
+    #[test]
+    fn test_apply_profiles() {
+        let mut pdf = parse_document(SOURCE).unwrap();
+        apply_profiles(&mut pdf, &["pdf"]);
+        assert_eq!(
+            texts(&pdf),
+            vec!["Intro.", "Printed copies are numbered.", "Shared.", "Text."]
+        );
+        assert!(pdf
+            .root
+            .iter_all_nodes()
+            .all(|item| item.annotations().is_empty()));
+
+        let mut web = parse_document(SOURCE).unwrap();
+        apply_profiles(&mut web, &["web"]);
+        assert_eq!(texts(&web), vec!["Intro.", "Shared."]);
+        assert_eq!(web.root.iter_sessions_recursive().count(), 1);
+    }
+}
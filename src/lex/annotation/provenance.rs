@@ -0,0 +1,164 @@
+//! Import provenance
+//!
+//! Documents imported from another format record where they came from in a
+//! `:: provenance ::` annotation following their title:
+//!
+//! ```text
+//! Lecture 3
+//!
+//! :: provenance source=srt, title="Lecture 3" ::
+//!     Cue numbers were dropped; cues are renumbered on export.
+//!     Lines of multi-line cues were joined.
+//! ```
+//!
+//! The `source` parameter names the format imported from and the other parameters are the
+//! import options. Each content line is a lossy conversion the importer performed, so users
+//! can audit what did not make it, and exporters can read [Provenance::of] a document to
+//! make better choices, such as writing back to the original format.
+
+use crate::lex::ast::{Annotation, ContentItem, Document};
+
+/// Annotation label of provenance records
+pub const PROVENANCE_LABEL: &str = "provenance";
+
+/// Where an imported document came from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Provenance {
+    /// Format imported from, as named in the format registry
+    pub source: String,
+    /// Import options, in the order given
+    pub options: Vec<(String, String)>,
+    /// Lossy conversions performed, each a sentence
+    pub losses: Vec<String>,
+}
+
+impl Provenance {
+    pub fn new(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// Builder-style import option
+    pub fn with_option(mut self, key: &str, value: &str) -> Self {
+        self.options.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Record a lossy conversion; recording the same one again is a no-op
+    pub fn lost(&mut self, loss: impl Into<String>) {
+        let loss = loss.into();
+        if !self.losses.contains(&loss) {
+            self.losses.push(loss);
+        }
+    }
+
+    /// Lex source of the provenance annotation
+    pub fn to_annotation(&self) -> String {
+        let mut out = format!(":: {PROVENANCE_LABEL} source={}", self.source);
+        for (key, value) in &self.options {
+            out.push_str(&format!(", {key}=\"{}\"", value.replace('"', "'")));
+        }
+        out.push_str(" ::\n");
+        for loss in &self.losses {
+            out.push_str(&format!("    {loss}\n"));
+        }
+        out
+    }
+
+    /// Lex source of a document titled `title`, with the provenance annotation between the
+    /// title and `body`
+    pub fn document(&self, title: &str, body: &str) -> String {
+        let mut out = String::new();
+        if !title.is_empty() {
+            out.push_str(&format!("{title}\n\n"));
+        }
+        out.push_str(&self.to_annotation());
+        if !body.is_empty() {
+            out.push_str(&format!("\n{body}"));
+        }
+        out
+    }
+
+    /// Provenance recorded by `annotation`, if it is a provenance annotation
+    pub fn from_annotation(annotation: &Annotation) -> Option<Self> {
+        if annotation.data.label.value.trim() != PROVENANCE_LABEL {
+            return None;
+        }
+        let mut provenance = Self::default();
+        for parameter in &annotation.data.parameters {
+            let value = parameter.value.trim_matches('"').to_string();
+            if parameter.key == "source" {
+                provenance.source = value;
+            } else {
+                provenance.options.push((parameter.key.clone(), value));
+            }
+        }
+        for item in annotation.children.iter() {
+            if let ContentItem::Paragraph(paragraph) = item {
+                provenance.losses.extend(
+                    paragraph
+                        .text()
+                        .lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty())
+                        .map(str::to_string),
+                );
+            }
+        }
+        Some(provenance)
+    }
+
+    /// Provenance of `document`, from its document-level annotations
+    ///
+    /// An annotation followed by a paragraph attaches to the paragraph, so the first
+    /// element's annotations are looked at too.
+    pub fn of(document: &Document) -> Option<Self> {
+        let first = document
+            .root
+            .children
+            .iter()
+            .find(|item| !item.is_blank_line_group());
+        document
+            .annotations
+            .iter()
+            .chain(&document.root.annotations)
+            .chain(first.map(|item| item.annotations()).unwrap_or_default())
+            .find_map(Self::from_annotation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    #[test]
+    fn test_round_trip() {
+        let mut provenance = Provenance::new("srt").with_option("title", "Lecture \"3\"");
+        provenance.lost("Cue numbers were dropped.");
+        provenance.lost("Cue numbers were dropped.");
+        provenance.lost("Lines of multi-line cues were joined.");
+        let source = provenance.document("Lecture 3", "Text.\n");
+        assert_eq!(
+            source,
+            "Lecture 3\n\n:: provenance source=srt, title=\"Lecture '3'\" ::\n    Cue numbers were dropped.\n    Lines of multi-line cues were joined.\n\nText.\n"
+        );
+
+        let doc = parse_document(&source).unwrap();
+        assert_eq!(doc.title(), "Lecture 3");
+        let read = Provenance::of(&doc).unwrap();
+        assert_eq!(read.source, "srt");
+        assert_eq!(
+            read.options,
+            [("title".to_string(), "Lecture '3'".to_string())]
+        );
+        assert_eq!(read.losses.len(), 2);
+
+        assert_eq!(
+            Provenance::of(&parse_document("Notes\n\nText.\n").unwrap()),
+            None
+        );
+    }
+}
@@ -0,0 +1,222 @@
+//! Table of contents
+//!
+//! A `:: toc ::` annotation stands for a table of contents of the scope it sits in: the
+//! sessions of the whole document, or of the session containing it. A `depth` parameter
+//! limits how many levels of sessions are listed:
+//!
+//! ```text
+//! :: toc depth=2 ::
+//! ```
+//!
+//! [expand_toc] replaces each such annotation in the source with its table, a nested list
+//! of session titles, before conversion, the way [preprocess](crate::lex::exec::preprocess)
+//! inserts the output of exec blocks. Every output format then gets the same table without
+//! knowing about the annotation.
+
+use crate::lex::ast::traits::AstNode;
+use crate::lex::ast::{Annotation, ContentItem, Document};
+use std::fmt;
+
+/// Annotation label asking for a table of contents
+pub const TOC_LABEL: &str = "toc";
+
+/// A table of contents to insert
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TocDirective {
+    /// Levels of sessions listed; `None` lists them all
+    pub depth: Option<usize>,
+    /// Line (0-based) of the `toc` annotation
+    pub line: usize,
+    /// Lines of the table, indented relative to the annotation
+    pub lines: Vec<String>,
+}
+
+/// Error that can occur while expanding tables of contents
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TocError {
+    /// `depth` is not a positive number
+    InvalidDepth { line: usize, value: String },
+}
+
+impl fmt::Display for TocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TocError::InvalidDepth { line, value } => write!(
+                f,
+                "Invalid toc depth '{value}' at line {}: expected a positive number",
+                line + 1
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TocError {}
+
+/// Tables of contents requested by `document`, in source order
+pub fn toc_directives(document: &Document) -> Result<Vec<TocDirective>, TocError> {
+    let mut annotations: Vec<&Annotation> = document
+        .annotations
+        .iter()
+        .chain(&document.root.annotations)
+        .collect();
+    for item in document.root.iter_all_nodes() {
+        annotations.extend(item.annotations());
+        if let ContentItem::Annotation(annotation) = item {
+            annotations.push(annotation);
+        }
+    }
+
+    let mut directives = Vec::new();
+    for annotation in annotations {
+        if annotation.data.label.value.trim() != TOC_LABEL {
+            continue;
+        }
+        let line = annotation.header_location().start.line;
+        let depth = match annotation
+            .data
+            .parameters
+            .iter()
+            .find(|param| param.key == "depth")
+        {
+            Some(param) => {
+                let value = param.value.trim_matches('"');
+                match value.parse::<usize>() {
+                    Ok(depth) if depth > 0 => Some(depth),
+                    _ => {
+                        return Err(TocError::InvalidDepth {
+                            line,
+                            value: value.to_string(),
+                        })
+                    }
+                }
+            }
+            None => None,
+        };
+
+        let mut lines = Vec::new();
+        table(scope(&document.root.children, line), depth, 0, &mut lines);
+        directives.push(TocDirective { depth, line, lines });
+    }
+    directives.sort_by_key(|directive| directive.line);
+    directives.dedup_by_key(|directive| directive.line);
+    Ok(directives)
+}
+
+/// Children of the innermost session whose body contains `line`, or `items` at top level
+fn scope(items: &[ContentItem], line: usize) -> &[ContentItem] {
+    for item in items {
+        let ContentItem::Session(session) = item else {
+            continue;
+        };
+        let title_line = session
+            .header_location()
+            .map_or(item.range().start.line, |header| header.start.line);
+        if title_line < line && line <= item.range().end.line {
+            return scope(&session.children, line);
+        }
+    }
+    items
+}
+
+/// List lines of the sessions in `items`, nested up to `depth` levels
+fn table(items: &[ContentItem], depth: Option<usize>, level: usize, lines: &mut Vec<String>) {
+    if depth.is_some_and(|depth| level >= depth) {
+        return;
+    }
+    for item in items {
+        if let ContentItem::Session(session) = item {
+            let indent = "    ".repeat(level);
+            lines.push(format!("{indent}- {}", session.title.as_string().trim()));
+            table(&session.children, depth, level + 1, lines);
+        }
+    }
+}
+
+/// `source` (parsed as `document`) with each `toc` annotation replaced by its table
+pub fn expand_toc(source: &str, document: &Document) -> Result<String, TocError> {
+    let directives = toc_directives(document)?;
+    if directives.is_empty() {
+        return Ok(source.to_string());
+    }
+
+    let mut lines: Vec<String> = source.lines().map(str::to_string).collect();
+    // Splice from the end so earlier line numbers stay valid
+    for directive in directives.iter().rev() {
+        let Some(annotation) = lines.get(directive.line) else {
+            continue;
+        };
+        let indent = &annotation[..annotation.len() - annotation.trim_start().len()];
+        let mut inserted: Vec<String> = directive
+            .lines
+            .iter()
+            .map(|line| format!("{indent}{line}"))
+            .collect();
+        let end = directive.line + 1;
+        let blank = |line: Option<&String>| line.is_none_or(|line| line.trim().is_empty());
+        if !blank(lines.get(end)) {
+            inserted.push(String::new());
+        }
+        if directive.line > 0 && !blank(lines.get(directive.line - 1)) {
+            inserted.insert(0, String::new());
+        }
+        lines.splice(directive.line..end, inserted);
+    }
+
+    let mut result = lines.join("\n");
+    if source.ends_with('\n') {
+        result.push('\n');
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    const SOURCE: &str = "Guide\n\nIntro.\n\n:: toc ::\n\n1. Setup\n\n    :: toc depth=1 ::\n\n    1.1. Install\n\n        Text.\n\n        1.1.1. Linux\n\n            Text.\n\n    1.2. Upgrade\n\n        Text.\n\n2. Usage\n\n    Text.\n";
+
+    #[test]
+    fn test_toc_directives() {
+        let doc = parse_document(SOURCE).unwrap();
+        let directives = toc_directives(&doc).unwrap();
+        assert_eq!(directives.len(), 2);
+        assert_eq!(
+            directives[0].lines,
+            vec![
+                "- 1. Setup",
+                "    - 1.1. Install",
+                "        - 1.1.1. Linux",
+                "    - 1.2. Upgrade",
+                "- 2. Usage",
+            ]
+        );
+        assert_eq!(directives[1].depth, Some(1));
+        assert_eq!(directives[1].line, 8);
+        assert_eq!(
+            directives[1].lines,
+            vec!["- 1.1. Install", "- 1.2. Upgrade"]
+        );
+    }
+
+    #[test]
+    fn test_expand_toc() {
+        let doc = parse_document(SOURCE).unwrap();
+        let expanded = expand_toc(SOURCE, &doc).unwrap();
+        assert!(expanded.starts_with(
+            "Guide\n\nIntro.\n\n- 1. Setup\n    - 1.1. Install\n        - 1.1.1. Linux\n    - 1.2. Upgrade\n- 2. Usage\n\n1. Setup\n\n    - 1.1. Install\n    - 1.2. Upgrade\n\n    1.1. Install\n"
+        ));
+        let expanded_doc = parse_document(&expanded).unwrap();
+        assert!(toc_directives(&expanded_doc).unwrap().is_empty());
+
+        let invalid = "Guide\n\n:: toc depth=none ::\n\nText.\n";
+        let doc = parse_document(invalid).unwrap();
+        assert_eq!(
+            expand_toc(invalid, &doc),
+            Err(TocError::InvalidDepth {
+                line: 2,
+                value: "none".to_string()
+            })
+        );
+    }
+}
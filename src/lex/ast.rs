@@ -94,6 +94,7 @@
 //! - `lookup` - Position-based AST node lookup functionality
 //! - `snapshot` - Normalized intermediate representation for serialization
 //! - `error` - Error types for AST operations
+//! - `v1` - Version 1 facade over the node types, with shims for changed shapes
 //!
 //! ## Type-Safe Containers
 //!
@@ -111,9 +112,13 @@ pub mod snapshot;
 pub mod text_content;
 pub mod trait_helpers;
 pub mod traits;
+pub mod v1;
 
 // Re-export commonly used types at module root
-pub use diagnostics::{validate_references, validate_structure, Diagnostic, DiagnosticSeverity};
+pub use diagnostics::{
+    validate_references, validate_structure, Diagnostic, DiagnosticRelatedInformation,
+    DiagnosticSeverity,
+};
 pub use elements::{
     Annotation, ContentItem, Data, Definition, Document, Label, List, ListItem, Paragraph,
     Parameter, Session, TextLine, Verbatim,
@@ -29,6 +29,7 @@
 
 use super::range::Range;
 use super::Document;
+use crate::lex::annotation::ignore::IgnoreDirectives;
 use std::fmt;
 
 /// Diagnostic severity levels matching LSP protocol
@@ -51,6 +52,13 @@ impl fmt::Display for DiagnosticSeverity {
     }
 }
 
+/// Another location relevant to a diagnostic, such as the first of two duplicates
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagnosticRelatedInformation {
+    pub range: Range,
+    pub message: String,
+}
+
 /// Structured diagnostic for LSP consumption
 #[derive(Debug, Clone, PartialEq)]
 pub struct Diagnostic {
@@ -59,6 +67,7 @@ pub struct Diagnostic {
     pub message: String,
     pub code: Option<String>,
     pub source: String,
+    pub related: Vec<DiagnosticRelatedInformation>,
 }
 
 impl Diagnostic {
@@ -69,6 +78,7 @@ impl Diagnostic {
             message,
             code: None,
             source: "lex-parser".to_string(),
+            related: Vec::new(),
         }
     }
 
@@ -81,6 +91,14 @@ impl Diagnostic {
         self.source = source.into();
         self
     }
+
+    pub fn with_related(mut self, range: Range, message: impl Into<String>) -> Self {
+        self.related.push(DiagnosticRelatedInformation {
+            range,
+            message: message.into(),
+        });
+        self
+    }
 }
 
 impl fmt::Display for Diagnostic {
@@ -101,6 +119,8 @@ impl Document {
     /// - Malformed structures (single-item lists, etc.)
     /// - Invalid annotation syntax
     ///
+    /// Diagnostics suppressed by `:: lex-ignore code ::` annotations are left out.
+    ///
     /// # Example
     /// ```rust,ignore
     /// let doc = parse_document(source)?;
@@ -118,7 +138,8 @@ impl Document {
         // Collect structure validation errors
         diagnostics.extend(validate_structure(self));
 
-        diagnostics
+        // Honor `lex-ignore` directives
+        IgnoreDirectives::collect(self).filter_diagnostics(diagnostics)
     }
 }
 
@@ -127,7 +148,7 @@ impl Document {
 /// Checks for:
 /// - Broken footnote references `[42]` without matching annotation
 /// - Broken citation references `[@key]` without matching annotation
-/// - Broken session references `[#section]` without matching session
+/// - Broken session references `[#section]` without matching session or session id
 ///
 /// # Arguments
 /// * `document` - The document to validate
@@ -135,81 +156,39 @@ impl Document {
 /// # Returns
 /// Vector of diagnostics for broken references
 pub fn validate_references(document: &Document) -> Vec<Diagnostic> {
-    use super::traits::{AstNode, Container};
-    use crate::lex::inlines::ReferenceType;
+    use super::traits::Container;
+    use crate::lex::analysis::definitions::{find_annotation, ReferenceIndex, TargetKey};
 
     let mut diagnostics = Vec::new();
 
-    // Iterate all references in the document
-    for reference in document.iter_all_references() {
-        match &reference.reference_type {
-            ReferenceType::FootnoteNumber { number } => {
-                // Check if annotation with this label exists
-                let label = number.to_string();
-                if document.find_annotation_by_label(&label).is_none() {
-                    // We don't have location info for inline elements yet
-                    // Using document root location as fallback
-                    let range = document.root.range().clone();
-                    let diag = Diagnostic::new(
-                        range,
-                        DiagnosticSeverity::Warning,
-                        format!(
-                            "Broken footnote reference: no annotation found with label '{label}'"
-                        ),
-                    )
-                    .with_code("broken-reference");
-                    diagnostics.push(diag);
-                }
-            }
-            ReferenceType::FootnoteLabeled { label } => {
-                if document.find_annotation_by_label(label).is_none() {
-                    let range = document.root.range().clone();
-                    let diag = Diagnostic::new(
-                        range,
-                        DiagnosticSeverity::Warning,
-                        format!(
-                            "Broken footnote reference: no annotation found with label '{label}'"
-                        ),
-                    )
-                    .with_code("broken-reference");
-                    diagnostics.push(diag);
-                }
-            }
-            ReferenceType::Citation(citation_data) => {
-                for key in &citation_data.keys {
-                    if document.find_annotation_by_label(key).is_none() {
-                        let range = document.root.range().clone();
-                        let diag = Diagnostic::new(
-                            range,
-                            DiagnosticSeverity::Warning,
-                            format!(
-                                "Broken citation reference: no annotation found with label '{key}'"
-                            ),
-                        )
-                        .with_code("broken-citation");
-                        diagnostics.push(diag);
-                    }
-                }
-            }
-            ReferenceType::Session { target } => {
-                // Check if a session with this label exists
-                let sessions: Vec<_> = document.root.iter_sessions_recursive().collect();
-                let found = sessions.iter().any(|s| s.label() == target);
-                if !found {
-                    let range = document.root.range().clone();
-                    let diag = Diagnostic::new(
-                        range,
-                        DiagnosticSeverity::Warning,
-                        format!("Broken session reference: no session found with title '{target}'"),
-                    )
-                    .with_code("broken-session-ref");
-                    diagnostics.push(diag);
-                }
+    // Every reference, with its range in the source
+    for usage in ReferenceIndex::build(document).usages {
+        let (message, code) = match &usage.key {
+            TargetKey::Annotation(label) if find_annotation(document, label).is_none() => (
+                format!("Broken footnote reference: no annotation found with label '{label}'"),
+                "broken-reference",
+            ),
+            TargetKey::Citation(key) if find_annotation(document, key).is_none() => (
+                format!("Broken citation reference: no annotation found with label '{key}'"),
+                "broken-citation",
+            ),
+            TargetKey::Session(target)
+                if !document
+                    .root
+                    .iter_sessions_recursive()
+                    .any(|s| s.label() == target || s.id() == Some(target.as_str())) =>
+            {
+                (
+                    format!("Broken session reference: no session found with title '{target}'"),
+                    "broken-session-ref",
+                )
             }
-            _ => {
-                // URL, File, General, ToCome, and NotSure references don't need validation
-            }
-        }
+            // General references may point at anything, and the rest don't need validation
+            _ => continue,
+        };
+        diagnostics.push(
+            Diagnostic::new(usage.range, DiagnosticSeverity::Warning, message).with_code(code),
+        );
     }
 
     diagnostics
@@ -291,6 +270,29 @@ pub fn validate_structure(document: &Document) -> Vec<Diagnostic> {
                     .with_code("empty-verbatim-label");
                     diagnostics.push(diag);
                 }
+
+                // Check `lines` and `emphasize` ranges
+                let line_count = verbatim.content_lines().len();
+                let ranges = [
+                    ("lines", verbatim.line_selection()),
+                    ("emphasize", verbatim.emphasized_lines()),
+                ];
+                for (param, ranges) in ranges {
+                    let message = match ranges {
+                        Some(Err(err)) => format!("Invalid '{param}' parameter: {err}"),
+                        Some(Ok(ranges)) if ranges.first() > line_count => format!(
+                            "'{param}' parameter starts past the block's {line_count} line(s)"
+                        ),
+                        _ => continue,
+                    };
+                    let diag = Diagnostic::new(
+                        verbatim.closing_data.location.clone(),
+                        DiagnosticSeverity::Warning,
+                        message,
+                    )
+                    .with_code("invalid-line-range");
+                    diagnostics.push(diag);
+                }
             }
             _ => {
                 // Other content types don't need structural validation
@@ -298,6 +300,34 @@ pub fn validate_structure(document: &Document) -> Vec<Diagnostic> {
         }
     }
 
+    // Session ids must be unique, or references to them are ambiguous
+    let mut ids: Vec<(&str, &Range)> = Vec::new();
+    for session in document.root.iter_sessions_recursive() {
+        let Some(id) = session.id() else {
+            continue;
+        };
+        let range = session.header_location().unwrap_or(&session.location);
+        match ids.iter().find(|(seen, _)| *seen == id) {
+            Some((_, first)) => {
+                let diag = Diagnostic::new(
+                    range.clone(),
+                    DiagnosticSeverity::Warning,
+                    format!(
+                        "Duplicate session id '{id}', first used at line {}",
+                        first.start.line + 1
+                    ),
+                )
+                .with_code("duplicate-id")
+                .with_related(
+                    (*first).clone(),
+                    format!("Session id '{id}' first used here"),
+                );
+                diagnostics.push(diag);
+            }
+            None => ids.push((id, range)),
+        }
+    }
+
     diagnostics
 }
 
@@ -335,6 +365,9 @@ mod tests {
                 .any(|d| d.message.contains("Broken footnote reference")
                     && d.message.contains("'42'"))
         );
+        // The diagnostic covers the reference itself
+        assert_eq!(diagnostics[0].range.start.column, 38);
+        assert_eq!(diagnostics[0].range.end.column, 42);
     }
 
     #[test]
@@ -365,6 +398,15 @@ mod tests {
         assert!(diagnostics.is_empty());
     }
 
+    #[test]
+    fn test_document_diagnostics_honor_ignore_directives() {
+        let source = ":: lex-ignore broken-reference ::\n\nA paragraph with [42].\n\n";
+        let doc = parse_document(source).unwrap();
+
+        assert!(!validate_references(&doc).is_empty());
+        assert!(doc.diagnostics().is_empty());
+    }
+
     #[test]
     fn test_document_diagnostics_api() {
         let source = "A paragraph with [42].\n\n:: 42 :: Valid footnote.\n\n";
@@ -377,4 +419,45 @@ mod tests {
             .iter()
             .any(|d| d.message.contains("Broken footnote reference")));
     }
+
+    #[test]
+    fn test_session_ids() {
+        let source = "Title\n\nSee [#intro].\n\n:: id intro ::\n1. Intro\n\n    Text.\n\n:: id intro ::\n2. Usage\n\n    Text.\n";
+        let doc = parse_document(source).unwrap();
+        assert_eq!(
+            doc.find_session_by_id("intro").unwrap().full_title(),
+            "1. Intro"
+        );
+        assert!(validate_references(&doc).is_empty());
+
+        let duplicates: Vec<_> = validate_structure(&doc)
+            .into_iter()
+            .filter(|d| d.code.as_deref() == Some("duplicate-id"))
+            .collect();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].range.start.line, 10);
+        assert_eq!(
+            duplicates[0].message,
+            "Duplicate session id 'intro', first used at line 6"
+        );
+    }
+
+    #[test]
+    fn test_invalid_line_ranges() {
+        let source = "Title\n\nText.\n\nCode:\n    a\n    b\n:: rust lines=5-6, emphasize=x\n";
+        let doc = parse_document(source).unwrap();
+
+        let messages: Vec<_> = validate_structure(&doc)
+            .into_iter()
+            .filter(|d| d.code.as_deref() == Some("invalid-line-range"))
+            .map(|d| d.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "'lines' parameter starts past the block's 2 line(s)",
+                "Invalid 'emphasize' parameter: invalid line number 'x'",
+            ]
+        );
+    }
 }
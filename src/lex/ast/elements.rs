@@ -159,5 +159,5 @@ pub use parameter::Parameter;
 pub use sequence_marker::{DecorationStyle, Form, Separator, SequenceMarker};
 pub use session::Session;
 pub use typed_content::{ContentElement, ListContent, SessionContent, VerbatimContent};
-pub use verbatim::Verbatim;
+pub use verbatim::{LineRanges, NumberedLine, Verbatim};
 pub use verbatim_line::VerbatimLine;
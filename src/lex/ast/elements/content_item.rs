@@ -171,6 +171,19 @@ impl ContentItem {
         }
     }
 
+    /// Annotations attached to this node (empty for nodes that can't carry any)
+    pub fn annotations(&self) -> &[Annotation] {
+        match self {
+            ContentItem::Paragraph(p) => &p.annotations,
+            ContentItem::Session(s) => &s.annotations,
+            ContentItem::List(l) => &l.annotations,
+            ContentItem::ListItem(li) => &li.annotations,
+            ContentItem::Definition(d) => &d.annotations,
+            ContentItem::VerbatimBlock(fb) => &fb.annotations,
+            _ => &[],
+        }
+    }
+
     pub fn children_mut(&mut self) -> Option<&mut Vec<ContentItem>> {
         match self {
             ContentItem::Session(s) => Some(s.children.as_mut_vec()),
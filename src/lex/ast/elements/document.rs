@@ -236,6 +236,21 @@ impl Document {
         results
     }
 
+    /// Find the first session whose explicit id (`:: id intro ::`) is `id`.
+    ///
+    /// # Example
+    /// ```rust,ignore
+    /// // Resolve [#intro] to its session
+    /// if let Some(session) = document.find_session_by_id("intro") {
+    ///     // Jump to the session title
+    /// }
+    /// ```
+    pub fn find_session_by_id(&self, id: &str) -> Option<&Session> {
+        self.root
+            .iter_sessions_recursive()
+            .find(|session| session.id() == Some(id))
+    }
+
     /// Iterate all inline references at any depth.
     ///
     /// This method recursively walks the document tree, parses inline content,
@@ -5,8 +5,11 @@
 
 mod base;
 mod references;
+mod roles;
 
 pub use base::{InlineContent, InlineNode};
 pub use references::{
-    CitationData, CitationLocator, PageFormat, PageRange, ReferenceInline, ReferenceType,
+    CitationData, CitationLocator, ImageData, PageFormat, PageRange, ReferenceInline,
+    ReferenceType, WikiLinkData,
 };
+pub use roles::RoleInline;
@@ -24,6 +24,7 @@
 
 use super::super::annotation::Annotation;
 use super::references::ReferenceInline;
+use super::roles::RoleInline;
 
 /// Sequence of inline nodes produced from a [`TextContent`](crate::lex::ast::TextContent).
 pub type InlineContent = Vec<InlineNode>;
@@ -64,6 +65,17 @@ pub enum InlineNode {
         data: ReferenceInline,
         annotations: Vec<Annotation>,
     },
+    /// Ruby text `{base|gloss}`: a pronunciation gloss set above its base text.
+    Ruby {
+        base: String,
+        text: String,
+        annotations: Vec<Annotation>,
+    },
+    /// Span of a custom role registered with the parser, `{role|content}`.
+    Role {
+        data: RoleInline,
+        annotations: Vec<Annotation>,
+    },
 }
 
 impl InlineNode {
@@ -115,12 +127,30 @@ impl InlineNode {
         }
     }
 
+    /// Creates a ruby node without annotations.
+    pub fn ruby(base: String, text: String) -> Self {
+        InlineNode::Ruby {
+            base,
+            text,
+            annotations: Vec::new(),
+        }
+    }
+
+    /// Creates a role node without annotations.
+    pub fn role(data: RoleInline) -> Self {
+        InlineNode::Role {
+            data,
+            annotations: Vec::new(),
+        }
+    }
+
     /// Returns the plain text from this node when available.
     pub fn as_plain(&self) -> Option<&str> {
         match self {
             InlineNode::Plain { text, .. } => Some(text),
             InlineNode::Code { text, .. } => Some(text),
             InlineNode::Math { text, .. } => Some(text),
+            InlineNode::Ruby { base, .. } => Some(base),
             _ => None,
         }
     }
@@ -148,7 +178,9 @@ impl InlineNode {
             | InlineNode::Emphasis { annotations, .. }
             | InlineNode::Code { annotations, .. }
             | InlineNode::Math { annotations, .. }
-            | InlineNode::Reference { annotations, .. } => annotations,
+            | InlineNode::Reference { annotations, .. }
+            | InlineNode::Ruby { annotations, .. }
+            | InlineNode::Role { annotations, .. } => annotations,
         }
     }
 
@@ -160,7 +192,9 @@ impl InlineNode {
             | InlineNode::Emphasis { annotations, .. }
             | InlineNode::Code { annotations, .. }
             | InlineNode::Math { annotations, .. }
-            | InlineNode::Reference { annotations, .. } => annotations,
+            | InlineNode::Reference { annotations, .. }
+            | InlineNode::Ruby { annotations, .. }
+            | InlineNode::Role { annotations, .. } => annotations,
         }
     }
 
@@ -39,10 +39,69 @@ pub enum ReferenceType {
     File { target: String },
     /// `[Introduction]` or other document references.
     General { target: String },
+    /// `[[Target Note]]` or `[[target#section|text]]`, when wiki links are enabled.
+    WikiLink(WikiLinkData),
+    /// `[!./photo.png alt="A sunset" width=120]`
+    Image(ImageData),
     /// Unable to classify.
     NotSure,
 }
 
+/// Wiki link payload: the linked document, section and display text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WikiLinkData {
+    /// Document title or file name, as written.
+    pub target: String,
+    pub section: Option<String>,
+    pub text: Option<String>,
+}
+
+impl WikiLinkData {
+    /// Text to display: the explicit text, else the section, else the target.
+    pub fn display_text(&self) -> &str {
+        self.text
+            .as_deref()
+            .or(self.section.as_deref())
+            .unwrap_or(&self.target)
+    }
+}
+
+/// Inline image payload: the image file or URL and its parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageData {
+    pub src: String,
+    /// Alternative text, for readers who can't see the image.
+    pub alt: Option<String>,
+    /// Width as written: a number of pixels (`120`), a percentage (`50%`) or a length with
+    /// a unit (`3cm`).
+    pub width: Option<String>,
+    pub height: Option<String>,
+}
+
+impl ImageData {
+    /// The image as an inline HTML `<img>` element.
+    pub fn to_html(&self) -> String {
+        let escape = |text: &str| {
+            text.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+        };
+        let mut html = format!("<img src=\"{}\"", escape(&self.src));
+        html.push_str(&format!(
+            " alt=\"{}\"",
+            escape(self.alt.as_deref().unwrap_or_default())
+        ));
+        for (name, value) in [("width", &self.width), ("height", &self.height)] {
+            if let Some(value) = value {
+                html.push_str(&format!(" {name}=\"{}\"", escape(value)));
+            }
+        }
+        html.push('>');
+        html
+    }
+}
+
 /// Structured citation payload capturing parsed information.
 #[derive(Debug, Clone, PartialEq)]
 pub struct CitationData {
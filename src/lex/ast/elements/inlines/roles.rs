@@ -0,0 +1,22 @@
+//! Custom inline role nodes.
+//!
+//! Roles are inline spans defined outside the core grammar, registered with the inline
+//! parser (see [crate::lex::inlines::roles]).
+
+/// Inline span of a registered role, written `{role|content}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoleInline {
+    /// Name of the role, such as `kbd`
+    pub role: String,
+    /// Content after the `|`, as returned by the role's parse hook
+    pub content: String,
+}
+
+impl RoleInline {
+    pub fn new(role: impl Into<String>, content: impl Into<String>) -> Self {
+        Self {
+            role: role.into(),
+            content: content.into(),
+        }
+    }
+}
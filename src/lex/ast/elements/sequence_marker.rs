@@ -56,10 +56,12 @@
 use super::super::range::{Position, Range};
 use super::super::text_content::TextContent;
 use super::super::traits::AstNode;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// Decoration style for sequence markers
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum DecorationStyle {
     /// Plain dash marker: `-` (lists only, not sessions)
     Plain,
@@ -72,7 +74,8 @@ pub enum DecorationStyle {
 }
 
 /// Separator style for sequence markers
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum Separator {
     /// Period separator: `1.`, `a.`, `I.`
     Period,
@@ -83,7 +86,8 @@ pub enum Separator {
 }
 
 /// Form of sequence marker (simple vs extended)
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum Form {
     /// Short form: single level marker (e.g., `1.`, `a)`)
     Short,
@@ -114,6 +114,34 @@ impl Session {
         &self.annotations
     }
 
+    /// Whether an attached annotation asks for the session to start collapsed
+    ///
+    /// Set with a `collapsed` parameter (`:: details collapsed=true ::`, or just
+    /// `collapsed`); renderers show such sessions folded, as `<details>` in HTML.
+    pub fn is_collapsed(&self) -> bool {
+        self.annotations
+            .iter()
+            .flat_map(|annotation| annotation.data.parameters.iter())
+            .filter(|param| param.key == "collapsed")
+            .any(|param| {
+                let value = param.value.trim_matches('"');
+                value.is_empty() || value.eq_ignore_ascii_case("true")
+            })
+    }
+
+    /// Explicit id given with an `:: id <name> ::` annotation, on the line above the title
+    ///
+    /// Session references (`[#name]`) and `#fragment`s of file references resolve to it,
+    /// so links keep working when the title changes.
+    pub fn id(&self) -> Option<&str> {
+        self.annotations.iter().find_map(|annotation| {
+            let rest = annotation.data.label.value.trim().strip_prefix("id")?;
+            let id = rest.trim();
+            (rest.starts_with(char::is_whitespace) && !id.contains(char::is_whitespace))
+                .then_some(id)
+        })
+    }
+
     /// Range covering only the session title line, if available.
     pub fn header_location(&self) -> Option<&Range> {
         self.title.location.as_ref()
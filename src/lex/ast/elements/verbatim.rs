@@ -120,6 +120,20 @@
 //!
 //!     - Verbatim blocks spec: specs/v1/elements/verbatim.lex
 //!
+//! Line Numbers and Emphasis
+//!
+//!     Two closing parameters control how renderers present a block's lines, numbered from 1
+//!     across all groups: `lines` selects the lines shown (keeping their numbers) and
+//!     `emphasize` highlights lines. Both take comma separated numbers and ranges, quoted
+//!     since commas also separate parameters:
+//!
+//!         :: rust lines="1-10,15", emphasize=3-4
+//!
+//!     Renderers that write the lines themselves, such as the `ansi` format, use
+//!     [Verbatim::numbered_lines], which applies both. Renderers handing the whole block to
+//!     a target that selects and highlights lines, such as `lstlisting` in the `latex`
+//!     format, read the parsed [Verbatim::line_selection] and [Verbatim::emphasized_lines].
+//!
 
 use super::super::range::{Position, Range};
 use super::super::text_content::TextContent;
@@ -129,11 +143,15 @@ use super::container::VerbatimContainer;
 use super::content_item::ContentItem;
 use super::data::Data;
 use super::typed_content::VerbatimContent;
+use super::verbatim_line::VerbatimLine;
+use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::ops::RangeInclusive;
 use std::slice;
 
 /// Represents the mode of a verbatim block.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 pub enum VerbatimBlockMode {
     /// The block's content is indented relative to the subject line.
     Inflow,
@@ -258,6 +276,136 @@ impl Verbatim {
     pub fn group_len(&self) -> usize {
         1 + self.additional_groups.len()
     }
+
+    /// Value of the closing parameter `key`
+    pub fn parameter(&self, key: &str) -> Option<&str> {
+        self.closing_data
+            .parameters
+            .iter()
+            .find(|param| param.key == key)
+            .map(|param| param.value.as_str())
+    }
+
+    /// Lines selected by the `lines` parameter, if present
+    pub fn line_selection(&self) -> Option<Result<LineRanges, String>> {
+        self.parameter("lines").map(LineRanges::parse)
+    }
+
+    /// Lines highlighted by the `emphasize` parameter, if present
+    pub fn emphasized_lines(&self) -> Option<Result<LineRanges, String>> {
+        self.parameter("emphasize").map(LineRanges::parse)
+    }
+
+    /// Content lines of all groups, numbered from 1
+    pub fn content_lines(&self) -> Vec<&VerbatimLine> {
+        self.group()
+            .flat_map(|group| group.children.iter())
+            .filter_map(ContentItem::as_verbatim_line)
+            .collect()
+    }
+
+    /// Content lines to render, with their numbers and emphasis
+    ///
+    /// Applies the `lines` and `emphasize` parameters; invalid values are ignored (they are
+    /// reported by the document diagnostics).
+    pub fn numbered_lines(&self) -> Vec<NumberedLine<'_>> {
+        let selection = self.line_selection().and_then(Result::ok);
+        let emphasized = self.emphasized_lines().and_then(Result::ok);
+        self.content_lines()
+            .into_iter()
+            .enumerate()
+            .map(|(index, line)| NumberedLine {
+                number: index + 1,
+                line,
+                emphasized: emphasized
+                    .as_ref()
+                    .is_some_and(|ranges| ranges.contains(index + 1)),
+            })
+            .filter(|numbered| {
+                selection
+                    .as_ref()
+                    .is_none_or(|ranges| ranges.contains(numbered.number))
+            })
+            .collect()
+    }
+}
+
+/// A verbatim content line with its number, as rendered
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NumberedLine<'a> {
+    /// 1-based number of the line within the block
+    pub number: usize,
+    pub line: &'a VerbatimLine,
+    /// Whether the `emphasize` parameter highlights the line
+    pub emphasized: bool,
+}
+
+/// 1-based line numbers and ranges, as in `1-10,15`
+///
+/// A range may be open ended (`20-`). Numbers are separated by commas or spaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineRanges {
+    ranges: Vec<RangeInclusive<usize>>,
+}
+
+impl LineRanges {
+    /// Parse a parameter value; surrounding quotes are ignored
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|inner| inner.strip_suffix('"'))
+            .unwrap_or(value);
+        let number = |text: &str| -> Result<usize, String> {
+            match text.trim().parse::<usize>() {
+                Ok(number) if number > 0 => Ok(number),
+                _ => Err(format!("invalid line number '{}'", text.trim())),
+            }
+        };
+
+        let mut ranges = Vec::new();
+        for part in value
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|part| !part.is_empty())
+        {
+            let range = match part.split_once('-') {
+                Some((start, "")) => number(start)?..=usize::MAX,
+                Some((start, end)) => {
+                    let (start, end) = (number(start)?, number(end)?);
+                    if start > end {
+                        return Err(format!("empty line range '{part}'"));
+                    }
+                    start..=end
+                }
+                None => {
+                    let line = number(part)?;
+                    line..=line
+                }
+            };
+            ranges.push(range);
+        }
+        if ranges.is_empty() {
+            return Err("no line numbers".to_string());
+        }
+        Ok(Self { ranges })
+    }
+
+    pub fn ranges(&self) -> &[RangeInclusive<usize>] {
+        &self.ranges
+    }
+
+    pub fn contains(&self, line: usize) -> bool {
+        self.ranges.iter().any(|range| range.contains(&line))
+    }
+
+    /// Smallest line number in the set
+    pub fn first(&self) -> usize {
+        self.ranges
+            .iter()
+            .map(|range| *range.start())
+            .min()
+            .unwrap_or(1)
+    }
 }
 
 impl AstNode for Verbatim {
@@ -375,3 +523,51 @@ impl<'a> Iterator for VerbatimGroupIter<'a> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    fn first_verbatim(source: &str) -> Verbatim {
+        let doc = parse_document(source).unwrap();
+        let verbatim = doc.root.iter_all_nodes().find_map(|item| match item {
+            ContentItem::VerbatimBlock(verbatim) => Some(verbatim.as_ref().clone()),
+            _ => None,
+        });
+        verbatim.unwrap()
+    }
+
+    #[test]
+    fn test_line_ranges() {
+        let ranges = LineRanges::parse("\"1-3, 5 8-\"").unwrap();
+        assert_eq!(ranges.ranges(), &[1..=3, 5..=5, 8..=usize::MAX]);
+        assert!(ranges.contains(2) && ranges.contains(100) && !ranges.contains(4));
+        assert_eq!(ranges.first(), 1);
+        assert!(LineRanges::parse("0").is_err());
+        assert!(LineRanges::parse("4-2").is_err());
+        assert!(LineRanges::parse("a").is_err());
+    }
+
+    #[test]
+    fn test_numbered_lines() {
+        let verbatim = first_verbatim(
+            "Title\n\nText.\n\nCode:\n    a\n    b\n    c\n    d\n:: rust lines=\"2-3,4\", emphasize=3\n",
+        );
+        let lines: Vec<(usize, &str, bool)> = verbatim
+            .numbered_lines()
+            .iter()
+            .map(|numbered| {
+                (
+                    numbered.number,
+                    numbered.line.content.as_string(),
+                    numbered.emphasized,
+                )
+            })
+            .collect();
+        assert_eq!(
+            lines,
+            vec![(2, "b", false), (3, "c", true), (4, "d", false)]
+        );
+    }
+}
@@ -20,7 +20,7 @@ use super::{
     Annotation, ContentItem, Definition, Document, List, ListItem, Paragraph, Range, Session,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 /// A snapshot of an AST node in a normalized, serializable form
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -31,8 +31,9 @@ pub struct AstSnapshot {
     /// The primary label or text content of the node
     pub label: String,
 
-    /// Additional attributes specific to the node type
-    pub attributes: HashMap<String, String>,
+    /// Additional attributes specific to the node type, ordered by key so serialized
+    /// snapshots are reproducible
+    pub attributes: BTreeMap<String, String>,
 
     /// The source range of the node
     pub range: Range,
@@ -47,7 +48,7 @@ impl AstSnapshot {
         Self {
             node_type,
             label,
-            attributes: HashMap::new(),
+            attributes: BTreeMap::new(),
             range,
             children: Vec::new(),
         }
@@ -0,0 +1,71 @@
+//! Version 1 of the AST, as a stable facade
+//!
+//!     Downstream crates (the language server, babel, the viewer) pattern match on node
+//!     shapes, so changing a field breaks them all at once. Importing nodes from this module
+//!     instead of [ast](super) pins them to the shapes of version 1, letting them migrate at
+//!     their own pace, the way the parser keeps designs side by side.
+//!
+//!     Today the current AST is version 1, and this module re-exports it. When a node's shape
+//!     changes, its old shape moves here as its own type, converted from and to the new one
+//!     with [FromCurrent] and [IntoCurrent], and its re-export from [ast](super) keeps the new
+//!     shape. Shims stay for at least one release, marked `#[deprecated]` with a note on what
+//!     replaced them, before they are removed.
+//!
+//!     [parse_document] parses straight into version 1 nodes:
+//!
+//!         use lex_core::lex::ast::v1;
+//!         let doc: v1::Document = v1::parse_document("Hello\n")?;
+
+pub use super::elements::{
+    Annotation, ContentItem, Data, Definition, Document, Label, List, ListItem, Paragraph,
+    Parameter, Session, TextLine, Verbatim,
+};
+pub use super::range::{Position, Range};
+pub use super::text_content::TextContent;
+
+/// AST version this module pins
+pub const VERSION: u32 = 1;
+
+/// Conversion of a node of the current AST into its version 1 shape
+pub trait FromCurrent<T>: Sized {
+    fn from_current(node: T) -> Self;
+}
+
+/// Conversion of a version 1 node into the current AST
+pub trait IntoCurrent<T> {
+    fn into_current(self) -> T;
+}
+
+// Nodes whose shape has not changed convert to themselves
+impl<T> FromCurrent<T> for T {
+    fn from_current(node: T) -> Self {
+        node
+    }
+}
+
+impl<T> IntoCurrent<T> for T {
+    fn into_current(self) -> T {
+        self
+    }
+}
+
+/// Parse `source` into a version 1 document
+pub fn parse_document(source: &str) -> Result<Document, String> {
+    crate::lex::parsing::parse_document(source).map(Document::from_current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let source = "Title\n\nIntro:\n    A paragraph.\n";
+        let doc = parse_document(source).unwrap();
+        let current: crate::lex::ast::Document = doc.clone().into_current();
+        assert_eq!(
+            current,
+            crate::lex::parsing::parse_document(source).unwrap()
+        );
+    }
+}
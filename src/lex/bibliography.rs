@@ -0,0 +1,104 @@
+//! Bibliographies
+//!
+//!     Citations (`[@key]`, see [citations](crate::lex::inlines)) name entries of a
+//!     bibliography. This module holds the entry model and formats citations and reference
+//!     lists in a citation style, so every output format renders them alike.
+//!
+//!     Styles are a built-in subset of CSL: APA, IEEE and Chicago author-date (see [style]).
+//!     A [Bibliography] orders the cited entries as the style requires, formats in-text
+//!     citations and renders the reference list:
+//!
+//!         let mut registry = BibRegistry::new();
+//!         registry.load_file("refs.bib")?;
+//!         let entries: Vec<BibEntry> = registry.entries().cloned().collect();
+//!         let keys = cited_keys(&document);
+//!         let bibliography = Bibliography::new(CitationStyle::Apa, &entries, &keys);
+//!         let references = bibliography.render();
+//!
+//!     Entries are read from BibTeX ([bibtex]) or CSL-JSON ([csl_json]) files into a
+//!     [BibRegistry], which reports duplicate keys and cited keys missing from every file.
+//!     Keys naming a DOI or ISBN (`[@doi:10.1000/xyz]`) can be resolved by a [Fetcher]
+//!     instead, an opt-in step that caches records locally (see [fetch]).
+
+pub mod bibtex;
+pub mod csl_json;
+pub mod entry;
+pub mod fetch;
+pub mod registry;
+pub mod style;
+
+pub use bibtex::parse_bibtex;
+pub use csl_json::parse_csl_json;
+pub use entry::{BibEntry, EntryKind, Name};
+pub use fetch::{Fetcher, Identifier, MetadataCache, MetadataSource};
+pub use registry::{BibRegistry, EntryOrigin};
+pub use style::{Bibliography, CitationStyle};
+
+use crate::lex::ast::Document;
+use crate::lex::inlines::ReferenceType;
+use std::fmt;
+
+/// Error that can occur while reading a bibliography
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BibliographyError {
+    /// The source is not valid; `line` is 0-based
+    Parse {
+        line: Option<usize>,
+        message: String,
+    },
+    /// The file could not be read
+    Io(String),
+    /// The file extension is neither `.bib` nor `.json`
+    UnsupportedFormat(String),
+    /// Metadata for an identifier could not be obtained
+    Fetch(String),
+}
+
+impl fmt::Display for BibliographyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BibliographyError::Parse {
+                line: Some(line),
+                message,
+            } => write!(f, "Parse error at line {}: {message}", line + 1),
+            BibliographyError::Parse {
+                line: None,
+                message,
+            } => write!(f, "Parse error: {message}"),
+            BibliographyError::Io(msg) => write!(f, "IO error: {msg}"),
+            BibliographyError::UnsupportedFormat(name) => {
+                write!(f, "Unsupported bibliography format: {name}")
+            }
+            BibliographyError::Fetch(msg) => write!(f, "Fetch error: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for BibliographyError {}
+
+/// Citation keys used in `document`, in order of first citation
+pub fn cited_keys(document: &Document) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+    for reference in document.iter_all_references() {
+        if let ReferenceType::Citation(citation) = reference.reference_type {
+            for key in citation.keys {
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    #[test]
+    fn test_cited_keys() {
+        let doc = parse_document("Title\n\nAs shown [@b; @a] and [@b, p. 4].\n").unwrap();
+        assert_eq!(cited_keys(&doc), vec!["b", "a"]);
+    }
+}
@@ -0,0 +1,433 @@
+//! BibTeX import
+//!
+//! Reads `.bib` files into [BibEntry]s. Supported: regular entries with braced, quoted,
+//! numeric and macro values, `#` concatenation, `@string` macros (plus the month
+//! abbreviations), and `@comment` / `@preamble` blocks, which are skipped. Braces are dropped
+//! from values, except around author names, where `{World Health Organization}` marks a
+//! literal name. Common LaTeX escapes (`\&`, `~`, `--`) are translated; other commands are
+//! kept as written.
+
+use super::entry::{BibEntry, EntryKind, Name};
+use super::BibliographyError;
+use std::collections::HashMap;
+
+/// An entry together with the line (0-based) it starts on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BibtexEntry {
+    pub entry: BibEntry,
+    pub line: usize,
+}
+
+/// Parse a BibTeX source
+pub fn parse_bibtex(source: &str) -> Result<Vec<BibtexEntry>, BibliographyError> {
+    Parser::new(source).entries()
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    position: usize,
+    macros: HashMap<String, String>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Self {
+        let months = [
+            "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+        ];
+        let macros = months
+            .iter()
+            .enumerate()
+            .map(|(index, month)| (month.to_string(), (index + 1).to_string()))
+            .collect();
+        Self {
+            source,
+            position: 0,
+            macros,
+        }
+    }
+
+    fn entries(mut self) -> Result<Vec<BibtexEntry>, BibliographyError> {
+        let mut entries = Vec::new();
+        // Anything outside of `@` blocks is a comment
+        while let Some(offset) = self.source[self.position..].find('@') {
+            self.position += offset;
+            let line = self.line();
+            self.position += 1;
+            let kind = self.identifier().to_lowercase();
+            self.skip_whitespace();
+            let close = match self.peek() {
+                Some('{') => '}',
+                Some('(') => ')',
+                _ => return Err(self.error("expected '{' or '(' after the entry type")),
+            };
+            self.position += 1;
+            match kind.as_str() {
+                "comment" | "preamble" => self.skip_block(close)?,
+                "string" => {
+                    let (name, value) = self.field()?;
+                    self.macros.insert(name, value);
+                    self.expect(close)?;
+                }
+                _ => {
+                    let entry = self.entry(&kind, close)?;
+                    entries.push(BibtexEntry { entry, line });
+                }
+            }
+        }
+        Ok(entries)
+    }
+
+    fn entry(&mut self, kind: &str, close: char) -> Result<BibEntry, BibliographyError> {
+        self.skip_whitespace();
+        let key_start = self.position;
+        while let Some(c) = self.peek() {
+            if c == ',' || c == close || c.is_whitespace() {
+                break;
+            }
+            self.position += c.len_utf8();
+        }
+        let key = self.source[key_start..self.position].to_string();
+        if key.is_empty() {
+            return Err(self.error("missing citation key"));
+        }
+
+        let mut fields = HashMap::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.position += 1;
+                    self.skip_whitespace();
+                    if self.peek() == Some(close) {
+                        continue;
+                    }
+                    let (name, value) = self.field()?;
+                    fields.insert(name, value);
+                }
+                Some(c) if c == close => {
+                    self.position += 1;
+                    break;
+                }
+                _ => return Err(self.error(&format!("expected ',' or '{close}'"))),
+            }
+        }
+        Ok(to_entry(key, kind, &fields))
+    }
+
+    /// `name = value`, with the name lowercased and the value still holding inner braces
+    fn field(&mut self) -> Result<(String, String), BibliographyError> {
+        self.skip_whitespace();
+        let name = self.identifier().to_lowercase();
+        if name.is_empty() {
+            return Err(self.error("expected a field name"));
+        }
+        self.skip_whitespace();
+        self.expect('=')?;
+        let mut value = String::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some('{') => {
+                    self.position += 1;
+                    value.push_str(&self.braced()?);
+                }
+                Some('"') => {
+                    self.position += 1;
+                    value.push_str(&self.quoted()?);
+                }
+                Some(c) if c.is_ascii_digit() => {
+                    let start = self.position;
+                    while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                        self.position += 1;
+                    }
+                    value.push_str(&self.source[start..self.position]);
+                }
+                Some(_) => {
+                    let name = self.identifier();
+                    if name.is_empty() {
+                        return Err(self.error("expected a field value"));
+                    }
+                    let expansion = self.macros.get(&name.to_lowercase()).cloned();
+                    value.push_str(&expansion.unwrap_or(name));
+                }
+                None => return Err(self.error("unexpected end of input")),
+            }
+            self.skip_whitespace();
+            if self.peek() == Some('#') {
+                self.position += 1;
+            } else {
+                break;
+            }
+        }
+        Ok((name, value))
+    }
+
+    /// Content up to the brace closing an already consumed `{`, inner braces included
+    fn braced(&mut self) -> Result<String, BibliographyError> {
+        let start = self.position;
+        let mut depth = 0;
+        while let Some(c) = self.peek() {
+            self.position += c.len_utf8();
+            match c {
+                '{' => depth += 1,
+                '}' if depth == 0 => return Ok(self.source[start..self.position - 1].to_string()),
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        Err(self.error("unclosed '{'"))
+    }
+
+    /// Content up to the quote closing an already consumed `"`, ignoring quotes in braces
+    fn quoted(&mut self) -> Result<String, BibliographyError> {
+        let start = self.position;
+        let mut depth = 0;
+        while let Some(c) = self.peek() {
+            self.position += c.len_utf8();
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                '"' if depth == 0 => return Ok(self.source[start..self.position - 1].to_string()),
+                _ => {}
+            }
+        }
+        Err(self.error("unclosed '\"'"))
+    }
+
+    fn skip_block(&mut self, close: char) -> Result<(), BibliographyError> {
+        if close == '}' {
+            self.braced().map(|_| ())
+        } else {
+            match self.source[self.position..].find(')') {
+                Some(offset) => {
+                    self.position += offset + 1;
+                    Ok(())
+                }
+                None => Err(self.error("unclosed '('")),
+            }
+        }
+    }
+
+    fn identifier(&mut self) -> String {
+        let start = self.position;
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || matches!(c, '_' | '-' | ':' | '.' | '+' | '/') {
+                self.position += c.len_utf8();
+            } else {
+                break;
+            }
+        }
+        self.source[start..self.position].to_string()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), BibliographyError> {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.position += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{expected}'")))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.position += c.len_utf8();
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.source[self.position..].chars().next()
+    }
+
+    fn line(&self) -> usize {
+        self.source[..self.position].matches('\n').count()
+    }
+
+    fn error(&self, message: &str) -> BibliographyError {
+        BibliographyError::Parse {
+            line: Some(self.line()),
+            message: message.to_string(),
+        }
+    }
+}
+
+fn entry_kind(kind: &str) -> EntryKind {
+    match kind {
+        "article" => EntryKind::Article,
+        "book" | "booklet" => EntryKind::Book,
+        "inbook" | "incollection" => EntryKind::Chapter,
+        "inproceedings" | "conference" => EntryKind::Conference,
+        "techreport" | "report" => EntryKind::Report,
+        "phdthesis" | "mastersthesis" | "thesis" => EntryKind::Thesis,
+        "online" | "electronic" | "www" => EntryKind::Webpage,
+        _ => EntryKind::Misc,
+    }
+}
+
+fn to_entry(key: String, kind: &str, fields: &HashMap<String, String>) -> BibEntry {
+    let text = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| fields.get(*name))
+            .map(|value| clean(value))
+            .filter(|value| !value.is_empty())
+    };
+    let year = text(&["year", "date"]).and_then(|value| {
+        let digits: String = value.chars().take_while(char::is_ascii_digit).collect();
+        digits.parse().ok()
+    });
+    BibEntry {
+        key,
+        kind: entry_kind(kind),
+        title: text(&["title"]).unwrap_or_default(),
+        authors: fields
+            .get("author")
+            .map(|value| names(value))
+            .unwrap_or_default(),
+        editors: fields
+            .get("editor")
+            .map(|value| names(value))
+            .unwrap_or_default(),
+        year,
+        container_title: text(&["journal", "journaltitle", "booktitle"]),
+        volume: text(&["volume"]),
+        issue: text(&["number", "issue"]),
+        pages: text(&["pages"]),
+        publisher: text(&["publisher", "institution", "school", "organization"]),
+        publisher_place: text(&["address", "location"]),
+        doi: text(&["doi"]),
+        url: text(&["url"]),
+    }
+}
+
+/// Split a name list on top level `and`s
+fn names(value: &str) -> Vec<Name> {
+    let mut names = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    let bytes = value.as_bytes();
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'{' => depth += 1,
+            b'}' => depth -= 1,
+            _ if depth == 0 && value[index..].starts_with(" and ") => {
+                names.push(name(&value[start..index]));
+                index += " and ".len();
+                start = index;
+                continue;
+            }
+            _ => {}
+        }
+        index += 1;
+    }
+    names.push(name(&value[start..]));
+    names.retain(|name| !name.family.is_empty());
+    names
+}
+
+/// `Family, Given`, `Given Family` or a braced literal
+fn name(text: &str) -> Name {
+    let text = text.trim();
+    if text.starts_with('{') && text.ends_with('}') && !text[1..text.len() - 1].contains('{') {
+        return Name::literal(clean(text));
+    }
+    let text = clean(text);
+    if let Some((family, given)) = text.split_once(',') {
+        return Name::new(family.trim(), given.trim());
+    }
+    match text.rsplit_once(' ') {
+        Some((given, family)) => Name::new(family.trim(), given.trim()),
+        None => Name::literal(text),
+    }
+}
+
+/// Drop braces, translate common escapes and collapse whitespace
+fn clean(value: &str) -> String {
+    let text = value
+        .replace(['{', '}'], "")
+        .replace("\\&", "&")
+        .replace("\\%", "%")
+        .replace("\\_", "_")
+        .replace("---", "—")
+        .replace("--", "-")
+        .replace('~', " ");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = r#"
+Some free text is a comment.
+
+@string{ jpt = "Journal of Plain Text" }
+
+@comment{ ignored {nested} }
+
+@Article{smith2020,
+  author  = {Smith, John Ronald and Ann Jones and {World Health Organization}},
+  title   = {Writing in {Lines} \& {Columns}},
+  journal = jpt,
+  year    = 2020,
+  month   = mar,
+  volume  = "12",
+  pages   = {45--67},
+  doi     = {10.1000/xyz},
+}
+
+@book(adams1979,
+  author = "Douglas Adams",
+  title = "The hitchhiker's " # "guide",
+  publisher = {Pan Books},
+  address = {London},
+  date = {1979-10-12}
+)
+"#;
+
+    #[test]
+    fn test_parses_entries() {
+        let entries = parse_bibtex(SOURCE).unwrap();
+        assert_eq!(entries.len(), 2);
+
+        let smith = &entries[0];
+        assert_eq!(smith.line, 7);
+        assert_eq!(smith.entry.key, "smith2020");
+        assert_eq!(smith.entry.kind, EntryKind::Article);
+        assert_eq!(smith.entry.title, "Writing in Lines & Columns");
+        assert_eq!(
+            smith.entry.authors,
+            vec![
+                Name::new("Smith", "John Ronald"),
+                Name::new("Jones", "Ann"),
+                Name::literal("World Health Organization"),
+            ]
+        );
+        assert_eq!(
+            smith.entry.container_title.as_deref(),
+            Some("Journal of Plain Text")
+        );
+        assert_eq!(smith.entry.year, Some(2020));
+        assert_eq!(smith.entry.pages.as_deref(), Some("45-67"));
+
+        let adams = &entries[1].entry;
+        assert_eq!(adams.kind, EntryKind::Book);
+        assert_eq!(adams.title, "The hitchhiker's guide");
+        assert_eq!(adams.year, Some(1979));
+        assert_eq!(adams.publisher_place.as_deref(), Some("London"));
+    }
+
+    #[test]
+    fn test_reports_errors_with_lines() {
+        let error = parse_bibtex("@article{key,\n  title = {unclosed\n").unwrap_err();
+        assert_eq!(
+            error,
+            BibliographyError::Parse {
+                line: Some(2),
+                message: "unclosed '{'".to_string()
+            }
+        );
+    }
+}
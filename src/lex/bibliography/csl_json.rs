@@ -0,0 +1,161 @@
+//! CSL-JSON import
+//!
+//! Reads CSL-JSON, the bibliography format of citeproc processors and the export format of
+//! Zotero and similar reference managers: an array of items, each with an `id` (the citation
+//! key) and CSL variables. Variables the styles don't use are ignored.
+
+use super::entry::{BibEntry, EntryKind, Name};
+use super::BibliographyError;
+use serde_json::Value;
+
+/// Parse a CSL-JSON source
+pub fn parse_csl_json(source: &str) -> Result<Vec<BibEntry>, BibliographyError> {
+    let json: Value = serde_json::from_str(source).map_err(|err| BibliographyError::Parse {
+        line: Some(err.line().saturating_sub(1)),
+        message: err.to_string(),
+    })?;
+    let items = match json {
+        Value::Array(items) => items,
+        // A single item is accepted as well
+        item @ Value::Object(_) => vec![item],
+        _ => return Err(error("expected an array of items")),
+    };
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            to_entry(item).ok_or_else(|| error(&format!("item {index} has no id")))
+        })
+        .collect()
+}
+
+fn error(message: &str) -> BibliographyError {
+    BibliographyError::Parse {
+        line: None,
+        message: message.to_string(),
+    }
+}
+
+fn entry_kind(kind: &str) -> EntryKind {
+    match kind {
+        "article" | "article-journal" | "article-magazine" | "article-newspaper" => {
+            EntryKind::Article
+        }
+        "book" => EntryKind::Book,
+        "chapter" => EntryKind::Chapter,
+        "paper-conference" => EntryKind::Conference,
+        "report" => EntryKind::Report,
+        "thesis" => EntryKind::Thesis,
+        "webpage" | "post" | "post-weblog" => EntryKind::Webpage,
+        _ => EntryKind::Misc,
+    }
+}
+
+fn to_entry(item: &Value) -> Option<BibEntry> {
+    let key = text(item.get("id")?)?;
+    let field = |name: &str| item.get(name).and_then(text);
+    Some(BibEntry {
+        key,
+        kind: field("type")
+            .map(|kind| entry_kind(&kind))
+            .unwrap_or_default(),
+        title: field("title").unwrap_or_default(),
+        authors: names(item.get("author")),
+        editors: names(item.get("editor")),
+        year: item.get("issued").and_then(year),
+        container_title: field("container-title"),
+        volume: field("volume"),
+        issue: field("issue"),
+        pages: field("page"),
+        publisher: field("publisher"),
+        publisher_place: field("publisher-place"),
+        doi: field("DOI"),
+        url: field("URL"),
+    })
+}
+
+/// Strings and numbers as text
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.trim().to_string()).filter(|text| !text.is_empty()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+}
+
+fn names(value: Option<&Value>) -> Vec<Name> {
+    let Some(Value::Array(names)) = value else {
+        return Vec::new();
+    };
+    names
+        .iter()
+        .filter_map(|name| {
+            if let Some(literal) = name.get("literal").and_then(text) {
+                return Some(Name::literal(literal));
+            }
+            let family = name.get("family").and_then(text)?;
+            Some(match name.get("given").and_then(text) {
+                Some(given) => Name::new(family, given),
+                None => Name::literal(family),
+            })
+        })
+        .collect()
+}
+
+/// Year of a CSL date: `{"date-parts": [[2020, 3]]}` or `{"raw": "2020-03"}`
+fn year(date: &Value) -> Option<i32> {
+    if let Some(first) = date.pointer("/date-parts/0/0") {
+        return match first {
+            Value::Number(number) => number.as_i64().and_then(|year| i32::try_from(year).ok()),
+            Value::String(text) => text.parse().ok(),
+            _ => None,
+        };
+    }
+    let raw = date.get("raw").and_then(Value::as_str)?;
+    let digits: String = raw.chars().take_while(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_items() {
+        let source = r#"[
+            {
+                "id": "smith2020",
+                "type": "article-journal",
+                "title": "Writing in lines",
+                "author": [{"family": "Smith", "given": "John"}, {"literal": "WHO"}],
+                "issued": {"date-parts": [[2020, 3]]},
+                "container-title": "Journal of Plain Text",
+                "volume": 12,
+                "page": "45-67",
+                "DOI": "10.1000/xyz"
+            },
+            {"id": "adams1979", "type": "book", "title": "Guide", "issued": {"raw": "1979"}}
+        ]"#;
+        let entries = parse_csl_json(source).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].kind, EntryKind::Article);
+        assert_eq!(
+            entries[0].authors,
+            vec![Name::new("Smith", "John"), Name::literal("WHO")]
+        );
+        assert_eq!(entries[0].year, Some(2020));
+        assert_eq!(entries[0].volume.as_deref(), Some("12"));
+        assert_eq!(entries[0].doi.as_deref(), Some("10.1000/xyz"));
+        assert_eq!(entries[1].kind, EntryKind::Book);
+        assert_eq!(entries[1].year, Some(1979));
+    }
+
+    #[test]
+    fn test_rejects_items_without_id() {
+        assert!(parse_csl_json(r#"[{"title": "No id"}]"#).is_err());
+        assert!(matches!(
+            parse_csl_json("[{"),
+            Err(BibliographyError::Parse { line: Some(0), .. })
+        ));
+    }
+}
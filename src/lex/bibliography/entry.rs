@@ -0,0 +1,108 @@
+//! Bibliography entries
+//!
+//! A style independent model of a bibliographic item, close to the CSL data model but
+//! limited to the fields the built-in styles use.
+
+/// Kind of work an entry describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EntryKind {
+    /// Journal or magazine article
+    Article,
+    Book,
+    /// Chapter or section of an edited book
+    Chapter,
+    /// Paper in conference proceedings
+    Conference,
+    /// Technical report
+    Report,
+    Thesis,
+    Webpage,
+    #[default]
+    Misc,
+}
+
+/// A person's name
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Name {
+    pub family: String,
+    /// Given names; `None` for literal names such as organizations
+    pub given: Option<String>,
+}
+
+impl Name {
+    pub fn new(family: impl Into<String>, given: impl Into<String>) -> Self {
+        Self {
+            family: family.into(),
+            given: Some(given.into()),
+        }
+    }
+
+    /// A name used as is, such as an organization
+    pub fn literal(name: impl Into<String>) -> Self {
+        Self {
+            family: name.into(),
+            given: None,
+        }
+    }
+
+    /// Initials of the given names, such as `J. R.` for `John Ronald`
+    ///
+    /// Hyphenated names keep the hyphen: `Jean-Paul` gives `J.-P.`.
+    pub fn initials(&self) -> Option<String> {
+        let given = self.given.as_deref()?;
+        let initials: Vec<String> = given
+            .split_whitespace()
+            .map(|part| {
+                part.split('-')
+                    .filter_map(|piece| piece.chars().next())
+                    .map(|c| format!("{c}."))
+                    .collect::<Vec<_>>()
+                    .join("-")
+            })
+            .collect();
+        (!initials.is_empty()).then(|| initials.join(" "))
+    }
+}
+
+/// A bibliography entry
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct BibEntry {
+    /// Citation key, as used in `[@key]`
+    pub key: String,
+    pub kind: EntryKind,
+    pub title: String,
+    pub authors: Vec<Name>,
+    pub editors: Vec<Name>,
+    pub year: Option<i32>,
+    /// Journal, book or proceedings the work appears in
+    pub container_title: Option<String>,
+    pub volume: Option<String>,
+    pub issue: Option<String>,
+    /// Page range, such as `45-67`
+    pub pages: Option<String>,
+    pub publisher: Option<String>,
+    pub publisher_place: Option<String>,
+    pub doi: Option<String>,
+    pub url: Option<String>,
+}
+
+impl BibEntry {
+    pub fn new(key: impl Into<String>, kind: EntryKind, title: impl Into<String>) -> Self {
+        Self {
+            key: key.into(),
+            kind,
+            title: title.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Key used to sort author-date bibliographies: first author, year, title
+    pub(crate) fn sort_key(&self) -> (String, Option<i32>, String) {
+        let author = self
+            .authors
+            .first()
+            .map(|name| name.family.to_lowercase())
+            .unwrap_or_else(|| self.title.to_lowercase());
+        (author, self.year, self.title.to_lowercase())
+    }
+}
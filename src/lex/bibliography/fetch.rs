@@ -0,0 +1,302 @@
+//! Metadata fetching
+//!
+//! Citations may name a work by identifier instead of a bibliography key: `[@doi:10.1000/xyz]`
+//! or `[@isbn:9780345391803]`. A [Fetcher] resolves such keys into [BibEntry]s, keeping every
+//! record it obtains in a local [MetadataCache] so later runs work offline.
+//!
+//! The library does no networking itself: records come from a [MetadataSource] the caller
+//! provides (for instance an HTTP client asking `https://doi.org/<doi>` for
+//! `application/vnd.citationstyles.csl+json`). Without a source, the fetcher only reads the
+//! cache.
+
+use super::csl_json::parse_csl_json;
+use super::entry::BibEntry;
+use super::registry::BibRegistry;
+use super::{cited_keys, BibliographyError};
+use crate::lex::ast::Document;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Resolver URLs DOIs are written under, stripped from DOI keys
+const DOI_RESOLVERS: [&str; 4] = [
+    "https://doi.org/",
+    "http://doi.org/",
+    "https://dx.doi.org/",
+    "http://dx.doi.org/",
+];
+
+/// A work identifier a record can be fetched for
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Identifier {
+    /// A DOI, lowercased, without resolver prefix
+    Doi(String),
+    /// An ISBN, digits (and a final `X`) only
+    Isbn(String),
+}
+
+impl Identifier {
+    /// The identifier a citation key names: `doi:`, `isbn:`, a bare DOI (`10.` prefix) or a
+    /// DOI URL (`https://doi.org/`, or the older `dx.doi.org`)
+    pub fn from_key(key: &str) -> Option<Self> {
+        let lower = key.to_lowercase();
+        if let Some(doi) = lower.strip_prefix("doi:") {
+            return Self::doi(doi);
+        }
+        if let Some(isbn) = lower.strip_prefix("isbn:") {
+            return Self::isbn(isbn);
+        }
+        if lower.starts_with("10.") || DOI_RESOLVERS.iter().any(|url| lower.starts_with(url)) {
+            return Self::doi(&lower);
+        }
+        None
+    }
+
+    fn doi(doi: &str) -> Option<Self> {
+        let doi = doi.trim();
+        let doi = DOI_RESOLVERS
+            .iter()
+            .find_map(|url| doi.strip_prefix(url))
+            .unwrap_or(doi);
+        (doi.starts_with("10.") && doi.contains('/')).then(|| Identifier::Doi(doi.to_lowercase()))
+    }
+
+    fn isbn(isbn: &str) -> Option<Self> {
+        let isbn: String = isbn
+            .chars()
+            .filter(|c| c.is_ascii_digit() || *c == 'x' || *c == 'X')
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        matches!(isbn.len(), 10 | 13).then_some(Identifier::Isbn(isbn))
+    }
+
+    /// File name of the cached record, with the identifier percent-encoded so distinct
+    /// identifiers never share a file
+    fn cache_name(&self) -> String {
+        let (prefix, value) = match self {
+            Identifier::Doi(doi) => ("doi", doi),
+            Identifier::Isbn(isbn) => ("isbn", isbn),
+        };
+        let mut name = format!("{prefix}-");
+        for byte in value.bytes() {
+            if byte.is_ascii_alphanumeric() || byte == b'.' || byte == b'-' {
+                name.push(byte as char);
+            } else {
+                name.push_str(&format!("%{byte:02X}"));
+            }
+        }
+        name.push_str(".json");
+        name
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Identifier::Doi(doi) => write!(f, "doi:{doi}"),
+            Identifier::Isbn(isbn) => write!(f, "isbn:{isbn}"),
+        }
+    }
+}
+
+/// Where records come from, such as an HTTP client
+pub trait MetadataSource {
+    /// The CSL-JSON record of `identifier`
+    fn fetch(&self, identifier: &Identifier) -> Result<String, BibliographyError>;
+}
+
+/// Fetched CSL-JSON records, one file per identifier
+#[derive(Debug, Clone)]
+pub struct MetadataCache {
+    dir: PathBuf,
+}
+
+impl MetadataCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The cached record of `identifier`, if any
+    pub fn get(&self, identifier: &Identifier) -> Option<String> {
+        std::fs::read_to_string(self.dir.join(identifier.cache_name())).ok()
+    }
+
+    pub fn put(&self, identifier: &Identifier, record: &str) -> Result<(), BibliographyError> {
+        let io = |err: std::io::Error| BibliographyError::Io(err.to_string());
+        std::fs::create_dir_all(&self.dir).map_err(io)?;
+        std::fs::write(self.dir.join(identifier.cache_name()), record).map_err(io)
+    }
+}
+
+/// Result of resolving a document's identifier keys
+#[derive(Debug, Clone, Default)]
+pub struct FetchReport {
+    /// Resolved entries, keyed by the citation key that named them
+    pub entries: Vec<BibEntry>,
+    /// Keys that could not be resolved, with the reason
+    pub failed: Vec<(String, BibliographyError)>,
+}
+
+/// Resolves identifier keys through the cache, then the source
+pub struct Fetcher<'a> {
+    cache: MetadataCache,
+    source: Option<&'a dyn MetadataSource>,
+}
+
+impl<'a> Fetcher<'a> {
+    /// A fetcher that only reads the cache
+    pub fn offline(cache: MetadataCache) -> Self {
+        Self {
+            cache,
+            source: None,
+        }
+    }
+
+    /// A fetcher asking `source` for records missing from the cache
+    pub fn new(cache: MetadataCache, source: &'a dyn MetadataSource) -> Self {
+        Self {
+            cache,
+            source: Some(source),
+        }
+    }
+
+    /// The entry for citation key `key`, which must name an identifier
+    pub fn resolve(&self, key: &str) -> Result<BibEntry, BibliographyError> {
+        let identifier = Identifier::from_key(key)
+            .ok_or_else(|| BibliographyError::Fetch(format!("'{key}' is not a DOI or ISBN")))?;
+        let record = match (self.cache.get(&identifier), self.source) {
+            (Some(record), _) => record,
+            (None, Some(source)) => {
+                let record = source.fetch(&identifier)?;
+                // Only cache records that parse
+                parse_record(&identifier, &record)?;
+                self.cache.put(&identifier, &record)?;
+                record
+            }
+            (None, None) => {
+                return Err(BibliographyError::Fetch(format!(
+                    "{identifier} is not cached"
+                )))
+            }
+        };
+        let mut entry = parse_record(&identifier, &record)?;
+        entry.key = key.to_string();
+        Ok(entry)
+    }
+
+    /// Resolve the identifier keys `document` cites that `registry` lacks
+    pub fn fetch_document(&self, document: &Document, registry: &BibRegistry) -> FetchReport {
+        let mut report = FetchReport::default();
+        for key in cited_keys(document) {
+            if registry.get(&key).is_some() || Identifier::from_key(&key).is_none() {
+                continue;
+            }
+            match self.resolve(&key) {
+                Ok(entry) => report.entries.push(entry),
+                Err(err) => report.failed.push((key, err)),
+            }
+        }
+        report
+    }
+}
+
+fn parse_record(identifier: &Identifier, record: &str) -> Result<BibEntry, BibliographyError> {
+    parse_csl_json(record)?
+        .into_iter()
+        .next()
+        .ok_or_else(|| BibliographyError::Fetch(format!("empty record for {identifier}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+    use std::cell::Cell;
+
+    struct Source {
+        calls: Cell<usize>,
+    }
+
+    impl MetadataSource for Source {
+        fn fetch(&self, identifier: &Identifier) -> Result<String, BibliographyError> {
+            self.calls.set(self.calls.get() + 1);
+            match identifier {
+                Identifier::Doi(doi) => Ok(format!(
+                    r#"{{"id": "{doi}", "type": "article-journal", "title": "Fetched"}}"#
+                )),
+                Identifier::Isbn(_) => Err(BibliographyError::Fetch("not found".to_string())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_identifiers() {
+        assert_eq!(
+            Identifier::from_key("doi:10.1000/XYZ"),
+            Some(Identifier::Doi("10.1000/xyz".to_string()))
+        );
+        assert_eq!(
+            Identifier::from_key("https://doi.org/10.1000/xyz"),
+            Some(Identifier::Doi("10.1000/xyz".to_string()))
+        );
+        for url in [
+            "http://dx.doi.org/10.1000/xyz",
+            "https://dx.doi.org/10.1000/XYZ",
+            "doi:https://dx.doi.org/10.1000/xyz",
+        ] {
+            assert_eq!(
+                Identifier::from_key(url),
+                Some(Identifier::Doi("10.1000/xyz".to_string())),
+                "{url}"
+            );
+        }
+        assert_eq!(
+            Identifier::from_key("isbn:978-0-345-39180-3"),
+            Some(Identifier::Isbn("9780345391803".to_string()))
+        );
+        assert_eq!(Identifier::from_key("smith2020"), None);
+        assert_eq!(Identifier::from_key("isbn:123"), None);
+    }
+
+    #[test]
+    fn test_cache_names_are_distinct() {
+        let slash = Identifier::Doi("10.1000/a_b".to_string());
+        let underscore = Identifier::Doi("10.1000_a/b".to_string());
+        assert_eq!(slash.cache_name(), "doi-10.1000%2Fa%5Fb.json");
+        assert_ne!(slash.cache_name(), underscore.cache_name());
+    }
+
+    #[test]
+    fn test_fetch_caches_records() {
+        let dir = std::env::temp_dir().join(format!("lex-fetch-test-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let source = Source {
+            calls: Cell::new(0),
+        };
+        let doc = parse_document("Title\n\nSee [@doi:10.1000/xyz; @isbn:9780345391803; @smith].\n")
+            .unwrap();
+
+        let fetcher = Fetcher::new(MetadataCache::new(&dir), &source);
+        let report = fetcher.fetch_document(&doc, &BibRegistry::new());
+        assert_eq!(report.entries.len(), 1);
+        assert_eq!(report.entries[0].key, "doi:10.1000/xyz");
+        assert_eq!(report.entries[0].title, "Fetched");
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "isbn:9780345391803");
+
+        // A second run is served from the cache, also offline
+        fetcher.resolve("doi:10.1000/xyz").unwrap();
+        assert_eq!(source.calls.get(), 2);
+        let offline = Fetcher::offline(MetadataCache::new(&dir));
+        assert_eq!(offline.resolve("doi:10.1000/xyz").unwrap().title, "Fetched");
+        assert!(matches!(
+            offline.resolve("doi:10.1000/other"),
+            Err(BibliographyError::Fetch(_))
+        ));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,235 @@
+//! Bibliography registry
+//!
+//! Entries from any number of bibliography files, keyed by citation key. The first entry
+//! registered for a key wins; later ones are kept aside as duplicates and reported by
+//! [BibRegistry::diagnostics], together with keys a document cites but no file defines.
+
+use super::bibtex::parse_bibtex;
+use super::csl_json::parse_csl_json;
+use super::entry::BibEntry;
+use super::{cited_keys, BibliographyError};
+use crate::lex::ast::traits::AstNode;
+use crate::lex::ast::{Diagnostic, DiagnosticSeverity, Document, Position, Range};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Where an entry was read from
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct EntryOrigin {
+    /// File or source name
+    pub source: String,
+    /// Line (0-based) the entry starts on, when known
+    pub line: Option<usize>,
+}
+
+impl EntryOrigin {
+    fn describe(&self) -> String {
+        match self.line {
+            Some(line) => format!("{}:{}", self.source, line + 1),
+            None => self.source.clone(),
+        }
+    }
+}
+
+/// A registered entry and its origin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegisteredEntry {
+    pub entry: BibEntry,
+    pub origin: EntryOrigin,
+}
+
+/// Bibliography entries keyed by citation key
+#[derive(Debug, Clone, Default)]
+pub struct BibRegistry {
+    entries: Vec<RegisteredEntry>,
+    index: HashMap<String, usize>,
+    duplicates: Vec<RegisteredEntry>,
+}
+
+impl BibRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `entry`; returns false if its key was already taken
+    pub fn insert(&mut self, entry: BibEntry, origin: EntryOrigin) -> bool {
+        let registered = RegisteredEntry { entry, origin };
+        if self.index.contains_key(&registered.entry.key) {
+            self.duplicates.push(registered);
+            return false;
+        }
+        self.index
+            .insert(registered.entry.key.clone(), self.entries.len());
+        self.entries.push(registered);
+        true
+    }
+
+    /// Register the entries of a BibTeX source named `name`; returns how many were read
+    pub fn load_bibtex(&mut self, name: &str, source: &str) -> Result<usize, BibliographyError> {
+        let entries = parse_bibtex(source)?;
+        let count = entries.len();
+        for parsed in entries {
+            let origin = EntryOrigin {
+                source: name.to_string(),
+                line: Some(parsed.line),
+            };
+            self.insert(parsed.entry, origin);
+        }
+        Ok(count)
+    }
+
+    /// Register the entries of a CSL-JSON source named `name`; returns how many were read
+    pub fn load_csl_json(&mut self, name: &str, source: &str) -> Result<usize, BibliographyError> {
+        let entries = parse_csl_json(source)?;
+        let count = entries.len();
+        for entry in entries {
+            let origin = EntryOrigin {
+                source: name.to_string(),
+                line: None,
+            };
+            self.insert(entry, origin);
+        }
+        Ok(count)
+    }
+
+    /// Register the entries of a `.bib` or `.json` file
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<usize, BibliographyError> {
+        let path = path.as_ref();
+        let name = path.display().to_string();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+        let source =
+            std::fs::read_to_string(path).map_err(|err| BibliographyError::Io(err.to_string()))?;
+        match extension.as_deref() {
+            Some("bib") => self.load_bibtex(&name, &source),
+            Some("json") => self.load_csl_json(&name, &source),
+            _ => Err(BibliographyError::UnsupportedFormat(name)),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&BibEntry> {
+        self.index.get(key).map(|index| &self.entries[*index].entry)
+    }
+
+    pub fn origin(&self, key: &str) -> Option<&EntryOrigin> {
+        self.index
+            .get(key)
+            .map(|index| &self.entries[*index].origin)
+    }
+
+    /// Registered entries, in registration order
+    pub fn entries(&self) -> impl Iterator<Item = &BibEntry> {
+        self.entries.iter().map(|registered| &registered.entry)
+    }
+
+    /// Entries whose key was already taken when they were registered
+    pub fn duplicates(&self) -> &[RegisteredEntry] {
+        &self.duplicates
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Duplicate keys, and keys cited by `document` without an entry
+    ///
+    /// Missing key diagnostics span the document, as citations carry no location yet.
+    /// Duplicate key diagnostics point at the line of the duplicate in its bibliography file,
+    /// which the message names.
+    pub fn diagnostics(&self, document: &Document) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = self
+            .duplicates
+            .iter()
+            .map(|duplicate| {
+                let key = &duplicate.entry.key;
+                let first = self
+                    .origin(key)
+                    .map(EntryOrigin::describe)
+                    .unwrap_or_default();
+                let line = duplicate.origin.line.unwrap_or_default();
+                let position = Position::new(line, 0);
+                Diagnostic::new(
+                    Range::new(0..0, position, position),
+                    DiagnosticSeverity::Warning,
+                    format!(
+                        "Duplicate bibliography key '{key}' in {}, first defined in {first}",
+                        duplicate.origin.describe()
+                    ),
+                )
+                .with_code("duplicate-citation-key")
+            })
+            .collect();
+
+        diagnostics.extend(
+            cited_keys(document)
+                .into_iter()
+                .filter(|key| self.get(key).is_none())
+                .map(|key| {
+                    Diagnostic::new(
+                        document.root.range().clone(),
+                        DiagnosticSeverity::Warning,
+                        format!("Citation key '{key}' is not in the bibliography"),
+                    )
+                    .with_code("missing-citation-key")
+                }),
+        );
+        diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    #[test]
+    fn test_registry_and_diagnostics() {
+        let mut registry = BibRegistry::new();
+        let bib = "@book{adams, title = {Guide}}\n\n@book{smith, title = {Lines}}\n";
+        assert_eq!(registry.load_bibtex("refs.bib", bib).unwrap(), 2);
+        let json = r#"[{"id": "smith", "title": "Other"}, {"id": "jones", "title": "Columns"}]"#;
+        assert_eq!(registry.load_csl_json("more.json", json).unwrap(), 2);
+
+        assert_eq!(registry.len(), 3);
+        assert_eq!(registry.get("smith").unwrap().title, "Lines");
+        assert_eq!(registry.duplicates().len(), 1);
+
+        let doc = parse_document("Title\n\nSee [@smith; @nobody].\n").unwrap();
+        let diagnostics = registry.diagnostics(&doc);
+        let codes: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.code.as_deref().unwrap())
+            .collect();
+        assert_eq!(
+            codes,
+            vec!["duplicate-citation-key", "missing-citation-key"]
+        );
+        assert_eq!(
+            diagnostics[0].message,
+            "Duplicate bibliography key 'smith' in more.json, first defined in refs.bib:3"
+        );
+        assert!(diagnostics[1].message.contains("'nobody'"));
+    }
+
+    #[test]
+    fn test_load_file_rejects_unknown_formats() {
+        let mut registry = BibRegistry::new();
+        assert!(matches!(
+            registry.load_file("refs.txt"),
+            Err(BibliographyError::Io(_))
+        ));
+        let path = std::env::temp_dir().join("lex-bibliography-test.yaml");
+        std::fs::write(&path, "").unwrap();
+        assert!(matches!(
+            registry.load_file(&path),
+            Err(BibliographyError::UnsupportedFormat(_))
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,584 @@
+//! Citation styles
+//!
+//! A minimal subset of three common styles: APA (7th edition), IEEE and Chicago (author-date).
+//! Each style formats in-text citations and reference list entries. Output is Lex inline text,
+//! titles set in italics being wrapped in `_` so every output format renders them the same way.
+
+use super::entry::{BibEntry, EntryKind, Name};
+use crate::lex::ast::elements::inlines::{CitationData, CitationLocator, PageFormat};
+use std::fmt;
+
+/// Built-in citation styles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CitationStyle {
+    /// APA, author-date with parenthetical citations
+    #[default]
+    Apa,
+    /// IEEE, numbered in order of first citation
+    Ieee,
+    /// Chicago author-date
+    Chicago,
+}
+
+impl CitationStyle {
+    pub const ALL: [CitationStyle; 3] = [
+        CitationStyle::Apa,
+        CitationStyle::Ieee,
+        CitationStyle::Chicago,
+    ];
+
+    /// Style name, as used in configuration
+    pub fn name(&self) -> &'static str {
+        match self {
+            CitationStyle::Apa => "apa",
+            CitationStyle::Ieee => "ieee",
+            CitationStyle::Chicago => "chicago",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|style| style.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Whether references are numbered (and cited by number) rather than by author and year
+    pub fn is_numeric(&self) -> bool {
+        matches!(self, CitationStyle::Ieee)
+    }
+
+    /// Format a reference list entry, without any numbering
+    pub fn format_entry(&self, entry: &BibEntry) -> String {
+        match self {
+            CitationStyle::Apa => apa_entry(entry),
+            CitationStyle::Ieee => ieee_entry(entry),
+            CitationStyle::Chicago => chicago_entry(entry),
+        }
+    }
+
+    /// Author and year part of an author-date citation, such as `Smith & Jones, 2020`
+    fn author_date(&self, entry: &BibEntry) -> String {
+        let year = entry
+            .year
+            .map(|year| year.to_string())
+            .unwrap_or_else(|| "n.d.".to_string());
+        let (and, separator) = match self {
+            CitationStyle::Chicago => ("and", " "),
+            _ => ("&", ", "),
+        };
+        let authors = match entry.authors.as_slice() {
+            [] => format!("_{}_", entry.title),
+            [one] => one.family.clone(),
+            [first, second] => format!("{} {and} {}", first.family, second.family),
+            [first, second, third] if *self == CitationStyle::Chicago => {
+                format!("{}, {}, and {}", first.family, second.family, third.family)
+            }
+            [first, ..] => format!("{} et al.", first.family),
+        };
+        format!("{authors}{separator}{year}")
+    }
+
+    /// Locator as cited, such as `p. 4` (APA, IEEE) or `4` (Chicago)
+    fn locator(&self, locator: &CitationLocator) -> String {
+        let ranges: Vec<String> = locator
+            .ranges
+            .iter()
+            .map(|range| match range.end {
+                Some(end) => format!("{}–{end}", range.start),
+                None => range.start.to_string(),
+            })
+            .collect();
+        let pages = if ranges.is_empty() {
+            locator.raw.clone()
+        } else {
+            ranges.join(", ")
+        };
+        match (self, &locator.format) {
+            (CitationStyle::Chicago, _) => pages,
+            (_, PageFormat::P) => format!("p. {pages}"),
+            (_, PageFormat::Pp) => format!("pp. {pages}"),
+        }
+    }
+}
+
+impl fmt::Display for CitationStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The cited entries of a document, ordered and numbered as `style` requires
+///
+/// Numeric styles keep the order of first citation; author-date styles sort by first author,
+/// year and title.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bibliography<'a> {
+    pub style: CitationStyle,
+    entries: Vec<&'a BibEntry>,
+}
+
+impl<'a> Bibliography<'a> {
+    /// Bibliography of the entries cited by `cited_keys` (in order of first citation)
+    ///
+    /// Keys without an entry are left out; see the registry for missing key diagnostics.
+    pub fn new(
+        style: CitationStyle,
+        entries: impl IntoIterator<Item = &'a BibEntry>,
+        cited_keys: &[String],
+    ) -> Self {
+        let available: Vec<&BibEntry> = entries.into_iter().collect();
+        let mut cited: Vec<&BibEntry> = Vec::new();
+        for key in cited_keys {
+            let entry = available.iter().find(|entry| entry.key == *key);
+            if let Some(entry) = entry {
+                if !cited.iter().any(|known| known.key == entry.key) {
+                    cited.push(entry);
+                }
+            }
+        }
+        if !style.is_numeric() {
+            cited.sort_by_key(|entry| entry.sort_key());
+        }
+        Self {
+            style,
+            entries: cited,
+        }
+    }
+
+    /// Entries in reference list order
+    pub fn entries(&self) -> &[&'a BibEntry] {
+        &self.entries
+    }
+
+    /// 1-based number of the entry for `key`, in reference list order
+    pub fn number(&self, key: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|entry| entry.key == key)
+            .map(|index| index + 1)
+    }
+
+    /// In-text citation for `citation`; keys without an entry are shown as `key?`
+    pub fn cite(&self, citation: &CitationData) -> String {
+        let locator = citation
+            .locator
+            .as_ref()
+            .map(|locator| self.style.locator(locator));
+        let entry = |key: &str| self.entries.iter().find(|entry| entry.key == key);
+
+        if self.style.is_numeric() {
+            let numbers: Vec<String> = citation
+                .keys
+                .iter()
+                .map(|key| match self.number(key) {
+                    Some(number) => number.to_string(),
+                    None => format!("{key}?"),
+                })
+                .collect();
+            return match locator {
+                Some(locator) => format!("[{}, {locator}]", numbers.join("], [")),
+                None => format!("[{}]", numbers.join("], [")),
+            };
+        }
+
+        let mut parts: Vec<String> = citation
+            .keys
+            .iter()
+            .map(|key| match entry(key) {
+                Some(entry) => self.style.author_date(entry),
+                None => format!("{key}?"),
+            })
+            .collect();
+        if let (Some(locator), Some(last)) = (locator, parts.last_mut()) {
+            last.push_str(", ");
+            last.push_str(&locator);
+        }
+        format!("({})", parts.join("; "))
+    }
+
+    /// Formatted reference list, numbered for numeric styles
+    pub fn render(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .enumerate()
+            .map(|(index, entry)| {
+                let text = self.style.format_entry(entry);
+                if self.style.is_numeric() {
+                    format!("[{}] {text}", index + 1)
+                } else {
+                    text
+                }
+            })
+            .collect()
+    }
+}
+
+/// Join `items` as `a, b, and c`, with `and` as the final conjunction
+fn join_names(items: &[String], and: &str, serial_comma: bool) -> String {
+    match items {
+        [] => String::new(),
+        [one] => one.clone(),
+        [first, second] if !serial_comma => format!("{first} {and} {second}"),
+        [rest @ .., last] => format!("{}, {and} {last}", rest.join(", ")),
+    }
+}
+
+/// `Family, G. I.`
+fn inverted_initials(name: &Name) -> String {
+    match name.initials() {
+        Some(initials) => format!("{}, {initials}", name.family),
+        None => name.family.clone(),
+    }
+}
+
+/// `G. I. Family`
+fn initials_first(name: &Name) -> String {
+    match name.initials() {
+        Some(initials) => format!("{initials} {}", name.family),
+        None => name.family.clone(),
+    }
+}
+
+/// `Given Family`
+fn given_first(name: &Name) -> String {
+    match &name.given {
+        Some(given) => format!("{given} {}", name.family),
+        None => name.family.clone(),
+    }
+}
+
+/// Append `text` followed by a period, unless it already ends in punctuation
+fn sentence(out: &mut String, text: &str) {
+    if !out.is_empty() {
+        out.push(' ');
+    }
+    out.push_str(text);
+    if !text.ends_with(['.', '?', '!']) {
+        out.push('.');
+    }
+}
+
+fn doi_url(entry: &BibEntry) -> Option<String> {
+    entry
+        .doi
+        .as_ref()
+        .map(|doi| format!("https://doi.org/{doi}"))
+        .or_else(|| entry.url.clone())
+}
+
+fn apa_entry(entry: &BibEntry) -> String {
+    let mut out = String::new();
+    let authors: Vec<String> = entry.authors.iter().map(inverted_initials).collect();
+    let year = entry
+        .year
+        .map(|year| year.to_string())
+        .unwrap_or_else(|| "n.d.".to_string());
+    let italic_title = !matches!(
+        entry.kind,
+        EntryKind::Article | EntryKind::Chapter | EntryKind::Conference
+    );
+    let title = if italic_title {
+        format!("_{}_", entry.title)
+    } else {
+        entry.title.clone()
+    };
+
+    if authors.is_empty() {
+        sentence(&mut out, &title);
+        out.push_str(&format!(" ({year})."));
+    } else {
+        let names = match authors.as_slice() {
+            [one] => one.clone(),
+            [rest @ .., last] => format!("{}, & {last}", rest.join(", ")),
+            [] => unreachable!(),
+        };
+        sentence(&mut out, &names);
+        out.push_str(&format!(" ({year})."));
+        sentence(&mut out, &title);
+    }
+
+    match entry.kind {
+        EntryKind::Article => {
+            if let Some(journal) = &entry.container_title {
+                let mut source = format!("_{journal}_");
+                if let Some(volume) = &entry.volume {
+                    source.push_str(&format!(", _{volume}_"));
+                }
+                if let Some(issue) = &entry.issue {
+                    source.push_str(&format!("({issue})"));
+                }
+                if let Some(pages) = &entry.pages {
+                    source.push_str(&format!(", {pages}"));
+                }
+                sentence(&mut out, &source);
+            }
+        }
+        EntryKind::Chapter | EntryKind::Conference => {
+            if let Some(container) = &entry.container_title {
+                let mut source = String::from("In ");
+                if !entry.editors.is_empty() {
+                    let editors: Vec<String> = entry.editors.iter().map(initials_first).collect();
+                    let role = if editors.len() == 1 { "Ed." } else { "Eds." };
+                    source.push_str(&format!("{} ({role}), ", join_names(&editors, "&", false)));
+                }
+                source.push_str(&format!("_{container}_"));
+                if let Some(pages) = &entry.pages {
+                    source.push_str(&format!(" (pp. {pages})"));
+                }
+                sentence(&mut out, &source);
+            }
+            if let Some(publisher) = &entry.publisher {
+                sentence(&mut out, publisher);
+            }
+        }
+        _ => {
+            if let Some(publisher) = &entry.publisher {
+                sentence(&mut out, publisher);
+            }
+        }
+    }
+
+    if let Some(link) = doi_url(entry) {
+        out.push(' ');
+        out.push_str(&link);
+    }
+    out
+}
+
+fn ieee_entry(entry: &BibEntry) -> String {
+    let authors: Vec<String> = if entry.authors.len() > 6 {
+        vec![format!("{} et al.", initials_first(&entry.authors[0]))]
+    } else {
+        entry.authors.iter().map(initials_first).collect()
+    };
+    let mut parts: Vec<String> = Vec::new();
+    if !authors.is_empty() {
+        parts.push(join_names(&authors, "and", authors.len() > 2));
+    }
+
+    let year = entry.year.map(|year| year.to_string());
+    let mut out;
+    match entry.kind {
+        EntryKind::Book | EntryKind::Report | EntryKind::Thesis => {
+            parts.push(format!("_{}_", entry.title));
+            out = parts.join(", ");
+            let mut imprint = Vec::new();
+            match (&entry.publisher_place, &entry.publisher) {
+                (Some(place), Some(publisher)) => imprint.push(format!("{place}: {publisher}")),
+                (None, Some(publisher)) => imprint.push(publisher.clone()),
+                _ => {}
+            }
+            imprint.extend(year);
+            out.push('.');
+            if !imprint.is_empty() {
+                out.push_str(&format!(" {}.", imprint.join(", ")));
+            }
+        }
+        _ => {
+            parts.push(format!("\"{},\"", entry.title));
+            out = parts.join(", ");
+            let mut details = Vec::new();
+            if let Some(container) = &entry.container_title {
+                let prefix = if entry.kind == EntryKind::Article {
+                    ""
+                } else {
+                    "in "
+                };
+                details.push(format!("{prefix}_{container}_"));
+            }
+            if let Some(volume) = &entry.volume {
+                details.push(format!("vol. {volume}"));
+            }
+            if let Some(issue) = &entry.issue {
+                details.push(format!("no. {issue}"));
+            }
+            if let Some(pages) = &entry.pages {
+                details.push(format!("pp. {pages}"));
+            }
+            details.extend(year);
+            if let Some(doi) = &entry.doi {
+                details.push(format!("doi: {doi}"));
+            }
+            if !details.is_empty() {
+                out.push(' ');
+                out.push_str(&details.join(", "));
+            } else {
+                // Drop the comma closing the title
+                out = out.replacen(",\"", "\"", 1);
+            }
+            out.push('.');
+            if entry.doi.is_none() {
+                if let Some(url) = &entry.url {
+                    out.push_str(&format!(" [Online]. Available: {url}"));
+                }
+            }
+        }
+    }
+    out
+}
+
+fn chicago_entry(entry: &BibEntry) -> String {
+    let mut out = String::new();
+    let authors: Vec<String> = entry
+        .authors
+        .iter()
+        .enumerate()
+        .map(|(index, name)| match (index, &name.given) {
+            (0, Some(given)) => format!("{}, {given}", name.family),
+            _ => given_first(name),
+        })
+        .collect();
+    if !authors.is_empty() {
+        sentence(&mut out, &join_names(&authors, "and", true));
+    }
+    let year = entry
+        .year
+        .map(|year| year.to_string())
+        .unwrap_or_else(|| "n.d.".to_string());
+    sentence(&mut out, &year);
+
+    match entry.kind {
+        EntryKind::Article => {
+            out.push_str(&format!(" \"{}.\"", entry.title.trim_end_matches('.')));
+            if let Some(journal) = &entry.container_title {
+                let mut source = format!("_{journal}_");
+                if let Some(volume) = &entry.volume {
+                    source.push_str(&format!(" {volume}"));
+                }
+                if let Some(issue) = &entry.issue {
+                    source.push_str(&format!(" ({issue})"));
+                }
+                if let Some(pages) = &entry.pages {
+                    source.push_str(&format!(": {pages}"));
+                }
+                sentence(&mut out, &source);
+            }
+        }
+        EntryKind::Chapter | EntryKind::Conference => {
+            out.push_str(&format!(" \"{}.\"", entry.title.trim_end_matches('.')));
+            if let Some(container) = &entry.container_title {
+                let mut source = format!("In _{container}_");
+                if !entry.editors.is_empty() {
+                    let editors: Vec<String> = entry.editors.iter().map(given_first).collect();
+                    source.push_str(&format!(
+                        ", edited by {}",
+                        join_names(&editors, "and", true)
+                    ));
+                }
+                if let Some(pages) = &entry.pages {
+                    source.push_str(&format!(", {pages}"));
+                }
+                sentence(&mut out, &source);
+            }
+        }
+        _ => sentence(&mut out, &format!("_{}_", entry.title)),
+    }
+
+    match (&entry.publisher_place, &entry.publisher) {
+        (Some(place), Some(publisher)) => sentence(&mut out, &format!("{place}: {publisher}")),
+        (None, Some(publisher)) => sentence(&mut out, publisher),
+        _ => {}
+    }
+    if let Some(link) = doi_url(entry) {
+        sentence(&mut out, &link);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::ast::elements::inlines::PageRange;
+
+    fn article() -> BibEntry {
+        BibEntry {
+            authors: vec![Name::new("Smith", "John Ronald"), Name::new("Jones", "Ann")],
+            year: Some(2020),
+            container_title: Some("Journal of Plain Text".to_string()),
+            volume: Some("12".to_string()),
+            issue: Some("3".to_string()),
+            pages: Some("45-67".to_string()),
+            doi: Some("10.1000/xyz".to_string()),
+            ..BibEntry::new("smith2020", EntryKind::Article, "Writing in lines")
+        }
+    }
+
+    fn book() -> BibEntry {
+        BibEntry {
+            authors: vec![Name::new("Adams", "Douglas")],
+            year: Some(1979),
+            publisher: Some("Pan Books".to_string()),
+            publisher_place: Some("London".to_string()),
+            ..BibEntry::new("adams1979", EntryKind::Book, "The hitchhiker's guide")
+        }
+    }
+
+    #[test]
+    fn test_apa_entries() {
+        assert_eq!(
+            CitationStyle::Apa.format_entry(&article()),
+            "Smith, J. R., & Jones, A. (2020). Writing in lines. _Journal of Plain Text_, _12_(3), 45-67. https://doi.org/10.1000/xyz"
+        );
+        assert_eq!(
+            CitationStyle::Apa.format_entry(&book()),
+            "Adams, D. (1979). _The hitchhiker's guide_. Pan Books."
+        );
+    }
+
+    #[test]
+    fn test_ieee_entries() {
+        assert_eq!(
+            CitationStyle::Ieee.format_entry(&article()),
+            "J. R. Smith and A. Jones, \"Writing in lines,\" _Journal of Plain Text_, vol. 12, no. 3, pp. 45-67, 2020, doi: 10.1000/xyz."
+        );
+        assert_eq!(
+            CitationStyle::Ieee.format_entry(&book()),
+            "D. Adams, _The hitchhiker's guide_. London: Pan Books, 1979."
+        );
+    }
+
+    #[test]
+    fn test_chicago_entries() {
+        assert_eq!(
+            CitationStyle::Chicago.format_entry(&article()),
+            "Smith, John Ronald, and Ann Jones. 2020. \"Writing in lines.\" _Journal of Plain Text_ 12 (3): 45-67. https://doi.org/10.1000/xyz."
+        );
+        assert_eq!(
+            CitationStyle::Chicago.format_entry(&book()),
+            "Adams, Douglas. 1979. _The hitchhiker's guide_. London: Pan Books."
+        );
+    }
+
+    #[test]
+    fn test_bibliography_order_and_citations() {
+        let entries = [article(), book()];
+        let cited = vec!["smith2020".to_string(), "adams1979".to_string()];
+        let citation = CitationData {
+            keys: vec!["smith2020".to_string(), "unknown".to_string()],
+            locator: Some(CitationLocator {
+                format: PageFormat::P,
+                ranges: vec![PageRange {
+                    start: 4,
+                    end: None,
+                }],
+                raw: "p.4".to_string(),
+            }),
+        };
+
+        let apa = Bibliography::new(CitationStyle::Apa, &entries, &cited);
+        assert_eq!(apa.entries()[0].key, "adams1979");
+        assert_eq!(apa.cite(&citation), "(Smith & Jones, 2020; unknown?, p. 4)");
+
+        let ieee = Bibliography::new(CitationStyle::Ieee, &entries, &cited);
+        assert_eq!(ieee.number("adams1979"), Some(2));
+        assert_eq!(ieee.cite(&citation), "[1], [unknown?, p. 4]");
+        assert!(ieee.render()[1].starts_with("[2] D. Adams"));
+
+        let chicago = Bibliography::new(CitationStyle::Chicago, &entries, &cited[..1]);
+        assert_eq!(chicago.entries().len(), 1);
+        assert_eq!(
+            chicago.cite(&citation),
+            "(Smith and Jones 2020; unknown?, 4)"
+        );
+    }
+}
@@ -0,0 +1,238 @@
+//! Diagram blocks
+//!
+//!     Verbatim blocks labeled `mermaid`, `graphviz` (or `dot`) and `plantuml` (or `puml`)
+//!     hold diagram sources:
+//!
+//!         Request flow:
+//!             graph LR
+//!                 client --> server
+//!         :: mermaid
+//!
+//!     A [DiagramRenderer] is a [block extension](crate::lex::extensions) that pipes such a
+//!     block to a local tool (`mmdc`, `dot`, `plantuml`) and renders it as SVG in the
+//!     formats in [SVG_FORMATS]; Markdown takes the SVG as inline HTML. Rendering runs
+//!     programs, so it is opt-in: [register_diagram_renderers] adds the renderers whose
+//!     tool is installed to a format registry's extensions, and only registries they were
+//!     added to render diagrams:
+//!
+//!         let mut formats = FormatRegistry::with_defaults();
+//!         register_diagram_renderers(formats.extensions_mut());
+//!         let markdown = formats.serialize(&doc, "markdown")?;
+//!
+//!     When the tool is missing or fails, the renderer declines and the format writes the
+//!     plain code block.
+
+use crate::lex::ast::Verbatim;
+use crate::lex::exec::pipe_through;
+use crate::lex::extensions::{BlockExtension, ExtensionBlock, ExtensionRegistry};
+use crate::lex::literate::code_block;
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// Formats a rendered diagram is written to as SVG
+pub const SVG_FORMATS: [&str; 1] = ["markdown"];
+
+/// Diagram language of a verbatim block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagramKind {
+    Mermaid,
+    Graphviz,
+    PlantUml,
+}
+
+impl DiagramKind {
+    pub const ALL: [DiagramKind; 3] = [
+        DiagramKind::Mermaid,
+        DiagramKind::Graphviz,
+        DiagramKind::PlantUml,
+    ];
+
+    /// Canonical verbatim label
+    pub fn label(&self) -> &'static str {
+        match self {
+            DiagramKind::Mermaid => "mermaid",
+            DiagramKind::Graphviz => "graphviz",
+            DiagramKind::PlantUml => "plantuml",
+        }
+    }
+
+    /// Kind named by a verbatim label, case-insensitively; `dot` and `puml` are accepted
+    /// as aliases
+    pub fn from_label(label: &str) -> Option<Self> {
+        let label = label.trim().to_lowercase();
+        match label.as_str() {
+            "dot" => Some(DiagramKind::Graphviz),
+            "puml" => Some(DiagramKind::PlantUml),
+            _ => Self::ALL.into_iter().find(|kind| kind.label() == label),
+        }
+    }
+
+    /// Program and arguments turning the source on stdin into SVG on stdout
+    pub fn default_command(&self) -> (&'static str, &'static [&'static str]) {
+        match self {
+            DiagramKind::Mermaid => ("mmdc", &["-i", "-", "-o", "-", "-e", "svg"]),
+            DiagramKind::Graphviz => ("dot", &["-Tsvg"]),
+            DiagramKind::PlantUml => ("plantuml", &["-tsvg", "-pipe"]),
+        }
+    }
+}
+
+/// Renders diagram blocks of one kind with a local program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagramRenderer {
+    pub kind: DiagramKind,
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl DiagramRenderer {
+    /// Renderer running the kind's usual tool
+    pub fn new(kind: DiagramKind) -> Self {
+        let (program, args) = kind.default_command();
+        Self::with_command(kind, program, args)
+    }
+
+    /// Renderer running `program` with `args` instead
+    pub fn with_command(kind: DiagramKind, program: &str, args: &[&str]) -> Self {
+        Self {
+            kind,
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+
+    /// Whether the program is installed: a path to a file, or found on `PATH`
+    pub fn is_available(&self) -> bool {
+        let program = Path::new(&self.program);
+        if program.components().count() > 1 {
+            return program.is_file();
+        }
+        std::env::var_os("PATH").is_some_and(|paths| {
+            std::env::split_paths(&paths).any(|dir| {
+                dir.join(program).is_file() || dir.join(format!("{}.exe", self.program)).is_file()
+            })
+        })
+    }
+
+    /// SVG of the diagram `source`
+    pub fn render_svg(&self, source: &str) -> Result<String, String> {
+        let output = pipe_through(&self.program, &self.args, source)?;
+        match output.find("<svg") {
+            Some(start) => Ok(output[start..].trim_end().to_string()),
+            None => Err(format!("'{}' wrote no SVG", self.program)),
+        }
+    }
+}
+
+impl BlockExtension for DiagramRenderer {
+    fn name(&self) -> &str {
+        self.kind.label()
+    }
+
+    fn recognizes(&self, block: &Verbatim) -> bool {
+        DiagramKind::from_label(&block.closing_data.label.value) == Some(self.kind)
+    }
+
+    fn build(&self, block: &Verbatim) -> Result<Value, String> {
+        Ok(json!({ "source": code_block(block).text }))
+    }
+
+    fn render(&self, block: &ExtensionBlock, format: &str) -> Option<String> {
+        if !SVG_FORMATS.contains(&format) {
+            return None;
+        }
+        let source = block.data.get("source")?.as_str()?;
+        self.render_svg(source).ok()
+    }
+
+    fn description(&self) -> &str {
+        "Diagram rendered to SVG by a local tool"
+    }
+}
+
+/// Register a renderer for each diagram kind whose tool is installed; returns the kinds
+/// registered
+pub fn register_diagram_renderers(registry: &mut ExtensionRegistry) -> Vec<DiagramKind> {
+    let mut registered = Vec::new();
+    for kind in DiagramKind::ALL {
+        let renderer = DiagramRenderer::new(kind);
+        if renderer.is_available() {
+            registry.register(renderer);
+            registered.push(kind);
+        }
+    }
+    registered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::formats::FormatRegistry;
+    use crate::lex::parsing::parse_document;
+
+    const SOURCE: &str = "Design\n\nFlow:\n    digraph { a -> b }\n:: dot\n\nSequence:\n    Alice -> Bob\n:: plantuml\n";
+
+    #[test]
+    fn test_kinds() {
+        assert_eq!(
+            DiagramKind::from_label("Mermaid"),
+            Some(DiagramKind::Mermaid)
+        );
+        assert_eq!(DiagramKind::from_label("dot"), Some(DiagramKind::Graphviz));
+        assert_eq!(DiagramKind::from_label("python"), None);
+        assert!(
+            !DiagramRenderer::with_command(DiagramKind::Mermaid, "no-such-tool", &[])
+                .is_available()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_render() {
+        let doc = parse_document(SOURCE).unwrap();
+        let mut registry = ExtensionRegistry::new();
+        registry.register(DiagramRenderer::with_command(
+            DiagramKind::Graphviz,
+            "sh",
+            &["-c", "printf '<?xml?>\\n<svg>'; cat; printf '</svg>\\n'"],
+        ));
+        registry.register(DiagramRenderer::with_command(
+            DiagramKind::PlantUml,
+            "false",
+            &[],
+        ));
+
+        let output = registry.apply(&doc);
+        assert_eq!(output.blocks.len(), 2);
+        assert_eq!(
+            registry.render(&output.blocks[0], "markdown").as_deref(),
+            Some("<svg>digraph { a -> b }\n</svg>")
+        );
+        assert_eq!(registry.render(&output.blocks[0], "latex"), None);
+        // A failing tool falls back to the code block
+        assert_eq!(registry.render(&output.blocks[1], "markdown"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_render_through_format_registry() {
+        let doc = parse_document(SOURCE).unwrap();
+        let mut formats = FormatRegistry::with_defaults();
+        formats
+            .extensions_mut()
+            .register(DiagramRenderer::with_command(
+                DiagramKind::Graphviz,
+                "sh",
+                &["-c", "printf '<svg>'; cat; printf '</svg>\n'"],
+            ));
+
+        let markdown = formats.serialize(&doc, "markdown").unwrap();
+        assert!(markdown.contains("<svg>digraph { a -> b }\n</svg>"));
+        assert!(!markdown.contains("```dot"));
+        // Blocks without a renderer stay code blocks
+        assert!(markdown.contains("```plantuml\nAlice -> Bob\n```"));
+        // Formats without extension support write the code block
+        let latex = formats.serialize(&doc, "latex").unwrap();
+        assert!(!latex.contains("<svg>"));
+    }
+}
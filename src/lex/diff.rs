@@ -0,0 +1,117 @@
+//! Diff blocks
+//!
+//! Verbatim blocks labeled `diff` (or `patch`) hold unified diffs, as found in change logs
+//! and code reviews:
+//!
+//!     Rename the flag:
+//!         --- a/src/main.rs
+//!         +++ b/src/main.rs
+//!         @@ -1,3 +1,3 @@
+//!          fn main() {
+//!         -    let verbose = true;
+//!         +    let quiet = false;
+//!          }
+//!     :: diff
+//!
+//! Renderers color their lines by [DiffLine] kind: added lines, removed lines, hunk headers
+//! and file headers. [diff_block] writes such a block from two revisions of a file.
+
+use crate::lex::formatting::unified_diff;
+
+/// Verbatim labels of diff blocks
+pub const DIFF_LANGUAGES: [&str; 2] = ["diff", "patch"];
+
+/// Whether a verbatim block labeled `language` holds a diff
+pub fn is_diff(language: &str) -> bool {
+    DIFF_LANGUAGES.contains(&language.trim().to_lowercase().as_str())
+}
+
+/// Kind of a line of a unified diff
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffLine {
+    /// `---`, `+++` and `diff` lines naming the files
+    Header,
+    /// `@@ -1,3 +1,3 @@`
+    Hunk,
+    Added,
+    Removed,
+    /// Unchanged lines, and anything else
+    Context,
+}
+
+impl DiffLine {
+    pub fn classify(line: &str) -> Self {
+        if line.starts_with("+++") || line.starts_with("---") || line.starts_with("diff ") {
+            DiffLine::Header
+        } else if line.starts_with("@@") {
+            DiffLine::Hunk
+        } else if line.starts_with('+') {
+            DiffLine::Added
+        } else if line.starts_with('-') {
+            DiffLine::Removed
+        } else {
+            DiffLine::Context
+        }
+    }
+}
+
+/// Lex source of a diff block titled `subject`, turning `original` into `updated`
+///
+/// `path` names the file in the diff headers. Equal texts give a block with no lines.
+pub fn diff_block(subject: &str, path: &str, original: &str, updated: &str) -> String {
+    let subject = subject.trim().trim_end_matches(':');
+    let mut out = format!("{subject}:\n");
+    for line in unified_diff(path, original, updated).lines() {
+        out.push_str(&format!("    {line}\n").replace("    \n", "\n"));
+    }
+    out.push_str(":: diff\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::ast::ContentItem;
+    use crate::lex::literate::code_block;
+    use crate::lex::parsing::parse_document;
+
+    #[test]
+    fn test_diff_block() {
+        let block = diff_block(
+            "Rename the flag",
+            "main.rs",
+            "fn main() {\n    let verbose = true;\n}\n",
+            "fn main() {\n    let quiet = false;\n}\n",
+        );
+        assert_eq!(
+            block,
+            "Rename the flag:\n    --- a/main.rs\n    +++ b/main.rs\n    @@ -1,3 +1,3 @@\n     fn main() {\n    -    let verbose = true;\n    +    let quiet = false;\n     }\n:: diff\n"
+        );
+
+        let doc = parse_document(&format!("Changes\n\n{block}")).unwrap();
+        let verbatim = doc
+            .root
+            .children
+            .iter()
+            .find_map(|item| match item {
+                ContentItem::VerbatimBlock(verbatim) => Some(verbatim),
+                _ => None,
+            })
+            .unwrap();
+        let code = code_block(verbatim);
+        assert!(is_diff(&code.language));
+        let kinds: Vec<DiffLine> = code.text.lines().map(DiffLine::classify).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                DiffLine::Header,
+                DiffLine::Header,
+                DiffLine::Hunk,
+                DiffLine::Context,
+                DiffLine::Removed,
+                DiffLine::Added,
+                DiffLine::Context,
+            ]
+        );
+    }
+}
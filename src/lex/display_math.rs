@@ -0,0 +1,164 @@
+//! Display math
+//!
+//! Inline math (`#x^2#`) sits in running text. Equations set on their own line are verbatim
+//! blocks labeled `math`, written in AsciiMath like math inlines are:
+//!
+//!     The energy of a body at rest:
+//!         E = m c^2
+//!     :: math id=energy
+//!
+//! Equations are numbered in document order. One with an `id` parameter can be referenced as
+//! `[#energy]`, the way sessions with an id are. Output formats map them to their display
+//! math: `$$...$$` in Markdown, an `equation` environment in LaTeX, block math in Typst, and
+//! block MathML in HTML.
+//! The block's lines are a single equation; its subject is a caption, not part of the math.
+
+use crate::lex::ast::{ContentItem, Document, Verbatim};
+use crate::lex::formats::latex::asciimath;
+use crate::lex::formats::typst::asciimath as typst_math;
+use crate::lex::literate::code_block;
+
+/// Label of verbatim blocks holding display math
+pub const MATH_LABEL: &str = "math";
+
+/// Whether a verbatim block labeled `language` holds display math
+pub fn is_math(language: &str) -> bool {
+    language.trim().eq_ignore_ascii_case(MATH_LABEL)
+}
+
+/// A display math block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Equation {
+    /// Position in document order, from 1
+    pub number: usize,
+    /// Value of the `id` parameter
+    pub id: Option<String>,
+    /// The block's subject
+    pub caption: String,
+    /// AsciiMath source, the block's lines joined by spaces
+    pub source: String,
+}
+
+impl Equation {
+    /// Equation of `verbatim`, numbered `number`, if it is a math block
+    pub fn from_verbatim(verbatim: &Verbatim, number: usize) -> Option<Self> {
+        let block = code_block(verbatim);
+        if !is_math(&block.language) {
+            return None;
+        }
+        let source: Vec<&str> = block
+            .text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        Some(Equation {
+            number,
+            id: verbatim
+                .parameter("id")
+                .map(|id| id.trim_matches('"').to_string())
+                .filter(|id| !id.is_empty()),
+            caption: block.subject.trim().trim_end_matches(':').to_string(),
+            source: source.join(" "),
+        })
+    }
+
+    /// The equation as a LaTeX `equation` environment, labeled with its id
+    pub fn to_latex(&self) -> String {
+        let mut out = format!(
+            "\\begin{{equation}}\n{}\n",
+            asciimath::to_latex(&self.source)
+        );
+        if let Some(id) = &self.id {
+            out.push_str(&format!("\\label{{{id}}}\n"));
+        }
+        out.push_str("\\end{equation}");
+        out
+    }
+
+    /// The equation as a Markdown `$$` block, in LaTeX notation
+    pub fn to_markdown(&self) -> String {
+        format!("$$\n{}\n$$", asciimath::to_latex(&self.source))
+    }
+
+    /// The equation as Typst block math, labeled with its id
+    pub fn to_typst(&self) -> String {
+        let math = format!("$ {} $", typst_math::to_typst(&self.source));
+        match &self.id {
+            Some(id) => format!("{math} <{id}>"),
+            None => math,
+        }
+    }
+
+    /// The equation as block MathML (`<math display="block">`)
+    #[cfg(feature = "math")]
+    pub fn to_mathml(&self) -> String {
+        polymath_rs::to_math_ml(&self.source)
+    }
+}
+
+/// Display math blocks of `doc`, numbered in document order
+pub fn equations(doc: &Document) -> Vec<Equation> {
+    let mut found = Vec::new();
+    for item in doc.root.iter_all_nodes() {
+        if let ContentItem::VerbatimBlock(verbatim) = item {
+            if let Some(equation) = Equation::from_verbatim(verbatim, found.len() + 1) {
+                found.push(equation);
+            }
+        }
+    }
+    found
+}
+
+/// The equation `[#id]` points at, if any
+pub fn find_equation<'a>(equations: &'a [Equation], id: &str) -> Option<&'a Equation> {
+    equations
+        .iter()
+        .find(|equation| equation.id.as_deref() == Some(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    const SOURCE: &str = "Physics\n\nRest energy:\n    E = m c^2\n:: math id=energy\n\nSample:\n    print(1)\n:: python\n\nPythagoras:\n    a^2 + b^2\n    = c^2\n:: math\n";
+
+    #[test]
+    fn test_equations() {
+        let doc = parse_document(SOURCE).unwrap();
+        let found = equations(&doc);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].number, 1);
+        assert_eq!(found[0].id.as_deref(), Some("energy"));
+        assert_eq!(found[0].caption, "Rest energy");
+        assert_eq!(found[0].source, "E = m c^2");
+        assert_eq!(found[1].number, 2);
+        assert_eq!(found[1].id, None);
+        assert_eq!(found[1].source, "a^2 + b^2 = c^2");
+        assert_eq!(find_equation(&found, "energy").unwrap().number, 1);
+        assert!(find_equation(&found, "mass").is_none());
+    }
+
+    #[test]
+    fn test_output() {
+        let doc = parse_document(SOURCE).unwrap();
+        let energy = &equations(&doc)[0];
+        assert_eq!(
+            energy.to_latex(),
+            "\\begin{equation}\nE=mc^{2}\n\\label{energy}\n\\end{equation}"
+        );
+        assert_eq!(energy.to_markdown(), "$$\nE=mc^{2}\n$$");
+        assert_eq!(energy.to_typst(), "$ E=m c^2 $ <energy>");
+    }
+
+    #[cfg(feature = "math")]
+    #[test]
+    fn test_mathml() {
+        let doc = parse_document(SOURCE).unwrap();
+        let energy = &equations(&doc)[0];
+        let mathml = energy.to_mathml();
+        assert!(mathml.starts_with("<math display=\"block\""));
+        assert!(mathml.contains("<msup>"));
+    }
+}
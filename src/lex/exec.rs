@@ -0,0 +1,294 @@
+//! Executable blocks
+//!
+//!     A verbatim block annotated with `:: exec <interpreter> ::` is a program whose output
+//!     belongs in the document, such as a generated table or figure in a report:
+//!
+//!         :: exec sh ::
+//!         Disk usage:
+//!             du -sh data/*
+//!         :: shell
+//!
+//!     [preprocess] runs each such block and rewrites the source before conversion. With
+//!     `mode=append` (the default) the output is inserted after the block; with
+//!     `mode=replace` it takes the place of the annotation and the block. Output is inserted
+//!     as Lex source at the block's indentation, so a block can generate any Lex content.
+//!
+//!     Running code from a document is never implicit: unless [ExecOptions::allow] is set,
+//!     preprocessing fails with [ExecError::NotAllowed] when the document has exec blocks.
+
+use crate::lex::ast::{ContentItem, Document, Verbatim};
+use crate::lex::literate::code_block;
+use std::fmt;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Annotation label marking an executable block
+pub const EXEC_LABEL: &str = "exec";
+
+/// Where a block's output goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecMode {
+    /// After the block, which is kept
+    #[default]
+    Append,
+    /// In place of the block and its annotation
+    Replace,
+}
+
+impl ExecMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim_matches('"') {
+            "append" => Some(ExecMode::Append),
+            "replace" => Some(ExecMode::Replace),
+            _ => None,
+        }
+    }
+}
+
+/// A verbatim block to execute
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecBlock {
+    /// Program the code is piped to, such as `sh` or `python3`
+    pub interpreter: String,
+    /// Content, with indentation relative to the block's wall
+    pub code: String,
+    pub mode: ExecMode,
+    /// First line (0-based) of the `exec` annotation
+    pub start_line: usize,
+    /// Line (0-based) of the block's closing label
+    pub end_line: usize,
+    /// Column of the block's subject
+    pub indent: usize,
+}
+
+/// Whether and how exec blocks run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecOptions {
+    /// Allow running code; off by default
+    pub allow: bool,
+}
+
+/// Error that can occur while preprocessing exec blocks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExecError {
+    /// The document has exec blocks but running code was not allowed
+    NotAllowed { count: usize },
+    /// A block named no interpreter or an unknown mode
+    Invalid { line: usize, message: String },
+    /// A block failed to run
+    Failed { line: usize, message: String },
+}
+
+impl fmt::Display for ExecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecError::NotAllowed { count } => write!(
+                f,
+                "Document has {count} exec block(s); running them must be explicitly allowed"
+            ),
+            ExecError::Invalid { line, message } => {
+                write!(f, "Invalid exec block at line {}: {message}", line + 1)
+            }
+            ExecError::Failed { line, message } => {
+                write!(f, "Exec block at line {} failed: {message}", line + 1)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ExecError {}
+
+/// Exec blocks of `document`, in document order
+pub fn exec_blocks(document: &Document) -> Result<Vec<ExecBlock>, ExecError> {
+    let mut blocks = Vec::new();
+    for item in document.root.iter_all_nodes() {
+        let ContentItem::VerbatimBlock(verbatim) = item else {
+            continue;
+        };
+        if let Some(block) = exec_block(verbatim)? {
+            blocks.push(block);
+        }
+    }
+    Ok(blocks)
+}
+
+fn exec_block(verbatim: &Verbatim) -> Result<Option<ExecBlock>, ExecError> {
+    let Some(annotation) = verbatim.annotations.iter().find(|annotation| {
+        annotation.data.label.value.split_whitespace().next() == Some(EXEC_LABEL)
+    }) else {
+        return Ok(None);
+    };
+    let line = annotation.header_location().start.line;
+    let interpreter = annotation
+        .data
+        .label
+        .value
+        .split_whitespace()
+        .nth(1)
+        .ok_or_else(|| ExecError::Invalid {
+            line,
+            message: "no interpreter given".to_string(),
+        })?;
+    let mode = match annotation
+        .data
+        .parameters
+        .iter()
+        .find(|param| param.key == "mode")
+    {
+        Some(param) => ExecMode::from_name(&param.value).ok_or_else(|| ExecError::Invalid {
+            line,
+            message: format!("unknown mode '{}'", param.value),
+        })?,
+        None => ExecMode::Append,
+    };
+    let end = &verbatim.location.end;
+    let end_line = if end.column == 0 && end.line > line {
+        end.line - 1
+    } else {
+        end.line
+    };
+
+    Ok(Some(ExecBlock {
+        interpreter: interpreter.to_string(),
+        code: code_block(verbatim).text,
+        mode,
+        start_line: line,
+        end_line,
+        indent: verbatim.location.start.column,
+    }))
+}
+
+/// Run `block` by piping its code to its interpreter; returns the program's stdout
+pub fn run_interpreter(block: &ExecBlock) -> Result<String, String> {
+    pipe_through(&block.interpreter, &[], &block.code)
+}
+
+/// Run `program` with `args`, piping `input` to it; returns the program's stdout
+pub(crate) fn pipe_through(program: &str, args: &[String], input: &str) -> Result<String, String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|error| format!("cannot run '{program}': {error}"))?;
+    let input = input.to_string();
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .map_err(|error| error.to_string())?;
+    let _ = writer.join();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("{} ({})", stderr.trim(), output.status));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Run the exec blocks of `document` (parsed from `source`) with `run`, and return the
+/// source with their output inserted
+pub fn preprocess(
+    source: &str,
+    document: &Document,
+    options: &ExecOptions,
+    run: impl Fn(&ExecBlock) -> Result<String, String>,
+) -> Result<String, ExecError> {
+    let blocks = exec_blocks(document)?;
+    if blocks.is_empty() {
+        return Ok(source.to_string());
+    }
+    if !options.allow {
+        return Err(ExecError::NotAllowed {
+            count: blocks.len(),
+        });
+    }
+
+    let mut lines: Vec<String> = source.lines().map(str::to_string).collect();
+    // Splice from the end so earlier line numbers stay valid
+    for block in blocks.iter().rev() {
+        let output = run(block).map_err(|message| ExecError::Failed {
+            line: block.start_line,
+            message,
+        })?;
+        let indent = " ".repeat(block.indent);
+        let mut inserted: Vec<String> = output
+            .trim_end()
+            .lines()
+            .map(|line| {
+                if line.is_empty() {
+                    String::new()
+                } else {
+                    format!("{indent}{line}")
+                }
+            })
+            .collect();
+        let end = (block.end_line + 1).min(lines.len());
+        match block.mode {
+            ExecMode::Replace => {
+                lines.splice(block.start_line..end, inserted);
+            }
+            ExecMode::Append => {
+                inserted.insert(0, String::new());
+                lines.splice(end..end, inserted);
+            }
+        }
+    }
+
+    let mut result = lines.join("\n");
+    if source.ends_with('\n') {
+        result.push('\n');
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    const SOURCE: &str = "Report\n\nUsage follows.\n\n:: exec sh ::\nCount:\n    echo 'Files: 3.'\n:: shell\n\n:: exec sh mode=replace ::\nTable:\n    printf 'Generated.\\n'\n:: shell\n\nEnd.\n";
+
+    fn fake(block: &ExecBlock) -> Result<String, String> {
+        Ok(format!("Ran {} lines.\n", block.code.lines().count()))
+    }
+
+    #[test]
+    fn test_exec_blocks() {
+        let doc = parse_document(SOURCE).unwrap();
+        let blocks = exec_blocks(&doc).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].interpreter, "sh");
+        assert_eq!(blocks[0].code, "echo 'Files: 3.'\n");
+        assert_eq!((blocks[0].start_line, blocks[0].end_line), (4, 7));
+        assert_eq!(blocks[1].mode, ExecMode::Replace);
+    }
+
+    #[test]
+    fn test_preprocess() {
+        let doc = parse_document(SOURCE).unwrap();
+        assert_eq!(
+            preprocess(SOURCE, &doc, &ExecOptions::default(), fake),
+            Err(ExecError::NotAllowed { count: 2 })
+        );
+
+        let allowed = ExecOptions { allow: true };
+        let output = preprocess(SOURCE, &doc, &allowed, fake).unwrap();
+        assert_eq!(
+            output,
+            "Report\n\nUsage follows.\n\n:: exec sh ::\nCount:\n    echo 'Files: 3.'\n:: shell\n\nRan 1 lines.\n\nRan 1 lines.\n\nEnd.\n"
+        );
+        assert!(parse_document(&output).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_interpreter() {
+        let doc = parse_document(SOURCE).unwrap();
+        let allowed = ExecOptions { allow: true };
+        let output = preprocess(SOURCE, &doc, &allowed, run_interpreter).unwrap();
+        assert!(output.contains("\nFiles: 3.\n"));
+        assert!(output.contains("\nGenerated.\n"));
+        assert!(!output.contains("printf"));
+    }
+}
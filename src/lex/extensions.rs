@@ -0,0 +1,243 @@
+//! Block extensions
+//!
+//!     Experimental block types (timelines, quizzes, ...) can be prototyped without touching
+//!     the grammar. They are written as verbatim blocks whose closing label names the type,
+//!     so every Lex tool can already parse, format and round-trip them:
+//!
+//!         Release plan:
+//!             2024-01  Alpha
+//!             2024-06  Beta
+//!         :: timeline
+//!
+//!     A [BlockExtension] claims such blocks (the recognizer), turns their content into
+//!     structured data (the builder) and renders that data in output formats (the serializer
+//!     hooks). Extensions are registered in an [ExtensionRegistry], which applies them to a
+//!     parsed document.
+//!
+//!     A [FormatRegistry](crate::lex::formats::FormatRegistry) serializes documents with the
+//!     extensions in its [extensions](crate::lex::formats::FormatRegistry::extensions_mut)
+//!     registry, passed to
+//!     [Formatter::serialize_with_extensions](crate::lex::formats::Formatter::serialize_with_extensions).
+//!     Formats that support
+//!     extensions (`markdown`) ask it to [render](ExtensionRegistry::render_block) each
+//!     verbatim block, and write the plain verbatim block when no extension claims it or the
+//!     extension has no hook for the format. Other formats ignore extensions.
+
+use crate::lex::ast::{ContentItem, Diagnostic, DiagnosticSeverity, Document, Range, Verbatim};
+use serde_json::Value;
+
+/// A custom block type built from labeled verbatim blocks
+pub trait BlockExtension: Send + Sync {
+    /// Name of the extension; also the closing label it claims by default
+    fn name(&self) -> &str;
+
+    /// Whether `block` is one of this extension's blocks
+    fn recognizes(&self, block: &Verbatim) -> bool {
+        block.closing_data.label.value == self.name()
+    }
+
+    /// Build the block's data from its content
+    fn build(&self, block: &Verbatim) -> Result<Value, String>;
+
+    /// Render built data in `format`; `None` leaves the block to the format's default
+    fn render(&self, _block: &ExtensionBlock, _format: &str) -> Option<String> {
+        None
+    }
+
+    /// Optional description of this extension
+    fn description(&self) -> &str {
+        ""
+    }
+}
+
+/// A verbatim block claimed and built by an extension
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtensionBlock {
+    /// Name of the extension that built the block
+    pub extension: String,
+    pub subject: String,
+    pub data: Value,
+    pub range: Range,
+}
+
+/// A block an extension recognized but failed to build
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtensionError {
+    pub extension: String,
+    pub message: String,
+    pub range: Range,
+}
+
+impl ExtensionError {
+    pub fn to_diagnostic(&self) -> Diagnostic {
+        Diagnostic::new(
+            self.range.clone(),
+            DiagnosticSeverity::Error,
+            format!("{} block: {}", self.extension, self.message),
+        )
+        .with_code("extension-block-error")
+    }
+}
+
+/// Result of applying extensions to a document
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExtensionOutput {
+    /// Built blocks, in document order
+    pub blocks: Vec<ExtensionBlock>,
+    pub errors: Vec<ExtensionError>,
+}
+
+/// Registered block extensions, tried in registration order
+#[derive(Default)]
+pub struct ExtensionRegistry {
+    extensions: Vec<Box<dyn BlockExtension>>,
+}
+
+impl ExtensionRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an extension, replacing one with the same name
+    pub fn register<E: BlockExtension + 'static>(&mut self, extension: E) {
+        self.extensions
+            .retain(|existing| existing.name() != extension.name());
+        self.extensions.push(Box::new(extension));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn BlockExtension> {
+        self.extensions
+            .iter()
+            .find(|extension| extension.name() == name)
+            .map(|extension| extension.as_ref())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.extensions.is_empty()
+    }
+
+    /// Names of the registered extensions, in registration order
+    pub fn names(&self) -> Vec<&str> {
+        self.extensions
+            .iter()
+            .map(|extension| extension.name())
+            .collect()
+    }
+
+    /// The extension claiming `block`, if any
+    pub fn recognize(&self, block: &Verbatim) -> Option<&dyn BlockExtension> {
+        self.extensions
+            .iter()
+            .find(|extension| extension.recognizes(block))
+            .map(|extension| extension.as_ref())
+    }
+
+    /// Build every block of `document` claimed by an extension
+    pub fn apply(&self, document: &Document) -> ExtensionOutput {
+        let mut output = ExtensionOutput::default();
+        for item in document.root.iter_all_nodes() {
+            let ContentItem::VerbatimBlock(block) = item else {
+                continue;
+            };
+            match self.build(block) {
+                Some(Ok(built)) => output.blocks.push(built),
+                Some(Err(error)) => output.errors.push(error),
+                None => {}
+            }
+        }
+        output
+    }
+
+    /// Build `block` with the extension claiming it, if any
+    fn build(&self, block: &Verbatim) -> Option<Result<ExtensionBlock, ExtensionError>> {
+        let extension = self.recognize(block)?;
+        let built = match extension.build(block) {
+            Ok(data) => Ok(ExtensionBlock {
+                extension: extension.name().to_string(),
+                subject: block.subject.as_string().to_string(),
+                data,
+                range: block.location.clone(),
+            }),
+            Err(message) => Err(ExtensionError {
+                extension: extension.name().to_string(),
+                message,
+                range: block.location.clone(),
+            }),
+        };
+        Some(built)
+    }
+
+    /// Build and render the verbatim `block` in `format`; `None` when no extension claims
+    /// it, building fails or the extension leaves the format to its default
+    pub fn render_block(&self, block: &Verbatim, format: &str) -> Option<String> {
+        let built = self.build(block)?.ok()?;
+        self.render(&built, format)
+    }
+
+    /// Render `block` in `format` with the extension that built it
+    pub fn render(&self, block: &ExtensionBlock, format: &str) -> Option<String> {
+        self.get(&block.extension)?.render(block, format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+    use serde_json::json;
+
+    struct Timeline;
+
+    impl BlockExtension for Timeline {
+        fn name(&self) -> &str {
+            "timeline"
+        }
+
+        fn build(&self, block: &Verbatim) -> Result<Value, String> {
+            let mut events = Vec::new();
+            for line in block.content_lines() {
+                let text = line.content.as_string();
+                let (date, event) = text
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| format!("expected a date and an event: '{text}'"))?;
+                events.push(json!({ "date": date, "event": event.trim() }));
+            }
+            Ok(Value::Array(events))
+        }
+
+        fn render(&self, block: &ExtensionBlock, format: &str) -> Option<String> {
+            (format == "text").then(|| {
+                let count = block.data.as_array().map_or(0, Vec::len);
+                format!("{}: {count} events", block.subject)
+            })
+        }
+    }
+
+    #[test]
+    fn test_apply_and_render() {
+        let source = "Title\n\nPlans.\n\nRelease plan:\n    2024-01  Alpha\n    2024-06  Beta\n:: timeline\n\nBroken:\n    someday\n:: timeline\n\nCode:\n    x = 1\n:: python\n";
+        let doc = parse_document(source).unwrap();
+        let mut registry = ExtensionRegistry::new();
+        registry.register(Timeline);
+
+        let output = registry.apply(&doc);
+        assert_eq!(output.blocks.len(), 1);
+        let block = &output.blocks[0];
+        assert_eq!(
+            block.data,
+            json!([
+                { "date": "2024-01", "event": "Alpha" },
+                { "date": "2024-06", "event": "Beta" }
+            ])
+        );
+        assert_eq!(
+            registry.render(block, "text").as_deref(),
+            Some("Release plan: 2 events")
+        );
+        assert_eq!(registry.render(block, "html"), None);
+
+        assert_eq!(output.errors.len(), 1);
+        let diagnostic = output.errors[0].to_diagnostic();
+        assert!(diagnostic.message.starts_with("timeline block: expected"));
+    }
+}
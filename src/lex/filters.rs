@@ -0,0 +1,259 @@
+//! AST filters
+//!
+//!     A filter stage sits between parsing and serialization: each filter receives the
+//!     document's AST as JSON (an [AstSnapshot]) and returns a modified one. Filters let users
+//!     apply custom publishing transformations without recompiling Lex.
+//!
+//!     [CommandFilter] runs an external program, in the manner of pandoc's JSON filters: the
+//!     AST is written to the program's stdin, the target format is passed as its first
+//!     argument, and the modified AST is read from its stdout. Embedded script or WASM
+//!     runtimes can implement [DocumentFilter] the same way.
+//!
+//!     Filters run in order through a [FilterChain]; the JSON each one returns must still
+//!     describe a snapshot, or the chain stops with [FilterError::InvalidOutput].
+
+use crate::lex::ast::{snapshot_from_document_with_options, AstSnapshot, Document};
+use serde_json::Value;
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// Error that can occur while running a filter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterError {
+    /// The filter program could not be started
+    Spawn { filter: String, message: String },
+    /// The filter exited unsuccessfully
+    Failed { filter: String, stderr: String },
+    /// The filter returned something other than an AST
+    InvalidOutput { filter: String, message: String },
+}
+
+impl fmt::Display for FilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterError::Spawn { filter, message } => {
+                write!(f, "Cannot run filter '{filter}': {message}")
+            }
+            FilterError::Failed { filter, stderr } => {
+                write!(f, "Filter '{filter}' failed: {}", stderr.trim())
+            }
+            FilterError::InvalidOutput { filter, message } => {
+                write!(f, "Filter '{filter}' returned an invalid AST: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+/// A transformation of the JSON AST
+pub trait DocumentFilter {
+    /// Name used in error messages
+    fn name(&self) -> String;
+
+    /// Transform `ast` for output in `format`
+    fn apply(&self, ast: Value, format: &str) -> Result<Value, FilterError>;
+}
+
+/// A filter run as an external program, reading and writing JSON
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandFilter {
+    pub program: PathBuf,
+    /// Arguments passed after the target format
+    pub args: Vec<String>,
+}
+
+impl CommandFilter {
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+        }
+    }
+
+    pub fn with_args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+}
+
+impl DocumentFilter for CommandFilter {
+    fn name(&self) -> String {
+        self.program.display().to_string()
+    }
+
+    fn apply(&self, ast: Value, format: &str) -> Result<Value, FilterError> {
+        let spawn_error = |error: std::io::Error| FilterError::Spawn {
+            filter: self.name(),
+            message: error.to_string(),
+        };
+        let mut child = Command::new(&self.program)
+            .arg(format)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(spawn_error)?;
+
+        let input = ast.to_string();
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+        let output = child.wait_with_output().map_err(spawn_error)?;
+        // A filter may exit without reading all of its input; its status decides the outcome.
+        let _ = writer.join();
+
+        if !output.status.success() {
+            return Err(FilterError::Failed {
+                filter: self.name(),
+                stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            });
+        }
+        serde_json::from_slice(&output.stdout).map_err(|error| FilterError::InvalidOutput {
+            filter: self.name(),
+            message: error.to_string(),
+        })
+    }
+}
+
+/// Filters applied in order
+#[derive(Default)]
+pub struct FilterChain {
+    filters: Vec<Box<dyn DocumentFilter>>,
+}
+
+impl FilterChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_filter<F: DocumentFilter + 'static>(mut self, filter: F) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+    }
+
+    /// Run every filter over `snapshot` for output in `format`
+    pub fn run(&self, snapshot: AstSnapshot, format: &str) -> Result<AstSnapshot, FilterError> {
+        let mut snapshot = snapshot;
+        for filter in &self.filters {
+            let ast =
+                serde_json::to_value(&snapshot).map_err(|error| FilterError::InvalidOutput {
+                    filter: filter.name(),
+                    message: error.to_string(),
+                })?;
+            let ast = filter.apply(ast, format)?;
+            snapshot = serde_json::from_value(ast).map_err(|error| FilterError::InvalidOutput {
+                filter: filter.name(),
+                message: error.to_string(),
+            })?;
+        }
+        Ok(snapshot)
+    }
+
+    /// Snapshot `document`, annotations included, and run every filter over it
+    pub fn run_document(
+        &self,
+        document: &Document,
+        format: &str,
+    ) -> Result<AstSnapshot, FilterError> {
+        self.run(snapshot_from_document_with_options(document, true), format)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    struct Uppercase;
+
+    impl DocumentFilter for Uppercase {
+        fn name(&self) -> String {
+            "uppercase".to_string()
+        }
+
+        fn apply(&self, mut ast: Value, _format: &str) -> Result<Value, FilterError> {
+            fn walk(node: &mut Value) {
+                if let Some(Value::String(label)) = node.get_mut("label") {
+                    *label = label.to_uppercase();
+                }
+                if let Some(Value::Array(children)) = node.get_mut("children") {
+                    children.iter_mut().for_each(walk);
+                }
+            }
+            walk(&mut ast);
+            Ok(ast)
+        }
+    }
+
+    fn labels(snapshot: &AstSnapshot, out: &mut Vec<String>) {
+        out.push(snapshot.label.clone());
+        snapshot
+            .children
+            .iter()
+            .for_each(|child| labels(child, out));
+    }
+
+    #[test]
+    fn test_in_process_filter() {
+        let doc = parse_document("Title\n\nHello world.\n").unwrap();
+        let snapshot = FilterChain::new()
+            .with_filter(Uppercase)
+            .run_document(&doc, "html")
+            .unwrap();
+        let mut found = Vec::new();
+        labels(&snapshot, &mut found);
+        assert!(found.iter().any(|label| label.contains("HELLO WORLD.")));
+    }
+
+    #[cfg(unix)]
+    fn script(name: &str, body: &str) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = std::env::temp_dir().join(format!("lex-filters-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_filter() {
+        let doc = parse_document("Title\n\nHello world.\n").unwrap();
+        let rename = script("rename.sh", "test \"$1\" = html && sed 's/Hello/Howdy/g'");
+        let snapshot = FilterChain::new()
+            .with_filter(CommandFilter::new(rename))
+            .run_document(&doc, "html")
+            .unwrap();
+        let mut found = Vec::new();
+        labels(&snapshot, &mut found);
+        assert!(found.iter().any(|label| label.contains("Howdy world.")));
+
+        let failing = script("failing.sh", "echo broken >&2; exit 3");
+        let error = FilterChain::new()
+            .with_filter(CommandFilter::new(failing))
+            .run_document(&doc, "html")
+            .unwrap_err();
+        assert!(matches!(error, FilterError::Failed { ref stderr, .. } if stderr == "broken\n"));
+
+        let garbage = script("garbage.sh", "cat >/dev/null; echo '{}'");
+        let error = FilterChain::new()
+            .with_filter(CommandFilter::new(garbage))
+            .run_document(&doc, "html")
+            .unwrap_err();
+        assert!(matches!(error, FilterError::InvalidOutput { .. }));
+
+        let error = FilterChain::new()
+            .with_filter(CommandFilter::new("/nonexistent/lex-filter"))
+            .run_document(&doc, "html")
+            .unwrap_err();
+        assert!(matches!(error, FilterError::Spawn { .. }));
+    }
+}
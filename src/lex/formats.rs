@@ -2,14 +2,67 @@
 //!
 //! This module contains different format implementations for serializing:
 //! - AST Documents to various output formats (tag, treeviz)
+//! - Reference graphs of documents (refs, refs-dot)
+//! - Table blocks as delimited data (csv, tsv)
 //! - Token streams back to source text (detokenizer)
+//! - Documents rendered for the terminal (ansi)
+//! - Session outlines for outliners (opml) and web front ends (outline-json)
+//! - Documents written back as canonical Lex source (lex)
+//! - LaTeX articles (latex)
+//! - Markdown, as CommonMark or GitHub Flavored Markdown (markdown)
+//! - Typst markup, for fast PDF builds (typst)
+//! - PDF pages, laid out without external tools (pdf)
+//! - Transcripts as subtitles (srt, vtt)
+//! - Document structure as semantic XML, read back as Lex source (xml)
+//! - The complete AST as JSON, for tools in other languages (json)
+//!
+//! Further formats come from [external] adapter programs found on the `PATH`.
+//!
+//! Many files are converted at once with [batch], and a single session of a document with
+//! [select]. What a conversion loses is listed in a [report](report::ConversionReport).
 
+pub mod ansi;
+pub mod asciimath;
+pub mod batch;
+pub mod csv;
 pub mod detokenizer;
+pub mod external;
+pub mod json;
+pub mod latex;
+pub mod lex;
+pub mod markdown;
+pub(crate) mod markup;
+pub mod opml;
+pub mod outline;
+pub mod pdf;
+pub mod refs;
 pub mod registry;
+pub mod report;
+pub mod select;
+pub mod subtitles;
 pub mod tag;
 pub mod treeviz;
+pub mod typst;
+pub mod xml;
 
+pub use ansi::{AnsiFormatter, AnsiOptions};
+pub use batch::{convert_batch, BatchOptions, BatchReport};
+pub use csv::{CsvFormatter, TsvFormatter};
 pub use detokenizer::{detokenize, ToLexString};
+pub use external::{ExternalFormatter, ADAPTER_PREFIX};
+pub use json::{JsonDocument, JsonFormatter};
+pub use latex::{LatexFormatter, LatexOptions};
+pub use lex::LexFormatter;
+pub use markdown::{MarkdownFlavor, MarkdownFormatter, MarkdownOptions};
+pub use opml::{import_opml, OpmlFormatter};
+pub use outline::OutlineJsonFormatter;
+pub use pdf::{PdfFormatter, PdfOptions, PdfPageSize};
+pub use refs::{RefsDotFormatter, RefsFormatter};
 pub use registry::{FormatError, FormatRegistry, Formatter};
+pub use report::{ConversionLoss, ConversionReport};
+pub use select::{select_session, SessionSelector};
+pub use subtitles::{import_srt, import_vtt, SrtFormatter, VttFormatter};
 pub use tag::{serialize_document as serialize_ast_tag, TagFormatter};
 pub use treeviz::{to_treeviz_str, TreevizFormatter};
+pub use typst::TypstFormatter;
+pub use xml::{import_xml, XmlFormatter, XmlOptions};
@@ -0,0 +1,419 @@
+//! Terminal output (ANSI)
+//!
+//! Renders a document for reading in a terminal, as `lex view --plain` would, with ANSI
+//! escape codes: session titles bold and colored, strong text bold, emphasis italic,
+//! strikethrough (the [`del` role](crate::lex::inlines::DEL_ROLE)) struck, code and math
+//! colored, references underlined, annotations dimmed. Nesting is shown by
+//! indentation, two columns per level.
+//!
+//! Verbatim blocks get a light, language-independent highlighting: strings, numbers,
+//! comments (`//`, `#` and `--` to the end of the line) and a set of keywords common to
+//! most languages. [Diff blocks](crate::lex::diff) are colored by line instead: additions
+//! green, removals red, hunk headers cyan. Blocks with a `lines` parameter show the selected
+//! lines alone, with a gutter of line numbers; lines the `emphasize` parameter highlights are
+//! shown in reverse video.
+//!
+//! Annotations attached to an element are shown dimmed beneath it, indented one level;
+//! [AnsiOptions::annotations] hides them, for reading metadata-heavy documents. Document
+//! annotations are always shown, under the title.
+//!
+//! The output is meant for a terminal or a pager that passes escape codes through
+//! (`less -R`).
+
+use crate::lex::ast::{Annotation, ContentItem, Document, TextContent, Verbatim};
+use crate::lex::diff::{is_diff, DiffLine};
+use crate::lex::formats::registry::{flag_param, FormatError, Formatter};
+use crate::lex::inlines::{InlineNode, ReferenceType, DEL_ROLE};
+use crate::lex::literate::code_block;
+use std::collections::HashMap;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const ITALIC: &str = "\x1b[3m";
+const UNDERLINE: &str = "\x1b[4m";
+const REVERSE: &str = "\x1b[7m";
+const STRIKE: &str = "\x1b[9m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const BLUE: &str = "\x1b[34m";
+const MAGENTA: &str = "\x1b[35m";
+const CYAN: &str = "\x1b[36m";
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "case", "class", "const", "continue", "def", "do", "elif",
+    "else", "enum", "export", "false", "fn", "for", "from", "func", "function", "if", "impl",
+    "import", "in", "let", "match", "mut", "nil", "None", "null", "pub", "return", "self",
+    "static", "struct", "switch", "trait", "true", "type", "use", "var", "while", "yield",
+];
+
+/// Document rendered with ANSI escape codes
+pub struct AnsiFormatter;
+
+impl Formatter for AnsiFormatter {
+    fn name(&self) -> &str {
+        "ansi"
+    }
+
+    fn extension(&self) -> &str {
+        "txt"
+    }
+
+    fn serialize(&self, doc: &Document) -> Result<String, FormatError> {
+        Ok(render_document(doc))
+    }
+
+    /// `annotations=false` hides annotations attached to elements
+    fn serialize_with_params(
+        &self,
+        doc: &Document,
+        params: &HashMap<String, String>,
+    ) -> Result<String, FormatError> {
+        let options = AnsiOptions {
+            annotations: flag_param(params, "annotations").unwrap_or(true),
+        };
+        Ok(render_document_with_options(doc, &options))
+    }
+
+    fn description(&self) -> &str {
+        "Terminal output with colors and highlighted code"
+    }
+}
+
+/// How to render a document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnsiOptions {
+    /// Show annotations attached to elements
+    pub annotations: bool,
+}
+
+impl Default for AnsiOptions {
+    fn default() -> Self {
+        Self { annotations: true }
+    }
+}
+
+/// Render `doc` with ANSI escape codes
+pub fn render_document(doc: &Document) -> String {
+    render_document_with_options(doc, &AnsiOptions::default())
+}
+
+/// Render `doc` with ANSI escape codes, as set by `options`
+pub fn render_document_with_options(doc: &Document, options: &AnsiOptions) -> String {
+    let mut renderer = Renderer {
+        output: String::new(),
+        options,
+    };
+    let title = doc.title().trim();
+    if !title.is_empty() {
+        renderer.line(0, &format!("{BOLD}{UNDERLINE}{title}{RESET}"));
+        renderer.blank();
+    }
+    for annotation in &doc.annotations {
+        renderer.annotation(annotation, 0);
+    }
+    renderer.items(&doc.root.children, 0);
+    while renderer.output.ends_with("\n\n") {
+        renderer.output.pop();
+    }
+    renderer.output
+}
+
+struct Renderer<'a> {
+    output: String,
+    options: &'a AnsiOptions,
+}
+
+impl Renderer<'_> {
+    fn line(&mut self, depth: usize, text: &str) {
+        self.output.push_str(&"  ".repeat(depth));
+        self.output.push_str(text);
+        self.output.push('\n');
+    }
+
+    fn blank(&mut self) {
+        if !self.output.is_empty() && !self.output.ends_with("\n\n") {
+            self.output.push('\n');
+        }
+    }
+
+    fn items(&mut self, items: &[ContentItem], depth: usize) {
+        for item in items {
+            self.item(item, depth);
+        }
+    }
+
+    fn item(&mut self, item: &ContentItem, depth: usize) {
+        match item {
+            ContentItem::Session(session) => {
+                let title = inlines(&session.title);
+                self.line(depth, &format!("{BOLD}{CYAN}{title}{RESET}"));
+                self.attached(item, depth);
+                self.blank();
+                self.items(&session.children, depth + 1);
+                self.blank();
+                return;
+            }
+            ContentItem::Paragraph(paragraph) => {
+                self.items(&paragraph.lines, depth);
+                self.blank();
+            }
+            ContentItem::TextLine(line) => self.line(depth, &inlines(&line.content)),
+            ContentItem::List(list) => {
+                self.items(&list.items, depth);
+                self.blank();
+            }
+            ContentItem::ListItem(list_item) => {
+                let text: Vec<String> = list_item.text.iter().map(inlines).collect();
+                let marker = list_item.marker();
+                self.line(depth, &format!("{BOLD}{marker}{RESET} {}", text.join(" ")));
+                self.items(&list_item.children, depth + 1);
+            }
+            ContentItem::Definition(definition) => {
+                let subject = inlines(&definition.subject);
+                self.line(depth, &format!("{BOLD}{subject}{RESET}"));
+                self.items(&definition.children, depth + 1);
+                self.blank();
+            }
+            ContentItem::Annotation(annotation) => self.annotation(annotation, depth),
+            ContentItem::VerbatimBlock(verbatim) => self.verbatim(verbatim, depth),
+            ContentItem::VerbatimLine(line) => {
+                self.line(depth, &highlight(line.content.as_string()))
+            }
+            ContentItem::BlankLineGroup(_) => {}
+        }
+        self.attached(item, depth);
+    }
+
+    /// Annotations attached to `item`, beneath it
+    fn attached(&mut self, item: &ContentItem, depth: usize) {
+        if !self.options.annotations || item.annotations().is_empty() {
+            return;
+        }
+        if self.output.ends_with("\n\n") {
+            self.output.pop();
+        }
+        for annotation in item.annotations() {
+            self.annotation(annotation, depth + 1);
+        }
+        self.blank();
+    }
+
+    fn annotation(&mut self, annotation: &Annotation, depth: usize) {
+        let mut header = annotation.data.label.value.clone();
+        for param in &annotation.data.parameters {
+            header.push_str(&format!(" {}={}", param.key, param.value));
+        }
+        self.line(depth, &format!("{DIM}:: {header} ::{RESET}"));
+        let mut body = Renderer {
+            output: String::new(),
+            options: self.options,
+        };
+        body.items(&annotation.children, depth + 1);
+        for line in body.output.trim_end_matches('\n').lines() {
+            if line.is_empty() {
+                self.output.push('\n');
+            } else {
+                self.output.push_str(&format!("{DIM}{line}{RESET}\n"));
+            }
+        }
+    }
+
+    fn verbatim(&mut self, verbatim: &Verbatim, depth: usize) {
+        let block = code_block(verbatim);
+        self.line(depth, &format!("{BOLD}{}{RESET}", block.subject.trim()));
+        let diff = is_diff(&block.language);
+        let text: Vec<&str> = block.text.lines().collect();
+        let numbered = verbatim.numbered_lines();
+        let gutter = verbatim.line_selection().is_some();
+        let width = numbered
+            .last()
+            .map_or(0, |line| line.number.to_string().len());
+        for numbered in numbered {
+            let text = text.get(numbered.number - 1).copied().unwrap_or_default();
+            let mut line = if diff {
+                highlight_diff(text)
+            } else {
+                highlight(text)
+            };
+            if numbered.emphasized {
+                line = format!(
+                    "{REVERSE}{}{RESET}",
+                    line.replace(RESET, &format!("{RESET}{REVERSE}"))
+                );
+            }
+            if gutter {
+                line = format!("{DIM}{:>width$} │{RESET} {line}", numbered.number);
+            }
+            self.line(depth + 1, &line);
+        }
+        self.line(depth, &format!("{DIM}:: {}{RESET}", block.language));
+        self.blank();
+    }
+}
+
+fn inlines(text: &TextContent) -> String {
+    render_inlines(&text.inline_items())
+}
+
+fn render_inlines(nodes: &[InlineNode]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        match node {
+            InlineNode::Plain { text, .. } => out.push_str(text),
+            InlineNode::Strong { content, .. } => {
+                out.push_str(&format!("{BOLD}{}{RESET}", render_inlines(content)))
+            }
+            InlineNode::Emphasis { content, .. } => {
+                out.push_str(&format!("{ITALIC}{}{RESET}", render_inlines(content)))
+            }
+            InlineNode::Code { text, .. } => out.push_str(&format!("{YELLOW}{text}{RESET}")),
+            InlineNode::Math { text, .. } => out.push_str(&format!("{MAGENTA}{text}{RESET}")),
+            InlineNode::Reference { data, .. } => {
+                let text = match &data.reference_type {
+                    ReferenceType::WikiLink(link) => link.display_text().to_string(),
+                    ReferenceType::Image(image) => {
+                        format!("[image: {}]", image.alt.as_deref().unwrap_or(&image.src))
+                    }
+                    _ => format!("[{}]", data.raw),
+                };
+                out.push_str(&format!("{BLUE}{UNDERLINE}{text}{RESET}"))
+            }
+            InlineNode::Ruby { base, text, .. } => {
+                out.push_str(&format!("{base}{DIM}({text}){RESET}"))
+            }
+            InlineNode::Role { data, .. } if data.role == DEL_ROLE => {
+                out.push_str(&format!("{STRIKE}{}{RESET}", data.content))
+            }
+            InlineNode::Role { data, .. } => out.push_str(&data.content),
+        }
+    }
+    out
+}
+
+/// `line` of code with strings, numbers, comments and keywords colored
+fn highlight(line: &str) -> String {
+    let chars: Vec<char> = line.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let rest = &chars[i..];
+        let comment = rest.starts_with(&['/', '/'])
+            || rest.starts_with(&['-', '-'])
+            || (c == '#' && (i == 0 || chars[i - 1].is_whitespace()));
+        if comment {
+            let text: String = rest.iter().collect();
+            out.push_str(&format!("{DIM}{text}{RESET}"));
+            break;
+        } else if c == '"' || c == '\'' {
+            let end = rest[1..]
+                .iter()
+                .position(|&next| next == c)
+                .map_or(chars.len(), |end| i + end + 2);
+            let text: String = chars[i..end].iter().collect();
+            out.push_str(&format!("{GREEN}{text}{RESET}"));
+            i = end;
+        } else if c.is_alphanumeric() || c == '_' {
+            let end = rest
+                .iter()
+                .position(|next| !(next.is_alphanumeric() || *next == '_'))
+                .map_or(chars.len(), |end| i + end);
+            let word: String = chars[i..end].iter().collect();
+            if word.chars().all(|ch| ch.is_ascii_digit()) {
+                out.push_str(&format!("{RED}{word}{RESET}"));
+            } else if KEYWORDS.contains(&word.as_str()) {
+                out.push_str(&format!("{MAGENTA}{word}{RESET}"));
+            } else {
+                out.push_str(&word);
+            }
+            i = end;
+        } else {
+            out.push(c);
+            i += 1;
+        }
+    }
+    out
+}
+
+/// `line` of a diff colored by its kind
+fn highlight_diff(line: &str) -> String {
+    match DiffLine::classify(line) {
+        DiffLine::Header => format!("{BOLD}{line}{RESET}"),
+        DiffLine::Hunk => format!("{CYAN}{line}{RESET}"),
+        DiffLine::Added => format!("{GREEN}{line}{RESET}"),
+        DiffLine::Removed => format!("{RED}{line}{RESET}"),
+        DiffLine::Context => line.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    #[test]
+    fn test_render_document() {
+        let source = "Guide\n\n:: status draft ::\n\nIntro with *bold* and `code`.\n\nOld {del|draft}.\n\n1. Setup\n\n    - First [./a.lex]\n    - Second\n\n    Example:\n        let x = \"hi\"; // greet\n    :: rust\n";
+        let doc = parse_document(source).unwrap();
+        let output = render_document(&doc);
+        assert!(output.starts_with("\x1b[1m\x1b[4mGuide\x1b[0m\n\n"));
+        assert!(output.contains("Intro with \x1b[1mbold\x1b[0m and \x1b[33mcode\x1b[0m."));
+        assert!(output.contains("Old \x1b[9mdraft\x1b[0m."));
+        assert!(output.contains("\x1b[1m\x1b[36m1. Setup\x1b[0m\n"));
+        assert!(output.contains("  \x1b[1m-\x1b[0m First \x1b[34m\x1b[4m[./a.lex]\x1b[0m\n"));
+        assert!(output.contains(":: status draft ::"));
+        assert!(output.contains("\x1b[2m:: rust\x1b[0m"));
+        assert!(!output.ends_with("\n\n"));
+    }
+
+    #[test]
+    fn test_attached_annotations() {
+        let source = "Notes\n\n:: reviewed by=ada ::\nChecked paragraph.\n\nNext paragraph.\n";
+        let doc = parse_document(source).unwrap();
+        assert_eq!(
+            render_document(&doc),
+            "\x1b[1m\x1b[4mNotes\x1b[0m\n\nChecked paragraph.\n  \x1b[2m:: reviewed by=ada ::\x1b[0m\n\nNext paragraph.\n"
+        );
+        let hidden = AnsiOptions { annotations: false };
+        assert_eq!(
+            render_document_with_options(&doc, &hidden),
+            "\x1b[1m\x1b[4mNotes\x1b[0m\n\nChecked paragraph.\n\nNext paragraph.\n"
+        );
+    }
+
+    #[test]
+    fn test_line_numbers_and_emphasis() {
+        let source =
+            "Code\n\nLoop:\n    a = 1\n    b = 2\n    c = 3\n:: text lines=2-3, emphasize=3\n";
+        let output = render_document(&parse_document(source).unwrap());
+        assert!(output.contains(
+            "  \x1b[2m2 │\x1b[0m b = \x1b[31m2\x1b[0m\n  \x1b[2m3 │\x1b[0m \x1b[7mc = \x1b[31m3\x1b[0m\x1b[7m\x1b[0m\n"
+        ));
+        assert!(!output.contains("a = "));
+
+        let source = "Code\n\nLoop:\n    a\n    b\n:: text emphasize=1\n";
+        let output = render_document(&parse_document(source).unwrap());
+        assert!(output.contains("  \x1b[7ma\x1b[0m\n  b\n"));
+    }
+
+    #[test]
+    fn test_highlight() {
+        assert_eq!(
+            highlight("let n = 42; // note"),
+            "\x1b[35mlet\x1b[0m n = \x1b[31m42\x1b[0m; \x1b[2m// note\x1b[0m"
+        );
+        assert_eq!(highlight("say 'it'"), "say \x1b[32m'it'\x1b[0m");
+    }
+
+    #[test]
+    fn test_diff_block() {
+        let source =
+            "Changes\n\nFix:\n    @@ -1 +1 @@\n    -let x = 1;\n    +let x = 2;\n:: diff\n";
+        let output = render_document(&parse_document(source).unwrap());
+        assert!(output.contains(
+            "  \x1b[36m@@ -1 +1 @@\x1b[0m\n  \x1b[31m-let x = 1;\x1b[0m\n  \x1b[32m+let x = 2;\x1b[0m\n"
+        ));
+    }
+}
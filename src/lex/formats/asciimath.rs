@@ -0,0 +1,480 @@
+//! AsciiMath
+//!
+//! Lex math (`#x^2 + y#`) is AsciiMath. [parse] reads it into a tree of [Expr], following
+//! the AsciiMath grammar: symbols are matched longest first, and `_`, `^` and `/` bind to
+//! the simple expression on each side. Formats with native math write the tree in their own
+//! language, with [LaTeX](super::latex::asciimath) and [Typst](super::typst::asciimath)
+//! emitters. [SYMBOLS] is the one table of known symbols, with how each is written in both;
+//! characters it doesn't know are kept, and escaped by each emitter where its math would
+//! read them as markup.
+
+/// Role of a symbol in the grammar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Const,
+    Left,
+    Right,
+    /// Function applied to one argument
+    Unary,
+    /// Function applied to two arguments
+    Binary,
+    /// `stackrel`: the first argument set over the second
+    Over,
+    /// `text`: the argument as text
+    Text,
+    Frac,
+    Sub,
+    Sup,
+}
+
+use Kind::*;
+
+/// An AsciiMath symbol and how it is written in each target
+///
+/// For unary and binary functions, `latex` is a template where `#` marks each argument and
+/// `typst` is the name of the function called with them.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: &'static str,
+    pub latex: &'static str,
+    pub typst: &'static str,
+    pub kind: Kind,
+}
+
+const fn symbol(
+    name: &'static str,
+    latex: &'static str,
+    typst: &'static str,
+    kind: Kind,
+) -> Symbol {
+    Symbol {
+        name,
+        latex,
+        typst,
+        kind,
+    }
+}
+
+pub const SYMBOLS: &[Symbol] = &[
+    // Greek letters
+    symbol("alpha", "\\alpha", "alpha", Const),
+    symbol("beta", "\\beta", "beta", Const),
+    symbol("gamma", "\\gamma", "gamma", Const),
+    symbol("Gamma", "\\Gamma", "Gamma", Const),
+    symbol("delta", "\\delta", "delta", Const),
+    symbol("Delta", "\\Delta", "Delta", Const),
+    symbol("epsilon", "\\epsilon", "epsilon", Const),
+    symbol("varepsilon", "\\varepsilon", "epsilon.alt", Const),
+    symbol("zeta", "\\zeta", "zeta", Const),
+    symbol("eta", "\\eta", "eta", Const),
+    symbol("theta", "\\theta", "theta", Const),
+    symbol("Theta", "\\Theta", "Theta", Const),
+    symbol("vartheta", "\\vartheta", "theta.alt", Const),
+    symbol("iota", "\\iota", "iota", Const),
+    symbol("kappa", "\\kappa", "kappa", Const),
+    symbol("lambda", "\\lambda", "lambda", Const),
+    symbol("Lambda", "\\Lambda", "Lambda", Const),
+    symbol("mu", "\\mu", "mu", Const),
+    symbol("nu", "\\nu", "nu", Const),
+    symbol("xi", "\\xi", "xi", Const),
+    symbol("Xi", "\\Xi", "Xi", Const),
+    symbol("pi", "\\pi", "pi", Const),
+    symbol("Pi", "\\Pi", "Pi", Const),
+    symbol("rho", "\\rho", "rho", Const),
+    symbol("sigma", "\\sigma", "sigma", Const),
+    symbol("Sigma", "\\Sigma", "Sigma", Const),
+    symbol("tau", "\\tau", "tau", Const),
+    symbol("upsilon", "\\upsilon", "upsilon", Const),
+    symbol("phi", "\\phi", "phi.alt", Const),
+    symbol("Phi", "\\Phi", "Phi", Const),
+    symbol("varphi", "\\varphi", "phi", Const),
+    symbol("chi", "\\chi", "chi", Const),
+    symbol("psi", "\\psi", "psi", Const),
+    symbol("Psi", "\\Psi", "Psi", Const),
+    symbol("omega", "\\omega", "omega", Const),
+    symbol("Omega", "\\Omega", "Omega", Const),
+    // Operators
+    symbol("*", "\\cdot", "dot.op", Const),
+    symbol("**", "\\ast", "ast", Const),
+    symbol("***", "\\star", "star", Const),
+    symbol("//", "/", "slash", Const),
+    symbol("\\\\", "\\backslash", "backslash", Const),
+    symbol("xx", "\\times", "times", Const),
+    symbol("-:", "\\div", "div", Const),
+    symbol("@", "\\circ", "compose", Const),
+    symbol("o+", "\\oplus", "plus.circle", Const),
+    symbol("ox", "\\otimes", "times.circle", Const),
+    symbol("o.", "\\odot", "dot.circle", Const),
+    symbol("sum", "\\sum", "sum", Const),
+    symbol("prod", "\\prod", "product", Const),
+    symbol("^^", "\\wedge", "and", Const),
+    symbol("vv", "\\vee", "or", Const),
+    symbol("nn", "\\cap", "sect", Const),
+    symbol("uu", "\\cup", "union", Const),
+    symbol("int", "\\int", "integral", Const),
+    symbol("oint", "\\oint", "integral.cont", Const),
+    // Relations
+    symbol("!=", "\\neq", "!=", Const),
+    symbol("<=", "\\leq", "<=", Const),
+    symbol(">=", "\\geq", ">=", Const),
+    symbol("-<", "\\prec", "prec", Const),
+    symbol(">-", "\\succ", "succ", Const),
+    symbol("in", "\\in", "in", Const),
+    symbol("!in", "\\notin", "in.not", Const),
+    symbol("sub", "\\subset", "subset", Const),
+    symbol("sup", "\\supset", "supset", Const),
+    symbol("sube", "\\subseteq", "subset.eq", Const),
+    symbol("supe", "\\supseteq", "supset.eq", Const),
+    symbol("-=", "\\equiv", "equiv", Const),
+    symbol("~=", "\\cong", "tilde.equiv", Const),
+    symbol("~~", "\\approx", "approx", Const),
+    symbol("~", "\\sim", "tilde.op", Const),
+    symbol("prop", "\\propto", "prop", Const),
+    // Logic
+    symbol("and", "\\text{ and }", "\" and \"", Const),
+    symbol("or", "\\text{ or }", "\" or \"", Const),
+    symbol("not", "\\neg", "not", Const),
+    symbol("=>", "\\Rightarrow", "=>", Const),
+    symbol("<=>", "\\Leftrightarrow", "<=>", Const),
+    symbol("AA", "\\forall", "forall", Const),
+    symbol("EE", "\\exists", "exists", Const),
+    symbol("_|_", "\\bot", "bot", Const),
+    symbol("TT", "\\top", "top", Const),
+    symbol("|--", "\\vdash", "tack.r", Const),
+    symbol("|==", "\\models", "models", Const),
+    // Miscellaneous
+    symbol("oo", "\\infty", "infinity", Const),
+    symbol("del", "\\partial", "diff", Const),
+    symbol("grad", "\\nabla", "nabla", Const),
+    symbol("O/", "\\emptyset", "emptyset", Const),
+    symbol("aleph", "\\aleph", "aleph", Const),
+    symbol("...", "\\ldots", "dots.h", Const),
+    symbol("cdots", "\\cdots", "dots.c", Const),
+    symbol("NN", "\\mathbb{N}", "NN", Const),
+    symbol("ZZ", "\\mathbb{Z}", "ZZ", Const),
+    symbol("QQ", "\\mathbb{Q}", "QQ", Const),
+    symbol("RR", "\\mathbb{R}", "RR", Const),
+    symbol("CC", "\\mathbb{C}", "CC", Const),
+    // Arrows
+    symbol("->", "\\to", "->", Const),
+    symbol("|->", "\\mapsto", "|->", Const),
+    symbol("uarr", "\\uparrow", "arrow.t", Const),
+    symbol("darr", "\\downarrow", "arrow.b", Const),
+    symbol("rarr", "\\rightarrow", "arrow.r", Const),
+    symbol("larr", "\\leftarrow", "arrow.l", Const),
+    symbol("harr", "\\leftrightarrow", "arrow.l.r", Const),
+    symbol("rArr", "\\Rightarrow", "arrow.r.double", Const),
+    symbol("lArr", "\\Leftarrow", "arrow.l.double", Const),
+    symbol("hArr", "\\Leftrightarrow", "arrow.l.r.double", Const),
+    // Functions
+    symbol("sin", "\\sin", "sin", Const),
+    symbol("cos", "\\cos", "cos", Const),
+    symbol("tan", "\\tan", "tan", Const),
+    symbol("sec", "\\sec", "sec", Const),
+    symbol("csc", "\\csc", "csc", Const),
+    symbol("cot", "\\cot", "cot", Const),
+    symbol("sinh", "\\sinh", "sinh", Const),
+    symbol("cosh", "\\cosh", "cosh", Const),
+    symbol("tanh", "\\tanh", "tanh", Const),
+    symbol("log", "\\log", "log", Const),
+    symbol("ln", "\\ln", "ln", Const),
+    symbol("exp", "\\exp", "exp", Const),
+    symbol("det", "\\det", "det", Const),
+    symbol("dim", "\\dim", "dim", Const),
+    symbol("gcd", "\\gcd", "gcd", Const),
+    symbol("lim", "\\lim", "lim", Const),
+    symbol("max", "\\max", "max", Const),
+    symbol("min", "\\min", "min", Const),
+    // Unary and binary functions
+    symbol("sqrt", "\\sqrt{#}", "sqrt", Unary),
+    symbol("abs", "\\left|#\\right|", "abs", Unary),
+    symbol("floor", "\\lfloor #\\rfloor", "floor", Unary),
+    symbol("ceil", "\\lceil #\\rceil", "ceil", Unary),
+    symbol("hat", "\\hat{#}", "hat", Unary),
+    symbol("bar", "\\overline{#}", "overline", Unary),
+    symbol("vec", "\\vec{#}", "arrow", Unary),
+    symbol("dot", "\\dot{#}", "dot", Unary),
+    symbol("ddot", "\\ddot{#}", "dot.double", Unary),
+    symbol("ul", "\\underline{#}", "underline", Unary),
+    symbol("bb", "\\mathbf{#}", "bold", Unary),
+    symbol("cc", "\\mathcal{#}", "cal", Unary),
+    symbol("tt", "\\mathtt{#}", "mono", Unary),
+    symbol("text", "", "", Text),
+    symbol("frac", "\\frac{#}{#}", "frac", Binary),
+    symbol("root", "\\sqrt[#]{#}", "root", Binary),
+    symbol("stackrel", "\\stackrel{#}{#}", "", Over),
+    // Grouping
+    symbol("(", "(", "(", Left),
+    symbol(")", ")", ")", Right),
+    symbol("[", "[", "[", Left),
+    symbol("]", "]", "]", Right),
+    symbol("{", "\\{", "\\{", Left),
+    symbol("}", "\\}", "\\}", Right),
+    symbol("(:", "\\langle", "angle.l", Left),
+    symbol(":)", "\\rangle", "angle.r", Right),
+    symbol("{:", "", "", Left),
+    symbol(":}", "", "", Right),
+    symbol("/", "/", "/", Frac),
+    symbol("_", "_", "_", Sub),
+    symbol("^", "^", "^", Sup),
+];
+
+/// A parsed AsciiMath expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A symbol of [SYMBOLS], where no argument follows it
+    Symbol(&'static Symbol),
+    /// A character [SYMBOLS] doesn't know
+    Char(char),
+    Number(String),
+    /// A quoted string, or the argument of `text`
+    Text(String),
+    /// Content between brackets; `close` is missing for an unclosed bracket
+    Group {
+        open: &'static Symbol,
+        content: Vec<Expr>,
+        close: Option<&'static Symbol>,
+    },
+    /// A unary, binary or `stackrel` function and its arguments
+    Apply(&'static Symbol, Vec<Expr>),
+    Frac(Box<Expr>, Box<Expr>),
+    Script {
+        base: Box<Expr>,
+        sub: Option<Box<Expr>>,
+        sup: Option<Box<Expr>>,
+    },
+    /// An argument missing at the end of the source
+    Missing,
+}
+
+impl Expr {
+    /// Content of a bracket group, which functions and scripts take without the brackets
+    pub fn argument(&self) -> Option<&[Expr]> {
+        match self {
+            Expr::Group { content, .. } => Some(content),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Symbol(&'static Symbol),
+    Char(char),
+    Number(String),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    /// Byte range of the token in the source, for `text` arguments
+    start: usize,
+    end: usize,
+}
+
+impl Token {
+    fn grammar(&self) -> Kind {
+        match self.kind {
+            TokenKind::Symbol(symbol) => symbol.kind,
+            _ => Const,
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut rest = source.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let start = source.len() - rest.len();
+        let (kind, length) = if c == '"' {
+            let end = rest[1..].find('"').map_or(rest.len(), |end| end + 2);
+            let text = rest[1..end].trim_end_matches('"');
+            (TokenKind::Text(text.to_string()), end)
+        } else if c.is_ascii_digit() {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(rest.len());
+            // A trailing period is punctuation, not part of the number
+            let number = rest[..end].trim_end_matches('.');
+            (TokenKind::Number(number.to_string()), number.len())
+        } else {
+            match SYMBOLS
+                .iter()
+                .filter(|symbol| rest.starts_with(symbol.name))
+                .max_by_key(|symbol| symbol.name.len())
+            {
+                Some(symbol) => (TokenKind::Symbol(symbol), symbol.name.len()),
+                None => (TokenKind::Char(c), c.len_utf8()),
+            }
+        };
+        tokens.push(Token {
+            kind,
+            start,
+            end: start + length,
+        });
+        rest = rest[length..].trim_start();
+    }
+    tokens
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<Kind> {
+        self.tokens.get(self.position).map(Token::grammar)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    /// Source of the tokens from index `from` up to `to`, exclusive
+    fn span(&self, from: usize, to: usize) -> &str {
+        let to = to.min(self.tokens.len());
+        if from >= to {
+            return "";
+        }
+        &self.source[self.tokens[from].start..self.tokens[to - 1].end]
+    }
+
+    fn expression(&mut self, in_group: bool) -> Vec<Expr> {
+        let mut out = Vec::new();
+        while let Some(kind) = self.peek() {
+            if kind == Right && in_group {
+                break;
+            }
+            let left = self.intermediate();
+            if self.peek() == Some(Frac) {
+                self.next();
+                let right = self.intermediate();
+                out.push(Expr::Frac(Box::new(left), Box::new(right)));
+            } else {
+                out.push(left);
+            }
+        }
+        out
+    }
+
+    fn intermediate(&mut self) -> Expr {
+        let base = self.simple();
+        let mut script = |kind| {
+            (self.peek() == Some(kind)).then(|| {
+                self.next();
+                Box::new(self.simple())
+            })
+        };
+        let sub = script(Sub);
+        let sup = script(Sup);
+        if sub.is_none() && sup.is_none() {
+            return base;
+        }
+        Expr::Script {
+            base: Box::new(base),
+            sub,
+            sup,
+        }
+    }
+
+    fn simple(&mut self) -> Expr {
+        let Some(token) = self.next() else {
+            return Expr::Missing;
+        };
+        let symbol = match token.kind {
+            TokenKind::Symbol(symbol) => symbol,
+            TokenKind::Char(c) => return Expr::Char(c),
+            TokenKind::Number(number) => return Expr::Number(number),
+            TokenKind::Text(text) => return Expr::Text(text),
+        };
+        match symbol.kind {
+            Left => {
+                let content = self.expression(true);
+                let close = match self.peek() {
+                    Some(Right) => match self.next().map(|token| token.kind) {
+                        Some(TokenKind::Symbol(close)) => Some(close),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                Expr::Group {
+                    open: symbol,
+                    content,
+                    close,
+                }
+            }
+            Unary => Expr::Apply(symbol, vec![self.simple()]),
+            Binary | Over => {
+                let first = self.simple();
+                let second = self.simple();
+                Expr::Apply(symbol, vec![first, second])
+            }
+            Text => {
+                let from = self.position;
+                let argument = self.simple();
+                let text = match &argument {
+                    Expr::Group { close, .. } => {
+                        self.span(from + 1, self.position - usize::from(close.is_some()))
+                    }
+                    _ => self.span(from, self.position),
+                };
+                Expr::Text(text.trim().to_string())
+            }
+            Const | Right | Frac | Sub | Sup => Expr::Symbol(symbol),
+        }
+    }
+}
+
+/// The expressions of the AsciiMath `source`, in order
+pub fn parse(source: &str) -> Vec<Expr> {
+    let mut parser = Parser {
+        source,
+        tokens: tokenize(source),
+        position: 0,
+    };
+    parser.expression(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(name: &str) -> &'static Symbol {
+        SYMBOLS.iter().find(|symbol| symbol.name == name).unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("(a+b)/2"),
+            vec![Expr::Frac(
+                Box::new(Expr::Group {
+                    open: find("("),
+                    content: vec![Expr::Char('a'), Expr::Char('+'), Expr::Char('b')],
+                    close: Some(find(")")),
+                }),
+                Box::new(Expr::Number("2".into())),
+            )]
+        );
+        assert_eq!(
+            parse("x_1^n"),
+            vec![Expr::Script {
+                base: Box::new(Expr::Char('x')),
+                sub: Some(Box::new(Expr::Number("1".into()))),
+                sup: Some(Box::new(Expr::Char('n'))),
+            }]
+        );
+        assert_eq!(
+            parse("text(hello world) sqrt"),
+            vec![
+                Expr::Text("hello world".into()),
+                Expr::Apply(find("sqrt"), vec![Expr::Missing]),
+            ]
+        );
+    }
+}
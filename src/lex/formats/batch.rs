@@ -0,0 +1,313 @@
+//! Batch conversion
+//!
+//! [convert_batch] converts many Lex files in one run. Inputs can be files, directories
+//! (their `.lex` files, recursively with [BatchOptions::recursive]) and glob patterns, where
+//! `*` and `?` match within a path component and `**` matches any number of components:
+//!
+//! ```text
+//! docs/**/*.lex
+//! ```
+//!
+//! Each output is written next to its source, with the format's
+//! [extension](super::Formatter::extension), or under [BatchOptions::out_dir] at the source's
+//! path relative to its input (the directory given, or the part of a glob before its first
+//! pattern). A file that cannot be read, parsed, converted or written is reported in the
+//! [BatchReport] and the run continues with the next one.
+
+use super::registry::FormatRegistry;
+use crate::lex::analysis::search::lex_files;
+use crate::lex::parsing::parse_document;
+use crate::lex::watch::write_atomic;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// Options of a batch conversion
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchOptions {
+    /// Descend into subdirectories of directory inputs
+    pub recursive: bool,
+    /// Directory outputs are written to, mirroring the inputs' structure; next to the
+    /// sources when unset
+    pub out_dir: Option<PathBuf>,
+}
+
+/// A source file to convert
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchInput {
+    pub path: PathBuf,
+    /// Path relative to the input it was found from
+    pub relative: PathBuf,
+}
+
+/// A converted file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchConversion {
+    pub source: PathBuf,
+    pub output: PathBuf,
+}
+
+/// An input or file that could not be converted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchFailure {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+/// Outcome of a batch conversion, in input order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchReport {
+    pub converted: Vec<BatchConversion>,
+    pub failures: Vec<BatchFailure>,
+}
+
+impl BatchReport {
+    /// Whether every file was converted
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Convert every file of `inputs` to `format`
+pub fn convert_batch(
+    inputs: &[&str],
+    format: &str,
+    options: &BatchOptions,
+    registry: &FormatRegistry,
+) -> BatchReport {
+    let mut report = BatchReport::default();
+    let Some(formatter) = registry.get(format) else {
+        report.failures.push(BatchFailure {
+            path: PathBuf::new(),
+            message: format!("Format '{format}' not found"),
+        });
+        return report;
+    };
+    let (files, failures) = expand_inputs(inputs, options.recursive);
+    report.failures = failures;
+
+    for input in files {
+        let output = match &options.out_dir {
+            Some(dir) => dir.join(&input.relative),
+            None => input.path.clone(),
+        }
+        .with_extension(formatter.extension());
+        let converted = std::fs::read_to_string(&input.path)
+            .map_err(|error| error.to_string())
+            .and_then(|source| parse_document(&source))
+            .and_then(|document| {
+                formatter
+                    .serialize(&document)
+                    .map_err(|error| error.to_string())
+            })
+            .and_then(|text| {
+                if let Some(parent) = output.parent() {
+                    std::fs::create_dir_all(parent).map_err(|error| error.to_string())?;
+                }
+                write_atomic(&output, &text).map_err(|error| error.to_string())
+            });
+        match converted {
+            Ok(()) => report.converted.push(BatchConversion {
+                source: input.path,
+                output,
+            }),
+            Err(message) => report.failures.push(BatchFailure {
+                path: input.path,
+                message,
+            }),
+        }
+    }
+    report
+}
+
+/// Files of `inputs`, each once, with the inputs that match none
+pub fn expand_inputs(inputs: &[&str], recursive: bool) -> (Vec<BatchInput>, Vec<BatchFailure>) {
+    let mut files = Vec::new();
+    let mut failures = Vec::new();
+    let mut seen = BTreeSet::new();
+    for input in inputs {
+        let found = if input.contains(['*', '?']) {
+            glob_files(input)
+        } else {
+            path_files(Path::new(input), recursive)
+        };
+        match found {
+            Ok(found) if found.is_empty() => failures.push(BatchFailure {
+                path: PathBuf::from(input),
+                message: "No Lex files found".to_string(),
+            }),
+            Ok(found) => files.extend(
+                found
+                    .into_iter()
+                    .filter(|file| seen.insert(file.path.clone())),
+            ),
+            Err(message) => failures.push(BatchFailure {
+                path: PathBuf::from(input),
+                message,
+            }),
+        }
+    }
+    (files, failures)
+}
+
+/// A file, or the `.lex` files of a directory
+fn path_files(path: &Path, recursive: bool) -> Result<Vec<BatchInput>, String> {
+    if !path.is_dir() {
+        if !path.exists() {
+            return Err("No such file or directory".to_string());
+        }
+        let name = path.file_name().map(PathBuf::from).unwrap_or_default();
+        return Ok(vec![BatchInput {
+            path: path.to_path_buf(),
+            relative: name,
+        }]);
+    }
+    let mut paths = Vec::new();
+    if recursive {
+        lex_files(path, &mut paths).map_err(|error| error.to_string())?;
+    } else {
+        for entry in std::fs::read_dir(path).map_err(|error| error.to_string())? {
+            let file = entry.map_err(|error| error.to_string())?.path();
+            if file.is_file() && file.extension().and_then(|ext| ext.to_str()) == Some("lex") {
+                paths.push(file);
+            }
+        }
+    }
+    paths.sort();
+    Ok(paths
+        .into_iter()
+        .map(|file| BatchInput {
+            relative: file.strip_prefix(path).unwrap_or(&file).to_path_buf(),
+            path: file,
+        })
+        .collect())
+}
+
+/// Files matching `pattern`, relative to the part of it before the first wildcard
+fn glob_files(pattern: &str) -> Result<Vec<BatchInput>, String> {
+    let components: Vec<&str> = pattern.split('/').collect();
+    let literal = components
+        .iter()
+        .take_while(|component| !component.contains(['*', '?']))
+        .count();
+    let base = match components[..literal].join("/") {
+        base if base.is_empty() && pattern.starts_with('/') => PathBuf::from("/"),
+        base if base.is_empty() => PathBuf::from("."),
+        base => PathBuf::from(base),
+    };
+    let rest = &components[literal..];
+
+    let mut paths = Vec::new();
+    all_files(&base, &mut paths).map_err(|error| error.to_string())?;
+    paths.sort();
+    Ok(paths
+        .into_iter()
+        .filter_map(|file| {
+            let relative = file.strip_prefix(&base).ok()?.to_path_buf();
+            let parts: Vec<String> = relative
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            let parts: Vec<&str> = parts.iter().map(String::as_str).collect();
+            glob_match(rest, &parts).then_some(BatchInput {
+                path: file,
+                relative,
+            })
+        })
+        .collect())
+}
+
+fn all_files(dir: &Path, out: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            all_files(&path, out)?;
+        } else {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Whether path components match pattern components, `**` matching any number of them
+fn glob_match(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| glob_match(rest, &path[skip..])),
+        Some((first, rest)) => {
+            !path.is_empty()
+                && component_match(first.as_bytes(), path[0].as_bytes())
+                && glob_match(rest, &path[1..])
+        }
+    }
+}
+
+/// Whether a path component matches a pattern with `*` and `?`
+fn component_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| component_match(rest, &text[skip..])),
+        Some((b'?', rest)) => !text.is_empty() && component_match(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && component_match(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        let matches = |pattern: &str, path: &str| {
+            let pattern: Vec<&str> = pattern.split('/').collect();
+            let path: Vec<&str> = path.split('/').collect();
+            glob_match(&pattern, &path)
+        };
+        assert!(matches("*.lex", "notes.lex"));
+        assert!(!matches("*.lex", "guide/notes.lex"));
+        assert!(matches("**/*.lex", "notes.lex"));
+        assert!(matches("**/*.lex", "a/b/notes.lex"));
+        assert!(matches("ch?/*.lex", "ch1/intro.lex"));
+        assert!(!matches("ch?/*.lex", "ch10/intro.lex"));
+    }
+
+    #[test]
+    fn test_convert_batch() {
+        let root = std::env::temp_dir().join(format!("lex-batch-{}", std::process::id()));
+        let docs = root.join("docs");
+        std::fs::create_dir_all(docs.join("guide")).unwrap();
+        std::fs::write(docs.join("intro.lex"), "Intro\n\nText.\n").unwrap();
+        std::fs::write(docs.join("guide/setup.lex"), "Setup\n\nText.\n").unwrap();
+        std::fs::write(docs.join("notes.txt"), "Not Lex.\n").unwrap();
+        // Not valid UTF-8, so it cannot be read
+        std::fs::write(docs.join("guide/broken.lex"), [0xff, 0xfe]).unwrap();
+        let registry = FormatRegistry::with_defaults();
+        let docs_input = docs.to_string_lossy().into_owned();
+
+        let (flat, _) = expand_inputs(&[&docs_input], false);
+        assert_eq!(flat.len(), 1);
+
+        let out = root.join("out");
+        let options = BatchOptions {
+            recursive: true,
+            out_dir: Some(out.clone()),
+        };
+        let missing = root.join("missing.lex").to_string_lossy().into_owned();
+        let report = convert_batch(&[&docs_input, &missing], "tag", &options, &registry);
+        assert_eq!(report.converted.len(), 2);
+        assert!(out.join("intro.xml").exists());
+        assert!(out.join("guide/setup.xml").exists());
+        let failed: Vec<_> = report.failures.iter().map(|f| f.path.clone()).collect();
+        assert_eq!(
+            failed,
+            vec![root.join("missing.lex"), docs.join("guide/broken.lex")]
+        );
+        assert!(!report.is_success());
+
+        let pattern = format!("{docs_input}/**/s*.lex");
+        let report = convert_batch(&[&pattern], "lex", &BatchOptions::default(), &registry);
+        assert!(report.is_success());
+        assert_eq!(report.converted[0].output, docs.join("guide/setup.lex"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,205 @@
+//! Delimited data (CSV, TSV)
+//!
+//! [import_delimited] turns CSV or TSV data into a [table block](crate::lex::tables), with
+//! columns padded to a common width (the formatter's `tables` rule keeps them aligned). The
+//! `csv` and `tsv` formats go the other way, exporting the rows of every table block in a
+//! document, one blank line between tables.
+
+use crate::lex::ast::{ContentItem, Document};
+use crate::lex::formats::registry::{FormatError, Formatter};
+pub use crate::lex::tables::{render_rows, table_block, table_rows, ColumnAlignment, TABLE_LABEL};
+
+/// Field separator of delimited data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    Comma,
+    Tab,
+}
+
+impl Delimiter {
+    pub fn as_char(self) -> char {
+        match self {
+            Delimiter::Comma => ',',
+            Delimiter::Tab => '\t',
+        }
+    }
+}
+
+/// Parse delimited data into rows of fields
+///
+/// Fields may be quoted with `"`, doubling quotes inside; quoted fields may span lines.
+/// Blank lines are skipped.
+pub fn parse_delimited(
+    source: &str,
+    delimiter: Delimiter,
+) -> Result<Vec<Vec<String>>, FormatError> {
+    let separator = delimiter.as_char();
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            '\r' => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                if row.len() > 1 || !row[0].is_empty() {
+                    rows.push(std::mem::take(&mut row));
+                } else {
+                    row.clear();
+                }
+            }
+            c if c == separator => row.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(FormatError::ParseError("unclosed quoted field".to_string()));
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+/// Write rows as delimited data, quoting fields where needed
+pub fn write_delimited(rows: &[Vec<String>], delimiter: Delimiter) -> String {
+    let separator = delimiter.as_char();
+    let mut out = String::new();
+    for row in rows {
+        let fields: Vec<String> = row
+            .iter()
+            .map(|field| {
+                if field.contains([separator, '"', '\n', '\r']) {
+                    format!("\"{}\"", field.replace('"', "\"\""))
+                } else {
+                    field.clone()
+                }
+            })
+            .collect();
+        out.push_str(&fields.join(&separator.to_string()));
+        out.push('\n');
+    }
+    out
+}
+
+/// Convert delimited data into a Lex table block titled `subject`
+pub fn import_delimited(
+    subject: &str,
+    source: &str,
+    delimiter: Delimiter,
+) -> Result<String, FormatError> {
+    Ok(table_block(subject, &parse_delimited(source, delimiter)?))
+}
+
+/// Rows of every table block in `doc`, in document order
+pub fn document_tables(doc: &Document) -> Vec<Vec<Vec<String>>> {
+    doc.root
+        .iter_all_nodes()
+        .filter_map(|item| match item {
+            ContentItem::VerbatimBlock(verbatim) => table_rows(verbatim),
+            _ => None,
+        })
+        .collect()
+}
+
+fn export(doc: &Document, delimiter: Delimiter) -> String {
+    document_tables(doc)
+        .iter()
+        .map(|rows| write_delimited(rows, delimiter))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Table blocks as CSV
+pub struct CsvFormatter;
+
+impl Formatter for CsvFormatter {
+    fn name(&self) -> &str {
+        "csv"
+    }
+
+    fn serialize(&self, doc: &Document) -> Result<String, FormatError> {
+        Ok(export(doc, Delimiter::Comma))
+    }
+
+    fn description(&self) -> &str {
+        "Table blocks as comma-separated values"
+    }
+}
+
+/// Table blocks as TSV
+pub struct TsvFormatter;
+
+impl Formatter for TsvFormatter {
+    fn name(&self) -> &str {
+        "tsv"
+    }
+
+    fn serialize(&self, doc: &Document) -> Result<String, FormatError> {
+        Ok(export(doc, Delimiter::Tab))
+    }
+
+    fn description(&self) -> &str {
+        "Table blocks as tab-separated values"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    #[test]
+    fn test_parse_and_write_delimited() {
+        let csv = "Item,Note\r\nApples,\"red, \"\"crisp\"\"\"\n\nPears,\"two\nlines\"\n";
+        let rows = parse_delimited(csv, Delimiter::Comma).unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                vec!["Item", "Note"],
+                vec!["Apples", "red, \"crisp\""],
+                vec!["Pears", "two\nlines"],
+            ]
+        );
+        assert_eq!(
+            write_delimited(&rows, Delimiter::Comma),
+            "Item,Note\nApples,\"red, \"\"crisp\"\"\"\nPears,\"two\nlines\"\n"
+        );
+        assert!(parse_delimited("a,\"b", Delimiter::Comma).is_err());
+    }
+
+    #[test]
+    fn test_import_and_export() {
+        let tsv = "Item\tPrice\nApples\t1.20\nA|B\t3\n";
+        let block = import_delimited("Prices", tsv, Delimiter::Tab).unwrap();
+        assert_eq!(
+            block,
+            "Prices:\n    | Item   | Price |\n    | Apples | 1.20  |\n    | A\\|B   | 3     |\n:: table\n"
+        );
+
+        let doc = parse_document(&format!("Title\n\nSome prices.\n\n{block}")).unwrap();
+        assert_eq!(
+            CsvFormatter.serialize(&doc).unwrap(),
+            "Item,Price\nApples,1.20\nA|B,3\n"
+        );
+        assert_eq!(
+            TsvFormatter.serialize(&doc).unwrap(),
+            "Item\tPrice\nApples\t1.20\nA|B\t3\n"
+        );
+    }
+}
@@ -0,0 +1,380 @@
+//! External format adapters
+//!
+//! A format can live outside Lex, in an adapter program named `lex-format-<name>` on the
+//! `PATH`. [FormatRegistry::discover_external] finds the adapters and registers each as an
+//! [ExternalFormatter] named `<name>`, so exotic formats are supported without being linked
+//! into Lex. Documents travel as the [JSON AST](super::json), and the adapter is run with
+//! one of three commands:
+//!
+//! - `describe` prints what the adapter does as a JSON object, every field optional:
+//!   `{"description": "Word documents", "extension": "docx", "serialize": true,
+//!   "parse": false}`. Adapters serialize by default and do not parse.
+//! - `serialize [key=value ...]` reads a JSON AST on stdin and writes the document in its
+//!   format to stdout. Format parameters are passed as arguments, sorted by key.
+//! - `parse` reads a document in its format on stdin and writes its JSON AST to stdout.
+//!
+//! An adapter that exits unsuccessfully fails the conversion with its stderr as the message.
+//!
+//! [FormatRegistry::discover_external]: super::FormatRegistry::discover_external
+
+use crate::lex::ast::Document;
+use crate::lex::formats::json::JsonDocument;
+use crate::lex::formats::registry::{FormatError, Formatter};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Prefix of adapter program names
+pub const ADAPTER_PREFIX: &str = "lex-format-";
+
+/// What an adapter reports through `describe`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct AdapterDescription {
+    pub description: String,
+    /// File extension of output, without the dot; the format name when empty
+    pub extension: String,
+    pub serialize: bool,
+    pub parse: bool,
+}
+
+impl Default for AdapterDescription {
+    fn default() -> Self {
+        Self {
+            description: String::new(),
+            extension: String::new(),
+            serialize: true,
+            parse: false,
+        }
+    }
+}
+
+/// A format implemented by an adapter program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalFormatter {
+    name: String,
+    program: PathBuf,
+    info: AdapterDescription,
+}
+
+impl ExternalFormatter {
+    /// Run `program` with `describe` and make a formatter named `name` from its answer
+    pub fn probe(
+        name: impl Into<String>,
+        program: impl Into<PathBuf>,
+    ) -> Result<Self, FormatError> {
+        let name = name.into();
+        let program = program.into();
+        let output = run(&name, &program, &["describe".to_string()], &[])
+            .map_err(FormatError::FormatNotFound)?;
+        let info = serde_json::from_slice(&output).map_err(|err| {
+            FormatError::FormatNotFound(format!("{name}: invalid description: {err}"))
+        })?;
+        Ok(Self {
+            name,
+            program,
+            info,
+        })
+    }
+
+    /// Path of the adapter program
+    pub fn program(&self) -> &Path {
+        &self.program
+    }
+}
+
+impl Formatter for ExternalFormatter {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn extension(&self) -> &str {
+        if self.info.extension.is_empty() {
+            &self.name
+        } else {
+            &self.info.extension
+        }
+    }
+
+    fn serialize(&self, doc: &Document) -> Result<String, FormatError> {
+        self.serialize_with_params(doc, &HashMap::new())
+    }
+
+    fn serialize_with_params(
+        &self,
+        doc: &Document,
+        params: &HashMap<String, String>,
+    ) -> Result<String, FormatError> {
+        let bytes = self.serialize_bytes(doc, params)?;
+        String::from_utf8(bytes).map_err(|_| {
+            FormatError::SerializationError(format!("{}: output is not UTF-8", self.name))
+        })
+    }
+
+    fn serialize_bytes(
+        &self,
+        doc: &Document,
+        params: &HashMap<String, String>,
+    ) -> Result<Vec<u8>, FormatError> {
+        if !self.info.serialize {
+            return Err(FormatError::SerializationError(format!(
+                "Format '{}' does not support serialization",
+                self.name
+            )));
+        }
+        let ast = serde_json::to_vec(&JsonDocument::from(doc))
+            .map_err(|err| FormatError::SerializationError(err.to_string()))?;
+        let mut params: Vec<_> = params.iter().collect();
+        params.sort();
+        let args: Vec<String> = std::iter::once("serialize".to_string())
+            .chain(
+                params
+                    .into_iter()
+                    .map(|(key, value)| format!("{key}={value}")),
+            )
+            .collect();
+        run(&self.name, &self.program, &args, &ast).map_err(FormatError::SerializationError)
+    }
+
+    fn supports_serialization(&self) -> bool {
+        self.info.serialize
+    }
+
+    fn supports_parsing(&self) -> bool {
+        self.info.parse
+    }
+
+    fn parse(&self, source: &str) -> Result<Document, FormatError> {
+        if !self.info.parse {
+            return Err(FormatError::ParseError(format!(
+                "Format '{}' does not support parsing",
+                self.name
+            )));
+        }
+        let output = run(
+            &self.name,
+            &self.program,
+            &["parse".to_string()],
+            source.as_bytes(),
+        )
+        .map_err(FormatError::ParseError)?;
+        let json: JsonDocument = serde_json::from_slice(&output).map_err(|err| {
+            FormatError::ParseError(format!("{}: invalid JSON AST: {err}", self.name))
+        })?;
+        json.into_document()
+    }
+
+    fn description(&self) -> &str {
+        &self.info.description
+    }
+}
+
+/// Run adapter `program` with `args`, piping `input` to it; returns its stdout
+fn run(name: &str, program: &Path, args: &[String], input: &[u8]) -> Result<Vec<u8>, String> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("cannot run adapter '{}': {err}", program.display()))?;
+    let input = input.to_vec();
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+    let output = child.wait_with_output().map_err(|err| err.to_string())?;
+    // An adapter may exit without reading all of its input; its status decides the outcome.
+    let _ = writer.join();
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "adapter '{name}' failed: {} ({})",
+            stderr.trim(),
+            output.status
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Adapter programs in `dirs`, as format names and paths
+///
+/// When several directories hold an adapter for the same format, the first one wins, as
+/// it would on the `PATH`. Adapters are returned sorted by name.
+pub fn find_adapters(dirs: impl IntoIterator<Item = PathBuf>) -> Vec<(String, PathBuf)> {
+    let mut found: Vec<(String, PathBuf)> = Vec::new();
+    for dir in dirs {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut adapters: Vec<_> = entries
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let path = entry.path();
+                let name = adapter_name(&path)?;
+                is_executable(&path).then_some((name, path))
+            })
+            .collect();
+        adapters.sort();
+        for (name, path) in adapters {
+            if !found.iter().any(|(known, _)| *known == name) {
+                found.push((name, path));
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+/// Directories of the `PATH`
+pub fn path_dirs() -> Vec<PathBuf> {
+    std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect())
+        .unwrap_or_default()
+}
+
+/// Format name of the adapter at `path`, if its file name is that of an adapter
+fn adapter_name(path: &Path) -> Option<String> {
+    let file_name = if cfg!(windows) {
+        path.file_stem()?
+    } else {
+        path.file_name()?
+    };
+    let name = file_name.to_str()?.strip_prefix(ADAPTER_PREFIX)?;
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("exe"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adapter_name() {
+        assert_eq!(
+            adapter_name(Path::new("/usr/bin/lex-format-docx")),
+            Some("docx".to_string())
+        );
+        assert_eq!(adapter_name(Path::new("/usr/bin/lex-format-")), None);
+        assert_eq!(adapter_name(Path::new("/usr/bin/lex")), None);
+    }
+
+    #[cfg(unix)]
+    mod adapters {
+        use super::super::*;
+        use crate::lex::formats::FormatRegistry;
+        use crate::lex::parsing::parse_document;
+
+        fn script(dir: &Path, name: &str, body: &str, mode: u32) -> PathBuf {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::create_dir_all(dir).unwrap();
+            let path = dir.join(name);
+            std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+            path
+        }
+
+        #[test]
+        fn test_discover_and_convert() {
+            let root = std::env::temp_dir().join(format!("lex-external-{}", std::process::id()));
+            let first = root.join("first");
+            let second = root.join("second");
+            // Passes the JSON AST through, so its "format" is the JSON AST itself
+            script(
+                &first,
+                "lex-format-echo",
+                r#"case "$1" in
+describe) echo '{"description": "Echo", "extension": "ast", "parse": true}' ;;
+serialize) cat ;;
+parse) cat ;;
+esac"#,
+                0o755,
+            );
+            script(
+                &first,
+                "lex-format-shout",
+                r#"case "$1" in
+describe) echo '{}' ;;
+serialize) cat >/dev/null; echo "$@" ;;
+esac"#,
+                0o755,
+            );
+            script(&second, "lex-format-echo", "exit 1", 0o755);
+            script(&second, "lex-format-json", "echo '{}'", 0o755);
+            script(&second, "lex-format-broken", "exit 1", 0o755);
+            script(&second, "lex-format-plain", "echo '{}'", 0o644);
+
+            let adapters = find_adapters([first.clone(), second.clone()]);
+            let names: Vec<_> = adapters.iter().map(|(name, _)| name.as_str()).collect();
+            assert_eq!(names, ["broken", "echo", "json", "shout"]);
+            assert_eq!(adapters[1].1, first.join("lex-format-echo"));
+
+            let mut registry = FormatRegistry::with_defaults();
+            let added = registry.discover_external_in([first, second]);
+            assert_eq!(added, ["echo", "shout"]);
+            assert!(!registry.has("broken"));
+            assert!(registry
+                .get("json")
+                .unwrap()
+                .description()
+                .starts_with("Complete document AST"));
+
+            let echo = registry.get("echo").unwrap();
+            assert_eq!(echo.extension(), "ast");
+            assert_eq!(echo.description(), "Echo");
+            assert!(echo.supports_parsing());
+
+            let doc = parse_document("Guide\n\nHello *world*.\n").unwrap();
+            let ast = registry.serialize(&doc, "echo").unwrap();
+            assert!(ast.contains("\"lex-ast\""));
+            assert_eq!(registry.parse(&ast, "echo").unwrap(), doc);
+
+            let shout = registry.get("shout").unwrap();
+            assert_eq!(shout.extension(), "shout");
+            assert!(!shout.supports_parsing());
+            let params = HashMap::from([
+                ("b".to_string(), "2".to_string()),
+                ("a".to_string(), "1".to_string()),
+            ]);
+            assert_eq!(
+                registry
+                    .serialize_with_params(&doc, "shout", &params)
+                    .unwrap(),
+                "serialize a=1 b=2\n"
+            );
+            assert!(matches!(
+                registry.parse("text", "shout"),
+                Err(FormatError::ParseError(_))
+            ));
+
+            let failing = script(&root, "failing", "echo broken >&2; exit 3", 0o755);
+            let error = ExternalFormatter {
+                name: "failing".to_string(),
+                program: failing,
+                info: AdapterDescription::default(),
+            }
+            .serialize(&doc)
+            .unwrap_err();
+            assert!(
+                matches!(error, FormatError::SerializationError(ref msg) if msg.contains("broken"))
+            );
+
+            let _ = std::fs::remove_dir_all(&root);
+        }
+    }
+}
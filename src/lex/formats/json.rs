@@ -0,0 +1,702 @@
+//! JSON AST
+//!
+//! The `json` format writes the complete AST of a document as JSON, every element with its
+//! source location and attached annotations, so tools in other languages can read Lex
+//! documents, and write them, without a Lex parser. Parsing the format rebuilds the AST
+//! written: a document goes through JSON and back unchanged.
+//!
+//! The schema is that of [JsonDocument], also given as JSON Schema in [SCHEMA]:
+//!
+//! - The document has a `format` (`lex-ast`) and the schema `version`, its document-level
+//!   `annotations` and its `root` session, whose title is the document title.
+//! - Elements are [JsonNode]s, told apart by their `type`: `session`, `paragraph`, `list`,
+//!   `list-item`, `definition`, `annotation`, `verbatim`, `text-line`, `verbatim-line` and
+//!   `blank-lines`. Sessions, paragraphs, lists, list items, definitions and verbatim blocks
+//!   have the `annotations` attached to them.
+//! - Text is Lex inline source (`*strong*`, `[#2]`), with its location. Inline markup is
+//!   parsed again when reading.
+//! - Locations are a byte `span` with the zero-based `line` and `column` of its `start`
+//!   and `end`.
+//!
+//! ```text
+//! {
+//!   "format": "lex-ast",
+//!   "version": 1,
+//!   "annotations": [],
+//!   "root": {
+//!     "title": { "text": "Guide", "location": { "span": { "start": 0, "end": 5 }, ... } },
+//!     "children": {
+//!       "items": [{ "type": "paragraph", "lines": [{ "type": "text-line", ... }], ... }],
+//!       ...
+//! ```
+
+use crate::lex::ast::elements::container::{Container, ContainerPolicy};
+use crate::lex::ast::elements::verbatim::{VerbatimBlockMode, VerbatimGroupItem};
+use crate::lex::ast::elements::{
+    BlankLineGroup, DecorationStyle, Form, Separator, SequenceMarker, VerbatimLine,
+};
+use crate::lex::ast::traits::AstNode;
+use crate::lex::ast::{
+    Annotation, ContentItem, Data, Definition, Document, Label, List, ListItem, Paragraph,
+    Parameter, Range, Session, TextContent, TextLine, Verbatim,
+};
+use crate::lex::formats::registry::{FormatError, Formatter};
+use crate::lex::token::Token;
+use serde::{Deserialize, Serialize};
+
+/// The `format` of JSON AST documents
+pub const FORMAT: &str = "lex-ast";
+
+/// Version of the schema, the `version` of JSON AST documents
+pub const VERSION: u32 = 1;
+
+/// JSON Schema of JSON AST documents
+pub const SCHEMA: &str = include_str!("json/schema.json");
+
+/// Complete document AST as JSON
+pub struct JsonFormatter;
+
+impl Formatter for JsonFormatter {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn serialize(&self, doc: &Document) -> Result<String, FormatError> {
+        serde_json::to_string_pretty(&JsonDocument::from(doc))
+            .map_err(|err| FormatError::SerializationError(err.to_string()))
+    }
+
+    fn supports_parsing(&self) -> bool {
+        true
+    }
+
+    /// The document whose AST `source` holds
+    fn parse(&self, source: &str) -> Result<Document, FormatError> {
+        let json: JsonDocument = serde_json::from_str(source)
+            .map_err(|err| FormatError::ParseError(format!("invalid JSON AST: {err}")))?;
+        json.into_document()
+    }
+
+    fn description(&self) -> &str {
+        "Complete document AST as JSON, with locations, read back without loss"
+    }
+}
+
+/// A document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonDocument {
+    /// Always [FORMAT]
+    pub format: String,
+    /// Schema [VERSION] the document follows
+    pub version: u32,
+    /// Document-level annotations
+    pub annotations: Vec<JsonAnnotation>,
+    /// Root session, titled with the document title
+    pub root: JsonSession,
+}
+
+/// Text and its location
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonText {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<Range>,
+}
+
+/// Children of an element
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonContainer {
+    pub items: Vec<JsonNode>,
+    pub location: Range,
+}
+
+/// Sequence marker of a session or list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonMarker {
+    pub style: DecorationStyle,
+    pub separator: Separator,
+    pub form: Form,
+    /// The marker as written, `1.` or `(a)`
+    pub text: JsonText,
+    pub location: Range,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonSession {
+    pub title: JsonText,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub marker: Option<JsonMarker>,
+    pub children: JsonContainer,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<JsonAnnotation>,
+    pub location: Range,
+}
+
+/// Label and parameters of an annotation or verbatim closing line
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonData {
+    pub label: String,
+    pub label_location: Range,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parameters: Vec<JsonParameter>,
+    pub location: Range,
+}
+
+/// A parameter, whose value is as written, quotes included
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonParameter {
+    pub key: String,
+    pub value: String,
+    pub location: Range,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonAnnotation {
+    pub data: JsonData,
+    pub children: JsonContainer,
+    pub location: Range,
+}
+
+/// A subject of a verbatim block and its lines
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonVerbatimGroup {
+    pub subject: JsonText,
+    pub children: JsonContainer,
+}
+
+/// An element, tagged with its `type`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum JsonNode {
+    Session(JsonSession),
+    Paragraph {
+        /// `text-line` nodes
+        lines: Vec<JsonNode>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        annotations: Vec<JsonAnnotation>,
+        location: Range,
+    },
+    List {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        marker: Option<JsonMarker>,
+        /// `list-item` nodes
+        items: JsonContainer,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        annotations: Vec<JsonAnnotation>,
+        location: Range,
+    },
+    ListItem {
+        marker: JsonText,
+        text: Vec<JsonText>,
+        children: JsonContainer,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        annotations: Vec<JsonAnnotation>,
+        location: Range,
+    },
+    Definition {
+        subject: JsonText,
+        children: JsonContainer,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        annotations: Vec<JsonAnnotation>,
+        location: Range,
+    },
+    Annotation(JsonAnnotation),
+    Verbatim {
+        /// At least one group
+        groups: Vec<JsonVerbatimGroup>,
+        closing: JsonData,
+        mode: VerbatimBlockMode,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        annotations: Vec<JsonAnnotation>,
+        location: Range,
+    },
+    TextLine {
+        content: JsonText,
+        location: Range,
+    },
+    VerbatimLine {
+        content: JsonText,
+        location: Range,
+    },
+    BlankLines {
+        count: usize,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        tokens: Vec<Token>,
+        location: Range,
+    },
+}
+
+impl From<&Document> for JsonDocument {
+    fn from(doc: &Document) -> Self {
+        Self {
+            format: FORMAT.to_string(),
+            version: VERSION,
+            annotations: doc.annotations.iter().map(JsonAnnotation::from).collect(),
+            root: JsonSession::from(&doc.root),
+        }
+    }
+}
+
+impl From<&TextContent> for JsonText {
+    fn from(text: &TextContent) -> Self {
+        Self {
+            text: text.as_string().to_string(),
+            location: text.location.clone(),
+        }
+    }
+}
+
+impl From<&SequenceMarker> for JsonMarker {
+    fn from(marker: &SequenceMarker) -> Self {
+        Self {
+            style: marker.style,
+            separator: marker.separator,
+            form: marker.form,
+            text: JsonText::from(&marker.raw_text),
+            location: marker.location.clone(),
+        }
+    }
+}
+
+impl From<&Session> for JsonSession {
+    fn from(session: &Session) -> Self {
+        Self {
+            title: JsonText::from(&session.title),
+            marker: session.marker.as_ref().map(JsonMarker::from),
+            children: JsonContainer::from(&session.children),
+            annotations: annotations(&session.annotations),
+            location: session.location.clone(),
+        }
+    }
+}
+
+impl From<&Data> for JsonData {
+    fn from(data: &Data) -> Self {
+        Self {
+            label: data.label.value.clone(),
+            label_location: data.label.location.clone(),
+            parameters: data
+                .parameters
+                .iter()
+                .map(|parameter| JsonParameter {
+                    key: parameter.key.clone(),
+                    value: parameter.value.clone(),
+                    location: parameter.location.clone(),
+                })
+                .collect(),
+            location: data.location.clone(),
+        }
+    }
+}
+
+impl From<&Annotation> for JsonAnnotation {
+    fn from(annotation: &Annotation) -> Self {
+        Self {
+            data: JsonData::from(&annotation.data),
+            children: JsonContainer::from(&annotation.children),
+            location: annotation.location.clone(),
+        }
+    }
+}
+
+impl<P: ContainerPolicy> From<&Container<P>> for JsonContainer {
+    fn from(container: &Container<P>) -> Self {
+        Self {
+            items: container.iter().map(JsonNode::from).collect(),
+            location: container.location.clone(),
+        }
+    }
+}
+
+impl From<&ContentItem> for JsonNode {
+    fn from(item: &ContentItem) -> Self {
+        match item {
+            ContentItem::Session(session) => JsonNode::Session(JsonSession::from(session)),
+            ContentItem::Paragraph(paragraph) => JsonNode::Paragraph {
+                lines: paragraph.lines.iter().map(JsonNode::from).collect(),
+                annotations: annotations(&paragraph.annotations),
+                location: paragraph.location.clone(),
+            },
+            ContentItem::List(list) => JsonNode::List {
+                marker: list.marker.as_ref().map(JsonMarker::from),
+                items: JsonContainer::from(&list.items),
+                annotations: annotations(&list.annotations),
+                location: list.location.clone(),
+            },
+            ContentItem::ListItem(list_item) => JsonNode::ListItem {
+                marker: JsonText::from(&list_item.marker),
+                text: list_item.text.iter().map(JsonText::from).collect(),
+                children: JsonContainer::from(&list_item.children),
+                annotations: annotations(&list_item.annotations),
+                location: list_item.location.clone(),
+            },
+            ContentItem::Definition(definition) => JsonNode::Definition {
+                subject: JsonText::from(&definition.subject),
+                children: JsonContainer::from(&definition.children),
+                annotations: annotations(&definition.annotations),
+                location: definition.location.clone(),
+            },
+            ContentItem::Annotation(annotation) => {
+                JsonNode::Annotation(JsonAnnotation::from(annotation))
+            }
+            ContentItem::VerbatimBlock(verbatim) => JsonNode::Verbatim {
+                groups: verbatim
+                    .group()
+                    .map(|group| JsonVerbatimGroup {
+                        subject: JsonText::from(group.subject),
+                        children: JsonContainer::from(group.children),
+                    })
+                    .collect(),
+                closing: JsonData::from(&verbatim.closing_data),
+                mode: verbatim.mode,
+                annotations: annotations(&verbatim.annotations),
+                location: verbatim.location.clone(),
+            },
+            ContentItem::TextLine(line) => JsonNode::TextLine {
+                content: JsonText::from(&line.content),
+                location: line.location.clone(),
+            },
+            ContentItem::VerbatimLine(line) => JsonNode::VerbatimLine {
+                content: JsonText::from(&line.content),
+                location: line.location.clone(),
+            },
+            ContentItem::BlankLineGroup(group) => JsonNode::BlankLines {
+                count: group.count,
+                tokens: group.source_tokens.clone(),
+                location: group.location.clone(),
+            },
+        }
+    }
+}
+
+fn annotations(annotations: &[Annotation]) -> Vec<JsonAnnotation> {
+    annotations.iter().map(JsonAnnotation::from).collect()
+}
+
+fn invalid(message: impl std::fmt::Display) -> FormatError {
+    FormatError::ParseError(format!("invalid JSON AST: {message}"))
+}
+
+impl JsonDocument {
+    /// The document this AST describes
+    pub fn into_document(self) -> Result<Document, FormatError> {
+        if self.format != FORMAT {
+            return Err(invalid(format!(
+                "format is \"{}\", not \"{FORMAT}\"",
+                self.format
+            )));
+        }
+        if self.version > VERSION {
+            return Err(invalid(format!(
+                "schema version {} is newer than {VERSION}",
+                self.version
+            )));
+        }
+        let annotations = self
+            .annotations
+            .into_iter()
+            .map(JsonAnnotation::into_annotation)
+            .collect::<Result<_, _>>()?;
+        Ok(Document {
+            annotations,
+            root: self.root.into_session()?,
+        })
+    }
+}
+
+impl JsonText {
+    /// Text without inline markup: markers, verbatim lines
+    fn into_text(self) -> TextContent {
+        TextContent::from_string(self.text, self.location)
+    }
+
+    /// Text whose inline markup is parsed, as the parser does
+    fn into_inlines(self) -> TextContent {
+        let mut text = self.into_text();
+        text.ensure_inline_parsed();
+        text
+    }
+}
+
+impl JsonMarker {
+    fn into_marker(self) -> SequenceMarker {
+        SequenceMarker::new(
+            self.style,
+            self.separator,
+            self.form,
+            self.text.into_text(),
+            self.location,
+        )
+    }
+}
+
+impl JsonSession {
+    fn into_session(self) -> Result<Session, FormatError> {
+        Ok(Session {
+            title: self.title.into_inlines(),
+            marker: self.marker.map(JsonMarker::into_marker),
+            children: self.children.into_container()?,
+            annotations: into_annotations(self.annotations)?,
+            location: self.location,
+        })
+    }
+}
+
+impl JsonData {
+    fn into_data(self) -> Data {
+        let parameters = self
+            .parameters
+            .into_iter()
+            .map(|parameter| Parameter {
+                key: parameter.key,
+                value: parameter.value,
+                location: parameter.location,
+            })
+            .collect();
+        Data {
+            label: Label {
+                value: self.label,
+                location: self.label_location,
+            },
+            parameters,
+            location: self.location,
+        }
+    }
+}
+
+impl JsonAnnotation {
+    fn into_annotation(self) -> Result<Annotation, FormatError> {
+        Ok(Annotation {
+            data: self.data.into_data(),
+            children: self.children.into_container()?,
+            location: self.location,
+        })
+    }
+}
+
+impl JsonContainer {
+    /// Container of the items, which must be allowed in it
+    fn into_container<P: ContainerPolicy>(self) -> Result<Container<P>, FormatError> {
+        let mut container = Container::empty().at(self.location);
+        for node in self.items {
+            let item = node.into_item()?;
+            P::validate(&item).map_err(invalid)?;
+            container.push(item);
+        }
+        Ok(container)
+    }
+}
+
+fn into_annotations(annotations: Vec<JsonAnnotation>) -> Result<Vec<Annotation>, FormatError> {
+    annotations
+        .into_iter()
+        .map(JsonAnnotation::into_annotation)
+        .collect()
+}
+
+impl JsonNode {
+    fn into_item(self) -> Result<ContentItem, FormatError> {
+        Ok(match self {
+            JsonNode::Session(session) => ContentItem::Session(session.into_session()?),
+            JsonNode::Paragraph {
+                lines,
+                annotations,
+                location,
+            } => {
+                let lines = lines
+                    .into_iter()
+                    .map(|line| match line.into_item()? {
+                        ContentItem::TextLine(line) => Ok(ContentItem::TextLine(line)),
+                        other => Err(invalid(format!(
+                            "a paragraph holds text lines, not {}",
+                            other.node_type()
+                        ))),
+                    })
+                    .collect::<Result<_, _>>()?;
+                ContentItem::Paragraph(Paragraph {
+                    lines,
+                    annotations: into_annotations(annotations)?,
+                    location,
+                })
+            }
+            JsonNode::List {
+                marker,
+                items,
+                annotations,
+                location,
+            } => ContentItem::List(List {
+                items: items.into_container()?,
+                marker: marker.map(JsonMarker::into_marker),
+                annotations: into_annotations(annotations)?,
+                location,
+            }),
+            JsonNode::ListItem {
+                marker,
+                text,
+                children,
+                annotations,
+                location,
+            } => ContentItem::ListItem(ListItem {
+                marker: marker.into_text(),
+                text: text.into_iter().map(JsonText::into_inlines).collect(),
+                children: children.into_container()?,
+                annotations: into_annotations(annotations)?,
+                location,
+            }),
+            JsonNode::Definition {
+                subject,
+                children,
+                annotations,
+                location,
+            } => ContentItem::Definition(Definition {
+                subject: subject.into_inlines(),
+                children: children.into_container()?,
+                annotations: into_annotations(annotations)?,
+                location,
+            }),
+            JsonNode::Annotation(annotation) => {
+                ContentItem::Annotation(annotation.into_annotation()?)
+            }
+            JsonNode::Verbatim {
+                groups,
+                closing,
+                mode,
+                annotations,
+                location,
+            } => {
+                let mut groups = groups.into_iter();
+                let first = groups
+                    .next()
+                    .ok_or_else(|| invalid("a verbatim block has no group"))?;
+                let additional = groups
+                    .map(|group| {
+                        Ok(VerbatimGroupItem {
+                            subject: group.subject.into_inlines(),
+                            children: group.children.into_container()?,
+                        })
+                    })
+                    .collect::<Result<_, FormatError>>()?;
+                let mut verbatim = Verbatim::new(
+                    first.subject.into_inlines(),
+                    Vec::new(),
+                    closing.into_data(),
+                    mode,
+                )
+                .with_additional_groups(additional)
+                .at(location);
+                verbatim.children = first.children.into_container()?;
+                verbatim.annotations = into_annotations(annotations)?;
+                ContentItem::VerbatimBlock(Box::new(verbatim))
+            }
+            JsonNode::TextLine { content, location } => ContentItem::TextLine(TextLine {
+                content: content.into_inlines(),
+                location,
+            }),
+            JsonNode::VerbatimLine { content, location } => {
+                ContentItem::VerbatimLine(VerbatimLine {
+                    content: content.into_text(),
+                    location,
+                })
+            }
+            JsonNode::BlankLines {
+                count,
+                tokens,
+                location,
+            } => ContentItem::BlankLineGroup(BlankLineGroup {
+                count,
+                source_tokens: tokens,
+                location,
+            }),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    const SOURCE: &str = "Guide\n\n:: status value=draft ::\n\nIntro with *care*, see [#2].\n\n1. Setup\n\n    - First\n    - Second\n        Nested text.\n\n    Cache:\n        Where downloads go.\n\n    Example:\n        fn main() {}\n    Other:\n        run\n    :: rust title=\"Main\"\n\n2. Notes\n\n    :: note :: Kept for later.\n    Annotated paragraph.\n";
+
+    #[test]
+    fn test_round_trip() {
+        let doc = parse_document(SOURCE).unwrap();
+        let json = JsonFormatter.serialize(&doc).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["format"], "lex-ast");
+        assert_eq!(value["annotations"][0]["data"]["label"], "status");
+        assert_eq!(value["root"]["title"]["text"], "Guide");
+        assert_eq!(value["root"]["children"]["items"][1]["type"], "paragraph");
+
+        let read = JsonFormatter.parse(&json).unwrap();
+        assert_eq!(read, doc);
+        assert_eq!(JsonFormatter.serialize(&read).unwrap(), json);
+    }
+
+    #[test]
+    fn test_markers() {
+        let doc = parse_document("Guide\n\n1. Setup\n\n    a) One\n    b) Two\n").unwrap();
+        let json = JsonDocument::from(&doc);
+        let JsonNode::Session(session) = &json.root.children.items[0] else {
+            panic!("expected a session");
+        };
+        let marker = session.marker.as_ref().unwrap();
+        assert_eq!(marker.text.text, "1.");
+        let value = serde_json::to_value(marker).unwrap();
+        assert_eq!(value["style"], "numerical");
+        assert_eq!(value["separator"], "period");
+        let list = serde_json::to_value(&session.children.items[0]).unwrap();
+        assert_eq!(list["type"], "list");
+        assert_eq!(list["marker"]["style"], "alphabetical");
+        assert_eq!(list["items"]["items"][1]["marker"]["text"], "b)");
+    }
+
+    #[test]
+    fn test_invalid() {
+        let doc = parse_document("Guide\n\nText.\n").unwrap();
+        let mut json = JsonDocument::from(&doc);
+        json.format = "pandoc".to_string();
+        assert!(json.clone().into_document().is_err());
+
+        json.format = FORMAT.to_string();
+        let session = json.root.clone();
+        let JsonNode::Paragraph { lines, .. } = &mut json.root.children.items[0] else {
+            panic!("expected a paragraph");
+        };
+        lines.push(JsonNode::Session(session));
+        let error = json.into_document().unwrap_err();
+        assert_eq!(
+            error,
+            FormatError::ParseError(
+                "invalid JSON AST: a paragraph holds text lines, not Session".to_string()
+            )
+        );
+        assert!(JsonFormatter.parse("{\"format\": \"lex-ast\"}").is_err());
+    }
+
+    #[test]
+    fn test_schema() {
+        let schema: serde_json::Value = serde_json::from_str(SCHEMA).unwrap();
+        let types: Vec<&str> = schema["$defs"]["node"]["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|node| node["properties"]["type"]["const"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            types,
+            [
+                "session",
+                "paragraph",
+                "list",
+                "list-item",
+                "definition",
+                "annotation",
+                "verbatim",
+                "text-line",
+                "verbatim-line",
+                "blank-lines"
+            ]
+        );
+    }
+}
@@ -0,0 +1,727 @@
+//! LaTeX
+//!
+//! The `latex` format writes a document as a LaTeX article, the usual target for scientific
+//! writing:
+//!
+//! - Sessions become the `\section` hierarchy (`\subsection`, `\subsubsection`, then
+//!   `\paragraph` and `\subparagraph`). Sessions without a marker are unnumbered (`\section*`),
+//!   and sessions with an id get a `\label` that `[#id]` references point at with `\ref`.
+//! - Lists become `itemize`, or `enumerate` for numbered and lettered markers.
+//! - Definitions become `description` environments.
+//! - Verbatim blocks become `lstlisting` listings, captioned with their subject. The `lines`
+//!   parameter becomes a line range shown with line numbers, and lines the `emphasize`
+//!   parameter highlights are set on a colored background. Table blocks
+//!   become `tabular` environments, and [display math](crate::lex::display_math) blocks
+//!   `equation` environments, which `[#id]` references point at with `\eqref`.
+//! - Math inlines, written in AsciiMath, become native math (see [asciimath]).
+//! - Inline images become `\includegraphics`, sized by their `width` and `height`.
+//! - Footnote references become `\footnote`s holding the footnote text, and citations
+//!   `\cite` commands.
+//! - Strikethrough, the [`del` role](crate::lex::inlines::DEL_ROLE), becomes `\sout`.
+//! - Ruby becomes `\ruby{base}{gloss}`. The default preamble defines `\ruby` as the base
+//!   followed by the gloss in parentheses; preambles that load a ruby package (such as
+//!   `ruby` or `luatexja-ruby`) get glosses set above the base.
+//! - Callouts become quotes, with the callout title in bold. Their kind is only given by
+//!   the title: callouts are not boxed or colored.
+//!
+//! Other annotations are metadata and are left out. The document title and `author` make the
+//! `\maketitle` block. The [report](super::report) lists annotations left out and sessions
+//! nested deeper than `\subparagraph`, which are flattened to it.
+//!
+//! The preamble, between `\documentclass` and `\begin{document}`, is a template: `{{...}}`
+//! placeholders are resolved as [variables](crate::lex::variables) are. Users replace it to
+//! load their own packages or set up fonts; [LatexOptions::fragment] leaves the preamble out
+//! entirely, for documents included in a larger LaTeX project.
+
+pub mod asciimath;
+
+use crate::lex::analysis::definitions::{ReferenceIndex, TargetKey};
+use crate::lex::annotation::callout::Callout;
+use crate::lex::ast::elements::verbatim::LineRanges;
+use crate::lex::ast::{Annotation, ContentItem, Document, TextContent};
+use crate::lex::display_math::{equations, Equation};
+use crate::lex::formats::registry::{flag_param, FormatError, Formatter};
+use crate::lex::formats::report::ConversionReport;
+use crate::lex::inlines::{InlineNode, InlineParser, ReferenceType, DEL_ROLE};
+use crate::lex::literate::code_block;
+use crate::lex::tables::{table_rows, TABLE_LABEL};
+use crate::lex::variables::Variables;
+use std::collections::{HashMap, HashSet};
+
+/// Packages loaded by default
+pub const DEFAULT_PREAMBLE: &str = "\\usepackage[utf8]{inputenc}
+\\usepackage[T1]{fontenc}
+\\usepackage{amsmath,amssymb}
+\\usepackage{graphicx}
+\\usepackage{listings}
+\\usepackage{xcolor}
+\\usepackage[normalem]{ulem}
+\\usepackage{hyperref}
+\\providecommand{\\ruby}[2]{#1 (#2)}
+";
+
+/// Sectioning commands, by session depth
+const SECTIONS: [&str; 5] = [
+    "section",
+    "subsection",
+    "subsubsection",
+    "paragraph",
+    "subparagraph",
+];
+
+/// Languages known to the listings package, by verbatim label
+const LISTINGS_LANGUAGES: &[(&str, &str)] = &[
+    ("bash", "bash"),
+    ("c", "C"),
+    ("c++", "C++"),
+    ("cpp", "C++"),
+    ("haskell", "Haskell"),
+    ("html", "HTML"),
+    ("java", "Java"),
+    ("latex", "TeX"),
+    ("lisp", "Lisp"),
+    ("make", "make"),
+    ("matlab", "Matlab"),
+    ("perl", "Perl"),
+    ("php", "PHP"),
+    ("python", "Python"),
+    ("ruby", "Ruby"),
+    ("sh", "bash"),
+    ("shell", "bash"),
+    ("sql", "SQL"),
+    ("tex", "TeX"),
+    ("xml", "XML"),
+];
+
+/// How to write a LaTeX document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatexOptions {
+    pub document_class: String,
+    /// Preamble template
+    pub preamble: String,
+    /// Write the body alone, without preamble and `document` environment
+    pub fragment: bool,
+}
+
+impl Default for LatexOptions {
+    fn default() -> Self {
+        Self {
+            document_class: "article".to_string(),
+            preamble: DEFAULT_PREAMBLE.to_string(),
+            fragment: false,
+        }
+    }
+}
+
+/// Document written as LaTeX
+#[derive(Debug, Clone, Default)]
+pub struct LatexFormatter {
+    pub options: LatexOptions,
+}
+
+impl LatexFormatter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: LatexOptions) -> Self {
+        Self { options }
+    }
+}
+
+impl Formatter for LatexFormatter {
+    fn name(&self) -> &str {
+        "latex"
+    }
+
+    fn extension(&self) -> &str {
+        "tex"
+    }
+
+    fn serialize(&self, doc: &Document) -> Result<String, FormatError> {
+        render_document(doc, &self.options)
+    }
+
+    /// `document-class` and `fragment` override the formatter's options
+    fn serialize_with_params(
+        &self,
+        doc: &Document,
+        params: &HashMap<String, String>,
+    ) -> Result<String, FormatError> {
+        let mut options = self.options.clone();
+        if let Some(class) = params.get("document-class") {
+            options.document_class = class.clone();
+        }
+        if let Some(fragment) = flag_param(params, "fragment") {
+            options.fragment = fragment;
+        }
+        render_document(doc, &options)
+    }
+
+    fn serialize_with_report(
+        &self,
+        doc: &Document,
+    ) -> Result<(String, ConversionReport), FormatError> {
+        render_document_with_report(doc, &self.options)
+    }
+
+    fn description(&self) -> &str {
+        "LaTeX article, with native math and listings"
+    }
+}
+
+/// Write `doc` as LaTeX, as set by `options`
+pub fn render_document(doc: &Document, options: &LatexOptions) -> Result<String, FormatError> {
+    render_document_with_report(doc, options).map(|(out, _)| out)
+}
+
+/// Write `doc` as LaTeX, as set by `options`, with a report of what was left out
+pub fn render_document_with_report(
+    doc: &Document,
+    options: &LatexOptions,
+) -> Result<(String, ConversionReport), FormatError> {
+    let mut renderer = Renderer {
+        doc,
+        ids: doc
+            .root
+            .iter_sessions_recursive()
+            .filter_map(|session| session.id())
+            .collect(),
+        equations: equations(doc)
+            .into_iter()
+            .filter_map(|equation| equation.id)
+            .collect(),
+        footnotes: ReferenceIndex::build(doc)
+            .usages
+            .into_iter()
+            .filter_map(|usage| match usage.key {
+                TargetKey::Annotation(label) => Some(label),
+                _ => None,
+            })
+            .collect(),
+        output: String::new(),
+        report: ConversionReport::new("latex"),
+    };
+    renderer.items(&doc.root.children, 0);
+    // Document-level annotations are metadata; `meta` feeds the title block and preamble
+    for annotation in doc.annotations.iter().chain(&doc.root.annotations) {
+        renderer.annotation(annotation);
+    }
+    let mut report = renderer.report;
+    report
+        .losses
+        .sort_by_key(|loss| loss.range.as_ref().map(|range| range.span.start));
+    let body = renderer.output.trim_end();
+    if options.fragment {
+        return Ok((format!("{body}\n"), report));
+    }
+
+    let variables = Variables::for_document(doc);
+    let preamble = variables
+        .render(&options.preamble)
+        .map_err(|error| FormatError::SerializationError(error.to_string()))?;
+    let mut out = format!("\\documentclass{{{}}}\n", options.document_class);
+    out.push_str(&preamble);
+    if !out.ends_with('\n') {
+        out.push('\n');
+    }
+    let title = doc.title().trim();
+    if !title.is_empty() {
+        out.push_str(&format!("\\title{{{}}}\n", escape(title)));
+        if let Some(author) = variables.meta.get("author") {
+            out.push_str(&format!("\\author{{{}}}\n", escape(author)));
+        }
+    }
+    out.push_str("\n\\begin{document}\n\n");
+    if !title.is_empty() {
+        out.push_str("\\maketitle\n\n");
+    }
+    if !body.is_empty() {
+        out.push_str(body);
+        out.push_str("\n\n");
+    }
+    out.push_str("\\end{document}\n");
+    Ok((out, report))
+}
+
+struct Renderer<'a> {
+    doc: &'a Document,
+    /// Session ids, which `[#id]` references point at
+    ids: HashSet<&'a str>,
+    /// Equation ids, which `[#id]` references point at
+    equations: HashSet<String>,
+    /// Labels of referenced footnotes, which are inlined where referenced
+    footnotes: HashSet<String>,
+    output: String,
+    report: ConversionReport,
+}
+
+impl Renderer<'_> {
+    fn line(&mut self, text: &str) {
+        self.output.push_str(text);
+        self.output.push('\n');
+    }
+
+    fn blank(&mut self) {
+        if !self.output.is_empty() && !self.output.ends_with("\n\n") {
+            self.output.push('\n');
+        }
+    }
+
+    /// End `environment`, right after its content
+    fn end(&mut self, environment: &str) {
+        if self.output.ends_with("\n\n") {
+            self.output.pop();
+        }
+        self.line(&format!("\\end{{{environment}}}"));
+    }
+
+    fn items(&mut self, items: &[ContentItem], depth: usize) {
+        for item in items {
+            self.item(item, depth);
+        }
+    }
+
+    fn item(&mut self, item: &ContentItem, depth: usize) {
+        match item {
+            ContentItem::Session(session) => {
+                let command = SECTIONS[depth.min(SECTIONS.len() - 1)];
+                if depth >= SECTIONS.len() {
+                    self.report.lost(
+                        format!(
+                            "Session '{}' at depth {} was flattened to \\{command}.",
+                            session.title_text().trim(),
+                            depth + 1
+                        ),
+                        session.header_location().cloned(),
+                    );
+                }
+                let star = if session.marker.is_some() { "" } else { "*" };
+                let title = self.inlines(&InlineParser::new().parse(session.title_text().trim()));
+                self.blank();
+                self.line(&format!("\\{command}{star}{{{title}}}"));
+                if let Some(id) = session.id() {
+                    self.line(&format!("\\label{{{id}}}"));
+                }
+                self.blank();
+                self.items(&session.children, depth + 1);
+            }
+            ContentItem::Paragraph(paragraph) => {
+                let lines: Vec<String> = paragraph
+                    .lines
+                    .iter()
+                    .filter_map(|line| match line {
+                        ContentItem::TextLine(line) => Some(self.text(&line.content)),
+                        _ => None,
+                    })
+                    .collect();
+                match Callout::from_item(item) {
+                    Some(callout) => {
+                        self.line("\\begin{quote}");
+                        self.line(&format!("\\textbf{{{}.}}", escape(callout.display_title())));
+                        self.line(&lines.join("\n"));
+                        self.line("\\end{quote}");
+                    }
+                    None => self.line(&lines.join("\n")),
+                }
+                self.blank();
+            }
+            ContentItem::TextLine(line) => {
+                let text = self.text(&line.content);
+                self.line(&text);
+            }
+            ContentItem::List(list) => {
+                let numbered = list.items.iter().find_map(|item| match item {
+                    ContentItem::ListItem(item) => {
+                        Some(item.marker().starts_with(|c: char| c.is_alphanumeric()))
+                    }
+                    _ => None,
+                });
+                let environment = if numbered == Some(true) {
+                    "enumerate"
+                } else {
+                    "itemize"
+                };
+                self.line(&format!("\\begin{{{environment}}}"));
+                self.items(&list.items, depth);
+                self.end(environment);
+                self.blank();
+            }
+            ContentItem::ListItem(list_item) => {
+                let text: Vec<String> = list_item.text.iter().map(|text| self.text(text)).collect();
+                self.line(&format!("\\item {}", text.join(" ").trim_end()));
+                self.items(&list_item.children, depth);
+            }
+            ContentItem::Definition(definition) => {
+                let subject = self.text(&definition.subject);
+                self.line("\\begin{description}");
+                self.line(&format!("\\item[{subject}]"));
+                self.items(&definition.children, depth);
+                self.end("description");
+                self.blank();
+            }
+            ContentItem::VerbatimBlock(verbatim) => {
+                if let Some(equation) = Equation::from_verbatim(verbatim, 0) {
+                    self.line(&equation.to_latex());
+                    self.blank();
+                    return;
+                }
+                let block = code_block(verbatim);
+                if block.language == TABLE_LABEL {
+                    if let Some(rows) = table_rows(verbatim) {
+                        self.table(&rows);
+                        return;
+                    }
+                }
+                let mut settings = Vec::new();
+                let language = block.language.to_lowercase();
+                if let Some((_, name)) = LISTINGS_LANGUAGES
+                    .iter()
+                    .find(|(label, _)| *label == language)
+                {
+                    settings.push(format!("language={name}"));
+                }
+                let subject = block.subject.trim();
+                if !subject.is_empty() {
+                    settings.push(format!("caption={{{}}}", escape(subject)));
+                }
+                let count = block.text.lines().count();
+                if let Some(selection) = verbatim.line_selection().and_then(Result::ok) {
+                    settings.extend(line_range_settings(&selection, count));
+                }
+                let emphasized = verbatim.emphasized_lines().and_then(Result::ok);
+                let emphasized = emphasized.filter(|_| !block.text.contains(ESCAPE_END));
+                if emphasized.is_some() {
+                    settings.push(format!("escapeinside={{{ESCAPE_START}}}{{{ESCAPE_END}}}"));
+                }
+                if settings.is_empty() {
+                    self.line("\\begin{lstlisting}");
+                } else {
+                    self.line(&format!("\\begin{{lstlisting}}[{}]", settings.join(", ")));
+                }
+                for (index, line) in block.text.lines().enumerate() {
+                    match &emphasized {
+                        Some(lines) if lines.contains(index + 1) => {
+                            self.line(&highlighted_line(line))
+                        }
+                        _ => self.line(line),
+                    }
+                }
+                self.line("\\end{lstlisting}");
+                self.blank();
+            }
+            ContentItem::VerbatimLine(line) => self.line(line.content.as_string()),
+            ContentItem::Annotation(annotation) => self.annotation(annotation),
+            ContentItem::BlankLineGroup(_) => {}
+        }
+    }
+
+    /// Report `annotation` as left out, unless it is a footnote or `meta`
+    fn annotation(&mut self, annotation: &Annotation) {
+        let label = annotation.data.label.value.trim();
+        if !self.footnotes.contains(label) && label != "meta" {
+            self.report.lost(
+                format!("Annotation '{label}' was left out."),
+                Some(annotation.location.clone()),
+            );
+        }
+    }
+
+    fn table(&mut self, rows: &[Vec<String>]) {
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        self.line(&format!("\\begin{{tabular}}{{{}}}", "l".repeat(columns)));
+        for row in rows {
+            let cells: Vec<String> = row.iter().map(|cell| escape(cell)).collect();
+            self.line(&format!("{} \\\\", cells.join(" & ")));
+        }
+        self.line("\\end{tabular}");
+        self.blank();
+    }
+
+    fn text(&self, text: &TextContent) -> String {
+        self.inlines(&text.inline_items())
+    }
+
+    fn inlines(&self, nodes: &[InlineNode]) -> String {
+        let mut out = String::new();
+        for node in nodes {
+            match node {
+                InlineNode::Plain { text, .. } => out.push_str(&escape(text)),
+                InlineNode::Strong { content, .. } => {
+                    out.push_str(&format!("\\textbf{{{}}}", self.inlines(content)))
+                }
+                InlineNode::Emphasis { content, .. } => {
+                    out.push_str(&format!("\\emph{{{}}}", self.inlines(content)))
+                }
+                InlineNode::Code { text, .. } => {
+                    out.push_str(&format!("\\texttt{{{}}}", escape(text)))
+                }
+                InlineNode::Math { text, .. } => {
+                    out.push_str(&format!("${}$", asciimath::to_latex(text)))
+                }
+                InlineNode::Reference { data, .. } => {
+                    out.push_str(&self.reference(&data.reference_type, &data.raw))
+                }
+                InlineNode::Ruby { base, text, .. } => {
+                    out.push_str(&format!("\\ruby{{{}}}{{{}}}", escape(base), escape(text)))
+                }
+                InlineNode::Role { data, .. } if data.role == DEL_ROLE => {
+                    out.push_str(&format!("\\sout{{{}}}", escape(&data.content)))
+                }
+                InlineNode::Role { data, .. } => out.push_str(&escape(&data.content)),
+            }
+        }
+        out
+    }
+
+    fn reference(&self, reference: &ReferenceType, raw: &str) -> String {
+        match reference {
+            ReferenceType::Url { target } => {
+                format!(
+                    "\\url{{{}}}",
+                    target.replace('%', "\\%").replace('#', "\\#")
+                )
+            }
+            ReferenceType::File { target } => format!("\\texttt{{{}}}", escape(target)),
+            ReferenceType::FootnoteNumber { number } => self.footnote(&number.to_string(), raw),
+            ReferenceType::FootnoteLabeled { label } => self.footnote(label, raw),
+            ReferenceType::Citation(citation) => {
+                let keys = citation.keys.join(",");
+                match &citation.locator {
+                    Some(locator) => format!("\\cite[{}]{{{keys}}}", escape(&locator.raw)),
+                    None => format!("\\cite{{{keys}}}"),
+                }
+            }
+            ReferenceType::Session { target } if self.ids.contains(target.as_str()) => {
+                format!("\\ref{{{target}}}")
+            }
+            ReferenceType::Session { target } if self.equations.contains(target) => {
+                format!("\\eqref{{{target}}}")
+            }
+            ReferenceType::General { target } => escape(target),
+            ReferenceType::WikiLink(link) => escape(link.display_text()),
+            ReferenceType::Image(image) => {
+                let mut options = Vec::new();
+                if let Some(width) = &image.width {
+                    options.push(format!("width={}", length(width, "\\linewidth")));
+                }
+                if let Some(height) = &image.height {
+                    options.push(format!("height={}", length(height, "\\textheight")));
+                }
+                if options.is_empty() {
+                    format!("\\includegraphics{{{}}}", image.src)
+                } else {
+                    format!("\\includegraphics[{}]{{{}}}", options.join(","), image.src)
+                }
+            }
+            _ => escape(&format!("[{raw}]")),
+        }
+    }
+
+    /// The footnote labeled `label`, inline, or the reference as written if there is none
+    fn footnote(&self, label: &str, raw: &str) -> String {
+        let Some(annotation) = self.doc.find_annotation_by_label(label) else {
+            return escape(&format!("[{raw}]"));
+        };
+        let text: Vec<String> = annotation
+            .children
+            .iter()
+            .filter_map(|item| match item {
+                ContentItem::Paragraph(paragraph) => Some(
+                    paragraph
+                        .lines
+                        .iter()
+                        .filter_map(|line| match line {
+                            ContentItem::TextLine(line) => Some(self.text(&line.content)),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                ),
+                _ => None,
+            })
+            .collect();
+        format!("\\footnote{{{}}}", text.join(" "))
+    }
+}
+
+/// An image size as a LaTeX length: pixels for bare numbers, a fraction of `whole` for
+/// percentages, other lengths as written
+fn length(size: &str, whole: &str) -> String {
+    let size = size.trim();
+    if let Some(percent) = size.strip_suffix('%') {
+        if let Ok(percent) = percent.trim().parse::<f64>() {
+            return format!("{}{whole}", percent / 100.0);
+        }
+    }
+    if size.parse::<f64>().is_ok() {
+        return format!("{size}px");
+    }
+    size.to_string()
+}
+
+/// `text` with the characters LaTeX reserves escaped
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\\' => out.push_str("\\textbackslash{}"),
+            '~' => out.push_str("\\textasciitilde{}"),
+            '^' => out.push_str("\\textasciicircum{}"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Delimiters of the LaTeX escaped into listings to highlight lines
+const ESCAPE_START: &str = "(*@";
+const ESCAPE_END: &str = "@*)";
+
+/// `lstlisting` settings showing the `lines` of a listing of `count` lines, numbered
+fn line_range_settings(selection: &LineRanges, count: usize) -> Vec<String> {
+    let mut settings = vec!["numbers=left".to_string()];
+    let ranges: Vec<(usize, usize)> = selection
+        .ranges()
+        .iter()
+        .map(|range| (*range.start(), (*range.end()).min(count)))
+        .collect();
+    match ranges.as_slice() {
+        [(first, last)] => {
+            settings.push(format!("firstline={first}"));
+            settings.push(format!("lastline={last}"));
+            settings.push(format!("firstnumber={first}"));
+        }
+        _ => {
+            let ranges: Vec<String> = ranges
+                .iter()
+                .map(|(first, last)| format!("{first}-{last}"))
+                .collect();
+            settings.push(format!("linerange={{{}}}", ranges.join(",")));
+        }
+    }
+    settings
+}
+
+/// A listing line set on a colored background, through `escapeinside`
+fn highlighted_line(line: &str) -> String {
+    let text = line.trim_start();
+    if text.is_empty() {
+        return line.to_string();
+    }
+    let indent = &line[..line.len() - text.len()];
+    format!(
+        "{indent}{ESCAPE_START}\\colorbox{{yellow!30}}{{\\strut {}}}{ESCAPE_END}",
+        escape(text)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    const SOURCE: &str = "Field Notes\n\n:: meta author=\"Ada Lovelace\" ::\n\nCosts rose 5% & more, see [#method] and [1].\n\n:: id method ::\n1. Method\n\n    Energy is #E = m c^2#, with *care* and `code_{x}`.\n\n    Logo [!./logo.png alt=\"Logo\" width=50%] and [!./icon.png height=12].\n\n    - First\n    - Second\n\n    Sample:\n        print(\"hi\")\n    :: python\n\n    Appendix\n\n        Rest energy:\n            E = m c^2\n        :: math id=energy\n\n        As [#energy] shows.\n\n        Prices:\n            | Item | Price |\n            | Tea  | 2     |\n        :: table\n\n:: 1 ::\n    A footnote.\n::\n";
+
+    #[test]
+    fn test_render_document() {
+        let doc = parse_document(SOURCE).unwrap();
+        let latex = LatexFormatter::new().serialize(&doc).unwrap();
+        assert!(latex.starts_with("\\documentclass{article}\n\\usepackage[utf8]{inputenc}\n"));
+        assert!(latex.contains("\\title{Field Notes}\n\\author{Ada Lovelace}\n"));
+        assert!(latex.contains("\\maketitle\n\n"));
+        assert!(latex
+            .contains("Costs rose 5\\% \\& more, see \\ref{method} and \\footnote{A footnote.}."));
+        assert!(latex.contains("\\section{Method}\n\\label{method}\n"));
+        assert!(latex
+            .contains("Energy is $E=mc^{2}$, with \\textbf{care} and \\texttt{code\\_\\{x\\}}."));
+        assert!(latex.contains(
+            "Logo \\includegraphics[width=0.5\\linewidth]{./logo.png} and \\includegraphics[height=12px]{./icon.png}."
+        ));
+        assert!(latex.contains("\\begin{itemize}\n\\item First\n\\item Second\n\\end{itemize}"));
+        assert!(latex.contains("\\begin{lstlisting}[language=Python, caption={Sample}]\nprint(\"hi\")\n\\end{lstlisting}"));
+        assert!(latex.contains("\\subsection*{Appendix}"));
+        assert!(latex.contains("\\begin{equation}\nE=mc^{2}\n\\label{energy}\n\\end{equation}"));
+        assert!(latex.contains("As \\eqref{energy} shows."));
+        assert!(
+            latex.contains("\\begin{tabular}{ll}\nItem & Price \\\\\nTea & 2 \\\\\n\\end{tabular}")
+        );
+        assert!(latex.ends_with("\\end{document}\n"));
+    }
+
+    #[test]
+    fn test_listing_lines() {
+        let source = "Notes\n\nLoop:\n    for x in xs:\n        print(x)\n    done()\n:: python lines=2-, emphasize=3\n\nGaps:\n    a\n    b\n    c\n:: text lines=\"1,3\"\n\nSaid {漢字|かんじ}.\n";
+        let doc = parse_document(source).unwrap();
+        let latex = LatexFormatter::new().serialize(&doc).unwrap();
+        assert!(latex.contains(
+            "\\begin{lstlisting}[language=Python, caption={Loop}, numbers=left, firstline=2, lastline=3, firstnumber=2, escapeinside={(*@}{@*)}]\nfor x in xs:\n    print(x)\n(*@\\colorbox{yellow!30}{\\strut done()}@*)\n\\end{lstlisting}"
+        ));
+        assert!(latex.contains(
+            "\\begin{lstlisting}[caption={Gaps}, numbers=left, linerange={1-1,3-3}]\na\nb\nc\n"
+        ));
+        assert!(latex.contains("Said \\ruby{漢字}{かんじ}."));
+
+        let doc = parse_document("Notes\n\nWas {del|5%} 4.\n").unwrap();
+        let latex = LatexFormatter::new().serialize(&doc).unwrap();
+        assert!(latex.contains("Was \\sout{5\\%} 4."));
+    }
+
+    #[test]
+    fn test_options() {
+        let doc = parse_document(
+            "Notes\n\n:: meta author=Ada ::\n\n1. Terms\n\n    Cache:\n        Kept files.\n",
+        )
+        .unwrap();
+        let fragment = LatexOptions {
+            fragment: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            render_document(&doc, &fragment).unwrap(),
+            "\\section{Terms}\n\n\\begin{description}\n\\item[Cache]\nKept files.\n\\end{description}\n"
+        );
+
+        let custom = LatexOptions {
+            document_class: "report".to_string(),
+            preamble: "% by {{meta.author}}\n".to_string(),
+            ..Default::default()
+        };
+        let latex = render_document(&doc, &custom).unwrap();
+        assert!(latex.starts_with("\\documentclass{report}\n% by Ada\n\\title{Notes}\n"));
+
+        let unknown = LatexOptions {
+            preamble: "{{nothing}}".to_string(),
+            ..Default::default()
+        };
+        assert!(render_document(&doc, &unknown).is_err());
+    }
+
+    #[test]
+    fn test_report() {
+        let source = "Notes\n\n1. A\n\n    1.1. B\n\n        1.1.1. C\n\n            1.1.1.1. D\n\n                1.1.1.1.1. E\n\n                    1.1.1.1.1.1. F\n\n                        Text.[1]\n\n:: 1 ::\n    The footnote.\n\n:: draft ::\n    Not for print.\n";
+        let doc = parse_document(source).unwrap();
+        let (latex, report) = LatexFormatter::new().serialize_with_report(&doc).unwrap();
+        assert_eq!(
+            latex,
+            render_document(&doc, &LatexOptions::default()).unwrap()
+        );
+        let messages: Vec<&str> = report
+            .losses
+            .iter()
+            .map(|loss| loss.message.as_str())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Session 'F' at depth 6 was flattened to \\subparagraph.",
+                "Annotation 'draft' was left out.",
+            ]
+        );
+        assert_eq!(report.losses[0].range.as_ref().unwrap().start.line, 12);
+    }
+}
@@ -0,0 +1,120 @@
+//! AsciiMath to LaTeX
+//!
+//! Lex math (`#x^2 + y#`) is AsciiMath. [to_latex] writes the [parsed](crate::lex::formats::asciimath)
+//! expression as LaTeX math for the `latex` format: the brackets around an argument are
+//! dropped (`(a+b)/2` becomes `\frac{a+b}{2}`), and characters the symbol table doesn't
+//! know are kept, escaped where LaTeX would read them as markup.
+
+use crate::lex::formats::asciimath::{parse, Expr, Kind};
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '#' | '$' | '%' | '&' | '_' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\\' => out.push_str("\\backslash "),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn sequence(expressions: &[Expr]) -> String {
+    let mut out = String::new();
+    for expression in expressions {
+        push(&mut out, &latex(expression));
+    }
+    out
+}
+
+/// `expression` as a function or script argument, without its brackets
+fn argument(expression: &Expr) -> String {
+    match expression.argument() {
+        Some(content) => sequence(content),
+        None => latex(expression),
+    }
+}
+
+fn latex(expression: &Expr) -> String {
+    match expression {
+        // A script or fraction operator without operands is dropped
+        Expr::Symbol(symbol) if matches!(symbol.kind, Kind::Sub | Kind::Sup) => String::new(),
+        Expr::Symbol(symbol) => symbol.latex.to_string(),
+        Expr::Char(c) => escape(&c.to_string()),
+        Expr::Number(number) => number.clone(),
+        Expr::Text(text) => format!("\\text{{{}}}", escape(text)),
+        Expr::Group {
+            open,
+            content,
+            close,
+        } => {
+            let mut out = open.latex.to_string();
+            push(&mut out, &sequence(content));
+            push(&mut out, close.map_or("", |close| close.latex));
+            out
+        }
+        Expr::Apply(symbol, arguments) => {
+            let mut pieces = symbol.latex.split('#');
+            let mut out = pieces.next().unwrap_or_default().to_string();
+            for (piece, value) in pieces.zip(arguments) {
+                out.push_str(&argument(value));
+                out.push_str(piece);
+            }
+            out
+        }
+        Expr::Frac(numerator, denominator) => {
+            format!(
+                "\\frac{{{}}}{{{}}}",
+                argument(numerator),
+                argument(denominator)
+            )
+        }
+        Expr::Script { base, sub, sup } => {
+            let mut out = latex(base);
+            for (operator, script) in [('_', sub), ('^', sup)] {
+                if let Some(script) = script {
+                    out = format!("{out}{operator}{{{}}}", argument(script));
+                }
+            }
+            out
+        }
+        Expr::Missing => String::new(),
+    }
+}
+
+/// Append `piece` to `out`, spaced from a preceding control word it would extend
+fn push(out: &mut String, piece: &str) {
+    let control_word = out
+        .rsplit_once('\\')
+        .is_some_and(|(_, word)| !word.is_empty() && word.chars().all(char::is_alphabetic));
+    if control_word && piece.starts_with(char::is_alphabetic) {
+        out.push(' ');
+    }
+    out.push_str(piece);
+}
+
+/// LaTeX math for the AsciiMath expression `source`
+pub fn to_latex(source: &str) -> String {
+    sequence(&parse(source))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_latex() {
+        assert_eq!(to_latex("x^2 + y"), "x^{2}+y");
+        assert_eq!(to_latex("(a+b)/2"), "\\frac{a+b}{2}");
+        assert_eq!(to_latex("sum_(i=1)^n i"), "\\sum_{i=1}^{n}i");
+        assert_eq!(to_latex("sqrt(x) <= oo"), "\\sqrt{x}\\leq\\infty");
+        assert_eq!(to_latex("alpha in RR"), "\\alpha\\in\\mathbb{R}");
+        assert_eq!(to_latex("sin x"), "\\sin x");
+        assert_eq!(to_latex("frac a b"), "\\frac{a}{b}");
+        assert_eq!(to_latex("\"if\" x > 0"), "\\text{if}x>0");
+        assert_eq!(to_latex("50% & 3.5"), "50\\%\\&3.5");
+    }
+}
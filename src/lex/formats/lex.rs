@@ -0,0 +1,85 @@
+//! Lex source
+//!
+//! The `lex` format writes a document back as Lex source, through the
+//! [formatter's serializer](crate::lex::formatting::serializer): canonical indentation, list
+//! markers and blank line runs, as set by a [FormattingRulesConfig]. It makes Lex a target
+//! like any other format, so documents built by importers or filters can be written out as
+//! `.lex` files, and formatting Lex source is parsing it and writing it back.
+//!
+//! Written source parses back to the same structure, with one exception: a definition
+//! without content (left when the parser skips its only line) has no source form and is
+//! written as a paragraph.
+
+use crate::lex::ast::Document;
+use crate::lex::formats::registry::{FormatError, Formatter};
+use crate::lex::formatting::{serialize_document, FormattingRulesConfig};
+use crate::lex::parsing::parse_document;
+
+/// Document written as Lex source
+#[derive(Debug, Clone, Default)]
+pub struct LexFormatter {
+    pub rules: FormattingRulesConfig,
+}
+
+impl LexFormatter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_rules(rules: FormattingRulesConfig) -> Self {
+        Self { rules }
+    }
+}
+
+impl Formatter for LexFormatter {
+    fn name(&self) -> &str {
+        "lex"
+    }
+
+    fn serialize(&self, doc: &Document) -> Result<String, FormatError> {
+        Ok(serialize_document(doc, &self.rules))
+    }
+
+    fn supports_parsing(&self) -> bool {
+        true
+    }
+
+    fn parse(&self, source: &str) -> Result<Document, FormatError> {
+        parse_document(source).map_err(FormatError::ParseError)
+    }
+
+    fn description(&self) -> &str {
+        "Canonical Lex source"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::formats::import_opml;
+    use crate::lex::formatting::format_document;
+    use crate::lex::parsing::parse_document;
+
+    #[test]
+    fn test_round_trip() {
+        let source = "Guide\n\n\n\nIntro.\n\n1. Setup\n\n  * First\n  * Second\n\n  Example:\n      let x = 1;\n  :: rust\n";
+        let doc = parse_document(source).unwrap();
+        let rules = FormattingRulesConfig::default();
+        let written = LexFormatter::new().serialize(&doc).unwrap();
+        assert_eq!(written, format_document(source, &rules).unwrap());
+        let rewritten = LexFormatter::new()
+            .serialize(&parse_document(&written).unwrap())
+            .unwrap();
+        assert_eq!(rewritten, written);
+    }
+
+    #[test]
+    fn test_imported_document() {
+        let lex = import_opml(
+            "<opml><head><title>Trip</title></head><body><outline text=\"Packing\"><outline text=\"Passport\"/><outline text=\"Charger\"/></outline></body></opml>",
+        )
+        .unwrap();
+        let doc = parse_document(&lex).unwrap();
+        assert_eq!(LexFormatter::new().serialize(&doc).unwrap(), lex);
+    }
+}
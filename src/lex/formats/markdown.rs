@@ -0,0 +1,699 @@
+//! Markdown
+//!
+//! The `markdown` format writes a document as Markdown, in one of two flavors:
+//! [CommonMark](MarkdownFlavor::CommonMark), the default, which any Markdown tool reads, or
+//! [GitHub Flavored Markdown](MarkdownFlavor::Gfm), which adds syntax for what CommonMark
+//! can only approximate. The flavor is the `flavor` parameter (`commonmark` or `gfm`).
+//!
+//! - The document title is a level 1 heading and sessions the headings below it, down to
+//!   level 6. Sessions with an id get an anchor that `[#id]` references link to.
+//! - Lists become bullet or ordered lists. With GFM, items starting with `[ ]` or `[x]` are
+//!   task list items.
+//! - Definitions become a bold subject followed by their content.
+//! - Verbatim blocks become fenced code, labeled with their language. Table blocks become
+//!   GFM tables; CommonMark has none, so they stay code blocks.
+//! - Callouts become `> [!NOTE]` alerts with GFM, and quotes with a bold title otherwise.
+//! - Footnote references become `[^1]` footnotes with GFM, whose text follows the document.
+//!   CommonMark has no footnotes: references are superscripts and the footnotes a list at
+//!   the end.
+//! - URLs become autolinks: bare with GFM, in angle brackets otherwise. Math inlines become
+//!   `$...$` math with GFM and code otherwise.
+//! - Strikethrough is the `del` role, `{del|old text}`: `~~old text~~` with GFM, a `<del>`
+//!   element otherwise.
+//!
+//! - Links kept by a [`markdown-links`](LINKS_LABEL) annotation, which [import] writes for
+//!   reference-style links, are written as `[text][id]` again, with their definitions after
+//!   the body.
+//!
+//! Other annotations are metadata and are left out.
+//!
+//! Markdown is read back with [import] (with the `obsidian` feature), which converts the
+//! flavors the source appears to use.
+
+#[cfg(feature = "obsidian")]
+pub mod import;
+
+use crate::lex::analysis::definitions::{ReferenceIndex, TargetKey};
+use crate::lex::annotation::callout::Callout;
+use crate::lex::ast::{ContentItem, Document, TextContent};
+use crate::lex::extensions::ExtensionRegistry;
+use crate::lex::formats::registry::{FormatError, Formatter};
+use crate::lex::inlines::{InlineNode, InlineParser, ReferenceType, DEL_ROLE};
+use crate::lex::literate::code_block;
+use crate::lex::tables::{table_rows, TABLE_LABEL};
+use std::collections::{HashMap, HashSet};
+
+/// Label of the document annotation keeping reference-style links: `id="url"`,
+/// `id.text="text"` and, if the definition has one, `id.title="title"`
+pub const LINKS_LABEL: &str = "markdown-links";
+
+/// A reference-style link, `[text][id]` with the definition `[id]: url "title"`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkReference {
+    pub id: String,
+    pub url: String,
+    pub text: String,
+    pub title: Option<String>,
+}
+
+impl LinkReference {
+    /// Whether a Lex reference to `target` is this link
+    fn links_to(&self, target: &str) -> bool {
+        target == self.url || target == link_target(&self.url)
+    }
+}
+
+/// Reference-style links kept in the `markdown-links` annotations of `doc`, in order
+pub fn link_references(doc: &Document) -> Vec<LinkReference> {
+    let mut links: Vec<LinkReference> = Vec::new();
+    let params = doc
+        .annotations
+        .iter()
+        .chain(&doc.root.annotations)
+        .filter(|annotation| annotation.data.label.value.trim() == LINKS_LABEL)
+        .flat_map(|annotation| &annotation.data.parameters);
+    for param in params {
+        let value = param.value.trim_matches('"').to_string();
+        let (id, field) = param.key.split_once('.').unwrap_or((&param.key, ""));
+        let index = match links.iter().position(|link| link.id == id) {
+            Some(index) => index,
+            None => {
+                links.push(LinkReference {
+                    id: id.to_string(),
+                    url: String::new(),
+                    text: String::new(),
+                    title: None,
+                });
+                links.len() - 1
+            }
+        };
+        let link = &mut links[index];
+        match field {
+            "" => link.url = value,
+            "text" => link.text = value,
+            "title" => link.title = Some(value),
+            _ => {}
+        }
+    }
+    links.retain(|link| !link.url.is_empty() && !link.text.is_empty());
+    links
+}
+
+/// A Markdown link target as a Lex reference target: URLs as they are, links to notes
+/// pointing at the `.lex` file, and other files relative
+pub(crate) fn link_target(url: &str) -> String {
+    let url = url.replace("%20", " ");
+    if url.contains("://") || url.starts_with("mailto:") {
+        url
+    } else if let Some(note) = url.strip_suffix(".md") {
+        relative_reference(&format!("{note}.lex"))
+    } else {
+        relative_reference(&url)
+    }
+}
+
+/// A file target as a Lex file reference, which must start with `.` or `/`
+pub(crate) fn relative_reference(target: &str) -> String {
+    if target.starts_with('.') || target.starts_with('/') {
+        target.to_string()
+    } else {
+        format!("./{target}")
+    }
+}
+
+/// Markdown dialect written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MarkdownFlavor {
+    #[default]
+    CommonMark,
+    /// GitHub Flavored Markdown: task lists, tables, strikethrough, footnotes, autolinks
+    Gfm,
+}
+
+impl MarkdownFlavor {
+    /// Flavor named `name` (`commonmark` or `gfm`), case-insensitively
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "commonmark" => Some(MarkdownFlavor::CommonMark),
+            "gfm" => Some(MarkdownFlavor::Gfm),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MarkdownFlavor::CommonMark => "commonmark",
+            MarkdownFlavor::Gfm => "gfm",
+        }
+    }
+}
+
+/// Options of the Markdown output
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MarkdownOptions {
+    pub flavor: MarkdownFlavor,
+}
+
+/// Document written as Markdown
+#[derive(Debug, Clone, Default)]
+pub struct MarkdownFormatter {
+    pub options: MarkdownOptions,
+}
+
+impl MarkdownFormatter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: MarkdownOptions) -> Self {
+        Self { options }
+    }
+}
+
+impl Formatter for MarkdownFormatter {
+    fn name(&self) -> &str {
+        "markdown"
+    }
+
+    fn extension(&self) -> &str {
+        "md"
+    }
+
+    fn serialize(&self, doc: &Document) -> Result<String, FormatError> {
+        Ok(render_document(doc, &self.options))
+    }
+
+    /// `flavor` overrides the formatter's flavor
+    fn serialize_with_params(
+        &self,
+        doc: &Document,
+        params: &HashMap<String, String>,
+    ) -> Result<String, FormatError> {
+        self.serialize_with_extensions(doc, params, &ExtensionRegistry::new())
+    }
+
+    /// Verbatim blocks an extension renders in `markdown` are written as it renders them
+    fn serialize_with_extensions(
+        &self,
+        doc: &Document,
+        params: &HashMap<String, String>,
+        extensions: &ExtensionRegistry,
+    ) -> Result<String, FormatError> {
+        let mut options = self.options.clone();
+        if let Some(flavor) = params.get("flavor") {
+            options.flavor = MarkdownFlavor::from_name(flavor).ok_or_else(|| {
+                FormatError::SerializationError(format!("Unknown Markdown flavor '{flavor}'"))
+            })?;
+        }
+        Ok(render_document_with_extensions(doc, &options, extensions))
+    }
+
+    #[cfg(feature = "obsidian")]
+    fn supports_parsing(&self) -> bool {
+        true
+    }
+
+    /// The document [import::import_markdown] converts, with the detected flavors
+    #[cfg(feature = "obsidian")]
+    fn parse(&self, source: &str) -> Result<Document, FormatError> {
+        let options = import::MarkdownImportOptions::detect(source);
+        let import = import::import_markdown(source, "", &options);
+        crate::lex::parsing::parse_document(&import.source).map_err(FormatError::ParseError)
+    }
+
+    fn description(&self) -> &str {
+        "Markdown, as CommonMark or GitHub Flavored Markdown"
+    }
+}
+
+/// Write `doc` as Markdown, as set by `options`
+pub fn render_document(doc: &Document, options: &MarkdownOptions) -> String {
+    render_document_with_extensions(doc, options, &ExtensionRegistry::new())
+}
+
+/// Write `doc` as Markdown, with the verbatim blocks `extensions` render in `markdown`
+pub fn render_document_with_extensions(
+    doc: &Document,
+    options: &MarkdownOptions,
+    extensions: &ExtensionRegistry,
+) -> String {
+    let mut renderer = Renderer {
+        doc,
+        extensions,
+        gfm: options.flavor == MarkdownFlavor::Gfm,
+        parser: InlineParser::new(),
+        referenced: ReferenceIndex::build(doc)
+            .usages
+            .into_iter()
+            .filter_map(|usage| match usage.key {
+                TargetKey::Annotation(label) => Some(label),
+                _ => None,
+            })
+            .collect(),
+        footnotes: Vec::new(),
+        links: link_references(doc),
+        used_links: Vec::new(),
+    };
+    let mut out = String::new();
+    let title = doc.title().trim();
+    if !title.is_empty() {
+        out.push_str(&format!("# {}\n\n", renderer.inline_text(title)));
+    }
+    let body = renderer.items(&doc.root.children, 0);
+    out.push_str(&body.join("\n"));
+    let definitions = renderer.link_definitions();
+    if !definitions.is_empty() {
+        out.push_str("\n\n");
+        out.push_str(&definitions.join("\n"));
+    }
+    let notes = renderer.footnote_lines();
+    if !notes.is_empty() {
+        out.push_str("\n\n");
+        out.push_str(&notes.join("\n"));
+    }
+    format!("{}\n", out.trim_end())
+}
+
+struct Renderer<'a> {
+    doc: &'a Document,
+    extensions: &'a ExtensionRegistry,
+    gfm: bool,
+    parser: InlineParser,
+    /// Labels of the annotations footnote references point at
+    referenced: HashSet<String>,
+    /// Footnotes referenced so far, in order of first reference
+    footnotes: Vec<String>,
+    /// Reference-style links of the document
+    links: Vec<LinkReference>,
+    /// Indices of the links written so far, in order of first use
+    used_links: Vec<usize>,
+}
+
+impl Renderer<'_> {
+    /// Lines of `items`, blocks separated by blank lines
+    fn items(&mut self, items: &[ContentItem], depth: usize) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+        for item in items {
+            let block = self.item(item, depth);
+            if block.is_empty() {
+                continue;
+            }
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.extend(block);
+        }
+        lines
+    }
+
+    fn item(&mut self, item: &ContentItem, depth: usize) -> Vec<String> {
+        match item {
+            ContentItem::Session(session) => {
+                let level = (depth + 2).min(6);
+                let mut lines = Vec::new();
+                if let Some(id) = session.id() {
+                    lines.push(format!("<a id=\"{id}\"></a>"));
+                }
+                let title = self.inline_text(session.title.as_string().trim());
+                lines.push(format!("{} {title}", "#".repeat(level)));
+                let body = self.items(&session.children, depth + 1);
+                if !body.is_empty() {
+                    lines.push(String::new());
+                    lines.extend(body);
+                }
+                lines
+            }
+            ContentItem::Paragraph(paragraph) => {
+                let lines: Vec<String> = paragraph
+                    .lines
+                    .iter()
+                    .filter_map(|line| match line {
+                        ContentItem::TextLine(line) => Some(self.text(&line.content)),
+                        _ => None,
+                    })
+                    .collect();
+                match Callout::from_item(item) {
+                    Some(callout) => {
+                        let mut quoted = Vec::new();
+                        if self.gfm {
+                            quoted.push(callout.kind.gfm_alert());
+                            if let Some(title) = callout.title() {
+                                quoted.push(format!("**{}**", escape(title)));
+                            }
+                        } else {
+                            quoted.push(format!("**{}.**", escape(callout.display_title())));
+                        }
+                        quoted.extend(lines);
+                        quoted.iter().map(|line| format!("> {line}")).collect()
+                    }
+                    None => lines,
+                }
+            }
+            ContentItem::TextLine(line) => vec![self.text(&line.content)],
+            ContentItem::List(list) => {
+                let numbered = list.items.iter().find_map(|item| match item {
+                    ContentItem::ListItem(item) => {
+                        Some(item.marker().starts_with(|c: char| c.is_alphanumeric()))
+                    }
+                    _ => None,
+                }) == Some(true);
+                let items = list.items.iter().filter_map(ContentItem::as_list_item);
+                let mut lines = Vec::new();
+                for (index, list_item) in items.enumerate() {
+                    let marker = if numbered {
+                        format!("{}.", index + 1)
+                    } else {
+                        "-".to_string()
+                    };
+                    let raw: Vec<&str> = list_item
+                        .text
+                        .iter()
+                        .map(|text| text.as_string().trim())
+                        .collect();
+                    let raw = raw.join(" ");
+                    let text = match task(&raw) {
+                        Some((checkbox, rest)) if self.gfm => {
+                            format!("{checkbox} {}", self.inline_text(rest))
+                        }
+                        Some((checkbox, rest)) => {
+                            format!("{} {}", escape(checkbox), self.inline_text(rest))
+                        }
+                        None => self.inline_text(&raw),
+                    };
+                    lines.push(format!("{marker} {text}").trim_end().to_string());
+                    let indent = " ".repeat(marker.len() + 1);
+                    for line in self.items(&list_item.children, depth) {
+                        if line.is_empty() {
+                            lines.push(line);
+                        } else {
+                            lines.push(format!("{indent}{line}"));
+                        }
+                    }
+                }
+                lines
+            }
+            ContentItem::Definition(definition) => {
+                let subject = self.text(&definition.subject);
+                let mut lines = vec![format!("**{}**", subject.trim())];
+                let body = self.items(&definition.children, depth);
+                if !body.is_empty() {
+                    lines.push(String::new());
+                    lines.extend(body);
+                }
+                lines
+            }
+            ContentItem::VerbatimBlock(verbatim) => {
+                if let Some(rendered) = self.extensions.render_block(verbatim, "markdown") {
+                    return rendered.lines().map(str::to_string).collect();
+                }
+                let block = code_block(verbatim);
+                if self.gfm && block.language == TABLE_LABEL {
+                    if let Some(rows) = table_rows(verbatim) {
+                        return table(&rows);
+                    }
+                }
+                let mut lines = Vec::new();
+                let subject = block.subject.trim().trim_end_matches(':');
+                if !subject.is_empty() {
+                    lines.push(format!("*{}*", escape(subject)));
+                    lines.push(String::new());
+                }
+                let fence = "`".repeat(longest_run(&block.text, '`').max(2) + 1);
+                let language = if block.language == TABLE_LABEL {
+                    ""
+                } else {
+                    block.language.trim()
+                };
+                lines.push(format!("{fence}{language}"));
+                lines.extend(block.text.lines().map(str::to_string));
+                lines.push(fence);
+                lines
+            }
+            ContentItem::VerbatimLine(line) => vec![line.content.as_string().to_string()],
+            ContentItem::ListItem(_)
+            | ContentItem::Annotation(_)
+            | ContentItem::BlankLineGroup(_) => Vec::new(),
+        }
+    }
+
+    fn text(&mut self, text: &TextContent) -> String {
+        self.inline_text(text.as_string())
+    }
+
+    fn inline_text(&mut self, text: &str) -> String {
+        let nodes = self.parser.parse(text);
+        self.inlines(&nodes)
+    }
+
+    fn inlines(&mut self, nodes: &[InlineNode]) -> String {
+        let mut out = String::new();
+        for node in nodes {
+            match node {
+                InlineNode::Plain { text, .. } => out.push_str(&escape(text)),
+                InlineNode::Strong { content, .. } => {
+                    out.push_str(&format!("**{}**", self.inlines(content)))
+                }
+                InlineNode::Emphasis { content, .. } => {
+                    out.push_str(&format!("*{}*", self.inlines(content)))
+                }
+                InlineNode::Code { text, .. } => out.push_str(&code_span(text)),
+                InlineNode::Math { text, .. } if self.gfm => out.push_str(&format!("${text}$")),
+                InlineNode::Math { text, .. } => out.push_str(&code_span(text)),
+                InlineNode::Reference { data, .. } => {
+                    if !self.reference_link(&mut out, &data.reference_type) {
+                        out.push_str(&self.reference(&data.reference_type, &data.raw))
+                    }
+                }
+                InlineNode::Ruby { base, text, .. } => out.push_str(&format!(
+                    "<ruby>{}<rt>{}</rt></ruby>",
+                    escape(base),
+                    escape(text)
+                )),
+                InlineNode::Role { data, .. } if data.role == DEL_ROLE => {
+                    if self.gfm {
+                        out.push_str(&format!("~~{}~~", escape(&data.content)))
+                    } else {
+                        out.push_str(&format!("<del>{}</del>", escape(&data.content)))
+                    }
+                }
+                InlineNode::Role { data, .. } => out.push_str(&escape(&data.content)),
+            }
+        }
+        out
+    }
+
+    fn reference(&mut self, reference: &ReferenceType, raw: &str) -> String {
+        match reference {
+            ReferenceType::Url { target } if self.gfm => target.clone(),
+            ReferenceType::Url { target } => format!("<{target}>"),
+            ReferenceType::File { target } => format!("[{}]({target})", escape(target)),
+            ReferenceType::FootnoteNumber { number } => self.footnote(&number.to_string(), raw),
+            ReferenceType::FootnoteLabeled { label } => self.footnote(label, raw),
+            ReferenceType::Session { target } => format!("[{}](#{target})", escape(target)),
+            ReferenceType::General { target } => escape(target),
+            ReferenceType::WikiLink(link) => escape(link.display_text()),
+            ReferenceType::Image(image) => format!(
+                "![{}]({})",
+                escape(image.alt.as_deref().unwrap_or_default()),
+                image.src
+            ),
+            _ => escape(&format!("[{raw}]")),
+        }
+    }
+
+    /// Write a reference following its link text as `[text][id]`, if it is a
+    /// reference-style link; whether it is
+    fn reference_link(&mut self, out: &mut String, reference: &ReferenceType) -> bool {
+        let (ReferenceType::Url { target } | ReferenceType::File { target }) = reference else {
+            return false;
+        };
+        let found = self.links.iter().position(|link| {
+            link.links_to(target) && out.ends_with(&format!("{} ", escape(&link.text)))
+        });
+        let Some(index) = found else {
+            return false;
+        };
+        let link = &self.links[index];
+        out.truncate(out.len() - escape(&link.text).len() - 1);
+        out.push_str(&format!("[{}][{}]", escape(&link.text), link.id));
+        if !self.used_links.contains(&index) {
+            self.used_links.push(index);
+        }
+        true
+    }
+
+    /// Definitions of the reference-style links written, `[id]: url "title"`
+    fn link_definitions(&self) -> Vec<String> {
+        self.used_links
+            .iter()
+            .map(|&index| {
+                let link = &self.links[index];
+                match &link.title {
+                    Some(title) => format!("[{}]: {} \"{title}\"", link.id, link.url),
+                    None => format!("[{}]: {}", link.id, link.url),
+                }
+            })
+            .collect()
+    }
+
+    /// Reference to the footnote labeled `label`, or the reference as written if there is
+    /// none
+    fn footnote(&mut self, label: &str, raw: &str) -> String {
+        if !self.referenced.contains(label) || self.doc.find_annotation_by_label(label).is_none() {
+            return escape(&format!("[{raw}]"));
+        }
+        if !self.footnotes.iter().any(|note| note == label) {
+            self.footnotes.push(label.to_string());
+        }
+        if self.gfm {
+            format!("[^{label}]")
+        } else {
+            format!("<sup>{label}</sup>")
+        }
+    }
+
+    /// Text of the referenced footnotes, as footnote definitions with GFM and a list of
+    /// superscripted notes otherwise
+    fn footnote_lines(&mut self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for label in self.footnotes.clone() {
+            let Some(annotation) = self.doc.find_annotation_by_label(&label) else {
+                continue;
+            };
+            let mut text = Vec::new();
+            for item in annotation.children.iter() {
+                if let ContentItem::Paragraph(paragraph) = item {
+                    for line in paragraph.lines.iter() {
+                        if let ContentItem::TextLine(line) = line {
+                            text.push(self.text(&line.content));
+                        }
+                    }
+                }
+            }
+            let text = text.join(" ");
+            if self.gfm {
+                lines.push(format!("[^{label}]: {text}"));
+            } else {
+                lines.push(format!("- <sup>{label}</sup> {text}"));
+            }
+        }
+        lines
+    }
+}
+
+/// Checkbox and text of a task list item
+fn task(raw: &str) -> Option<(&'static str, &str)> {
+    [("[ ]", "[ ]"), ("[x]", "[x]"), ("[X]", "[x]")]
+        .into_iter()
+        .find_map(|(written, checkbox)| {
+            let rest = raw.strip_prefix(written)?;
+            rest.starts_with(' ').then(|| (checkbox, rest.trim_start()))
+        })
+}
+
+/// A GFM table, with the first row as header
+fn table(rows: &[Vec<String>]) -> Vec<String> {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let row = |cells: &[String]| {
+        let mut cells: Vec<String> = cells
+            .iter()
+            .map(|cell| escape(cell).replace('|', "\\|"))
+            .collect();
+        cells.resize(columns, String::new());
+        format!("| {} |", cells.join(" | "))
+    };
+    let mut lines = Vec::new();
+    if let Some((header, body)) = rows.split_first() {
+        lines.push(row(header));
+        lines.push(format!("|{}", " --- |".repeat(columns)));
+        lines.extend(body.iter().map(|cells| row(cells)));
+    }
+    lines
+}
+
+/// `text` as a code span, with enough backticks to hold the ones inside
+fn code_span(text: &str) -> String {
+    let fence = "`".repeat(longest_run(text, '`') + 1);
+    if text.starts_with('`') || text.ends_with('`') {
+        format!("{fence} {text} {fence}")
+    } else {
+        format!("{fence}{text}{fence}")
+    }
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for next in text.chars() {
+        current = if next == c { current + 1 } else { 0 };
+        longest = longest.max(current);
+    }
+    longest
+}
+
+/// Escape characters Markdown reads as syntax
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '~' | '#'
+        ) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    const SOURCE: &str = "Field Notes\n\nCosts rose, see [#method] and [1]. Old price {del|4 EUR}.\n\n:: id method ::\n1. Method\n\n    Energy is #E = m c^2#, with *care* and `code`, at [https://example.com].\n\n    - [x] Measure\n    - [ ] Report\n\n    Prices:\n        | Item | Price |\n        | Tea  | 2     |\n    :: table\n\n2. Caveats\n\n    :: warning title=\"Data loss\" ::\n    Clearing deletes everything.\n\n:: 1 ::\n    A footnote.\n::\n";
+
+    fn render(flavor: MarkdownFlavor) -> String {
+        let doc = parse_document(SOURCE).unwrap();
+        render_document(&doc, &MarkdownOptions { flavor })
+    }
+
+    #[test]
+    fn test_commonmark() {
+        assert_eq!(
+            render(MarkdownFlavor::CommonMark),
+            "# Field Notes\n\nCosts rose, see [method](#method) and <sup>1</sup>. Old price <del>4 EUR</del>.\n\n<a id=\"method\"></a>\n## 1. Method\n\nEnergy is `E = m c^2`, with **care** and `code`, at <https://example.com>.\n\n- \\[x\\] Measure\n- \\[ \\] Report\n\n*Prices*\n\n```\n| Item | Price |\n| Tea  | 2     |\n```\n\n## 2. Caveats\n\n> **Data loss.**\n> Clearing deletes everything.\n\n- <sup>1</sup> A footnote.\n"
+        );
+    }
+
+    #[test]
+    fn test_gfm() {
+        assert_eq!(
+            render(MarkdownFlavor::Gfm),
+            "# Field Notes\n\nCosts rose, see [method](#method) and [^1]. Old price ~~4 EUR~~.\n\n<a id=\"method\"></a>\n## 1. Method\n\nEnergy is $E = m c^2$, with **care** and `code`, at https://example.com.\n\n- [x] Measure\n- [ ] Report\n\n| Item | Price |\n| --- | --- |\n| Tea | 2 |\n\n## 2. Caveats\n\n> [!WARNING]\n> **Data loss**\n> Clearing deletes everything.\n\n[^1]: A footnote.\n"
+        );
+    }
+
+    #[cfg(feature = "obsidian")]
+    #[test]
+    fn test_reference_links_round_trip() {
+        let markdown = "# Notes\n\nRead [the docs][docs] and [the FAQ][faq].\n\n[docs]: https://example.com \"Docs\"\n[faq]: faq.md\n";
+        let imported = import::import_markdown(markdown, "notes", &Default::default());
+        let doc = parse_document(&imported.source).unwrap();
+        assert_eq!(link_references(&doc)[0].title.as_deref(), Some("Docs"));
+        assert_eq!(render_document(&doc, &MarkdownOptions::default()), markdown);
+    }
+
+    #[test]
+    fn test_flavor_param() {
+        let doc = parse_document(SOURCE).unwrap();
+        let params = HashMap::from([("flavor".to_string(), "GFM".to_string())]);
+        let formatter = MarkdownFormatter::new();
+        assert_eq!(
+            formatter.serialize_with_params(&doc, &params).unwrap(),
+            render(MarkdownFlavor::Gfm)
+        );
+        let params = HashMap::from([("flavor".to_string(), "mmd".to_string())]);
+        assert!(formatter.serialize_with_params(&doc, &params).is_err());
+    }
+}
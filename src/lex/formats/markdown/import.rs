@@ -0,0 +1,850 @@
+//! Markdown import
+//!
+//! [import_markdown] converts Markdown to Lex source:
+//!
+//! - YAML frontmatter: `tags` become a `:: tags ::` annotation, `title` the document title,
+//!   and other keys parameters of a `:: meta ::` annotation (see
+//!   [frontmatter](crate::lex::frontmatter)).
+//! - Headings become nested sessions; a leading level 1 heading is the title. Without one,
+//!   the title is the name given, usually the file name.
+//! - `[text](url)` links become `text [url]`; links to `.md` files point at the `.lex` file.
+//!   Reference-style links (`[text][id]` with a `[id]: url` definition) become the same, and
+//!   their ids, texts and titles are kept in a [`markdown-links`](super::LINKS_LABEL)
+//!   annotation, so the Markdown output writes them as reference-style links again.
+//! - Fenced code becomes verbatim blocks labeled with the language. Tables become table
+//!   blocks.
+//! - `**strong**` and `*emphasis*` become `*strong*` and `_emphasis_`.
+//! - `<!-- lex: label params -->` comments become `:: label params ::` annotations. A
+//!   comment whose `-->` is on a later line is an annotation with the lines between as its
+//!   content.
+//!
+//! Flavors add syntax CommonMark doesn't have. Each is converted only when its option of
+//! [MarkdownImportOptions] is set, and read as plain text otherwise;
+//! [MarkdownImportOptions::detect] sets the options of the flavors a source appears to use.
+//!
+//! - GFM footnotes: `[^1]` references become `[1]` footnote references, `[^label]` stay
+//!   labeled ones, and `[^1]: text` definitions become footnote annotations at the end of
+//!   the document.
+//! - Obsidian: callouts (`> [!warning] Title`) become callout annotations on their text,
+//!   `[[Wiki Links]]` are kept, to be read with wiki links enabled
+//!   ([InlineParser::with_wiki_links](crate::lex::inlines::InlineParser::with_wiki_links)),
+//!   embeds (`![[diagram.png]]`) of attachments become file references, and inline `#tags`
+//!   join the document tags.
+//! - MultiMarkdown metadata: `Key: value` lines opening the document are read as
+//!   frontmatter is.
+//!
+//! A flavor construct has no exact Lex equivalent, so each one converted is reported as an
+//! [ImportWarning], with the line it came from.
+
+use super::{link_target, relative_reference, LINKS_LABEL};
+use crate::lex::annotation::callout::CalloutKind;
+use crate::lex::frontmatter::Frontmatter;
+use crate::lex::tables::table_block;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Markdown flavors to convert
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MarkdownImportOptions {
+    pub gfm_footnotes: bool,
+    /// Callouts, wiki links, embeds and inline tags
+    pub obsidian: bool,
+    pub multimarkdown_metadata: bool,
+}
+
+impl MarkdownImportOptions {
+    /// Options for the flavors `source` appears to use
+    pub fn detect(source: &str) -> Self {
+        let source = source.replace("\r\n", "\n");
+        let lines: Vec<&str> = source.lines().collect();
+        Self {
+            gfm_footnotes: lines.iter().any(|line| footnote_definition(line).is_some()),
+            obsidian: lines.iter().any(|line| {
+                line.trim().starts_with("> [!")
+                    || line
+                        .split_once("[[")
+                        .is_some_and(|(_, rest)| rest.contains("]]"))
+            }),
+            multimarkdown_metadata: multimarkdown_metadata(&source).is_some(),
+        }
+    }
+}
+
+/// A flavor construct converted to its closest Lex equivalent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportWarning {
+    /// 1-based line of the Markdown source
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ImportWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Lex source converted from Markdown, with the flavor constructs it converted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MarkdownImport {
+    pub source: String,
+    pub warnings: Vec<ImportWarning>,
+}
+
+/// Convert the Markdown `source` to Lex, titled `name` unless it has a title
+pub fn import_markdown(
+    source: &str,
+    name: &str,
+    options: &MarkdownImportOptions,
+) -> MarkdownImport {
+    Converter::new(*options, Path::new(""), &BTreeMap::new()).convert(source, name)
+}
+
+/// Target and title of a `[id]: url "title"` link definition
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct LinkDefinition {
+    url: String,
+    title: Option<String>,
+}
+
+/// What the last output line belongs to, to place blank lines between blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    None,
+    Paragraph,
+    List,
+}
+
+pub(crate) struct Converter<'a> {
+    options: MarkdownImportOptions,
+    /// Directory of the note, relative to the vault
+    dir: &'a Path,
+    /// Files embeds can point at, by lowercase file name
+    attachments: &'a BTreeMap<String, PathBuf>,
+    tags: Vec<String>,
+    lines: Vec<String>,
+    last: Block,
+    /// Levels of the open headings
+    headings: Vec<usize>,
+    /// Footnote definitions, as label and Lex text
+    footnotes: Vec<(String, String)>,
+    /// Link definitions, by lowercase id
+    definitions: BTreeMap<String, LinkDefinition>,
+    /// Ids of the reference-style links used, with the text of their first use
+    links: Vec<(String, String)>,
+    /// Source line being converted
+    line: usize,
+    warnings: Vec<ImportWarning>,
+}
+
+impl<'a> Converter<'a> {
+    pub(crate) fn new(
+        options: MarkdownImportOptions,
+        dir: &'a Path,
+        attachments: &'a BTreeMap<String, PathBuf>,
+    ) -> Self {
+        Self {
+            options,
+            dir,
+            attachments,
+            tags: Vec::new(),
+            lines: Vec::new(),
+            last: Block::None,
+            headings: Vec::new(),
+            footnotes: Vec::new(),
+            definitions: BTreeMap::new(),
+            links: Vec::new(),
+            line: 1,
+            warnings: Vec::new(),
+        }
+    }
+
+    pub(crate) fn convert(mut self, source: &str, name: &str) -> MarkdownImport {
+        let source = source.replace("\r\n", "\n");
+        let (frontmatter, mut body) = Frontmatter::split(&source);
+        let mut frontmatter = match frontmatter {
+            Some(frontmatter) => frontmatter,
+            None if self.options.multimarkdown_metadata => match multimarkdown_metadata(body) {
+                Some((metadata, rest)) => {
+                    self.warn("MultiMarkdown metadata became document metadata".to_string());
+                    body = rest;
+                    metadata
+                }
+                None => Frontmatter::default(),
+            },
+            None => Frontmatter::default(),
+        };
+
+        let mut body = body.trim_start_matches('\n');
+        if let Some(heading) = body.lines().next().and_then(|line| line.strip_prefix("# ")) {
+            if frontmatter.title.is_none() {
+                frontmatter.title = Some(heading.trim().to_string());
+            }
+            body = body.split_once('\n').map_or("", |(_, rest)| rest);
+        }
+        for (id, definition) in body.lines().filter_map(link_definition) {
+            self.definitions
+                .entry(id.to_lowercase())
+                .or_insert(definition);
+        }
+        let offset = source[..source.len() - body.len()].matches('\n').count();
+        self.body(body, offset);
+
+        frontmatter.add_tags(std::mem::take(&mut self.tags));
+        let title = frontmatter
+            .title
+            .clone()
+            .unwrap_or_else(|| name.to_string());
+        let mut output = format!("{title}\n{}", frontmatter.to_annotations());
+        output.push_str(&self.links_annotation());
+        for (label, text) in std::mem::take(&mut self.footnotes) {
+            self.blank();
+            self.lines.push(format!(":: {label} ::"));
+            self.lines.push(format!("    {text}"));
+            self.lines.push("::".to_string());
+        }
+        while self.lines.last().is_some_and(String::is_empty) {
+            self.lines.pop();
+        }
+        if !self.lines.is_empty() {
+            output.push('\n');
+            output.push_str(&self.lines.join("\n"));
+            output.push('\n');
+        }
+        MarkdownImport {
+            source: output,
+            warnings: self.warnings,
+        }
+    }
+
+    /// The `markdown-links` annotation keeping the reference-style links used, preceded by
+    /// a blank line; empty if there are none
+    fn links_annotation(&self) -> String {
+        let mut params = Vec::new();
+        for (id, text) in &self.links {
+            let definition = &self.definitions[&id.to_lowercase()];
+            params.push(format!("{id}=\"{}\"", definition.url.replace('"', "%22")));
+            params.push(format!("{id}.text=\"{}\"", text.replace('"', "'")));
+            if let Some(title) = &definition.title {
+                params.push(format!("{id}.title=\"{}\"", title.replace('"', "'")));
+            }
+        }
+        if params.is_empty() {
+            return String::new();
+        }
+        format!("\n:: {LINKS_LABEL} {} ::\n", params.join(", "))
+    }
+
+    fn warn(&mut self, message: String) {
+        self.warnings.push(ImportWarning {
+            line: self.line,
+            message,
+        });
+    }
+
+    /// Convert `body`, whose first line is line `offset + 1` of the source
+    fn body(&mut self, body: &str, offset: usize) {
+        let lines: Vec<&str> = body.lines().collect();
+        let mut index = 0;
+        while index < lines.len() {
+            let line = lines[index];
+            let trimmed = line.trim();
+            self.line = offset + index + 1;
+            index += 1;
+            let definition = if self.options.gfm_footnotes {
+                footnote_definition(line)
+            } else {
+                None
+            };
+            if trimmed.is_empty() {
+                self.last = Block::None;
+            } else if let Some((label, text)) = definition {
+                let mut text = vec![text.trim()];
+                while index < lines.len() && lines[index].starts_with([' ', '\t']) {
+                    text.push(lines[index].trim());
+                    index += 1;
+                }
+                self.footnote(label, &text.join(" "));
+            } else if link_definition(line).is_some() {
+                self.last = Block::None;
+            } else if let Some(header) = lex_comment(trimmed) {
+                let start = index;
+                let marker = header.strip_suffix("-->");
+                if marker.is_none() {
+                    while index < lines.len() && lines[index].trim() != "-->" {
+                        index += 1;
+                    }
+                }
+                let header = marker.unwrap_or(header).trim();
+                self.annotation(header, &lines[start..index]);
+                if marker.is_none() {
+                    index += 1;
+                }
+            } else if let Some(fence) = trimmed.strip_prefix("```") {
+                let start = index;
+                while index < lines.len() && !lines[index].trim().starts_with("```") {
+                    index += 1;
+                }
+                self.code(fence.trim(), &lines[start..index]);
+                index += 1;
+            } else if let Some((level, heading)) = heading(trimmed) {
+                self.heading(level, heading);
+            } else if trimmed.starts_with('>') {
+                let start = index - 1;
+                while index < lines.len() && lines[index].trim().starts_with('>') {
+                    index += 1;
+                }
+                self.quote(&lines[start..index]);
+            } else if trimmed.starts_with('|') {
+                let start = index - 1;
+                while index < lines.len() && lines[index].trim().starts_with('|') {
+                    index += 1;
+                }
+                self.table(&lines[start..index]);
+            } else if is_rule(trimmed) {
+                self.last = Block::None;
+            } else if let Some((level, marker, text)) = list_item(line) {
+                if self.last != Block::List {
+                    self.blank();
+                }
+                let indent = self.indent() + "    ".repeat(level).as_str();
+                let text = self.inline(text);
+                self.lines.push(format!("{indent}{marker} {text}"));
+                self.last = Block::List;
+            } else {
+                if self.last != Block::Paragraph {
+                    self.blank();
+                }
+                let text = format!("{}{}", self.indent(), self.inline(trimmed));
+                self.lines.push(text);
+                self.last = Block::Paragraph;
+            }
+        }
+    }
+
+    /// Indentation of content in the innermost open session
+    fn indent(&self) -> String {
+        "    ".repeat(self.headings.len())
+    }
+
+    fn blank(&mut self) {
+        if self.lines.last().is_some_and(|line| !line.is_empty()) {
+            self.lines.push(String::new());
+        }
+    }
+
+    fn heading(&mut self, level: usize, heading: &str) {
+        while self.headings.last().is_some_and(|open| *open >= level) {
+            self.headings.pop();
+        }
+        self.blank();
+        let title = format!("{}{}", self.indent(), self.inline(heading));
+        self.lines.push(title);
+        self.lines.push(String::new());
+        self.headings.push(level);
+        self.last = Block::None;
+    }
+
+    /// A `<!-- lex: -->` comment with the `header` label and parameters, and the `body`
+    /// lines up to its closing `-->`
+    fn annotation(&mut self, header: &str, body: &[&str]) {
+        let indent = self.indent();
+        self.blank();
+        let text: Vec<&str> = body
+            .iter()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .collect();
+        self.lines.push(format!("{indent}:: {header} ::"));
+        for line in &text {
+            let line = format!("{indent}    {}", self.inline(line));
+            self.lines.push(line);
+        }
+        if !text.is_empty() {
+            self.lines.push(format!("{indent}::"));
+        }
+        self.last = Block::None;
+    }
+
+    fn footnote(&mut self, label: &str, text: &str) {
+        self.warn(format!(
+            "GFM footnote '{label}' became a footnote annotation"
+        ));
+        let text = self.inline(text);
+        self.footnotes.push((label.to_string(), text));
+    }
+
+    fn code(&mut self, language: &str, code: &[&str]) {
+        let indent = self.indent();
+        let label = language.split_whitespace().next().unwrap_or("code");
+        self.blank();
+        self.lines.push(format!("{indent}Code:"));
+        let wall = code
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.len() - line.trim_start().len())
+            .min()
+            .unwrap_or(0);
+        for line in code {
+            if line.trim().is_empty() {
+                self.lines.push(String::new());
+            } else {
+                self.lines.push(format!("{indent}    {}", &line[wall..]));
+            }
+        }
+        self.lines.push(format!("{indent}:: {label}"));
+        self.last = Block::None;
+    }
+
+    fn quote(&mut self, lines: &[&str]) {
+        let text: Vec<&str> = lines
+            .iter()
+            .map(|line| line.trim().trim_start_matches('>').trim())
+            .collect();
+        let indent = self.indent();
+        self.blank();
+        let mut body = &text[..];
+        let callout = text[0].strip_prefix("[!").filter(|_| self.options.obsidian);
+        if let Some(callout) = callout {
+            let (kind, title) = callout.split_once(']').unwrap_or((callout, ""));
+            let kind = kind.trim_end_matches(['+', '-']);
+            let label = CalloutKind::from_label(kind)
+                .unwrap_or(CalloutKind::Note)
+                .label();
+            self.warn(format!(
+                "Obsidian callout '{kind}' became a '{label}' callout annotation"
+            ));
+            let title = title.trim();
+            if title.is_empty() {
+                self.lines.push(format!("{indent}:: {label} ::"));
+            } else {
+                let title = title.replace('"', "'");
+                self.lines
+                    .push(format!("{indent}:: {label} title=\"{title}\" ::"));
+            }
+            body = &text[1..];
+        }
+        for line in body.iter().filter(|line| !line.is_empty()) {
+            let line = format!("{indent}{}", self.inline(line));
+            self.lines.push(line);
+        }
+        self.last = Block::None;
+    }
+
+    fn table(&mut self, lines: &[&str]) {
+        let rows: Vec<Vec<String>> = lines
+            .iter()
+            .map(|line| line.trim().trim_matches('|'))
+            .filter(|line| !line.chars().all(|c| matches!(c, '-' | ':' | '|' | ' ')))
+            .map(|line| {
+                line.split('|')
+                    .map(|cell| cell.trim().to_string())
+                    .collect()
+            })
+            .collect();
+        let indent = self.indent();
+        self.blank();
+        for line in table_block("Table", &rows).lines() {
+            self.lines.push(format!("{indent}{line}"));
+        }
+        self.last = Block::None;
+    }
+
+    /// Markdown inline syntax converted to Lex
+    fn inline(&mut self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut out = String::with_capacity(text.len());
+        let obsidian = self.options.obsidian;
+        let mut i = 0;
+        while i < chars.len() {
+            let rest: String = chars[i..].iter().collect();
+            let prev = i.checked_sub(1).map(|index| chars[index]);
+            if chars[i] == '`' {
+                let end = chars[i + 1..].iter().position(|c| *c == '`');
+                let end = end.map_or(chars.len(), |end| i + end + 2);
+                out.extend(&chars[i..end]);
+                i = end;
+            } else if let Some(embed) = rest.strip_prefix("![[").filter(|_| obsidian) {
+                let Some(end) = embed.find("]]") else {
+                    out.push_str("\\!");
+                    i += 1;
+                    continue;
+                };
+                out.push_str(&self.embed(&embed[..end]));
+                i += 3 + embed[..end].chars().count() + 2;
+            } else if let Some(link) = rest.strip_prefix("[[").filter(|_| obsidian) {
+                let Some(end) = link.find("]]") else {
+                    out.push_str("\\[");
+                    i += 1;
+                    continue;
+                };
+                self.warn(format!(
+                    "Obsidian wiki link '{}' was kept as a wiki link",
+                    &link[..end]
+                ));
+                out.push_str(&wiki_link(&link[..end]));
+                i += 2 + link[..end].chars().count() + 2;
+            } else if let Some(label) =
+                footnote_reference(&rest).filter(|_| self.options.gfm_footnotes)
+            {
+                let reference = if label.chars().all(|c| c.is_ascii_digit()) {
+                    format!("[{label}]")
+                } else {
+                    format!("[^{label}]")
+                };
+                self.warn(format!(
+                    "GFM footnote reference '[^{label}]' became {reference}"
+                ));
+                out.push_str(&reference);
+                i += label.chars().count() + 3;
+            } else if let Some((length, converted)) = self.reference_link(&rest) {
+                out.push_str(&converted);
+                i += length;
+            } else if let Some((length, converted)) = self.markdown_link(&rest) {
+                out.push_str(&converted);
+                i += length;
+            } else if rest.starts_with("**") || rest.starts_with("__") {
+                out.push('*');
+                i += 2;
+            } else if chars[i] == '*' {
+                out.push('_');
+                i += 1;
+            } else if obsidian && chars[i] == '#' && !prev.is_some_and(char::is_alphanumeric) {
+                let tag: String = chars[i + 1..]
+                    .iter()
+                    .take_while(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '/'))
+                    .collect();
+                if tag.chars().any(char::is_alphabetic) {
+                    self.warn(format!("Obsidian tag '#{tag}' became a document tag"));
+                    self.tags.push(tag.to_lowercase());
+                    out.push_str(&tag);
+                    i += 1 + tag.chars().count();
+                } else {
+                    out.push_str("\\#");
+                    i += 1;
+                }
+            } else if matches!(chars[i], '#' | '[' | '{') {
+                out.push('\\');
+                out.push(chars[i]);
+                i += 1;
+            } else {
+                out.push(chars[i]);
+                i += 1;
+            }
+        }
+        out
+    }
+
+    /// `[text](target)` or `![alt](target)` at the start of `text`: its length in chars
+    /// and its conversion
+    fn markdown_link(&self, text: &str) -> Option<(usize, String)> {
+        let image = text.starts_with('!');
+        let rest = text.strip_prefix('!').unwrap_or(text).strip_prefix('[')?;
+        let (label, rest) = rest.split_once("](")?;
+        let (target, _) = rest.split_once(')')?;
+        if label.contains(['[', ']']) || target.contains(char::is_whitespace) {
+            return None;
+        }
+        let length = usize::from(image) + label.chars().count() + target.chars().count() + 4;
+        let reference = link_target(target);
+        let label = label.replace(['[', '#', '{'], "");
+        if label.is_empty() {
+            Some((length, format!("[{reference}]")))
+        } else {
+            Some((length, format!("{label} [{reference}]")))
+        }
+    }
+
+    /// `[text][id]`, `[text][]` or `[text]` at the start of `text`, with a definition for
+    /// its id: its length in chars and its conversion
+    fn reference_link(&mut self, text: &str) -> Option<(usize, String)> {
+        let rest = text.strip_prefix('[')?;
+        let end = rest.find(']')?;
+        let label = &rest[..end];
+        if label.is_empty() || label.contains('[') || label.starts_with('^') {
+            return None;
+        }
+        let after = &rest[end + 1..];
+        let (id, length) = match after.strip_prefix('[') {
+            Some(reference) => {
+                let close = reference.find(']')?;
+                let id = &reference[..close];
+                let length = label.chars().count() + id.chars().count() + 4;
+                (if id.is_empty() { label } else { id }, length)
+            }
+            None if after.starts_with('(') => return None,
+            None => (label, label.chars().count() + 2),
+        };
+        let definition = self.definitions.get(&id.to_lowercase())?;
+        let reference = link_target(&definition.url);
+        let text = label.replace(['[', '#', '{'], "");
+        // Ids that can't be annotation parameter keys are converted, but not kept
+        let keepable = id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+        let known = self
+            .links
+            .iter()
+            .any(|(kept, _)| kept.eq_ignore_ascii_case(id));
+        if keepable && !known {
+            self.links.push((id.to_string(), text.clone()));
+        }
+        Some((length, format!("{text} [{reference}]")))
+    }
+
+    /// An embedded file: a reference to it when it is an attachment of the vault, else a
+    /// wiki link to the embedded note
+    fn embed(&mut self, target: &str) -> String {
+        let name = target.split(['|', '#']).next().unwrap_or_default().trim();
+        match self.attachments.get(&name.to_lowercase()) {
+            Some(path) => {
+                self.warn(format!("Obsidian embed '{target}' became a file reference"));
+                let up = "../".repeat(self.dir.components().count());
+                format!(
+                    "[{}]",
+                    relative_reference(&format!("{up}{}", path.display()))
+                )
+            }
+            None => {
+                self.warn(format!("Obsidian embed '{target}' became a wiki link"));
+                wiki_link(target)
+            }
+        }
+    }
+}
+
+/// Id and definition of a `[id]: url "title"` link definition line
+fn link_definition(line: &str) -> Option<(&str, LinkDefinition)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let rest = line[indent..].strip_prefix('[').filter(|_| indent <= 3)?;
+    let (id, rest) = rest.split_once("]:")?;
+    if id.trim().is_empty() || id.starts_with('^') || id.contains(['[', ']']) {
+        return None;
+    }
+    let rest = rest.trim();
+    let (url, title) = match rest.strip_prefix('<') {
+        Some(bracketed) => bracketed.split_once('>')?,
+        None => rest.split_once(char::is_whitespace).unwrap_or((rest, "")),
+    };
+    if url.is_empty() {
+        return None;
+    }
+    let title = title.trim();
+    let title = [('"', '"'), ('\'', '\''), ('(', ')')]
+        .iter()
+        .find_map(|(open, close)| title.strip_prefix(*open)?.strip_suffix(*close));
+    Some((
+        id.trim(),
+        LinkDefinition {
+            url: url.to_string(),
+            title: title.map(str::to_string),
+        },
+    ))
+}
+
+/// Label and text of a `[^label]: text` footnote definition
+fn footnote_definition(line: &str) -> Option<(&str, &str)> {
+    let label = footnote_reference(line)?;
+    let text = line[label.len() + 3..].strip_prefix(':')?;
+    Some((label, text))
+}
+
+/// Label of a `[^label]` footnote reference at the start of `text`
+fn footnote_reference(text: &str) -> Option<&str> {
+    let rest = text.strip_prefix("[^")?;
+    let end = rest.find(']')?;
+    let label = &rest[..end];
+    (!label.is_empty() && !label.contains(char::is_whitespace)).then_some(label)
+}
+
+/// MultiMarkdown metadata opening `source`, if any, and the rest of the source
+///
+/// Metadata is `Key: value` lines up to the first blank line; indented lines continue the
+/// value above. Keys are compared without spaces and ignoring case, as MultiMarkdown does.
+fn multimarkdown_metadata(source: &str) -> Option<(Frontmatter, &str)> {
+    let end = source.find("\n\n").map_or(source.len(), |end| end + 1);
+    let (block, rest) = source.split_at(end);
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for line in block.lines() {
+        if line.starts_with([' ', '\t']) {
+            let (_, value) = fields.last_mut()?;
+            value.push(' ');
+            value.push_str(line.trim());
+            continue;
+        }
+        let (key, value) = line.split_once(':')?;
+        // `https://...` opens a line of text, not metadata
+        let valid = !value.starts_with("//")
+            && key.starts_with(|c: char| c.is_ascii_alphabetic())
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, ' ' | '-' | '_'));
+        if !valid {
+            return None;
+        }
+        let key: String = key.split_whitespace().collect::<String>().to_lowercase();
+        fields.push((key, value.trim().to_string()));
+    }
+    if fields.is_empty() {
+        return None;
+    }
+    let mut frontmatter = Frontmatter::default();
+    for (key, value) in fields {
+        match key.as_str() {
+            "title" => frontmatter.title = Some(value),
+            "tags" | "keywords" => frontmatter.add_tags(
+                value
+                    .split(',')
+                    .map(|tag| tag.trim().to_string())
+                    .filter(|tag| !tag.is_empty()),
+            ),
+            _ => frontmatter.meta.push((key, value)),
+        }
+    }
+    Some((frontmatter, rest))
+}
+
+/// A Lex wiki link to `target`, without the `.md` extension Obsidian allows
+fn wiki_link(target: &str) -> String {
+    let split = target.find(['#', '|']).unwrap_or(target.len());
+    let (file, rest) = target.split_at(split);
+    format!("[[{}{rest}]]", file.strip_suffix(".md").unwrap_or(file))
+}
+
+/// Level and text of a `#` heading
+/// Label and parameters of a `<!-- lex: ... -->` comment line, with the closing `-->` if
+/// it is on the same line
+fn lex_comment(line: &str) -> Option<&str> {
+    let rest = line
+        .strip_prefix("<!--")?
+        .trim_start()
+        .strip_prefix("lex:")?;
+    let header = rest.trim();
+    let label = header.strip_suffix("-->").unwrap_or(header).trim();
+    (!label.is_empty()).then_some(header)
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let text = line[level..].strip_prefix(' ')?;
+    (1..=6).contains(&level).then_some((level, text.trim()))
+}
+
+fn is_rule(line: &str) -> bool {
+    line.len() >= 3
+        && ["-", "*", "_"]
+            .iter()
+            .any(|mark| line.chars().all(|c| c.to_string() == *mark || c == ' '))
+}
+
+/// Nesting level, Lex marker and text of a list item line
+fn list_item(line: &str) -> Option<(usize, String, &str)> {
+    let content = line.trim_start();
+    let leading = &line[..line.len() - content.len()];
+    let width: usize = leading.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum();
+    let level = width / 2;
+    if let Some(text) = content
+        .strip_prefix("- ")
+        .or_else(|| content.strip_prefix("* "))
+        .or_else(|| content.strip_prefix("+ "))
+    {
+        return Some((level, "-".to_string(), text.trim()));
+    }
+    let digits = content.chars().take_while(char::is_ascii_digit).count();
+    let text = content[digits..]
+        .strip_prefix(". ")
+        .or_else(|| content[digits..].strip_prefix(") "))?;
+    (digits > 0).then(|| (level, format!("{}.", &content[..digits]), text.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    const FLAVORED: &str = "Title: Cache Design\nAuthor: Ada\nTags: Rust, caching\n\nHot entries stay[^1], see [[Eviction]].\n\n> [!tip] Sizing\n> Start small.\n\n[^1]: Until they expire,\n    or are evicted.\n";
+
+    #[test]
+    fn test_detect() {
+        let detected = MarkdownImportOptions::detect(FLAVORED);
+        assert!(detected.gfm_footnotes && detected.obsidian && detected.multimarkdown_metadata);
+        let plain =
+            MarkdownImportOptions::detect("# Notes\n\nSee [the docs](https://example.com).\n");
+        assert_eq!(plain, MarkdownImportOptions::default());
+    }
+
+    #[test]
+    fn test_import_flavors() {
+        let import = import_markdown(FLAVORED, "notes", &MarkdownImportOptions::detect(FLAVORED));
+        assert_eq!(
+            import.source,
+            "Cache Design\n\n:: tags caching, rust ::\n\n:: meta author=\"Ada\" ::\n\nHot entries stay[1], see [[Eviction]].\n\n:: tip title=\"Sizing\" ::\nStart small.\n\n:: 1 ::\n    Until they expire, or are evicted.\n::\n"
+        );
+        let warnings: Vec<String> = import.warnings.iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "line 1: MultiMarkdown metadata became document metadata",
+                "line 5: GFM footnote reference '[^1]' became [1]",
+                "line 5: Obsidian wiki link 'Eviction' was kept as a wiki link",
+                "line 7: Obsidian callout 'tip' became a 'tip' callout annotation",
+                "line 10: GFM footnote '1' became a footnote annotation",
+            ]
+        );
+        let doc = parse_document(&import.source).unwrap();
+        assert_eq!(doc.title(), "Cache Design");
+        assert!(doc.find_annotation_by_label("1").is_some());
+    }
+
+    #[test]
+    fn test_import_lex_comments() {
+        let markdown = "# Notes\n\n<!-- lex: status draft=true -->\nStill rough.\n\n## Open\n\n<!-- lex: todo\nCheck the **numbers**.\n-->\nNothing yet.\n";
+        let import = import_markdown(markdown, "notes", &MarkdownImportOptions::default());
+        assert_eq!(
+            import.source,
+            "Notes\n\n:: status draft=true ::\n\nStill rough.\n\nOpen\n\n    :: todo ::\n        Check the *numbers*.\n    ::\n\n    Nothing yet.\n"
+        );
+        let doc = parse_document(&import.source).unwrap();
+        let labels: Vec<&str> = doc
+            .annotations
+            .iter()
+            .chain(
+                doc.root
+                    .iter_all_nodes()
+                    .flat_map(|item| item.annotations()),
+            )
+            .map(|annotation| annotation.data.label.value.as_str())
+            .collect();
+        assert_eq!(labels, vec!["status", "todo"]);
+    }
+
+    #[test]
+    fn test_import_reference_links() {
+        let markdown = "# Notes\n\nRead [the docs][docs], the [FAQ][] and [Docs] again.\n\n[docs]: https://example.com/docs \"Docs\"\n[faq]: <./faq.md>\n";
+        let import = import_markdown(markdown, "notes", &MarkdownImportOptions::default());
+        assert_eq!(
+            import.source,
+            "Notes\n\n:: markdown-links docs=\"https://example.com/docs\", docs.text=\"the docs\", docs.title=\"Docs\", FAQ=\"./faq.md\", FAQ.text=\"FAQ\" ::\n\nRead the docs [https://example.com/docs], the FAQ [./faq.lex] and Docs [https://example.com/docs] again.\n"
+        );
+        assert_eq!(
+            link_definition("  [a b]: /x 'T'").unwrap().1,
+            LinkDefinition {
+                url: "/x".to_string(),
+                title: Some("T".to_string())
+            }
+        );
+        assert!(link_definition("[^1]: note").is_none());
+    }
+
+    #[test]
+    fn test_import_without_flavors() {
+        let import = import_markdown(FLAVORED, "notes", &MarkdownImportOptions::default());
+        assert!(import.warnings.is_empty());
+        assert!(import.source.starts_with("notes\n\nTitle: Cache Design\n"));
+        assert!(import
+            .source
+            .contains("Hot entries stay\\[^1], see \\[\\[Eviction]]."));
+        assert!(import.source.contains("\\[!tip] Sizing\nStart small."));
+    }
+}
@@ -0,0 +1,280 @@
+//! Outlines (OPML)
+//!
+//! OPML is the exchange format of outliners (OmniOutliner, Workflowy, Dynalist). The `opml`
+//! format exports the session hierarchy of a document: one `<outline>` per session, with the
+//! session title as its `text`, nested as the sessions are.
+//!
+//! [import_opml] goes the other way, building a skeleton Lex document from an outline.
+//! Outlines with children become sessions and childless ones list items of their parent. An
+//! outline's `_note` becomes a paragraph of its session. The `<title>` of the head is the
+//! document title. Other outline attributes are dropped, and listed in the document's
+//! [provenance](crate::lex::annotation::provenance).
+
+use crate::lex::annotation::provenance::Provenance;
+use crate::lex::ast::traits::AstNode;
+use crate::lex::ast::{ContentItem, Document};
+use crate::lex::formats::markup::{escape, unescape, ATTRIBUTE};
+use crate::lex::formats::registry::{FormatError, Formatter};
+use crate::lex::formats::report::ConversionReport;
+use crate::lex::parsing::parse_document;
+use std::collections::BTreeSet;
+
+/// Session hierarchy as OPML
+pub struct OpmlFormatter;
+
+impl Formatter for OpmlFormatter {
+    fn name(&self) -> &str {
+        "opml"
+    }
+
+    fn serialize(&self, doc: &Document) -> Result<String, FormatError> {
+        Ok(serialize_outline(doc))
+    }
+
+    /// Reports the content of sessions other than their subsessions, which outlines drop
+    fn serialize_with_report(
+        &self,
+        doc: &Document,
+    ) -> Result<(String, ConversionReport), FormatError> {
+        let mut report = ConversionReport::new("opml");
+        let sessions = std::iter::once(&doc.root).chain(doc.root.iter_sessions_recursive());
+        for session in sessions {
+            for item in session.children.iter() {
+                if !matches!(item, ContentItem::Session(_)) && !item.is_blank_line_group() {
+                    report.lost(
+                        format!(
+                            "{} was left out; outlines keep session titles only.",
+                            item.node_type()
+                        ),
+                        Some(item.range().clone()),
+                    );
+                }
+            }
+        }
+        Ok((serialize_outline(doc), report))
+    }
+
+    fn supports_parsing(&self) -> bool {
+        true
+    }
+
+    /// The document [import_opml] builds
+    fn parse(&self, source: &str) -> Result<Document, FormatError> {
+        parse_document(&import_opml(source)?).map_err(FormatError::ParseError)
+    }
+
+    fn description(&self) -> &str {
+        "Session outline as OPML, for outliners"
+    }
+}
+
+/// OPML outline of the sessions of `doc`
+pub fn serialize_outline(doc: &Document) -> String {
+    let mut out =
+        String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n");
+    out.push_str("  <head>\n");
+    out.push_str(&format!(
+        "    <title>{}</title>\n",
+        escape(doc.title().trim())
+    ));
+    out.push_str("  </head>\n  <body>\n");
+    outlines(&doc.root.children, 2, &mut out);
+    out.push_str("  </body>\n</opml>\n");
+    out
+}
+
+fn outlines(items: &[ContentItem], depth: usize, out: &mut String) {
+    for item in items {
+        let ContentItem::Session(session) = item else {
+            continue;
+        };
+        let indent = "  ".repeat(depth);
+        let text = escape(session.title.as_string().trim());
+        if session.iter_sessions().next().is_none() {
+            out.push_str(&format!("{indent}<outline text=\"{text}\"/>\n"));
+        } else {
+            out.push_str(&format!("{indent}<outline text=\"{text}\">\n"));
+            outlines(&session.children, depth + 1, out);
+            out.push_str(&format!("{indent}</outline>\n"));
+        }
+    }
+}
+
+/// An `<outline>` element
+#[derive(Debug, Default)]
+struct Outline {
+    text: String,
+    note: Option<String>,
+    children: Vec<Outline>,
+}
+
+/// Skeleton Lex document of the OPML outline `source`
+pub fn import_opml(source: &str) -> Result<String, FormatError> {
+    let error = |message: &str| FormatError::ParseError(format!("invalid OPML: {message}"));
+    let mut title = String::new();
+    let mut dropped = BTreeSet::new();
+    // Open outlines, the body being the first
+    let mut stack = vec![Outline::default()];
+    let mut rest = source;
+    while let Some(start) = rest.find('<') {
+        let end = rest[start..]
+            .find('>')
+            .map(|end| start + end)
+            .ok_or_else(|| error("unclosed tag"))?;
+        let tag = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+
+        if let Some(closing) = tag.strip_prefix('/') {
+            if closing.trim() == "outline" {
+                let outline = stack.pop().filter(|_| !stack.is_empty());
+                let outline = outline.ok_or_else(|| error("unbalanced </outline>"))?;
+                stack.last_mut().unwrap().children.push(outline);
+            }
+            continue;
+        }
+        let name = tag
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default();
+        if name == "outline" {
+            let mut outline = Outline::default();
+            for captures in ATTRIBUTE.captures_iter(tag) {
+                let value = captures
+                    .get(2)
+                    .or(captures.get(3))
+                    .map_or("", |m| m.as_str());
+                match &captures[1] {
+                    "text" => outline.text = unescape(value).trim().to_string(),
+                    "_note" => outline.note = Some(unescape(value).trim().to_string()),
+                    other => {
+                        dropped.insert(other.to_string());
+                    }
+                }
+            }
+            if tag.ends_with('/') {
+                stack.last_mut().unwrap().children.push(outline);
+            } else {
+                stack.push(outline);
+            }
+        } else if name == "title" && !tag.ends_with('/') {
+            let close = rest
+                .find("</title>")
+                .ok_or_else(|| error("unclosed <title>"))?;
+            title = unescape(&rest[..close]).trim().to_string();
+            rest = &rest[close..];
+        }
+    }
+    if stack.len() != 1 {
+        return Err(error("unclosed <outline>"));
+    }
+
+    let mut provenance = Provenance::new("opml");
+    if !dropped.is_empty() {
+        let names: Vec<String> = dropped.into_iter().collect();
+        provenance.lost(format!(
+            "Outline attributes were dropped: {}.",
+            names.join(", ")
+        ));
+    }
+
+    let body = stack.pop().unwrap();
+    let mut lines = Vec::new();
+    write_children(&body.children, 0, &mut lines);
+    while lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+    let mut out = lines.join("\n");
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    Ok(provenance.document(&title, &out))
+}
+
+fn write_children(children: &[Outline], depth: usize, lines: &mut Vec<String>) {
+    let indent = "    ".repeat(depth);
+    let mut in_list = false;
+    for outline in children {
+        if outline.children.is_empty() && outline.note.is_none() {
+            in_list = true;
+            lines.push(format!("{indent}- {}", outline.text));
+            continue;
+        }
+        if in_list {
+            lines.push(String::new());
+            in_list = false;
+        }
+        lines.push(format!("{indent}{}", outline.text));
+        lines.push(String::new());
+        if let Some(note) = outline.note.as_ref().filter(|note| !note.is_empty()) {
+            for line in note.lines() {
+                lines.push(format!("{indent}    {}", line.trim()));
+            }
+            lines.push(String::new());
+        }
+        write_children(&outline.children, depth + 1, lines);
+    }
+    if in_list {
+        lines.push(String::new());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    #[test]
+    fn test_serialize_outline() {
+        let source = "Guide\n\nIntro.\n\n1. Setup & Install\n\n    Text.\n\n    1.1. Linux\n\n        Text.\n\n2. Usage\n\n    Text.\n";
+        let doc = parse_document(source).unwrap();
+        assert_eq!(
+            OpmlFormatter.serialize(&doc).unwrap(),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n  <head>\n    <title>Guide</title>\n  </head>\n  <body>\n    <outline text=\"1. Setup &amp; Install\">\n      <outline text=\"1.1. Linux\"/>\n    </outline>\n    <outline text=\"2. Usage\"/>\n  </body>\n</opml>\n"
+        );
+
+        let (_, report) = OpmlFormatter.serialize_with_report(&doc).unwrap();
+        assert_eq!(report.losses.len(), 4);
+        assert_eq!(
+            report.losses[0].message,
+            "Paragraph was left out; outlines keep session titles only."
+        );
+        assert_eq!(report.losses[0].range.as_ref().unwrap().start.line, 2);
+    }
+
+    #[test]
+    fn test_import_opml() {
+        let opml = r#"<?xml version="1.0"?>
+<opml version="2.0">
+  <head><title>Trip &amp; Plans</title></head>
+  <body>
+    <outline text="Packing" _note="Pack light." created="2024-05-01">
+      <outline text="Passport"/>
+      <outline text="Charger"/>
+    </outline>
+    <outline text='Route'>
+      <outline text="Day one">
+        <outline text="Drive north"/>
+        <outline text="Camp"/>
+      </outline>
+    </outline>
+  </body>
+</opml>"#;
+        let lex = import_opml(opml).unwrap();
+        assert_eq!(
+            lex,
+            "Trip & Plans\n\n:: provenance source=opml ::\n    Outline attributes were dropped: created.\n\nPacking\n\n    Pack light.\n\n    - Passport\n    - Charger\n\nRoute\n\n    Day one\n\n        - Drive north\n        - Camp\n"
+        );
+
+        let doc = parse_document(&lex).unwrap();
+        assert_eq!(doc.title(), "Trip & Plans");
+        let titles: Vec<String> = doc
+            .root
+            .iter_sessions_recursive()
+            .map(|session| session.title.as_string().to_string())
+            .collect();
+        assert_eq!(titles, vec!["Packing", "Route", "Day one"]);
+        assert_eq!(Provenance::of(&doc).unwrap().source, "opml");
+
+        assert!(import_opml("<opml><body><outline text=\"x\"></body></opml>").is_err());
+    }
+}
@@ -0,0 +1,48 @@
+//! Outline JSON
+//!
+//! The `outline-json` format serializes the session tree of a document as compact JSON (see
+//! [outline_json]) for web front ends and documentation portals, which render their own
+//! navigation from it. It is much smaller than the document itself, as no content is
+//! included.
+
+use crate::lex::analysis::outline_json;
+use crate::lex::ast::Document;
+use crate::lex::formats::registry::{FormatError, Formatter};
+
+/// Session tree as compact JSON
+pub struct OutlineJsonFormatter;
+
+impl Formatter for OutlineJsonFormatter {
+    fn name(&self) -> &str {
+        "outline-json"
+    }
+
+    fn extension(&self) -> &str {
+        "json"
+    }
+
+    fn serialize(&self, doc: &Document) -> Result<String, FormatError> {
+        serde_json::to_string(&outline_json(doc))
+            .map_err(|err| FormatError::SerializationError(err.to_string()))
+    }
+
+    fn description(&self) -> &str {
+        "Session tree as compact JSON (titles, levels, ids, ranges, word counts)"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    #[test]
+    fn test_outline_json_format() {
+        let doc = parse_document("Guide\n\n1. Setup\n\n    Text here.\n").unwrap();
+        let written = OutlineJsonFormatter.serialize(&doc).unwrap();
+        assert!(!written.contains('\n'));
+        let json: serde_json::Value = serde_json::from_str(&written).unwrap();
+        assert_eq!(json["sessions"][0]["title"], "1. Setup");
+        assert_eq!(json["sessions"][0]["words"], 4);
+    }
+}
@@ -0,0 +1,677 @@
+//! PDF
+//!
+//! The `pdf` format lays a document out on pages itself, so `lex convert --to pdf` works
+//! without a TeX or Typst installation. The layout is plain rather than typeset; for
+//! typeset output, convert to [typst](super::typst) or [latex](super::latex) and compile.
+//!
+//! - The document title opens the first page, large and bold, and sessions are bold
+//!   headings sized by depth.
+//! - Paragraphs are wrapped to the text width. List items hang from their bullet or marker,
+//!   and definitions are a bold subject over their indented content.
+//! - Verbatim blocks are set in Courier, broken at the right margin.
+//! - Callouts are indented under their title in bold.
+//! - Inline markup is dropped for its text; URLs are written out.
+//! - Pages are numbered at the bottom.
+//!
+//! Other annotations are metadata and are left out; the title and the `author` from the
+//! `meta` annotation fill the document information.
+//!
+//! Pages have the size of [PdfOptions::page_size] (the `page-size` parameter) and one inch
+//! margins. Text uses the standard Helvetica and Courier fonts every PDF reader provides, so
+//! no font is embedded; characters outside Windows-1252 print as `?`. Streams are not
+//! compressed and strings are escaped, so the file is 7-bit ASCII, and
+//! [Formatter::serialize] can return it as a string.
+
+use crate::lex::annotation::callout::Callout;
+use crate::lex::ast::{ContentItem, Document, TextContent};
+use crate::lex::formats::registry::{FormatError, Formatter};
+use crate::lex::inlines::{InlineNode, InlineParser, ReferenceType};
+use crate::lex::literate::code_block;
+use crate::lex::variables::Variables;
+use std::collections::HashMap;
+
+/// Page margin, in points
+const MARGIN: f64 = 72.0;
+const BODY_SIZE: f64 = 11.0;
+const CODE_SIZE: f64 = 9.5;
+const TITLE_SIZE: f64 = 20.0;
+/// Heading sizes, by session depth; deeper sessions take the last
+const HEADING_SIZES: [f64; 3] = [16.0, 13.5, 12.0];
+/// Indentation of nested content, in points
+const INDENT: f64 = 18.0;
+/// Line height, as a multiple of the font size
+const LEADING: f64 = 1.35;
+
+/// Widths of the printable ASCII characters in Helvetica, in thousandths of the font size
+const HELVETICA: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+/// Widths of the printable ASCII characters in Helvetica Bold
+const HELVETICA_BOLD: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722, 722, 667,
+    611, 778, 722, 278, 556, 722, 611, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556,
+    278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+/// Paper size of the pages
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PdfPageSize {
+    #[default]
+    A4,
+    A5,
+    Letter,
+    Legal,
+}
+
+impl PdfPageSize {
+    /// Size named `name` (`a4`, `a5`, `letter` or `legal`), case-insensitively
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "a4" => Some(PdfPageSize::A4),
+            "a5" => Some(PdfPageSize::A5),
+            "letter" => Some(PdfPageSize::Letter),
+            "legal" => Some(PdfPageSize::Legal),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PdfPageSize::A4 => "a4",
+            PdfPageSize::A5 => "a5",
+            PdfPageSize::Letter => "letter",
+            PdfPageSize::Legal => "legal",
+        }
+    }
+
+    /// Width and height, in points
+    pub fn dimensions(&self) -> (f64, f64) {
+        match self {
+            PdfPageSize::A4 => (595.28, 841.89),
+            PdfPageSize::A5 => (419.53, 595.28),
+            PdfPageSize::Letter => (612.0, 792.0),
+            PdfPageSize::Legal => (612.0, 1008.0),
+        }
+    }
+}
+
+/// Options of the PDF output
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PdfOptions {
+    pub page_size: PdfPageSize,
+}
+
+/// Document laid out as PDF pages
+#[derive(Debug, Clone, Default)]
+pub struct PdfFormatter {
+    pub options: PdfOptions,
+}
+
+impl PdfFormatter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: PdfOptions) -> Self {
+        Self { options }
+    }
+
+    /// The formatter's options, with `page-size` overriding the page size
+    fn options_with(&self, params: &HashMap<String, String>) -> Result<PdfOptions, FormatError> {
+        let mut options = self.options.clone();
+        if let Some(size) = params.get("page-size") {
+            options.page_size = PdfPageSize::from_name(size).ok_or_else(|| {
+                FormatError::SerializationError(format!("Unknown page size '{size}'"))
+            })?;
+        }
+        Ok(options)
+    }
+}
+
+impl Formatter for PdfFormatter {
+    fn name(&self) -> &str {
+        "pdf"
+    }
+
+    fn serialize(&self, doc: &Document) -> Result<String, FormatError> {
+        ascii(render_document(doc, &self.options))
+    }
+
+    /// `page-size` overrides the formatter's page size
+    fn serialize_with_params(
+        &self,
+        doc: &Document,
+        params: &HashMap<String, String>,
+    ) -> Result<String, FormatError> {
+        ascii(render_document(doc, &self.options_with(params)?))
+    }
+
+    fn serialize_bytes(
+        &self,
+        doc: &Document,
+        params: &HashMap<String, String>,
+    ) -> Result<Vec<u8>, FormatError> {
+        Ok(render_document(doc, &self.options_with(params)?))
+    }
+
+    fn description(&self) -> &str {
+        "PDF pages, laid out without external tools"
+    }
+}
+
+fn ascii(bytes: Vec<u8>) -> Result<String, FormatError> {
+    String::from_utf8(bytes).map_err(|error| FormatError::SerializationError(error.to_string()))
+}
+
+/// Lay `doc` out as a PDF file, as set by `options`
+pub fn render_document(doc: &Document, options: &PdfOptions) -> Vec<u8> {
+    let (width, height) = options.page_size.dimensions();
+    let mut layout = Layout {
+        width,
+        height,
+        pages: Vec::new(),
+        page: String::new(),
+        y: height - MARGIN,
+    };
+    let title = doc.title().trim();
+    if !title.is_empty() {
+        let title = plain(&InlineParser::new().parse(title));
+        layout.paragraph(&title, Font::Bold, TITLE_SIZE, 0.0);
+        layout.gap(TITLE_SIZE * 0.6);
+    }
+    layout.items(&doc.root.children, 0, 0.0);
+    let pages = layout.finish();
+
+    let mut info = Vec::new();
+    if !title.is_empty() {
+        info.push(("Title", title.to_string()));
+        if let Some(author) = Variables::for_document(doc).meta.get("author") {
+            info.push(("Author", author.clone()));
+        }
+    }
+    write_pdf(&pages, &info, width, height)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Font {
+    Regular,
+    Bold,
+    Mono,
+}
+
+impl Font {
+    /// Name of the font in page resources
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+            Font::Mono => "F3",
+        }
+    }
+
+    /// Width of `c` set at `size`, in points
+    fn width(self, c: char, size: f64) -> f64 {
+        let widths = match self {
+            Font::Regular => &HELVETICA,
+            Font::Bold => &HELVETICA_BOLD,
+            Font::Mono => return size * 0.6,
+        };
+        let units = match c {
+            ' '..='~' => widths[c as usize - 32],
+            '\u{2022}' => 350,
+            _ => 556,
+        };
+        size * f64::from(units) / 1000.0
+    }
+
+    fn text_width(self, text: &str, size: f64) -> f64 {
+        text.chars().map(|c| self.width(c, size)).sum()
+    }
+}
+
+/// Pages laid out so far, and the position on the current one
+struct Layout {
+    width: f64,
+    height: f64,
+    /// Content streams of the finished pages
+    pages: Vec<String>,
+    page: String,
+    /// Baseline of the last line, from the bottom of the page
+    y: f64,
+}
+
+impl Layout {
+    fn text_width(&self) -> f64 {
+        self.width - 2.0 * MARGIN
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(std::mem::take(&mut self.page));
+        self.y = self.height - MARGIN;
+    }
+
+    /// Vertical space, dropped at the top of a page
+    fn gap(&mut self, height: f64) {
+        if !self.page.is_empty() {
+            self.y -= height;
+        }
+    }
+
+    /// Draw `text` on a new line, `indent` points from the margin
+    fn line(&mut self, text: &str, font: Font, size: f64, indent: f64) {
+        self.y -= size * LEADING;
+        if self.y < MARGIN {
+            self.new_page();
+            self.y -= size * LEADING;
+        }
+        let x = MARGIN + indent;
+        self.draw(text, font, size, x, self.y);
+    }
+
+    fn draw(&mut self, text: &str, font: Font, size: f64, x: f64, y: f64) {
+        self.page.push_str(&format!(
+            "BT /{} {} Tf {} {} Td {} Tj ET\n",
+            font.resource(),
+            number(size),
+            number(x),
+            number(y),
+            pdf_string(text)
+        ));
+    }
+
+    /// Draw `text` wrapped to the text width, `indent` points from the margin
+    fn paragraph(&mut self, text: &str, font: Font, size: f64, indent: f64) {
+        for line in wrap(text, font, size, self.text_width() - indent) {
+            self.line(&line, font, size, indent);
+        }
+    }
+
+    fn items(&mut self, items: &[ContentItem], depth: usize, indent: f64) {
+        for item in items {
+            self.item(item, depth, indent);
+        }
+    }
+
+    fn item(&mut self, item: &ContentItem, depth: usize, indent: f64) {
+        match item {
+            ContentItem::Session(session) => {
+                let size = HEADING_SIZES[depth.min(HEADING_SIZES.len() - 1)];
+                self.gap(size * 0.6);
+                let title = plain(&InlineParser::new().parse(session.title.as_string().trim()));
+                self.paragraph(&title, Font::Bold, size, indent);
+                self.gap(size * 0.3);
+                self.items(&session.children, depth + 1, indent);
+            }
+            ContentItem::Paragraph(paragraph) => {
+                let lines: Vec<String> = paragraph
+                    .lines
+                    .iter()
+                    .filter_map(|line| match line {
+                        ContentItem::TextLine(line) => Some(text(&line.content)),
+                        _ => None,
+                    })
+                    .collect();
+                match Callout::from_item(item) {
+                    Some(callout) => {
+                        let title = format!("{}.", callout.display_title());
+                        self.paragraph(&title, Font::Bold, BODY_SIZE, indent + INDENT);
+                        self.paragraph(&lines.join(" "), Font::Regular, BODY_SIZE, indent + INDENT);
+                    }
+                    None => self.paragraph(&lines.join(" "), Font::Regular, BODY_SIZE, indent),
+                }
+                self.gap(BODY_SIZE * 0.6);
+            }
+            ContentItem::TextLine(line) => {
+                self.paragraph(&text(&line.content), Font::Regular, BODY_SIZE, indent)
+            }
+            ContentItem::List(list) => {
+                for list_item in list.items.iter().filter_map(ContentItem::as_list_item) {
+                    let marker = list_item.marker().trim();
+                    let marker = if marker.starts_with(|c: char| c.is_alphanumeric()) {
+                        marker
+                    } else {
+                        "\u{2022}"
+                    };
+                    let content: Vec<String> = list_item.text.iter().map(text).collect();
+                    let lines = wrap(
+                        content.join(" ").trim(),
+                        Font::Regular,
+                        BODY_SIZE,
+                        self.text_width() - indent - INDENT,
+                    );
+                    for (index, line) in lines.iter().enumerate() {
+                        self.line(line, Font::Regular, BODY_SIZE, indent + INDENT);
+                        if index == 0 {
+                            let y = self.y;
+                            self.draw(marker, Font::Regular, BODY_SIZE, MARGIN + indent, y);
+                        }
+                    }
+                    self.items(&list_item.children, depth, indent + INDENT);
+                }
+                self.gap(BODY_SIZE * 0.6);
+            }
+            ContentItem::Definition(definition) => {
+                let subject = text(&definition.subject);
+                self.paragraph(subject.trim(), Font::Bold, BODY_SIZE, indent);
+                self.items(&definition.children, depth, indent + INDENT);
+            }
+            ContentItem::VerbatimBlock(verbatim) => {
+                let block = code_block(verbatim);
+                let subject = block.subject.trim().trim_end_matches(':');
+                if !subject.is_empty() {
+                    self.paragraph(subject, Font::Bold, BODY_SIZE, indent);
+                }
+                let columns = ((self.text_width() - indent) / (CODE_SIZE * 0.6)) as usize;
+                for line in block.text.lines() {
+                    for part in break_line(line, columns.max(1)) {
+                        self.line(&part, Font::Mono, CODE_SIZE, indent);
+                    }
+                }
+                self.gap(BODY_SIZE * 0.6);
+            }
+            ContentItem::VerbatimLine(line) => {
+                self.line(line.content.as_string(), Font::Mono, CODE_SIZE, indent)
+            }
+            ContentItem::ListItem(_)
+            | ContentItem::Annotation(_)
+            | ContentItem::BlankLineGroup(_) => {}
+        }
+    }
+
+    /// Content streams of all pages, numbered at the bottom
+    fn finish(mut self) -> Vec<String> {
+        if !self.page.is_empty() || self.pages.is_empty() {
+            self.new_page();
+        }
+        let count = self.pages.len();
+        let mut pages = std::mem::take(&mut self.pages);
+        for (index, page) in pages.iter_mut().enumerate() {
+            if count > 1 {
+                let label = (index + 1).to_string();
+                let x = (self.width - Font::Regular.text_width(&label, 9.0)) / 2.0;
+                self.page = std::mem::take(page);
+                self.draw(&label, Font::Regular, 9.0, x, MARGIN / 2.0);
+                *page = std::mem::take(&mut self.page);
+            }
+        }
+        pages
+    }
+}
+
+/// `text` broken into lines of at most `width` points at `size`; words wider than a line
+/// are broken where they reach its end
+fn wrap(text: &str, font: Font, size: f64, width: f64) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if current.is_empty() {
+            word.to_string()
+        } else {
+            format!("{current} {word}")
+        };
+        if font.text_width(&candidate, size) <= width {
+            current = candidate;
+            continue;
+        }
+        if !current.is_empty() {
+            lines.push(std::mem::take(&mut current));
+        }
+        for c in word.chars() {
+            if !current.is_empty() && font.text_width(&current, size) + font.width(c, size) > width
+            {
+                lines.push(std::mem::take(&mut current));
+            }
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+/// `line` broken into parts of at most `columns` characters
+fn break_line(line: &str, columns: usize) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+    chars
+        .chunks(columns)
+        .map(|part| part.iter().collect())
+        .collect()
+}
+
+fn text(text: &TextContent) -> String {
+    plain(&text.inline_items())
+}
+
+/// Text of inline nodes, without their markup
+fn plain(nodes: &[InlineNode]) -> String {
+    let mut out = String::new();
+    for node in nodes {
+        match node {
+            InlineNode::Plain { text, .. }
+            | InlineNode::Code { text, .. }
+            | InlineNode::Math { text, .. } => out.push_str(text),
+            InlineNode::Strong { content, .. } | InlineNode::Emphasis { content, .. } => {
+                out.push_str(&plain(content))
+            }
+            InlineNode::Reference { data, .. } => match &data.reference_type {
+                ReferenceType::Url { target } | ReferenceType::General { target } => {
+                    out.push_str(target)
+                }
+                ReferenceType::WikiLink(link) => out.push_str(link.display_text()),
+                ReferenceType::Image(image) => {
+                    out.push_str(image.alt.as_deref().unwrap_or_default())
+                }
+                _ => out.push_str(&format!("[{}]", data.raw)),
+            },
+            InlineNode::Ruby { base, text, .. } => out.push_str(&format!("{base} ({text})")),
+            InlineNode::Role { data, .. } => out.push_str(&data.content),
+        }
+    }
+    out
+}
+
+/// A number as written in PDF content, with at most two decimals
+fn number(value: f64) -> String {
+    let text = format!("{value:.2}");
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// `text` as a PDF string in Windows-1252, bytes outside ASCII escaped
+fn pdf_string(text: &str) -> String {
+    let mut out = String::from("(");
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                out.push('\\');
+                out.push(c);
+            }
+            ' '..='~' => out.push(c),
+            '\t' => out.push(' '),
+            _ => match win_ansi(c) {
+                Some(byte) => out.push_str(&format!("\\{byte:03o}")),
+                None => out.push('?'),
+            },
+        }
+    }
+    out.push(')');
+    out
+}
+
+/// Byte of `c` in Windows-1252, for characters outside ASCII
+fn win_ansi(c: char) -> Option<u8> {
+    const SPECIALS: [(char, u8); 27] = [
+        ('€', 0x80),
+        ('‚', 0x82),
+        ('ƒ', 0x83),
+        ('„', 0x84),
+        ('…', 0x85),
+        ('†', 0x86),
+        ('‡', 0x87),
+        ('ˆ', 0x88),
+        ('‰', 0x89),
+        ('Š', 0x8A),
+        ('‹', 0x8B),
+        ('Œ', 0x8C),
+        ('Ž', 0x8E),
+        ('‘', 0x91),
+        ('’', 0x92),
+        ('“', 0x93),
+        ('”', 0x94),
+        ('•', 0x95),
+        ('–', 0x96),
+        ('—', 0x97),
+        ('˜', 0x98),
+        ('™', 0x99),
+        ('š', 0x9A),
+        ('›', 0x9B),
+        ('œ', 0x9C),
+        ('ž', 0x9E),
+        ('Ÿ', 0x9F),
+    ];
+    match c {
+        '\u{A0}'..='\u{FF}' => Some(c as u8),
+        _ => SPECIALS
+            .iter()
+            .find(|(special, _)| *special == c)
+            .map(|(_, byte)| *byte),
+    }
+}
+
+/// A PDF file of the content streams `pages`, with document information `info`
+fn write_pdf(pages: &[String], info: &[(&str, String)], width: f64, height: f64) -> Vec<u8> {
+    // Catalog, page tree, the three fonts and the information dictionary come first, then
+    // each page and its content stream
+    let kids: Vec<String> = (0..pages.len())
+        .map(|index| format!("{} 0 R", 7 + 2 * index))
+        .collect();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} /MediaBox [0 0 {} {}] >>",
+            kids.join(" "),
+            pages.len(),
+            number(width),
+            number(height)
+        ),
+    ];
+    for font in ["Helvetica", "Helvetica-Bold", "Courier"] {
+        objects.push(format!(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /{font} /Encoding /WinAnsiEncoding >>"
+        ));
+    }
+    let entries: Vec<String> = info
+        .iter()
+        .map(|(key, value)| format!("/{key} {}", pdf_string(value)))
+        .chain(["/Producer (lex)".to_string()])
+        .collect();
+    objects.push(format!("<< {} >>", entries.join(" ")));
+    for (index, content) in pages.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /Resources << /Font << /F1 3 0 R /F2 4 0 R /F3 5 0 R >> >> /Contents {} 0 R >>",
+            8 + 2 * index
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{content}endstream",
+            content.len()
+        ));
+    }
+
+    let mut out = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.push_str(&format!("{} 0 obj\n{object}\nendobj\n", index + 1));
+    }
+    let xref = out.len();
+    out.push_str(&format!(
+        "xref\n0 {}\n0000000000 65535 f \n",
+        objects.len() + 1
+    ));
+    for offset in offsets {
+        out.push_str(&format!("{offset:010} 00000 n \n"));
+    }
+    out.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R /Info 6 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+        objects.len() + 1
+    ));
+    out.into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    const SOURCE: &str = "Field Notes\n\n:: meta author=\"Ada Lovelace\" ::\n\nCosts rose (a lot) at [https://example.com], caf\u{e9} included.\n\n1. Method\n\n    - First\n    - Second\n\n    Sample:\n        print(\"hi\")\n    :: python\n";
+
+    fn render(source: &str, page_size: PdfPageSize) -> String {
+        let doc = parse_document(source).unwrap();
+        String::from_utf8(render_document(&doc, &PdfOptions { page_size })).unwrap()
+    }
+
+    #[test]
+    fn test_render_document() {
+        let pdf = render(SOURCE, PdfPageSize::A4);
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        assert!(pdf.is_ascii());
+        assert!(pdf.contains("/Count 1 /MediaBox [0 0 595.28 841.89]"));
+        assert!(pdf.contains("/Title (Field Notes) /Author (Ada Lovelace)"));
+        assert!(pdf.contains("BT /F2 20 Tf 72 742.89 Td (Field Notes) Tj ET"));
+        assert!(
+            pdf.contains("(Costs rose \\(a lot\\) at https://example.com, caf\\351 included.) Tj")
+        );
+        assert!(pdf.contains("(\\225) Tj"));
+        assert!(pdf.contains("/F3 9.5 Tf 72"));
+
+        // Every cross-reference entry points at its object
+        let xref = pdf.rfind("xref\n").unwrap();
+        for (index, entry) in pdf[xref..].lines().skip(3).take(9).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj", index + 1)));
+        }
+    }
+
+    #[test]
+    fn test_pages() {
+        let long = format!("Long\n\n{}", "A paragraph of text.\n\n".repeat(80));
+        let pdf = render(&long, PdfPageSize::Letter);
+        assert!(pdf.contains("/Count 3 /MediaBox [0 0 612 792]"));
+        assert!(pdf.contains("Td (3) Tj ET"));
+
+        let doc = parse_document(&long).unwrap();
+        let params = HashMap::from([("page-size".to_string(), "Legal".to_string())]);
+        let bytes = PdfFormatter::new().serialize_bytes(&doc, &params).unwrap();
+        assert!(String::from_utf8(bytes)
+            .unwrap()
+            .contains("/MediaBox [0 0 612 1008]"));
+        let params = HashMap::from([("page-size".to_string(), "b5".to_string())]);
+        assert!(PdfFormatter::new().serialize_bytes(&doc, &params).is_err());
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(
+            wrap("one two three", Font::Regular, 10.0, 45.0),
+            vec!["one two", "three"]
+        );
+        assert_eq!(
+            wrap("abcdefgh", Font::Mono, 10.0, 24.0),
+            vec!["abcd", "efgh"]
+        );
+        assert_eq!(break_line("abcdefg", 3), vec!["abc", "def", "g"]);
+        assert_eq!(number(841.89), "841.89");
+        assert_eq!(number(72.0), "72");
+    }
+}
@@ -0,0 +1,69 @@
+//! Reference graph formats
+//!
+//! Serialize a document's [ReferenceGraph] rather than its tree: `refs` renders it as JSON
+//! (nodes, edges, cycles and orphans), `refs-dot` as Graphviz DOT.
+
+use crate::lex::analysis::ReferenceGraph;
+use crate::lex::ast::Document;
+use crate::lex::formats::registry::{FormatError, Formatter};
+
+/// Reference graph as JSON
+pub struct RefsFormatter;
+
+impl Formatter for RefsFormatter {
+    fn name(&self) -> &str {
+        "refs"
+    }
+
+    fn extension(&self) -> &str {
+        "json"
+    }
+
+    fn serialize(&self, doc: &Document) -> Result<String, FormatError> {
+        serde_json::to_string_pretty(&ReferenceGraph::build(doc).to_json())
+            .map_err(|err| FormatError::SerializationError(err.to_string()))
+    }
+
+    fn description(&self) -> &str {
+        "Reference graph as JSON, with cycles and orphans"
+    }
+}
+
+/// Reference graph as Graphviz DOT
+pub struct RefsDotFormatter;
+
+impl Formatter for RefsDotFormatter {
+    fn name(&self) -> &str {
+        "refs-dot"
+    }
+
+    fn extension(&self) -> &str {
+        "dot"
+    }
+
+    fn serialize(&self, doc: &Document) -> Result<String, FormatError> {
+        Ok(ReferenceGraph::build(doc).to_dot())
+    }
+
+    fn description(&self) -> &str {
+        "Reference graph as Graphviz DOT"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    #[test]
+    fn test_refs_formats() {
+        let doc = parse_document("Title\n\nSee [Missing].\n").unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&RefsFormatter.serialize(&doc).unwrap()).unwrap();
+        assert_eq!(json["edges"].as_array().unwrap().len(), 1);
+
+        let dot = RefsDotFormatter.serialize(&doc).unwrap();
+        assert!(dot.contains("[label=\"Missing\", style=dashed]"));
+    }
+}
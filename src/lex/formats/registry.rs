@@ -2,10 +2,18 @@
 //!
 //! This module provides a pluggable registry system for document serialization formats.
 //! Each format implements the `Formatter` trait and can be registered with `FormatRegistry`.
+//! Formats that can also read documents (such as `lex`, `opml` and the subtitle formats)
+//! implement [Formatter::parse] and report it through [Formatter::supports_parsing].
+//! Downstream crates add their own formats the same way, and front ends list what is
+//! available from the registry rather than from a fixed set. Formats implemented by programs
+//! outside Lex are registered with [FormatRegistry::discover_external].
 
+use super::report::ConversionReport;
 use crate::lex::ast::Document;
+use crate::lex::extensions::ExtensionRegistry;
 use std::collections::HashMap;
 use std::fmt;
+use std::path::PathBuf;
 
 /// Error that can occur during formatting
 #[derive(Debug, Clone, PartialEq)]
@@ -14,6 +22,8 @@ pub enum FormatError {
     FormatNotFound(String),
     /// Error during serialization
     SerializationError(String),
+    /// Error reading a document, or parsing not supported by the format
+    ParseError(String),
 }
 
 impl fmt::Display for FormatError {
@@ -21,12 +31,54 @@ impl fmt::Display for FormatError {
         match self {
             FormatError::FormatNotFound(name) => write!(f, "Format '{name}' not found"),
             FormatError::SerializationError(msg) => write!(f, "Serialization error: {msg}"),
+            FormatError::ParseError(msg) => write!(f, "Parse error: {msg}"),
         }
     }
 }
 
 impl std::error::Error for FormatError {}
 
+/// Counts of lines typical of Markdown and of Lex
+fn structure_signals(content: &str) -> (usize, usize) {
+    let mut markdown = 0;
+    let mut lex = 0;
+    let mut previous: Option<&str> = None;
+    let mut after_blank = false;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            after_blank = previous.is_some();
+            continue;
+        }
+        let hashes = trimmed.chars().take_while(|c| *c == '#').count();
+        if (1..=6).contains(&hashes) && trimmed[hashes..].starts_with(' ') {
+            markdown += 1;
+        }
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            markdown += 1;
+        }
+        if trimmed.contains("](") {
+            markdown += 1;
+        }
+        if trimmed.starts_with(":: ") && trimmed[3..].contains("::") {
+            lex += 1;
+        }
+        // A heading line, a blank line, then content indented under it: a Lex session
+        let indented = line.starts_with("    ") || line.starts_with('\t');
+        if indented && after_blank && previous.is_some_and(|p| !p.starts_with([' ', '\t'])) {
+            lex += 1;
+        }
+        previous = Some(line);
+        after_blank = false;
+    }
+    (markdown, lex)
+}
+
+/// Value of a boolean parameter: set unless given as `false`
+pub(crate) fn flag_param(params: &HashMap<String, String>, key: &str) -> Option<bool> {
+    params.get(key).map(|value| value != "false")
+}
+
 /// Trait for document formatters
 ///
 /// Implementors provide a way to serialize a Document to a string representation.
@@ -34,9 +86,85 @@ pub trait Formatter: Send + Sync {
     /// The name of this format (e.g., "treeviz", "tag")
     fn name(&self) -> &str;
 
+    /// File extension of output in this format, without the dot; the name by default
+    fn extension(&self) -> &str {
+        self.name()
+    }
+
     /// Serialize a document to this format
     fn serialize(&self, doc: &Document) -> Result<String, FormatError>;
 
+    /// Serialize a document with format-specific parameters (such as `ast-full` for `tag`)
+    ///
+    /// Formats without parameters ignore them, which is the default.
+    fn serialize_with_params(
+        &self,
+        doc: &Document,
+        params: &HashMap<String, String>,
+    ) -> Result<String, FormatError> {
+        let _ = params;
+        self.serialize(doc)
+    }
+
+    /// Serialize a document with parameters, letting `extensions` render the verbatim blocks
+    /// they claim
+    ///
+    /// Formats that do not support [block extensions](crate::lex::extensions) ignore them,
+    /// which is the default.
+    fn serialize_with_extensions(
+        &self,
+        doc: &Document,
+        params: &HashMap<String, String>,
+        extensions: &ExtensionRegistry,
+    ) -> Result<String, FormatError> {
+        let _ = extensions;
+        self.serialize_with_params(doc, params)
+    }
+
+    /// Serialize a document with parameters to bytes, for writing to a file
+    ///
+    /// Text formats return their [serialize_with_params](Formatter::serialize_with_params)
+    /// output as UTF-8, which is the default; binary formats such as `pdf` override it.
+    fn serialize_bytes(
+        &self,
+        doc: &Document,
+        params: &HashMap<String, String>,
+    ) -> Result<Vec<u8>, FormatError> {
+        self.serialize_with_params(doc, params)
+            .map(String::into_bytes)
+    }
+
+    /// Serialize a document, with a report of what the conversion dropped or degraded
+    ///
+    /// Formats that do not track losses return an empty report, which is the default.
+    fn serialize_with_report(
+        &self,
+        doc: &Document,
+    ) -> Result<(String, ConversionReport), FormatError> {
+        Ok((self.serialize(doc)?, ConversionReport::new(self.name())))
+    }
+
+    /// Whether this format can write documents; true by default
+    fn supports_serialization(&self) -> bool {
+        true
+    }
+
+    /// Whether this format can read documents, through [Formatter::parse]
+    fn supports_parsing(&self) -> bool {
+        false
+    }
+
+    /// Read a document from source in this format
+    ///
+    /// Formats that implement it also override [Formatter::supports_parsing].
+    fn parse(&self, source: &str) -> Result<Document, FormatError> {
+        let _ = source;
+        Err(FormatError::ParseError(format!(
+            "Format '{}' does not support parsing",
+            self.name()
+        )))
+    }
+
     /// Optional description of this format
     fn description(&self) -> &str {
         ""
@@ -46,9 +174,11 @@ pub trait Formatter: Send + Sync {
 /// Registry of document formatters
 ///
 /// Provides a centralized registry for all available serialization formats.
-/// Formats can be registered and retrieved by name.
+/// Formats can be registered and retrieved by name. Documents are serialized with the
+/// registry's [block extensions](crate::lex::extensions), which are empty by default.
 pub struct FormatRegistry {
     formatters: HashMap<String, Box<dyn Formatter>>,
+    extensions: ExtensionRegistry,
 }
 
 impl FormatRegistry {
@@ -56,9 +186,20 @@ impl FormatRegistry {
     pub fn new() -> Self {
         FormatRegistry {
             formatters: HashMap::new(),
+            extensions: ExtensionRegistry::new(),
         }
     }
 
+    /// Block extensions documents are serialized with
+    pub fn extensions(&self) -> &ExtensionRegistry {
+        &self.extensions
+    }
+
+    /// Block extensions documents are serialized with, for registering extensions
+    pub fn extensions_mut(&mut self) -> &mut ExtensionRegistry {
+        &mut self.extensions
+    }
+
     /// Register a formatter
     ///
     /// If a formatter with the same name already exists, it will be replaced.
@@ -67,6 +208,14 @@ impl FormatRegistry {
             .insert(formatter.name().to_string(), Box::new(formatter));
     }
 
+    /// Register a boxed formatter, such as one chosen at runtime by a plugin
+    ///
+    /// If a formatter with the same name already exists, it will be replaced.
+    pub fn register_boxed(&mut self, formatter: Box<dyn Formatter>) {
+        self.formatters
+            .insert(formatter.name().to_string(), formatter);
+    }
+
     /// Get a formatter by name
     pub fn get(&self, name: &str) -> Option<&dyn Formatter> {
         self.formatters.get(name).map(|f| f.as_ref())
@@ -82,7 +231,57 @@ impl FormatRegistry {
         let formatter = self
             .get(format)
             .ok_or_else(|| FormatError::FormatNotFound(format.to_string()))?;
-        formatter.serialize(doc)
+        if self.extensions.is_empty() {
+            return formatter.serialize(doc);
+        }
+        formatter.serialize_with_extensions(doc, &HashMap::new(), &self.extensions)
+    }
+
+    /// Serialize a document using the specified format, with format-specific parameters
+    pub fn serialize_with_params(
+        &self,
+        doc: &Document,
+        format: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<String, FormatError> {
+        let formatter = self
+            .get(format)
+            .ok_or_else(|| FormatError::FormatNotFound(format.to_string()))?;
+        formatter.serialize_with_extensions(doc, params, &self.extensions)
+    }
+
+    /// Serialize a document to bytes using the specified format, with format-specific
+    /// parameters
+    pub fn serialize_bytes(
+        &self,
+        doc: &Document,
+        format: &str,
+        params: &HashMap<String, String>,
+    ) -> Result<Vec<u8>, FormatError> {
+        let formatter = self
+            .get(format)
+            .ok_or_else(|| FormatError::FormatNotFound(format.to_string()))?;
+        formatter.serialize_bytes(doc, params)
+    }
+
+    /// Serialize a document using the specified format, with a report of its losses
+    pub fn serialize_with_report(
+        &self,
+        doc: &Document,
+        format: &str,
+    ) -> Result<(String, ConversionReport), FormatError> {
+        let formatter = self
+            .get(format)
+            .ok_or_else(|| FormatError::FormatNotFound(format.to_string()))?;
+        formatter.serialize_with_report(doc)
+    }
+
+    /// Parse source using the specified format
+    pub fn parse(&self, source: &str, format: &str) -> Result<Document, FormatError> {
+        let formatter = self
+            .get(format)
+            .ok_or_else(|| FormatError::FormatNotFound(format.to_string()))?;
+        formatter.parse(source)
     }
 
     /// List all available format names (sorted)
@@ -92,6 +291,137 @@ impl FormatRegistry {
         names
     }
 
+    /// Names of the formats documents can be read from (sorted)
+    pub fn formats_supporting_parsing(&self) -> Vec<String> {
+        self.formats_where(|formatter| formatter.supports_parsing())
+    }
+
+    /// Names of the formats documents can be written to (sorted)
+    pub fn formats_supporting_serialization(&self) -> Vec<String> {
+        self.formats_where(|formatter| formatter.supports_serialization())
+    }
+
+    fn formats_where(&self, keep: impl Fn(&dyn Formatter) -> bool) -> Vec<String> {
+        let mut names: Vec<_> = self
+            .formatters
+            .iter()
+            .filter(|(_, formatter)| keep(formatter.as_ref()))
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Guess the format of a file from its extension
+    pub fn detect_format_from_filename(filename: &str) -> Option<&'static str> {
+        let extension = std::path::Path::new(filename)
+            .extension()?
+            .to_str()?
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            "lex" => Some("lex"),
+            "md" | "markdown" => Some("markdown"),
+            "html" | "htm" => Some("html"),
+            "opml" => Some("opml"),
+            "srt" => Some("srt"),
+            "vtt" => Some("vtt"),
+            "tex" => Some("latex"),
+            "typ" => Some("typst"),
+            "xml" => Some("xml"),
+            _ => None,
+        }
+    }
+
+    /// Guess the format of an input from its file name, falling back to its content when
+    /// the name is missing (stdin) or has no known extension
+    pub fn detect_format(filename: Option<&str>, content: &str) -> Option<&'static str> {
+        filename
+            .and_then(Self::detect_format_from_filename)
+            .or_else(|| Self::detect_format_from_content(content))
+    }
+
+    /// Guess the format of `content` from its structure
+    ///
+    /// A fallback for when a file has no extension, or a wrong one, such as input read
+    /// from stdin. The first line identifies Markdown with YAML frontmatter, HTML
+    /// (`<!DOCTYPE` or `<html`), Pandoc's JSON AST, the Lex JSON AST, OPML, Lex XML, WebVTT
+    /// and SRT. Otherwise the whole text is weighed: `#` headings, code fences and
+    /// `[text](url)` links count for Markdown; `:: label ::` annotations and indented blocks
+    /// under a heading line count for Lex. The name returned may be one the registry has no
+    /// formatter for; `None` means no signal was found.
+    pub fn detect_format_from_content(content: &str) -> Option<&'static str> {
+        let content = content.trim_start_matches('\u{feff}');
+        let mut lines = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty());
+        let first = lines.next()?;
+        let lower = first.to_ascii_lowercase();
+
+        if content.starts_with("---\n") || content.starts_with("---\r\n") {
+            return Some("markdown");
+        }
+        if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
+            return Some("html");
+        }
+        if lower.starts_with("<?xml") || lower.starts_with("<opml") {
+            if content.contains("<opml") {
+                return Some("opml");
+            }
+            return content.contains(super::xml::NAMESPACE).then_some("xml");
+        }
+        if first.starts_with('{') {
+            let value = serde_json::from_str::<serde_json::Value>(content).ok()?;
+            if value.get("format").and_then(|format| format.as_str()) == Some(super::json::FORMAT) {
+                return Some("json");
+            }
+            let is_pandoc =
+                value.get("pandoc-api-version").is_some() && value.get("blocks").is_some();
+            return is_pandoc.then_some("pandoc");
+        }
+        if first == "WEBVTT" || first.starts_with("WEBVTT ") {
+            return Some("vtt");
+        }
+        if first.chars().all(|c| c.is_ascii_digit())
+            && lines.next().is_some_and(|line| line.contains(" --> "))
+        {
+            return Some("srt");
+        }
+        let (markdown, lex) = structure_signals(content);
+        if markdown > lex {
+            Some("markdown")
+        } else if lex > 0 {
+            Some("lex")
+        } else {
+            None
+        }
+    }
+
+    /// Register the [external format adapters](super::external) found on the `PATH`
+    ///
+    /// Returns the names of the formats added, sorted.
+    pub fn discover_external(&mut self) -> Vec<String> {
+        self.discover_external_in(super::external::path_dirs())
+    }
+
+    /// Register the external format adapters found in `dirs`, searched in order
+    ///
+    /// Formats already registered, built-in ones included, are kept; adapters that do not
+    /// answer `describe` are skipped. Returns the names of the formats added, sorted.
+    pub fn discover_external_in(&mut self, dirs: impl IntoIterator<Item = PathBuf>) -> Vec<String> {
+        let mut added = Vec::new();
+        for (name, program) in super::external::find_adapters(dirs) {
+            if self.has(&name) {
+                continue;
+            }
+            if let Ok(formatter) = super::ExternalFormatter::probe(name.clone(), program) {
+                self.register(formatter);
+                added.push(name);
+            }
+        }
+        added
+    }
+
     /// Create a registry with default formatters
     pub fn with_defaults() -> Self {
         let mut registry = Self::new();
@@ -99,6 +429,22 @@ impl FormatRegistry {
         // Register built-in formatters
         registry.register(super::TreevizFormatter);
         registry.register(super::TagFormatter);
+        registry.register(super::RefsFormatter);
+        registry.register(super::RefsDotFormatter);
+        registry.register(super::CsvFormatter);
+        registry.register(super::TsvFormatter);
+        registry.register(super::AnsiFormatter);
+        registry.register(super::OpmlFormatter);
+        registry.register(super::OutlineJsonFormatter);
+        registry.register(super::LexFormatter::new());
+        registry.register(super::LatexFormatter::new());
+        registry.register(super::MarkdownFormatter::new());
+        registry.register(super::TypstFormatter);
+        registry.register(super::PdfFormatter::new());
+        registry.register(super::SrtFormatter);
+        registry.register(super::VttFormatter);
+        registry.register(super::XmlFormatter);
+        registry.register(super::JsonFormatter);
 
         registry
     }
@@ -144,6 +490,51 @@ mod tests {
         assert_eq!(registry.list_formats(), vec!["test"]);
     }
 
+    #[test]
+    fn test_registry_capabilities() {
+        struct ReadOnlyFormat;
+        impl Formatter for ReadOnlyFormat {
+            fn name(&self) -> &str {
+                "plain"
+            }
+            fn serialize(&self, _doc: &Document) -> Result<String, FormatError> {
+                unreachable!()
+            }
+            fn supports_serialization(&self) -> bool {
+                false
+            }
+            fn supports_parsing(&self) -> bool {
+                true
+            }
+            fn parse(&self, source: &str) -> Result<Document, FormatError> {
+                crate::lex::parsing::parse_document(source).map_err(FormatError::ParseError)
+            }
+        }
+
+        let mut registry = FormatRegistry::with_defaults();
+        registry.register_boxed(Box::new(ReadOnlyFormat));
+        assert!(registry
+            .formats_supporting_parsing()
+            .contains(&"plain".to_string()));
+        assert!(!registry
+            .formats_supporting_serialization()
+            .contains(&"plain".to_string()));
+        let mut parsing = vec!["json", "lex", "opml", "plain", "srt", "vtt", "xml"];
+        if cfg!(feature = "obsidian") {
+            parsing.insert(2, "markdown");
+        }
+        assert_eq!(registry.formats_supporting_parsing(), parsing);
+        let doc = registry.parse("Notes\n\nText.\n", "plain").unwrap();
+        assert_eq!(doc.title(), "Notes");
+
+        assert_eq!(
+            registry.parse("Text.\n", "tag"),
+            Err(FormatError::ParseError(
+                "Format 'tag' does not support parsing".to_string()
+            ))
+        );
+    }
+
     #[test]
     fn test_registry_get() {
         let mut registry = FormatRegistry::new();
@@ -183,6 +574,31 @@ mod tests {
         assert_eq!(result.unwrap(), "test output");
     }
 
+    #[test]
+    fn test_registry_serialize_with_params() {
+        let registry = FormatRegistry::with_defaults();
+        let doc = crate::lex::parsing::parse_document(":: meta ::\n\nText.\n").unwrap();
+        let params = HashMap::from([("ast-full".to_string(), "true".to_string())]);
+        let full = registry
+            .serialize_with_params(&doc, "tag", &params)
+            .unwrap();
+        assert!(full.contains("<annotation>"));
+        assert!(!registry
+            .serialize(&doc, "tag")
+            .unwrap()
+            .contains("<annotation>"));
+
+        // Formats without parameters ignore them
+        let mut test_registry = FormatRegistry::new();
+        test_registry.register(TestFormatter);
+        assert_eq!(
+            test_registry
+                .serialize_with_params(&doc, "test", &params)
+                .unwrap(),
+            "test output"
+        );
+    }
+
     #[test]
     fn test_registry_serialize_not_found() {
         let registry = FormatRegistry::new();
@@ -211,6 +627,12 @@ mod tests {
         let registry = FormatRegistry::with_defaults();
         assert!(registry.has("treeviz"));
         assert!(registry.has("tag"));
+        assert!(registry.has("refs"));
+        assert!(registry.has("refs-dot"));
+        assert!(registry.has("csv"));
+        assert!(registry.has("tsv"));
+        assert!(registry.has("ansi"));
+        assert!(registry.has("outline-json"));
     }
 
     #[test]
@@ -229,6 +651,68 @@ mod tests {
         assert_eq!(format!("{err2}"), "Serialization error: error");
     }
 
+    #[test]
+    fn test_detect_format_from_content() {
+        let detect = FormatRegistry::detect_format_from_content;
+        assert_eq!(detect("---\ntitle: Notes\n---\n\nText\n"), Some("markdown"));
+        assert_eq!(detect("# Notes\n\nText\n"), Some("markdown"));
+        assert_eq!(detect("<!DOCTYPE html>\n<html></html>\n"), Some("html"));
+        assert_eq!(
+            detect(r#"{"pandoc-api-version":[1,23],"meta":{},"blocks":[]}"#),
+            Some("pandoc")
+        );
+        assert_eq!(detect(r#"{"blocks":[]}"#), None);
+        assert_eq!(detect(r#"{"format":"lex-ast","version":1}"#), Some("json"));
+        assert_eq!(
+            detect("<?xml version=\"1.0\"?>\n<opml version=\"2.0\"></opml>\n"),
+            Some("opml")
+        );
+        assert_eq!(
+            detect("<?xml version=\"1.0\"?>\n<document xmlns=\"https://lex.ing/xml/1\">\n"),
+            Some("xml")
+        );
+        assert_eq!(detect("<?xml version=\"1.0\"?>\n<svg/>\n"), None);
+        assert_eq!(
+            detect("WEBVTT\n\n00:01.000 --> 00:02.000\nHi\n"),
+            Some("vtt")
+        );
+        assert_eq!(
+            detect("1\n00:00:01,000 --> 00:00:02,000\nHi\n"),
+            Some("srt")
+        );
+        assert_eq!(detect("Notes\n\n1. Intro\n\n    Text.\n"), Some("lex"));
+        assert_eq!(detect("Notes\n\n:: author Ana ::\n\nText.\n"), Some("lex"));
+        assert_eq!(
+            detect("Notes\n\nSee [the guide](guide.md).\n\n```\ncode\n```\n"),
+            Some("markdown")
+        );
+        // Markdown's indented code blocks alone do not outweigh its headings
+        assert_eq!(
+            detect("## Usage\n\nRun:\n\n    make\n\n## Options\n"),
+            Some("markdown")
+        );
+        assert_eq!(detect("Just a line of text.\n"), None);
+        assert_eq!(detect(""), None);
+    }
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(
+            FormatRegistry::detect_format_from_filename("notes.MD"),
+            Some("markdown")
+        );
+        assert_eq!(FormatRegistry::detect_format_from_filename("README"), None);
+        assert_eq!(
+            FormatRegistry::detect_format(Some("notes.lex"), "# Looks like Markdown\n"),
+            Some("lex")
+        );
+        assert_eq!(
+            FormatRegistry::detect_format(Some("README"), "# Notes\n"),
+            Some("markdown")
+        );
+        assert_eq!(FormatRegistry::detect_format(None, "WEBVTT\n"), Some("vtt"));
+    }
+
     #[test]
     fn test_registry_replace_formatter() {
         let mut registry = FormatRegistry::new();
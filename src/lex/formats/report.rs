@@ -0,0 +1,99 @@
+//! Conversion reports
+//!
+//! Converting Lex to another format is lossy: sessions nest deeper than LaTeX sectioning
+//! goes, outlines keep only session titles, subtitles only timed items. A
+//! [ConversionReport] lists what a conversion dropped or degraded, so users know what to
+//! check in the output (`lex convert --report`).
+//!
+//! Reports are opt-in: [Formatter::serialize_with_report](super::Formatter::serialize_with_report)
+//! returns one alongside the output. Formats that do not track losses return an empty report.
+
+use crate::lex::ast::Range;
+use std::fmt;
+
+/// A construct dropped or degraded by a conversion
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConversionLoss {
+    /// What happened, as a sentence
+    pub message: String,
+    /// Where the construct is in the source, when known
+    pub range: Option<Range>,
+}
+
+impl fmt::Display for ConversionLoss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.range {
+            Some(range) => write!(f, "{}: {}", range.start, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// What a conversion lost, in document order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConversionReport {
+    /// Format converted to
+    pub format: String,
+    pub losses: Vec<ConversionLoss>,
+}
+
+impl ConversionReport {
+    pub fn new(format: &str) -> Self {
+        Self {
+            format: format.to_string(),
+            losses: Vec::new(),
+        }
+    }
+
+    /// Record a loss at `range`
+    pub fn lost(&mut self, message: impl Into<String>, range: Option<Range>) {
+        self.losses.push(ConversionLoss {
+            message: message.into(),
+            range,
+        });
+    }
+
+    /// Whether nothing was reported lost
+    pub fn is_lossless(&self) -> bool {
+        self.losses.is_empty()
+    }
+}
+
+impl fmt::Display for ConversionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_lossless() {
+            return writeln!(f, "No losses converting to {}", self.format);
+        }
+        writeln!(
+            f,
+            "Converting to {} lost {} construct(s):",
+            self.format,
+            self.losses.len()
+        )?;
+        for loss in &self.losses {
+            writeln!(f, "  {loss}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::ast::Position;
+
+    #[test]
+    fn test_report_display() {
+        let mut report = ConversionReport::new("latex");
+        assert!(report.is_lossless());
+        assert_eq!(report.to_string(), "No losses converting to latex\n");
+
+        let range = Range::new(0..5, Position::new(2, 4), Position::new(2, 9));
+        report.lost("Annotation 'note' was left out.", Some(range));
+        report.lost("Inline styles were dropped.", None);
+        assert_eq!(
+            report.to_string(),
+            "Converting to latex lost 2 construct(s):\n  2:4: Annotation 'note' was left out.\n  Inline styles were dropped.\n"
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! Partial conversion
+//!
+//! [select_session] cuts a document down to one session subtree, so a chapter of a large
+//! document can be converted on its own. The session is picked by its
+//! [SessionSelector]: a number (`1.2`), matching the session's marker or, for sessions
+//! without one, its position among its siblings at each level, or an explicit id
+//! (`:: id intro ::`).
+//!
+//! The selected document keeps the original's title and document-level annotations, so
+//! metadata and front matter are the same as when converting the whole document. Locations
+//! are those of the original source.
+
+use crate::lex::ast::{ContentItem, Document, Session};
+
+/// Which session to keep
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionSelector {
+    /// Marker (`1.2`, with or without the trailing period), else 1-based position path
+    Number(String),
+    /// Explicit `:: id ::`
+    Id(String),
+}
+
+/// `document` reduced to the selected session, or `None` if no session matches
+pub fn select_session(document: &Document, selector: &SessionSelector) -> Option<Document> {
+    let session = match selector {
+        SessionSelector::Id(id) => document.find_session_by_id(id.trim())?,
+        SessionSelector::Number(number) => {
+            let number = number.trim().trim_end_matches('.');
+            document
+                .root
+                .iter_sessions_recursive()
+                .find(|session| {
+                    session
+                        .marker
+                        .as_ref()
+                        .is_some_and(|marker| marker.as_str().trim_end_matches('.') == number)
+                })
+                .or_else(|| session_at_path(&document.root, number))?
+        }
+    };
+    let mut selected = Document::with_content(vec![ContentItem::Session(session.clone())]);
+    selected.annotations = document.annotations.clone();
+    selected.root.title = document.root.title.clone();
+    selected.root.annotations = document.root.annotations.clone();
+    Some(selected)
+}
+
+/// Session at a path of 1-based positions among sibling sessions, such as `2.1`
+fn session_at_path<'a>(root: &'a Session, path: &str) -> Option<&'a Session> {
+    let mut session = root;
+    for index in path.split('.') {
+        let index: usize = index.parse().ok()?;
+        session = session.iter_sessions().nth(index.checked_sub(1)?)?;
+    }
+    Some(session)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::formats::{Formatter, LexFormatter};
+    use crate::lex::parsing::parse_document;
+
+    #[test]
+    fn test_select_session() {
+        let source = "Guide\n\n1. Intro\n\n    Text.\n\n2. Usage\n\n    :: id setup ::\n    2.1. Setup\n\n        Steps.\n\n    Options\n\n        More.\n";
+        let doc = parse_document(source).unwrap();
+        let written = |selector: SessionSelector| {
+            let selected = select_session(&doc, &selector).unwrap();
+            assert_eq!(selected.title(), "Guide");
+            LexFormatter::new().serialize(&selected).unwrap()
+        };
+
+        let setup = written(SessionSelector::Number("2.1.".to_string()));
+        assert!(setup.starts_with("Guide\n\n"));
+        assert!(setup.contains("2.1. Setup\n\n    Steps.\n"));
+        assert!(!setup.contains("Intro"));
+        assert_eq!(written(SessionSelector::Id("setup".to_string())), setup);
+        // Unnumbered sessions by position
+        assert!(
+            written(SessionSelector::Number("2.2".to_string())).contains("Options\n\n    More.")
+        );
+
+        assert!(select_session(&doc, &SessionSelector::Number("3".to_string())).is_none());
+        assert!(select_session(&doc, &SessionSelector::Id("missing".to_string())).is_none());
+    }
+}
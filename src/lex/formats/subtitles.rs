@@ -0,0 +1,441 @@
+//! Subtitles and transcripts (SRT, WebVTT)
+//!
+//! A transcript in Lex is a list with one item per cue, each annotated with the cue's
+//! timing:
+//!
+//!     - Hello and welcome.
+//!         :: cue start="00:00:01.000" end="00:00:04.000" ::
+//!     - Today we look at limits.
+//!         :: cue start="00:00:04.500" end="00:00:07.250" ::
+//!
+//! so lectures and interviews can be edited, annotated and commented like any document.
+//! [import_srt] and [import_vtt] build such a document from subtitle files; a cue's lines
+//! are joined into one item, and WebVTT cue ids are kept as an `id` parameter. What the
+//! import drops (cue numbers, cue settings, notes) is listed in the document's
+//! [provenance](crate::lex::annotation::provenance). The `srt` and `vtt` formats go the other
+//! way, exporting every annotated item of a document, in order.
+
+use crate::lex::annotation::provenance::Provenance;
+use crate::lex::ast::{ContentItem, Document, ListItem};
+use crate::lex::formats::registry::{FormatError, Formatter};
+use crate::lex::formats::report::ConversionReport;
+use crate::lex::parsing::parse_document;
+
+/// Annotation label of cue timings
+pub const CUE_LABEL: &str = "cue";
+
+/// A timed piece of text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    pub id: Option<String>,
+    /// Start, in milliseconds
+    pub start: u64,
+    /// End, in milliseconds
+    pub end: u64,
+    pub text: String,
+}
+
+fn invalid(message: String) -> FormatError {
+    FormatError::ParseError(message)
+}
+
+/// Milliseconds of a `HH:MM:SS.mmm` timestamp; hours are optional and the milliseconds may
+/// follow a comma, as in SRT
+pub fn parse_timestamp(text: &str) -> Result<u64, FormatError> {
+    let error = || invalid(format!("invalid timestamp '{text}'"));
+    let (clock, millis) = text.trim().split_once(['.', ',']).ok_or_else(error)?;
+    let millis: u64 = millis.parse().map_err(|_| error())?;
+    let mut seconds = 0;
+    let parts: Vec<&str> = clock.split(':').collect();
+    if !(2..=3).contains(&parts.len()) || millis > 999 {
+        return Err(error());
+    }
+    for part in parts {
+        seconds = seconds * 60 + part.parse::<u64>().map_err(|_| error())?;
+    }
+    Ok(seconds * 1000 + millis)
+}
+
+/// `HH:MM:SS` and milliseconds, separated by `separator`
+pub fn format_timestamp(millis: u64, separator: char) -> String {
+    let seconds = millis / 1000;
+    format!(
+        "{:02}:{:02}:{:02}{separator}{:03}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        millis % 1000
+    )
+}
+
+/// Start and end of a `start --> end` timing line, ignoring WebVTT cue settings
+fn parse_timing(line: &str) -> Result<(u64, u64), FormatError> {
+    let (start, rest) = line
+        .split_once("-->")
+        .ok_or_else(|| invalid(format!("invalid cue timing '{line}'")))?;
+    let end = rest.split_whitespace().next().unwrap_or_default();
+    Ok((parse_timestamp(start)?, parse_timestamp(end)?))
+}
+
+/// Blocks of non-blank lines
+fn blocks(source: &str) -> Vec<Vec<&str>> {
+    let mut blocks = vec![Vec::new()];
+    for line in source.trim_start_matches('\u{feff}').lines() {
+        if line.trim().is_empty() {
+            if !blocks.last().unwrap().is_empty() {
+                blocks.push(Vec::new());
+            }
+        } else {
+            blocks.last_mut().unwrap().push(line);
+        }
+    }
+    blocks.retain(|block| !block.is_empty());
+    blocks
+}
+
+fn cue(
+    id: Option<&str>,
+    timing: &str,
+    text: &[&str],
+    provenance: &mut Provenance,
+) -> Result<Cue, FormatError> {
+    let (start, end) = parse_timing(timing)?;
+    let settings = timing.split("-->").nth(1).unwrap_or_default();
+    if settings.split_whitespace().count() > 1 {
+        provenance.lost("Cue settings (position, alignment) were dropped.");
+    }
+    if text.len() > 1 {
+        provenance.lost("Lines of multi-line cues were joined.");
+    }
+    let text: Vec<&str> = text.iter().map(|line| line.trim()).collect();
+    Ok(Cue {
+        id: id.map(|id| id.trim().to_string()),
+        start,
+        end,
+        text: text.join(" "),
+    })
+}
+
+/// Cues of an SRT file
+pub fn parse_srt(source: &str) -> Result<Vec<Cue>, FormatError> {
+    srt_cues(source, &mut Provenance::new("srt"))
+}
+
+/// Cues of a WebVTT file
+pub fn parse_vtt(source: &str) -> Result<Vec<Cue>, FormatError> {
+    vtt_cues(source, &mut Provenance::new("vtt"))
+}
+
+/// Cues of an SRT file, recording what the conversion drops in `provenance`
+fn srt_cues(source: &str, provenance: &mut Provenance) -> Result<Vec<Cue>, FormatError> {
+    blocks(source)
+        .into_iter()
+        .map(|block| {
+            // The numeric index is optional in practice, and renumbered on export
+            let timing = block.iter().position(|line| line.contains("-->"));
+            let timing =
+                timing.ok_or_else(|| invalid(format!("cue without timing: '{}'", block[0])))?;
+            if timing > 0 {
+                provenance.lost("Cue numbers were dropped; cues are renumbered on export.");
+            }
+            cue(None, block[timing], &block[timing + 1..], provenance)
+        })
+        .collect()
+}
+
+/// Cues of a WebVTT file, recording what the conversion drops in `provenance`
+fn vtt_cues(source: &str, provenance: &mut Provenance) -> Result<Vec<Cue>, FormatError> {
+    let mut blocks = blocks(source).into_iter();
+    let header = blocks.next().unwrap_or_default();
+    if !header
+        .first()
+        .is_some_and(|line| line.starts_with("WEBVTT"))
+    {
+        return Err(invalid("missing WEBVTT header".to_string()));
+    }
+    let mut cues = Vec::new();
+    for block in blocks {
+        if let Some(keyword) = ["NOTE", "STYLE", "REGION"]
+            .iter()
+            .find(|keyword| block[0].starts_with(*keyword))
+        {
+            provenance.lost(format!("{keyword} blocks were dropped."));
+            continue;
+        }
+        match block.iter().position(|line| line.contains("-->")) {
+            Some(0) => cues.push(cue(None, block[0], &block[1..], provenance)?),
+            Some(1) => cues.push(cue(Some(block[0]), block[1], &block[2..], provenance)?),
+            _ => return Err(invalid(format!("cue without timing: '{}'", block[0]))),
+        }
+    }
+    Ok(cues)
+}
+
+/// Lex source of a transcript titled `title` holding `cues`
+pub fn transcript(title: &str, cues: &[Cue]) -> String {
+    format!("{title}\n\n{}", transcript_items(cues))
+}
+
+/// List of a transcript's cues
+fn transcript_items(cues: &[Cue]) -> String {
+    let mut out = String::new();
+    for cue in cues {
+        out.push_str(&format!("- {}\n", cue.text));
+        let mut params = format!(
+            "start=\"{}\" end=\"{}\"",
+            format_timestamp(cue.start, '.'),
+            format_timestamp(cue.end, '.')
+        );
+        if let Some(id) = &cue.id {
+            params.push_str(&format!(" id=\"{id}\""));
+        }
+        out.push_str(&format!("    :: {CUE_LABEL} {params} ::\n"));
+    }
+    out
+}
+
+/// Convert an SRT file into a Lex transcript titled `title`, recording its
+/// [provenance](crate::lex::annotation::provenance)
+pub fn import_srt(title: &str, source: &str) -> Result<String, FormatError> {
+    let mut provenance = Provenance::new("srt").with_option("title", title);
+    let cues = srt_cues(source, &mut provenance)?;
+    Ok(provenance.document(title, &transcript_items(&cues)))
+}
+
+/// Convert a WebVTT file into a Lex transcript titled `title`, recording its
+/// [provenance](crate::lex::annotation::provenance)
+pub fn import_vtt(title: &str, source: &str) -> Result<String, FormatError> {
+    let mut provenance = Provenance::new("vtt").with_option("title", title);
+    let cues = vtt_cues(source, &mut provenance)?;
+    Ok(provenance.document(title, &transcript_items(&cues)))
+}
+
+/// Cues of the list items of `doc` annotated with their timing, in document order
+pub fn document_cues(doc: &Document) -> Result<Vec<Cue>, FormatError> {
+    let mut cues = Vec::new();
+    for item in doc.root.iter_all_nodes() {
+        if let ContentItem::ListItem(list_item) = item {
+            if let Some(cue) = item_cue(list_item)? {
+                cues.push(cue);
+            }
+        }
+    }
+    Ok(cues)
+}
+
+/// Report of the list items without a cue, which subtitles leave out
+fn untimed_items(doc: &Document, format: &str) -> ConversionReport {
+    let mut report = ConversionReport::new(format);
+    for item in doc.root.iter_list_items_recursive() {
+        if matches!(item_cue(item), Ok(None)) {
+            report.lost(
+                "List item without a cue was left out.",
+                Some(item.location.clone()),
+            );
+        }
+    }
+    report
+}
+
+fn item_cue(item: &ListItem) -> Result<Option<Cue>, FormatError> {
+    let Some(annotation) = item
+        .annotations()
+        .iter()
+        .find(|annotation| annotation.data.label.value.trim() == CUE_LABEL)
+    else {
+        return Ok(None);
+    };
+    let param = |key: &str| {
+        annotation
+            .data
+            .parameters
+            .iter()
+            .find(|param| param.key == key)
+            .map(|param| param.value.trim_matches('"').to_string())
+    };
+    let timestamp = |key: &str| {
+        let value = param(key).ok_or_else(|| {
+            FormatError::SerializationError(format!("cue without {key}: '{}'", item.text().trim()))
+        })?;
+        parse_timestamp(&value)
+            .map_err(|_| FormatError::SerializationError(format!("invalid cue {key} '{value}'")))
+    };
+    let text: Vec<&str> = item
+        .text
+        .iter()
+        .map(|text| text.as_string().trim())
+        .collect();
+    Ok(Some(Cue {
+        id: param("id"),
+        start: timestamp("start")?,
+        end: timestamp("end")?,
+        text: text.join(" "),
+    }))
+}
+
+/// Cues as an SRT file, numbered from 1
+pub fn write_srt(cues: &[Cue]) -> String {
+    let blocks: Vec<String> = cues
+        .iter()
+        .enumerate()
+        .map(|(index, cue)| {
+            format!(
+                "{}\n{} --> {}\n{}\n",
+                index + 1,
+                format_timestamp(cue.start, ','),
+                format_timestamp(cue.end, ','),
+                cue.text
+            )
+        })
+        .collect();
+    blocks.join("\n")
+}
+
+/// Cues as a WebVTT file
+pub fn write_vtt(cues: &[Cue]) -> String {
+    let mut out = String::from("WEBVTT\n");
+    for cue in cues {
+        out.push('\n');
+        if let Some(id) = &cue.id {
+            out.push_str(&format!("{id}\n"));
+        }
+        out.push_str(&format!(
+            "{} --> {}\n{}\n",
+            format_timestamp(cue.start, '.'),
+            format_timestamp(cue.end, '.'),
+            cue.text
+        ));
+    }
+    out
+}
+
+/// Transcript cues as SRT subtitles
+pub struct SrtFormatter;
+
+impl Formatter for SrtFormatter {
+    fn name(&self) -> &str {
+        "srt"
+    }
+
+    fn serialize(&self, doc: &Document) -> Result<String, FormatError> {
+        Ok(write_srt(&document_cues(doc)?))
+    }
+
+    fn serialize_with_report(
+        &self,
+        doc: &Document,
+    ) -> Result<(String, ConversionReport), FormatError> {
+        Ok((write_srt(&document_cues(doc)?), untimed_items(doc, "srt")))
+    }
+
+    fn supports_parsing(&self) -> bool {
+        true
+    }
+
+    /// The untitled transcript [import_srt] builds
+    fn parse(&self, source: &str) -> Result<Document, FormatError> {
+        parse_document(&import_srt("", source)?).map_err(FormatError::ParseError)
+    }
+
+    fn description(&self) -> &str {
+        "Transcript cues as SRT subtitles"
+    }
+}
+
+/// Transcript cues as WebVTT subtitles
+pub struct VttFormatter;
+
+impl Formatter for VttFormatter {
+    fn name(&self) -> &str {
+        "vtt"
+    }
+
+    fn serialize(&self, doc: &Document) -> Result<String, FormatError> {
+        Ok(write_vtt(&document_cues(doc)?))
+    }
+
+    fn serialize_with_report(
+        &self,
+        doc: &Document,
+    ) -> Result<(String, ConversionReport), FormatError> {
+        Ok((write_vtt(&document_cues(doc)?), untimed_items(doc, "vtt")))
+    }
+
+    fn supports_parsing(&self) -> bool {
+        true
+    }
+
+    /// The untitled transcript [import_vtt] builds
+    fn parse(&self, source: &str) -> Result<Document, FormatError> {
+        parse_document(&import_vtt("", source)?).map_err(FormatError::ParseError)
+    }
+
+    fn description(&self) -> &str {
+        "Transcript cues as WebVTT subtitles"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    const SRT: &str = "1\r\n00:00:01,000 --> 00:00:04,000\r\nHello and welcome.\r\n\r\n2\r\n00:00:04,500 --> 00:00:07,250\r\nToday we look\r\nat limits.\r\n";
+
+    #[test]
+    fn test_timestamps() {
+        assert_eq!(parse_timestamp("01:02:03,004").unwrap(), 3_723_004);
+        assert_eq!(parse_timestamp("02:03.500").unwrap(), 123_500);
+        assert!(parse_timestamp("1:2").is_err());
+        assert_eq!(format_timestamp(3_723_004, '.'), "01:02:03.004");
+    }
+
+    #[test]
+    fn test_srt_round_trip() {
+        let lex = import_srt("Lecture", SRT).unwrap();
+        assert_eq!(
+            lex,
+            "Lecture\n\n:: provenance source=srt, title=\"Lecture\" ::\n    Cue numbers were dropped; cues are renumbered on export.\n    Lines of multi-line cues were joined.\n\n- Hello and welcome.\n    :: cue start=\"00:00:01.000\" end=\"00:00:04.000\" ::\n- Today we look at limits.\n    :: cue start=\"00:00:04.500\" end=\"00:00:07.250\" ::\n"
+        );
+        let doc = parse_document(&lex).unwrap();
+        assert_eq!(
+            SrtFormatter.serialize(&doc).unwrap(),
+            "1\n00:00:01,000 --> 00:00:04,000\nHello and welcome.\n\n2\n00:00:04,500 --> 00:00:07,250\nToday we look at limits.\n"
+        );
+        let (_, report) = SrtFormatter.serialize_with_report(&doc).unwrap();
+        assert!(report.is_lossless());
+
+        let untimed = parse_document(&format!("{lex}- An untimed remark.\n")).unwrap();
+        let (_, report) = VttFormatter.serialize_with_report(&untimed).unwrap();
+        assert_eq!(report.losses.len(), 1);
+        assert_eq!(
+            report.losses[0].message,
+            "List item without a cue was left out."
+        );
+    }
+
+    #[test]
+    fn test_vtt() {
+        let vtt = "WEBVTT - Interview\n\nNOTE recorded live\n\nintro\n00:01.000 --> 00:03.000 align:start\n<v Ada>Welcome.\n\n00:03.500 --> 00:05.000\nThanks.\n";
+        let cues = parse_vtt(vtt).unwrap();
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].id.as_deref(), Some("intro"));
+        assert_eq!((cues[0].start, cues[0].end), (1_000, 3_000));
+
+        let doc = parse_document(&import_vtt("Interview", vtt).unwrap()).unwrap();
+        let provenance = Provenance::of(&doc).unwrap();
+        assert_eq!(provenance.source, "vtt");
+        assert_eq!(
+            provenance.losses,
+            [
+                "NOTE blocks were dropped.",
+                "Cue settings (position, alignment) were dropped."
+            ]
+        );
+        assert_eq!(
+            VttFormatter.serialize(&doc).unwrap(),
+            "WEBVTT\n\nintro\n00:00:01.000 --> 00:00:03.000\n<v Ada>Welcome.\n\n00:00:03.500 --> 00:00:05.000\nThanks.\n"
+        );
+        assert!(parse_vtt("1\n00:01.000 --> 00:02.000\nNo header.\n").is_err());
+    }
+}
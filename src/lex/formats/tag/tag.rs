@@ -22,7 +22,9 @@
 //! </document>
 //! ```
 
-use crate::lex::ast::{AstSnapshot, Document};
+use crate::lex::ast::{snapshot_from_document_with_options, AstSnapshot, Document};
+use crate::lex::formats::registry::flag_param;
+use std::collections::HashMap;
 
 /// Tag serializer that converts AstSnapshot to XML-like format
 struct TagSerializer {
@@ -83,6 +85,12 @@ fn to_tag_name(node_type: &str) -> String {
 
 /// Serialize a document to AST tag format
 pub fn serialize_document(doc: &Document) -> String {
+    serialize_document_with_options(doc, false)
+}
+
+/// Serialize a document to AST tag format, with every node (annotations, document-level
+/// ones included) when `include_all` is set
+pub fn serialize_document_with_options(doc: &Document, include_all: bool) -> String {
     let mut result = String::new();
     result.push_str("<document>\n");
 
@@ -90,7 +98,7 @@ pub fn serialize_document(doc: &Document) -> String {
     serializer.indent_level = 1;
 
     // Serialize the root session
-    let snapshot = crate::lex::ast::snapshot_from_document(doc);
+    let snapshot = snapshot_from_document_with_options(doc, include_all);
     serializer.serialize_snapshot(&snapshot);
 
     result.push_str(&serializer.output);
@@ -115,6 +123,10 @@ impl crate::lex::formats::registry::Formatter for TagFormatter {
         "tag"
     }
 
+    fn extension(&self) -> &str {
+        "xml"
+    }
+
     fn serialize(
         &self,
         doc: &Document,
@@ -122,6 +134,16 @@ impl crate::lex::formats::registry::Formatter for TagFormatter {
         Ok(serialize_document(doc))
     }
 
+    /// `ast-full` includes every node, annotations included
+    fn serialize_with_params(
+        &self,
+        doc: &Document,
+        params: &HashMap<String, String>,
+    ) -> Result<String, crate::lex::formats::registry::FormatError> {
+        let include_all = flag_param(params, "ast-full").unwrap_or(false);
+        Ok(serialize_document_with_options(doc, include_all))
+    }
+
     fn description(&self) -> &str {
         "XML-like tag format with hierarchical structure"
     }
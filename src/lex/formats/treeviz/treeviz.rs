@@ -62,7 +62,8 @@
 //!         ReferenceFootnote: ³
 //!         ReferenceSession: #
 
-use crate::lex::ast::{snapshot_from_document, AstSnapshot, Document};
+use crate::lex::ast::{snapshot_from_document_with_options, AstSnapshot, Document};
+use crate::lex::formats::registry::flag_param;
 use std::collections::HashMap;
 
 fn truncate(s: &str, max_chars: usize) -> String {
@@ -154,13 +155,13 @@ pub fn to_treeviz_str(doc: &Document) -> String {
     to_treeviz_str_with_params(doc, &HashMap::new())
 }
 
+/// Treeviz of `doc`; `show-linum` prefixes nodes with their line, `ast-full` includes every
+/// node, annotations included
 pub fn to_treeviz_str_with_params(doc: &Document, params: &HashMap<String, String>) -> String {
-    let show_linum = params
-        .get("show-linum")
-        .map(|v| v != "false")
-        .unwrap_or(false);
+    let show_linum = flag_param(params, "show-linum").unwrap_or(false);
+    let include_all = flag_param(params, "ast-full").unwrap_or(false);
 
-    let snapshot = snapshot_from_document(doc);
+    let snapshot = snapshot_from_document_with_options(doc, include_all);
     format_document_snapshot(&snapshot, show_linum)
 }
 
@@ -172,6 +173,10 @@ impl crate::lex::formats::registry::Formatter for TreevizFormatter {
         "treeviz"
     }
 
+    fn extension(&self) -> &str {
+        "txt"
+    }
+
     fn serialize(
         &self,
         doc: &Document,
@@ -179,6 +184,14 @@ impl crate::lex::formats::registry::Formatter for TreevizFormatter {
         Ok(to_treeviz_str(doc))
     }
 
+    fn serialize_with_params(
+        &self,
+        doc: &Document,
+        params: &HashMap<String, String>,
+    ) -> Result<String, crate::lex::formats::registry::FormatError> {
+        Ok(to_treeviz_str_with_params(doc, params))
+    }
+
     fn description(&self) -> &str {
         "Visual tree representation with indentation and Unicode icons"
     }
@@ -0,0 +1,433 @@
+//! Typst
+//!
+//! The `typst` format writes a document as [Typst](https://typst.app) markup, which the
+//! `typst` compiler turns into PDF in a fraction of the time a LaTeX run takes:
+//!
+//! - The document title sets the document metadata and opens the page, with the `author`
+//!   from the `meta` annotation. Sessions become headings (`=`, `==`, ...); sessions with an
+//!   id get a label that `[#id]` references link to.
+//! - Lists become bullet (`-`) or numbered (`+`) lists, and definitions term lists.
+//! - Verbatim blocks become raw blocks, labeled with their language and set in a figure
+//!   captioned with their subject. Table blocks become `#table` calls, and
+//!   [display math](crate::lex::display_math) blocks block equations.
+//! - Math inlines, written in AsciiMath, become native Typst math (see [asciimath]).
+//! - Footnote references become `#footnote`s holding the footnote text, citations `#cite`
+//!   calls and inline images `#image` calls.
+//! - Callouts become block quotes, with the callout title in bold. Strikethrough, the
+//!   [`del` role](crate::lex::inlines::DEL_ROLE), becomes `#strike`.
+//!
+//! Other annotations are metadata and are left out.
+
+pub mod asciimath;
+
+use crate::lex::annotation::callout::Callout;
+use crate::lex::ast::{ContentItem, Document, TextContent};
+use crate::lex::display_math::{equations, Equation};
+use crate::lex::formats::registry::{FormatError, Formatter};
+use crate::lex::inlines::{InlineNode, InlineParser, ReferenceType, DEL_ROLE};
+use crate::lex::literate::code_block;
+use crate::lex::tables::{table_rows, TABLE_LABEL};
+use crate::lex::variables::Variables;
+use std::collections::HashMap;
+
+/// Document written as Typst markup
+pub struct TypstFormatter;
+
+impl Formatter for TypstFormatter {
+    fn name(&self) -> &str {
+        "typst"
+    }
+
+    fn extension(&self) -> &str {
+        "typ"
+    }
+
+    fn serialize(&self, doc: &Document) -> Result<String, FormatError> {
+        Ok(render_document(doc))
+    }
+
+    fn description(&self) -> &str {
+        "Typst markup, with native math, for fast PDF builds"
+    }
+}
+
+/// Write `doc` as Typst markup
+pub fn render_document(doc: &Document) -> String {
+    let mut renderer = Renderer {
+        doc,
+        parser: InlineParser::new(),
+        sessions: doc
+            .root
+            .iter_sessions_recursive()
+            .filter_map(|session| {
+                let id = session.id()?;
+                Some((id.to_string(), session.title_text().trim().to_string()))
+            })
+            .collect(),
+        equations: equations(doc)
+            .into_iter()
+            .filter_map(|equation| Some((equation.id?, equation.number)))
+            .collect(),
+    };
+    let mut out = String::new();
+    let title = doc.title().trim();
+    if !title.is_empty() {
+        let mut settings = vec![format!("title: {}", string(title))];
+        if let Some(author) = Variables::for_document(doc).meta.get("author") {
+            settings.push(format!("author: {}", string(author)));
+        }
+        out.push_str(&format!("#set document({})\n\n", settings.join(", ")));
+        out.push_str(&format!(
+            "#align(center, text(size: 1.6em, weight: \"bold\")[{}])\n\n",
+            renderer.inline_text(title)
+        ));
+    }
+    let body = renderer.items(&doc.root.children, 0);
+    out.push_str(&body.join("\n"));
+    format!("{}\n", out.trim_end())
+}
+
+struct Renderer<'a> {
+    doc: &'a Document,
+    parser: InlineParser,
+    /// Titles of the sessions with an id, by id
+    sessions: HashMap<String, String>,
+    /// Numbers of the equations with an id, by id
+    equations: HashMap<String, usize>,
+}
+
+impl Renderer<'_> {
+    /// Lines of `items`, blocks separated by blank lines
+    fn items(&mut self, items: &[ContentItem], depth: usize) -> Vec<String> {
+        let mut lines: Vec<String> = Vec::new();
+        for item in items {
+            let block = self.item(item, depth);
+            if block.is_empty() {
+                continue;
+            }
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.extend(block);
+        }
+        lines
+    }
+
+    fn item(&mut self, item: &ContentItem, depth: usize) -> Vec<String> {
+        match item {
+            ContentItem::Session(session) => {
+                let title = self.inline_text(session.title.as_string().trim());
+                let mut heading = format!("{} {}", "=".repeat(depth + 1), line_start(&title));
+                if let Some(id) = session.id() {
+                    heading.push_str(&format!(" <{id}>"));
+                }
+                let mut lines = vec![heading];
+                let body = self.items(&session.children, depth + 1);
+                if !body.is_empty() {
+                    lines.push(String::new());
+                    lines.extend(body);
+                }
+                lines
+            }
+            ContentItem::Paragraph(paragraph) => {
+                let lines: Vec<String> = paragraph
+                    .lines
+                    .iter()
+                    .filter_map(|line| match line {
+                        ContentItem::TextLine(line) => Some(line_start(&self.text(&line.content))),
+                        _ => None,
+                    })
+                    .collect();
+                match Callout::from_item(item) {
+                    Some(callout) => {
+                        let mut quoted = vec!["#quote(block: true)[".to_string()];
+                        quoted.push(format!("  *{}.*", escape(callout.display_title())));
+                        quoted.extend(lines.iter().map(|line| format!("  {line}")));
+                        quoted.push("]".to_string());
+                        quoted
+                    }
+                    None => lines,
+                }
+            }
+            ContentItem::TextLine(line) => vec![line_start(&self.text(&line.content))],
+            ContentItem::List(list) => {
+                let numbered = list.items.iter().find_map(|item| match item {
+                    ContentItem::ListItem(item) => {
+                        Some(item.marker().starts_with(|c: char| c.is_alphanumeric()))
+                    }
+                    _ => None,
+                }) == Some(true);
+                let marker = if numbered { "+" } else { "-" };
+                let mut lines = Vec::new();
+                for list_item in list.items.iter().filter_map(ContentItem::as_list_item) {
+                    let text: Vec<String> =
+                        list_item.text.iter().map(|text| self.text(text)).collect();
+                    let text = line_start(text.join(" ").trim());
+                    lines.push(format!("{marker} {text}").trim_end().to_string());
+                    lines.extend(indented(self.items(&list_item.children, depth)));
+                }
+                lines
+            }
+            ContentItem::Definition(definition) => {
+                let subject = self.text(&definition.subject);
+                let mut lines = vec![format!("/ {}:", subject.trim().trim_end_matches(':'))];
+                lines.extend(indented(self.items(&definition.children, depth)));
+                lines
+            }
+            ContentItem::VerbatimBlock(verbatim) => {
+                if let Some(equation) = Equation::from_verbatim(verbatim, 0) {
+                    return vec![equation.to_typst()];
+                }
+                let block = code_block(verbatim);
+                if block.language == TABLE_LABEL {
+                    if let Some(rows) = table_rows(verbatim) {
+                        return table(&rows);
+                    }
+                }
+                let fence = "`".repeat(longest_run(&block.text, '`').max(2) + 1);
+                let mut raw = vec![format!("{fence}{}", block.language.trim())];
+                raw.extend(block.text.lines().map(str::to_string));
+                raw.push(fence);
+                let subject = block.subject.trim().trim_end_matches(':');
+                if subject.is_empty() {
+                    return raw;
+                }
+                let mut lines = vec![format!("#figure(caption: [{}])[", escape(subject))];
+                lines.extend(raw);
+                lines.push("]".to_string());
+                lines
+            }
+            ContentItem::VerbatimLine(line) => vec![line.content.as_string().to_string()],
+            ContentItem::ListItem(_)
+            | ContentItem::Annotation(_)
+            | ContentItem::BlankLineGroup(_) => Vec::new(),
+        }
+    }
+
+    fn text(&mut self, text: &TextContent) -> String {
+        self.inline_text(text.as_string())
+    }
+
+    fn inline_text(&mut self, text: &str) -> String {
+        let nodes = self.parser.parse(text);
+        self.inlines(&nodes)
+    }
+
+    fn inlines(&mut self, nodes: &[InlineNode]) -> String {
+        let mut out = String::new();
+        for node in nodes {
+            match node {
+                InlineNode::Plain { text, .. } => out.push_str(&escape(text)),
+                InlineNode::Strong { content, .. } => {
+                    out.push_str(&format!("*{}*", self.inlines(content)))
+                }
+                InlineNode::Emphasis { content, .. } => {
+                    out.push_str(&format!("_{}_", self.inlines(content)))
+                }
+                InlineNode::Code { text, .. } if text.contains('`') => {
+                    out.push_str(&format!("#raw({})", string(text)))
+                }
+                InlineNode::Code { text, .. } => out.push_str(&format!("`{text}`")),
+                InlineNode::Math { text, .. } => {
+                    out.push_str(&format!("${}$", asciimath::to_typst(text)))
+                }
+                InlineNode::Reference { data, .. } => {
+                    out.push_str(&self.reference(&data.reference_type, &data.raw))
+                }
+                InlineNode::Ruby { base, text, .. } => {
+                    out.push_str(&format!("{} ({})", escape(base), escape(text)))
+                }
+                InlineNode::Role { data, .. } if data.role == DEL_ROLE => {
+                    out.push_str(&format!("#strike[{}]", escape(&data.content)))
+                }
+                InlineNode::Role { data, .. } => out.push_str(&escape(&data.content)),
+            }
+        }
+        out
+    }
+
+    fn reference(&mut self, reference: &ReferenceType, raw: &str) -> String {
+        match reference {
+            ReferenceType::Url { target } => format!("#link({})", string(target)),
+            ReferenceType::File { target } => {
+                format!("#link({})[{}]", string(target), escape(target))
+            }
+            ReferenceType::FootnoteNumber { number } => self.footnote(&number.to_string(), raw),
+            ReferenceType::FootnoteLabeled { label } => self.footnote(label, raw),
+            ReferenceType::Citation(citation) => citation
+                .keys
+                .iter()
+                .map(|key| match &citation.locator {
+                    Some(locator) => {
+                        format!("#cite(<{key}>, supplement: [{}])", escape(&locator.raw))
+                    }
+                    None => format!("#cite(<{key}>)"),
+                })
+                .collect(),
+            ReferenceType::Session { target } if self.sessions.contains_key(target) => {
+                let title = self.inline_text(&self.sessions[target].clone());
+                format!("#link(<{target}>)[{title}]")
+            }
+            ReferenceType::Session { target } if self.equations.contains_key(target) => {
+                format!("#link(<{target}>)[({})]", self.equations[target])
+            }
+            ReferenceType::General { target } => escape(target),
+            ReferenceType::WikiLink(link) => escape(link.display_text()),
+            ReferenceType::Image(image) => {
+                let mut arguments = vec![string(&image.src)];
+                if let Some(alt) = &image.alt {
+                    arguments.push(format!("alt: {}", string(alt)));
+                }
+                if let Some(width) = &image.width {
+                    arguments.push(format!("width: {}", length(width)));
+                }
+                if let Some(height) = &image.height {
+                    arguments.push(format!("height: {}", length(height)));
+                }
+                format!("#box(image({}))", arguments.join(", "))
+            }
+            _ => escape(&format!("[{raw}]")),
+        }
+    }
+
+    /// The footnote labeled `label`, inline, or the reference as written if there is none
+    fn footnote(&mut self, label: &str, raw: &str) -> String {
+        let Some(annotation) = self.doc.find_annotation_by_label(label) else {
+            return escape(&format!("[{raw}]"));
+        };
+        let mut text = Vec::new();
+        for item in annotation.children.iter() {
+            if let ContentItem::Paragraph(paragraph) = item {
+                for line in paragraph.lines.iter() {
+                    if let ContentItem::TextLine(line) = line {
+                        text.push(self.text(&line.content));
+                    }
+                }
+            }
+        }
+        format!("#footnote[{}]", text.join(" "))
+    }
+}
+
+/// `lines` indented to continue a list item or term
+fn indented(lines: Vec<String>) -> Vec<String> {
+    lines
+        .into_iter()
+        .map(|line| {
+            if line.is_empty() {
+                line
+            } else {
+                format!("  {line}")
+            }
+        })
+        .collect()
+}
+
+/// A `#table` call, with the first row as header
+fn table(rows: &[Vec<String>]) -> Vec<String> {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let mut lines = vec!["#table(".to_string(), format!("  columns: {columns},")];
+    for (index, row) in rows.iter().enumerate() {
+        let mut cells: Vec<String> = row
+            .iter()
+            .map(|cell| format!("[{}]", escape(cell)))
+            .collect();
+        cells.resize(columns, "[]".to_string());
+        let cells = cells.join(", ");
+        if index == 0 {
+            lines.push(format!("  table.header({cells}),"));
+        } else {
+            lines.push(format!("  {cells},"));
+        }
+    }
+    lines.push(")".to_string());
+    lines
+}
+
+/// An image size as a Typst length: points for bare numbers of pixels, other sizes as
+/// written
+fn length(size: &str) -> String {
+    let size = size.trim();
+    match size.parse::<f64>() {
+        Ok(pixels) => format!("{}pt", pixels * 0.75),
+        Err(_) => size.to_string(),
+    }
+}
+
+fn longest_run(text: &str, c: char) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for next in text.chars() {
+        current = if next == c { current + 1 } else { 0 };
+        longest = longest.max(current);
+    }
+    longest
+}
+
+/// `text` as a Typst string literal
+fn string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// `line` with a leading heading, list or term marker escaped, so it stays text
+fn line_start(line: &str) -> String {
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 && line[digits..].starts_with(". ") {
+        return format!("{}\\{}", &line[..digits], &line[digits..]);
+    }
+    if ["= ", "- ", "+ ", "/ "]
+        .iter()
+        .any(|marker| line.starts_with(marker))
+    {
+        return format!("\\{line}");
+    }
+    line.to_string()
+}
+
+/// Escape characters Typst markup reads as syntax
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let comment = c == '/' && matches!(chars.peek(), Some('/') | Some('*'));
+        if comment
+            || matches!(
+                c,
+                '\\' | '#' | '*' | '_' | '`' | '$' | '@' | '<' | '[' | ']' | '~'
+            )
+        {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    const SOURCE: &str = "Field Notes\n\n:: meta author=\"Ada Lovelace\" ::\n\nCosts rose 5% @ noon, see [#method] and [1]. Old price {del|4 EUR}.\n\n:: id method ::\n1. Method\n\n    Energy is #E = m c^2#, with *care* and `code_{x}`.\n\n    Logo [!./logo.png alt=\"Logo\" width=50%].\n\n    - First\n    - Second\n\n    Sample:\n        print(\"hi\")\n    :: python\n\n    Appendix\n\n        Rest energy:\n            E = m c^2\n        :: math id=energy\n\n        As [#energy] shows.\n\n        Prices:\n            | Item | Price |\n            | Tea  | 2     |\n        :: table\n\n        Term:\n            Meaning.\n\n2. Caveats\n\n    :: warning title=\"Data loss\" ::\n    Clearing deletes everything.\n\n:: 1 ::\n    A footnote.\n::\n";
+
+    #[test]
+    fn test_render_document() {
+        let doc = parse_document(SOURCE).unwrap();
+        assert_eq!(
+            render_document(&doc),
+            "#set document(title: \"Field Notes\", author: \"Ada Lovelace\")\n\n#align(center, text(size: 1.6em, weight: \"bold\")[Field Notes])\n\nCosts rose 5% \\@ noon, see #link(<method>)[Method] and #footnote[A footnote.]. Old price #strike[4 EUR].\n\n= 1\\. Method <method>\n\nEnergy is $E=m c^2$, with *care* and `code_{x}`.\n\nLogo #box(image(\"./logo.png\", alt: \"Logo\", width: 50%)).\n\n- First\n- Second\n\n#figure(caption: [Sample])[\n```python\nprint(\"hi\")\n```\n]\n\n== Appendix\n\n$ E=m c^2 $ <energy>\n\nAs #link(<energy>)[(1)] shows.\n\n#table(\n  columns: 2,\n  table.header([Item], [Price]),\n  [Tea], [2],\n)\n\n/ Term:\n  Meaning.\n\n= 2\\. Caveats\n\n#quote(block: true)[\n  *Data loss.*\n  Clearing deletes everything.\n]\n"
+        );
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape("a_b #c $5 <x> // not a comment"),
+            "a\\_b \\#c \\$5 \\<x> \\// not a comment"
+        );
+        assert_eq!(line_start("1. One"), "1\\. One");
+        assert_eq!(line_start("- dash"), "\\- dash");
+        assert_eq!(line_start("2024 was good"), "2024 was good");
+        assert_eq!(length("120"), "90pt");
+    }
+}
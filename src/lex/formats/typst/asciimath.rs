@@ -0,0 +1,114 @@
+//! AsciiMath to Typst math
+//!
+//! Typst math is close to AsciiMath: `_`, `^` and `/` bind the same way and drop the
+//! brackets around their operands, so `(a+b)/2` and `sum_(i=1)^n i` read the same in both.
+//! [to_typst] writes the [parsed](crate::lex::formats::asciimath) expression with the
+//! symbols whose names differ mapped (`xx` is `times`, `oo` is `infinity`), unary and binary
+//! functions as calls (`sqrt x` becomes `sqrt(x)`, `frac a b` becomes `frac(a, b)`), and
+//! letters spaced apart, since Typst reads `xy` as one name where AsciiMath reads `x` times
+//! `y`. Characters the symbol table doesn't know are kept, escaped where Typst would read
+//! them as markup.
+
+use crate::lex::formats::asciimath::{parse, Expr, Kind};
+
+/// `text` as a Typst math string
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn escape(c: char) -> String {
+    match c {
+        '#' | '$' | '&' | '@' | '\\' | ',' | ';' => format!("\\{c}"),
+        _ => c.to_string(),
+    }
+}
+
+fn sequence(expressions: &[Expr]) -> String {
+    let mut out = String::new();
+    for expression in expressions {
+        push(&mut out, &typst(expression));
+    }
+    out
+}
+
+/// `expression` as a function argument, without its brackets
+fn argument(expression: &Expr) -> String {
+    match expression.argument() {
+        Some(content) => sequence(content),
+        None => typst(expression),
+    }
+}
+
+fn typst(expression: &Expr) -> String {
+    match expression {
+        Expr::Symbol(symbol) => symbol.typst.to_string(),
+        Expr::Char(c) => escape(*c),
+        Expr::Number(number) => number.clone(),
+        Expr::Text(text) => quote(text),
+        Expr::Group {
+            open,
+            content,
+            close,
+        } => {
+            let mut out = open.typst.to_string();
+            push(&mut out, &sequence(content));
+            push(&mut out, close.map_or("", |close| close.typst));
+            out
+        }
+        Expr::Apply(symbol, arguments) if symbol.kind == Kind::Over => {
+            let over = arguments.first().map(argument).unwrap_or_default();
+            let base = arguments.get(1).map(argument).unwrap_or_default();
+            format!("attach({base}, t: {over})")
+        }
+        Expr::Apply(symbol, arguments) => {
+            let arguments: Vec<String> = arguments.iter().map(argument).collect();
+            format!("{}({})", symbol.typst, arguments.join(", "))
+        }
+        Expr::Frac(numerator, denominator) => {
+            format!("{}/{}", typst(numerator), typst(denominator))
+        }
+        Expr::Script { base, sub, sup } => {
+            let mut out = typst(base);
+            for (operator, script) in [('_', sub), ('^', sup)] {
+                if let Some(script) = script {
+                    out = format!("{out}{operator}{}", typst(script));
+                }
+            }
+            out
+        }
+        Expr::Missing => String::new(),
+    }
+}
+
+/// Append `piece` to `out`, spaced from a preceding name, number or string it would run into
+fn push(out: &mut String, piece: &str) {
+    let joins = |c: char| c.is_alphanumeric() || c == '"' || c == '.';
+    if out.ends_with(joins) && piece.starts_with(|c: char| joins(c) || c == '(') {
+        out.push(' ');
+    }
+    out.push_str(piece);
+}
+
+/// Typst math for the AsciiMath expression `source`
+pub fn to_typst(source: &str) -> String {
+    sequence(&parse(source))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_typst() {
+        assert_eq!(to_typst("x^2 + y"), "x^2+y");
+        assert_eq!(to_typst("(a+b)/2"), "(a+b)/2");
+        assert_eq!(to_typst("sum_(i=1)^n i"), "sum_(i=1)^n i");
+        assert_eq!(to_typst("sqrt(x) <= oo"), "sqrt(x)<=infinity");
+        assert_eq!(to_typst("alpha in RR"), "alpha in RR");
+        assert_eq!(to_typst("xy xx 2x"), "x y times 2 x");
+        assert_eq!(to_typst("frac a b"), "frac(a, b)");
+        assert_eq!(to_typst("\"if\" x > 0"), "\"if\" x>0");
+        assert_eq!(to_typst("text(hello world) # 1"), "\"hello world\"\\#1");
+        assert_eq!(to_typst("stackrel(def)(=)"), "attach(=, t: d e f)");
+    }
+}
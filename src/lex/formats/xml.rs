@@ -0,0 +1,842 @@
+//! Semantic XML
+//!
+//! The `xml` format writes the structure of a document as XML, for XPath, XSLT and the other
+//! tools that read XML. Unlike the `tag` format, a debugging view of the AST, it has a stable
+//! schema, in the [NAMESPACE] namespace:
+//!
+//! - The root is `<document>`, whose `version` attribute is the schema version. Its
+//!   `<title>` is the document title.
+//! - `<session level="1">` holds the session `<title>`, then the session content. Sessions
+//!   numbered with a sequence marker have `marker`, `style`, `separator` and `form`
+//!   attributes.
+//! - `<paragraph>` holds a `<line>` per text line.
+//! - `<list>` holds an `<item marker="-">` per list item: its `<text>`, then its content. The
+//!   `style`, `separator` and `form` of a list are those of its first marker.
+//! - `<definition>` holds its `<subject>`, then its content.
+//! - `<annotation label="note">` holds a `<parameter key="status" value="draft"/>` per
+//!   parameter, then its content. Values written in quotes have `quoted="true"`.
+//! - `<verbatim label="rust" mode="inflow">` holds the parameters of its closing line, then
+//!   each group: a `<subject>` followed by its `<line>`s, which keep their indentation past
+//!   the block's.
+//! - `<blank-lines count="2"/>` stands for the blank lines between two elements.
+//!
+//! Text is Lex inline source (`*strong*`, `[#2]`), escaped for XML. Annotations are placed
+//! where they are in the source, among the elements they annotate. With the `ranges`
+//! parameter, elements have `start` and `end` attributes: their source range, as zero-based
+//! `line:column` positions.
+//!
+//! ```text
+//! <?xml version="1.0" encoding="UTF-8"?>
+//! <document xmlns="https://lex.ing/xml/1" version="1">
+//!   <title>Guide</title>
+//!   <session level="1" marker="1." style="numerical" separator="period" form="short">
+//!     <title>Setup</title>
+//!     <paragraph>
+//!       <line>Install it *first*.</line>
+//!     </paragraph>
+//!   </session>
+//! </document>
+//! ```
+//!
+//! [import_xml] reads the format back as Lex source, which parses to the structure of the
+//! document written: the same elements, text and verbatim content. A definition without
+//! content (left when the parser skips its only line) has no source form and is read back
+//! as a paragraph. Source ranges, and elements the schema doesn't define, are ignored.
+
+use crate::lex::ast::elements::sequence_marker::{DecorationStyle, Form, Separator};
+use crate::lex::ast::elements::verbatim::VerbatimBlockMode;
+use crate::lex::ast::elements::SequenceMarker;
+use crate::lex::ast::{
+    Annotation, ContentItem, Definition, Document, ListItem, Parameter, Range, Session, Verbatim,
+};
+use crate::lex::formats::markup::{escape, unescape, ATTRIBUTE};
+use crate::lex::formats::registry::{flag_param, FormatError, Formatter};
+use crate::lex::formatting::serializer::{
+    annotation_location, document_annotations, inner_annotations, ordered_entries, subject_text,
+    Entry,
+};
+use crate::lex::parsing::parse_document;
+use std::collections::HashMap;
+
+/// Namespace of the elements of the format
+pub const NAMESPACE: &str = "https://lex.ing/xml/1";
+
+/// Version of the schema, the `version` attribute of `<document>`
+pub const VERSION: &str = "1";
+
+/// Indentation of a nesting level of the Lex source written by [import_xml]
+const INDENT: &str = "    ";
+
+/// Document structure as semantic XML
+pub struct XmlFormatter;
+
+impl Formatter for XmlFormatter {
+    fn name(&self) -> &str {
+        "xml"
+    }
+
+    fn serialize(&self, doc: &Document) -> Result<String, FormatError> {
+        Ok(serialize_document(doc))
+    }
+
+    /// `ranges` gives elements their source range
+    fn serialize_with_params(
+        &self,
+        doc: &Document,
+        params: &HashMap<String, String>,
+    ) -> Result<String, FormatError> {
+        let options = XmlOptions {
+            ranges: flag_param(params, "ranges").unwrap_or(false),
+        };
+        Ok(serialize_document_with_options(doc, &options))
+    }
+
+    fn supports_parsing(&self) -> bool {
+        true
+    }
+
+    /// The document [import_xml] writes
+    fn parse(&self, source: &str) -> Result<Document, FormatError> {
+        parse_document(&import_xml(source)?).map_err(FormatError::ParseError)
+    }
+
+    fn description(&self) -> &str {
+        "Document structure as semantic XML"
+    }
+}
+
+/// How to write a document as XML
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct XmlOptions {
+    /// Give elements `start` and `end` attributes with their source range
+    pub ranges: bool,
+}
+
+/// `doc` as XML
+pub fn serialize_document(doc: &Document) -> String {
+    serialize_document_with_options(doc, &XmlOptions::default())
+}
+
+/// `doc` as XML, as set by `options`
+pub fn serialize_document_with_options(doc: &Document, options: &XmlOptions) -> String {
+    let mut writer = Writer {
+        out: String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"),
+        options,
+        level: 0,
+    };
+    writer.out.push_str(&format!(
+        "<document xmlns=\"{NAMESPACE}\" version=\"{VERSION}\">\n"
+    ));
+    let (leading, trailing) = document_annotations(doc);
+    for annotation in leading {
+        writer.annotation(annotation, 1);
+    }
+    if !doc.root.title.is_empty() {
+        let title = &doc.root.title;
+        writer.text(1, "title", title.as_string(), title.location.as_ref());
+    }
+    let items: Vec<ContentItem> = doc.root.children.iter().cloned().collect();
+    let mut inner = trailing;
+    inner.extend(doc.root.annotations.iter());
+    writer.items(&items, &inner, 1);
+    writer.out.push_str("</document>\n");
+    writer.out
+}
+
+struct Writer<'a> {
+    out: String,
+    options: &'a XmlOptions,
+    /// Level of the session being written
+    level: usize,
+}
+
+impl Writer<'_> {
+    /// Start tag of a `name` element, up to its closing `>`
+    fn start(
+        &mut self,
+        depth: usize,
+        name: &str,
+        attributes: &[(&str, String)],
+        range: Option<&Range>,
+    ) {
+        self.out.push_str(&"  ".repeat(depth));
+        self.out.push('<');
+        self.out.push_str(name);
+        for (key, value) in attributes {
+            self.out.push_str(&format!(" {key}=\"{}\"", escape(value)));
+        }
+        let range = range.filter(|range| self.options.ranges && **range != Range::default());
+        if let Some(range) = range {
+            self.out.push_str(&format!(
+                " start=\"{}:{}\" end=\"{}:{}\"",
+                range.start.line, range.start.column, range.end.line, range.end.column
+            ));
+        }
+    }
+
+    fn open(&mut self, depth: usize, name: &str, attributes: &[(&str, String)], range: &Range) {
+        self.start(depth, name, attributes, Some(range));
+        self.out.push_str(">\n");
+    }
+
+    fn close(&mut self, depth: usize, name: &str) {
+        self.out
+            .push_str(&format!("{}</{name}>\n", "  ".repeat(depth)));
+    }
+
+    fn empty(&mut self, depth: usize, name: &str, attributes: &[(&str, String)]) {
+        self.start(depth, name, attributes, None);
+        self.out.push_str("/>\n");
+    }
+
+    /// An element holding `text`
+    fn text(&mut self, depth: usize, name: &str, text: &str, range: Option<&Range>) {
+        self.start(depth, name, &[], range);
+        if text.is_empty() {
+            self.out.push_str("/>\n");
+        } else {
+            self.out.push_str(&format!(">{}</{name}>\n", escape(text)));
+        }
+    }
+
+    fn blank_lines(&mut self, depth: usize, count: usize) {
+        if count > 0 {
+            self.empty(depth, "blank-lines", &[("count", count.to_string())]);
+        }
+    }
+
+    /// Elements of a container's children, interleaving attached annotations in source order
+    ///
+    /// As in Lex source, blank lines consumed by verbatim blocks are counted from the gaps
+    /// between the source lines of siblings.
+    fn items(&mut self, items: &[ContentItem], inner: &[&Annotation], depth: usize) {
+        let mut blank_lines = 0;
+        let mut previous_end: Option<usize> = None;
+        let mut previous_verbatim = false;
+        for entry in ordered_entries(items, inner) {
+            if let Entry::Item(ContentItem::BlankLineGroup(group)) = entry {
+                blank_lines += group.count.max(1);
+                continue;
+            }
+            let (start, end) = entry.source_lines();
+            let touches_verbatim = previous_verbatim || entry.ends_with_verbatim();
+            if let (Some(previous_end), Some(start), true) = (previous_end, start, touches_verbatim)
+            {
+                blank_lines = blank_lines.max(start.saturating_sub(previous_end + 1));
+            }
+            self.blank_lines(depth, blank_lines);
+            match entry {
+                Entry::Item(item) => self.item(item, depth),
+                Entry::Annotation(annotation) => self.annotation(annotation, depth),
+            }
+            blank_lines = 0;
+            previous_end = end;
+            previous_verbatim = entry.ends_with_verbatim();
+        }
+        self.blank_lines(depth, blank_lines);
+    }
+
+    fn item(&mut self, item: &ContentItem, depth: usize) {
+        match item {
+            ContentItem::Paragraph(paragraph) => {
+                self.open(depth, "paragraph", &[], &paragraph.location);
+                for line in &paragraph.lines {
+                    self.item(line, depth + 1);
+                }
+                self.close(depth, "paragraph");
+            }
+            ContentItem::Session(session) => self.session(session, depth),
+            ContentItem::List(list) => {
+                let attributes = list.marker.as_ref().map(marker_attributes);
+                self.open(
+                    depth,
+                    "list",
+                    &attributes.unwrap_or_default(),
+                    &list.location,
+                );
+                let items: Vec<ContentItem> = list.items.iter().cloned().collect();
+                for entry in ordered_entries(&items, &[]) {
+                    match entry {
+                        Entry::Item(item) => self.item(item, depth + 1),
+                        Entry::Annotation(annotation) => self.annotation(annotation, depth + 1),
+                    }
+                }
+                self.close(depth, "list");
+            }
+            ContentItem::ListItem(list_item) => self.list_item(list_item, depth),
+            ContentItem::Definition(definition) => self.definition(definition, depth),
+            ContentItem::Annotation(annotation) => self.annotation(annotation, depth),
+            ContentItem::VerbatimBlock(verbatim) => self.verbatim(verbatim, depth),
+            ContentItem::TextLine(line) => {
+                self.text(depth, "line", line.text(), Some(&line.location))
+            }
+            ContentItem::VerbatimLine(line) => self.text(
+                depth,
+                "line",
+                line.content.as_string(),
+                Some(&line.location),
+            ),
+            ContentItem::BlankLineGroup(group) => self.blank_lines(depth, group.count),
+        }
+    }
+
+    fn session(&mut self, session: &Session, depth: usize) {
+        self.level += 1;
+        let mut attributes = vec![("level", self.level.to_string())];
+        if let Some(marker) = &session.marker {
+            attributes.push(("marker", marker.as_str().to_string()));
+            attributes.extend(marker_attributes(marker));
+        }
+        self.open(depth, "session", &attributes, &session.location);
+        let title = &session.title;
+        self.text(
+            depth + 1,
+            "title",
+            session_title(session),
+            title.location.as_ref(),
+        );
+        let items: Vec<ContentItem> = session.children.iter().cloned().collect();
+        let inner = inner_annotations(&session.annotations, &session.location);
+        self.items(&items, &inner, depth + 1);
+        self.close(depth, "session");
+        self.level -= 1;
+    }
+
+    fn list_item(&mut self, list_item: &ListItem, depth: usize) {
+        let marker = list_item.marker().to_string();
+        self.open(depth, "item", &[("marker", marker)], &list_item.location);
+        let text: Vec<&str> = list_item.text.iter().map(|text| text.as_string()).collect();
+        self.text(depth + 1, "text", text.join(" ").trim_start(), None);
+        let items: Vec<ContentItem> = list_item.children.iter().cloned().collect();
+        let inner = inner_annotations(&list_item.annotations, &list_item.location);
+        self.items(&items, &inner, depth + 1);
+        self.close(depth, "item");
+    }
+
+    fn definition(&mut self, definition: &Definition, depth: usize) {
+        self.open(depth, "definition", &[], &definition.location);
+        let subject = &definition.subject;
+        let text = subject_text(subject.as_string());
+        self.text(depth + 1, "subject", text, subject.location.as_ref());
+        let items: Vec<ContentItem> = definition.children.iter().cloned().collect();
+        let inner = inner_annotations(&definition.annotations, &definition.location);
+        self.items(&items, &inner, depth + 1);
+        self.close(depth, "definition");
+    }
+
+    fn annotation(&mut self, annotation: &Annotation, depth: usize) {
+        let attributes = [("label", annotation.data.label.value.clone())];
+        // Marker-form annotations may carry an empty placeholder paragraph
+        let children: Vec<ContentItem> = annotation
+            .children
+            .iter()
+            .filter(|child| child.as_paragraph().is_none_or(|p| !p.text().is_empty()))
+            .cloned()
+            .collect();
+        let range = annotation_location(annotation);
+        if children.is_empty() && annotation.data.parameters.is_empty() {
+            self.start(depth, "annotation", &attributes, Some(range));
+            self.out.push_str("/>\n");
+            return;
+        }
+        self.open(depth, "annotation", &attributes, range);
+        self.parameters(&annotation.data.parameters, depth + 1);
+        self.items(&children, &[], depth + 1);
+        self.close(depth, "annotation");
+    }
+
+    fn verbatim(&mut self, verbatim: &Verbatim, depth: usize) {
+        let mode = match verbatim.mode {
+            VerbatimBlockMode::Inflow => "inflow",
+            VerbatimBlockMode::Fullwidth => "fullwidth",
+        };
+        let attributes = [
+            ("label", verbatim.closing_data.label.value.clone()),
+            ("mode", mode.to_string()),
+        ];
+        self.open(depth, "verbatim", &attributes, &verbatim.location);
+        self.parameters(&verbatim.closing_data.parameters, depth + 1);
+        for group in verbatim.group() {
+            // The colon ending a subject is not part of it, unless the parser kept it
+            let subject = group.subject;
+            let text = subject.as_string().trim_end();
+            self.text(depth + 1, "subject", text, subject.location.as_ref());
+            let lines: Vec<_> = group
+                .children
+                .iter()
+                .filter_map(|line| line.as_verbatim_line())
+                .collect();
+            // Content is stored without leading whitespace: its indentation past the wall
+            // is recovered from its column
+            let wall = match verbatim.mode {
+                VerbatimBlockMode::Inflow => lines
+                    .iter()
+                    .filter(|line| !line.content.as_string().is_empty())
+                    .map(|line| line.location.start.column)
+                    .min()
+                    .unwrap_or(0),
+                VerbatimBlockMode::Fullwidth => 1,
+            };
+            for line in lines {
+                let content = line.content.as_string();
+                let text = if content.is_empty() {
+                    String::new()
+                } else {
+                    let extra = line.location.start.column.saturating_sub(wall);
+                    format!("{}{content}", " ".repeat(extra))
+                };
+                self.text(depth + 1, "line", &text, Some(&line.location));
+            }
+        }
+        self.close(depth, "verbatim");
+    }
+
+    fn parameters(&mut self, parameters: &[Parameter], depth: usize) {
+        for parameter in parameters {
+            let quoted = parameter
+                .value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'));
+            let mut attributes = vec![
+                ("key", parameter.key.clone()),
+                ("value", quoted.unwrap_or(&parameter.value).to_string()),
+            ];
+            if quoted.is_some() {
+                attributes.push(("quoted", "true".to_string()));
+            }
+            self.start(depth, "parameter", &attributes, Some(&parameter.location));
+            self.out.push_str("/>\n");
+        }
+    }
+}
+
+/// Title of a session, past its marker and the space that follows it
+///
+/// Further spacing is kept, so that `marker title` is the title as written.
+fn session_title(session: &Session) -> &str {
+    let title = session.title.as_string();
+    let rest = session
+        .marker
+        .as_ref()
+        .and_then(|marker| title.strip_prefix(marker.as_str()));
+    match rest {
+        Some(rest) => rest.strip_prefix(' ').unwrap_or(rest),
+        None => session.title_text(),
+    }
+}
+
+/// `style`, `separator` and `form` attributes of a sequence marker; plain markers have a
+/// style only
+fn marker_attributes(marker: &SequenceMarker) -> Vec<(&'static str, String)> {
+    let style = match marker.style {
+        DecorationStyle::Plain => return vec![("style", "plain".to_string())],
+        DecorationStyle::Numerical => "numerical",
+        DecorationStyle::Alphabetical => "alphabetical",
+        DecorationStyle::Roman => "roman",
+    };
+    let separator = match marker.separator {
+        Separator::Period => "period",
+        Separator::Parenthesis => "parenthesis",
+        Separator::DoubleParens => "double-parens",
+    };
+    let form = match marker.form {
+        Form::Short => "short",
+        Form::Extended => "extended",
+    };
+    vec![
+        ("style", style.to_string()),
+        ("separator", separator.to_string()),
+        ("form", form.to_string()),
+    ]
+}
+
+/// An element of an XML source, under its local name
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+#[derive(Debug)]
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+impl Element {
+    fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|element| element.name == name)
+    }
+
+    /// Text of the element, without that of nested elements
+    fn text(&self) -> String {
+        self.children
+            .iter()
+            .filter_map(|node| match node {
+                Node::Text(text) => Some(text.as_str()),
+                Node::Element(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// Root element of the XML `source`
+fn parse_xml(source: &str) -> Result<Element, FormatError> {
+    let error = |message: &str| FormatError::ParseError(format!("invalid XML: {message}"));
+    // Open elements, under a placeholder for the root
+    let mut stack = vec![Element::default()];
+    let mut rest = source;
+    while let Some(start) = rest.find('<') {
+        let text = unescape(&rest[..start]);
+        rest = &rest[start..];
+        if !text.is_empty() {
+            stack.last_mut().unwrap().children.push(Node::Text(text));
+        }
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let end = comment
+                .find("-->")
+                .ok_or_else(|| error("unclosed comment"))?;
+            rest = &comment[end + 3..];
+            continue;
+        }
+        if let Some(data) = rest.strip_prefix("<![CDATA[") {
+            let end = data.find("]]>").ok_or_else(|| error("unclosed CDATA"))?;
+            let text = Node::Text(data[..end].to_string());
+            stack.last_mut().unwrap().children.push(text);
+            rest = &data[end + 3..];
+            continue;
+        }
+        let end = rest.find('>').ok_or_else(|| error("unclosed tag"))?;
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+        if let Some(closing) = tag.strip_prefix('/') {
+            let element = stack.pop().filter(|_| !stack.is_empty());
+            let element = element.ok_or_else(|| error("unbalanced closing tag"))?;
+            if local_name(closing.trim()) != element.name {
+                return Err(error(&format!(
+                    "</{}> closes <{}>",
+                    closing.trim(),
+                    element.name
+                )));
+            }
+            let parent = stack.last_mut().unwrap();
+            parent.children.push(Node::Element(element));
+            continue;
+        }
+        let (tag, is_empty) = match tag.strip_suffix('/') {
+            Some(tag) => (tag, true),
+            None => (tag, false),
+        };
+        let name = tag.split_whitespace().next().unwrap_or_default();
+        let attributes = ATTRIBUTE
+            .captures_iter(&tag[name.len()..])
+            .map(|captures| {
+                let value = captures
+                    .get(2)
+                    .or(captures.get(3))
+                    .map_or("", |m| m.as_str());
+                (local_name(&captures[1]).to_string(), unescape(value))
+            })
+            .collect();
+        let element = Element {
+            name: local_name(name).to_string(),
+            attributes,
+            children: Vec::new(),
+        };
+        if is_empty {
+            let parent = stack.last_mut().unwrap();
+            parent.children.push(Node::Element(element));
+        } else {
+            stack.push(element);
+        }
+    }
+    if stack.len() != 1 {
+        return Err(error(&format!("unclosed <{}>", stack.last().unwrap().name)));
+    }
+    let placeholder = stack.pop().unwrap();
+    placeholder
+        .children
+        .into_iter()
+        .find_map(|node| match node {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+        .ok_or_else(|| error("no root element"))
+}
+
+/// Name without its namespace prefix
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Lex source of a document written as XML by the `xml` format
+pub fn import_xml(source: &str) -> Result<String, FormatError> {
+    let root = parse_xml(source)?;
+    if root.name != "document" {
+        return Err(FormatError::ParseError(format!(
+            "invalid Lex XML: the root element is <{}>, not <document>",
+            root.name
+        )));
+    }
+    let mut writer = LexWriter::default();
+    for element in root.elements() {
+        if element.name == "title" {
+            if !writer.lines.is_empty() {
+                writer.blanks(1);
+            }
+            writer.line(0, &element.text());
+            writer.blanks(1);
+        } else {
+            writer.item(element, 0);
+        }
+    }
+    if writer.lines.is_empty() {
+        return Ok(String::new());
+    }
+    Ok(format!("{}\n", writer.lines.join("\n")))
+}
+
+/// Lex lines, with the blank lines between them
+#[derive(Default)]
+struct LexWriter {
+    lines: Vec<String>,
+    /// Blank lines to write before the next line
+    blank_lines: usize,
+}
+
+impl LexWriter {
+    /// Request at least `count` blank lines before the next line
+    fn blanks(&mut self, count: usize) {
+        self.blank_lines = self.blank_lines.max(count);
+    }
+
+    fn line(&mut self, depth: usize, text: &str) {
+        self.raw(format!("{}{text}", INDENT.repeat(depth)).trim_end());
+    }
+
+    fn raw(&mut self, text: &str) {
+        for _ in 0..self.blank_lines {
+            self.lines.push(String::new());
+        }
+        self.blank_lines = 0;
+        self.lines.push(text.to_string());
+    }
+
+    fn item(&mut self, element: &Element, depth: usize) {
+        let content = |skip: &'static str| element.elements().filter(move |e| e.name != skip);
+        match element.name.as_str() {
+            "blank-lines" => {
+                let count = element.attribute("count").and_then(|n| n.parse().ok());
+                self.blanks(count.unwrap_or(1));
+            }
+            "paragraph" | "list" => self.items(element.elements(), depth),
+            "line" => self.line(depth, &element.text()),
+            "session" => {
+                let title = element.child("title").map(Element::text);
+                let title = title.unwrap_or_default();
+                match element.attribute("marker") {
+                    Some(marker) => self.line(depth, &format!("{marker} {title}")),
+                    None => self.line(depth, &title),
+                }
+                if content("title").next().is_some() {
+                    self.blanks(1);
+                }
+                self.items(content("title"), depth + 1);
+            }
+            "item" => {
+                let marker = element.attribute("marker").unwrap_or("-");
+                let text = element.child("text").map(Element::text);
+                self.line(depth, &format!("{marker} {}", text.unwrap_or_default()));
+                self.items(content("text"), depth + 1);
+            }
+            "definition" => self.definition(element, depth, true),
+            "annotation" => self.annotation(element, depth),
+            "verbatim" => self.verbatim(element, depth),
+            _ => {}
+        }
+    }
+
+    /// Elements of a container, in order
+    ///
+    /// As in the serializer, a definition closed by an annotation is written without its
+    /// colon, and a blank line separates a paragraph from an element that follows it.
+    fn items<'e>(&mut self, elements: impl Iterator<Item = &'e Element>, depth: usize) {
+        let elements: Vec<&Element> = elements.collect();
+        let mut previous_paragraph = false;
+        for (index, element) in elements.iter().enumerate() {
+            let name = element.name.as_str();
+            if previous_paragraph && !matches!(name, "paragraph" | "annotation" | "blank-lines") {
+                self.blanks(1);
+            }
+            if name == "definition" {
+                let closed = elements[index + 1..]
+                    .iter()
+                    .find(|next| next.name != "blank-lines")
+                    .is_some_and(|next| next.name == "annotation");
+                self.definition(element, depth, !closed);
+            } else {
+                self.item(element, depth);
+            }
+            if name != "blank-lines" {
+                previous_paragraph = name == "paragraph";
+            }
+        }
+    }
+
+    fn definition(&mut self, element: &Element, depth: usize, colon: bool) {
+        let subject = element.child("subject").map(Element::text);
+        let colon = if colon { ":" } else { "" };
+        self.line(depth, &format!("{}{colon}", subject.unwrap_or_default()));
+        self.items(
+            element.elements().filter(|e| e.name != "subject"),
+            depth + 1,
+        );
+    }
+
+    fn annotation(&mut self, element: &Element, depth: usize) {
+        let header = format!(":: {} ::", data_header(element));
+        let children: Vec<&Element> = element
+            .elements()
+            .filter(|e| e.name != "parameter")
+            .collect();
+        if children.is_empty() {
+            self.line(depth, &header);
+            return;
+        }
+        // Text on the header line keeps the whitespace that follows the header
+        if let [paragraph] = children.as_slice() {
+            let lines: Vec<&Element> = paragraph.elements().collect();
+            if let ("paragraph", [line]) = (paragraph.name.as_str(), lines.as_slice()) {
+                let text = line.text();
+                if text.starts_with(char::is_whitespace) {
+                    self.line(depth, &format!("{header} {}", text.trim_start()));
+                    return;
+                }
+            }
+        }
+        self.line(depth, &header);
+        self.items(children.into_iter(), depth + 1);
+        self.line(depth, "::");
+    }
+
+    fn verbatim(&mut self, element: &Element, depth: usize) {
+        let fullwidth = element.attribute("mode") == Some("fullwidth");
+        for child in element.elements() {
+            match child.name.as_str() {
+                "subject" => {
+                    let subject = child.text();
+                    if subject.ends_with(':') {
+                        // The parser keeps the colon of a subject line ending in whitespace
+                        self.raw(&format!("{}{subject} ", INDENT.repeat(depth)));
+                    } else {
+                        self.line(depth, &format!("{subject}:"));
+                    }
+                }
+                "line" => {
+                    let text = child.text();
+                    if text.is_empty() {
+                        self.raw("");
+                    } else if fullwidth {
+                        self.line(0, &format!(" {text}"));
+                    } else {
+                        self.line(depth + 1, &text);
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.line(depth, &format!(":: {}", data_header(element)));
+    }
+}
+
+/// Label and parameters of an annotation or verbatim element, as written in Lex
+fn data_header(element: &Element) -> String {
+    let mut header = element.attribute("label").unwrap_or_default().to_string();
+    let parameters: Vec<String> = element
+        .elements()
+        .filter(|e| e.name == "parameter")
+        .map(|parameter| {
+            let key = parameter.attribute("key").unwrap_or_default();
+            let value = parameter.attribute("value").unwrap_or_default();
+            if parameter.attribute("quoted") == Some("true") {
+                format!("{key}=\"{value}\"")
+            } else {
+                format!("{key}={value}")
+            }
+        })
+        .collect();
+    if !parameters.is_empty() {
+        header.push(' ');
+        header.push_str(&parameters.join(", "));
+    }
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    const SOURCE: &str = "Guide\n\n:: status value=draft ::\n\nIntro with *care* & <tags>.\nSecond line.\n\n1. Setup\n\n    - First\n    - Second\n\n    Cache:\n        Where downloads go.\n\n    Example:\n        fn main() {\n            run();\n        }\n    :: rust title=\"Main\", lines=2\n\n2. Notes\n\n    :: note :: Kept for later.\n\n    :: warning id=w1 ::\n        Mind the gap.\n\n        Twice.\n    ::\n\n    a) One\n    b) Two\n";
+
+    #[test]
+    fn test_serialize() {
+        let doc = parse_document("Guide\n\n1. Setup\n\n    Install it *first*.\n").unwrap();
+        assert_eq!(
+            XmlFormatter.serialize(&doc).unwrap(),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<document xmlns=\"https://lex.ing/xml/1\" version=\"1\">\n  <title>Guide</title>\n  <session level=\"1\" marker=\"1.\" style=\"numerical\" separator=\"period\" form=\"short\">\n    <title>Setup</title>\n    <paragraph>\n      <line>Install it *first*.</line>\n    </paragraph>\n  </session>\n</document>\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let doc = parse_document(SOURCE).unwrap();
+        let xml = XmlFormatter.serialize(&doc).unwrap();
+        assert!(xml.contains("<line>Intro with *care* &amp; &lt;tags&gt;.</line>"));
+        assert!(xml.contains("<parameter key=\"title\" value=\"Main\" quoted=\"true\"/>"));
+        assert!(
+            xml.contains("<list style=\"alphabetical\" separator=\"parenthesis\" form=\"short\">")
+        );
+        assert!(xml.contains("<line>    run();</line>"));
+        assert_eq!(import_xml(&xml).unwrap(), SOURCE);
+
+        let read = XmlFormatter.parse(&xml).unwrap();
+        assert_eq!(XmlFormatter.serialize(&read).unwrap(), xml);
+    }
+
+    #[test]
+    fn test_ranges() {
+        let doc = parse_document("Guide\n\nText.\n").unwrap();
+        let params = HashMap::from([("ranges".to_string(), "true".to_string())]);
+        let xml = XmlFormatter.serialize_with_params(&doc, &params).unwrap();
+        assert!(xml.contains("<paragraph start=\"2:0\" end=\"2:5\">"));
+        assert!(xml.contains("<title start=\"0:0\" end=\"0:5\">Guide</title>"));
+        assert_eq!(import_xml(&xml).unwrap(), "Guide\n\nText.\n");
+    }
+
+    #[test]
+    fn test_import_xml() {
+        let xml = "<?xml version=\"1.0\"?>\n<!-- exported -->\n<lex:document xmlns:lex=\"https://lex.ing/xml/1\"><lex:title>Notes</lex:title><lex:paragraph><lex:line><![CDATA[a < b]]></lex:line></lex:paragraph></lex:document>";
+        assert_eq!(import_xml(xml).unwrap(), "Notes\n\na < b\n");
+        assert!(import_xml("<document><paragraph></document>").is_err());
+        assert!(import_xml("<opml version=\"2.0\"/>").is_err());
+    }
+}
@@ -0,0 +1,152 @@
+//! Lex source formatter
+//!
+//!     The formatter turns a Lex document back into canonical Lex source. It parses the
+//!     input, walks the resulting AST and re-emits every element according to a
+//!     [FormattingRulesConfig](rules::FormattingRulesConfig): indentation, blank line runs
+//!     and list markers are normalized, while text, labels, parameters and verbatim content
+//!     are kept as written.
+//!
+//!     Each normalization is a named [FormattingRule](rules::FormattingRule) (blank-lines,
+//!     markers, indentation, tables, wrapping), applied in pipeline order and individually
+//!     toggleable. [explain](explain::explain) reports which rule changed what. Rules can
+//!     also be switched off for single elements, or a whole file, with `:: lex-ignore ::`
+//!     annotations.
+//!
+//!     Since formatting goes through the AST, the output parses to the same structure as the
+//!     input: the same elements, with the same text up to trailing whitespace and the same
+//!     verbatim content. Source lines the parser does not keep in any element are copied as
+//!     written. Formatting is idempotent: formatting formatted source is a no-op.
+//!
+//! Entry Points
+//!
+//!     - [format_document]: the whole document, as a new string.
+//!     - [format_parsed_document]: the same, for a document the caller has already parsed.
+//!     - [format_document_edits]: the whole document, as a list of [TextEdit](edits::TextEdit)s
+//!       over the original source (for editor document formatting).
+//!     - [check_formatting]: whether a file is canonically formatted, with the diff that would
+//!       make it so (for `--check` runs in CI).
+//!     - [format_range](range::format_range): only the blocks enclosing a range, as a list of
+//!       [TextEdit](edits::TextEdit)s over the original source (for editor range formatting
+//!       and format-on-paste).
+//!     - [structural_edit](structural::structural_edit): outliner operations (move an element
+//!       past its siblings, indent, outdent) on the element under the cursor, as
+//!       [TextEdit](edits::TextEdit)s.
+//!
+//!     Edits are computed with a line diff (see [edits]), so callers get the minimal set of
+//!     replacements rather than a whole-document rewrite.
+
+pub mod edits;
+pub mod explain;
+pub mod range;
+pub mod rules;
+pub mod serializer;
+pub mod structural;
+pub mod wrapping;
+
+pub use edits::{apply_edits, compute_edits, unified_diff, TextEdit};
+pub use explain::{explain, render_explanation, RuleChanges};
+pub use range::format_range;
+pub use rules::{FormattingRule, FormattingRulesConfig};
+pub use serializer::{serialize_document, serialize_document_with_source};
+pub use structural::{structural_edit, StructuralEdit};
+
+use crate::lex::annotation::ignore::IgnoreDirectives;
+use crate::lex::ast::Document;
+use crate::lex::parsing::parse_document;
+use std::fmt;
+
+/// Error that can occur while formatting
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormattingError {
+    /// The source could not be parsed
+    ParseError(String),
+}
+
+impl fmt::Display for FormattingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormattingError::ParseError(msg) => write!(f, "Parse error: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for FormattingError {}
+
+/// Format a whole Lex document
+pub fn format_document(
+    source: &str,
+    rules: &FormattingRulesConfig,
+) -> Result<String, FormattingError> {
+    let doc = parse_document(source).map_err(FormattingError::ParseError)?;
+    Ok(format_parsed_document(&doc, source, rules))
+}
+
+/// Format a Lex document already parsed from `source`, without parsing it again
+pub fn format_parsed_document(
+    doc: &Document,
+    source: &str,
+    rules: &FormattingRulesConfig,
+) -> String {
+    if IgnoreDirectives::collect(doc).ignores_all_formatting() {
+        return source.to_string();
+    }
+    serialize_document_with_source(doc, source, rules)
+}
+
+/// Format a whole Lex document, returning the minimal edits over `source`
+///
+/// Applying the edits to `source` gives [format_document]'s output; an already formatted
+/// document yields no edits.
+pub fn format_document_edits(
+    source: &str,
+    rules: &FormattingRulesConfig,
+) -> Result<Vec<TextEdit>, FormattingError> {
+    let formatted = format_document(source, rules)?;
+    Ok(compute_edits(source, &formatted))
+}
+
+/// Check that `source` is canonically formatted
+///
+/// Returns `None` when it is, else a unified diff of the changes formatting would make,
+/// with `path` in its headers.
+pub fn check_formatting(
+    path: &str,
+    source: &str,
+    rules: &FormattingRulesConfig,
+) -> Result<Option<String>, FormattingError> {
+    let formatted = format_document(source, rules)?;
+    Ok((formatted != source).then(|| unified_diff(path, source, &formatted)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_edits() {
+        let source = "Title\n\n-   First\n- Second\n\n\n\n\nText.\n";
+        let rules = FormattingRulesConfig::default();
+        let edits = format_document_edits(source, &rules).unwrap();
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits[0].range.start.line, 2);
+        let formatted = format_document(source, &rules).unwrap();
+        assert_eq!(apply_edits(source, &edits), formatted);
+        assert!(format_document_edits(&formatted, &rules)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_check_formatting() {
+        let rules = FormattingRulesConfig::default();
+        let diff = check_formatting("notes.lex", "Title\n\n-   Item\n- Other\n", &rules)
+            .unwrap()
+            .unwrap();
+        assert!(diff.starts_with("--- a/notes.lex\n"));
+        assert!(diff.contains("\n+- Item\n"));
+        assert_eq!(
+            check_formatting("notes.lex", "Title\n\n- Item\n- Other\n", &rules).unwrap(),
+            None
+        );
+    }
+}
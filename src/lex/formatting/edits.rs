@@ -0,0 +1,284 @@
+//! Text edits between source revisions
+//!
+//! The formatter produces a whole new document, but editors want the smallest set of
+//! replacements that turn the old text into the new one. This module diffs both texts
+//! line by line and reports each changed run of lines as a `TextEdit` over the original.
+
+use crate::lex::ast::{Range, SourceLocation};
+use std::ops::Range as ByteRange;
+
+/// Above this many cells the line diff falls back to a single replacement
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// A replacement of a range of the original source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    /// Range in the original source being replaced
+    pub range: Range,
+    /// Text to insert in place of `range`
+    pub new_text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range, new_text: String) -> Self {
+        Self { range, new_text }
+    }
+}
+
+/// A run of changed lines: `old` lines are replaced by `new` lines
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineHunk {
+    pub old: ByteRange<usize>,
+    pub new: ByteRange<usize>,
+}
+
+/// Split text into lines, keeping line terminators so trailing newlines count
+pub fn split_lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// Compute the changed line runs between two line sequences
+pub fn diff_lines(old: &[&str], new: &[&str]) -> Vec<LineHunk> {
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_mid = &old[prefix..old.len() - suffix];
+    let new_mid = &new[prefix..new.len() - suffix];
+    if old_mid.is_empty() && new_mid.is_empty() {
+        return Vec::new();
+    }
+    if old_mid.is_empty()
+        || new_mid.is_empty()
+        || old_mid.len().saturating_mul(new_mid.len()) > MAX_DIFF_CELLS
+    {
+        return vec![LineHunk {
+            old: prefix..prefix + old_mid.len(),
+            new: prefix..prefix + new_mid.len(),
+        }];
+    }
+
+    lcs_hunks(old_mid, new_mid)
+        .into_iter()
+        .map(|hunk| LineHunk {
+            old: hunk.old.start + prefix..hunk.old.end + prefix,
+            new: hunk.new.start + prefix..hunk.new.end + prefix,
+        })
+        .collect()
+}
+
+/// Longest-common-subsequence diff, grouping non-matching lines into hunks
+fn lcs_hunks(old: &[&str], new: &[&str]) -> Vec<LineHunk> {
+    let (n, m) = (old.len(), new.len());
+    // table[i][j] = LCS length of old[i..] and new[j..]
+    let mut table = vec![0u32; (n + 1) * (m + 1)];
+    let at = |i: usize, j: usize| i * (m + 1) + j;
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            table[at(i, j)] = if old[i] == new[j] {
+                table[at(i + 1, j + 1)] + 1
+            } else {
+                table[at(i + 1, j)].max(table[at(i, j + 1)])
+            };
+        }
+    }
+
+    let mut hunks = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let mut open: Option<(usize, usize)> = None;
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            if let Some((oi, nj)) = open.take() {
+                hunks.push(LineHunk {
+                    old: oi..i,
+                    new: nj..j,
+                });
+            }
+            i += 1;
+            j += 1;
+            continue;
+        }
+        open.get_or_insert((i, j));
+        if j < m && (i == n || table[at(i, j + 1)] >= table[at(i + 1, j)]) {
+            j += 1;
+        } else {
+            i += 1;
+        }
+    }
+    if let Some((oi, nj)) = open {
+        hunks.push(LineHunk {
+            old: oi..n,
+            new: nj..m,
+        });
+    }
+    hunks
+}
+
+/// Convert line hunks into text edits over `original`
+pub fn hunks_to_edits(original: &str, new_lines: &[&str], hunks: &[LineHunk]) -> Vec<TextEdit> {
+    let locations = SourceLocation::new(original);
+    let line_count = split_lines(original).len();
+    let line_offset = |line: usize| {
+        if line >= line_count {
+            original.len()
+        } else {
+            locations.line_start(line).unwrap_or(original.len())
+        }
+    };
+    hunks
+        .iter()
+        .map(|hunk| {
+            let span = line_offset(hunk.old.start)..line_offset(hunk.old.end);
+            let range = locations.byte_range_to_ast_range(&span);
+            TextEdit::new(range, new_lines[hunk.new.clone()].concat())
+        })
+        .collect()
+}
+
+/// Minimal edits turning `original` into `updated`
+pub fn compute_edits(original: &str, updated: &str) -> Vec<TextEdit> {
+    let old_lines = split_lines(original);
+    let new_lines = split_lines(updated);
+    let hunks = diff_lines(&old_lines, &new_lines);
+    hunks_to_edits(original, &new_lines, &hunks)
+}
+
+/// Unified diff (as `diff -u` prints it) turning `original` into `updated`
+///
+/// `path` names the file in the `---`/`+++` headers. Empty when both texts are equal.
+pub fn unified_diff(path: &str, original: &str, updated: &str) -> String {
+    const CONTEXT: usize = 3;
+    let old_lines = split_lines(original);
+    let new_lines = split_lines(updated);
+    let hunks = diff_lines(&old_lines, &new_lines);
+    if hunks.is_empty() {
+        return String::new();
+    }
+
+    // Merge hunks whose context overlaps into a single block
+    let mut blocks: Vec<Vec<&LineHunk>> = Vec::new();
+    for hunk in &hunks {
+        match blocks.last_mut() {
+            Some(block) if hunk.old.start <= block.last().unwrap().old.end + 2 * CONTEXT => {
+                block.push(hunk)
+            }
+            _ => blocks.push(vec![hunk]),
+        }
+    }
+
+    let push_line = |out: &mut String, prefix: char, line: &str| {
+        out.push(prefix);
+        out.push_str(line);
+        if !line.ends_with('\n') {
+            out.push_str("\n\\ No newline at end of file\n");
+        }
+    };
+
+    let mut out = format!("--- a/{path}\n+++ b/{path}\n");
+    for block in blocks {
+        let first = block[0];
+        let last = block[block.len() - 1];
+        let old_start = first.old.start.saturating_sub(CONTEXT);
+        let old_end = (last.old.end + CONTEXT).min(old_lines.len());
+        // Old and new line numbers only differ inside hunks, so context maps one to one
+        let new_start = first.new.start - (first.old.start - old_start);
+        let new_end = last.new.end + (old_end - last.old.end);
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            old_start + 1,
+            old_end - old_start,
+            new_start + 1,
+            new_end - new_start
+        ));
+
+        let mut cursor = old_start;
+        for hunk in block {
+            for line in &old_lines[cursor..hunk.old.start] {
+                push_line(&mut out, ' ', line);
+            }
+            for line in &old_lines[hunk.old.clone()] {
+                push_line(&mut out, '-', line);
+            }
+            for line in &new_lines[hunk.new.clone()] {
+                push_line(&mut out, '+', line);
+            }
+            cursor = hunk.old.end;
+        }
+        for line in &old_lines[cursor..old_end] {
+            push_line(&mut out, ' ', line);
+        }
+    }
+    out
+}
+
+/// Apply edits (non-overlapping, any order) to `source`
+pub fn apply_edits(source: &str, edits: &[TextEdit]) -> String {
+    let mut sorted: Vec<&TextEdit> = edits.iter().collect();
+    sorted.sort_by_key(|edit| edit.range.span.start);
+    let mut out = String::with_capacity(source.len());
+    let mut cursor = 0;
+    for edit in sorted {
+        out.push_str(&source[cursor..edit.range.span.start]);
+        out.push_str(&edit.new_text);
+        cursor = edit.range.span.end;
+    }
+    out.push_str(&source[cursor..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_texts_have_no_edits() {
+        assert!(compute_edits("a\nb\n", "a\nb\n").is_empty());
+    }
+
+    #[test]
+    fn test_edits_are_minimal_hunks() {
+        let old = "a\nb\nc\nd\ne\n";
+        let new = "a\nB\nc\nd\nE\n";
+        let edits = compute_edits(old, new);
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits[0].range.start.line, 1);
+        assert_eq!(edits[0].new_text, "B\n");
+        assert_eq!(edits[1].range.start.line, 4);
+        assert_eq!(apply_edits(old, &edits), new);
+    }
+
+    #[test]
+    fn test_insertions_and_deletions() {
+        let old = "a\n\n\n\nb\n";
+        let new = "a\n\nb\nc";
+        let edits = compute_edits(old, new);
+        assert_eq!(apply_edits(old, &edits), new);
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let new = "a\nb\nc\nD\ne\nf\ng\nh\ni\nj\n";
+        let diff = unified_diff("doc.lex", old, new);
+        assert_eq!(
+            diff,
+            "--- a/doc.lex\n+++ b/doc.lex\n@@ -1,7 +1,7 @@\n a\n b\n c\n-d\n+D\n e\n f\n g\n"
+        );
+        assert!(unified_diff("doc.lex", old, old).is_empty());
+    }
+
+    #[test]
+    fn test_missing_trailing_newline() {
+        let edits = compute_edits("a\nb", "a\nb\n");
+        assert_eq!(edits.len(), 1);
+        assert_eq!(apply_edits("a\nb", &edits), "a\nb\n");
+    }
+}
@@ -0,0 +1,129 @@
+//! Per-rule explanation of formatting changes
+//!
+//! Runs the rule pipeline one rule at a time: the document is first serialized with every
+//! rule disabled, then each enabled rule is switched on in pipeline order, and the difference
+//! between consecutive outputs is attributed to that rule. This backs `lex fmt --explain`.
+//!
+//! Edits of a rule are expressed over the text as it enters that rule, that is, the output
+//! of the previous step. Changes no rule is responsible for (canonical serialization of
+//! elements, such as closing an annotation block) are not reported. Each step is formatted
+//! as [format_parsed_document] formats, so a file-level `:: lex-ignore ::` leaves every rule
+//! without changes.
+
+use super::edits::{compute_edits, TextEdit};
+use super::rules::{FormattingRule, FormattingRulesConfig};
+use super::{format_parsed_document, FormattingError};
+use crate::lex::parsing::parse_document;
+
+/// The changes a single rule made
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleChanges {
+    pub rule: FormattingRule,
+    pub edits: Vec<TextEdit>,
+}
+
+/// Attribute the changes formatting `source` would make to the rules making them
+pub fn explain(
+    source: &str,
+    rules: &FormattingRulesConfig,
+) -> Result<Vec<RuleChanges>, FormattingError> {
+    let doc = parse_document(source).map_err(FormattingError::ParseError)?;
+
+    let mut step = rules.clone();
+    for rule in FormattingRule::PIPELINE {
+        step.set_enabled(rule, false);
+    }
+    let mut previous = format_parsed_document(&doc, source, &step);
+
+    let mut changes = Vec::new();
+    for rule in rules.enabled_rules() {
+        step.set_enabled(rule, true);
+        let output = format_parsed_document(&doc, source, &step);
+        changes.push(RuleChanges {
+            rule,
+            edits: compute_edits(&previous, &output),
+        });
+        previous = output;
+    }
+    Ok(changes)
+}
+
+/// Human readable report of [explain]'s output, one section per rule
+pub fn render_explanation(changes: &[RuleChanges]) -> String {
+    let mut out = String::new();
+    for change in changes {
+        if change.edits.is_empty() {
+            out.push_str(&format!("{}: no changes\n", change.rule));
+            continue;
+        }
+        let plural = if change.edits.len() == 1 { "" } else { "s" };
+        out.push_str(&format!(
+            "{}: {} change{plural}\n",
+            change.rule,
+            change.edits.len()
+        ));
+        for edit in &change.edits {
+            let removed = edit.range.end.line - edit.range.start.line;
+            let added = edit.new_text.lines().count();
+            out.push_str(&format!(
+                "  line {}: {removed} line(s) replaced by {added}\n",
+                edit.range.start.line + 1
+            ));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::testing::lexplore::Lexplore;
+
+    #[test]
+    fn test_changes_are_attributed_to_rules() {
+        let source = Lexplore::benchmark(10).source();
+        let messy = source
+            .replace("- Item 1 in definition", "1. Item 1 in definition")
+            .replace("- Item 2 in definition", "1. Item 2 in definition")
+            .replace("\n\n    - Followed by", "\n\n\n\n\n    - Followed by");
+        let changes = explain(&messy, &FormattingRulesConfig::default()).unwrap();
+
+        let rules: Vec<FormattingRule> = changes.iter().map(|change| change.rule).collect();
+        assert_eq!(
+            rules,
+            vec![
+                FormattingRule::BlankLines,
+                FormattingRule::Markers,
+                FormattingRule::Indentation,
+                FormattingRule::Tables
+            ]
+        );
+        assert_eq!(changes[0].edits.len(), 1);
+        assert_eq!(changes[1].edits.len(), 1);
+        assert!(changes[1].edits[0]
+            .new_text
+            .contains("2. Item 2 in definition"));
+        assert!(changes[2].edits.is_empty());
+        assert!(changes[3].edits.is_empty());
+    }
+
+    #[test]
+    fn test_file_level_ignore() {
+        let source = ":: lex-ignore ::\n\nTitle\n\n1. Item\n1. Item\n\n\n\nText.\n";
+        let changes = explain(source, &FormattingRulesConfig::default()).unwrap();
+        assert!(!changes.is_empty());
+        assert!(changes.iter().all(|change| change.edits.is_empty()));
+    }
+
+    #[test]
+    fn test_render_explanation() {
+        let source = Lexplore::benchmark(10).source();
+        let messy = source
+            .replace("- Item 1 in definition", "1. Item 1 in definition")
+            .replace("- Item 2 in definition", "1. Item 2 in definition");
+        let changes = explain(&messy, &FormattingRulesConfig::default()).unwrap();
+        let report = render_explanation(&changes);
+        assert!(report.contains("blank-lines: no changes\n"));
+        assert!(report.contains("markers: 1 change\n"));
+    }
+}
@@ -0,0 +1,133 @@
+//! Range formatting
+//!
+//!     Reformats only the block elements enclosing a source range. Editors call this for
+//!     "format selection" and format-on-paste, where touching the rest of the document would
+//!     be surprising.
+//!
+//!     The enclosing blocks are found by descending the AST: while the range sits inside the
+//!     body of a single container, we look at that container's children instead. The lines
+//!     spanned by the blocks overlapping the range at that level form the formatting window.
+//!     The whole document is then formatted from the same parse, and only the edits touching
+//!     the window are kept, which keeps the output consistent with full-document formatting.
+
+use super::edits::{diff_lines, hunks_to_edits, split_lines, TextEdit};
+use super::rules::FormattingRulesConfig;
+use super::{format_parsed_document, FormattingError};
+use crate::lex::ast::traits::AstNode;
+use crate::lex::ast::{ContentItem, Range};
+use crate::lex::parsing::parse_document;
+
+/// Format the block elements enclosing `range`, returning edits over `source`
+pub fn format_range(
+    source: &str,
+    range: &Range,
+    rules: &FormattingRulesConfig,
+) -> Result<Vec<TextEdit>, FormattingError> {
+    let doc = parse_document(source).map_err(FormattingError::ParseError)?;
+    let formatted = format_parsed_document(&doc, source, rules);
+
+    let requested = (range.start.line, range.end.line.max(range.start.line));
+    let items: Vec<&ContentItem> = doc.root.children.iter().collect();
+    let (first, last) = enclosing_lines(&items, requested).unwrap_or(requested);
+
+    let old_lines = split_lines(source);
+    let new_lines = split_lines(&formatted);
+    let hunks: Vec<_> = diff_lines(&old_lines, &new_lines)
+        .into_iter()
+        .filter(|hunk| {
+            if hunk.old.is_empty() {
+                // Pure insertions touch the window when they land inside or right after it
+                hunk.old.start >= first && hunk.old.start <= last + 1
+            } else {
+                hunk.old.start <= last && hunk.old.end > first
+            }
+        })
+        .collect();
+
+    Ok(hunks_to_edits(source, &new_lines, &hunks))
+}
+
+/// Line span (inclusive) of the blocks enclosing `lines`, descending into single containers
+fn enclosing_lines(items: &[&ContentItem], lines: (usize, usize)) -> Option<(usize, usize)> {
+    let overlapping: Vec<&ContentItem> = items
+        .iter()
+        .copied()
+        .filter(|item| !item.is_blank_line_group())
+        .filter(|item| {
+            let range = item.range();
+            range.start.line <= lines.1 && range.end.line >= lines.0
+        })
+        .collect();
+
+    if let [single] = overlapping.as_slice() {
+        if let Some(body) = body_location(single) {
+            if body.start.line <= lines.0 && lines.1 <= body.end.line {
+                let children: Vec<&ContentItem> = single.children().unwrap_or(&[]).iter().collect();
+                if let Some(inner) = enclosing_lines(&children, lines) {
+                    return Some(inner);
+                }
+            }
+        }
+    }
+
+    let first = overlapping
+        .iter()
+        .map(|item| item.range().start.line)
+        .min()?;
+    let last = overlapping.iter().map(|item| item.range().end.line).max()?;
+    Some((first.min(lines.0), last.max(lines.1)))
+}
+
+fn body_location(item: &ContentItem) -> Option<Range> {
+    match item {
+        ContentItem::Session(session) => session.body_location(),
+        ContentItem::Definition(definition) => definition.body_location(),
+        ContentItem::Annotation(annotation) => annotation.body_location(),
+        ContentItem::List(list) => list.body_location(),
+        ContentItem::ListItem(list_item) => {
+            Range::bounding_box(list_item.children.iter().map(|child| child.range()))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::ast::Position;
+    use crate::lex::formatting::edits::apply_edits;
+    use crate::lex::testing::lexplore::Lexplore;
+
+    fn line_range(start: usize, end: usize) -> Range {
+        Range::new(0..0, Position::new(start, 0), Position::new(end, 0))
+    }
+
+    #[test]
+    fn test_range_outside_changes_yields_no_edits() {
+        let source = Lexplore::benchmark(10).source();
+        let messy = source.replace("- This list has two items.", "-   This list has two items.");
+        let rules = FormattingRulesConfig::default();
+        // First paragraph of the document: nothing to fix there
+        let edits = format_range(&messy, &line_range(2, 2), &rules).unwrap();
+        assert!(edits.is_empty());
+    }
+
+    #[test]
+    fn test_range_only_touches_enclosing_block() {
+        let source = Lexplore::benchmark(10).source();
+        let messy = source
+            .replace("- Item 2 in definition", "-  Item 2 in definition")
+            .replace("- This list has two items.", "-   This list has two items.");
+        let rules = FormattingRulesConfig::default();
+        let line = messy
+            .lines()
+            .position(|line| line.contains("Item 2 in definition"))
+            .unwrap();
+
+        let edits = format_range(&messy, &line_range(line, line), &rules).unwrap();
+        assert_eq!(edits.len(), 1);
+        let result = apply_edits(&messy, &edits);
+        assert!(result.contains("    - Item 2 in definition"));
+        assert!(result.contains("-   This list has two items."));
+    }
+}
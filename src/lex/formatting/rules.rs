@@ -0,0 +1,184 @@
+//! Formatting rules configuration
+//!
+//! The formatter is an ordered pipeline of named rules, each of which can be turned on or
+//! off in `FormattingRulesConfig`. A disabled rule leaves its aspect of the source as written:
+//! with `indentation` off, lines keep their original leading whitespace; with `markers` off,
+//! list markers are kept verbatim, and so on.
+//!
+//! The defaults describe canonical Lex: four-space indentation, at most two consecutive blank
+//! lines, list markers normalized to the first item's style and table columns aligned.
+//! Wrapping is opt-in, as it changes paragraph text layout.
+
+use crate::lex::tables::ColumnAlignment;
+use std::fmt;
+
+/// A named formatting rule, in pipeline order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FormattingRule {
+    /// Cap runs of consecutive blank lines
+    BlankLines,
+    /// Renumber list items and normalize marker spacing
+    Markers,
+    /// Re-indent every nesting level with the configured indentation string
+    Indentation,
+    /// Align the columns of table blocks (verbatim blocks labeled `table`)
+    Tables,
+    /// Break paragraph lines longer than the configured width
+    Wrapping,
+}
+
+impl FormattingRule {
+    /// All rules, in the order the pipeline applies them
+    pub const PIPELINE: [FormattingRule; 5] = [
+        FormattingRule::BlankLines,
+        FormattingRule::Markers,
+        FormattingRule::Indentation,
+        FormattingRule::Tables,
+        FormattingRule::Wrapping,
+    ];
+
+    /// Stable rule name, as used in configuration and reports
+    pub fn name(&self) -> &'static str {
+        match self {
+            FormattingRule::BlankLines => "blank-lines",
+            FormattingRule::Markers => "markers",
+            FormattingRule::Indentation => "indentation",
+            FormattingRule::Tables => "tables",
+            FormattingRule::Wrapping => "wrapping",
+        }
+    }
+
+    /// Look up a rule by its name
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::PIPELINE.into_iter().find(|rule| rule.name() == name)
+    }
+}
+
+impl fmt::Display for FormattingRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Configuration for the Lex formatter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormattingRulesConfig {
+    /// Enable the `blank-lines` rule
+    pub blank_lines: bool,
+    /// Enable the `markers` rule
+    pub markers: bool,
+    /// Enable the `indentation` rule
+    pub indentation: bool,
+    /// Enable the `tables` rule
+    pub tables: bool,
+    /// Enable the `wrapping` rule
+    pub wrapping: bool,
+    /// String emitted once per indentation level
+    pub indent_string: String,
+    /// Maximum number of consecutive blank lines kept in the output (never below 1,
+    /// as blank lines are structural for sessions and lists)
+    pub max_blank_lines: usize,
+    /// Maximum line width, including indentation, for the `wrapping` rule
+    pub max_line_width: usize,
+    /// Alignment of cells within table columns, for the `tables` rule
+    pub table_alignment: ColumnAlignment,
+    /// Width table columns are padded to at most, for the `tables` rule
+    pub max_column_width: usize,
+}
+
+impl FormattingRulesConfig {
+    /// Whether `rule` is enabled
+    pub fn is_enabled(&self, rule: FormattingRule) -> bool {
+        match rule {
+            FormattingRule::BlankLines => self.blank_lines,
+            FormattingRule::Markers => self.markers,
+            FormattingRule::Indentation => self.indentation,
+            FormattingRule::Tables => self.tables,
+            FormattingRule::Wrapping => self.wrapping,
+        }
+    }
+
+    /// Enable or disable `rule`
+    pub fn set_enabled(&mut self, rule: FormattingRule, enabled: bool) {
+        match rule {
+            FormattingRule::BlankLines => self.blank_lines = enabled,
+            FormattingRule::Markers => self.markers = enabled,
+            FormattingRule::Indentation => self.indentation = enabled,
+            FormattingRule::Tables => self.tables = enabled,
+            FormattingRule::Wrapping => self.wrapping = enabled,
+        }
+    }
+
+    /// Builder-style variant of [set_enabled](Self::set_enabled)
+    pub fn with_rule(mut self, rule: FormattingRule, enabled: bool) -> Self {
+        self.set_enabled(rule, enabled);
+        self
+    }
+
+    /// Enabled rules, in pipeline order
+    pub fn enabled_rules(&self) -> Vec<FormattingRule> {
+        FormattingRule::PIPELINE
+            .into_iter()
+            .filter(|rule| self.is_enabled(*rule))
+            .collect()
+    }
+
+    /// Effective blank line cap, clamped so structural blank lines are never dropped
+    ///
+    /// Unbounded when the `blank-lines` rule is disabled.
+    pub fn blank_line_cap(&self) -> usize {
+        if self.blank_lines {
+            self.max_blank_lines.max(1)
+        } else {
+            usize::MAX
+        }
+    }
+}
+
+impl Default for FormattingRulesConfig {
+    fn default() -> Self {
+        Self {
+            blank_lines: true,
+            markers: true,
+            indentation: true,
+            tables: true,
+            wrapping: false,
+            indent_string: "    ".to_string(),
+            max_blank_lines: 2,
+            max_line_width: 80,
+            table_alignment: ColumnAlignment::Left,
+            max_column_width: 40,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_names_round_trip() {
+        for rule in FormattingRule::PIPELINE {
+            assert_eq!(FormattingRule::from_name(rule.name()), Some(rule));
+        }
+        assert_eq!(FormattingRule::from_name("spelling"), None);
+    }
+
+    #[test]
+    fn test_toggling_rules() {
+        let rules = FormattingRulesConfig::default()
+            .with_rule(FormattingRule::Markers, false)
+            .with_rule(FormattingRule::Wrapping, true);
+        assert_eq!(
+            rules.enabled_rules(),
+            vec![
+                FormattingRule::BlankLines,
+                FormattingRule::Indentation,
+                FormattingRule::Tables,
+                FormattingRule::Wrapping
+            ]
+        );
+        let rules = rules.with_rule(FormattingRule::BlankLines, false);
+        assert_eq!(rules.blank_line_cap(), usize::MAX);
+    }
+}
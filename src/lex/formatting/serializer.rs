@@ -0,0 +1,1092 @@
+//! AST to Lex source serializer
+//!
+//!     Walks a Document and re-emits it as canonical Lex source: one indentation string per
+//!     nesting level, blank line runs capped, list markers normalized and annotations placed
+//!     back where they were found in the source. Each of these normalizations belongs to a
+//!     [FormattingRule](super::rules::FormattingRule) and is only applied when the rule is
+//!     enabled.
+//!
+//!     Keeping the original indentation when the indentation rule is off requires the source
+//!     text, see [serialize_document_with_source]. Without it, canonical indentation is used.
+//!
+//!     `lex-ignore` directives (see [ignore](crate::lex::annotation::ignore)) switch rules off
+//!     for the elements they are attached to; naming `fmt`, or no rule at all, keeps the
+//!     element's source lines untouched. The blank-lines rule can only be ignored file-wide.
+//!
+//!     Annotations are attached to nodes during assembly, so they are no longer part of the
+//!     children they were parsed from. The serializer re-interleaves them with their siblings
+//!     using their source locations; annotations without a location (built programmatically)
+//!     are emitted right before the node they are attached to.
+
+use super::rules::{FormattingRule, FormattingRulesConfig};
+use super::wrapping::wrap_line;
+use crate::lex::annotation::ignore::{IgnoreDirectives, RuleKind};
+use crate::lex::ast::elements::sequence_marker::{DecorationStyle, Form, Separator};
+use crate::lex::ast::elements::verbatim::VerbatimBlockMode;
+use crate::lex::ast::elements::SequenceMarker;
+use crate::lex::ast::traits::AstNode;
+use crate::lex::ast::{
+    Annotation, ContentItem, Data, Definition, Document, List, ListItem, Paragraph, Range, Session,
+    TextContent, Verbatim,
+};
+use crate::lex::tables::{is_pipe_row, render_rows, split_row, TABLE_LABEL};
+
+/// Serialize a document to canonical Lex source
+pub fn serialize_document(doc: &Document, rules: &FormattingRulesConfig) -> String {
+    let mut serializer = LexSerializer::new(rules);
+    serializer.document(doc);
+    serializer.finish()
+}
+
+/// Serialize a document parsed from `source`
+///
+/// The source lets disabled rules keep what was written, such as each line's indentation.
+pub fn serialize_document_with_source(
+    doc: &Document,
+    source: &str,
+    rules: &FormattingRulesConfig,
+) -> String {
+    let mut serializer = LexSerializer::new(rules).with_source(source);
+    serializer.document(doc);
+    serializer.finish()
+}
+
+/// Serialize a run of content items, as found in a container at `depth`
+pub fn serialize_items(
+    items: &[ContentItem],
+    depth: usize,
+    rules: &FormattingRulesConfig,
+) -> String {
+    let mut serializer = LexSerializer::new(rules);
+    serializer.items(items, &[], depth);
+    serializer.finish()
+}
+
+/// Line-oriented writer that owns the blank line policy
+struct LineWriter {
+    lines: Vec<String>,
+    pending_blanks: usize,
+    cap: usize,
+}
+
+impl LineWriter {
+    fn new(cap: usize) -> Self {
+        Self {
+            lines: Vec::new(),
+            pending_blanks: 0,
+            cap,
+        }
+    }
+
+    /// Request at least `count` blank lines before the next line
+    fn blanks(&mut self, count: usize) {
+        self.pending_blanks = self.pending_blanks.max(count);
+    }
+
+    fn flush_blanks(&mut self) {
+        for _ in 0..self.pending_blanks.min(self.cap) {
+            self.lines.push(String::new());
+        }
+        self.pending_blanks = 0;
+    }
+
+    /// Emit a Lex line (trailing whitespace is not significant and dropped)
+    fn line(&mut self, text: String) {
+        self.flush_blanks();
+        self.lines.push(text.trim_end().to_string());
+    }
+
+    /// Emit a line verbatim
+    fn raw(&mut self, text: String) {
+        self.flush_blanks();
+        self.lines.push(text);
+    }
+
+    fn finish(self) -> String {
+        if self.lines.is_empty() {
+            return String::new();
+        }
+        let mut out = self.lines.join("\n");
+        out.push('\n');
+        out
+    }
+}
+
+/// An entry in a container's emission order
+#[derive(Clone, Copy)]
+pub(crate) enum Entry<'d> {
+    Item(&'d ContentItem),
+    Annotation(&'d Annotation),
+}
+
+impl Entry<'_> {
+    /// First and last source lines of the entry, when known
+    ///
+    /// Block annotations don't record their closing marker line, so their end is unknown.
+    pub(crate) fn source_lines(&self) -> (Option<usize>, Option<usize>) {
+        let range = match self {
+            Entry::Item(item) => item.range(),
+            Entry::Annotation(annotation) => {
+                let header = annotation_location(annotation);
+                if *header == Range::default() {
+                    return (None, None);
+                }
+                let line = header.start.line;
+                let is_block = annotation.location.end.line > line;
+                return (Some(line), (!is_block).then_some(line));
+            }
+        };
+        Self::source_lines_of(range)
+    }
+
+    /// First and last source lines of `range`, unless it is unpositioned
+    fn source_lines_of(range: &Range) -> (Option<usize>, Option<usize>) {
+        if *range == Range::default() {
+            return (None, None);
+        }
+        // Ranges are end-exclusive: an end at column 0 closes the previous line
+        let end_line = if range.end.column == 0 && range.end.line > range.start.line {
+            range.end.line - 1
+        } else {
+            range.end.line
+        };
+        (Some(range.start.line), Some(end_line))
+    }
+
+    /// Whether the entry is, or ends with, a verbatim block
+    pub(crate) fn ends_with_verbatim(&self) -> bool {
+        let Entry::Item(mut item) = *self else {
+            return false;
+        };
+        loop {
+            if item.is_verbatim_block() {
+                return true;
+            }
+            let last = item
+                .children()
+                .and_then(|children| children.iter().rev().find(|c| !c.is_blank_line_group()));
+            match last {
+                Some(child) => item = child,
+                None => return false,
+            }
+        }
+    }
+}
+
+struct LexSerializer<'r> {
+    rules: &'r FormattingRulesConfig,
+    source_lines: Vec<&'r str>,
+    /// Non-blank source lines no element was parsed from, in order
+    dropped: Vec<usize>,
+    next_dropped: usize,
+    ignores: IgnoreDirectives,
+    out: LineWriter,
+}
+
+impl<'r> LexSerializer<'r> {
+    fn new(rules: &'r FormattingRulesConfig) -> Self {
+        Self {
+            rules,
+            source_lines: Vec::new(),
+            dropped: Vec::new(),
+            next_dropped: 0,
+            ignores: IgnoreDirectives::default(),
+            out: LineWriter::new(rules.blank_line_cap()),
+        }
+    }
+
+    fn with_source(mut self, source: &'r str) -> Self {
+        self.source_lines = source.lines().collect();
+        self
+    }
+
+    fn finish(self) -> String {
+        self.out.finish()
+    }
+
+    fn indent(&self, depth: usize) -> String {
+        self.rules.indent_string.repeat(depth)
+    }
+
+    /// Indentation for a line emitted at `depth` from source line `line`
+    ///
+    /// With the indentation rule disabled, the source line's own leading whitespace is kept
+    /// when it is known.
+    fn indent_at(&self, depth: usize, line: Option<usize>) -> String {
+        if let Some(line) = line.filter(|line| !self.applies(FormattingRule::Indentation, *line)) {
+            if let Some(text) = self.source_lines.get(line) {
+                return text[..text.len() - text.trim_start().len()].to_string();
+            }
+        }
+        self.indent(depth)
+    }
+
+    /// Whether `rule` is enabled and not ignored at source line `line`
+    fn applies(&self, rule: FormattingRule, line: usize) -> bool {
+        self.rules.is_enabled(rule)
+            && !self
+                .ignores
+                .ignores_line(rule.name(), RuleKind::Format, line)
+    }
+
+    /// Emit an element's source lines as written, if formatting is ignored for it
+    fn emit_if_ignored(&mut self, item: &ContentItem) -> bool {
+        let (Some(start), Some(end)) = Entry::Item(item).source_lines() else {
+            return false;
+        };
+        if self.source_lines.is_empty() || !self.ignores.ignores_formatting_at(start) {
+            return false;
+        }
+        let lines = &self.source_lines
+            [start.min(self.source_lines.len())..(end + 1).min(self.source_lines.len())];
+        let trailing_blanks = lines
+            .iter()
+            .rev()
+            .take_while(|line| line.trim().is_empty())
+            .count();
+        for line in &lines[..lines.len() - trailing_blanks] {
+            self.out.raw(line.to_string());
+        }
+        while self
+            .dropped
+            .get(self.next_dropped)
+            .is_some_and(|line| *line <= end)
+        {
+            self.next_dropped += 1;
+        }
+        self.out.blanks(trailing_blanks);
+        true
+    }
+
+    fn document(&mut self, doc: &Document) {
+        self.ignores = IgnoreDirectives::collect(doc);
+        self.dropped = dropped_lines(doc, &self.source_lines);
+        let (leading, trailing) = document_annotations(doc);
+
+        for annotation in &leading {
+            self.annotation(annotation, 0);
+        }
+        if !leading.is_empty() {
+            self.out.blanks(1);
+        }
+        if !doc.root.title.is_empty() {
+            self.out.line(doc.root.title.as_string().to_string());
+            self.out.blanks(1);
+        }
+        let items: Vec<ContentItem> = doc.root.children.iter().cloned().collect();
+        let mut inner = trailing;
+        inner.extend(doc.root.annotations.iter());
+        self.items(&items, &inner, 0);
+        self.emit_dropped_lines(usize::MAX, &mut 0);
+    }
+
+    /// Emit a container's children, interleaving attached annotations in source order
+    ///
+    /// Some grammar rules (verbatim blocks, notably) consume the blank lines around them
+    /// without producing blank line groups, so gaps between siblings' source lines are
+    /// honored as blank lines as well.
+    ///
+    /// A paragraph directly followed by another element only parses that way when lines the
+    /// parser dropped sit between them; unless those lines are copied from the source, a
+    /// blank line keeps the element from being read as more paragraph text.
+    fn items(&mut self, items: &[ContentItem], inner: &[&Annotation], depth: usize) {
+        let entries = ordered_entries(items, inner);
+        let mut pending_blank_lines = 0;
+        let mut previous_end: Option<usize> = None;
+        let mut previous_verbatim = false;
+        let mut previous_paragraph = false;
+        for (index, entry) in entries.iter().enumerate() {
+            let (start, end) = entry.source_lines();
+            let dropped =
+                start.and_then(|start| self.emit_dropped_lines(start, &mut pending_blank_lines));
+            if dropped.is_some() {
+                previous_end = dropped;
+                previous_paragraph = false;
+            }
+            if let Entry::Item(ContentItem::BlankLineGroup(group)) = entry {
+                pending_blank_lines += group.count.max(1);
+                continue;
+            }
+            let touches_verbatim = previous_verbatim || entry.ends_with_verbatim();
+            if let (Some(previous_end), Some(start), true) = (previous_end, start, touches_verbatim)
+            {
+                pending_blank_lines =
+                    pending_blank_lines.max(start.saturating_sub(previous_end + 1));
+            }
+            if previous_paragraph && matches!(entry, Entry::Item(item) if !item.is_paragraph()) {
+                pending_blank_lines = pending_blank_lines.max(1);
+            }
+            self.out.blanks(pending_blank_lines);
+            match entry {
+                Entry::Item(item @ ContentItem::Definition(definition)) => {
+                    if !self.emit_if_ignored(item) {
+                        let closed = closed_by_annotation(&entries[index + 1..]);
+                        self.definition(definition, depth, !closed);
+                    }
+                }
+                Entry::Item(item) => {
+                    if !self.emit_if_ignored(item) {
+                        self.item(item, depth)
+                    }
+                }
+                Entry::Annotation(annotation) => self.annotation(annotation, depth),
+            }
+            pending_blank_lines = 0;
+            previous_end = end;
+            previous_verbatim = entry.ends_with_verbatim();
+            previous_paragraph = matches!(entry, Entry::Item(item) if item.is_paragraph());
+        }
+        self.out.blanks(pending_blank_lines);
+    }
+
+    /// Copy the source lines the parser dropped before line `before`, as written
+    ///
+    /// Returns the last line copied, if any.
+    fn emit_dropped_lines(
+        &mut self,
+        before: usize,
+        pending_blank_lines: &mut usize,
+    ) -> Option<usize> {
+        let mut last = None;
+        while let Some(&line) = self.dropped.get(self.next_dropped) {
+            if line >= before {
+                break;
+            }
+            self.out.blanks(std::mem::take(pending_blank_lines));
+            self.out.raw(self.source_lines[line].to_string());
+            self.next_dropped += 1;
+            last = Some(line);
+        }
+        last
+    }
+
+    fn item(&mut self, item: &ContentItem, depth: usize) {
+        match item {
+            ContentItem::Paragraph(paragraph) => self.paragraph(paragraph, depth),
+            ContentItem::Session(session) => self.session(session, depth),
+            ContentItem::List(list) => self.list(list, depth),
+            ContentItem::ListItem(list_item) => self.list_item(list_item, None, depth),
+            ContentItem::Definition(definition) => self.definition(definition, depth, true),
+            ContentItem::Annotation(annotation) => self.annotation(annotation, depth),
+            ContentItem::VerbatimBlock(verbatim) => self.verbatim(verbatim, depth),
+            ContentItem::TextLine(line) => {
+                let indent = self.indent_at(depth, Some(line.location.start.line));
+                self.out.line(format!("{indent}{}", line.text()))
+            }
+            ContentItem::VerbatimLine(line) => self.out.raw(format!(
+                "{}{}",
+                self.indent(depth),
+                line.content.as_string()
+            )),
+            ContentItem::BlankLineGroup(group) => self.out.blanks(group.count),
+        }
+    }
+
+    fn paragraph(&mut self, paragraph: &Paragraph, depth: usize) {
+        for line in &paragraph.lines {
+            if let ContentItem::TextLine(text_line) = line {
+                let line_number = text_line.location.start.line;
+                let indent = self.indent_at(depth, Some(line_number));
+                if !self.applies(FormattingRule::Wrapping, line_number) {
+                    self.out.line(format!("{indent}{}", text_line.text()));
+                    continue;
+                }
+                let width = self
+                    .rules
+                    .max_line_width
+                    .saturating_sub(indent.chars().count());
+                for wrapped in wrap_line(text_line.text(), width) {
+                    self.out.line(format!("{indent}{wrapped}"));
+                }
+            }
+        }
+    }
+
+    fn session(&mut self, session: &Session, depth: usize) {
+        let indent = self.indent_at(depth, Some(session.location.start.line));
+        self.out
+            .line(format!("{indent}{}", session.title.as_string()));
+        let items: Vec<ContentItem> = session.children.iter().cloned().collect();
+        let inner = inner_annotations(&session.annotations, &session.location);
+        if items.is_empty() && inner.is_empty() {
+            return;
+        }
+        self.out.blanks(1);
+        self.items(&items, &inner, depth + 1);
+    }
+
+    /// Emit a definition; its subject's colon is optional, and left out when `colon` is false
+    fn definition(&mut self, definition: &Definition, depth: usize, colon: bool) {
+        let indent = self.indent_at(depth, Some(definition.location.start.line));
+        let colon = if colon { ":" } else { "" };
+        self.out.line(format!(
+            "{indent}{}{colon}",
+            subject_text(definition.subject.as_string())
+        ));
+        let items: Vec<ContentItem> = definition.children.iter().cloned().collect();
+        let inner = inner_annotations(&definition.annotations, &definition.location);
+        self.items(&items, &inner, depth + 1);
+    }
+
+    fn list(&mut self, list: &List, depth: usize) {
+        let normalize = self.applies(FormattingRule::Markers, list.range().start.line);
+        let mut index = 0;
+        for entry in ordered_entries(&list.items.iter().cloned().collect::<Vec<_>>(), &[]) {
+            match entry {
+                Entry::Item(ContentItem::ListItem(list_item)) => {
+                    let marker = if normalize {
+                        list.marker
+                            .as_ref()
+                            .and_then(|first| normalized_marker(first, index))
+                    } else {
+                        None
+                    };
+                    self.list_item(list_item, marker, depth);
+                    index += 1;
+                }
+                Entry::Item(item) => self.item(item, depth),
+                Entry::Annotation(annotation) => self.annotation(annotation, depth),
+            }
+        }
+    }
+
+    fn list_item(&mut self, list_item: &ListItem, marker: Option<String>, depth: usize) {
+        let marker = marker.unwrap_or_else(|| list_item.marker().to_string());
+        let text = list_item
+            .text
+            .iter()
+            .map(|text| text.as_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        // The markers rule collapses the spacing between marker and text to a single space
+        let text = if self.applies(FormattingRule::Markers, list_item.location.start.line) {
+            text.trim_start()
+        } else {
+            &text
+        };
+        let indent = self.indent_at(depth, Some(list_item.location.start.line));
+        self.out.line(format!("{indent}{marker} {text}"));
+        let items: Vec<ContentItem> = list_item.children.iter().cloned().collect();
+        let inner = inner_annotations(&list_item.annotations, &list_item.location);
+        self.items(&items, &inner, depth + 1);
+    }
+
+    fn annotation(&mut self, annotation: &Annotation, depth: usize) {
+        let location = annotation_location(annotation);
+        let line = (*location != Range::default()).then_some(location.start.line);
+        let indent = self.indent_at(depth, line);
+        let header = format!("{indent}{} ::", data_header(&annotation.data));
+        // Marker-form annotations may carry an empty placeholder paragraph
+        let children: Vec<ContentItem> = annotation
+            .children
+            .iter()
+            .filter(|child| child.as_paragraph().is_none_or(|p| !p.text().is_empty()))
+            .cloned()
+            .collect();
+
+        if children.is_empty() {
+            self.out.line(header);
+            return;
+        }
+
+        if let [ContentItem::Paragraph(paragraph)] = children.as_slice() {
+            let same_line = paragraph.location == Range::default()
+                || paragraph.location.start.line == annotation.data.location.start.line;
+            if paragraph.lines.len() == 1 && same_line {
+                self.out
+                    .line(format!("{header} {}", paragraph.text().trim_start()));
+                return;
+            }
+        }
+
+        self.out.line(header);
+        self.items(&children, &[], depth + 1);
+        self.out.line(format!("{indent}::"));
+    }
+
+    fn verbatim(&mut self, verbatim: &Verbatim, depth: usize) {
+        for group in verbatim.group() {
+            let subject_line = group
+                .subject
+                .location
+                .as_ref()
+                .map(|range| range.start.line);
+            let indent = self.indent_at(depth, subject_line);
+            let subject = group.subject.as_string().trim_end();
+            if subject.ends_with(':') {
+                // The parser keeps the colon of a subject line ending in whitespace
+                self.out.raw(format!("{indent}{subject} "));
+            } else {
+                self.out.line(format!("{indent}{subject}:"));
+            }
+            // Content keeps its source lines exactly when the subject keeps its indentation
+            let keep_source = !self.source_lines.is_empty()
+                && subject_line
+                    .is_some_and(|line| !self.applies(FormattingRule::Indentation, line));
+
+            // Content is stored without leading whitespace: recover each line's indentation
+            // past the wall from its column, and re-emit it past the canonical wall.
+            let lines: Vec<&ContentItem> = group.children.iter().collect();
+            let (wall, content_indent) = match verbatim.mode {
+                VerbatimBlockMode::Inflow => {
+                    let wall = lines
+                        .iter()
+                        .filter(|line| {
+                            line.as_verbatim_line()
+                                .is_some_and(|line| !line.content.as_string().is_empty())
+                        })
+                        .map(|line| line.range().start.column)
+                        .min()
+                        .unwrap_or(0);
+                    (wall, self.indent(depth + 1))
+                }
+                VerbatimBlockMode::Fullwidth => (1, " ".to_string()),
+            };
+
+            let mut table = self
+                .aligned_table(verbatim, &lines, subject_line)
+                .into_iter();
+            for line in lines {
+                if let Some(line) = line.as_verbatim_line() {
+                    let text = line.content.as_string();
+                    let original = keep_source
+                        .then(|| self.source_lines.get(line.location.start.line))
+                        .flatten();
+                    if text.is_empty() {
+                        self.out.raw(String::new());
+                    } else if let Some(row) = table.next() {
+                        let indent = original.map_or(content_indent.as_str(), |original| {
+                            &original[..original.len() - original.trim_start().len()]
+                        });
+                        self.out.line(format!("{indent}{row}"));
+                    } else if let Some(original) = original {
+                        self.out.raw(original.to_string());
+                    } else {
+                        // Trailing whitespace is content in verbatim lines
+                        let extra = line.location.start.column.saturating_sub(wall);
+                        self.out
+                            .raw(format!("{content_indent}{}{text}", " ".repeat(extra)));
+                    }
+                }
+            }
+        }
+        let closing_line = verbatim.closing_data.location.start.line;
+        let indent = self.indent_at(depth, Some(closing_line));
+        self.out
+            .line(format!("{indent}{}", data_header(&verbatim.closing_data)));
+    }
+
+    /// Aligned rows of a table block's non-empty lines, if the `tables` rule applies to it
+    ///
+    /// Only inflow blocks whose every line is a `| a | b |` row are aligned: fullwidth blocks
+    /// and free-form tables are kept as written.
+    fn aligned_table(
+        &self,
+        verbatim: &Verbatim,
+        lines: &[&ContentItem],
+        subject_line: Option<usize>,
+    ) -> Vec<String> {
+        let applies = subject_line.map_or(self.rules.tables, |line| {
+            self.applies(FormattingRule::Tables, line)
+        });
+        if !applies
+            || verbatim.closing_data.label.value != TABLE_LABEL
+            || verbatim.mode == VerbatimBlockMode::Fullwidth
+        {
+            return Vec::new();
+        }
+        let texts: Vec<&str> = lines
+            .iter()
+            .filter_map(|line| line.as_verbatim_line())
+            .map(|line| line.content.as_string().trim())
+            .filter(|text| !text.is_empty())
+            .collect();
+        if !texts.iter().all(|text| is_pipe_row(text)) {
+            return Vec::new();
+        }
+        let rows: Vec<Vec<String>> = texts.into_iter().map(split_row).collect();
+        render_rows(
+            &rows,
+            self.rules.table_alignment,
+            self.rules.max_column_width,
+        )
+    }
+}
+
+/// Document-level annotations written before the title, and those kept after the content
+///
+/// Document-level annotations come from the document start, but also from its end (when the
+/// last element is followed by an annotation), where they stay.
+pub(crate) fn document_annotations(doc: &Document) -> (Vec<&Annotation>, Vec<&Annotation>) {
+    let content_start = doc
+        .root
+        .title
+        .location
+        .as_ref()
+        .or_else(|| {
+            doc.root
+                .children
+                .iter()
+                .map(|item| item.range())
+                .find(|range| **range != Range::default())
+        })
+        .map(|range| range.start.line);
+    doc.annotations.iter().partition(|annotation| {
+        let location = annotation_location(annotation);
+        *location == Range::default() || content_start.is_none_or(|line| location.start.line < line)
+    })
+}
+
+/// Annotations attached to a container node that sit inside its body in the source
+pub(crate) fn inner_annotations<'d>(
+    annotations: &'d [Annotation],
+    host: &Range,
+) -> Vec<&'d Annotation> {
+    annotations
+        .iter()
+        .filter(|annotation| is_inside(annotation, host))
+        .collect()
+}
+
+fn is_inside(annotation: &Annotation, host: &Range) -> bool {
+    let location = annotation_location(annotation);
+    *host != Range::default()
+        && *location != Range::default()
+        && location.start.line > host.start.line
+        && location.start.line <= host.end.line
+}
+
+/// Source position of an annotation, taken from its header
+///
+/// The annotation's own range is a bounding box over its children, which may include
+/// placeholder nodes without a location; the header data is always positioned.
+pub(crate) fn annotation_location(annotation: &Annotation) -> &Range {
+    if annotation.data.location != Range::default() {
+        &annotation.data.location
+    } else {
+        &annotation.location
+    }
+}
+
+/// Annotations attached to `item` that are emitted before or around it (not inside its body)
+fn outer_annotations(item: &ContentItem) -> Vec<&Annotation> {
+    let (annotations, has_body): (&[Annotation], bool) = match item {
+        ContentItem::Paragraph(paragraph) => (&paragraph.annotations, false),
+        ContentItem::Session(session) => (&session.annotations, true),
+        ContentItem::List(list) => (&list.annotations, false),
+        ContentItem::ListItem(list_item) => (&list_item.annotations, true),
+        ContentItem::Definition(definition) => (&definition.annotations, true),
+        ContentItem::VerbatimBlock(verbatim) => (&verbatim.annotations, false),
+        _ => (&[], false),
+    };
+    annotations
+        .iter()
+        .filter(|annotation| !(has_body && is_inside(annotation, item.range())))
+        .collect()
+}
+
+/// Interleave items and their attached annotations in source order
+pub(crate) fn ordered_entries<'d>(
+    items: &'d [ContentItem],
+    inner: &[&'d Annotation],
+) -> Vec<Entry<'d>> {
+    let mut slots: Vec<Vec<Entry<'d>>> = (0..=items.len()).map(|_| Vec::new()).collect();
+
+    let slot_for = |annotation: &Annotation| -> usize {
+        let location = annotation_location(annotation);
+        let key = (location.start.line, location.start.column);
+        items
+            .iter()
+            .position(|item| {
+                let range = item.range();
+                *range != Range::default() && (range.start.line, range.start.column) > key
+            })
+            .unwrap_or(items.len())
+    };
+
+    for (index, item) in items.iter().enumerate() {
+        for annotation in outer_annotations(item) {
+            let slot = if *annotation_location(annotation) == Range::default() {
+                index
+            } else {
+                slot_for(annotation)
+            };
+            slots[slot].push(Entry::Annotation(annotation));
+        }
+    }
+    for annotation in inner {
+        let slot = if *annotation_location(annotation) == Range::default() {
+            items.len()
+        } else {
+            slot_for(annotation)
+        };
+        slots[slot].push(Entry::Annotation(annotation));
+    }
+
+    let mut entries = Vec::with_capacity(items.len());
+    for (index, slot) in slots.into_iter().enumerate() {
+        entries.extend(slot);
+        if let Some(item) = items.get(index) {
+            entries.push(Entry::Item(item));
+        }
+    }
+    entries
+}
+
+/// Whether the next entry, past blank lines, is an annotation
+///
+/// A subject with a colon followed by indented content and an annotation is a verbatim
+/// block, so a definition in that position was written without its colon.
+fn closed_by_annotation(following: &[Entry]) -> bool {
+    following
+        .iter()
+        .find(|entry| !matches!(entry, Entry::Item(ContentItem::BlankLineGroup(_))))
+        .is_some_and(|entry| matches!(entry, Entry::Annotation(_)))
+}
+
+/// Non-blank source lines that are not part of any element of `doc`, in order
+///
+/// The parser skips a few malformed constructs (stray indented lines after a paragraph,
+/// notably); the serializer copies these lines as written rather than losing them.
+fn dropped_lines(doc: &Document, source_lines: &[&str]) -> Vec<usize> {
+    if source_lines.is_empty() {
+        return Vec::new();
+    }
+    let mut covered = vec![false; source_lines.len()];
+    if let Some(location) = &doc.root.title.location {
+        cover(&mut covered, location);
+    }
+    for annotation in doc.annotations.iter().chain(&doc.root.annotations) {
+        cover_annotation(&mut covered, annotation, source_lines);
+    }
+    for item in doc.root.children.iter() {
+        cover_item(&mut covered, item, source_lines);
+    }
+    (0..source_lines.len())
+        .filter(|line| !covered[*line] && !source_lines[*line].trim().is_empty())
+        .collect()
+}
+
+/// Mark the lines of `range` as covered
+fn cover(covered: &mut [bool], range: &Range) {
+    if let (Some(start), Some(end)) = Entry::source_lines_of(range) {
+        for line in covered.iter_mut().take(end + 1).skip(start) {
+            *line = true;
+        }
+    }
+}
+
+/// Mark the first line of `range` as covered
+fn cover_start(covered: &mut [bool], range: &Range) {
+    if *range != Range::default() {
+        if let Some(line) = covered.get_mut(range.start.line) {
+            *line = true;
+        }
+    }
+}
+
+/// Mark the line of a session title or definition subject as covered
+///
+/// The element's own range may be widened by attached annotations, so the heading's
+/// location is preferred.
+fn cover_heading(covered: &mut [bool], heading: &TextContent, location: &Range) {
+    match &heading.location {
+        Some(heading) => cover(covered, heading),
+        None => cover_start(covered, location),
+    }
+}
+
+fn cover_item(covered: &mut [bool], item: &ContentItem, source_lines: &[&str]) {
+    let annotations: &[Annotation] = match item {
+        ContentItem::Paragraph(paragraph) => {
+            for line in &paragraph.lines {
+                cover(covered, line.range());
+            }
+            &paragraph.annotations
+        }
+        ContentItem::Session(session) => {
+            cover_heading(covered, &session.title, &session.location);
+            &session.annotations
+        }
+        ContentItem::List(list) => &list.annotations,
+        ContentItem::ListItem(list_item) => {
+            cover_start(covered, &list_item.location);
+            for text in &list_item.text {
+                if let Some(location) = &text.location {
+                    cover(covered, location);
+                }
+            }
+            &list_item.annotations
+        }
+        ContentItem::Definition(definition) => {
+            cover_heading(covered, &definition.subject, &definition.location);
+            &definition.annotations
+        }
+        ContentItem::Annotation(annotation) => {
+            cover_annotation(covered, annotation, source_lines);
+            return;
+        }
+        ContentItem::VerbatimBlock(verbatim) => {
+            for group in verbatim.group() {
+                if let Some(location) = &group.subject.location {
+                    cover(covered, location);
+                }
+                for line in group.children.iter() {
+                    cover(covered, line.range());
+                }
+            }
+            cover(covered, &verbatim.closing_data.location);
+            &verbatim.annotations
+        }
+        ContentItem::TextLine(_) | ContentItem::VerbatimLine(_) => {
+            cover(covered, item.range());
+            return;
+        }
+        ContentItem::BlankLineGroup(_) => return,
+    };
+    if !item.is_verbatim_block() {
+        for child in item.children().into_iter().flatten() {
+            cover_item(covered, child, source_lines);
+        }
+    }
+    for annotation in annotations {
+        cover_annotation(covered, annotation, source_lines);
+    }
+}
+
+/// Mark an annotation's header, content and closing `::` line as covered
+fn cover_annotation(covered: &mut [bool], annotation: &Annotation, source_lines: &[&str]) {
+    let header = annotation_location(annotation);
+    cover(covered, header);
+    for child in annotation.children.iter() {
+        cover_item(covered, child, source_lines);
+    }
+    // The closing marker of a block annotation is not recorded; it is the first `::` line
+    // after the header that no nested annotation closed
+    if *header != Range::default() && annotation.location.end.line > header.start.line {
+        let closing = (header.start.line + 1..source_lines.len())
+            .find(|line| !covered[*line] && source_lines[*line].trim() == "::");
+        if let Some(line) = closing {
+            covered[line] = true;
+        }
+    }
+}
+
+/// Subject text without its trailing colon (some subjects keep it in the source text)
+pub(crate) fn subject_text(subject: &str) -> &str {
+    let subject = subject.trim_end();
+    subject.strip_suffix(':').unwrap_or(subject).trim_end()
+}
+
+/// Render the `:: label params` part shared by annotations and verbatim closings
+fn data_header(data: &Data) -> String {
+    let mut header = format!(":: {}", data.label.value);
+    if !data.parameters.is_empty() {
+        let params = data
+            .parameters
+            .iter()
+            .map(|param| format!("{}={}", param.key, param.value))
+            .collect::<Vec<_>>()
+            .join(", ");
+        header.push(' ');
+        header.push_str(&params);
+    }
+    header
+}
+
+/// Marker for the `index`-th item of a list whose first item uses `first`
+///
+/// Returns `None` when the marker can't be normalized (extended forms, alphabetical
+/// overflow), in which case the item's own marker is kept.
+pub(crate) fn normalized_marker(first: &SequenceMarker, index: usize) -> Option<String> {
+    if first.form == Form::Extended {
+        return None;
+    }
+    let ordinal = index + 1;
+    let label = match first.style {
+        DecorationStyle::Plain => return Some("-".to_string()),
+        DecorationStyle::Numerical => ordinal.to_string(),
+        DecorationStyle::Alphabetical => {
+            if ordinal > 26 {
+                return None;
+            }
+            let uppercase = first
+                .as_str()
+                .chars()
+                .find(|c| c.is_alphabetic())
+                .is_some_and(|c| c.is_uppercase());
+            let base = if uppercase { b'A' } else { b'a' };
+            ((base + (ordinal - 1) as u8) as char).to_string()
+        }
+        DecorationStyle::Roman => to_roman(ordinal),
+    };
+    Some(match first.separator {
+        Separator::Period => format!("{label}."),
+        Separator::Parenthesis => format!("{label})"),
+        Separator::DoubleParens => format!("({label})"),
+    })
+}
+
+fn to_roman(mut value: usize) -> String {
+    const NUMERALS: [(usize, &str); 13] = [
+        (1000, "M"),
+        (900, "CM"),
+        (500, "D"),
+        (400, "CD"),
+        (100, "C"),
+        (90, "XC"),
+        (50, "L"),
+        (40, "XL"),
+        (10, "X"),
+        (9, "IX"),
+        (5, "V"),
+        (4, "IV"),
+        (1, "I"),
+    ];
+    let mut out = String::new();
+    for (amount, numeral) in NUMERALS {
+        while value >= amount {
+            out.push_str(numeral);
+            value -= amount;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::ast::{snapshot_from_document, AstSnapshot};
+    use crate::lex::parsing::parse_document;
+    use crate::lex::tables::ColumnAlignment;
+    use crate::lex::testing::lexplore::Lexplore;
+
+    fn roundtrip(source: &str) -> String {
+        let doc = parse_document(source).unwrap();
+        serialize_document(&doc, &FormattingRulesConfig::default())
+    }
+
+    #[test]
+    fn test_formatting_is_idempotent_on_benchmark() {
+        let source = Lexplore::benchmark(10).source();
+        let once = roundtrip(&source);
+        let twice = roundtrip(&once);
+        assert_eq!(once, twice);
+    }
+
+    #[test]
+    fn test_paragraph_source_is_preserved() {
+        let source = Lexplore::paragraph(2).source();
+        assert_eq!(roundtrip(&source), source);
+    }
+
+    #[test]
+    fn test_verbatim_keeps_relative_indentation() {
+        let doc = Lexplore::benchmark(10).parse().unwrap();
+        let output = serialize_document(&doc, &FormattingRulesConfig::default());
+        assert!(output.contains("        function example() {\n            return \"lex\";\n"));
+        assert!(output.contains("    :: javascript\n"));
+    }
+
+    #[test]
+    fn test_disabled_indentation_keeps_source_indentation() {
+        let source = Lexplore::benchmark(10)
+            .source()
+            .replace("    - Item", "\t- Item");
+        let doc = parse_document(&source).unwrap();
+        let rules = FormattingRulesConfig::default().with_rule(FormattingRule::Indentation, false);
+        let output = serialize_document_with_source(&doc, &source, &rules);
+        assert!(output.contains("\t- Item 1 in definition"));
+        let output = serialize_document_with_source(&doc, &source, &Default::default());
+        assert!(output.contains("    - Item 1 in definition"));
+    }
+
+    #[test]
+    fn test_wrapping_keeps_structure() {
+        fn node_types(snapshot: &AstSnapshot, out: &mut Vec<String>) {
+            if !matches!(snapshot.node_type.as_str(), "TextLine" | "BlankLineGroup") {
+                out.push(snapshot.node_type.clone());
+            }
+            for child in &snapshot.children {
+                node_types(child, out);
+            }
+        }
+        let structure = |doc: &Document| {
+            let mut types = Vec::new();
+            node_types(&snapshot_from_document(doc), &mut types);
+            types
+        };
+
+        let source = Lexplore::benchmark(10).source();
+        let doc = parse_document(&source).unwrap();
+        let rules = FormattingRulesConfig {
+            wrapping: true,
+            max_line_width: 40,
+            ..Default::default()
+        };
+        let wrapped = serialize_document_with_source(&doc, &source, &rules);
+        assert_ne!(wrapped, source);
+        let reparsed = parse_document(&wrapped).unwrap();
+        assert_eq!(structure(&reparsed), structure(&doc));
+        assert_eq!(serialize_document(&reparsed, &rules), wrapped);
+    }
+
+    #[test]
+    fn test_ignore_directives() {
+        let source = "Title\n\nIntro.\n\n:: lex-ignore ::\n1. One\n1.   Two\n\n:: lex-ignore markers ::\n1. One\n1. Two\n\nOutro.\n\n1. One\n1. Two\n";
+        let output = crate::lex::formatting::format_document(source, &Default::default()).unwrap();
+        assert_eq!(
+            output,
+            "Title\n\nIntro.\n\n:: lex-ignore ::\n1. One\n1.   Two\n\n:: lex-ignore markers ::\n1. One\n1. Two\n\nOutro.\n\n1. One\n2. Two\n"
+        );
+
+        let file_level = format!(":: lex-ignore fmt ::\n\n{source}");
+        let output =
+            crate::lex::formatting::format_document(&file_level, &Default::default()).unwrap();
+        assert_eq!(output, file_level);
+    }
+
+    #[test]
+    fn test_table_columns_are_aligned() {
+        let source = "Title\n\nSome prices.\n\nPrices:\n    | Item | Price |\n    |Apples|1.20|\n:: table ::\n";
+        let doc = parse_document(source).unwrap();
+        let output = serialize_document_with_source(&doc, source, &Default::default());
+        assert_eq!(
+            output,
+            "Title\n\nSome prices.\n\nPrices:\n    | Item   | Price |\n    | Apples | 1.20  |\n:: table\n"
+        );
+
+        let rules = FormattingRulesConfig {
+            table_alignment: ColumnAlignment::Right,
+            max_column_width: 4,
+            ..Default::default()
+        };
+        let output = serialize_document_with_source(&doc, source, &rules);
+        assert!(output.contains("    | Item | Price |\n    | Apples | 1.20 |\n"));
+
+        let rules = FormattingRulesConfig::default().with_rule(FormattingRule::Tables, false);
+        let output = serialize_document_with_source(&doc, source, &rules);
+        assert!(output.contains("    |Apples|1.20|\n"));
+
+        let source =
+            "Title\n\nSome prices.\n\nPrices:\n    Item   Price\n    |Apples|1.20|\n:: table\n";
+        let doc = parse_document(source).unwrap();
+        let output = serialize_document_with_source(&doc, source, &Default::default());
+        assert_eq!(output, source);
+
+        let source = Lexplore::verbatim(14).source();
+        let doc = parse_document(&source).unwrap();
+        let output = serialize_document_with_source(&doc, &source, &Default::default());
+        assert!(output.contains("\n Header | Value | Notes\n -------+-------+------\n"));
+    }
+
+    #[test]
+    fn test_normalized_marker_styles() {
+        let marker = SequenceMarker::parse("a)", None).unwrap();
+        assert_eq!(normalized_marker(&marker, 2).as_deref(), Some("c)"));
+        let marker = SequenceMarker::parse("(I)", None).unwrap();
+        assert_eq!(normalized_marker(&marker, 3).as_deref(), Some("(IV)"));
+        let marker = SequenceMarker::parse("1.2.", None).unwrap();
+        assert_eq!(normalized_marker(&marker, 0), None);
+    }
+}
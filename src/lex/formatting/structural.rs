@@ -0,0 +1,224 @@
+//! Structural edits
+//!
+//!     Outliner operations on the element under the cursor: move it above its previous
+//!     sibling or below its next one, indent it one level or outdent it one level. The
+//!     element is the innermost list item, session, definition, annotation, paragraph or
+//!     verbatim block containing the cursor line, and the operation carries its whole
+//!     subtree along.
+//!
+//!     Moves swap the source lines of two siblings, leaving the blank lines between them in
+//!     place, so both keep their own spacing. Indenting adds four spaces to each non-blank
+//!     line of the element; outdenting removes one indentation level (four spaces or a tab)
+//!     and does nothing unless every line has one. The result is returned as minimal
+//!     [TextEdit]s over the original source, like [format_range](super::range::format_range).
+
+use super::edits::{compute_edits, TextEdit};
+use super::FormattingError;
+use crate::lex::ast::traits::AstNode;
+use crate::lex::ast::{ContentItem, Position};
+use crate::lex::parsing::parse_document;
+
+/// An outliner operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StructuralEdit {
+    MoveUp,
+    MoveDown,
+    Indent,
+    Outdent,
+}
+
+/// Apply `edit` to the element at `position`, returning edits over `source`; empty when
+/// there is no element there or the edit doesn't apply (moving the first sibling up,
+/// outdenting top level content)
+pub fn structural_edit(
+    source: &str,
+    position: Position,
+    edit: StructuralEdit,
+) -> Result<Vec<TextEdit>, FormattingError> {
+    let doc = parse_document(source).map_err(FormattingError::ParseError)?;
+    let mut lines: Vec<String> = source.lines().map(str::to_string).collect();
+    let Some((siblings, index)) = locate(&doc.root.children, position.line) else {
+        return Ok(Vec::new());
+    };
+    let span = line_span(&siblings[index], &lines);
+
+    let changed = match edit {
+        StructuralEdit::MoveUp => match previous_sibling(siblings, index) {
+            Some(previous) => {
+                let previous = line_span(previous, &lines);
+                swap(&mut lines, previous, span);
+                true
+            }
+            None => false,
+        },
+        StructuralEdit::MoveDown => match next_sibling(siblings, index) {
+            Some(next) => {
+                let next = line_span(next, &lines);
+                swap(&mut lines, span, next);
+                true
+            }
+            None => false,
+        },
+        StructuralEdit::Indent => {
+            for line in &mut lines[span.0..=span.1] {
+                if !line.trim().is_empty() {
+                    line.insert_str(0, "    ");
+                }
+            }
+            true
+        }
+        StructuralEdit::Outdent => {
+            let outdented: Option<Vec<String>> = lines[span.0..=span.1]
+                .iter()
+                .map(|line| outdent(line))
+                .collect();
+            match outdented {
+                Some(outdented) => {
+                    lines.splice(span.0..=span.1, outdented);
+                    true
+                }
+                None => false,
+            }
+        }
+    };
+    if !changed {
+        return Ok(Vec::new());
+    }
+
+    let mut updated = lines.join("\n");
+    if source.ends_with('\n') {
+        updated.push('\n');
+    }
+    Ok(compute_edits(source, &updated))
+}
+
+/// Siblings holding the innermost movable element on `line`, and its index among them
+fn locate(items: &[ContentItem], line: usize) -> Option<(&[ContentItem], usize)> {
+    for (index, item) in items.iter().enumerate() {
+        if item.is_blank_line_group() || item.range().start.line > line || last_line(item) < line {
+            continue;
+        }
+        let descend = matches!(
+            item,
+            ContentItem::Session(_)
+                | ContentItem::Definition(_)
+                | ContentItem::Annotation(_)
+                | ContentItem::List(_)
+                | ContentItem::ListItem(_)
+        );
+        if descend {
+            if let Some(found) = locate(item.children().unwrap_or(&[]), line) {
+                return Some(found);
+            }
+        }
+        if !matches!(item, ContentItem::List(_)) {
+            return Some((items, index));
+        }
+    }
+    None
+}
+
+fn previous_sibling(siblings: &[ContentItem], index: usize) -> Option<&ContentItem> {
+    siblings[..index]
+        .iter()
+        .rev()
+        .find(|item| !item.is_blank_line_group())
+}
+
+fn next_sibling(siblings: &[ContentItem], index: usize) -> Option<&ContentItem> {
+    siblings[index + 1..]
+        .iter()
+        .find(|item| !item.is_blank_line_group())
+}
+
+/// First and last line (inclusive) of `item`, without trailing blank lines
+fn line_span(item: &ContentItem, lines: &[String]) -> (usize, usize) {
+    let first = item.range().start.line;
+    let mut last = last_line(item).min(lines.len().saturating_sub(1));
+    while last > first && lines[last].trim().is_empty() {
+        last -= 1;
+    }
+    (first, last)
+}
+
+/// Last line of `item`; ranges ending at column 0 stop before that line
+fn last_line(item: &ContentItem) -> usize {
+    let range = item.range();
+    if range.end.column == 0 && range.end.line > range.start.line {
+        range.end.line - 1
+    } else {
+        range.end.line
+    }
+}
+
+/// Swap the line spans `first` and `second` (`first` above), keeping the lines between
+fn swap(lines: &mut Vec<String>, first: (usize, usize), second: (usize, usize)) {
+    let mut reordered: Vec<String> = lines[second.0..=second.1].to_vec();
+    reordered.extend_from_slice(&lines[first.1 + 1..second.0]);
+    reordered.extend_from_slice(&lines[first.0..=first.1]);
+    lines.splice(first.0..=second.1, reordered);
+}
+
+fn outdent(line: &str) -> Option<String> {
+    if line.trim().is_empty() {
+        Some(line.to_string())
+    } else {
+        line.strip_prefix("    ")
+            .or_else(|| line.strip_prefix('\t'))
+            .map(str::to_string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::formatting::edits::apply_edits;
+
+    const SOURCE: &str = "Guide\n\n1. Setup\n\n    Intro.\n\n    - First\n    - Second\n        Details.\n    - Third\n\n2. Usage\n\n    Text.\n";
+
+    fn apply(line: usize, edit: StructuralEdit) -> String {
+        let edits = structural_edit(SOURCE, Position::new(line, 6), edit).unwrap();
+        apply_edits(SOURCE, &edits)
+    }
+
+    #[test]
+    fn test_move_list_items() {
+        assert_eq!(
+            apply(9, StructuralEdit::MoveUp),
+            SOURCE.replace(
+                "    - Second\n        Details.\n    - Third\n",
+                "    - Third\n    - Second\n        Details.\n"
+            )
+        );
+        assert_eq!(
+            apply(6, StructuralEdit::MoveDown),
+            SOURCE.replace(
+                "    - First\n    - Second\n        Details.\n",
+                "    - Second\n        Details.\n    - First\n"
+            )
+        );
+        assert_eq!(apply(6, StructuralEdit::MoveUp), SOURCE);
+    }
+
+    #[test]
+    fn test_move_sessions() {
+        assert_eq!(
+            apply(11, StructuralEdit::MoveUp),
+            "Guide\n\n2. Usage\n\n    Text.\n\n1. Setup\n\n    Intro.\n\n    - First\n    - Second\n        Details.\n    - Third\n"
+        );
+        assert_eq!(apply(11, StructuralEdit::MoveDown), SOURCE);
+    }
+
+    #[test]
+    fn test_indent_and_outdent() {
+        assert_eq!(
+            apply(4, StructuralEdit::Indent),
+            SOURCE.replace("    Intro.", "        Intro.")
+        );
+        assert_eq!(
+            apply(7, StructuralEdit::Outdent),
+            SOURCE.replace("    - Second\n        Details.", "- Second\n    Details.")
+        );
+        assert_eq!(apply(2, StructuralEdit::Outdent), SOURCE);
+    }
+}
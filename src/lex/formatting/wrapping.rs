@@ -0,0 +1,111 @@
+//! Paragraph line wrapping
+//!
+//! Breaks long paragraph lines at spaces. Lex is line-oriented, so a break must never turn
+//! text into structure: every resulting line has to classify as a plain paragraph line (not a
+//! list item, subject, annotation, ...), and breaks are never placed inside inline spans
+//! (code, math, emphasis, references), whose delimiters must stay on one line.
+
+use crate::lex::lexing::line_classification::classify_line_tokens;
+use crate::lex::lexing::{tokenize, LineType};
+
+/// Inline delimiters that must be balanced on both sides of a break
+const PAIRED_DELIMITERS: [char; 4] = ['`', '*', '_', '#'];
+
+/// Wrap `text` so every line fits in `width` columns, where possible
+///
+/// Words longer than the width, and stretches without a safe break, are left on one line.
+pub fn wrap_line(text: &str, width: usize) -> Vec<String> {
+    if text.chars().count() <= width {
+        return vec![text.to_string()];
+    }
+
+    let mut lines = Vec::new();
+    let mut rest = text.trim_end();
+    while rest.chars().count() > width {
+        match best_break(rest, width) {
+            Some(index) => {
+                lines.push(rest[..index].trim_end().to_string());
+                rest = rest[index..].trim_start();
+            }
+            None => break,
+        }
+    }
+    lines.push(rest.to_string());
+    lines
+}
+
+/// Byte index where `text` should be broken: the last safe space within `width`, or the
+/// first safe one past it
+fn best_break(text: &str, width: usize) -> Option<usize> {
+    let candidates: Vec<usize> = text
+        .char_indices()
+        .filter(|(index, c)| *c == ' ' && *index > 0)
+        .map(|(index, _)| index)
+        .filter(|index| is_safe_break(text, *index))
+        .collect();
+    let fits = |index: &&usize| text[..**index].trim_end().chars().count() <= width;
+    candidates
+        .iter()
+        .rev()
+        .find(fits)
+        .or_else(|| candidates.first())
+        .copied()
+}
+
+fn is_safe_break(text: &str, index: usize) -> bool {
+    let (head, tail) = (text[..index].trim_end(), text[index..].trim_start());
+    if head.is_empty() || tail.is_empty() {
+        return false;
+    }
+    let balanced = PAIRED_DELIMITERS
+        .iter()
+        .all(|delimiter| head.matches(*delimiter).count() % 2 == 0)
+        && head.matches('[').count() == head.matches(']').count();
+    balanced && is_paragraph_line(head) && is_paragraph_line(tail)
+}
+
+fn is_paragraph_line(text: &str) -> bool {
+    let line = format!("{text}\n");
+    let tokens: Vec<_> = tokenize(&line)
+        .into_iter()
+        .map(|(token, _)| token)
+        .collect();
+    classify_line_tokens(&tokens) == LineType::ParagraphLine
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_lines_are_untouched() {
+        assert_eq!(wrap_line("Short line.", 80), vec!["Short line."]);
+    }
+
+    #[test]
+    fn test_wraps_at_last_space_within_width() {
+        let lines = wrap_line("one two three four five six", 13);
+        assert_eq!(lines, vec!["one two three", "four five six"]);
+    }
+
+    #[test]
+    fn test_never_starts_a_line_with_a_marker() {
+        let lines = wrap_line("Keep the dash - with its words here", 15);
+        assert!(lines.iter().all(|line| !line.starts_with("- ")));
+        assert_eq!(lines.join(" "), "Keep the dash - with its words here");
+    }
+
+    #[test]
+    fn test_never_breaks_inside_inline_code() {
+        let lines = wrap_line("Run `cargo test --workspace` now", 12);
+        assert!(lines
+            .iter()
+            .any(|line| line.contains("`cargo test --workspace`")));
+    }
+
+    #[test]
+    fn test_never_ends_a_line_with_a_colon() {
+        let lines = wrap_line("Note: the following words wrap", 6);
+        assert!(lines.iter().all(|line| !line.ends_with(':')));
+    }
+}
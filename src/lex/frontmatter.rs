@@ -0,0 +1,211 @@
+//! YAML frontmatter
+//!
+//!     Markdown documents carry their metadata in a YAML block at the top, which static site
+//!     generators (Hugo, Jekyll, Eleventy) read. This module maps it to and from Lex
+//!     document metadata:
+//!
+//!         ---
+//!         title: Cache Design
+//!         date: 2024-03-01
+//!         tags: [rust, caching]
+//!         ---
+//!
+//!     is, in Lex, the document title, a `:: tags ::` annotation (see
+//!     [tags](crate::lex::analysis::tags)) and a `:: meta ::` annotation holding the other
+//!     keys as parameters:
+//!
+//!         Cache Design
+//!
+//!         :: tags caching, rust ::
+//!
+//!         :: meta date="2024-03-01" ::
+//!
+//!     Markdown importers read it with [Frontmatter::split] and write it with
+//!     [Frontmatter::to_annotations]; exporters read it from a document with
+//!     [Frontmatter::of] and emit it with [Frontmatter::to_yaml], so converted content can be
+//!     dropped into a site as is. Nested YAML values are flattened to comma separated lists.
+
+use crate::lex::analysis::tags::document_tags;
+use crate::lex::ast::Document;
+use serde_yaml::{Mapping, Value};
+
+/// Label of the annotation holding metadata other than the title and tags
+pub const META_LABEL: &str = "meta";
+
+/// Document metadata, as found in frontmatter
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Frontmatter {
+    pub title: Option<String>,
+    /// Lowercase, sorted and deduplicated
+    pub tags: Vec<String>,
+    /// Other keys, in the order given
+    pub meta: Vec<(String, String)>,
+}
+
+impl Frontmatter {
+    /// Frontmatter of a Markdown source, if it has any, and the rest of the source
+    ///
+    /// A block that is not a YAML mapping is still taken out of the body, read as empty.
+    pub fn split(source: &str) -> (Option<Self>, &str) {
+        let Some(rest) = source.strip_prefix("---\n") else {
+            return (None, source);
+        };
+        let Some(end) = rest.find("\n---") else {
+            return (None, source);
+        };
+        let frontmatter = Self::parse(&rest[..end]).unwrap_or_default();
+        let body = &rest[end + 4..];
+        (Some(frontmatter), body.strip_prefix('\n').unwrap_or(body))
+    }
+
+    /// Frontmatter from the YAML between the `---` lines
+    pub fn parse(yaml: &str) -> Option<Self> {
+        let Ok(Value::Mapping(fields)) = serde_yaml::from_str::<Value>(yaml) else {
+            return None;
+        };
+        let mut frontmatter = Self::default();
+        for (key, value) in fields {
+            let Some(key) = key.as_str() else { continue };
+            match key {
+                "tags" | "tag" => frontmatter.add_tags(yaml_list(&value)),
+                "title" => frontmatter.title = yaml_list(&value).into_iter().next(),
+                _ => frontmatter
+                    .meta
+                    .push((key.to_string(), yaml_list(&value).join(", "))),
+            }
+        }
+        Some(frontmatter)
+    }
+
+    /// Metadata of `document`: its title, tags and `meta` parameters
+    pub fn of(document: &Document) -> Self {
+        let title = document.title().trim();
+        let meta = document
+            .annotations
+            .iter()
+            .chain(&document.root.annotations)
+            .filter(|annotation| annotation.data.label.value.trim() == META_LABEL)
+            .flat_map(|annotation| &annotation.data.parameters)
+            .map(|param| {
+                let value = param.value.trim_matches('"').to_string();
+                (param.key.clone(), value)
+            })
+            .collect();
+        Self {
+            title: (!title.is_empty()).then(|| title.to_string()),
+            tags: document_tags(document),
+            meta,
+        }
+    }
+
+    /// Add tags, keeping them lowercase, sorted and deduplicated
+    pub fn add_tags(&mut self, tags: impl IntoIterator<Item = String>) {
+        self.tags
+            .extend(tags.into_iter().map(|tag| tag.to_lowercase()));
+        self.tags.sort();
+        self.tags.dedup();
+    }
+
+    /// Whether there is no metadata at all
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none() && self.tags.is_empty() && self.meta.is_empty()
+    }
+
+    /// Lex source of the `tags` and `meta` annotations following a document title, each
+    /// preceded by a blank line; empty if there are neither
+    pub fn to_annotations(&self) -> String {
+        let mut out = String::new();
+        if !self.tags.is_empty() {
+            out.push_str(&format!("\n:: tags {} ::\n", self.tags.join(", ")));
+        }
+        if !self.meta.is_empty() {
+            let params: Vec<String> = self
+                .meta
+                .iter()
+                .map(|(key, value)| format!("{key}=\"{}\"", value.replace('"', "'")))
+                .collect();
+            out.push_str(&format!("\n:: {META_LABEL} {} ::\n", params.join(", ")));
+        }
+        out
+    }
+
+    /// The frontmatter block, `---` lines included; empty if there is no metadata
+    pub fn to_yaml(&self) -> String {
+        if self.is_empty() {
+            return String::new();
+        }
+        let mut fields = Mapping::new();
+        if let Some(title) = &self.title {
+            fields.insert("title".into(), title.as_str().into());
+        }
+        for (key, value) in &self.meta {
+            fields.insert(key.as_str().into(), value.as_str().into());
+        }
+        if !self.tags.is_empty() {
+            let tags = self.tags.iter().map(|tag| tag.as_str().into()).collect();
+            fields.insert("tags".into(), Value::Sequence(tags));
+        }
+        let yaml = serde_yaml::to_string(&fields).unwrap_or_default();
+        format!("---\n{yaml}---\n")
+    }
+}
+
+/// A frontmatter value as a list of strings: sequences item by item, strings split on
+/// commas
+fn yaml_list(value: &Value) -> Vec<String> {
+    let scalar = |value: &Value| match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        Value::Bool(flag) => Some(flag.to_string()),
+        _ => None,
+    };
+    match value {
+        Value::Sequence(items) => items.iter().filter_map(scalar).collect(),
+        Value::String(text) => text
+            .split(',')
+            .map(|part| part.trim().trim_start_matches('#').to_string())
+            .filter(|part| !part.is_empty())
+            .collect(),
+        other => scalar(other).into_iter().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    #[test]
+    fn test_round_trip() {
+        let markdown =
+            "---\ntitle: Cache Design\ndate: 2024-03-01\ntags: [Rust, caching]\n---\nBody\n";
+        let (frontmatter, body) = Frontmatter::split(markdown);
+        let frontmatter = frontmatter.unwrap();
+        assert_eq!(body, "Body\n");
+        assert_eq!(frontmatter.tags, ["caching", "rust"]);
+        assert_eq!(
+            frontmatter.to_annotations(),
+            "\n:: tags caching, rust ::\n\n:: meta date=\"2024-03-01\" ::\n"
+        );
+
+        let lex = format!("Cache Design\n{}\nText.\n", frontmatter.to_annotations());
+        let read = Frontmatter::of(&parse_document(&lex).unwrap());
+        assert_eq!(read, frontmatter);
+        assert_eq!(
+            read.to_yaml(),
+            "---\ntitle: Cache Design\ndate: 2024-03-01\ntags:\n- caching\n- rust\n---\n"
+        );
+    }
+
+    #[test]
+    fn test_without_frontmatter() {
+        assert_eq!(Frontmatter::split("# Notes\n"), (None, "# Notes\n"));
+        assert_eq!(
+            Frontmatter::split("---\n- a\n---\nText\n"),
+            (Some(Frontmatter::default()), "Text\n")
+        );
+        let untitled = Frontmatter::of(&parse_document("Text.\n").unwrap());
+        assert!(untitled.is_empty());
+        assert_eq!(untitled.to_yaml(), "");
+    }
+}
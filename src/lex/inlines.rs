@@ -16,17 +16,23 @@
 //!     This solves elegantly the fact that most inlines are simple and very much the same
 //!     structure, while allowing for more complex ones to handle their specific needs.
 //!
-//!     See [parser](parser) module for the inline parser implementation.
+//!     See [parser](parser) module for the inline parser implementation, and [roles] for
+//!     registering custom inline spans.
 
 mod citations;
+#[cfg(feature = "math")]
 pub mod math;
 mod parser;
 mod references;
+pub mod roles;
 
 pub use crate::lex::ast::elements::inlines::{
-    InlineContent, InlineNode, PageFormat, ReferenceInline, ReferenceType,
+    ImageData, InlineContent, InlineNode, PageFormat, ReferenceInline, ReferenceType, RoleInline,
+    WikiLinkData,
 };
 pub use crate::lex::token::InlineKind;
 pub use parser::{
     parse_inlines, parse_inlines_with_parser, InlineParser, InlinePostProcessor, InlineSpec,
 };
+pub use references::{parse_image, parse_wiki_link};
+pub use roles::{InlineRole, RoleRegistry, DEL_ROLE};
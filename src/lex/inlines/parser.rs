@@ -20,6 +20,10 @@
 //!     - **Emphasis** (_text_): Wraps content in `InlineNode::Emphasis(children)`
 //!     - **Code** (`text`): Wraps literal text in `InlineNode::Code(string)` - no nested parsing
 //!     - **Math** (#formula#): Wraps literal text in `InlineNode::Math(string)` - no nested parsing
+//!     - **Ruby** ({base|gloss}): Splits literal text at the first `|` into `InlineNode::Ruby`.
+//!       Braces without a gloss keep their braces as plain text, and the text inside them is
+//!       parsed as usual. Ruby glosses CJK text, which has no
+//!       spaces between words, so unlike the other elements it may start and end mid-word.
 //!
 //!     These are defined in the `default_specs()` function with just start/end tokens and whether
 //!     they're literal (no nested inline parsing inside).
@@ -60,8 +64,15 @@
 //!         .with_post_processor(InlineKind::Strong, my_custom_processor);
 //!     let result = parser.parse("*text*");
 //!     ```
+//!
+//!     Inline spans outside the grammar are added as roles, see [roles](super::roles):
+//!     ```
+//!     let parser = InlineParser::new().with_role(InlineRole::new("kbd"));
+//!     let result = parser.parse("{kbd|Ctrl+C}");
+//!     ```
 
-use super::references::classify_reference_node;
+use super::references::{classify_reference_node, classify_reference_node_with_wiki_links};
+use super::roles::{InlineRole, RoleRegistry};
 use crate::lex::ast::elements::inlines::{InlineContent, InlineNode, ReferenceInline};
 use crate::lex::token::InlineKind;
 use once_cell::sync::Lazy;
@@ -112,6 +123,8 @@ impl InlineSpec {
 pub struct InlineParser {
     specs: Vec<InlineSpec>,
     token_map: HashMap<char, usize>,
+    roles: RoleRegistry,
+    wiki_links: bool,
 }
 
 impl InlineParser {
@@ -127,6 +140,29 @@ impl InlineParser {
         self
     }
 
+    /// Register a custom inline role, parsed from `{name|content}` spans.
+    pub fn with_role(mut self, role: InlineRole) -> Self {
+        self.roles.register(role);
+        self
+    }
+
+    /// Recognize `[[target#section|text]]` wiki links as
+    /// [ReferenceType::WikiLink](crate::lex::inlines::ReferenceType::WikiLink) references.
+    ///
+    /// Off by default: without it, `[[Target]]` closes at the first `]` like any reference.
+    pub fn with_wiki_links(mut self) -> Self {
+        self.wiki_links = true;
+        self.with_post_processor(
+            InlineKind::Reference,
+            classify_reference_node_with_wiki_links,
+        )
+    }
+
+    /// Roles registered with this parser.
+    pub fn roles(&self) -> &RoleRegistry {
+        &self.roles
+    }
+
     pub fn parse(&self, text: &str) -> InlineContent {
         parse_with(self, text)
     }
@@ -136,7 +172,12 @@ impl InlineParser {
         for (index, spec) in specs.iter().enumerate() {
             token_map.insert(spec.start_token, index);
         }
-        Self { specs, token_map }
+        Self {
+            specs,
+            token_map,
+            roles: RoleRegistry::builtin(),
+            wiki_links: false,
+        }
     }
 
     fn spec(&self, index: usize) -> &InlineSpec {
@@ -195,6 +236,13 @@ fn default_specs() -> Vec<InlineSpec> {
             literal: true,
             post_process: Some(classify_reference_node),
         },
+        InlineSpec {
+            kind: InlineKind::Ruby,
+            start_token: '{',
+            end_token: '}',
+            literal: true,
+            post_process: None,
+        },
     ]
 }
 
@@ -242,6 +290,14 @@ fn parse_with(parser: &InlineParser, text: &str) -> InlineContent {
             if ch == spec.end_token {
                 if blocked.consume(spec_index) {
                     // Literal closing paired to a disallowed nested start.
+                } else if parser.wiki_links
+                    && spec.kind == InlineKind::Reference
+                    && next == Some(spec.end_token)
+                    && stack.last().unwrap().buffer.starts_with(spec.start_token)
+                {
+                    // Inner bracket of a `[[wiki link]]`; the next one closes the reference.
+                    stack.last_mut().unwrap().push_char(ch);
+                    consumed = true;
                 } else if is_valid_end(prev, next, spec) {
                     let mut frame = stack.pop().unwrap();
                     frame.flush_buffer();
@@ -251,9 +307,24 @@ fn parse_with(parser: &InlineParser, text: &str) -> InlineContent {
                         parent.push_char(spec.start_token);
                         parent.push_char(spec.end_token);
                     } else {
-                        let node = frame.into_node(spec);
-                        let node = spec.apply_post_process(node);
-                        stack.last_mut().unwrap().push_node(node);
+                        let start = frame.start;
+                        let parent_index = stack.len() - 1;
+                        match frame.into_node(spec, &parser.roles) {
+                            Some(node) => {
+                                let node = spec.apply_post_process(node);
+                                stack[parent_index].push_node(node);
+                            }
+                            None => {
+                                // Braces that are neither a role nor ruby: the text inside
+                                // them is ordinary inline markup.
+                                let parent = &mut stack[parent_index];
+                                parent.push_char(spec.start_token);
+                                for node in reparse(parser, &chars[start + 1..i]) {
+                                    parent.push_node(node);
+                                }
+                                parent.push_char(spec.end_token);
+                            }
+                        }
                     }
                     consumed = true;
                 }
@@ -271,7 +342,7 @@ fn parse_with(parser: &InlineParser, text: &str) -> InlineContent {
                         blocked.increment(spec_index);
                     } else {
                         stack.last_mut().unwrap().flush_buffer();
-                        stack.push(InlineFrame::new(spec_index));
+                        stack.push(InlineFrame::new(spec_index, i));
                         consumed = true;
                     }
                 }
@@ -298,7 +369,14 @@ fn parse_with(parser: &InlineParser, text: &str) -> InlineContent {
         let spec = parser.spec(spec_index);
         let parent = stack.last_mut().unwrap();
         parent.push_char(spec.start_token);
-        for child in frame.children {
+        // An unclosed literal start token is just a character; what follows it is
+        // parsed as usual rather than kept verbatim.
+        let children = if spec.literal {
+            reparse(parser, &chars[frame.start + 1..])
+        } else {
+            frame.children
+        };
+        for child in children {
             parent.push_node(child);
         }
     }
@@ -308,8 +386,14 @@ fn parse_with(parser: &InlineParser, text: &str) -> InlineContent {
     root.children
 }
 
+fn reparse(parser: &InlineParser, chars: &[char]) -> InlineContent {
+    parse_with(parser, &chars.iter().collect::<String>())
+}
+
 struct InlineFrame {
     spec_index: Option<usize>,
+    /// Index of the start token in the parsed text
+    start: usize,
     buffer: String,
     children: InlineContent,
 }
@@ -318,14 +402,16 @@ impl InlineFrame {
     fn root() -> Self {
         Self {
             spec_index: None,
+            start: 0,
             buffer: String::new(),
             children: Vec::new(),
         }
     }
 
-    fn new(spec_index: usize) -> Self {
+    fn new(spec_index: usize, start: usize) -> Self {
         Self {
             spec_index: Some(spec_index),
+            start,
             buffer: String::new(),
             children: Vec::new(),
         }
@@ -373,8 +459,9 @@ impl InlineFrame {
         }
     }
 
-    fn into_node(self, spec: &InlineSpec) -> InlineNode {
-        match spec.kind {
+    /// The node for a closed frame; `None` for braces that hold neither a role nor ruby.
+    fn into_node(self, spec: &InlineSpec, roles: &RoleRegistry) -> Option<InlineNode> {
+        let node = match spec.kind {
             InlineKind::Strong => InlineNode::Strong {
                 content: self.children,
                 annotations: Vec::new(),
@@ -395,7 +482,9 @@ impl InlineFrame {
                 data: ReferenceInline::new(flatten_literal(self.children)),
                 annotations: Vec::new(),
             },
-        }
+            InlineKind::Ruby => return brace_node(flatten_literal(self.children), roles),
+        };
+        Some(node)
     }
 
     fn is_literal(&self, parser: &InlineParser) -> bool {
@@ -416,6 +505,17 @@ fn flatten_literal(children: InlineContent) -> String {
     text
 }
 
+/// A `{role|content}` span when `role` is registered, otherwise ruby `{base|gloss}`.
+/// Spans that are neither give `None`.
+fn brace_node(raw: String, roles: &RoleRegistry) -> Option<InlineNode> {
+    let (name, content) = raw.split_once('|')?;
+    if let Some(role) = roles.get(name.trim()) {
+        return role.parse(content).map(InlineNode::role);
+    }
+    (!name.trim().is_empty() && !content.trim().is_empty())
+        .then(|| InlineNode::ruby(name.trim().to_string(), content.trim().to_string()))
+}
+
 fn fatal_literal_content() -> ! {
     panic!("Literal inline nodes must not contain nested nodes");
 }
@@ -449,7 +549,9 @@ impl BlockedClosings {
 }
 
 fn is_valid_start(prev: Option<char>, next: Option<char>, spec: &InlineSpec) -> bool {
-    if spec.kind == InlineKind::Reference {
+    if spec.kind == InlineKind::Ruby {
+        matches!(next, Some(ch) if !ch.is_whitespace())
+    } else if spec.kind == InlineKind::Reference {
         !is_word(prev) && next.is_some()
     } else {
         !is_word(prev) && is_word(next)
@@ -457,6 +559,9 @@ fn is_valid_start(prev: Option<char>, next: Option<char>, spec: &InlineSpec) ->
 }
 
 fn is_valid_end(prev: Option<char>, next: Option<char>, spec: &InlineSpec) -> bool {
+    if spec.kind == InlineKind::Ruby {
+        return matches!(prev, Some(ch) if !ch.is_whitespace());
+    }
     let inside_valid = if spec.literal {
         prev.is_some()
     } else {
@@ -694,6 +799,72 @@ mod tests {
         }
     }
 
+    #[test]
+    fn reference_detects_images() {
+        let nodes = parse_inlines("A [!./img/sunset.png width=50%] here");
+        match &nodes[1] {
+            InlineNode::Reference { data, .. } => match &data.reference_type {
+                ReferenceType::Image(image) => {
+                    assert_eq!(image.src, "./img/sunset.png");
+                    assert_eq!(image.width.as_deref(), Some("50%"));
+                    assert_eq!(image.alt, None);
+                }
+                other => panic!("Expected image, got {other:?}"),
+            },
+            _ => panic!("Expected reference"),
+        }
+        let nodes = parse_inlines(r#"[!https://x.org/a.png alt="A <b>" height=12]"#);
+        match &nodes[0] {
+            InlineNode::Reference { data, .. } => match &data.reference_type {
+                ReferenceType::Image(image) => {
+                    assert_eq!(image.alt.as_deref(), Some("A <b>"));
+                    assert_eq!(image.height.as_deref(), Some("12"));
+                    assert_eq!(
+                        image.to_html(),
+                        r#"<img src="https://x.org/a.png" alt="A &lt;b&gt;" height="12">"#
+                    );
+                }
+                other => panic!("Expected image, got {other:?}"),
+            },
+            _ => panic!("Expected reference"),
+        }
+    }
+
+    #[test]
+    fn wiki_links_require_opt_in() {
+        let wiki = InlineParser::new().with_wiki_links();
+        let nodes = wiki.parse("See [[Cache Notes#Eviction|eviction]] and [[Index]].");
+        let links: Vec<_> = nodes
+            .iter()
+            .filter_map(|node| match node {
+                InlineNode::Reference { data, .. } => match &data.reference_type {
+                    ReferenceType::WikiLink(link) => Some(link.clone()),
+                    other => panic!("Expected wiki link, got {other:?}"),
+                },
+                _ => None,
+            })
+            .collect();
+        assert_eq!(links.len(), 2);
+        assert_eq!(links[0].target, "Cache Notes");
+        assert_eq!(links[0].section.as_deref(), Some("Eviction"));
+        assert_eq!(links[0].display_text(), "eviction");
+        assert_eq!(links[1].display_text(), "Index");
+
+        match &wiki.parse("[Section Title]")[0] {
+            InlineNode::Reference { data, .. } => {
+                assert!(matches!(data.reference_type, ReferenceType::General { .. }))
+            }
+            _ => panic!("Expected reference"),
+        }
+        match &parse_inlines("[[Index]]")[0] {
+            InlineNode::Reference { data, .. } => match &data.reference_type {
+                ReferenceType::General { target } => assert_eq!(target, "[Index"),
+                other => panic!("Expected general reference, got {other:?}"),
+            },
+            _ => panic!("Expected reference"),
+        }
+    }
+
     fn annotate_strong(node: InlineNode) -> InlineNode {
         match node {
             InlineNode::Strong {
@@ -758,6 +929,52 @@ mod tests {
         assert_eq!(nodes, vec![InlineNode::plain("word*s*".into())]);
     }
 
+    #[test]
+    fn parses_ruby_inside_words() {
+        let nodes = parse_inlines("これは{漢字|かんじ}です");
+        assert_eq!(
+            nodes,
+            vec![
+                InlineNode::plain("これは".into()),
+                InlineNode::ruby("漢字".into(), "かんじ".into()),
+                InlineNode::plain("です".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn braces_without_gloss_stay_plain() {
+        let nodes = parse_inlines("a {set} and {|x} and fn() { }");
+        assert_eq!(
+            nodes,
+            vec![InlineNode::plain("a {set} and {|x} and fn() { }".into())]
+        );
+    }
+
+    #[test]
+    fn braces_without_gloss_keep_inner_markup() {
+        let strong_c = InlineNode::Strong {
+            content: vec![InlineNode::plain("c".into())],
+            annotations: Vec::new(),
+        };
+        assert_eq!(
+            parse_inlines("a {b *c* d} e"),
+            vec![
+                InlineNode::plain("a {b ".into()),
+                strong_c.clone(),
+                InlineNode::plain(" d} e".into()),
+            ]
+        );
+        assert_eq!(
+            parse_inlines("a {b *c* d e"),
+            vec![
+                InlineNode::plain("a {b ".into()),
+                strong_c,
+                InlineNode::plain(" d e".into()),
+            ]
+        );
+    }
+
     #[test]
     fn multiple_arithmetic_expressions() {
         let nodes = parse_inlines("Calculate 7 * 8 + 3 * 4");
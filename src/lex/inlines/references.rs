@@ -6,11 +6,14 @@
 //! - Session references (`[#42]`)
 //! - URLs (`[https://example.com]`)
 //! - File paths (`[./file.txt]`)
+//! - Inline images (`[!./photo.png alt="A sunset" width=120]`)
 //! - Footnotes (`[^note]`, `[42]`)
 //! - General references (`[Section Title]`)
+//! - Wiki links (`[[Target Note]]`, `[[target#section|text]]`), only when enabled with
+//!   [InlineParser::with_wiki_links](super::InlineParser::with_wiki_links)
 
 use super::citations::parse_citation_data;
-use crate::lex::ast::elements::inlines::{InlineNode, ReferenceType};
+use crate::lex::ast::elements::inlines::{ImageData, InlineNode, ReferenceType, WikiLinkData};
 
 /// Post-processor callback for reference nodes that classifies their type.
 pub(super) fn classify_reference_node(node: InlineNode) -> InlineNode {
@@ -26,6 +29,105 @@ pub(super) fn classify_reference_node(node: InlineNode) -> InlineNode {
     }
 }
 
+/// Post-processor callback for reference nodes that also recognizes wiki links.
+pub(super) fn classify_reference_node_with_wiki_links(node: InlineNode) -> InlineNode {
+    match node {
+        InlineNode::Reference {
+            mut data,
+            annotations,
+        } => {
+            data.reference_type = match parse_wiki_link(&data.raw) {
+                Some(link) => ReferenceType::WikiLink(link),
+                None => determine_reference_type(&data.raw),
+            };
+            InlineNode::Reference { data, annotations }
+        }
+        other => other,
+    }
+}
+
+/// Parse the content of a `[[target#section|text]]` wiki link.
+///
+/// `raw` is the content of the outer brackets, so it still carries the inner ones:
+/// `[Target Note]` for `[[Target Note]]`.
+pub fn parse_wiki_link(raw: &str) -> Option<WikiLinkData> {
+    let inner = raw.strip_prefix('[')?.strip_suffix(']')?;
+    let (link, text) = match inner.split_once('|') {
+        Some((link, text)) => (link, Some(text.trim())),
+        None => (inner, None),
+    };
+    let (target, section) = match link.split_once('#') {
+        Some((target, section)) => (target.trim(), Some(section.trim())),
+        None => (link.trim(), None),
+    };
+    if target.is_empty() || target.contains(['[', ']']) {
+        return None;
+    }
+    let non_empty = |part: Option<&str>| part.filter(|part| !part.is_empty()).map(str::to_string);
+    Some(WikiLinkData {
+        target: target.to_string(),
+        section: non_empty(section),
+        text: non_empty(text),
+    })
+}
+
+/// Parse the content of a `[!src key=value ...]` inline image.
+///
+/// The source must be a file path or URL, so `[!!!]` stays unclassified. Recognized
+/// parameters are `alt`, `width` and `height`; values with spaces are quoted.
+pub fn parse_image(raw: &str) -> Option<ImageData> {
+    let rest = raw.trim().strip_prefix('!')?;
+    let mut words = split_parameters(rest).into_iter();
+    let src = words.next()?;
+    if !is_file_reference(&src) && !is_url_reference(&src) {
+        return None;
+    }
+    let mut image = ImageData {
+        src,
+        alt: None,
+        width: None,
+        height: None,
+    };
+    for word in words {
+        let Some((key, value)) = word.split_once('=') else {
+            continue;
+        };
+        let value = Some(value.trim_matches('"').to_string());
+        match key {
+            "alt" => image.alt = value,
+            "width" => image.width = value,
+            "height" => image.height = value,
+            _ => {}
+        }
+    }
+    Some(image)
+}
+
+/// Split `text` at whitespace outside double quotes
+fn split_parameters(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                word.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            _ => word.push(c),
+        }
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
 /// Determine the reference type from raw content.
 fn determine_reference_type(raw: &str) -> ReferenceType {
     let trimmed = raw.trim();
@@ -37,6 +139,10 @@ fn determine_reference_type(raw: &str) -> ReferenceType {
         return ReferenceType::ToCome { identifier };
     }
 
+    if let Some(image) = parse_image(trimmed) {
+        return ReferenceType::Image(image);
+    }
+
     if let Some(rest) = trimmed.strip_prefix('@') {
         if let Some(citation) = parse_citation_data(rest) {
             return ReferenceType::Citation(citation);
@@ -110,15 +216,16 @@ fn detect_tk_reference(trimmed: &str) -> Option<Option<String>> {
 
 /// Parse session reference from content starting with `#`.
 ///
-/// Example: `[#2.1]` → Session reference to "2.1"
+/// Example: `[#2.1]` → Session reference to "2.1", `[#intro]` → to the session with id "intro"
 fn parse_session_reference(trimmed: &str) -> Option<String> {
     let rest = trimmed.strip_prefix('#')?;
     if rest.is_empty() {
         return None;
     }
+    // Session numbers (`#2.1`) or explicit ids (`#intro`)
     if rest
         .chars()
-        .all(|c| c.is_ascii_digit() || c == '.' || c == '-')
+        .all(|c| c.is_alphanumeric() || c == '.' || c == '-' || c == '_')
     {
         Some(rest.to_string())
     } else {
@@ -0,0 +1,182 @@
+//! Custom inline roles
+//!
+//!     Braces hold ruby text (`{漢字|かんじ}`), but when the part before the `|` names a role
+//!     registered with the parser, the span is that role instead: `{kbd|Ctrl+C}`. Roles let
+//!     downstream crates add inline spans of their own without forking the inline parser:
+//!
+//!         let kbd = InlineRole::new("kbd")
+//!             .with_renderer("html", |span| format!("<kbd>{}</kbd>", span.content));
+//!         let parser = InlineParser::new().with_role(kbd);
+//!         let nodes = parser.parse("Press {kbd|Ctrl+C} to copy.");
+//!
+//!     A role can validate or normalize its content with a parse hook; content the hook
+//!     rejects is kept as plain text. Output formats render role nodes through
+//!     [RoleRegistry::render], which calls the role's hook for that format, if it has one.
+//!
+//!     Every parser knows the built-in roles of [RoleRegistry::builtin]: strikethrough,
+//!     `{del|text}` ([DEL_ROLE]), so that all formats see the same nodes for it.
+
+use crate::lex::ast::elements::inlines::RoleInline;
+use std::collections::HashMap;
+
+/// Role of strikethrough spans
+pub const DEL_ROLE: &str = "del";
+
+/// Validates and normalizes the content of a role span; `None` rejects it.
+pub type RoleParseHook = fn(&str) -> Option<String>;
+
+/// Renders a role span in one output format.
+pub type RoleRenderHook = fn(&RoleInline) -> String;
+
+/// A custom inline span type
+#[derive(Clone)]
+pub struct InlineRole {
+    name: String,
+    parse: Option<RoleParseHook>,
+    renderers: HashMap<String, RoleRenderHook>,
+}
+
+impl InlineRole {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            parse: None,
+            renderers: HashMap::new(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Attach a hook that validates or normalizes content. Without one, content is kept
+    /// trimmed, and rejected only when empty.
+    pub fn with_parser(mut self, hook: RoleParseHook) -> Self {
+        self.parse = Some(hook);
+        self
+    }
+
+    /// Attach a rendering hook for the format named `format` (e.g. `html`)
+    pub fn with_renderer(mut self, format: impl Into<String>, hook: RoleRenderHook) -> Self {
+        self.renderers.insert(format.into(), hook);
+        self
+    }
+
+    /// Build the role's node data from the content after the `|`
+    pub fn parse(&self, content: &str) -> Option<RoleInline> {
+        let content = match self.parse {
+            Some(hook) => hook(content)?,
+            None => Some(content.trim())
+                .filter(|content| !content.is_empty())?
+                .to_string(),
+        };
+        Some(RoleInline::new(self.name.clone(), content))
+    }
+
+    /// Render `span` in `format`, if the role has a hook for it
+    pub fn render(&self, format: &str, span: &RoleInline) -> Option<String> {
+        self.renderers.get(format).map(|hook| hook(span))
+    }
+}
+
+/// Roles known to an inline parser, by name
+#[derive(Clone, Default)]
+pub struct RoleRegistry {
+    roles: HashMap<String, InlineRole>,
+}
+
+impl RoleRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry holding the built-in roles, which every [InlineParser](super::InlineParser)
+    /// starts with
+    pub fn builtin() -> Self {
+        let mut registry = Self::new();
+        registry.register(InlineRole::new(DEL_ROLE));
+        registry
+    }
+
+    /// Register `role`, returning the role it replaces, if any
+    pub fn register(&mut self, role: InlineRole) -> Option<InlineRole> {
+        self.roles.insert(role.name.clone(), role)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&InlineRole> {
+        self.roles.get(name)
+    }
+
+    /// Names of the registered roles, sorted
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.roles.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roles.is_empty()
+    }
+
+    /// Render `span` in `format` with its role's hook
+    pub fn render(&self, format: &str, span: &RoleInline) -> Option<String> {
+        self.get(&span.role)?.render(format, span)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::inlines::{InlineNode, InlineParser};
+
+    fn upper(content: &str) -> Option<String> {
+        let content = content.trim();
+        content
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '+')
+            .then(|| content.to_uppercase())
+    }
+
+    #[test]
+    fn test_role_parsing() {
+        let kbd = InlineRole::new("kbd").with_parser(upper);
+        let parser = InlineParser::new().with_role(kbd);
+
+        let nodes = parser.parse("Press {kbd|ctrl+c}, not {kbd|a b} or {漢字|かんじ}.");
+        assert_eq!(
+            nodes,
+            vec![
+                InlineNode::plain("Press ".into()),
+                InlineNode::role(RoleInline::new("kbd", "CTRL+C")),
+                InlineNode::plain(", not {kbd|a b} or ".into()),
+                InlineNode::ruby("漢字".into(), "かんじ".into()),
+                InlineNode::plain(".".into()),
+            ]
+        );
+
+        let nodes = InlineParser::new().parse("{kbd|Ctrl+C}");
+        assert_eq!(nodes, vec![InlineNode::ruby("kbd".into(), "Ctrl+C".into())]);
+
+        let nodes = InlineParser::new().parse("{del|gone}");
+        assert_eq!(
+            nodes,
+            vec![InlineNode::role(RoleInline::new(DEL_ROLE, "gone"))]
+        );
+    }
+
+    #[test]
+    fn test_role_rendering() {
+        let mut registry = RoleRegistry::new();
+        registry.register(
+            InlineRole::new("kbd")
+                .with_renderer("html", |span| format!("<kbd>{}</kbd>", span.content)),
+        );
+        let span = RoleInline::new("kbd", "Ctrl+C");
+        assert_eq!(
+            registry.render("html", &span).as_deref(),
+            Some("<kbd>Ctrl+C</kbd>")
+        );
+        assert_eq!(registry.render("latex", &span), None);
+        assert_eq!(registry.names(), vec!["kbd"]);
+    }
+}
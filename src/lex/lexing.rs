@@ -35,6 +35,9 @@
 //!     At this point, lexing is complete. We have a TokenStream of Line tokens + indent/dedent
 //!     tokens.
 //!
+//!     Tools that only need the tokens of step 1, with their line and column positions, can
+//!     iterate them with [tokens()](tokens::tokens) and skip the rest of the pipeline.
+//!
 //! Indentation Handling
 //!
 //!     In order to make indented blocks tractable by regular parser combinators libraries,
@@ -58,10 +61,13 @@ pub mod base_tokenization;
 pub mod common;
 pub mod line_classification;
 pub mod line_grouping;
+pub mod tokens;
 pub mod transformations;
 
 pub use base_tokenization::tokenize;
 pub use common::{LexError, Lexer, LexerOutput};
+pub use line_classification::{classify_line, classify_lines};
+pub use tokens::{tokens, SpannedToken, Tokens};
 // Re-export token types for consumers that still import them from `lexing`
 pub use crate::lex::token::{LineContainer, LineToken, LineType, Token};
 
@@ -30,9 +30,17 @@
 //!
 //!     This ordering ensures that more specific patterns (like annotation lines) are matched before
 //!     more general ones (like subject lines).
+//!
+//! Classifying Text
+//!
+//!     Tools that don't build the tree, such as diff highlighters or on-type formatting, can
+//!     classify text directly: [classify_line] takes one line, [classify_lines] every line of a
+//!     source. Classification looks at a line alone, so lines are never classified as dialog,
+//!     which depends on the lines around them.
 
 use crate::lex::annotation::analyze_annotation_header_tokens;
 use crate::lex::ast::elements::sequence_marker::{DecorationStyle, Form, Separator};
+use crate::lex::lexing::base_tokenization::tokenize;
 use crate::lex::token::{LineType, Token};
 
 /// Parsed details about a list marker at the start of a line.
@@ -52,6 +60,21 @@ pub struct ParsedListMarker {
     pub form: Form,
 }
 
+/// Classify one line of source text, with or without its newline.
+pub fn classify_line(line: &str) -> LineType {
+    let line = line.trim_end_matches(['\n', '\r']);
+    let tokens: Vec<Token> = tokenize(&format!("{line}\n"))
+        .into_iter()
+        .map(|(token, _)| token)
+        .collect();
+    classify_line_tokens(&tokens)
+}
+
+/// Classify every line of `source`, in order.
+pub fn classify_lines(source: &str) -> impl Iterator<Item = LineType> + '_ {
+    source.lines().map(classify_line)
+}
+
 /// Determine the type of a line based on its tokens.
 ///
 /// Classification follows this specific order (important for correctness):
@@ -437,6 +460,27 @@ pub fn ends_with_colon(tokens: &[Token]) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_classify_text() {
+        assert_eq!(classify_line(""), LineType::BlankLine);
+        assert_eq!(classify_line("    \n"), LineType::BlankLine);
+        assert_eq!(classify_line("    - item"), LineType::ListLine);
+        assert_eq!(classify_line(":: note ::"), LineType::AnnotationStartLine);
+        assert_eq!(classify_line("::"), LineType::AnnotationEndLine);
+        assert_eq!(classify_line("Setup:"), LineType::SubjectLine);
+        assert_eq!(classify_line("1. Setup:"), LineType::SubjectOrListItemLine);
+        assert_eq!(classify_line("Plain text."), LineType::ParagraphLine);
+        let types: Vec<LineType> = classify_lines("Title:\n\n- a\n").collect();
+        assert_eq!(
+            types,
+            vec![
+                LineType::SubjectLine,
+                LineType::BlankLine,
+                LineType::ListLine
+            ]
+        );
+    }
+
     #[test]
     fn test_classify_paragraph_line() {
         let tokens = vec![
@@ -0,0 +1,104 @@
+//! Public token stream
+//!
+//!     [tokens] iterates the base tokens of a source, each with its text, byte span and
+//!     line/column range, without running the rest of the pipeline. It is meant for tools
+//!     that only need tokens, such as syntax highlighters and search indexers.
+//!
+//!     These are the tokens of [base_tokenization](super::base_tokenization): indentation is
+//!     one [Token::Indentation] per level, not indent and dedent events, and every newline is
+//!     a [Token::BlankLine]. Unrecognized input is skipped, as in the parser. Lines and columns
+//!     are 0-based and columns count bytes, as everywhere in the AST.
+
+use crate::lex::ast::{Position, Range};
+use crate::lex::token::Token;
+use logos::Logos;
+
+/// A token with where it is in the source
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken<'a> {
+    pub token: Token,
+    /// Source text of the token
+    pub text: &'a str,
+    pub range: Range,
+}
+
+/// Iterator over the tokens of a source, created by [tokens]
+pub struct Tokens<'a> {
+    lexer: logos::Lexer<'a, Token>,
+    source: &'a str,
+    /// Line of `offset`, and byte offset of the line's start
+    line: usize,
+    line_start: usize,
+    offset: usize,
+}
+
+impl<'a> Tokens<'a> {
+    /// Position of `offset`, which must not be before the last position asked for
+    fn position(&mut self, offset: usize) -> Position {
+        for (index, byte) in self.source.as_bytes()[self.offset..offset]
+            .iter()
+            .enumerate()
+        {
+            if *byte == b'\n' {
+                self.line += 1;
+                self.line_start = self.offset + index + 1;
+            }
+        }
+        self.offset = offset;
+        Position::new(self.line, offset - self.line_start)
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = SpannedToken<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let token = self.lexer.next()?;
+            let Ok(token) = token else {
+                continue;
+            };
+            let span = self.lexer.span();
+            let start = self.position(span.start);
+            let end = self.position(span.end);
+            return Some(SpannedToken {
+                token,
+                text: &self.source[span.clone()],
+                range: Range::new(span, start, end),
+            });
+        }
+    }
+}
+
+/// Tokens of `source`, in order
+pub fn tokens(source: &str) -> Tokens<'_> {
+    Tokens {
+        lexer: Token::lexer(source),
+        source,
+        line: 0,
+        line_start: 0,
+        offset: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::lexing::tokenize;
+
+    #[test]
+    fn test_tokens() {
+        let source = "Title\n\n    - item\n";
+        let found: Vec<SpannedToken> = tokens(source).collect();
+        let spans: Vec<_> = found.iter().map(|t| t.range.span.clone()).collect();
+        let expected: Vec<_> = tokenize(source).into_iter().map(|(_, span)| span).collect();
+        assert_eq!(spans, expected);
+
+        let item = found.iter().find(|t| t.text == "item").unwrap();
+        assert_eq!(item.range.start, Position::new(2, 6));
+        assert_eq!(item.range.end, Position::new(2, 10));
+        // A newline ends on the next line
+        assert_eq!(found[1].token, Token::BlankLine(Some("\n".to_string())));
+        assert_eq!(found[1].range.end, Position::new(1, 0));
+    }
+}
@@ -0,0 +1,200 @@
+//! Lint rules and autofixes
+//!
+//!     Lint rules inspect a parsed document together with its source and report findings:
+//!     a [Diagnostic](crate::lex::ast::Diagnostic) plus, when the problem can be corrected
+//!     mechanically, a [Fix]. Fixes are expressed as [TextEdit]s over the source, the same
+//!     edits the formatter produces, so the CLI (`lex fix`) and editor code actions apply
+//!     them the same way.
+//!
+//!     Rules implement [LintRule] and are collected in a [Linter], which comes with the
+//!     built-in rules registered (see [rules]), optionally with the stricter structure rules.
+//!     Downstream code can register its own. Document templates are checked the same way,
+//!     with a [Schema] as the rule (`lex validate --schema`, see [schema]).
+//!     Findings are suppressed by `:: lex-ignore rule-name ::` directives, see
+//!     [ignore](crate::lex::annotation::ignore).
+//!
+//! Fixing
+//!
+//!     [fix::fix_source] applies every available fix, re-linting between passes since fixes
+//!     of different rules may touch the same lines. For dry runs,
+//!     [unified_diff](crate::lex::formatting::edits::unified_diff) previews the result.
+//!
+//! Reporting
+//!
+//!     Besides plain diagnostics, findings can be rendered as SARIF ([sarif]) for code
+//!     scanning and CI annotations (`lex lint --format sarif`).
+
+pub mod fix;
+pub mod rules;
+pub mod sarif;
+pub mod schema;
+
+pub use fix::{fix_source, FixOutcome};
+pub use sarif::{render_sarif, to_sarif, SarifFile};
+pub use schema::{Schema, SchemaError};
+
+use crate::lex::annotation::ignore::{IgnoreDirectives, RuleKind};
+use crate::lex::ast::{Diagnostic, DiagnosticSeverity, Document, Range};
+use crate::lex::formatting::TextEdit;
+use crate::lex::parsing::parse_document;
+use std::fmt;
+
+/// Source name used in lint diagnostics
+pub const LINT_SOURCE: &str = "lex-lint";
+
+/// Error that can occur while linting
+#[derive(Debug, Clone, PartialEq)]
+pub enum LintError {
+    /// The source could not be parsed
+    ParseError(String),
+}
+
+impl fmt::Display for LintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintError::ParseError(msg) => write!(f, "Parse error: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for LintError {}
+
+/// A mechanical correction for a finding
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fix {
+    /// Short description, suitable as a code action title
+    pub description: String,
+    /// Edits over the linted source
+    pub edits: Vec<TextEdit>,
+}
+
+impl Fix {
+    pub fn new(description: impl Into<String>, edits: Vec<TextEdit>) -> Self {
+        Self {
+            description: description.into(),
+            edits,
+        }
+    }
+}
+
+/// A problem reported by a lint rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct LintFinding {
+    pub diagnostic: Diagnostic,
+    pub fix: Option<Fix>,
+}
+
+impl LintFinding {
+    /// Create a finding for `rule`, without a fix
+    pub fn new(
+        rule: &dyn LintRule,
+        range: Range,
+        severity: DiagnosticSeverity,
+        message: impl Into<String>,
+    ) -> Self {
+        let diagnostic = Diagnostic::new(range, severity, message.into())
+            .with_code(rule.name())
+            .with_source(LINT_SOURCE);
+        Self {
+            diagnostic,
+            fix: None,
+        }
+    }
+
+    pub fn with_fix(mut self, fix: Fix) -> Self {
+        self.fix = Some(fix);
+        self
+    }
+
+    /// Name of the rule that reported the finding
+    pub fn rule(&self) -> &str {
+        self.diagnostic.code.as_deref().unwrap_or_default()
+    }
+}
+
+/// A lint rule
+pub trait LintRule: Send + Sync {
+    /// Stable rule name, used as the diagnostic code
+    fn name(&self) -> &'static str;
+
+    /// One line description of what the rule checks
+    fn description(&self) -> &'static str;
+
+    /// Report findings for `document`, parsed from `source`
+    fn check(&self, document: &Document, source: &str) -> Vec<LintFinding>;
+}
+
+/// A set of lint rules
+pub struct Linter {
+    rules: Vec<Box<dyn LintRule>>,
+}
+
+impl Linter {
+    /// Create a linter without any rules
+    pub fn empty() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// Add a rule
+    pub fn register<R: LintRule + 'static>(&mut self, rule: R) {
+        self.rules.push(Box::new(rule));
+    }
+
+    /// Registered rules, in registration order
+    pub fn rules(&self) -> impl Iterator<Item = &dyn LintRule> {
+        self.rules.iter().map(|rule| rule.as_ref())
+    }
+
+    /// Add the structure rules: heading levels, heading style and heading depth
+    ///
+    /// These are stricter than the defaults and meant for publishing pipelines.
+    pub fn with_structure_rules(mut self) -> Self {
+        self.register(rules::HeadingLevels);
+        self.register(rules::HeadingStyle);
+        self.register(rules::HeadingDepth::default());
+        self
+    }
+
+    /// Look up a rule by name
+    pub fn get(&self, name: &str) -> Option<&dyn LintRule> {
+        self.rules().find(|rule| rule.name() == name)
+    }
+
+    /// Run every rule over an already parsed document
+    ///
+    /// Findings suppressed by `lex-ignore` directives are dropped.
+    pub fn check(&self, document: &Document, source: &str) -> Vec<LintFinding> {
+        let ignores = IgnoreDirectives::collect(document);
+        let mut findings: Vec<LintFinding> = self
+            .rules
+            .iter()
+            .filter(|rule| !ignores.ignores_file(rule.name(), RuleKind::Lint))
+            .flat_map(|rule| rule.check(document, source))
+            .filter(|finding| {
+                let line = finding.diagnostic.range.start.line;
+                !ignores.ignores_line(finding.rule(), RuleKind::Lint, line)
+            })
+            .collect();
+        findings.sort_by_key(|finding| finding.diagnostic.range.span.start);
+        findings
+    }
+
+    /// Parse `source` and run every rule over it
+    pub fn lint(&self, source: &str) -> Result<Vec<LintFinding>, LintError> {
+        let document = parse_document(source).map_err(LintError::ParseError)?;
+        Ok(self.check(&document, source))
+    }
+}
+
+impl Default for Linter {
+    /// A linter with the built-in rules registered
+    fn default() -> Self {
+        let mut linter = Self::empty();
+        linter.register(rules::ListMarkers);
+        linter.register(rules::BlankLines::default());
+        linter.register(rules::SessionNumbering);
+        linter.register(rules::ImageAlt);
+        linter.register(rules::VerbatimClosing);
+        linter
+    }
+}
@@ -0,0 +1,108 @@
+//! Applying lint fixes
+//!
+//! Fixes are computed against the source they were reported for, so fixes whose edits
+//! overlap can't be applied together. Each pass applies a non-overlapping subset, then the
+//! result is linted again, until no fix is left (or a pass limit is hit, as a guard against
+//! rules whose fixes never settle).
+
+use super::{LintError, LintFinding, Linter};
+use crate::lex::formatting::{apply_edits, TextEdit};
+
+/// Upper bound on lint-and-fix passes
+const MAX_PASSES: usize = 8;
+
+/// Result of fixing a source
+#[derive(Debug, Clone, PartialEq)]
+pub struct FixOutcome {
+    /// Fixed source
+    pub output: String,
+    /// Findings whose fix was applied, in application order
+    pub applied: Vec<LintFinding>,
+}
+
+impl FixOutcome {
+    /// Whether any fix changed the source
+    pub fn changed(&self) -> bool {
+        !self.applied.is_empty()
+    }
+}
+
+/// Apply every fix `linter` can produce for `source`
+pub fn fix_source(source: &str, linter: &Linter) -> Result<FixOutcome, LintError> {
+    let mut output = source.to_string();
+    let mut applied = Vec::new();
+    for _ in 0..MAX_PASSES {
+        let findings = linter.lint(&output)?;
+        let batch = non_overlapping(findings);
+        if batch.is_empty() {
+            break;
+        }
+        let edits: Vec<TextEdit> = batch
+            .iter()
+            .flat_map(|finding| finding.fix.iter().flat_map(|fix| fix.edits.iter().cloned()))
+            .collect();
+        output = apply_edits(&output, &edits);
+        applied.extend(batch);
+    }
+    Ok(FixOutcome { output, applied })
+}
+
+/// Fixable findings whose edits don't overlap, in source order
+fn non_overlapping(findings: Vec<LintFinding>) -> Vec<LintFinding> {
+    let mut taken: Vec<std::ops::Range<usize>> = Vec::new();
+    let mut batch = Vec::new();
+    for finding in findings {
+        let Some(fix) = &finding.fix else {
+            continue;
+        };
+        if fix.edits.is_empty() {
+            continue;
+        }
+        let overlaps = fix.edits.iter().any(|edit| {
+            taken.iter().any(|span| {
+                edit.range.span.start < span.end && span.start < edit.range.span.end
+                    || edit.range.span == *span
+            })
+        });
+        if overlaps {
+            continue;
+        }
+        taken.extend(fix.edits.iter().map(|edit| edit.range.span.clone()));
+        batch.push(finding);
+    }
+    batch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::formatting::unified_diff;
+
+    #[test]
+    fn test_fix_source_applies_all_fixes() {
+        let source = "Title\n\n1. First\n\n    - a\n    - b\n\n\n\n\n3. Second\n\n    Text.\n";
+        let outcome = fix_source(source, &Linter::default()).unwrap();
+        assert_eq!(
+            outcome.output,
+            "Title\n\n1. First\n\n    - a\n    - b\n\n\n2. Second\n\n    Text.\n"
+        );
+        let rules: Vec<&str> = outcome
+            .applied
+            .iter()
+            .map(|finding| finding.rule())
+            .collect();
+        assert_eq!(rules, vec!["blank-lines", "session-numbering"]);
+        assert!(fix_source(&outcome.output, &Linter::default())
+            .unwrap()
+            .applied
+            .is_empty());
+    }
+
+    #[test]
+    fn test_dry_run_diff() {
+        let source = "Intro paragraph.\n\n1. One\n1. Two\n";
+        let outcome = fix_source(source, &Linter::default()).unwrap();
+        let diff = unified_diff("notes.lex", source, &outcome.output);
+        assert!(diff.contains("-1. Two\n+2. Two\n"));
+    }
+}
@@ -0,0 +1,770 @@
+//! Built-in lint rules
+//!
+//! - `list-markers`: list items numbered out of sequence, or with a marker style differing
+//!   from the list's first item. Fix: rewrite the marker.
+//! - `blank-lines`: runs of blank lines longer than allowed. Fix: drop the extra lines.
+//! - `session-numbering`: sibling sessions numbered out of sequence. Fix: renumber them.
+//! - `image-alt`: inline images without alternative text, or whose `alt` is empty.
+//! - `verbatim-closing`: `:: label` data lines that close no verbatim block, which leaves the
+//!   block unclosed and its content read as text. Fix: add the colon the block's subject is
+//!   missing, or indent content lines that break the block's wall.
+//!
+//! Structure rules, not registered by default (see
+//! [Linter::with_structure_rules](super::Linter::with_structure_rules)):
+//!
+//! - `heading-levels`: extended session markers whose depth doesn't match the nesting, such as
+//!   `1.1.1.` right under `1.`. Fix: renumber from the enclosing sessions.
+//! - `heading-style`: sessions at the same level numbered in different styles (`1.` and `a)`).
+//!   Fix: renumber in the style of the first session at that level.
+//! - `heading-depth`: sessions nested deeper than allowed.
+//!
+//! Marker normalization follows the formatter's, so fixed sources are left alone by `lex fmt`.
+
+use super::{Fix, LintFinding, LintRule};
+use crate::lex::ast::elements::{DecorationStyle, Form, SequenceMarker};
+use crate::lex::ast::{
+    ContentItem, DiagnosticSeverity, Document, Range, Session, SourceLocation, TextContent,
+};
+use crate::lex::formatting::serializer::normalized_marker;
+use crate::lex::formatting::TextEdit;
+use crate::lex::inlines::{InlineNode, ReferenceType};
+use crate::lex::parsing::recovery::stray_closings;
+
+/// List items must follow the numbering and style of the list's first item
+pub struct ListMarkers;
+
+impl LintRule for ListMarkers {
+    fn name(&self) -> &'static str {
+        "list-markers"
+    }
+
+    fn description(&self) -> &'static str {
+        "List markers follow the first item's style and numbering"
+    }
+
+    fn check(&self, document: &Document, source: &str) -> Vec<LintFinding> {
+        let locations = SourceLocation::new(source);
+        let mut findings = Vec::new();
+        for list in document.root.iter_lists_recursive() {
+            let Some(first) = &list.marker else {
+                continue;
+            };
+            let items = list.items.iter().filter_map(ContentItem::as_list_item);
+            for (index, item) in items.enumerate() {
+                let Some(location) = &item.marker.location else {
+                    continue;
+                };
+                if let Some(finding) =
+                    self.marker_finding(first, index, item.marker(), location, &locations)
+                {
+                    findings.push(finding);
+                }
+            }
+        }
+        findings
+    }
+}
+
+impl ListMarkers {
+    fn marker_finding(
+        &self,
+        first: &SequenceMarker,
+        index: usize,
+        marker: &str,
+        location: &Range,
+        locations: &SourceLocation,
+    ) -> Option<LintFinding> {
+        let expected = normalized_marker(first, index)?;
+        let marker = marker.trim();
+        if marker == expected {
+            return None;
+        }
+        let span = location.span.start..location.span.start + marker.len();
+        let range = locations.byte_range_to_ast_range(&span);
+        let finding = LintFinding::new(
+            self,
+            range.clone(),
+            DiagnosticSeverity::Warning,
+            format!("List marker '{marker}' should be '{expected}'"),
+        )
+        .with_fix(Fix::new(
+            format!("Replace '{marker}' with '{expected}'"),
+            vec![TextEdit::new(range, expected)],
+        ));
+        Some(finding)
+    }
+}
+
+/// Runs of blank lines are capped at `max` lines
+pub struct BlankLines {
+    pub max: usize,
+}
+
+impl Default for BlankLines {
+    fn default() -> Self {
+        Self { max: 2 }
+    }
+}
+
+impl LintRule for BlankLines {
+    fn name(&self) -> &'static str {
+        "blank-lines"
+    }
+
+    fn description(&self) -> &'static str {
+        "No more than the allowed number of consecutive blank lines"
+    }
+
+    fn check(&self, document: &Document, source: &str) -> Vec<LintFinding> {
+        let locations = SourceLocation::new(source);
+        let max = self.max.max(1);
+        let line_offset = |line: usize| locations.line_start(line).unwrap_or(source.len());
+        // Adjacent groups (e.g. one closing a list, the next one in its parent) form one run
+        let mut groups: Vec<&Range> = document
+            .root
+            .iter_all_nodes()
+            .filter_map(|item| match item {
+                ContentItem::BlankLineGroup(group) if group.location != Range::default() => {
+                    Some(&group.location)
+                }
+                _ => None,
+            })
+            .collect();
+        groups.sort_by_key(|location| location.start.line);
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        for location in groups {
+            match runs.last_mut() {
+                Some((_, end)) if *end == location.start.line => *end = location.end.line,
+                _ => runs.push((location.start.line, location.end.line)),
+            }
+        }
+
+        let mut findings = Vec::new();
+        for (start, end) in runs {
+            let count = end - start;
+            if count <= max {
+                continue;
+            }
+            let span = line_offset(start + max)..line_offset(end);
+            let range = locations.byte_range_to_ast_range(&span);
+            let run = locations.byte_range_to_ast_range(&(line_offset(start)..line_offset(end)));
+            findings.push(
+                LintFinding::new(
+                    self,
+                    run,
+                    DiagnosticSeverity::Information,
+                    format!("{count} consecutive blank lines, at most {max} allowed"),
+                )
+                .with_fix(Fix::new(
+                    format!("Remove {} blank line(s)", count - max),
+                    vec![TextEdit::new(range, String::new())],
+                )),
+            );
+        }
+        findings
+    }
+}
+
+/// Sibling sessions with sequence markers are numbered in sequence
+pub struct SessionNumbering;
+
+impl LintRule for SessionNumbering {
+    fn name(&self) -> &'static str {
+        "session-numbering"
+    }
+
+    fn description(&self) -> &'static str {
+        "Numbered sibling sessions follow the first session's numbering"
+    }
+
+    fn check(&self, document: &Document, source: &str) -> Vec<LintFinding> {
+        let locations = SourceLocation::new(source);
+        let mut findings = Vec::new();
+        let containers =
+            std::iter::once(&document.root).chain(document.root.iter_sessions_recursive());
+        for container in containers {
+            let numbered: Vec<&Session> = container
+                .iter_sessions()
+                .filter(|session| session.marker.is_some())
+                .collect();
+            let Some(first) = numbered.first().and_then(|session| session.marker.as_ref()) else {
+                continue;
+            };
+            if !first.is_valid_for_session() {
+                continue;
+            }
+            for (index, session) in numbered.iter().enumerate() {
+                let Some(marker) = &session.marker else {
+                    continue;
+                };
+                let Some(expected) = normalized_marker(first, index) else {
+                    continue;
+                };
+                if marker.as_str() == expected || marker.location == Range::default() {
+                    continue;
+                }
+                let range = locations.byte_range_to_ast_range(&marker.location.span);
+                findings.push(
+                    LintFinding::new(
+                        self,
+                        range.clone(),
+                        DiagnosticSeverity::Warning,
+                        format!(
+                            "Session '{}' should be numbered '{expected}'",
+                            session.title_text()
+                        ),
+                    )
+                    .with_fix(Fix::new(
+                        format!("Renumber to '{expected}'"),
+                        vec![TextEdit::new(range, expected)],
+                    )),
+                );
+            }
+        }
+        findings
+    }
+}
+
+/// Inline images carry alternative text
+pub struct ImageAlt;
+
+impl LintRule for ImageAlt {
+    fn name(&self) -> &'static str {
+        "image-alt"
+    }
+
+    fn description(&self) -> &'static str {
+        "Inline images have alternative text"
+    }
+
+    fn check(&self, document: &Document, source: &str) -> Vec<LintFinding> {
+        let locations = SourceLocation::new(source);
+        let mut findings = Vec::new();
+        for item in document.root.iter_all_nodes() {
+            let texts: Vec<&TextContent> = match item {
+                ContentItem::TextLine(line) => vec![&line.content],
+                ContentItem::Session(session) => vec![&session.title],
+                ContentItem::Definition(definition) => vec![&definition.subject],
+                ContentItem::ListItem(list_item) => list_item.text.iter().collect(),
+                _ => continue,
+            };
+            for text in texts {
+                let Some(location) = &text.location else {
+                    continue;
+                };
+                let written = text.as_string();
+                let mut from = 0;
+                for node in text.inline_items() {
+                    let InlineNode::Reference { data, .. } = node else {
+                        continue;
+                    };
+                    let ReferenceType::Image(image) = &data.reference_type else {
+                        continue;
+                    };
+                    let reference = format!("[{}]", data.raw);
+                    let Some(found) = written[from..].find(&reference) else {
+                        continue;
+                    };
+                    let start = location.span.start + from + found;
+                    from += found + reference.len();
+                    let message = match image.alt.as_deref().map(str::trim) {
+                        None => format!("Image '{}' has no alt text", image.src),
+                        Some("") => format!("Image '{}' has empty alt text", image.src),
+                        Some(_) => continue,
+                    };
+                    findings.push(LintFinding::new(
+                        self,
+                        locations.byte_range_to_ast_range(&(start..start + reference.len())),
+                        DiagnosticSeverity::Warning,
+                        message,
+                    ));
+                }
+            }
+        }
+        findings
+    }
+}
+
+/// Data lines close the verbatim block above them
+pub struct VerbatimClosing;
+
+impl LintRule for VerbatimClosing {
+    fn name(&self) -> &'static str {
+        "verbatim-closing"
+    }
+
+    fn description(&self) -> &'static str {
+        "Verbatim blocks are closed by their data line"
+    }
+
+    fn check(&self, document: &Document, source: &str) -> Vec<LintFinding> {
+        let locations = SourceLocation::new(source);
+        let lines: Vec<&str> = source.lines().collect();
+        let indent = |line: &str| line.len() - line.trim_start().len();
+        let mut findings = Vec::new();
+        for stray in stray_closings(document, source) {
+            let closing = lines[stray.line];
+            let start = locations.line_start(stray.line).unwrap_or(0);
+            let span = start + indent(closing)..start + closing.len();
+            let mut finding = LintFinding::new(
+                self,
+                locations.byte_range_to_ast_range(&span),
+                DiagnosticSeverity::Error,
+                format!("'{}' closes no verbatim block", closing.trim()),
+            );
+            let fix = stray
+                .opening
+                .filter(|&opening| indent(lines[opening]) == indent(closing))
+                .and_then(|opening| self.fix(&lines, opening, stray.line, &locations));
+            if let Some(fix) = fix {
+                finding = finding.with_fix(fix);
+            }
+            findings.push(finding);
+        }
+        findings
+    }
+}
+
+impl VerbatimClosing {
+    /// Close the block between the `opening` and `closing` lines: give the subject its
+    /// colon, or else move content lines short of the wall onto it, keeping their relative
+    /// indentation
+    fn fix(
+        &self,
+        lines: &[&str],
+        opening: usize,
+        closing: usize,
+        locations: &SourceLocation,
+    ) -> Option<Fix> {
+        let line_start = |line: usize| locations.line_start(line).unwrap_or(0);
+        let subject = lines[opening].trim_end();
+        if !subject.ends_with(':') {
+            let at = line_start(opening) + subject.len();
+            let range = locations.byte_range_to_ast_range(&(at..at));
+            return Some(Fix::new(
+                "Add the colon the verbatim subject is missing",
+                vec![TextEdit::new(range, ":".to_string())],
+            ));
+        }
+        let content: Vec<usize> = (opening + 1..closing)
+            .filter(|&line| !lines[line].trim().is_empty())
+            .collect();
+        if content
+            .iter()
+            .any(|&line| lines[line].trim_start_matches(' ').starts_with('\t'))
+        {
+            return None;
+        }
+        let indent = |line: usize| lines[line].len() - lines[line].trim_start().len();
+        let wall = indent(opening) + 4;
+        let shortest = content.iter().map(|&line| indent(line)).min()?;
+        if shortest >= wall {
+            return None;
+        }
+        let padding = " ".repeat(wall - shortest);
+        let edits = content
+            .iter()
+            .map(|&line| {
+                let at = line_start(line);
+                TextEdit::new(
+                    locations.byte_range_to_ast_range(&(at..at)),
+                    padding.clone(),
+                )
+            })
+            .collect();
+        Some(Fix::new(
+            format!(
+                "Indent the verbatim content by {} space(s)",
+                wall - shortest
+            ),
+            edits,
+        ))
+    }
+}
+
+/// A session with its position in the session hierarchy
+struct Heading<'a> {
+    session: &'a Session,
+    /// Nesting level, 1 for top level sessions
+    level: usize,
+    /// Position among numbered siblings, for numbered sessions
+    index: usize,
+    /// 1-based positions of the session and its ancestors among their numbered siblings;
+    /// shorter than `level` when an ancestor is unnumbered
+    path: Vec<usize>,
+}
+
+impl Heading<'_> {
+    fn marker(&self) -> Option<&SequenceMarker> {
+        self.session
+            .marker
+            .as_ref()
+            .filter(|marker| marker.is_valid_for_session())
+    }
+
+    /// Number of levels an extended marker spells out (`1.2.3.` has 3)
+    fn marker_depth(marker: &SequenceMarker) -> usize {
+        marker
+            .as_str()
+            .trim_end_matches(['.', ')'])
+            .split('.')
+            .count()
+    }
+
+    /// The marker this session should carry when numbered like `reference`
+    fn expected_marker(&self, reference: &SequenceMarker) -> Option<String> {
+        match reference.form {
+            Form::Short => normalized_marker(reference, self.index),
+            Form::Extended => self.extended_marker(reference.as_str().ends_with('.')),
+        }
+    }
+
+    /// Numerical extended marker spelling out the full path (`1.2.3.`)
+    fn extended_marker(&self, trailing_period: bool) -> Option<String> {
+        if self.path.len() != self.level {
+            return None;
+        }
+        let numbers: Vec<String> = self.path.iter().map(usize::to_string).collect();
+        let suffix = if trailing_period { "." } else { "" };
+        Some(format!("{}{suffix}", numbers.join(".")))
+    }
+
+    fn range(&self, locations: &SourceLocation) -> Option<Range> {
+        let location = match self.marker() {
+            Some(marker) => &marker.location,
+            None => self.session.title.location.as_ref()?,
+        };
+        (*location != Range::default()).then(|| locations.byte_range_to_ast_range(&location.span))
+    }
+}
+
+/// Every session of `document`, in document order
+fn headings(document: &Document) -> Vec<Heading<'_>> {
+    fn collect<'a>(
+        container: &'a Session,
+        level: usize,
+        path: &[usize],
+        out: &mut Vec<Heading<'a>>,
+    ) {
+        let mut index = 0;
+        for session in container.iter_sessions() {
+            let numbered = session
+                .marker
+                .as_ref()
+                .is_some_and(SequenceMarker::is_valid_for_session);
+            let mut own = path.to_vec();
+            if numbered && path.len() + 1 == level {
+                own.push(index + 1);
+            }
+            out.push(Heading {
+                session,
+                level,
+                index,
+                path: own.clone(),
+            });
+            if numbered {
+                index += 1;
+            }
+            collect(session, level + 1, &own, out);
+        }
+    }
+    let mut out = Vec::new();
+    collect(&document.root, 1, &[], &mut out);
+    out
+}
+
+/// Extended session markers spell out the session's nesting level
+pub struct HeadingLevels;
+
+impl LintRule for HeadingLevels {
+    fn name(&self) -> &'static str {
+        "heading-levels"
+    }
+
+    fn description(&self) -> &'static str {
+        "Extended session markers match the nesting level, without skipping levels"
+    }
+
+    fn check(&self, document: &Document, source: &str) -> Vec<LintFinding> {
+        let locations = SourceLocation::new(source);
+        let mut findings = Vec::new();
+        for heading in headings(document) {
+            let Some(marker) = heading.marker() else {
+                continue;
+            };
+            if marker.form != Form::Extended {
+                continue;
+            }
+            let depth = Heading::marker_depth(marker);
+            if depth == heading.level {
+                continue;
+            }
+            let Some(range) = heading.range(&locations) else {
+                continue;
+            };
+            let mut finding = LintFinding::new(
+                self,
+                range.clone(),
+                DiagnosticSeverity::Warning,
+                format!(
+                    "Session '{}' is numbered as level {depth} but nested at level {}",
+                    heading.session.title_text(),
+                    heading.level
+                ),
+            );
+            let numerical = marker.style == DecorationStyle::Numerical;
+            if let Some(expected) = heading
+                .extended_marker(marker.as_str().ends_with('.'))
+                .filter(|_| numerical)
+            {
+                finding = finding.with_fix(Fix::new(
+                    format!("Renumber to '{expected}'"),
+                    vec![TextEdit::new(range, expected)],
+                ));
+            }
+            findings.push(finding);
+        }
+        findings
+    }
+}
+
+/// Sessions at the same level share a numbering style
+pub struct HeadingStyle;
+
+impl LintRule for HeadingStyle {
+    fn name(&self) -> &'static str {
+        "heading-style"
+    }
+
+    fn description(&self) -> &'static str {
+        "Numbered sessions at the same level use the same marker style"
+    }
+
+    fn check(&self, document: &Document, source: &str) -> Vec<LintFinding> {
+        let locations = SourceLocation::new(source);
+        let headings = headings(document);
+        let mut references: Vec<Option<&SequenceMarker>> = Vec::new();
+        let mut findings = Vec::new();
+        for heading in &headings {
+            let Some(marker) = heading.marker() else {
+                continue;
+            };
+            if references.len() < heading.level {
+                references.resize(heading.level, None);
+            }
+            let reference = *references[heading.level - 1].get_or_insert(marker);
+            let same_style = marker.style == reference.style
+                && marker.separator == reference.separator
+                && marker.form == reference.form;
+            if same_style {
+                continue;
+            }
+            let Some(range) = heading.range(&locations) else {
+                continue;
+            };
+            let mut finding = LintFinding::new(
+                self,
+                range.clone(),
+                DiagnosticSeverity::Warning,
+                format!(
+                    "Session marker '{}' differs in style from '{}', used earlier at this level",
+                    marker.as_str(),
+                    reference.as_str()
+                ),
+            );
+            if let Some(expected) = heading.expected_marker(reference) {
+                finding = finding.with_fix(Fix::new(
+                    format!("Renumber to '{expected}'"),
+                    vec![TextEdit::new(range, expected)],
+                ));
+            }
+            findings.push(finding);
+        }
+        findings
+    }
+}
+
+/// Sessions are nested at most `max` levels deep
+pub struct HeadingDepth {
+    pub max: usize,
+}
+
+impl Default for HeadingDepth {
+    fn default() -> Self {
+        Self { max: 4 }
+    }
+}
+
+impl LintRule for HeadingDepth {
+    fn name(&self) -> &'static str {
+        "heading-depth"
+    }
+
+    fn description(&self) -> &'static str {
+        "Sessions are not nested deeper than the allowed depth"
+    }
+
+    fn check(&self, document: &Document, source: &str) -> Vec<LintFinding> {
+        let locations = SourceLocation::new(source);
+        headings(document)
+            .iter()
+            .filter(|heading| heading.level > self.max)
+            .filter_map(|heading| {
+                let range = heading.range(&locations)?;
+                Some(LintFinding::new(
+                    self,
+                    range,
+                    DiagnosticSeverity::Warning,
+                    format!(
+                        "Session '{}' is nested {} levels deep, at most {} allowed",
+                        heading.session.title_text(),
+                        heading.level,
+                        self.max
+                    ),
+                ))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::lint::Linter;
+    use crate::lex::testing::lexplore::Lexplore;
+
+    fn findings_for(rule: impl LintRule + 'static, source: &str) -> Vec<LintFinding> {
+        let mut linter = Linter::empty();
+        linter.register(rule);
+        linter.lint(source).unwrap()
+    }
+
+    #[test]
+    fn test_benchmark_is_clean() {
+        let source = Lexplore::benchmark(10).source();
+        assert!(Linter::default().lint(&source).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_list_markers() {
+        let source = "Intro paragraph.\n\n1. One\n1. Two\n4. Three\n";
+        let findings = findings_for(ListMarkers, source);
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].diagnostic.range.start.line, 3);
+        assert_eq!(findings[0].fix.as_ref().unwrap().edits[0].new_text, "2.");
+        assert_eq!(findings[0].rule(), "list-markers");
+    }
+
+    #[test]
+    fn test_blank_lines() {
+        let source = "Title\n\nFirst paragraph.\n\n\n\n\nSecond paragraph.\n";
+        let findings = findings_for(BlankLines::default(), source);
+        assert_eq!(findings.len(), 1);
+        let edit = &findings[0].fix.as_ref().unwrap().edits[0];
+        assert_eq!(edit.range.start.line, 5);
+        assert_eq!(edit.range.end.line, 7);
+    }
+
+    #[test]
+    fn test_ignored_findings() {
+        let source = "Intro paragraph.\n\n:: lex-ignore list-markers ::\n1. One\n1. Two\n\nOutro.\n\n1. One\n1. Two\n";
+        let findings = findings_for(ListMarkers, source);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].diagnostic.range.start.line, 9);
+
+        let file_level = format!(":: lex-ignore lint ::\n\n{source}");
+        assert!(findings_for(ListMarkers, &file_level).is_empty());
+    }
+
+    #[test]
+    fn test_session_numbering() {
+        let source = "Title\n\n1. First\n\n    Text.\n\n3. Second\n\n    Text.\n";
+        let findings = findings_for(SessionNumbering, source);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].diagnostic.range.start.line, 6);
+        assert_eq!(findings[0].fix.as_ref().unwrap().edits[0].new_text, "2.");
+    }
+
+    #[test]
+    fn test_image_alt() {
+        let source = "Title\n\nA [!./a.png alt=\"Chart\"] and [!./b.png width=10].\n\n- Icon [!./c.png alt=\"\"]\n- Plain\n";
+        let findings = findings_for(ImageAlt, source);
+        assert_eq!(findings.len(), 2);
+        assert_eq!(
+            findings[0].diagnostic.message,
+            "Image './b.png' has no alt text"
+        );
+        assert_eq!(findings[0].diagnostic.range.start.line, 2);
+        assert_eq!(findings[0].diagnostic.range.start.column, 29);
+        assert_eq!(findings[1].diagnostic.range.start.line, 4);
+        assert!(findings[1].diagnostic.message.ends_with("empty alt text"));
+    }
+
+    #[test]
+    fn test_verbatim_closing() {
+        let broken_wall = "Notes\n\nCode:\n    fn main() {\n  }\n:: rust\n";
+        let findings = findings_for(VerbatimClosing, broken_wall);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].diagnostic.range.start.line, 5);
+        let outcome = crate::lex::lint::fix_source(broken_wall, &Linter::default()).unwrap();
+        assert_eq!(
+            outcome.output,
+            "Notes\n\nCode:\n      fn main() {\n    }\n:: rust\n"
+        );
+
+        let no_colon = "Notes\n\nCode\n    fn main() {}\n:: rust\n";
+        let outcome = crate::lex::lint::fix_source(no_colon, &Linter::default()).unwrap();
+        assert_eq!(
+            outcome.output,
+            "Notes\n\nCode:\n    fn main() {}\n:: rust\n"
+        );
+        let document = crate::lex::parsing::parse_document(&outcome.output).unwrap();
+        assert!(document
+            .root
+            .iter_all_nodes()
+            .any(|item| matches!(item, ContentItem::VerbatimBlock(_))));
+    }
+
+    #[test]
+    fn test_heading_levels() {
+        let source = "Title\n\n1. Intro\n\n    1.1.1. Skipped\n\n        Text.\n\n    1.2. Fine\n\n        Text.\n";
+        let findings = findings_for(HeadingLevels, source);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].diagnostic.range.start.line, 4);
+        assert_eq!(findings[0].fix.as_ref().unwrap().edits[0].new_text, "1.1.");
+    }
+
+    #[test]
+    fn test_heading_style() {
+        let source =
+            "Title\n\n1. First\n\n    Text.\n\n2. Second\n\n    Text.\n\nc) Third\n\n    Text.\n";
+        let findings = findings_for(HeadingStyle, source);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].diagnostic.range.start.line, 10);
+        assert_eq!(findings[0].fix.as_ref().unwrap().edits[0].new_text, "3.");
+    }
+
+    #[test]
+    fn test_heading_depth() {
+        let source =
+            "Title\n\n1. One\n\n    1.1. Two\n\n        1.1.1. Three\n\n            Text.\n";
+        let findings = findings_for(HeadingDepth { max: 2 }, source);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].diagnostic.range.start.line, 6);
+        assert!(findings[0].fix.is_none());
+        assert!(findings_for(HeadingDepth::default(), source).is_empty());
+    }
+
+    #[test]
+    fn test_structure_fixes() {
+        let source =
+            "Title\n\n1. Intro\n\n    1.1.1. Skipped\n\n        Text.\n\nb) Second\n\n    Text.\n";
+        let linter = Linter::default().with_structure_rules();
+        let outcome = crate::lex::lint::fix_source(source, &linter).unwrap();
+        assert_eq!(
+            outcome.output,
+            "Title\n\n1. Intro\n\n    1.1. Skipped\n\n        Text.\n\n2. Second\n\n    Text.\n"
+        );
+        assert!(linter.lint(&outcome.output).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,186 @@
+//! SARIF output for lint findings
+//!
+//! Renders findings as a [SARIF 2.1.0](https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html)
+//! log, the format read by GitHub code scanning and most CI annotation tools. Every rule of
+//! the linter is listed in the tool metadata, whether or not it reported anything, and fixes
+//! are included as SARIF fix objects.
+//!
+//! SARIF lines and columns are 1-based and columns count UTF-16 code units, while AST
+//! positions are 0-based byte offsets, so each file's source is needed for the conversion.
+
+use super::{LintFinding, Linter};
+use crate::lex::ast::{DiagnosticSeverity, Position, Range};
+use serde_json::{json, Value};
+
+/// SARIF schema the output conforms to
+pub const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// SARIF format version
+pub const SARIF_VERSION: &str = "2.1.0";
+
+/// Tool name reported in the SARIF driver
+const TOOL_NAME: &str = "lex";
+
+/// Findings for one linted file
+#[derive(Debug, Clone, Copy)]
+pub struct SarifFile<'a> {
+    /// Path or URI of the file, as it should appear in the report
+    pub uri: &'a str,
+    /// Source the findings were computed from
+    pub source: &'a str,
+    pub findings: &'a [LintFinding],
+}
+
+/// Build a SARIF log for `files`, linted with `linter`
+pub fn to_sarif(linter: &Linter, files: &[SarifFile]) -> Value {
+    let rule_ids: Vec<&str> = linter.rules().map(|rule| rule.name()).collect();
+    let rules: Vec<Value> = linter
+        .rules()
+        .map(|rule| {
+            json!({
+                "id": rule.name(),
+                "name": rule.name(),
+                "shortDescription": { "text": rule.description() },
+            })
+        })
+        .collect();
+
+    let results: Vec<Value> = files
+        .iter()
+        .flat_map(|file| {
+            file.findings
+                .iter()
+                .map(|finding| result(finding, file, &rule_ids))
+        })
+        .collect();
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": SARIF_VERSION,
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": TOOL_NAME,
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+/// [to_sarif], rendered as pretty printed JSON
+pub fn render_sarif(linter: &Linter, files: &[SarifFile]) -> String {
+    serde_json::to_string_pretty(&to_sarif(linter, files)).unwrap_or_default()
+}
+
+fn result(finding: &LintFinding, file: &SarifFile, rule_ids: &[&str]) -> Value {
+    let diagnostic = &finding.diagnostic;
+    let mut result = json!({
+        "ruleId": finding.rule(),
+        "level": level(diagnostic.severity),
+        "message": { "text": diagnostic.message },
+        "locations": [{
+            "physicalLocation": {
+                "artifactLocation": { "uri": file.uri },
+                "region": region(&diagnostic.range, file.source),
+            }
+        }],
+    });
+    if let Some(index) = rule_ids.iter().position(|id| *id == finding.rule()) {
+        result["ruleIndex"] = json!(index);
+    }
+    if let Some(fix) = &finding.fix {
+        let replacements: Vec<Value> = fix
+            .edits
+            .iter()
+            .map(|edit| {
+                json!({
+                    "deletedRegion": region(&edit.range, file.source),
+                    "insertedContent": { "text": edit.new_text },
+                })
+            })
+            .collect();
+        result["fixes"] = json!([{
+            "description": { "text": fix.description },
+            "artifactChanges": [{
+                "artifactLocation": { "uri": file.uri },
+                "replacements": replacements,
+            }],
+        }]);
+    }
+    result
+}
+
+fn level(severity: DiagnosticSeverity) -> &'static str {
+    match severity {
+        DiagnosticSeverity::Error => "error",
+        DiagnosticSeverity::Warning => "warning",
+        DiagnosticSeverity::Information | DiagnosticSeverity::Hint => "note",
+    }
+}
+
+/// SARIF region of `range`, with end columns exclusive as in the AST
+fn region(range: &Range, source: &str) -> Value {
+    json!({
+        "startLine": range.start.line + 1,
+        "startColumn": utf16_column(&range.start, source),
+        "endLine": range.end.line + 1,
+        "endColumn": utf16_column(&range.end, source),
+    })
+}
+
+/// 1-based UTF-16 column of a byte position
+fn utf16_column(position: &Position, source: &str) -> usize {
+    let line = source.split('\n').nth(position.line).unwrap_or_default();
+    let prefix = line.get(..position.column.min(line.len())).unwrap_or(line);
+    prefix.encode_utf16().count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sarif_log() {
+        let source = "Intro paragraph.\n\n1. One\n1. Two\n";
+        let linter = Linter::default();
+        let findings = linter.lint(source).unwrap();
+        let log = to_sarif(
+            &linter,
+            &[SarifFile {
+                uri: "doc.lex",
+                source,
+                findings: &findings,
+            }],
+        );
+
+        assert_eq!(log["version"], SARIF_VERSION);
+        let run = &log["runs"][0];
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        assert_eq!(rules.len(), linter.rules().count());
+
+        let result = &run["results"][0];
+        assert_eq!(result["ruleId"], "list-markers");
+        assert_eq!(result["level"], "warning");
+        assert_eq!(
+            rules[result["ruleIndex"].as_u64().unwrap() as usize]["id"],
+            "list-markers"
+        );
+        let region = &result["locations"][0]["physicalLocation"]["region"];
+        assert_eq!(region["startLine"], 4);
+        assert_eq!(region["startColumn"], 1);
+        assert_eq!(region["endColumn"], 3);
+        let replacement = &result["fixes"][0]["artifactChanges"][0]["replacements"][0];
+        assert_eq!(replacement["insertedContent"]["text"], "2.");
+    }
+
+    #[test]
+    fn test_columns_count_utf16_units() {
+        let source = "é𝄞x\n";
+        assert_eq!(utf16_column(&Position::new(0, 6), source), 4);
+        assert_eq!(utf16_column(&Position::new(1, 0), source), 1);
+    }
+}
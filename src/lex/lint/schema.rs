@@ -0,0 +1,300 @@
+//! Document templates
+//!
+//! A [Schema] describes the structure documents of a team must follow, such as papers that
+//! need an abstract and a methods section. Schemas are written in a small line based
+//! language, usually kept in a `.lexschema` file:
+//!
+//! ```text
+//! # Sessions every paper has, in this order
+//! ordered
+//! session Abstract
+//! session Methods
+//!     session Data
+//! session Results
+//! definitions min-paragraphs 1
+//! ```
+//!
+//! - `session <title>` requires a session with that title (compared without the marker and
+//!   ignoring case). Lines indented under it require sessions inside it.
+//! - `ordered` requires the sessions to appear in the order listed, among their siblings.
+//! - `definitions min-paragraphs <n>` requires every definition to hold at least `n`
+//!   paragraphs.
+//! - `#` starts a comment.
+//!
+//! A schema is a [LintRule] named `schema`, so documents are checked with a [Linter]
+//! holding it ([Schema::validate]) and findings can be suppressed with `lex-ignore` like
+//! any other.
+
+use super::{LintError, LintFinding, LintRule, Linter};
+use crate::lex::ast::{ContentItem, DiagnosticSeverity, Document, Range, Session};
+use std::fmt;
+
+/// Error in a schema source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    /// 1-based line of the schema
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+/// A required session, with the sessions required inside it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequiredSession {
+    pub title: String,
+    pub children: Vec<RequiredSession>,
+}
+
+/// Structure documents must follow
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Schema {
+    /// Sessions required at the top level of the document
+    pub sessions: Vec<RequiredSession>,
+    /// Whether required sessions must appear in the listed order
+    pub ordered: bool,
+    /// Paragraphs every definition must hold at least
+    pub definition_min_paragraphs: usize,
+}
+
+impl Schema {
+    /// Read a schema from its source
+    pub fn parse(source: &str) -> Result<Self, SchemaError> {
+        let mut schema = Schema::default();
+        // Indentation and required session of each open nesting level
+        let mut open: Vec<(usize, RequiredSession)> = Vec::new();
+        for (index, raw) in source.lines().enumerate() {
+            let line = index + 1;
+            let error = |message: String| SchemaError { line, message };
+            let content = raw.split('#').next().unwrap_or_default();
+            if content.trim().is_empty() {
+                continue;
+            }
+            let indent = content.len() - content.trim_start().len();
+            close_sessions(&mut schema, &mut open, indent);
+
+            let mut words = content.split_whitespace();
+            match words.next() {
+                Some("session") => {
+                    let title = words.collect::<Vec<_>>().join(" ");
+                    let title = title.trim_matches('"');
+                    if title.is_empty() {
+                        return Err(error("`session` needs a title".to_string()));
+                    }
+                    open.push((
+                        indent,
+                        RequiredSession {
+                            title: title.to_string(),
+                            children: Vec::new(),
+                        },
+                    ));
+                }
+                _ if indent > 0 => {
+                    return Err(error("Only sessions can be nested".to_string()));
+                }
+                Some("ordered") => schema.ordered = true,
+                Some("definitions") => {
+                    let (Some("min-paragraphs"), Some(count), None) =
+                        (words.next(), words.next(), words.next())
+                    else {
+                        return Err(error(
+                            "Expected `definitions min-paragraphs <n>`".to_string(),
+                        ));
+                    };
+                    schema.definition_min_paragraphs = count
+                        .parse()
+                        .map_err(|_| error(format!("'{count}' is not a number")))?;
+                }
+                Some(other) => return Err(error(format!("Unknown directive '{other}'"))),
+                None => {}
+            }
+        }
+        close_sessions(&mut schema, &mut open, 0);
+        Ok(schema)
+    }
+
+    /// Check `source` against the schema
+    pub fn validate(&self, source: &str) -> Result<Vec<LintFinding>, LintError> {
+        let mut linter = Linter::empty();
+        linter.register(self.clone());
+        linter.lint(source)
+    }
+
+    fn check_sessions(
+        &self,
+        parent: &Session,
+        parent_title: Option<&str>,
+        required: &[RequiredSession],
+        findings: &mut Vec<LintFinding>,
+    ) {
+        let mut previous: Option<(&str, usize)> = None;
+        for requirement in required {
+            let found = parent
+                .iter_sessions()
+                .enumerate()
+                .find(|(_, session)| same_title(session, &requirement.title));
+            let Some((position, session)) = found else {
+                // The document itself has no header; its start is reported
+                let range = parent.header_location().cloned().unwrap_or_default();
+                let message = match parent_title {
+                    Some(parent) => format!(
+                        "Missing required session '{}' in '{parent}'",
+                        requirement.title
+                    ),
+                    None => format!("Missing required session '{}'", requirement.title),
+                };
+                findings.push(LintFinding::new(
+                    self,
+                    range,
+                    DiagnosticSeverity::Error,
+                    message,
+                ));
+                continue;
+            };
+            if let Some((before, before_position)) = previous {
+                if self.ordered && position < before_position {
+                    findings.push(LintFinding::new(
+                        self,
+                        session_range(session),
+                        DiagnosticSeverity::Error,
+                        format!("Session '{}' must come after '{before}'", requirement.title),
+                    ));
+                }
+            }
+            previous = Some((&requirement.title, position));
+            self.check_sessions(
+                session,
+                Some(&requirement.title),
+                &requirement.children,
+                findings,
+            );
+        }
+    }
+}
+
+impl LintRule for Schema {
+    fn name(&self) -> &'static str {
+        "schema"
+    }
+
+    fn description(&self) -> &'static str {
+        "The document follows the structure of its template"
+    }
+
+    fn check(&self, document: &Document, _source: &str) -> Vec<LintFinding> {
+        let mut findings = Vec::new();
+        self.check_sessions(&document.root, None, &self.sessions, &mut findings);
+        for definition in document.root.iter_definitions_recursive() {
+            let paragraphs = definition
+                .children
+                .iter()
+                .filter(|item| matches!(item, ContentItem::Paragraph(_)))
+                .count();
+            if paragraphs < self.definition_min_paragraphs {
+                let range = definition
+                    .header_location()
+                    .cloned()
+                    .unwrap_or_else(|| definition.location.clone());
+                findings.push(LintFinding::new(
+                    self,
+                    range,
+                    DiagnosticSeverity::Error,
+                    format!(
+                        "Definition '{}' has {paragraphs} paragraphs, at least {} required",
+                        definition.subject.as_string().trim(),
+                        self.definition_min_paragraphs
+                    ),
+                ));
+            }
+        }
+        findings
+    }
+}
+
+/// Close the sessions opened at `indent` or deeper, attaching each to its parent
+fn close_sessions(schema: &mut Schema, open: &mut Vec<(usize, RequiredSession)>, indent: usize) {
+    while let Some((level, session)) = open.pop() {
+        if level < indent {
+            open.push((level, session));
+            break;
+        }
+        match open.last_mut() {
+            Some((_, parent)) => parent.children.push(session),
+            None => schema.sessions.push(session),
+        }
+    }
+}
+
+fn same_title(session: &Session, title: &str) -> bool {
+    session
+        .title_text()
+        .trim()
+        .trim_end_matches(':')
+        .eq_ignore_ascii_case(title)
+}
+
+fn session_range(session: &Session) -> Range {
+    session
+        .header_location()
+        .cloned()
+        .unwrap_or_else(|| session.location.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCHEMA: &str = "# Papers\nordered\nsession Abstract\nsession Methods\n    session Data\ndefinitions min-paragraphs 1\n";
+
+    #[test]
+    fn test_parse_schema() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        assert!(schema.ordered);
+        assert_eq!(schema.definition_min_paragraphs, 1);
+        assert_eq!(schema.sessions.len(), 2);
+        assert_eq!(schema.sessions[1].children[0].title, "Data");
+
+        let error = Schema::parse("session Intro\n    ordered\n").unwrap_err();
+        assert_eq!(error.line, 2);
+        assert_eq!(
+            Schema::parse("sections 2\n").unwrap_err().to_string(),
+            "line 1: Unknown directive 'sections'"
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = Schema::parse(SCHEMA).unwrap();
+        let valid = "Paper\n\n1. Abstract\n\n    Text.\n\n2. Methods\n\n    2.1. Data\n\n        Term:\n            Meaning.\n";
+        assert!(schema.validate(valid).unwrap().is_empty());
+
+        let invalid = "Paper\n\n1. Methods\n\n    Term:\n        - one\n        - two\n\n2. Abstract\n\n    Text.\n";
+        let messages: Vec<String> = schema
+            .validate(invalid)
+            .unwrap()
+            .into_iter()
+            .map(|finding| finding.diagnostic.message)
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Session 'Methods' must come after 'Abstract'",
+                "Missing required session 'Data' in 'Methods'",
+                "Definition 'Term' has 0 paragraphs, at least 1 required",
+            ]
+        );
+
+        let missing = schema.validate("Paper\n\nText.\n").unwrap();
+        assert_eq!(
+            missing[0].diagnostic.message,
+            "Missing required session 'Abstract'"
+        );
+        assert_eq!(missing[0].rule(), "schema");
+    }
+}
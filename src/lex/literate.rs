@@ -0,0 +1,338 @@
+//! Literate programming
+//!
+//!     Verbatim blocks labeled with a language can be extracted ("tangled") into source files,
+//!     so a Lex document can be the primary source of a program:
+//!
+//!         Entry point:
+//!             fn main() {
+//!                 <<greeting>>
+//!             }
+//!         :: rust file=src/main.rs
+//!
+//!         The greeting:
+//!             println!("Hello");
+//!         :: rust name=greeting
+//!
+//!     - `file=path`: the block is written to `path`. Blocks naming the same file are
+//!       concatenated in document order.
+//!     - `name=chunk`: the block is a named chunk, included wherever a line reads
+//!       `<<chunk>>`, at that line's indentation. Chunks with the same name are concatenated.
+//!
+//!     Blocks can be filtered by language (the closing label) and by an annotation attached
+//!     to them (see [TangleOptions]). [weave_check] goes the other way: it compares tangled
+//!     files on disk with what the document would produce, to catch code edited outside the
+//!     document.
+
+use crate::lex::ast::{ContentItem, Document, Verbatim};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Which verbatim blocks take part in tangling
+#[derive(Debug, Clone, Default)]
+pub struct TangleOptions {
+    /// Languages (closing labels) to keep; all when empty
+    pub languages: Vec<String>,
+    /// Only keep blocks carrying an annotation with this label
+    pub annotation: Option<String>,
+}
+
+/// A verbatim block as source code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodeBlock {
+    pub subject: String,
+    /// Closing label of the block
+    pub language: String,
+    /// Value of the `file` parameter
+    pub file: Option<String>,
+    /// Value of the `name` parameter
+    pub name: Option<String>,
+    /// Content, with indentation relative to the block's wall
+    pub text: String,
+    /// Line (0-based) of the block's subject
+    pub line: usize,
+}
+
+/// A source file produced by tangling
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TangledFile {
+    pub path: String,
+    pub content: String,
+}
+
+/// Error that can occur while tangling
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TangleError {
+    /// A `<<name>>` line refers to no chunk
+    UnknownChunk { name: String, line: usize },
+    /// A chunk includes itself, directly or not
+    CyclicInclude { name: String },
+}
+
+impl fmt::Display for TangleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TangleError::UnknownChunk { name, line } => {
+                write!(f, "Unknown chunk '{name}' in block at line {}", line + 1)
+            }
+            TangleError::CyclicInclude { name } => write!(f, "Chunk '{name}' includes itself"),
+        }
+    }
+}
+
+impl std::error::Error for TangleError {}
+
+/// How a tangled file on disk differs from the document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WeaveMismatch {
+    /// The file does not exist
+    Missing { path: String },
+    /// The file differs, starting at `line` (0-based)
+    Modified { path: String, line: usize },
+}
+
+impl fmt::Display for WeaveMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WeaveMismatch::Missing { path } => write!(f, "{path}: missing"),
+            WeaveMismatch::Modified { path, line } => {
+                write!(f, "{path}: differs from the document at line {}", line + 1)
+            }
+        }
+    }
+}
+
+/// Code blocks of `doc` selected by `options`, in document order
+pub fn code_blocks(doc: &Document, options: &TangleOptions) -> Vec<CodeBlock> {
+    doc.root
+        .iter_all_nodes()
+        .filter_map(|item| match item {
+            ContentItem::VerbatimBlock(verbatim) => Some(verbatim.as_ref()),
+            _ => None,
+        })
+        .filter(|verbatim| selected(verbatim, options))
+        .map(code_block)
+        .collect()
+}
+
+/// Tangle `doc` into source files, in order of first appearance
+pub fn tangle(doc: &Document, options: &TangleOptions) -> Result<Vec<TangledFile>, TangleError> {
+    let blocks = code_blocks(doc, options);
+    let mut chunks: HashMap<&str, Vec<&CodeBlock>> = HashMap::new();
+    for block in &blocks {
+        if let Some(name) = &block.name {
+            chunks.entry(name).or_default().push(block);
+        }
+    }
+
+    let mut files: Vec<TangledFile> = Vec::new();
+    for block in &blocks {
+        let Some(path) = &block.file else {
+            continue;
+        };
+        let mut content = String::new();
+        expand(block, &chunks, "", &mut Vec::new(), &mut content)?;
+        match files.iter_mut().find(|file| &file.path == path) {
+            Some(file) => file.content.push_str(&content),
+            None => files.push(TangledFile {
+                path: path.clone(),
+                content,
+            }),
+        }
+    }
+    Ok(files)
+}
+
+/// Compare tangled files with their current contents, as returned by `read`
+pub fn weave_check(
+    doc: &Document,
+    options: &TangleOptions,
+    read: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<WeaveMismatch>, TangleError> {
+    let mut mismatches = Vec::new();
+    for file in tangle(doc, options)? {
+        let Some(current) = read(&file.path) else {
+            mismatches.push(WeaveMismatch::Missing { path: file.path });
+            continue;
+        };
+        if current == file.content {
+            continue;
+        }
+        let expected: Vec<&str> = file.content.lines().collect();
+        let actual: Vec<&str> = current.lines().collect();
+        let line = expected
+            .iter()
+            .zip(&actual)
+            .position(|(expected, actual)| expected != actual)
+            .unwrap_or(expected.len().min(actual.len()));
+        mismatches.push(WeaveMismatch::Modified {
+            path: file.path,
+            line,
+        });
+    }
+    Ok(mismatches)
+}
+
+fn selected(verbatim: &Verbatim, options: &TangleOptions) -> bool {
+    let language = &verbatim.closing_data.label.value;
+    if !options.languages.is_empty() && !options.languages.contains(language) {
+        return false;
+    }
+    match &options.annotation {
+        Some(label) => verbatim
+            .annotations
+            .iter()
+            .any(|annotation| &annotation.data.label.value == label),
+        None => true,
+    }
+}
+
+pub(crate) fn code_block(verbatim: &Verbatim) -> CodeBlock {
+    let lines = verbatim.content_lines();
+    let wall = lines
+        .iter()
+        .filter(|line| !line.content.as_string().is_empty())
+        .map(|line| line.location.start.column)
+        .min()
+        .unwrap_or(0);
+    let mut text = String::new();
+    for line in lines {
+        let content = line.content.as_string();
+        if !content.is_empty() {
+            let extra = line.location.start.column.saturating_sub(wall);
+            text.push_str(&" ".repeat(extra));
+            text.push_str(content);
+        }
+        text.push('\n');
+    }
+
+    CodeBlock {
+        subject: verbatim.subject.as_string().to_string(),
+        language: verbatim.closing_data.label.value.clone(),
+        file: verbatim.parameter("file").map(str::to_string),
+        name: verbatim.parameter("name").map(str::to_string),
+        text,
+        line: verbatim.location.start.line,
+    }
+}
+
+/// Append `block`'s text to `out`, expanding `<<name>>` lines
+fn expand<'a>(
+    block: &'a CodeBlock,
+    chunks: &HashMap<&'a str, Vec<&'a CodeBlock>>,
+    indent: &str,
+    stack: &mut Vec<&'a str>,
+    out: &mut String,
+) -> Result<(), TangleError> {
+    for line in block.text.lines() {
+        let trimmed = line.trim();
+        let include = trimmed
+            .strip_prefix("<<")
+            .and_then(|rest| rest.strip_suffix(">>"));
+        let Some(name) = include else {
+            if !line.is_empty() {
+                out.push_str(indent);
+                out.push_str(line);
+            }
+            out.push('\n');
+            continue;
+        };
+        let Some((key, parts)) = chunks.get_key_value(name) else {
+            return Err(TangleError::UnknownChunk {
+                name: name.to_string(),
+                line: block.line,
+            });
+        };
+        if stack.contains(key) {
+            return Err(TangleError::CyclicInclude {
+                name: name.to_string(),
+            });
+        }
+        stack.push(key);
+        let nested = format!("{indent}{}", &line[..line.len() - line.trim_start().len()]);
+        for part in parts {
+            expand(part, chunks, &nested, stack, out)?;
+        }
+        stack.pop();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    const SOURCE: &str = "Title\n\nThe program.\n\nEntry point:\n    fn main() {\n        <<greeting>>\n    }\n:: rust file=src/main.rs\n\nThe greeting, in two parts.\n\nGreeting:\n    println!(\"Hello\");\n:: rust name=greeting\n\nMore.\n\nGreeting:\n    println!(\"World\");\n:: rust name=greeting\n\nA script.\n\nScript:\n    print(1)\n:: python file=run.py\n";
+
+    #[test]
+    fn test_tangle() {
+        let doc = parse_document(SOURCE).unwrap();
+        let files = tangle(&doc, &TangleOptions::default()).unwrap();
+        assert_eq!(
+            files,
+            vec![
+                TangledFile {
+                    path: "src/main.rs".to_string(),
+                    content: "fn main() {\n    println!(\"Hello\");\n    println!(\"World\");\n}\n"
+                        .to_string(),
+                },
+                TangledFile {
+                    path: "run.py".to_string(),
+                    content: "print(1)\n".to_string(),
+                },
+            ]
+        );
+
+        let options = TangleOptions {
+            languages: vec!["python".to_string()],
+            ..Default::default()
+        };
+        let files = tangle(&doc, &options).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "run.py");
+    }
+
+    #[test]
+    fn test_tangle_errors() {
+        let doc = parse_document(
+            "Title\n\nText.\n\nLoop:\n    <<again>>\n:: rust file=a.rs, name=again\n",
+        )
+        .unwrap();
+        assert_eq!(
+            tangle(&doc, &TangleOptions::default()),
+            Err(TangleError::CyclicInclude {
+                name: "again".to_string()
+            })
+        );
+
+        let doc =
+            parse_document("Title\n\nText.\n\nMissing:\n    <<nowhere>>\n:: rust file=a.rs\n")
+                .unwrap();
+        assert!(matches!(
+            tangle(&doc, &TangleOptions::default()),
+            Err(TangleError::UnknownChunk { .. })
+        ));
+    }
+
+    #[test]
+    fn test_weave_check() {
+        let doc = parse_document(SOURCE).unwrap();
+        let mismatches = weave_check(&doc, &TangleOptions::default(), |path| match path {
+            "src/main.rs" => Some("fn main() {\n    println!(\"Hi\");\n}\n".to_string()),
+            _ => None,
+        })
+        .unwrap();
+        assert_eq!(
+            mismatches,
+            vec![
+                WeaveMismatch::Modified {
+                    path: "src/main.rs".to_string(),
+                    line: 1
+                },
+                WeaveMismatch::Missing {
+                    path: "run.py".to_string()
+                },
+            ]
+        );
+    }
+}
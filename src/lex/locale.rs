@@ -0,0 +1,305 @@
+//! Localized generated text
+//!
+//!     Output formats add text of their own to a document: "Table of Contents", "Figure",
+//!     callout titles, and so on. These strings are looked up in a [Locale] rather than
+//!     hard-coded, so a document written in French gets French labels.
+//!
+//!     Built-in tables cover English, German, French, Spanish, Portuguese and Italian. The
+//!     locale of a document comes from a `lang` tag on a document-level annotation
+//!     (`:: lang fr ::` or `:: meta lang=fr ::`), see [Locale::for_document]. Users can override or add any
+//!     translation with [Locale::set], or load a whole table from configuration with
+//!     [Locale::with_translations]. Terms missing from a table fall back to English.
+//!
+//!     Text direction (left-to-right or right-to-left) of documents and sessions is resolved
+//!     in [direction], and the language of individual elements (`:: lang fr ::`) in
+//!     [language].
+
+pub mod direction;
+pub mod language;
+
+pub use direction::{document_direction, session_direction, TextDirection};
+pub use language::{document_language, language_at, language_spans, LanguageSpan};
+
+use crate::lex::ast::Document;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A generated string
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Term {
+    TableOfContents,
+    Figure,
+    Table,
+    Footnotes,
+    References,
+    Note,
+    Tip,
+    Important,
+    Warning,
+    Caution,
+}
+
+impl Term {
+    pub const ALL: [Term; 10] = [
+        Term::TableOfContents,
+        Term::Figure,
+        Term::Table,
+        Term::Footnotes,
+        Term::References,
+        Term::Note,
+        Term::Tip,
+        Term::Important,
+        Term::Warning,
+        Term::Caution,
+    ];
+
+    /// Stable key, as used in configuration
+    pub fn key(&self) -> &'static str {
+        match self {
+            Term::TableOfContents => "table-of-contents",
+            Term::Figure => "figure",
+            Term::Table => "table",
+            Term::Footnotes => "footnotes",
+            Term::References => "references",
+            Term::Note => "note",
+            Term::Tip => "tip",
+            Term::Important => "important",
+            Term::Warning => "warning",
+            Term::Caution => "caution",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|term| term.key() == key)
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.key())
+    }
+}
+
+/// Built-in tables, in [Term::ALL] order
+const BUILTIN: [(&str, [&str; 10]); 6] = [
+    (
+        "en",
+        [
+            "Table of Contents",
+            "Figure",
+            "Table",
+            "Footnotes",
+            "References",
+            "Note",
+            "Tip",
+            "Important",
+            "Warning",
+            "Caution",
+        ],
+    ),
+    (
+        "de",
+        [
+            "Inhaltsverzeichnis",
+            "Abbildung",
+            "Tabelle",
+            "Fußnoten",
+            "Literatur",
+            "Hinweis",
+            "Tipp",
+            "Wichtig",
+            "Warnung",
+            "Vorsicht",
+        ],
+    ),
+    (
+        "fr",
+        [
+            "Table des matières",
+            "Figure",
+            "Tableau",
+            "Notes",
+            "Références",
+            "Remarque",
+            "Astuce",
+            "Important",
+            "Avertissement",
+            "Attention",
+        ],
+    ),
+    (
+        "es",
+        [
+            "Índice",
+            "Figura",
+            "Tabla",
+            "Notas",
+            "Referencias",
+            "Nota",
+            "Consejo",
+            "Importante",
+            "Advertencia",
+            "Precaución",
+        ],
+    ),
+    (
+        "pt",
+        [
+            "Sumário",
+            "Figura",
+            "Tabela",
+            "Notas de rodapé",
+            "Referências",
+            "Nota",
+            "Dica",
+            "Importante",
+            "Aviso",
+            "Cuidado",
+        ],
+    ),
+    (
+        "it",
+        [
+            "Indice",
+            "Figura",
+            "Tabella",
+            "Note",
+            "Bibliografia",
+            "Nota",
+            "Suggerimento",
+            "Importante",
+            "Avvertenza",
+            "Attenzione",
+        ],
+    ),
+];
+
+/// Translations of generated strings for one language
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    /// Language tag, such as `fr` or `pt-BR`
+    pub code: String,
+    strings: HashMap<Term, String>,
+}
+
+impl Locale {
+    /// An empty table for `code`, where every term falls back to English
+    pub fn new(code: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            strings: HashMap::new(),
+        }
+    }
+
+    pub fn english() -> Self {
+        Self::builtin("en").unwrap_or_else(|| Self::new("en"))
+    }
+
+    /// The built-in table for a language tag; regional variants use the language's table
+    pub fn builtin(code: &str) -> Option<Self> {
+        let language = code
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        let (_, strings) = BUILTIN.iter().find(|(tag, _)| *tag == language)?;
+        Some(Self {
+            code: code.to_string(),
+            strings: Term::ALL
+                .into_iter()
+                .zip(strings.iter())
+                .map(|(term, text)| (term, text.to_string()))
+                .collect(),
+        })
+    }
+
+    /// The locale named by the document's `lang` tag, or English
+    ///
+    /// Languages without a built-in table get an empty one (that is, English text) carrying
+    /// the document's language tag.
+    pub fn for_document(document: &Document) -> Self {
+        match document_language(document) {
+            Some(lang) => Self::builtin(&lang).unwrap_or_else(|| Self::new(lang)),
+            None => Self::english(),
+        }
+    }
+
+    /// Add or replace the translation of `term`
+    pub fn set(&mut self, term: Term, text: impl Into<String>) {
+        self.strings.insert(term, text.into());
+    }
+
+    /// Add translations keyed by [Term::key]; returns the keys that name no term
+    pub fn with_translations<'k>(
+        mut self,
+        translations: impl IntoIterator<Item = (&'k str, String)>,
+    ) -> (Self, Vec<String>) {
+        let mut unknown = Vec::new();
+        for (key, text) in translations {
+            match Term::from_key(key) {
+                Some(term) => self.set(term, text),
+                None => unknown.push(key.to_string()),
+            }
+        }
+        (self, unknown)
+    }
+
+    /// Direction of the locale's language
+    pub fn direction(&self) -> TextDirection {
+        TextDirection::of_language(&self.code)
+    }
+
+    /// Text for `term`, falling back to English
+    pub fn get(&self, term: Term) -> &str {
+        if let Some(text) = self.strings.get(&term) {
+            return text;
+        }
+        let index = Term::ALL
+            .iter()
+            .position(|candidate| *candidate == term)
+            .unwrap_or_default();
+        BUILTIN[0].1[index]
+    }
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self::english()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    #[test]
+    fn test_builtin_and_fallback() {
+        let french = Locale::builtin("fr-CA").unwrap();
+        assert_eq!(french.get(Term::TableOfContents), "Table des matières");
+        assert!(Locale::builtin("xx").is_none());
+
+        let mut custom = Locale::new("nl");
+        custom.set(Term::Figure, "Afbeelding");
+        assert_eq!(custom.get(Term::Figure), "Afbeelding");
+        assert_eq!(custom.get(Term::Footnotes), "Footnotes");
+
+        let (custom, unknown) = custom.with_translations([
+            ("footnotes", "Voetnoten".to_string()),
+            ("colophon", "Colofon".to_string()),
+        ]);
+        assert_eq!(custom.get(Term::Footnotes), "Voetnoten");
+        assert_eq!(unknown, vec!["colophon"]);
+    }
+
+    #[test]
+    fn test_document_locale() {
+        let doc = parse_document(":: meta lang=de ::\n\nTitel\n\nText.\n").unwrap();
+        let locale = Locale::for_document(&doc);
+        assert_eq!(locale.code, "de");
+        assert_eq!(locale.get(Term::Warning), "Warnung");
+
+        let doc = parse_document("Title\n\nText.\n").unwrap();
+        assert_eq!(Locale::for_document(&doc).code, "en");
+    }
+}
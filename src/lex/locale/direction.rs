@@ -0,0 +1,157 @@
+//! Text direction
+//!
+//! Documents and sessions are left-to-right unless told or detected otherwise. A `dir`
+//! parameter (`ltr` or `rtl`) on an annotation sets the direction explicitly: on a
+//! document-level annotation for the whole document, on a session's annotation for that
+//! session and its descendants. Without one, a document takes the direction of its `lang`
+//! (Arabic, Hebrew, Persian, ...), and failing that, of the first strong character of its text.
+//!
+//! Renderers use this for `dir="rtl"` in HTML, mirrored list markers and bidi-aware layout.
+
+use crate::lex::ast::{Annotation, Document, Session};
+use crate::lex::locale::Locale;
+use std::fmt;
+
+/// Languages written right-to-left
+const RTL_LANGUAGES: [&str; 9] = ["ar", "arc", "dv", "fa", "he", "ku", "ps", "ur", "yi"];
+
+/// Direction of a run of text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextDirection {
+    #[default]
+    Ltr,
+    Rtl,
+}
+
+impl TextDirection {
+    /// Name used in `dir` parameters and the HTML `dir` attribute
+    pub fn name(&self) -> &'static str {
+        match self {
+            TextDirection::Ltr => "ltr",
+            TextDirection::Rtl => "rtl",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().trim_matches('"').to_lowercase().as_str() {
+            "ltr" => Some(TextDirection::Ltr),
+            "rtl" => Some(TextDirection::Rtl),
+            _ => None,
+        }
+    }
+
+    /// Direction of a language tag, such as `ar` or `he-IL`
+    pub fn of_language(code: &str) -> Self {
+        let language = code
+            .split(['-', '_'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        if RTL_LANGUAGES.contains(&language.as_str()) {
+            TextDirection::Rtl
+        } else {
+            TextDirection::Ltr
+        }
+    }
+
+    /// Direction of the first strongly directional character of `text`, if any
+    pub fn detect(text: &str) -> Option<Self> {
+        text.chars().find_map(|c| {
+            if is_rtl_char(c) {
+                Some(TextDirection::Rtl)
+            } else if c.is_alphabetic() {
+                Some(TextDirection::Ltr)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl fmt::Display for TextDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Hebrew, Arabic, Syriac, Thaana, NKo and their presentation forms
+fn is_rtl_char(c: char) -> bool {
+    matches!(c,
+        '\u{0590}'..='\u{08FF}'
+        | '\u{FB1D}'..='\u{FDFF}'
+        | '\u{FE70}'..='\u{FEFF}'
+        | '\u{10800}'..='\u{10FFF}'
+        | '\u{1E800}'..='\u{1EFFF}')
+}
+
+/// Direction set by a `dir` parameter on one of `annotations`
+fn explicit<'a>(annotations: impl IntoIterator<Item = &'a Annotation>) -> Option<TextDirection> {
+    annotations
+        .into_iter()
+        .flat_map(|annotation| annotation.data.parameters.iter())
+        .find(|param| param.key == "dir")
+        .and_then(|param| TextDirection::from_name(&param.value))
+}
+
+/// Direction of a whole document
+pub fn document_direction(document: &Document) -> TextDirection {
+    let annotations = document
+        .annotations
+        .iter()
+        .chain(document.root.annotations.iter());
+    if let Some(direction) = explicit(annotations) {
+        return direction;
+    }
+    if Locale::for_document(document).direction() == TextDirection::Rtl {
+        return TextDirection::Rtl;
+    }
+    document
+        .root
+        .iter_paragraphs_recursive()
+        .find_map(|paragraph| TextDirection::detect(&paragraph.text()))
+        .unwrap_or_default()
+}
+
+/// Direction of `session`, given the direction of its parent
+pub fn session_direction(session: &Session, inherited: TextDirection) -> TextDirection {
+    explicit(&session.annotations).unwrap_or(inherited)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    #[test]
+    fn test_detection() {
+        assert_eq!(TextDirection::detect("123 שלום"), Some(TextDirection::Rtl));
+        assert_eq!(TextDirection::detect("- مرحبا"), Some(TextDirection::Rtl));
+        assert_eq!(
+            TextDirection::detect("Hello שלום"),
+            Some(TextDirection::Ltr)
+        );
+        assert_eq!(TextDirection::detect("42."), None);
+        assert_eq!(TextDirection::of_language("he-IL"), TextDirection::Rtl);
+        assert_eq!(TextDirection::of_language("en"), TextDirection::Ltr);
+    }
+
+    #[test]
+    fn test_document_and_session_direction() {
+        let doc = parse_document(":: meta lang=ar ::\n\nTitle\n\nText.\n").unwrap();
+        assert_eq!(document_direction(&doc), TextDirection::Rtl);
+
+        let doc = parse_document("Title\n\nשלום עולם.\n").unwrap();
+        assert_eq!(document_direction(&doc), TextDirection::Rtl);
+
+        let source =
+            "Title\n\nText.\n\n1. Plain\n\n    Text.\n\n:: meta dir=rtl ::\n2. Quoted\n\n    Text.\n";
+        let doc = parse_document(source).unwrap();
+        assert_eq!(document_direction(&doc), TextDirection::Ltr);
+        let directions: Vec<_> = doc
+            .root
+            .iter_sessions_recursive()
+            .map(|session| session_direction(session, TextDirection::Ltr))
+            .collect();
+        assert_eq!(directions, vec![TextDirection::Ltr, TextDirection::Rtl]);
+    }
+}
@@ -0,0 +1,144 @@
+//! Language tagging
+//!
+//! Any element can be tagged with the language it is written in, with a `lang` annotation
+//! (`:: lang fr ::`) or a `lang` parameter on one (`:: meta lang=fr ::`). A tag on a
+//! document-level annotation sets the language of the whole document; a tag on an element
+//! covers that element and its descendants, down to the next tag.
+//!
+//! Renderers use [language_spans] for `lang` attributes in HTML and hyphenation patterns in
+//! PDF; spell checkers use [language_at] to pick the dictionary for a position.
+
+use crate::lex::ast::traits::AstNode;
+use crate::lex::ast::{Annotation, ContentItem, Document, Position, Range};
+
+/// Annotation label tagging a language
+pub const LANG_LABEL: &str = "lang";
+
+/// An element tagged with a language different from the one it inherits
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LanguageSpan {
+    /// Range of the element, title included for sessions
+    pub range: Range,
+    /// Language tag, such as `fr` or `pt-BR`
+    pub language: String,
+}
+
+/// Language tagged by one of `annotations`
+pub fn language_of<'a>(annotations: impl IntoIterator<Item = &'a Annotation>) -> Option<String> {
+    annotations.into_iter().find_map(|annotation| {
+        let label = annotation.data.label.value.trim();
+        let tagged = label
+            .strip_prefix(LANG_LABEL)
+            .filter(|rest| rest.starts_with(char::is_whitespace))
+            .map(str::trim)
+            .or_else(|| {
+                annotation
+                    .data
+                    .parameters
+                    .iter()
+                    .find(|param| param.key == LANG_LABEL)
+                    .map(|param| param.value.trim_matches('"'))
+            })?;
+        (!tagged.is_empty()).then(|| tagged.to_string())
+    })
+}
+
+/// Language of the whole document, if tagged
+pub fn document_language(document: &Document) -> Option<String> {
+    language_of(
+        document
+            .annotations
+            .iter()
+            .chain(document.root.annotations.iter()),
+    )
+}
+
+/// Elements of `document` whose language differs from their parent's, in document order
+pub fn language_spans(document: &Document) -> Vec<LanguageSpan> {
+    let mut spans = Vec::new();
+    collect(
+        &document.root.children,
+        document_language(document).as_deref(),
+        &mut spans,
+    );
+    spans
+}
+
+fn collect(items: &[ContentItem], inherited: Option<&str>, out: &mut Vec<LanguageSpan>) {
+    for item in items {
+        let tagged = language_of(item.annotations());
+        let language = match &tagged {
+            Some(language) if Some(language.as_str()) != inherited => {
+                out.push(LanguageSpan {
+                    range: element_range(item),
+                    language: language.clone(),
+                });
+                Some(language.as_str())
+            }
+            _ => inherited,
+        };
+        collect(item.children().unwrap_or(&[]), language, out);
+    }
+}
+
+/// Language at `position`: that of the innermost tagged element containing it, else the
+/// document's
+pub fn language_at(document: &Document, position: Position) -> Option<String> {
+    let mut language = document_language(document);
+    let mut items: &[ContentItem] = &document.root.children;
+    while let Some(item) = items.iter().find(|item| {
+        let range = element_range(item);
+        !item.is_blank_line_group() && range.start <= position && position < range.end
+    }) {
+        if let Some(tagged) = language_of(item.annotations()) {
+            language = Some(tagged);
+        }
+        items = item.children().unwrap_or(&[]);
+    }
+    language
+}
+
+/// Range of `item`; sessions start at their title, which their own range may not
+fn element_range(item: &ContentItem) -> Range {
+    let range = item.range();
+    match item {
+        ContentItem::Session(session) => match session.header_location() {
+            Some(header) => Range::new(header.span.start..range.span.end, header.start, range.end),
+            None => range.clone(),
+        },
+        _ => range.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    const SOURCE: &str = "Notes\n\n:: lang en ::\n\nIntro.\n\n:: lang fr ::\nBonjour le monde.\n\nEnd of intro.\n\n1. Abroad\n\n    - uno\n    - dos\n\n    :: meta lang=it ::\n    Ciao.\n";
+
+    #[test]
+    fn test_language_spans() {
+        let doc = parse_document(SOURCE).unwrap();
+        assert_eq!(document_language(&doc), Some("en".to_string()));
+        let spans: Vec<(usize, String)> = language_spans(&doc)
+            .into_iter()
+            .map(|span| (span.range.start.line, span.language))
+            .collect();
+        assert_eq!(spans, vec![(7, "fr".to_string()), (17, "it".to_string())]);
+    }
+
+    #[test]
+    fn test_language_at() {
+        let doc = parse_document(SOURCE).unwrap();
+        let at = |line| language_at(&doc, Position::new(line, 5));
+        assert_eq!(at(4), Some("en".to_string()));
+        assert_eq!(at(7), Some("fr".to_string()));
+        assert_eq!(at(9), Some("en".to_string()));
+        assert_eq!(at(13), Some("en".to_string()));
+        assert_eq!(at(17), Some("it".to_string()));
+
+        let untagged = parse_document("Notes\n\nText.\n").unwrap();
+        assert_eq!(language_at(&untagged, Position::new(2, 1)), None);
+    }
+}
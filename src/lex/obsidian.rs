@@ -0,0 +1,218 @@
+//! Obsidian vault import
+//!
+//!     Converts an Obsidian vault, a directory of Markdown notes, into a Lex workspace: each
+//!     `.md` note becomes a `.lex` document at the same relative path, and every other file
+//!     (images, PDFs, ...) is copied as is. Directories starting with a dot, such as
+//!     `.obsidian`, are skipped.
+//!
+//!     Notes are converted as Markdown of the Obsidian flavor (see
+//!     [import](crate::lex::formats::markdown::import)):
+//!
+//!         - YAML frontmatter: `tags` become a `:: tags ::` annotation, `title` the document
+//!           title, and other keys parameters of a `:: meta ::` annotation (see
+//!           [frontmatter](crate::lex::frontmatter)). Inline `#tags` join the frontmatter
+//!           tags.
+//!         - Headings become nested sessions; a leading level 1 heading is the title.
+//!           Without one, the title is the note's file name.
+//!         - `[[Wiki Links]]` are kept, to be read with wiki links enabled
+//!           ([InlineParser::with_wiki_links](crate::lex::inlines::InlineParser::with_wiki_links)).
+//!           They resolve by file name, which is unchanged. Embeds (`![[diagram.png]]`) of
+//!           other files become file references to them.
+//!         - `[text](url)` links become `text [url]`; links to notes point at the `.lex` file.
+//!         - Callouts (`> [!warning] Title`) become callout annotations on their text.
+//!         - Fenced code becomes verbatim blocks labeled with the language. Tables become
+//!           table blocks.
+//!         - `**strong**` and `*emphasis*` become `*strong*` and `_emphasis_`.
+
+use crate::lex::formats::markdown::import::{Converter, MarkdownImportOptions};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Notes are read with the Obsidian flavor of Markdown
+const OPTIONS: MarkdownImportOptions = MarkdownImportOptions {
+    gfm_footnotes: false,
+    obsidian: true,
+    multimarkdown_metadata: false,
+};
+
+/// Error that can occur while importing a vault
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImportError {
+    Io { path: PathBuf, message: String },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io { path, message } => {
+                write!(f, "Cannot import '{}': {message}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Files written by an import, relative to the output directory
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VaultImport {
+    pub documents: Vec<PathBuf>,
+    pub attachments: Vec<PathBuf>,
+}
+
+/// Convert the vault at `vault` into a Lex workspace at `output`
+pub fn import_vault(vault: &Path, output: &Path) -> Result<VaultImport, ImportError> {
+    let mut files = Vec::new();
+    vault_files(vault, Path::new(""), &mut files)?;
+    files.sort();
+
+    // Obsidian finds embedded files by name, wherever they are in the vault
+    let attachments: BTreeMap<String, PathBuf> = files
+        .iter()
+        .filter(|path| !is_note(path))
+        .filter_map(|path| Some((path.file_name()?.to_str()?.to_lowercase(), path.clone())))
+        .collect();
+
+    let mut import = VaultImport::default();
+    for relative in files {
+        let source_path = vault.join(&relative);
+        let target = if is_note(&relative) {
+            relative.with_extension("lex")
+        } else {
+            relative.clone()
+        };
+        let target_path = output.join(&target);
+        if let Some(parent) = target_path.parent() {
+            std::fs::create_dir_all(parent).map_err(|error| io_error(parent, error))?;
+        }
+        if is_note(&relative) {
+            let source = std::fs::read_to_string(&source_path)
+                .map_err(|error| io_error(&source_path, error))?;
+            let name = relative
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or_default();
+            let dir = relative.parent().unwrap_or(Path::new(""));
+            let converted = Converter::new(OPTIONS, dir, &attachments)
+                .convert(&source, name)
+                .source;
+            std::fs::write(&target_path, converted)
+                .map_err(|error| io_error(&target_path, error))?;
+            import.documents.push(target);
+        } else {
+            std::fs::copy(&source_path, &target_path)
+                .map_err(|error| io_error(&target_path, error))?;
+            import.attachments.push(target);
+        }
+    }
+    Ok(import)
+}
+
+/// Lex source of the Markdown note `source`, titled `name` unless it has a title
+pub fn convert_note(source: &str, name: &str) -> String {
+    Converter::new(OPTIONS, Path::new(""), &BTreeMap::new())
+        .convert(source, name)
+        .source
+}
+
+fn io_error(path: &Path, error: std::io::Error) -> ImportError {
+    ImportError::Io {
+        path: path.to_path_buf(),
+        message: error.to_string(),
+    }
+}
+
+fn is_note(path: &Path) -> bool {
+    path.extension().and_then(|ext| ext.to_str()) == Some("md")
+}
+
+/// Every file under `dir`, relative to the vault, skipping dot directories
+fn vault_files(dir: &Path, relative: &Path, out: &mut Vec<PathBuf>) -> Result<(), ImportError> {
+    let entries = std::fs::read_dir(dir).map_err(|error| io_error(dir, error))?;
+    for entry in entries {
+        let entry = entry.map_err(|error| io_error(dir, error))?;
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            vault_files(&path, &relative.join(&name), out)?;
+        } else {
+            out.push(relative.join(&name));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::analysis::{document_tags, Workspace};
+    use crate::lex::parsing::parse_document;
+
+    const NOTE: &str = "---\ntags: [Rust, caching]\nauthor: Ada\n---\n# Cache Design\n\nWe keep **hot** entries, see [[Eviction#LRU|eviction]] and #perf.\n\n## Setup\n\n- Install it\n- Run [the docs](https://example.com)\n\n> [!warning] Data loss\n> Clearing deletes *everything*.\n\n```rust\nfn main() {}\n```\n\n| Key | Value |\n|-----|-------|\n| ttl | 60 |\n";
+
+    #[test]
+    fn test_convert_note() {
+        let lex = convert_note(NOTE, "cache-design");
+        assert_eq!(
+            lex,
+            "Cache Design\n\n:: tags caching, perf, rust ::\n\n:: meta author=\"Ada\" ::\n\nWe keep *hot* entries, see [[Eviction#LRU|eviction]] and perf.\n\nSetup\n\n    - Install it\n    - Run the docs [https://example.com]\n\n    :: warning title=\"Data loss\" ::\n    Clearing deletes _everything_.\n\n    Code:\n        fn main() {}\n    :: rust\n\n    Table:\n        | Key | Value |\n        | ttl | 60    |\n    :: table\n"
+        );
+        let doc = parse_document(&lex).unwrap();
+        assert_eq!(doc.title(), "Cache Design");
+        assert_eq!(document_tags(&doc), vec!["caching", "perf", "rust"]);
+        assert_eq!(doc.root.iter_sessions_recursive().count(), 1);
+    }
+
+    #[test]
+    fn test_convert_note_escapes_lex_syntax() {
+        let lex = convert_note("Issue #42 uses [brackets] and {braces}.\n", "Plain");
+        assert_eq!(
+            lex,
+            "Plain\n\nIssue \\#42 uses \\[brackets] and \\{braces}.\n"
+        );
+    }
+
+    #[test]
+    fn test_import_vault() {
+        let root = std::env::temp_dir().join(format!("lex-obsidian-{}", std::process::id()));
+        let vault = root.join("vault");
+        let output = root.join("lex");
+        std::fs::create_dir_all(vault.join("notes")).unwrap();
+        std::fs::create_dir_all(vault.join("assets")).unwrap();
+        std::fs::create_dir_all(vault.join(".obsidian")).unwrap();
+        std::fs::write(vault.join(".obsidian/app.json"), "{}").unwrap();
+        std::fs::write(vault.join("assets/diagram.png"), [0u8, 1, 2]).unwrap();
+        std::fs::write(vault.join("Index.md"), "Start at [[Cache Design]].\n").unwrap();
+        std::fs::write(
+            vault.join("notes/Cache Design.md"),
+            "Back to [[Index]].\n\n![[diagram.png]]\n",
+        )
+        .unwrap();
+
+        let import = import_vault(&vault, &output).unwrap();
+        assert_eq!(
+            import.documents,
+            vec![
+                PathBuf::from("Index.lex"),
+                PathBuf::from("notes/Cache Design.lex")
+            ]
+        );
+        assert_eq!(
+            import.attachments,
+            vec![PathBuf::from("assets/diagram.png")]
+        );
+        let note = std::fs::read_to_string(output.join("notes/Cache Design.lex")).unwrap();
+        assert!(note.contains("[../assets/diagram.png]"));
+
+        let mut workspace = Workspace::new().with_wiki_links();
+        assert!(workspace.add_dir(&output).unwrap().is_empty());
+        let backlinks = workspace.backlinks(&output.join("notes/Cache Design.lex"));
+        assert_eq!(backlinks.len(), 1);
+        assert_eq!(backlinks[0].source, output.join("Index.lex"));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
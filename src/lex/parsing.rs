@@ -29,7 +29,8 @@
 //!             This allows us to separate the semantic analysis from the ast building. This is
 //!             a good thing overall, but was instrumental during development, as we ran multiple
 //!             parsers in parallel and the ast building had to be unified (correct parsing would
-//!             result in the same node types + tokens).
+//!             result in the same node types + tokens). [designs] keeps that comparison
+//!             available for new designs.
 //!
 //!         AST Building (5.3):
 //!             From the IR nodes, we build the actual AST nodes. During this step, important
@@ -68,12 +69,16 @@
 
 // Parser implementations
 pub mod common;
+pub mod designs;
 pub mod engine;
 pub mod ir;
 pub mod parser;
+pub mod recovery;
 
 // Re-export common parser interfaces
 pub use common::{ParseError, ParserInput};
+pub use designs::{compare_designs, DesignComparison, DesignRegistry, ParserDesign};
+pub use recovery::{parse_with_recovery, Debouncer, RecoveredDocument};
 
 // Re-export AST types and utilities from the ast module
 pub use crate::lex::ast::{
@@ -0,0 +1,210 @@
+//! Parser designs, side by side
+//!
+//!     The parser was developed by running competing designs over the same sources and
+//!     comparing their trees (see [parsing](super)). This module keeps that workflow
+//!     available: a design is anything turning source into a [Document], registered by name
+//!     in a [DesignRegistry], and [compare_designs] runs every registered design over a
+//!     corpus and reports:
+//!
+//!         - the agreement rate: the share of files on which every design produced the same
+//!           document (or every design failed);
+//!         - for each design, how many files it parsed and failed, and its total parse time;
+//!         - the files on which designs diverged, to look at first.
+//!
+//!     The default registry holds the production pipeline (`declarative`) and the
+//!     error-recovering one (`recovering`, see [recovery](super::recovery)), which must agree
+//!     on every valid document. A new design is registered next to them and compared before
+//!     it replaces anything.
+
+use super::recovery::parse_with_recovery;
+use super::{parse_document, Document};
+use crate::lex::analysis::search::lex_files;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// A way of parsing Lex source
+pub trait ParserDesign: Send + Sync {
+    /// Name of the design, as reported
+    fn name(&self) -> &str;
+
+    /// Parse `source`
+    fn parse(&self, source: &str) -> Result<Document, String>;
+}
+
+/// A design from a name and a parse function
+pub struct FnDesign {
+    name: String,
+    parse: fn(&str) -> Result<Document, String>,
+}
+
+impl FnDesign {
+    pub fn new(name: &str, parse: fn(&str) -> Result<Document, String>) -> Self {
+        Self {
+            name: name.to_string(),
+            parse,
+        }
+    }
+}
+
+impl ParserDesign for FnDesign {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn parse(&self, source: &str) -> Result<Document, String> {
+        (self.parse)(source)
+    }
+}
+
+/// Registered parser designs, in name order
+pub struct DesignRegistry {
+    designs: BTreeMap<String, Box<dyn ParserDesign>>,
+}
+
+impl DesignRegistry {
+    /// Create a new empty registry
+    pub fn new() -> Self {
+        DesignRegistry {
+            designs: BTreeMap::new(),
+        }
+    }
+
+    /// Register a design, replacing any design of the same name
+    pub fn register<D: ParserDesign + 'static>(&mut self, design: D) {
+        self.designs
+            .insert(design.name().to_string(), Box::new(design));
+    }
+
+    /// Names of the registered designs
+    pub fn names(&self) -> Vec<String> {
+        self.designs.keys().cloned().collect()
+    }
+
+    /// Create a registry with the built-in designs
+    pub fn with_defaults() -> Self {
+        let mut registry = Self::new();
+        registry.register(FnDesign::new("declarative", parse_document));
+        registry.register(FnDesign::new("recovering", |source| {
+            Ok(parse_with_recovery(source).document)
+        }));
+        registry
+    }
+}
+
+impl Default for DesignRegistry {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
+
+/// Results of one design over a corpus
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DesignStats {
+    pub name: String,
+    pub parsed: usize,
+    pub failed: usize,
+    pub time: Duration,
+}
+
+/// Results of all designs over a corpus
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DesignComparison {
+    pub files: usize,
+    /// Share of files on which all designs agreed, from 0 to 1; 1 for an empty corpus
+    pub agreement: f64,
+    pub designs: Vec<DesignStats>,
+    /// Files on which designs produced different documents, in path order
+    pub divergent: Vec<PathBuf>,
+}
+
+/// Run every design of `registry` over the `.lex` files under `corpus`
+pub fn compare_designs(
+    registry: &DesignRegistry,
+    corpus: &Path,
+) -> std::io::Result<DesignComparison> {
+    let mut paths = Vec::new();
+    lex_files(corpus, &mut paths)?;
+    paths.sort();
+    let mut sources = Vec::new();
+    for path in paths {
+        let source = std::fs::read_to_string(&path)?;
+        sources.push((path, source));
+    }
+    Ok(compare_sources(registry, &sources))
+}
+
+/// Run every design of `registry` over named sources
+pub fn compare_sources(
+    registry: &DesignRegistry,
+    sources: &[(PathBuf, String)],
+) -> DesignComparison {
+    let mut designs: Vec<DesignStats> = registry
+        .designs
+        .keys()
+        .map(|name| DesignStats {
+            name: name.clone(),
+            ..DesignStats::default()
+        })
+        .collect();
+    let mut divergent = Vec::new();
+    for (path, source) in sources {
+        let mut results = Vec::new();
+        for (design, stats) in registry.designs.values().zip(designs.iter_mut()) {
+            let start = Instant::now();
+            let result = design.parse(source);
+            stats.time += start.elapsed();
+            match result {
+                Ok(_) => stats.parsed += 1,
+                Err(_) => stats.failed += 1,
+            }
+            results.push(result.ok());
+        }
+        if results.windows(2).any(|pair| pair[0] != pair[1]) {
+            divergent.push(path.clone());
+        }
+    }
+    let agreement = if sources.is_empty() {
+        1.0
+    } else {
+        (sources.len() - divergent.len()) as f64 / sources.len() as f64
+    };
+    DesignComparison {
+        files: sources.len(),
+        agreement,
+        designs,
+        divergent,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_designs() {
+        let sources = vec![
+            (PathBuf::from("a.lex"), "Title\n\nText.\n".to_string()),
+            (
+                PathBuf::from("b.lex"),
+                "Title\n\n1. Session\n\n    - one\n    - two\n".to_string(),
+            ),
+        ];
+        let mut registry = DesignRegistry::with_defaults();
+        let comparison = compare_sources(&registry, &sources);
+        assert_eq!(comparison.files, 2);
+        assert_eq!(comparison.agreement, 1.0);
+        assert_eq!(comparison.designs.len(), 2);
+        assert_eq!(comparison.designs[0].name, "declarative");
+        assert_eq!(comparison.designs[0].parsed, 2);
+
+        // A design that reads nothing diverges on every file
+        registry.register(FnDesign::new("empty", |_| Ok(Document::new())));
+        let comparison = compare_sources(&registry, &sources);
+        assert_eq!(comparison.agreement, 0.0);
+        assert_eq!(
+            comparison.divergent,
+            vec![PathBuf::from("a.lex"), PathBuf::from("b.lex")]
+        );
+    }
+}
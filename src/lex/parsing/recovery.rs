@@ -0,0 +1,433 @@
+//! Error-recovering parsing
+//!
+//!     [parse_document](super::parse_document) stops at the first error and reports it as a
+//!     string, which suits batch conversion but not editors and linters: they need a document
+//!     to work with whatever the state of the source, and every problem at once.
+//!
+//!     [parse_with_recovery] always returns a best-effort [Document] together with the
+//!     diagnostics found along the way:
+//!
+//!     - `parse-error`: the pipeline failed on a top-level block, a line at the left margin
+//!       after a blank line and the lines up to the next such line. Only that block is given
+//!       up: its indentation is moved to the end of its lines, which loses its nesting but
+//!       keeps its text, or it is blanked if that fails too. The rest of the document keeps
+//!       its structure. Every line keeps its length, so the ranges of the document point
+//!       into `source` as given, repaired blocks aside.
+//!     - `broken-indentation`: a line indented by spaces that don't make a full level (four
+//!       spaces or a tab). Such lines break the indentation wall of their container and are
+//!       read as part of the parent instead. Verbatim content is exempt.
+//!     - `stray-verbatim-closing`: a line the lexer classifies as a data line (`:: label`)
+//!       that closes no verbatim block, usually because a content line broke the block's wall
+//!       or the subject is missing its colon. The parser drops these lines, so the block's
+//!       content ends up as text. The line the block was likely opened at is given as related
+//!       information.
+//!     - `list-item-paragraph`: a list item standing alone. Lists take at least two items, so
+//!       the parser reads it as a paragraph, marker included.
+//!     - Every diagnostic of [Document::diagnostics], such as broken references.
+//!
+//!     Editors re-analyze as the text changes. A [Debouncer] holds back re-analysis until a
+//!     document has stopped changing for a moment, so diagnostics are published once per
+//!     pause in typing rather than on every keystroke.
+
+use crate::lex::ast::{
+    AstNode, ContentItem, Diagnostic, DiagnosticSeverity, Document, SourceLocation,
+};
+use crate::lex::lexing::line_classification::has_seq_marker;
+use crate::lex::lexing::{classify_lines, tokenize, LineType, Token};
+use crate::lex::parsing::parse_document;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+/// A document parsed despite errors, with what was found wrong
+#[derive(Debug, Clone)]
+pub struct RecoveredDocument {
+    pub document: Document,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl RecoveredDocument {
+    /// Whether any diagnostic is an error
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == DiagnosticSeverity::Error)
+    }
+}
+
+/// Parse `source`, recovering from errors, and collect its diagnostics
+pub fn parse_with_recovery(source: &str) -> RecoveredDocument {
+    let locations = SourceLocation::new(source);
+    let mut diagnostics = Vec::new();
+    let document = match parse_document(source) {
+        Ok(document) => document,
+        Err(_) => {
+            let repair = repair_blocks(source, parse_document);
+            for (block, message) in repair.failed {
+                let start = locations.line_start(block.start).unwrap_or(source.len());
+                let end = locations.line_start(block.end).unwrap_or(source.len());
+                let range = locations.byte_range_to_ast_range(&(start..end));
+                diagnostics.push(
+                    Diagnostic::new(range, DiagnosticSeverity::Error, message)
+                        .with_code("parse-error"),
+                );
+            }
+            parse_document(&repair.source).unwrap_or_default()
+        }
+    };
+
+    for item in document.root.iter_all_nodes() {
+        let ContentItem::Paragraph(paragraph) = item else {
+            continue;
+        };
+        if paragraph.lines.len() != 1 {
+            continue;
+        }
+        let text = paragraph.text();
+        let tokens: Vec<Token> = tokenize(text.trim())
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect();
+        if has_seq_marker(&tokens) {
+            diagnostics.push(
+                Diagnostic::new(
+                    paragraph.range().clone(),
+                    DiagnosticSeverity::Information,
+                    "List item standing alone: read as a paragraph, lists need two items"
+                        .to_string(),
+                )
+                .with_code("list-item-paragraph"),
+            );
+        }
+    }
+
+    let lines: Vec<&str> = source.lines().collect();
+    let verbatim_lines = verbatim_content_lines(&document);
+    for (number, line) in lines.iter().enumerate() {
+        if verbatim_lines.contains(&number) || line.trim().is_empty() {
+            continue;
+        }
+        let Some(start) = locations.line_start(number) else {
+            continue;
+        };
+        let indent = line.len() - line.trim_start().len();
+        let spaces = line[..indent].chars().filter(|c| *c == ' ').count();
+        if spaces % 4 != 0 {
+            let range = locations.byte_range_to_ast_range(&(start..start + indent));
+            diagnostics.push(
+                Diagnostic::new(
+                    range,
+                    DiagnosticSeverity::Warning,
+                    format!("Indentation of {spaces} spaces is not a whole level (4 spaces)"),
+                )
+                .with_code("broken-indentation"),
+            );
+        }
+    }
+
+    for stray in stray_closings(&document, source) {
+        let line = lines[stray.line];
+        let start = locations.line_start(stray.line).unwrap_or(0);
+        let indent = line.len() - line.trim_start().len();
+        let range = locations.byte_range_to_ast_range(&(start + indent..start + line.len()));
+        let mut diagnostic = Diagnostic::new(
+            range,
+            DiagnosticSeverity::Error,
+            format!(
+                "'{}' closes no verbatim block: check the block's subject and indentation",
+                line.trim()
+            ),
+        )
+        .with_code("stray-verbatim-closing");
+        if let Some(opening) = stray.opening {
+            let opening_start = locations.line_start(opening).unwrap_or(0);
+            let text = lines[opening];
+            let subject =
+                opening_start + text.len() - text.trim_start().len()..opening_start + text.len();
+            diagnostic = diagnostic.with_related(
+                locations.byte_range_to_ast_range(&subject),
+                "Verbatim block likely opened here",
+            );
+        }
+        diagnostics.push(diagnostic);
+    }
+
+    diagnostics.extend(document.diagnostics());
+    diagnostics
+        .sort_by_key(|diagnostic| (diagnostic.range.start.line, diagnostic.range.start.column));
+    RecoveredDocument {
+        document,
+        diagnostics,
+    }
+}
+
+/// A data line that closes no verbatim block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct StrayClosing {
+    /// 0-based line of the data line
+    pub line: usize,
+    /// Line the block was likely opened at: the closest line above at the data line's
+    /// indentation or less
+    pub opening: Option<usize>,
+}
+
+/// Lines of verbatim content of `document`, which may be indented freely
+fn verbatim_content_lines(document: &Document) -> HashSet<usize> {
+    let mut lines = HashSet::new();
+    for item in document.root.iter_all_nodes() {
+        if let ContentItem::VerbatimBlock(verbatim) = item {
+            lines.extend(verbatim.range().start.line + 1..verbatim.range().end.line);
+        }
+    }
+    lines
+}
+
+/// Data lines of `source` that close none of the verbatim blocks `document` was parsed into
+pub(crate) fn stray_closings(document: &Document, source: &str) -> Vec<StrayClosing> {
+    let closings: HashSet<usize> = document
+        .root
+        .iter_all_nodes()
+        .filter_map(|item| match item {
+            ContentItem::VerbatimBlock(verbatim) => Some(verbatim.closing_data.location.start.line),
+            _ => None,
+        })
+        .collect();
+    let content = verbatim_content_lines(document);
+    let lines: Vec<&str> = source.lines().collect();
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    classify_lines(source)
+        .enumerate()
+        .filter(|(number, line_type)| {
+            *line_type == LineType::DataLine
+                && !closings.contains(number)
+                && !content.contains(number)
+        })
+        .map(|(number, _)| StrayClosing {
+            line: number,
+            opening: (0..number).rev().find(|&previous| {
+                let text = lines[previous];
+                !text.trim().is_empty() && indent(text) <= indent(lines[number])
+            }),
+        })
+        .collect()
+}
+
+/// A source with the top-level blocks that fail to parse given up
+struct Repair {
+    source: String,
+    /// Line ranges of the blocks given up, with the error each failed with
+    failed: Vec<(Range<usize>, String)>,
+}
+
+/// Line ranges of the top-level blocks of `lines`: each starts at a line at the left margin
+/// after a blank line, and the first at the start
+fn top_level_blocks(lines: &[String]) -> Vec<Range<usize>> {
+    let mut blocks = Vec::new();
+    let mut start = 0;
+    for number in 1..lines.len() {
+        let line = &lines[number];
+        let margin = !line.trim().is_empty() && !line.starts_with([' ', '\t']);
+        if margin && lines[number - 1].trim().is_empty() {
+            blocks.push(start..number);
+            start = number;
+        }
+    }
+    if start < lines.len() {
+        blocks.push(start..lines.len());
+    }
+    blocks
+}
+
+/// Give up the top-level blocks of `source` that `parse` fails on
+///
+/// Blocks are checked in order, each parsed together with the (repaired) blocks before it,
+/// so an error is pinned to the block it first shows in. A failing block is flattened, its
+/// indentation moved to the end of its lines, or blanked with spaces if it still fails.
+/// Lines keep their byte length and line ending, so everything after a repaired block keeps
+/// its offsets, lines and columns. This parses once per block, which is only done once
+/// parsing the whole source has failed.
+fn repair_blocks(source: &str, parse: impl Fn(&str) -> Result<Document, String>) -> Repair {
+    let mut lines: Vec<String> = source.split_inclusive('\n').map(str::to_string).collect();
+    let text = |lines: &[String]| lines.concat();
+    let body = |line: &str| line.trim_end_matches(['\r', '\n']).len();
+    let mut failed = Vec::new();
+    for block in top_level_blocks(&lines) {
+        let Err(message) = parse(&text(&lines[..block.end])) else {
+            continue;
+        };
+        for line in &mut lines[block.clone()] {
+            let (content, ending) = line.split_at(body(line));
+            let indent = content.len() - content.trim_start().len();
+            *line = format!("{}{}{ending}", &content[indent..], &content[..indent]);
+        }
+        if parse(&text(&lines[..block.end])).is_err() {
+            for line in &mut lines[block.clone()] {
+                let (content, ending) = line.split_at(body(line));
+                *line = format!("{}{ending}", " ".repeat(content.len()));
+            }
+        }
+        failed.push((block, message));
+    }
+    Repair {
+        source: text(&lines),
+        failed,
+    }
+}
+
+/// Holds back re-analysis of changing documents until they have been still for a delay
+#[derive(Debug, Clone)]
+pub struct Debouncer<K> {
+    delay: Duration,
+    /// Time of the last change of each document waiting for analysis
+    pending: HashMap<K, Instant>,
+}
+
+impl<K: Clone + Eq + std::hash::Hash> Debouncer<K> {
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            delay,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Record a change of `document` at `now`, restarting its delay
+    pub fn changed(&mut self, document: K, now: Instant) {
+        self.pending.insert(document, now);
+    }
+
+    /// Documents unchanged for the delay as of `now`, which are no longer pending
+    pub fn due(&mut self, now: Instant) -> Vec<K> {
+        let due: Vec<K> = self
+            .pending
+            .iter()
+            .filter(|(_, changed)| now.saturating_duration_since(**changed) >= self.delay)
+            .map(|(document, _)| document.clone())
+            .collect();
+        for document in &due {
+            self.pending.remove(document);
+        }
+        due
+    }
+
+    /// When the next document becomes due, to sleep until then
+    pub fn next_deadline(&self) -> Option<Instant> {
+        self.pending
+            .values()
+            .map(|changed| *changed + self.delay)
+            .min()
+    }
+
+    /// Forget `document`, as when it is closed
+    pub fn cancel(&mut self, document: &K) {
+        self.pending.remove(document);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::ast::Range;
+
+    fn range_of<'a>(recovered: &'a RecoveredDocument, code: &str) -> &'a Range {
+        let diagnostic = recovered
+            .diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.code.as_deref() == Some(code));
+        &diagnostic.unwrap().range
+    }
+
+    #[test]
+    fn test_clean_document() {
+        let clean = parse_with_recovery(
+            "Notes\n\n- One\n- Two\n\nCode:\n    fn main() {\n      x\n    }\n:: rust\n",
+        );
+        assert!(clean.diagnostics.is_empty());
+        assert!(!clean.has_errors());
+    }
+
+    #[test]
+    fn test_collects_every_problem() {
+        let source = "Notes\n\nCode:\n    fn main() {\n  }\n:: rust\n\n- Alone\n\nSee [missing].\n";
+        let recovered = parse_with_recovery(source);
+        let codes: Vec<&str> = recovered
+            .diagnostics
+            .iter()
+            .filter_map(|diagnostic| diagnostic.code.as_deref())
+            .collect();
+        assert_eq!(
+            &codes[..3],
+            [
+                "broken-indentation",
+                "stray-verbatim-closing",
+                "list-item-paragraph"
+            ]
+        );
+        assert!(recovered.has_errors());
+
+        let indentation = range_of(&recovered, "broken-indentation");
+        assert_eq!((indentation.start.line, indentation.end.column), (4, 2));
+        let closing = range_of(&recovered, "stray-verbatim-closing");
+        assert_eq!((closing.start.line, closing.start.column), (5, 0));
+        let stray = recovered
+            .diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.code.as_deref() == Some("stray-verbatim-closing"))
+            .unwrap();
+        assert_eq!(stray.related[0].range.start.line, 2);
+        assert!(!recovered.document.root.children.is_empty());
+    }
+
+    #[test]
+    fn test_repair_gives_up_failing_blocks_only() {
+        // Stand-in for errors the parser can't be made to hit from plain text
+        let parse = |source: &str| {
+            if source.contains("BROKEN") {
+                Err("broken".to_string())
+            } else if source.lines().any(|line| line.starts_with("        !")) {
+                Err("too deep".to_string())
+            } else {
+                parse_document(source)
+            }
+        };
+        let source = "Title\n\n1. First\n\n    Text.\n\n        ! Deep.\n\n2. Second\n\n    BROKEN text.\n\n3. Third\n\n    More.\n";
+        let repair = repair_blocks(source, parse);
+        let failed: Vec<(std::ops::Range<usize>, &str)> = repair
+            .failed
+            .iter()
+            .map(|(block, message)| (block.clone(), message.as_str()))
+            .collect();
+        assert_eq!(failed, vec![(2..8, "too deep"), (8..12, "broken")]);
+        assert_eq!(
+            repair.source,
+            "Title\n\n1. First\n\nText.    \n\n! Deep.        \n\n         \n\n                \n\n3. Third\n\n    More.\n"
+        );
+        let document = parse_document(&repair.source).unwrap();
+        let third = document.root.iter_sessions().last().unwrap();
+        assert_eq!(third.title_text(), "Third");
+        assert_eq!(third.children.iter().count(), 1);
+
+        // The element after the repaired blocks is where it is in the source
+        let original = parse_document(source).unwrap();
+        let expected = original.root.iter_sessions().last().unwrap();
+        assert_eq!(expected.title_text(), "Third");
+        assert_eq!(third.range(), expected.range());
+    }
+
+    #[test]
+    fn test_debouncer() {
+        let start = Instant::now();
+        let delay = Duration::from_millis(300);
+        let mut debouncer = Debouncer::new(delay);
+        debouncer.changed("a.lex", start);
+        debouncer.changed("b.lex", start);
+        debouncer.changed("a.lex", start + Duration::from_millis(200));
+        assert_eq!(debouncer.next_deadline(), Some(start + delay));
+        assert_eq!(debouncer.due(start + delay), vec!["b.lex"]);
+        assert!(debouncer.due(start + delay).is_empty());
+        assert_eq!(
+            debouncer.due(start + Duration::from_millis(500)),
+            vec!["a.lex"]
+        );
+        assert_eq!(debouncer.next_deadline(), None);
+    }
+}
@@ -0,0 +1,177 @@
+//! Table blocks
+//!
+//! Lex has no table element: tables are verbatim blocks labeled `table`, holding one
+//! pipe-separated row per line:
+//!
+//!     Prices:
+//!         | Item   | Price |
+//!         | Apples | 1.20  |
+//!     :: table
+//!
+//! This module reads and writes those rows. The formatter's `tables` rule aligns their
+//! columns, the [csv](crate::lex::formats::csv) formats import and export them, and output
+//! formats render them as tables.
+
+use crate::lex::ast::{ContentItem, Verbatim};
+
+/// Label of verbatim blocks holding tables
+pub const TABLE_LABEL: &str = "table";
+
+/// Alignment of cells within their padded column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColumnAlignment {
+    #[default]
+    Left,
+    Right,
+    Center,
+}
+
+impl ColumnAlignment {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColumnAlignment::Left => "left",
+            ColumnAlignment::Right => "right",
+            ColumnAlignment::Center => "center",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Self::Left, Self::Right, Self::Center]
+            .into_iter()
+            .find(|alignment| alignment.name() == name)
+    }
+}
+
+/// Table rows as `| a | b |` lines, columns padded to a common width
+///
+/// Columns are padded to their widest cell, but no wider than `max_width`; longer cells are
+/// kept whole and push their row out of line.
+pub fn render_rows(
+    rows: &[Vec<String>],
+    alignment: ColumnAlignment,
+    max_width: usize,
+) -> Vec<String> {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| {
+            (0..columns)
+                .map(|index| escape_cell(row.get(index).map_or("", String::as_str)))
+                .collect()
+        })
+        .collect();
+    let widths: Vec<usize> = (0..columns)
+        .map(|index| {
+            cells
+                .iter()
+                .map(|row| row[index].chars().count())
+                .max()
+                .unwrap_or(0)
+                .min(max_width)
+        })
+        .collect();
+
+    cells
+        .iter()
+        .map(|row| {
+            let padded: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| match alignment {
+                    ColumnAlignment::Left => format!("{cell:<width$}"),
+                    ColumnAlignment::Right => format!("{cell:>width$}"),
+                    ColumnAlignment::Center => format!("{cell:^width$}"),
+                })
+                .collect();
+            format!("| {} |", padded.join(" | "))
+        })
+        .collect()
+}
+
+/// Lex source of a table block titled `subject` holding `rows`
+pub fn table_block(subject: &str, rows: &[Vec<String>]) -> String {
+    let mut out = format!("{subject}:\n");
+    for line in render_rows(rows, ColumnAlignment::Left, usize::MAX) {
+        out.push_str(&format!("    {line}\n"));
+    }
+    out.push_str(&format!(":: {TABLE_LABEL}\n"));
+    out
+}
+
+/// Rows of a table block; `None` if the block is not labeled `table`
+pub fn table_rows(verbatim: &Verbatim) -> Option<Vec<Vec<String>>> {
+    if verbatim.closing_data.label.value != TABLE_LABEL {
+        return None;
+    }
+    let rows = verbatim
+        .children
+        .iter()
+        .filter_map(ContentItem::as_verbatim_line)
+        .map(|line| line.content.as_string().trim())
+        .filter(|line| !line.is_empty())
+        .map(split_row)
+        .collect();
+    Some(rows)
+}
+
+fn escape_cell(cell: &str) -> String {
+    cell.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace(['\n', '\r'], " ")
+}
+
+/// Whether `line` is a `| a | b |` row
+pub fn is_pipe_row(line: &str) -> bool {
+    let line = line.trim();
+    line.len() > 1 && line.starts_with('|') && line.ends_with('|') && !line.ends_with("\\|")
+}
+
+/// Cells of a `| a | b |` row; `\|` and `\\` are escapes
+pub(crate) fn split_row(line: &str) -> Vec<String> {
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(next @ ('|' | '\\')) => cell.push(next),
+                Some(next) => {
+                    cell.push('\\');
+                    cell.push(next);
+                }
+                None => cell.push('\\'),
+            },
+            '|' => cells.push(std::mem::take(&mut cell).trim().to_string()),
+            _ => cell.push(c),
+        }
+    }
+    // Text after the last pipe is a final cell only when the row is not closed by one
+    let rest = cell.trim();
+    if !rest.is_empty() || cells.is_empty() {
+        cells.push(rest.to_string());
+    }
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_and_render_rows() {
+        assert_eq!(split_row("| a | b\\|c |"), vec!["a", "b|c"]);
+        assert_eq!(split_row("a | b"), vec!["a", "b"]);
+        assert!(is_pipe_row(" | a | b | "));
+        assert!(!is_pipe_row("a | b"));
+        assert!(!is_pipe_row("| a \\|"));
+        let rows = vec![
+            vec!["Item".to_string(), "Price".to_string()],
+            vec!["Apples".to_string(), "1.20".to_string()],
+        ];
+        assert_eq!(
+            render_rows(&rows, ColumnAlignment::Right, usize::MAX),
+            vec!["|   Item | Price |", "| Apples |  1.20 |"]
+        );
+    }
+}
@@ -0,0 +1,432 @@
+//! Document templates
+//!
+//!     Starting points for new documents. A template is Lex source with `{{variable}}`
+//!     placeholders; [Template::render] fills in `title`, `date` and `author`, plus any extra
+//!     variables. Built-in templates cover an article, meeting notes, a specification and a
+//!     journal entry; user templates are `.lex` files in a directory (such as one under the
+//!     user's configuration directory), loaded with [load_templates], and take precedence
+//!     over built-in templates of the same name.
+//!
+//!     [scaffold] turns a template and a document name into a file name and its contents, the
+//!     way `lex new <template> <name>` would.
+//!
+//! Snippets
+//!
+//!     [snippet] builds the source of a single element (a session, definition, list,
+//!     verbatim block, annotation, footnote or citation) for editors to insert, indented for
+//!     its nesting depth with the `indent_string` of the active
+//!     [FormattingRulesConfig], so inserted text is already formatted.
+
+use crate::lex::formatting::rules::FormattingRulesConfig;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const ARTICLE: &str = "{{title}}
+
+:: meta author=\"{{author}}\", date={{date}} ::
+
+A one-paragraph summary of the article.
+
+1. Introduction
+
+    What the article is about, and why it matters.
+
+2. Discussion
+
+    The main body of the article.
+
+3. Conclusion
+
+    What the reader should take away.
+";
+
+const MEETING_NOTES: &str = "{{title}}
+
+:: meta author=\"{{author}}\", date={{date}} ::
+
+Meeting notes from {{date}}.
+
+1. Attendees
+
+    - {{author}}
+
+2. Agenda
+
+    - First topic
+
+3. Decisions
+
+    - None yet
+
+4. Action Items
+
+    - Owner: task
+";
+
+const SPEC: &str = "{{title}}
+
+:: meta author=\"{{author}}\", date={{date}}, status=draft ::
+
+This document specifies the behavior described below.
+
+1. Scope
+
+    What this specification covers, and what it leaves out.
+
+2. Terminology
+
+    Term:
+        Definition of the term.
+
+3. Requirements
+
+    1. The first requirement.
+
+4. Open Questions
+
+    - None yet
+";
+
+const JOURNAL: &str = "{{title}}
+
+:: meta author=\"{{author}}\", date={{date}} ::
+
+1. Today
+
+    What happened.
+
+2. Thoughts
+
+    What it meant.
+
+3. Tomorrow
+
+    - Next step
+";
+
+/// Names and sources of the built-in templates
+const BUILTIN: [(&str, &str); 4] = [
+    ("article", ARTICLE),
+    ("meeting-notes", MEETING_NOTES),
+    ("spec", SPEC),
+    ("journal", JOURNAL),
+];
+
+/// Error that can occur while loading or rendering templates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    /// No template has this name
+    NotFound(String),
+    /// A placeholder has no value
+    MissingVariable {
+        template: String,
+        variable: String,
+    },
+    Io(String),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TemplateError::NotFound(name) => write!(f, "Template '{name}' not found"),
+            TemplateError::MissingVariable { template, variable } => {
+                write!(
+                    f,
+                    "Template '{template}' uses '{variable}', which has no value"
+                )
+            }
+            TemplateError::Io(message) => write!(f, "IO error: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// A named document template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    pub name: String,
+    pub source: String,
+}
+
+impl Template {
+    pub fn new(name: impl Into<String>, source: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            source: source.into(),
+        }
+    }
+
+    pub fn builtin(name: &str) -> Option<Self> {
+        BUILTIN
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(name, source)| Self::new(*name, *source))
+    }
+
+    /// Fill in the template's `{{variable}}` placeholders
+    pub fn render(&self, variables: &TemplateVariables) -> Result<String, TemplateError> {
+        let mut output = String::with_capacity(self.source.len());
+        let mut rest = self.source.as_str();
+        while let Some(start) = rest.find("{{") {
+            let Some(length) = rest[start + 2..].find("}}") else {
+                break;
+            };
+            let name = rest[start + 2..start + 2 + length].trim();
+            let value = variables
+                .get(name)
+                .ok_or_else(|| TemplateError::MissingVariable {
+                    template: self.name.clone(),
+                    variable: name.to_string(),
+                })?;
+            output.push_str(&rest[..start]);
+            output.push_str(value);
+            rest = &rest[start + 4 + length..];
+        }
+        output.push_str(rest);
+        Ok(output)
+    }
+}
+
+/// Values for template placeholders
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateVariables {
+    values: HashMap<String, String>,
+}
+
+impl TemplateVariables {
+    /// `title` and `author` as given, `date` as today's date (UTC)
+    pub fn new(title: impl Into<String>, author: impl Into<String>) -> Self {
+        let mut values = HashMap::new();
+        values.insert("title".to_string(), title.into());
+        values.insert("author".to_string(), author.into());
+        values.insert("date".to_string(), today());
+        Self { values }
+    }
+
+    /// Set a variable, replacing its value
+    pub fn set(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.values.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+}
+
+/// Today's date (UTC) as `YYYY-MM-DD`
+pub fn today() -> String {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// Calendar date of a day count since 1970-01-01 (Howard Hinnant's algorithm)
+pub(crate) fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// User templates: every `.lex` file in `dir`, named after its file stem
+pub fn load_templates(dir: &Path) -> Result<Vec<Template>, TemplateError> {
+    let entries = std::fs::read_dir(dir).map_err(|error| TemplateError::Io(error.to_string()))?;
+    let mut templates = Vec::new();
+    for entry in entries {
+        let path = entry
+            .map_err(|error| TemplateError::Io(error.to_string()))?
+            .path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("lex") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+            continue;
+        };
+        let source =
+            std::fs::read_to_string(&path).map_err(|error| TemplateError::Io(error.to_string()))?;
+        templates.push(Template::new(name, source));
+    }
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(templates)
+}
+
+/// The template called `name`: a user template if there is one, else a built-in one
+pub fn find_template(name: &str, user_templates: &[Template]) -> Result<Template, TemplateError> {
+    user_templates
+        .iter()
+        .find(|template| template.name == name)
+        .cloned()
+        .or_else(|| Template::builtin(name))
+        .ok_or_else(|| TemplateError::NotFound(name.to_string()))
+}
+
+/// Names of the built-in templates
+pub fn builtin_names() -> Vec<&'static str> {
+    BUILTIN.iter().map(|(name, _)| *name).collect()
+}
+
+/// File name and contents of a new document called `name`
+///
+/// The file name is `name` with a `.lex` extension. Unless `variables` sets a title, the
+/// title is derived from `name`: `release-plan` becomes "Release Plan".
+pub fn scaffold(
+    template: &Template,
+    name: &str,
+    variables: TemplateVariables,
+) -> Result<(String, String), TemplateError> {
+    let stem = name.strip_suffix(".lex").unwrap_or(name);
+    let variables = match variables.get("title") {
+        Some(title) if !title.is_empty() => variables,
+        _ => variables.set("title", title_from_name(stem)),
+    };
+    Ok((format!("{stem}.lex"), template.render(&variables)?))
+}
+
+fn title_from_name(name: &str) -> String {
+    name.split(['-', '_', ' '])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Kind of element a snippet inserts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnippetKind {
+    Session,
+    Definition,
+    List,
+    Verbatim,
+    Annotation,
+    /// Footnote body, the annotation a `[1]` reference points at
+    Footnote,
+    /// Citation reference, inserted inline
+    Citation,
+}
+
+/// Source of a new element of `kind` at nesting `depth`, indented as `rules` configure
+///
+/// Block snippets end with a newline; the citation snippet is inline text.
+pub fn snippet(kind: SnippetKind, depth: usize, rules: &FormattingRulesConfig) -> String {
+    let outer = rules.indent_string.repeat(depth);
+    let inner = rules.indent_string.repeat(depth + 1);
+    match kind {
+        SnippetKind::Session => format!("{outer}1. Title\n\n{inner}Content.\n"),
+        SnippetKind::Definition => format!("{outer}Term:\n{inner}Definition of the term.\n"),
+        SnippetKind::List => format!("{outer}- First item\n{outer}- Second item\n"),
+        SnippetKind::Verbatim => format!("{outer}Example:\n{inner}code\n{outer}:: text\n"),
+        SnippetKind::Annotation => format!("{outer}:: note ::\n{inner}Text of the note.\n"),
+        SnippetKind::Footnote => format!("{outer}:: 1 ::\n{inner}Text of the footnote.\n"),
+        SnippetKind::Citation => "[@key]".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    #[test]
+    fn test_builtin_templates_render_and_parse() {
+        let variables = TemplateVariables::new("Release Plan", "Ada").set("date", "2024-05-01");
+        for name in builtin_names() {
+            let rendered = Template::builtin(name).unwrap().render(&variables).unwrap();
+            assert!(rendered.starts_with("Release Plan\n"));
+            assert!(!rendered.contains("{{"));
+            let doc = parse_document(&rendered).unwrap();
+            assert!(doc.root.iter_sessions_recursive().count() >= 3, "{name}");
+        }
+    }
+
+    #[test]
+    fn test_scaffold() {
+        let template = Template::new("memo", "{{title}}\n\nBy {{author}}, {{ team }}.\n");
+        let variables = TemplateVariables::new("", "Ada").set("team", "Docs");
+        let (file, content) = scaffold(&template, "release-plan", variables).unwrap();
+        assert_eq!(file, "release-plan.lex");
+        assert_eq!(content, "Release Plan\n\nBy Ada, Docs.\n");
+
+        let error = template
+            .render(&TemplateVariables::new("T", "A"))
+            .unwrap_err();
+        assert_eq!(
+            error,
+            TemplateError::MissingVariable {
+                template: "memo".to_string(),
+                variable: "team".to_string()
+            }
+        );
+
+        let user = vec![Template::new("article", "Mine\n")];
+        assert_eq!(find_template("article", &user).unwrap().source, "Mine\n");
+        assert!(find_template("journal", &user).is_ok());
+        assert!(find_template("novel", &user).is_err());
+    }
+
+    #[test]
+    fn test_snippets() {
+        let rules = FormattingRulesConfig::default();
+        assert_eq!(
+            snippet(SnippetKind::Definition, 1, &rules),
+            "    Term:\n        Definition of the term.\n"
+        );
+        let tabs = FormattingRulesConfig {
+            indent_string: "\t".to_string(),
+            ..FormattingRulesConfig::default()
+        };
+        assert_eq!(
+            snippet(SnippetKind::Footnote, 0, &tabs),
+            ":: 1 ::\n\tText of the footnote.\n"
+        );
+
+        let blocks = [
+            SnippetKind::Session,
+            SnippetKind::Definition,
+            SnippetKind::List,
+            SnippetKind::Verbatim,
+            SnippetKind::Annotation,
+        ];
+        for kind in blocks {
+            let source = format!("Doc\n\n{}", snippet(kind, 0, &rules));
+            let doc = parse_document(&source).unwrap();
+            assert!(
+                !doc.root.children.is_empty() || !doc.annotations.is_empty(),
+                "{kind:?}"
+            );
+        }
+        let cited = format!("See {}.\n", snippet(SnippetKind::Citation, 0, &rules));
+        assert_eq!(
+            parse_document(&cited)
+                .unwrap()
+                .iter_all_references()
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_civil_from_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(19_844), (2024, 5, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+    }
+}
@@ -72,6 +72,7 @@ enum InlineExpectationKind {
     Code(TextMatch),
     Math(TextMatch),
     Reference(ReferenceExpectation),
+    Ruby { base: TextMatch, text: TextMatch },
 }
 
 #[allow(dead_code)]
@@ -120,6 +121,15 @@ impl InlineExpectation {
         }
     }
 
+    pub fn ruby(base: impl Into<String>, gloss: impl Into<String>) -> Self {
+        Self {
+            kind: InlineExpectationKind::Ruby {
+                base: TextMatch::Exact(base.into()),
+                text: TextMatch::Exact(gloss.into()),
+            },
+        }
+    }
+
     pub fn reference(expectation: ReferenceExpectation) -> Self {
         Self {
             kind: InlineExpectationKind::Reference(expectation),
@@ -152,6 +162,16 @@ impl InlineExpectation {
             (InlineExpectationKind::Reference(expectation), InlineNode::Reference { data, .. }) => {
                 expectation.assert(data, context);
             }
+            (
+                InlineExpectationKind::Ruby {
+                    base: base_matcher,
+                    text: text_matcher,
+                },
+                InlineNode::Ruby { base, text, .. },
+            ) => {
+                base_matcher.assert(base, &format!("{context}:base"));
+                text_matcher.assert(text, &format!("{context}:gloss"));
+            }
             (expected, got) => panic!("{context}: Expected inline {expected:?}, got {got:?}"),
         }
     }
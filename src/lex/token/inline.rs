@@ -20,6 +20,7 @@
 //!         - Code: `text` (monospace, literal)
 //!         - Math: #formula# (mathematical notation, literal)
 //!         - Reference: [target] (links, citations, footnotes)
+//!         - Ruby: {base|gloss} (pronunciation glosses over CJK text, literal)
 //!
 //!     References support multiple subtypes including:
 //!         - Citations: [@key] or [@key1; @key2, pp. 42-45]
@@ -66,6 +67,8 @@ pub enum InlineKind {
     Math,
     /// Reference (link, citation, footnote): \[target\] (literal, no nested inlines)
     Reference,
+    /// Ruby text (pronunciation gloss): {base|gloss} (literal, no nested inlines)
+    Ruby,
 }
 
 impl std::fmt::Display for InlineKind {
@@ -76,6 +79,7 @@ impl std::fmt::Display for InlineKind {
             InlineKind::Code => write!(f, "code"),
             InlineKind::Math => write!(f, "math"),
             InlineKind::Reference => write!(f, "reference"),
+            InlineKind::Ruby => write!(f, "ruby"),
         }
     }
 }
@@ -91,6 +95,7 @@ mod tests {
         assert_eq!(format!("{}", InlineKind::Code), "code");
         assert_eq!(format!("{}", InlineKind::Math), "math");
         assert_eq!(format!("{}", InlineKind::Reference), "reference");
+        assert_eq!(format!("{}", InlineKind::Ruby), "ruby");
     }
 
     #[test]
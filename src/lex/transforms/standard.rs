@@ -135,6 +135,29 @@ pub static STRING_TO_AST: Lazy<AstTransform> =
         })
     });
 
+/// Type alias for inspect transforms, rendering a view of the source
+pub type InspectTransform = Transform<String, String>;
+
+/// Indentation walls transform: String → String
+///
+/// Renders the source with each line's indentation wall drawn as a vertical guide, and the
+/// lines that miss their wall marked and explained. See
+/// [walls](crate::lex::analysis::walls).
+///
+/// # Example
+///
+/// ```rust
+/// use lex_parser::lex::transforms::standard::INDENTATION_WALLS;
+///
+/// let view = INDENTATION_WALLS.run("Session:\n    Content\n".to_string()).unwrap();
+/// ```
+pub static INDENTATION_WALLS: Lazy<InspectTransform> = Lazy::new(|| {
+    Transform::from_fn(|source: String| {
+        let report = crate::lex::analysis::indentation_walls(&source);
+        Ok(crate::lex::analysis::render_walls(&source, &report))
+    })
+});
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -190,6 +213,14 @@ mod tests {
         assert!(!result.root.children.is_empty());
     }
 
+    #[test]
+    fn test_indentation_walls() {
+        let view = INDENTATION_WALLS
+            .run("Session:\n    Content\n".to_string())
+            .unwrap();
+        assert_eq!(view, "1   Session:\n2      │Content\n");
+    }
+
     #[test]
     fn test_transforms_are_reusable() {
         // Test that we can use the same transform multiple times
@@ -0,0 +1,288 @@
+//! Variables and computed fields
+//!
+//!     Text can refer to the document's metadata and to values computed at conversion time
+//!     with `{{name}}` placeholders:
+//!
+//!         - `{{meta.<key>}}`: the `<key>` parameter of a document-level annotation
+//!           (`:: meta author="Ada Lovelace" ::`); `{{meta.title}}` is the document title
+//!           unless a parameter sets it.
+//!         - `{{date}}`: the conversion date, as `YYYY-MM-DD`. `{{date:<format>}}` formats it
+//!           with `strftime` specifiers: `%Y`, `%y`, `%m`, `%d`, `%e`, `%B`, `%b`, `%A`,
+//!           `%a`, `%j` and `%%`.
+//!         - `{{wordcount}}`: the number of words of the document, counted as by
+//!           [stats](crate::lex::analysis::stats).
+//!
+//!     [substitute] replaces them in the source before conversion, the way
+//!     [preprocess](crate::lex::exec::preprocess) inserts the output of exec blocks, so they
+//!     work in body text and anywhere else output formats take text from, such as running
+//!     headers and footers. The content of verbatim blocks is left alone: `{{...}}` in code
+//!     is code.
+
+use crate::lex::analysis::stats::stats;
+use crate::lex::ast::traits::AstNode;
+use crate::lex::ast::{ContentItem, Document};
+use crate::lex::templates::civil_from_days;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// Error that can occur while substituting variables
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VariableError {
+    /// A placeholder names no known variable
+    Unknown { line: usize, name: String },
+}
+
+impl fmt::Display for VariableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VariableError::Unknown { line, name } => {
+                write!(f, "Unknown variable '{name}' at line {}", line + 1)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VariableError {}
+
+/// Values placeholders are resolved from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variables {
+    /// Metadata, by key
+    pub meta: BTreeMap<String, String>,
+    /// Conversion date, in days since 1970-01-01
+    pub days: i64,
+    pub words: usize,
+}
+
+impl Variables {
+    /// Metadata and word count of `document`, dated today (UTC)
+    pub fn for_document(document: &Document) -> Self {
+        let mut meta = BTreeMap::new();
+        let title = document.title().trim();
+        if !title.is_empty() {
+            meta.insert("title".to_string(), title.to_string());
+        }
+        for annotation in document
+            .annotations
+            .iter()
+            .chain(document.root.annotations.iter())
+        {
+            for param in &annotation.data.parameters {
+                meta.insert(param.key.clone(), param.value.trim_matches('"').to_string());
+            }
+        }
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        Self {
+            meta,
+            days: (seconds / 86_400) as i64,
+            words: stats(document).words,
+        }
+    }
+
+    /// Date the conversion at `seconds` since the epoch, for reproducible output (as with
+    /// `SOURCE_DATE_EPOCH`)
+    pub fn with_timestamp(mut self, seconds: u64) -> Self {
+        self.days = (seconds / 86_400) as i64;
+        self
+    }
+
+    /// Value of the placeholder `name`, if known
+    pub fn resolve(&self, name: &str) -> Option<String> {
+        let name = name.trim();
+        if let Some(key) = name.strip_prefix("meta.") {
+            return self.meta.get(key.trim()).cloned();
+        }
+        match name.split_once(':') {
+            Some(("date", format)) => Some(self.format_date(format)),
+            _ if name == "date" => Some(self.format_date("%Y-%m-%d")),
+            _ if name == "wordcount" => Some(self.words.to_string()),
+            _ => None,
+        }
+    }
+
+    /// `text` with its placeholders replaced, as for templates of output formats (headers,
+    /// preambles) that are not part of the document
+    pub fn render(&self, text: &str) -> Result<String, VariableError> {
+        let mut output = String::with_capacity(text.len());
+        for (index, line) in text.split_inclusive('\n').enumerate() {
+            replace(line, index, self, &mut output)?;
+        }
+        Ok(output)
+    }
+
+    /// The conversion date, formatted with `strftime` specifiers
+    fn format_date(&self, format: &str) -> String {
+        let (year, month, day) = civil_from_days(self.days);
+        // 1970-01-01 was a Thursday
+        let weekday = (self.days + 3).rem_euclid(7) as usize;
+        let mut out = String::new();
+        let mut chars = format.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('Y') => out.push_str(&format!("{year:04}")),
+                Some('y') => out.push_str(&format!("{:02}", year.rem_euclid(100))),
+                Some('m') => out.push_str(&format!("{month:02}")),
+                Some('d') => out.push_str(&format!("{day:02}")),
+                Some('e') => out.push_str(&day.to_string()),
+                Some('B') => out.push_str(MONTHS[month as usize - 1]),
+                Some('b') => out.push_str(&MONTHS[month as usize - 1][..3]),
+                Some('A') => out.push_str(WEEKDAYS[weekday]),
+                Some('a') => out.push_str(&WEEKDAYS[weekday][..3]),
+                Some('j') => out.push_str(&format!("{:03}", day_of_year(year, month, day))),
+                Some('%') => out.push('%'),
+                Some(other) => {
+                    out.push('%');
+                    out.push(other);
+                }
+                None => out.push('%'),
+            }
+        }
+        out
+    }
+}
+
+fn day_of_year(year: i64, month: u32, day: u32) -> u32 {
+    const DAYS_BEFORE: [u32; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    DAYS_BEFORE[month as usize - 1] + day + u32::from(leap && month > 2)
+}
+
+/// `source` (parsed as `document`) with its placeholders replaced by their values
+pub fn substitute(
+    source: &str,
+    document: &Document,
+    variables: &Variables,
+) -> Result<String, VariableError> {
+    let verbatim: HashSet<usize> = document
+        .root
+        .iter_all_nodes()
+        .filter(|item| matches!(item, ContentItem::VerbatimLine(_)))
+        .flat_map(|item| item.range().start.line..=item.range().end.line)
+        .collect();
+
+    let mut output = String::with_capacity(source.len());
+    for (index, line) in source.split_inclusive('\n').enumerate() {
+        if verbatim.contains(&index) {
+            output.push_str(line);
+        } else {
+            replace(line, index, variables, &mut output)?;
+        }
+    }
+    Ok(output)
+}
+
+/// Append `line` (at `index`) to `output`, with its placeholders replaced
+fn replace(
+    line: &str,
+    index: usize,
+    variables: &Variables,
+    output: &mut String,
+) -> Result<(), VariableError> {
+    let mut rest = line;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + length];
+        let value = variables
+            .resolve(name)
+            .ok_or_else(|| VariableError::Unknown {
+                line: index,
+                name: name.trim().to_string(),
+            })?;
+        output.push_str(&rest[..start]);
+        output.push_str(&value);
+        rest = &rest[start + 4 + length..];
+    }
+    output.push_str(rest);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lex::parsing::parse_document;
+
+    // 2024-05-01, a Wednesday
+    const TIMESTAMP: u64 = 19_844 * 86_400;
+
+    #[test]
+    fn test_resolve() {
+        let doc =
+            parse_document("Report\n\n:: meta author=\"Ada Lovelace\" ::\n\nThree short words.\n")
+                .unwrap();
+        let variables = Variables::for_document(&doc).with_timestamp(TIMESTAMP);
+        assert_eq!(variables.resolve("meta.title").as_deref(), Some("Report"));
+        assert_eq!(
+            variables.resolve(" meta.author ").as_deref(),
+            Some("Ada Lovelace")
+        );
+        assert_eq!(variables.resolve("date").as_deref(), Some("2024-05-01"));
+        assert_eq!(
+            variables
+                .resolve("date:%A %e %B %Y (%a %d %b %y), day %j, 100%%")
+                .as_deref(),
+            Some("Wednesday 1 May 2024 (Wed 01 May 24), day 122, 100%")
+        );
+        assert_eq!(variables.resolve("wordcount").as_deref(), Some("3"));
+        assert_eq!(variables.resolve("meta.missing"), None);
+    }
+
+    #[test]
+    fn test_substitute() {
+        let source = "Report\n\n:: meta author=Ada ::\n\nBy {{meta.author}} on {{date}}, in {{ wordcount }} words.\n\nExample:\n    Hello {{name}}\n:: handlebars\n";
+        let doc = parse_document(source).unwrap();
+        let variables = Variables::for_document(&doc).with_timestamp(TIMESTAMP);
+        let words = variables.words;
+        assert_eq!(
+            substitute(source, &doc, &variables).unwrap(),
+            source.replace(
+                "By {{meta.author}} on {{date}}, in {{ wordcount }} words.",
+                &format!("By Ada on 2024-05-01, in {words} words.")
+            )
+        );
+
+        let unknown = "Report\n\nHello {{nobody}}.\n";
+        let doc = parse_document(unknown).unwrap();
+        assert_eq!(
+            substitute(unknown, &doc, &Variables::for_document(&doc)),
+            Err(VariableError::Unknown {
+                line: 2,
+                name: "nobody".to_string()
+            })
+        );
+    }
+}
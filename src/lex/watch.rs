@@ -0,0 +1,113 @@
+//! Watch mode support
+//!
+//!     Watch modes (`convert --watch`, `fmt --watch`) re-run a command whenever its inputs
+//!     change, so authors get live previews while editing. File events come from the
+//!     platform's notifier in front ends; this module holds what does not depend on one:
+//!
+//!         - [Snapshot]: modification times of a file, or of the `.lex` files under a
+//!           directory. Comparing two snapshots gives the paths changed, added or removed in
+//!           between, so watching works by polling where no notifier is available, and
+//!           notifier events can be checked against it (editors often touch a file several
+//!           times per save).
+//!         - [write_atomic]: writes output to a temporary file next to its target and renames
+//!           it into place, so a preview never reads a half-written file.
+//!
+//!     Bursts of changes are coalesced with a [Debouncer](crate::lex::parsing::Debouncer).
+
+use crate::lex::analysis::search::lex_files;
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Modification times of watched files
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    pub files: BTreeMap<PathBuf, SystemTime>,
+}
+
+impl Snapshot {
+    /// Snapshot of `path`: the file itself, or every `.lex` file under it if it is a
+    /// directory
+    pub fn take(path: &Path) -> io::Result<Self> {
+        let mut paths = Vec::new();
+        if path.is_dir() {
+            lex_files(path, &mut paths)?;
+        } else {
+            paths.push(path.to_path_buf());
+        }
+        let mut files = BTreeMap::new();
+        for path in paths {
+            let modified = std::fs::metadata(&path)?.modified()?;
+            files.insert(path, modified);
+        }
+        Ok(Self { files })
+    }
+
+    /// Paths modified, added or removed in `newer`, in path order
+    pub fn changes(&self, newer: &Snapshot) -> Vec<PathBuf> {
+        let mut changed: Vec<PathBuf> = newer
+            .files
+            .iter()
+            .filter(|(path, modified)| self.files.get(*path) != Some(modified))
+            .map(|(path, _)| path.clone())
+            .collect();
+        changed.extend(
+            self.files
+                .keys()
+                .filter(|path| !newer.files.contains_key(*path))
+                .cloned(),
+        );
+        changed.sort();
+        changed
+    }
+}
+
+/// Write `contents` to `path` through a temporary file renamed into place
+pub fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "path has no file name"))?;
+    let temporary = path.with_file_name(format!(".{}.tmp", name.to_string_lossy()));
+    std::fs::write(&temporary, contents)
+        .and_then(|_| std::fs::rename(&temporary, path))
+        .inspect_err(|_| {
+            let _ = std::fs::remove_file(&temporary);
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_snapshot_and_atomic_write() {
+        let dir = std::env::temp_dir().join(format!("lex-watch-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("notes")).unwrap();
+        let first = dir.join("notes/first.lex");
+        let second = dir.join("second.lex");
+        std::fs::write(&first, "First\n").unwrap();
+        std::fs::write(&second, "Second\n").unwrap();
+        std::fs::write(dir.join("ignored.txt"), "Text\n").unwrap();
+
+        let before = Snapshot::take(&dir).unwrap();
+        assert_eq!(before.files.len(), 2);
+        assert!(before.changes(&before).is_empty());
+
+        let earlier = SystemTime::now() - Duration::from_secs(60);
+        let file = std::fs::File::options().write(true).open(&second).unwrap();
+        file.set_modified(earlier).unwrap();
+        std::fs::remove_file(&first).unwrap();
+        let after = Snapshot::take(&dir).unwrap();
+        assert_eq!(before.changes(&after), vec![first.clone(), second.clone()]);
+
+        let output = dir.join("second.html");
+        write_atomic(&output, "<p>Second</p>\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&output).unwrap(), "<p>Second</p>\n");
+        assert!(!dir.join(".second.html.tmp").exists());
+        assert_eq!(Snapshot::take(&output).unwrap().files.len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,24 @@
+//! Helpers shared by the integration tests
+
+use lex_core::lex::testing::workspace_path;
+use std::path::{Path, PathBuf};
+
+fn lex_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            lex_files(&path, out);
+        } else if path.extension().is_some_and(|ext| ext == "lex") {
+            out.push(path);
+        }
+    }
+}
+
+/// Every Lex file of the spec corpus, sorted
+pub fn spec_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    lex_files(&workspace_path("specs/v1"), &mut files);
+    files.sort();
+    assert!(!files.is_empty());
+    files
+}
@@ -0,0 +1,53 @@
+//! Serializing the same document twice must give the same bytes, for every output format
+
+use lex_core::lex::ast::{snapshot_from_document, Document};
+use lex_core::lex::formats::FormatRegistry;
+use lex_core::lex::parsing::parse_document;
+use std::path::Path;
+
+mod common;
+
+/// Every format's output, plus the JSON and YAML snapshots, from a fresh registry
+fn outputs(doc: &Document, path: &Path) -> Vec<(String, String)> {
+    let registry = FormatRegistry::with_defaults();
+    let mut outputs: Vec<(String, String)> = registry
+        .list_formats()
+        .into_iter()
+        .map(|format| {
+            let output = registry
+                .serialize(doc, &format)
+                .unwrap_or_else(|err| panic!("{format} fails on {}: {err}", path.display()));
+            (format, output)
+        })
+        .collect();
+    let snapshot = snapshot_from_document(doc);
+    outputs.push((
+        "snapshot-json".to_string(),
+        serde_json::to_string(&snapshot).unwrap(),
+    ));
+    outputs.push((
+        "snapshot-yaml".to_string(),
+        serde_yaml::to_string(&snapshot).unwrap(),
+    ));
+    outputs
+}
+
+#[test]
+fn test_serializers_are_deterministic() {
+    for path in common::spec_files() {
+        let source = std::fs::read_to_string(&path).unwrap();
+        let Ok(doc) = parse_document(&source) else {
+            continue;
+        };
+        for ((format, first), (_, second)) in
+            outputs(&doc, &path).into_iter().zip(outputs(&doc, &path))
+        {
+            assert_eq!(
+                first,
+                second,
+                "{format} output of {} differs",
+                path.display()
+            );
+        }
+    }
+}
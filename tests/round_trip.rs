@@ -0,0 +1,150 @@
+//! Round trips over the spec corpus: Lex source written back out must parse to the same
+//! structure
+//!
+//! Structures are compared on full AST snapshots, leaving out what formatting is meant to
+//! change: source positions, list markers, blank line runs and trailing whitespace. The JSON
+//! AST keeps everything, positions included, so it is compared on complete snapshots.
+
+use lex_core::lex::ast::{snapshot_from_document_with_options, AstSnapshot, Range};
+use lex_core::lex::formats::FormatRegistry;
+use lex_core::lex::formatting::{format_document, FormattingRulesConfig};
+use lex_core::lex::parsing::parse_document;
+use lex_core::lex::testing::workspace_path;
+use std::path::{Path, PathBuf};
+
+mod common;
+
+/// Spec documents that parse, with their source
+fn spec_documents() -> Vec<(PathBuf, String)> {
+    common::spec_files()
+        .into_iter()
+        .filter_map(|path| {
+            let source = std::fs::read_to_string(&path).unwrap();
+            parse_document(&source).is_ok().then_some((path, source))
+        })
+        .collect()
+}
+
+fn normalize(snapshot: &mut AstSnapshot) {
+    snapshot.range = Range::default();
+    if matches!(
+        snapshot.node_type.as_str(),
+        "Document" | "ListItem" | "Marker"
+    ) {
+        snapshot.label.clear();
+    }
+    snapshot.label = snapshot
+        .label
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n");
+    snapshot
+        .children
+        .retain(|child| child.node_type != "BlankLineGroup");
+    snapshot.children.iter_mut().for_each(normalize);
+}
+
+/// Normalized structure of a Lex source
+fn structure(source: &str) -> AstSnapshot {
+    let doc = parse_document(source).unwrap();
+    let mut snapshot = snapshot_from_document_with_options(&doc, true);
+    normalize(&mut snapshot);
+    snapshot
+}
+
+#[test]
+fn test_formatting_keeps_structure() {
+    let rules = FormattingRulesConfig::default();
+    for (path, source) in spec_documents() {
+        let once = format_document(&source, &rules).unwrap();
+        assert_eq!(
+            structure(&once),
+            structure(&source),
+            "formatting changes the structure of {}",
+            path.display()
+        );
+        let twice = format_document(&once, &rules).unwrap();
+        assert_eq!(
+            twice,
+            once,
+            "formatting {} is not idempotent",
+            path.display()
+        );
+    }
+}
+
+/// Documents where the parser skips a definition's only content line
+///
+/// A definition without content has no Lex source form, so writing it from the AST alone
+/// turns it into a paragraph. The formatter, which has the source, copies the line instead.
+const CONTENTLESS_DEFINITIONS: &[&str] = &["elements/data.lex", "elements/verbatim.lex"];
+
+/// Spec documents that round trip through the AST alone
+fn round_trip_documents() -> Vec<(PathBuf, String)> {
+    let specs = workspace_path("specs/v1");
+    spec_documents()
+        .into_iter()
+        .filter(|(path, _)| {
+            let relative = path.strip_prefix(&specs).unwrap();
+            !CONTENTLESS_DEFINITIONS
+                .iter()
+                .any(|skipped| relative == Path::new(skipped))
+        })
+        .collect()
+}
+
+#[test]
+fn test_lex_format_keeps_structure() {
+    let registry = FormatRegistry::with_defaults();
+    for (path, source) in round_trip_documents() {
+        let doc = parse_document(&source).unwrap();
+        let written = registry.serialize(&doc, "lex").unwrap();
+        assert_eq!(
+            structure(&written),
+            structure(&source),
+            "the lex format changes the structure of {}",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn test_xml_keeps_structure() {
+    let registry = FormatRegistry::with_defaults();
+    for (path, source) in round_trip_documents() {
+        let doc = parse_document(&source).unwrap();
+        let xml = registry.serialize(&doc, "xml").unwrap();
+        let read = registry.parse(&xml, "xml").unwrap();
+        let mut snapshot = snapshot_from_document_with_options(&read, true);
+        normalize(&mut snapshot);
+        assert_eq!(
+            snapshot,
+            structure(&source),
+            "XML changes the structure of {}",
+            path.display()
+        );
+    }
+}
+
+#[test]
+fn test_json_is_lossless() {
+    let registry = FormatRegistry::with_defaults();
+    for (path, source) in spec_documents() {
+        let doc = parse_document(&source).unwrap();
+        let json = registry.serialize(&doc, "json").unwrap();
+        let read = registry.parse(&json, "json").unwrap();
+        assert_eq!(
+            snapshot_from_document_with_options(&read, true),
+            snapshot_from_document_with_options(&doc, true),
+            "JSON loses part of {}",
+            path.display()
+        );
+        assert_eq!(
+            registry.serialize(&read, "json").unwrap(),
+            json,
+            "JSON of {} reads back to a different AST",
+            path.display()
+        );
+    }
+}